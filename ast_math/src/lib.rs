@@ -7,6 +7,7 @@ use std::fmt::{ Display, Formatter, Result as FmtResult };
 #[derive(Debug, Clone)]
 pub enum AstNode {
 	Const  { val: f64 },
+	Var    { name: String },
 	Negate { lhs: Box<AstNode> },
 	Binary { op: BinOp, lhs: Box<AstNode>, rhs: Box<AstNode> },
 }
@@ -25,6 +26,7 @@ impl Display for AstNode {
 		// but we won't talk about that for another lecture or two...
 		match self {
 			Const  { val }          => write!(f, "{}", val),
+			Var    { name }         => write!(f, "{}", name),
 			Negate { lhs }          => write!(f, "-({})", lhs),
 			Binary { op, lhs, rhs } => write!(f, "({} {} {})", lhs, op, rhs),
 		}
@@ -39,6 +41,10 @@ impl AstNode {
 		Box::new(AstNode::Const { val })
 	}
 
+	pub fn var(name: &str) -> Box<AstNode> {
+		Box::new(AstNode::Var { name: name.into() })
+	}
+
 	pub fn neg(lhs: Box<AstNode>) -> Box<AstNode> {
 		Box::new(AstNode::Negate { lhs })
 	}
@@ -59,17 +65,37 @@ impl AstNode {
 		Box::new(AstNode::Binary { op: BinOp::Div, lhs, rhs })
 	}
 
+	// mod is a keyword in Rust
+	pub fn mod_(lhs: Box<AstNode>, rhs: Box<AstNode>) -> Box<AstNode> {
+		Box::new(AstNode::Binary { op: BinOp::Mod, lhs, rhs })
+	}
+
 	// This method evaluates the AST! neat!
+	// it doesn't know what any variables are, though, so it panics if there are any. for an AST
+	// with variables in it, use eval_with().
 	pub fn eval(&self) -> f64 {
+		match self.eval_with(&|_| None) {
+			Ok(val)   => val,
+			Err(name) => panic!("eval() doesn't know what '{}' is; use eval_with()", name),
+		}
+	}
+
+	// Evaluates the AST, asking `vars` for the value of each variable. `vars` is a *closure*, a
+	// function that can be written right where it's used, like `|name| env.get(name)`. it gives
+	// None if there's no variable with that name, and then this gives the name as an error.
+	pub fn eval_with(&self, vars: &dyn Fn(&str) -> Option<f64>) -> Result<f64, String> {
 		use AstNode::*;
 
 		match self {
 			// the *val here is because when you match on &self, all the matched
 			// pattern variables are references; so it's a &f64. *val gives me the
 			// f64 that it points to.
-			Const  { val }          => *val,
-			Negate { lhs }          => -lhs.eval(),
-			Binary { op, lhs, rhs } => op.eval(lhs.eval(), rhs.eval()),
+			Const  { val }          => Ok(*val),
+			Var    { name }         => vars(name).ok_or_else(|| name.clone()),
+
+			// the ? gives back the error right away, if there was one.
+			Negate { lhs }          => Ok(-lhs.eval_with(vars)?),
+			Binary { op, lhs, rhs } => Ok(op.eval(lhs.eval_with(vars)?, rhs.eval_with(vars)?)),
 		}
 	}
 
@@ -99,7 +125,7 @@ impl AstNode {
 
 #[derive(Debug, Clone)]
 pub enum BinOp {
-	Add, Sub, Mul, Div
+	Add, Sub, Mul, Div, Mod
 }
 
 impl Display for BinOp {
@@ -110,6 +136,7 @@ impl Display for BinOp {
 			Sub => write!(f, "-"),
 			Mul => write!(f, "*"),
 			Div => write!(f, "/"),
+			Mod => write!(f, "%"),
 		}
 	}
 }
@@ -123,6 +150,7 @@ impl BinOp {
 			Sub => lhs - rhs,
 			Mul => lhs * rhs,
 			Div => lhs / rhs,
+			Mod => lhs % rhs,
 		}
 	}
 }
\ No newline at end of file
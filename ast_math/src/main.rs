@@ -26,6 +26,13 @@ fn main() {
 	let original = AstNode::div(AstNode::num(3.), AstNode::num(5.));
 	let r = original.recip();
 	println!("the reciprocal of {} is {}", original, r);
+	println!();
+
+	// an AST with a variable in it needs eval_with(), which asks a closure for its value.
+	let v = AstNode::mul(AstNode::var("x"), AstNode::num(2.));
+	println!("{}, where x is 21", v);
+	println!("=> {:?}", v.eval_with(&|name| if name == "x" { Some(21.) } else { None }));
+	println!("=> {:?} when there's no x", v.eval_with(&|_| None));
 }
 
 // We take &Box and not just Box here because we just want to borrow
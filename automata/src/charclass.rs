@@ -0,0 +1,184 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+// ------------------------------------------------------------------------------------------------
+// Character classes
+// ------------------------------------------------------------------------------------------------
+
+/*
+In class, automata move from state to state on single characters. That's fine on paper, but a
+regex like `.` would need an edge for every one of the 1.1 million Unicode characters! So instead,
+every edge is labeled with a *set* of characters, stored as a list of ranges: `[a-zA-Z_]` is the
+three ranges a-z, A-Z, and _-_.
+
+The ranges are always kept sorted, with no overlaps and no two ranges right next to each other.
+That way, two CharClasses with the same characters always look exactly the same, so == works.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CharClass {
+	ranges: Vec<(char, char)>, // inclusive on both ends.
+}
+
+impl CharClass {
+	pub fn empty() -> Self {
+		CharClass { ranges: vec![] }
+	}
+
+	pub fn single(c: char) -> Self {
+		CharClass { ranges: vec![(c, c)] }
+	}
+
+	// every character from lo to hi, including both.
+	pub fn range(lo: char, hi: char) -> Self {
+		Self::from_ranges(vec![(lo, hi)])
+	}
+
+	// every character there is.
+	pub fn any() -> Self {
+		Self::range('\0', char::MAX)
+	}
+
+	// what `.` matches: anything but a newline.
+	pub fn dot() -> Self {
+		Self::single('\n').negate()
+	}
+
+	// \d, \w, and \s.
+	pub fn digit() -> Self {
+		Self::range('0', '9')
+	}
+
+	pub fn word() -> Self {
+		Self::from_ranges(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')])
+	}
+
+	pub fn space() -> Self {
+		Self::from_ranges(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')])
+	}
+
+	// the ranges can be in any order, and can overlap.
+	pub fn from_ranges(mut ranges: Vec<(char, char)>) -> Self {
+		ranges.retain(|&(lo, hi)| lo <= hi);
+		ranges.sort_unstable();
+
+		let mut ret: Vec<(char, char)> = vec![];
+
+		for (lo, hi) in ranges {
+			match ret.last_mut() {
+				// it overlaps or touches the last one, so stick them together.
+				Some(last) if next_char(last.1).is_none_or(|after| lo <= after) =>
+					last.1 = last.1.max(hi),
+				_ => ret.push((lo, hi)),
+			}
+		}
+
+		CharClass { ranges: ret }
+	}
+
+	pub fn ranges(&self) -> &[(char, char)] {
+		&self.ranges
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	pub fn contains(&self, c: char) -> bool {
+		self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi)
+	}
+
+	pub fn union(&self, other: &CharClass) -> CharClass {
+		Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).copied().collect())
+	}
+
+	// every character that *isn't* in this class. this is what `[^...]` means.
+	pub fn negate(&self) -> CharClass {
+		let mut ret = vec![];
+		let mut start = Some('\0');
+
+		for &(lo, hi) in &self.ranges {
+			if let Some(s) = start {
+				if s < lo {
+					ret.push((s, prev_char(lo).unwrap()));
+				}
+			}
+
+			start = next_char(hi);
+		}
+
+		if let Some(s) = start {
+			ret.push((s, char::MAX));
+		}
+
+		CharClass { ranges: ret }
+	}
+}
+
+// the characters right after and before c. these aren't just +1 and -1, because the numbers
+// D800 to DFFF (the "surrogates") aren't characters, so there's a gap there.
+pub(crate) fn next_char(c: char) -> Option<char> {
+	match c {
+		'\u{D7FF}' => Some('\u{E000}'),
+		char::MAX  => None,
+		_          => std::char::from_u32(c as u32 + 1),
+	}
+}
+
+pub(crate) fn prev_char(c: char) -> Option<char> {
+	match c {
+		'\u{E000}' => Some('\u{D7FF}'),
+		'\0'       => None,
+		_          => std::char::from_u32(c as u32 - 1),
+	}
+}
+
+// how a character is written in a regex, with a backslash if it would mean something else.
+// characters inside [] have different special characters than outside.
+pub(crate) fn escape_char(c: char, in_class: bool) -> String {
+	let special = if in_class { "\\]^-" } else { "\\|*+?()[]." };
+
+	match c {
+		'\n' => "\\n".into(),
+		'\t' => "\\t".into(),
+		'\r' => "\\r".into(),
+		c if special.contains(c) => format!("\\{}", c),
+		c if c.is_control() || (c.is_whitespace() && c != ' ') || c as u32 > 0xFFFF =>
+			format!("\\u{{{:x}}}", c as u32),
+		c => c.to_string(),
+	}
+}
+
+// Shows the class the way it'd be written in a regex, like `a`, `.`, `[0-9]`, or `[^"]`.
+impl Display for CharClass {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		if *self == CharClass::dot() {
+			return write!(f, ".");
+		}
+
+		if let [(lo, hi)] = self.ranges[..] {
+			if lo == hi {
+				return write!(f, "{}", escape_char(lo, false));
+			}
+		}
+
+		// [^"] is a lot easier to read than all the ranges that make it up!
+		let negated = self.negate();
+
+		let (prefix, ranges) = if negated.ranges.len() < self.ranges.len() {
+			("[^", &negated.ranges)
+		} else {
+			("[", &self.ranges)
+		};
+
+		write!(f, "{}", prefix)?;
+
+		for &(lo, hi) in ranges {
+			if lo == hi {
+				write!(f, "{}", escape_char(lo, true))?;
+			} else {
+				write!(f, "{}-{}", escape_char(lo, true), escape_char(hi, true))?;
+			}
+		}
+
+		write!(f, "]")
+	}
+}
@@ -0,0 +1,291 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::fmt::{ Display, Formatter, Result as FmtResult, Write };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// DFAs
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *deterministic finite automaton* has no ε edges, and from each state, each character goes to
+at most one other state. So running one is just a loop with one variable: the state it's in. That's
+what makes them so fast, and why lexers use them.
+
+Any NFA can be turned into a DFA with the *subset construction*. The NFA can be in a whole set of
+states at once, so each DFA state stands for one *set* of NFA states:
+
+- the DFA's start state is the ε closure of the NFA's start state.
+- from a DFA state S, on a character c, the DFA goes to the ε closure of every NFA state you can
+  get to from S on c. If that's a set we haven't seen before, it's a new DFA state.
+- a DFA state is accepting if any of its NFA states is. If they accept different rules, the
+  rule that came first in the list wins. (That's how a lexer makes `if` a keyword instead of an
+  identifier: the keyword rules go before the identifier rule.)
+
+There's one more wrinkle, because edges are labeled with sets of characters instead of single
+characters. We can't try every character one at a time, so first we cut the characters into
+pieces so that every edge's class either has *all* of a piece, or *none* of it. Then trying one
+character from each piece is the same as trying all of them.
+*/
+
+#[derive(Debug, Clone)]
+pub struct DfaState {
+	pub nfa_states:  BTreeSet<usize>,          // which NFA states this DFA state stands for.
+	pub transitions: Vec<(CharClass, usize)>, // no two of these have any characters in common.
+	pub accept:      Option<usize>,           // if this is an accepting state, which rule it's for.
+}
+
+// The start state is always state 0. If there's no transition for a character, the DFA gets
+// stuck, and the string doesn't match. (On paper, that'd be an edge to a "dead state.")
+#[derive(Debug, Clone)]
+pub struct Dfa {
+	pub states: Vec<DfaState>,
+	pub rules:  usize, // how many rules (regexes) it was made from.
+}
+
+impl Dfa {
+	pub fn new(nfa: &Nfa) -> Self {
+		let classes = nfa.states.iter().flat_map(|s| s.edges.iter().map(|(class, _)| class));
+		let pieces = alphabet_pieces(classes);
+		let start = nfa.epsilon_closure(&std::iter::once(nfa.start).collect());
+
+		let mut dfa = Dfa { states: vec![], rules: nfa.rules };
+		let mut ids = BTreeMap::new();
+		dfa.add_state(nfa, &mut ids, start);
+
+		// states get added while we're going through them, like in the Earley parser.
+		let mut i = 0;
+
+		while i < dfa.states.len() {
+			// where each piece of the alphabet goes. pieces that go to the same state are put
+			// back together into one class.
+			let mut targets: BTreeMap<usize, CharClass> = BTreeMap::new();
+
+			for piece in &pieces {
+				let (lo, _) = piece.ranges()[0];
+				let next = nfa.epsilon_closure(&nfa.step(&dfa.states[i].nfa_states, lo));
+
+				if !next.is_empty() {
+					let id = dfa.add_state(nfa, &mut ids, next);
+					let class = targets.entry(id).or_default();
+					*class = class.union(piece);
+				}
+			}
+
+			dfa.states[i].transitions = targets.into_iter().map(|(id, class)| (class, id))
+				.collect();
+			i += 1;
+		}
+
+		dfa
+	}
+
+	// gives the id of the DFA state for this set of NFA states, making it if it's new.
+	fn add_state(&mut self, nfa: &Nfa, ids: &mut BTreeMap<BTreeSet<usize>, usize>,
+	nfa_states: BTreeSet<usize>) -> usize {
+		if let Some(&id) = ids.get(&nfa_states) {
+			return id;
+		}
+
+		let id = self.states.len();
+		let accept = nfa_states.iter().filter_map(|&s| nfa.states[s].accept).min();
+		ids.insert(nfa_states.clone(), id);
+		self.states.push(DfaState { nfa_states, transitions: vec![], accept });
+		id
+	}
+
+	// The state that reading c from `state` goes to, or None if the DFA gets stuck.
+	pub fn next(&self, state: usize, c: char) -> Option<usize> {
+		self.states[state].transitions.iter()
+			.find(|(class, _)| class.contains(c))
+			.map(|&(_, next)| next)
+	}
+
+	// Does the *whole* string match (any of the rules)?
+	pub fn is_match(&self, s: &str) -> bool {
+		let mut state = 0;
+
+		for c in s.chars() {
+			match self.next(state, c) {
+				Some(next) => state = next,
+				None       => return false,
+			}
+		}
+
+		self.states[state].accept.is_some()
+	}
+
+	// DOT output, drawn the same way as Nfa::to_dot().
+	pub fn to_dot(&self) -> String {
+		let mut out = String::new();
+		out.push_str("digraph dfa {\n");
+		out.push_str("\trankdir=LR;\n");
+		out.push_str("\tnode [shape=circle];\n");
+
+		for (i, state) in self.states.iter().enumerate() {
+			if let Some(rule) = state.accept {
+				write_dot_accept(&mut out, i, rule, self.rules);
+			}
+		}
+
+		out.push_str("\tstart [shape=none, label=\"\"];\n");
+		out.push_str("\tstart -> 0;\n");
+
+		for (i, state) in self.states.iter().enumerate() {
+			for (class, next) in &state.transitions {
+				writeln!(out, "\t{} -> {} [label=\"{}\"];", i, next, escape(&class.to_string()))
+					.unwrap();
+			}
+		}
+
+		out.push_str("}\n");
+		out
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Minimization
+// ------------------------------------------------------------------------------------------------
+
+/*
+The subset construction often makes more states than it needs to. In the DFA for (a|b)*abb, the
+start state and the state after reading a b both mean "we haven't seen any of the abb yet," so
+they could be the same state. Two states are *equivalent* if, for every string, both accept it or
+both reject it; minimizing merges all the equivalent states together.
+
+This uses *Moore's algorithm*, which works backwards from "which states are definitely different":
+
+1. Split the states into groups by what they accept: the non-accepting states in one group, the
+   states that accept rule 0 in another, and so on. States in different groups are different.
+2. Two states in the same group are also different if some character takes them to *different*
+   groups. So split each group up by where its states go on every piece of the alphabet.
+3. Keep doing step 2 until no group gets split. Then every group is a set of equivalent states,
+   and each one becomes one state in the minimized DFA.
+
+(Hopcroft's algorithm is a smarter way to do the same thing, which is faster for big DFAs.)
+*/
+impl Dfa {
+	pub fn minimize(&self) -> Dfa {
+		let classes = self.states.iter().flat_map(|s| s.transitions.iter().map(|(class, _)| class));
+		let pieces = alphabet_pieces(classes);
+
+		// group[s] is which group state s is in. to start, that's just what it accepts.
+		let accepts = self.states.iter().map(|s| s.accept).collect::<Vec<_>>();
+		let mut group = renumber(&accepts);
+		let mut num_groups = group.iter().max().map_or(0, |&g| g + 1);
+
+		loop {
+			// two states stay together only if they were together, and every piece of the
+			// alphabet takes them to the same group (or gets them both stuck).
+			let signatures = (0 .. self.states.len()).map(|s| {
+				let nexts = pieces.iter().map(|piece| {
+					self.next(s, piece.ranges()[0].0).map(|next| group[next])
+				});
+
+				(group[s], nexts.collect::<Vec<_>>())
+			}).collect::<Vec<_>>();
+
+			let new_group = renumber(&signatures);
+			let new_num_groups = new_group.iter().max().map_or(0, |&g| g + 1);
+			group = new_group;
+
+			if new_num_groups == num_groups {
+				break;
+			}
+
+			num_groups = new_num_groups;
+		}
+
+		// each group becomes one state. its transitions are the same as any of its states'.
+		let mut states = vec![];
+
+		for g in 0 .. num_groups {
+			let members = (0 .. self.states.len()).filter(|&s| group[s] == g).collect::<Vec<_>>();
+			let first = &self.states[members[0]];
+
+			let mut targets: BTreeMap<usize, CharClass> = BTreeMap::new();
+
+			for (class, next) in &first.transitions {
+				let target = targets.entry(group[*next]).or_default();
+				*target = target.union(class);
+			}
+
+			states.push(DfaState {
+				nfa_states:  members.iter().flat_map(|&s| &self.states[s].nfa_states).copied()
+					.collect(),
+				transitions: targets.into_iter().map(|(g, class)| (class, g)).collect(),
+				accept:      first.accept,
+			});
+		}
+
+		Dfa { states, rules: self.rules }
+	}
+}
+
+// gives each different value a number, in the order they first appear. since state 0 comes
+// first, it's always in group 0, so the minimized DFA's start state is still state 0.
+fn renumber<T: Ord + Clone>(values: &[T]) -> Vec<usize> {
+	let mut ids = BTreeMap::new();
+
+	values.iter().map(|v| {
+		let next_id = ids.len();
+		*ids.entry(v.clone()).or_insert(next_id)
+	}).collect()
+}
+
+// cuts the characters used by these classes into pieces, so that each class has either all or
+// none of each piece. characters that no class uses aren't in any piece.
+fn alphabet_pieces<'a>(classes: impl Iterator<Item = &'a CharClass> + Clone) -> Vec<CharClass> {
+	// every place where some class starts or stops. a piece goes from one boundary up to just
+	// before the next one.
+	let mut boundaries = BTreeSet::new();
+
+	for class in classes.clone() {
+		for &(lo, hi) in class.ranges() {
+			boundaries.insert(lo);
+
+			if let Some(after) = next_char(hi) {
+				boundaries.insert(after);
+			}
+		}
+	}
+
+	let boundaries = boundaries.into_iter().collect::<Vec<_>>();
+	let mut pieces = vec![];
+
+	for (i, &lo) in boundaries.iter().enumerate() {
+		let hi = match boundaries.get(i + 1) {
+			Some(&next) => prev_char(next).unwrap(),
+			None        => char::MAX,
+		};
+
+		// only keep pieces that some class actually has.
+		if classes.clone().any(|class| class.contains(lo)) {
+			pieces.push(CharClass::range(lo, hi));
+		}
+	}
+
+	pieces
+}
+
+// A text listing of the states, with the NFA states that each one stands for.
+impl Display for Dfa {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (i, state) in self.states.iter().enumerate() {
+			let nfa_states = state.nfa_states.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+			let what = match state.accept {
+				Some(rule) => format!(" ({})", accept_label(rule, self.rules)),
+				None       => "".into(),
+			};
+
+			writeln!(f, "State {} = {{{}}}{}:", i, nfa_states.join(", "), what)?;
+
+			for (class, next) in &state.transitions {
+				writeln!(f, "    on {} go to {}", class, next)?;
+			}
+		}
+
+		Ok(())
+	}
+}
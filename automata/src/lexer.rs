@@ -0,0 +1,161 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, SourceFile, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Lexer generator
+// ------------------------------------------------------------------------------------------------
+
+/*
+This is what all the automata stuff is *for*. A lexer generator (like lex, flex, or Rust's logos)
+takes a list of rules, each a token name and a regex:
+
+	LParen  \(
+	If      if
+	Id      [a-zA-Z_][a-zA-Z0-9_]*
+	IntLit  \d+
+
+and makes one DFA that recognizes *all* of them, by making an NFA for each regex, joining them
+with ε edges from a new start state, and then doing the subset construction and minimization.
+Each accepting state knows which rule it's for. Then lexing is just running the DFA over and over:
+
+- *Maximal munch:* each token is as long as possible. The DFA keeps going until it gets stuck,
+  remembering the last place it was in an accepting state. That's where the token ends. So
+  `iffy` is one Id, not If followed by Id.
+- *Rule priority:* if more than one rule matches the same longest text, the one listed first
+  wins. So `if` is an If, not an Id, because If is listed first.
+
+There's also a *skip* regex for things between tokens that aren't tokens, like whitespace and
+comments. It's matched the same way, but doesn't make a token.
+*/
+
+#[derive(Debug, Clone)]
+pub struct DfaLexer {
+	names: Vec<String>, // the name of each rule, in order. the skip rule isn't in here.
+	dfa:   Dfa,
+}
+
+// A token found by a DfaLexer. `rule` is which rule matched, as an index into the list of rules
+// that the lexer was made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+	pub rule: usize,
+	pub text: String,
+	pub span: Span, // in characters, not bytes.
+}
+
+// An error in one of the rules' regexes. The diagnostic's span is inside that regex, so use
+// source() to show it.
+#[derive(Debug, Clone)]
+pub struct RuleError {
+	pub rule:  String,
+	pub regex: String,
+	pub diag:  Diagnostic,
+}
+
+impl RuleError {
+	pub fn source(&self) -> SourceFile {
+		SourceFile::new(format!("<{} rule>", self.rule), self.regex.as_str())
+	}
+}
+
+// When no rule matches at some point in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoMatch {
+	pub loc: usize, // in characters.
+	pub c:   char,
+}
+
+impl Display for NoMatch {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "invalid character '{}'", self.c.escape_debug())
+	}
+}
+
+impl std::error::Error for NoMatch {}
+
+impl From<NoMatch> for Diagnostic {
+	fn from(err: NoMatch) -> Diagnostic {
+		Diagnostic::error(err.to_string(), Span::point(err.loc))
+			.with_code(diagnostics::INVALID_CHAR)
+			.with_note("no token can start with it")
+	}
+}
+
+impl DfaLexer {
+	// `rules` is a list of (name, regex), and `skip` is a regex for what to skip between tokens.
+	pub fn new(rules: &[(&str, &str)], skip: &str) -> Result<Self, Vec<RuleError>> {
+		let mut regexes = vec![];
+		let mut errors = vec![];
+
+		for &(name, src) in rules.iter().chain(std::iter::once(&("skip", skip))) {
+			match parse_regex(src) {
+				Ok(re) => regexes.push(re),
+				Err(diag) => errors.push(RuleError { rule: name.into(), regex: src.into(), diag }),
+			}
+		}
+
+		if !errors.is_empty() {
+			return Err(errors);
+		}
+
+		// the skip rule goes last, so it has the lowest priority.
+		let dfa = Dfa::new(&Nfa::from_rules(&regexes)).minimize();
+		let names = rules.iter().map(|&(name, _)| name.into()).collect();
+		Ok(DfaLexer { names, dfa })
+	}
+
+	pub fn rule_name(&self, rule: usize) -> &str {
+		&self.names[rule]
+	}
+
+	// The DFA that all the rules were combined into. The skip rule is the last one.
+	pub fn dfa(&self) -> &Dfa {
+		&self.dfa
+	}
+
+	// Splits the source into tokens. The first thing that doesn't match any rule is an error.
+	pub fn lex(&self, src: &str) -> Result<Vec<Lexeme>, NoMatch> {
+		let chars = src.chars().collect::<Vec<_>>();
+		let skip = self.names.len();
+		let mut pos = 0;
+		let mut ret = vec![];
+
+		while pos < chars.len() {
+			let (rule, end) = self.longest_match(&chars, pos)
+				.ok_or(NoMatch { loc: pos, c: chars[pos] })?;
+
+			if rule != skip {
+				let text = chars[pos .. end].iter().collect();
+				ret.push(Lexeme { rule, text, span: Span::new(pos, end) });
+			}
+
+			pos = end;
+		}
+
+		Ok(ret)
+	}
+
+	// runs the DFA from `start` until it gets stuck. gives the rule and end position of the
+	// last accepting state it was in, if any. a rule that matches nothing (like `a*` on "b")
+	// doesn't count, or lexing would never move forward.
+	fn longest_match(&self, chars: &[char], start: usize) -> Option<(usize, usize)> {
+		let mut state = 0;
+		let mut last_accept = None;
+
+		for (i, &c) in chars.iter().enumerate().skip(start) {
+			match self.dfa.next(state, c) {
+				Some(next) => state = next,
+				None       => break,
+			}
+
+			if let Some(rule) = self.dfa.states[state].accept {
+				last_accept = Some((rule, i + 1));
+			}
+		}
+
+		last_accept
+	}
+}
@@ -0,0 +1,27 @@
+/*
+lexing_toy's lexer is written by hand, with a loop for identifiers, a loop for numbers, and so on.
+But in class, tokens are described with *regular expressions*, like `[a-zA-Z_][a-zA-Z0-9_]*` for
+identifiers, and there's a whole theory of how to turn those into code automatically. This crate
+is that theory, step by step:
+
+1. parse_regex() reads a regex into a Regex tree.
+2. Nfa::new() turns the Regex into an NFA with *Thompson's construction*.
+3. Dfa::new() turns the NFA into a DFA with the *subset construction*.
+4. Dfa::minimize() merges the DFA's equivalent states, so it has as few states as possible.
+5. DfaLexer does all of that for a whole list of token rules at once, making an actual lexer.
+
+NFAs and DFAs can both match strings, and both can be drawn with to_dot(), so you can check your
+answers to the homework!
+*/
+
+mod charclass;
+mod dfa;
+mod lexer;
+mod nfa;
+mod regex;
+
+pub use crate::charclass::*;
+pub use crate::dfa::*;
+pub use crate::lexer::*;
+pub use crate::nfa::*;
+pub use crate::regex::*;
@@ -0,0 +1,156 @@
+use std::process::exit;
+
+use automata::*;
+use diagnostics::{ render_all, render_all_colored, ColorChoice, SourceFile };
+
+// `cargo run` shows some examples. `cargo run -- '<regex>' strings...` says whether each string
+// matches the regex. `cargo run -- nfa '<regex>'` and `cargo run -- dfa '<regex>'` print the
+// automata for a regex in DOT format, so you can draw them with Graphviz, and `cargo run -- min
+// '<regex>'` prints the minimized DFA. (The quotes keep the shell from messing with characters
+// like * and |.)
+
+const USAGE: &str = "usage: automata [<regex> <strings>...] [--color=always|never|auto]
+       automata nfa <regex>
+       automata dfa <regex>
+       automata min <regex>";
+
+fn main() {
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+	let args = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+
+	let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		[]                 => { demo(); Ok(()) }
+		["nfa", re]        => read_regex(re, color).map(|re| print!("{}", Nfa::new(&re).to_dot())),
+		["dfa", re]        => read_regex(re, color).map(|re| {
+			print!("{}", Dfa::new(&Nfa::new(&re)).to_dot())
+		}),
+		["min", re]        => read_regex(re, color).map(|re| {
+			print!("{}", Dfa::new(&Nfa::new(&re)).minimize().to_dot())
+		}),
+		[re, ref strings @ ..] if !strings.is_empty() => match_strings(re, strings, color),
+		_                  => {
+			eprintln!("{}", USAGE);
+			exit(2);
+		}
+	};
+
+	// 1 means the regex had an error.
+	if let Err(code) = result {
+		exit(code);
+	}
+}
+
+fn read_regex(src: &str, color: ColorChoice) -> Result<Regex, i32> {
+	parse_regex(src).map_err(|err| {
+		eprint!("{}", render_all_colored(&[err], &SourceFile::new("<regex>", src), color));
+		1
+	})
+}
+
+fn match_strings(src: &str, strings: &[&str], color: ColorChoice) -> Result<(), i32> {
+	let dfa = Dfa::new(&Nfa::new(&read_regex(src, color)?)).minimize();
+
+	for s in strings {
+		println!("{:<20} {}", format!("'{}'", s), if dfa.is_match(s) { "matches" } else { "no" });
+	}
+
+	Ok(())
+}
+
+fn demo() {
+	dragon_demo();
+	ident_demo();
+	bad_regex_demo();
+	minimize_demo();
+	lexer_demo();
+}
+
+fn dragon_demo() {
+	// the classic example from the dragon book: strings of a and b that end in abb.
+	let re = parse_regex("(a|b)*abb").unwrap();
+	let nfa = Nfa::new(&re);
+	let dfa = Dfa::new(&nfa);
+
+	println!("Regex: {}\n\nNFA:\n{}\nDFA:\n{}", re, nfa, dfa);
+
+	for s in &["abb", "aabb", "babb", "ab", "abba", ""] {
+		// the NFA and DFA always agree. the DFA is just faster.
+		assert_eq!(nfa.is_match(s), dfa.is_match(s));
+		println!("{:<8} {}", format!("'{}'", s), if dfa.is_match(s) { "matches" } else { "no" });
+	}
+
+	println!("\n{}", dfa.to_dot());
+}
+
+fn ident_demo() {
+	// with character classes, an identifier regex only needs a few states, even though each
+	// edge stands for dozens of characters.
+	for src in &["[a-zA-Z_][a-zA-Z0-9_]*", "\"([^\"\\\\]|\\\\.)*\"", "-?\\d+(\\.\\d+)?"] {
+		let dfa = Dfa::new(&Nfa::new(&parse_regex(src).unwrap()));
+		println!("Regex: {}\n\nDFA:\n{}", src, dfa);
+	}
+}
+
+fn bad_regex_demo() {
+	for &src in &["(a|b*", "a|*b", "[z-a]", "abc)", "[]", "x\\"] {
+		let err = parse_regex(src).unwrap_err();
+		print!("{}", render_all(&[err], &SourceFile::new("<regex>", src)));
+	}
+}
+
+fn minimize_demo() {
+	// the subset construction gave 5 states for this, but it only needs 4.
+	let dfa = Dfa::new(&Nfa::new(&parse_regex("(a|b)*abb").unwrap()));
+	let min = dfa.minimize();
+	println!("\n(a|b)*abb has {} states, and {} when minimized:\n{}", dfa.states.len(),
+		min.states.len(), min);
+
+	// these regexes look different, but mean the same thing, so they minimize to the same DFA.
+	for src in &["(a|b)*", "(a*b*)*", "a*(ba*)*"] {
+		let dfa = Dfa::new(&Nfa::new(&parse_regex(src).unwrap()));
+		println!("{} has {} states, and {} when minimized", src, dfa.states.len(),
+			dfa.minimize().states.len());
+	}
+
+	println!();
+}
+
+fn lexer_demo() {
+	// a lexer for a little language. `if` and `else` come before Id, so they're keywords.
+	let lexer = DfaLexer::new(&[
+		("If",     "if"),
+		("Else",   "else"),
+		("Id",     "[a-zA-Z_][a-zA-Z0-9_]*"),
+		("Num",    "\\d+(\\.\\d+)?"),
+		("String", "\"([^\"\\\\]|\\\\.)*\""),
+		("Le",     "<="),
+		("Lt",     "<"),
+		("Assign", "="),
+		("LParen", "\\("),
+		("RParen", "\\)"),
+	], "\\s+|#.*").unwrap();
+
+	println!("The lexer's DFA has {} states.\n", lexer.dfa().states.len());
+
+	// iffy is an Id (maximal munch), and if is an If (rule priority). <= is one token.
+	let src = "if x <= 3.5 # a comment\n\tiffy = \"hi \\\"there\\\"\" else y=(1)";
+	println!("{}\n", src);
+
+	for lexeme in lexer.lex(src).unwrap() {
+		println!("{:<8} {:<16} {}..{}", lexer.rule_name(lexeme.rule), lexeme.text,
+			lexeme.span.start, lexeme.span.end);
+	}
+
+	// errors in the rules point into the regex they're in.
+	for err in DfaLexer::new(&[("Id", "[a-z"), ("Num", "+")], "\\s+").unwrap_err() {
+		print!("{}", err.diag.render(&err.source()));
+	}
+
+	// and errors in the input point into the input.
+	let src = "x = 1 $ 2";
+	let err = lexer.lex(src).unwrap_err();
+	print!("{}", render_all(&[err.into()], &SourceFile::new("<input>", src)));
+}
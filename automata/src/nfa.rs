@@ -0,0 +1,282 @@
+use std::collections::BTreeSet;
+use std::fmt::{ Display, Formatter, Result as FmtResult, Write };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// NFAs
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *nondeterministic finite automaton* is a bunch of states connected by edges. Each edge is labeled
+with a set of characters, or with ε, which means the automaton can take that edge *without*
+reading a character. From one state, there might be several edges for the same character, or
+none at all. That's the "nondeterministic" part: the NFA doesn't have to pick which way to go.
+Instead, it's in *all* the states it could possibly be in, at once. It matches a string if, after
+reading every character, any of those states is the accepting state.
+*/
+
+#[derive(Debug, Clone, Default)]
+pub struct NfaState {
+	pub eps:    Vec<usize>,              // ε edges, to these states.
+	pub edges:  Vec<(CharClass, usize)>, // on any of these characters, go to this state.
+	pub accept: Option<usize>,           // if this is an accepting state, which rule it's for.
+}
+
+/*
+An NFA made from one regex has one accepting state, for rule 0. A lexer needs to know *which* of
+its regexes matched, so an NFA made by Nfa::from_rules() has an accepting state for each regex,
+and each one remembers which rule (which regex in the list) it's for.
+*/
+#[derive(Debug, Clone)]
+pub struct Nfa {
+	pub states: Vec<NfaState>,
+	pub start:  usize,
+	pub rules:  usize, // how many rules (regexes) it was made from.
+}
+
+/*
+Thompson's construction turns a regex into an NFA piece by piece. Each piece of the regex becomes
+a *fragment* with one start state and one end state, and the fragments are glued together with ε
+edges. (s and e are the fragment's start and end; x and y are the fragments of what's inside.)
+
+	a:     s --a--> e
+	ε:     s --ε--> e
+	xy:    s --ε--> x --ε--> y --ε--> e
+	x|y:   s --ε--> x --ε--> e, and s --ε--> y --ε--> e
+	x*:    s --ε--> x --ε--> e, plus x's end --ε--> x's start (to go around again),
+	       and s --ε--> e (to skip it)
+	x+:    the same as x*, but without the edge that skips it.
+	x?:    the same as x*, but without the edge that goes around again.
+
+This makes more states and ε edges than you'd draw by hand, but it's simple, and it always works.
+Converting to a DFA gets rid of all the ε edges anyway.
+*/
+impl Nfa {
+	pub fn new(re: &Regex) -> Self {
+		Self::from_rules(std::slice::from_ref(re))
+	}
+
+	// One NFA that matches any of the regexes. It's just a new start state with an ε edge to the
+	// start of each regex's fragment, like x|y|z, except the ends aren't joined together.
+	pub fn from_rules(rules: &[Regex]) -> Self {
+		let mut nfa = Nfa { states: vec![], start: 0, rules: rules.len() };
+
+		if let [re] = rules {
+			let (start, accept) = nfa.build(re);
+			nfa.start = start;
+			nfa.states[accept].accept = Some(0);
+		} else {
+			nfa.start = nfa.add_state();
+
+			for (i, re) in rules.iter().enumerate() {
+				let (start, accept) = nfa.build(re);
+				nfa.add_eps(nfa.start, start);
+				nfa.states[accept].accept = Some(i);
+			}
+		}
+
+		nfa
+	}
+
+	fn add_state(&mut self) -> usize {
+		self.states.push(NfaState::default());
+		self.states.len() - 1
+	}
+
+	fn add_eps(&mut self, from: usize, to: usize) {
+		self.states[from].eps.push(to);
+	}
+
+	// returns the start and end states of the fragment for this regex.
+	fn build(&mut self, re: &Regex) -> (usize, usize) {
+		let s = self.add_state();
+
+		let e = match re {
+			Regex::Empty => {
+				let e = self.add_state();
+				self.add_eps(s, e);
+				e
+			}
+
+			Regex::Class(class) => {
+				let e = self.add_state();
+				self.states[s].edges.push((class.clone(), e));
+				e
+			}
+
+			Regex::Concat(parts) => {
+				let mut prev = s;
+
+				for part in parts {
+					let (x_start, x_end) = self.build(part);
+					self.add_eps(prev, x_start);
+					prev = x_end;
+				}
+
+				let e = self.add_state();
+				self.add_eps(prev, e);
+				e
+			}
+
+			Regex::Alt(alts) => {
+				let ends = alts.iter().map(|alt| {
+					let (x_start, x_end) = self.build(alt);
+					self.add_eps(s, x_start);
+					x_end
+				}).collect::<Vec<_>>();
+
+				let e = self.add_state();
+
+				for x_end in ends {
+					self.add_eps(x_end, e);
+				}
+
+				e
+			}
+
+			Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+				let (x_start, x_end) = self.build(inner);
+				let e = self.add_state();
+				self.add_eps(s, x_start);
+				self.add_eps(x_end, e);
+
+				// go around again.
+				if !matches!(re, Regex::Optional(..)) {
+					self.add_eps(x_end, x_start);
+				}
+
+				// skip it.
+				if !matches!(re, Regex::Plus(..)) {
+					self.add_eps(s, e);
+				}
+
+				e
+			}
+		};
+
+		(s, e)
+	}
+
+	// Every state you can get to from these states by following only ε edges (including the
+	// states themselves).
+	pub fn epsilon_closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
+		let mut ret = states.clone();
+		let mut todo = states.iter().copied().collect::<Vec<_>>();
+
+		while let Some(state) = todo.pop() {
+			for &next in &self.states[state].eps {
+				if ret.insert(next) {
+					todo.push(next);
+				}
+			}
+		}
+
+		ret
+	}
+
+	// The states you can get to from these states by reading c (not including the ε closure).
+	pub fn step(&self, states: &BTreeSet<usize>, c: char) -> BTreeSet<usize> {
+		states.iter()
+			.flat_map(|&state| self.states[state].edges.iter())
+			.filter(|(class, _)| class.contains(c))
+			.map(|&(_, next)| next)
+			.collect()
+	}
+
+	// Runs the NFA on the string by keeping track of every state it could be in. Does the
+	// *whole* string match (any of the rules)?
+	pub fn is_match(&self, s: &str) -> bool {
+		let mut states = self.epsilon_closure(&std::iter::once(self.start).collect());
+
+		for c in s.chars() {
+			states = self.epsilon_closure(&self.step(&states, c));
+
+			// if there's nowhere to go, it can never match.
+			if states.is_empty() {
+				return false;
+			}
+		}
+
+		states.iter().any(|&state| self.states[state].accept.is_some())
+	}
+
+	/*
+	DOT output (see rust_trees for more about DOT). Accepting states have a double circle, and
+	an arrow coming in from nowhere points at the start state, like in the drawings from class.
+	*/
+	pub fn to_dot(&self) -> String {
+		let mut out = String::new();
+		out.push_str("digraph nfa {\n");
+		out.push_str("\trankdir=LR;\n");
+		out.push_str("\tnode [shape=circle];\n");
+
+		for (i, state) in self.states.iter().enumerate() {
+			if let Some(rule) = state.accept {
+				write_dot_accept(&mut out, i, rule, self.rules);
+			}
+		}
+
+		out.push_str("\tstart [shape=none, label=\"\"];\n");
+		writeln!(out, "\tstart -> {};", self.start).unwrap();
+
+		for (i, state) in self.states.iter().enumerate() {
+			for &next in &state.eps {
+				writeln!(out, "\t{} -> {} [label=\"ε\"];", i, next).unwrap();
+			}
+
+			for (class, next) in &state.edges {
+				writeln!(out, "\t{} -> {} [label=\"{}\"];", i, next, escape(&class.to_string()))
+					.unwrap();
+			}
+		}
+
+		out.push_str("}\n");
+		out
+	}
+}
+
+// how accepting states are shown: "accept", or "accept rule 2" if there's more than one rule.
+pub(crate) fn accept_label(rule: usize, rules: usize) -> String {
+	if rules == 1 { "accept".into() } else { format!("accept rule {}", rule) }
+}
+
+// accepting states get a double circle, and a label saying which rule they're for (if there's
+// more than one).
+pub(crate) fn write_dot_accept(out: &mut String, state: usize, rule: usize, rules: usize) {
+	if rules == 1 {
+		writeln!(out, "\t{} [shape=doublecircle];", state).unwrap();
+	} else {
+		writeln!(out, "\t{} [shape=doublecircle, xlabel=\"rule {}\"];", state, rule).unwrap();
+	}
+}
+
+// quotes and backslashes have to be escaped inside DOT strings.
+pub(crate) fn escape(s: &str) -> String {
+	s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// A text listing of the states and their edges.
+impl Display for Nfa {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (i, state) in self.states.iter().enumerate() {
+			let what = match state.accept {
+				_ if i == self.start => " (start)".into(),
+				Some(rule)           => format!(" ({})", accept_label(rule, self.rules)),
+				None                 => "".into(),
+			};
+
+			writeln!(f, "State {}{}:", i, what)?;
+
+			for next in &state.eps {
+				writeln!(f, "    on ε go to {}", next)?;
+			}
+
+			for (class, next) in &state.edges {
+				writeln!(f, "    on {} go to {}", class, next)?;
+			}
+		}
+
+		Ok(())
+	}
+}
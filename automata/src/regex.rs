@@ -0,0 +1,363 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Regular expressions
+// ------------------------------------------------------------------------------------------------
+
+/*
+This is the regex syntax from class, plus a few conveniences that real regex libraries have:
+
+	a        the character a.
+	xy       x followed by y (concatenation).
+	x|y      x or y (alternation).
+	x*       zero or more x (Kleene star).
+	x+       one or more x. (same as xx*)
+	x?       zero or one x. (same as x|ε)
+	(x)      grouping.
+	[abc]    any one of a, b, or c. ranges work too, like [a-zA-Z].
+	[^abc]   any one character that's *not* a, b, or c.
+	.        any one character except a newline.
+	\d \w \s a digit [0-9], a word character [a-zA-Z0-9_], or whitespace [ \t\n\r].
+	\n \t \r a newline, tab, or carriage return. \u{3bb} is the character with that hex code.
+	\*       a backslash before any other character means that character, so \* matches a *.
+
+The operators from lowest to highest precedence are |, then concatenation, then * + ?. So `ab|c*`
+means `(ab)|(c*)`. An empty regex, or an empty side of a |, matches the empty string (ε).
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regex {
+	Empty,               // ε: matches the empty string.
+	Class(CharClass),    // one character. a single character like `a` is a class with just a in it.
+	Concat(Vec<Regex>),
+	Alt(Vec<Regex>),
+	Star(Box<Regex>),
+	Plus(Box<Regex>),
+	Optional(Box<Regex>),
+}
+
+impl Regex {
+	// the precedence of the outermost operator, for knowing where Display needs parentheses.
+	fn precedence(&self) -> u8 {
+		match self {
+			Regex::Alt(..)    => 0,
+			Regex::Concat(..) => 1,
+			_                 => 2,
+		}
+	}
+
+	fn fmt_inside(&self, f: &mut Formatter, min_prec: u8) -> FmtResult {
+		if self.precedence() < min_prec {
+			write!(f, "(")?;
+			self.fmt_inside(f, 0)?;
+			write!(f, ")")
+		} else {
+			write!(f, "{}", self)
+		}
+	}
+}
+
+// Shows the regex the way it would be written, with only the parentheses that are needed.
+impl Display for Regex {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Regex::Empty => write!(f, "()"),
+			Regex::Class(class) => write!(f, "{}", class),
+
+			Regex::Concat(parts) => {
+				for part in parts {
+					part.fmt_inside(f, 2)?;
+				}
+
+				Ok(())
+			}
+
+			Regex::Alt(alts) => {
+				for (i, alt) in alts.iter().enumerate() {
+					if i > 0 {
+						write!(f, "|")?;
+					}
+
+					alt.fmt_inside(f, 1)?;
+				}
+
+				Ok(())
+			}
+
+			Regex::Star(inner)     => { inner.fmt_inside(f, 3)?; write!(f, "*") }
+			Regex::Plus(inner)     => { inner.fmt_inside(f, 3)?; write!(f, "+") }
+			Regex::Optional(inner) => { inner.fmt_inside(f, 3)?; write!(f, "?") }
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Parsing regexes
+// ------------------------------------------------------------------------------------------------
+
+/*
+The grammar of regexes is tiny, so this is a recursive-descent parser, like parsing_lisp's:
+
+	Alt:    Concat ('|' Concat)*
+	Concat: Repeat*
+	Repeat: Atom ('*' | '+' | '?')*
+	Atom:   '(' Alt ')' | '[' Class ']' | '.' | '\' Escape | <any other character>
+
+Spans are character indexes into the regex, so errors can point at the problem.
+*/
+pub fn parse_regex(src: &str) -> Result<Regex, Diagnostic> {
+	let mut p = RegexParser { chars: src.chars().collect(), pos: 0 };
+	let ret = p.parse_alt()?;
+
+	match p.cur() {
+		None => Ok(ret),
+		// parse_alt() only stops early at a ')'.
+		Some(_) => Err(p.error("this ')' doesn't have a '(' to go with it")),
+	}
+}
+
+struct RegexParser {
+	chars: Vec<char>,
+	pos:   usize,
+}
+
+impl RegexParser {
+	fn cur(&self) -> Option<char> {
+		self.chars.get(self.pos).copied()
+	}
+
+	fn next(&mut self) {
+		self.pos += 1;
+	}
+
+	// Alt: Concat ('|' Concat)*
+	fn parse_alt(&mut self) -> Result<Regex, Diagnostic> {
+		let mut alts = vec![self.parse_concat()?];
+
+		while self.cur() == Some('|') {
+			self.next();
+			alts.push(self.parse_concat()?);
+		}
+
+		Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Regex::Alt(alts) })
+	}
+
+	// Concat: Repeat*
+	fn parse_concat(&mut self) -> Result<Regex, Diagnostic> {
+		let mut parts = vec![];
+
+		while let Some(c) = self.cur() {
+			if c == '|' || c == ')' {
+				break;
+			}
+
+			parts.push(self.parse_repeat()?);
+		}
+
+		Ok(match parts.len() {
+			0 => Regex::Empty,
+			1 => parts.pop().unwrap(),
+			_ => Regex::Concat(parts),
+		})
+	}
+
+	// Repeat: Atom ('*' | '+' | '?')*
+	fn parse_repeat(&mut self) -> Result<Regex, Diagnostic> {
+		let mut ret = self.parse_atom()?;
+
+		loop {
+			ret = match self.cur() {
+				Some('*') => Regex::Star(Box::new(ret)),
+				Some('+') => Regex::Plus(Box::new(ret)),
+				Some('?') => Regex::Optional(Box::new(ret)),
+				_         => break,
+			};
+
+			self.next();
+		}
+
+		Ok(ret)
+	}
+
+	// Atom: '(' Alt ')' | '[' Class ']' | '.' | '\' Escape | <any other character>
+	fn parse_atom(&mut self) -> Result<Regex, Diagnostic> {
+		let c = self.cur().unwrap();
+		let start = self.pos;
+
+		match c {
+			'(' => {
+				self.next();
+				let ret = self.parse_alt()?;
+
+				if self.cur() != Some(')') {
+					return Err(bad_regex("this '(' is never closed", Span::point(start)));
+				}
+
+				self.next();
+				Ok(ret)
+			}
+
+			'[' => self.parse_class(),
+			'.' => { self.next(); Ok(Regex::Class(CharClass::dot())) }
+			'\\' => self.parse_escape(false).map(Regex::Class),
+
+			'*' | '+' | '?' => Err(self.error(format!("there's nothing before this '{}' for it to \
+				repeat", c)).with_note(format!("to match a '{}' character, write '\\{}'", c, c))),
+
+			_ => { self.next(); Ok(Regex::Class(CharClass::single(c))) }
+		}
+	}
+
+	// Class: '^'? (Char ('-' Char)?)*
+	fn parse_class(&mut self) -> Result<Regex, Diagnostic> {
+		let start = self.pos;
+		self.next();
+
+		let negated = self.cur() == Some('^');
+
+		if negated {
+			self.next();
+		}
+
+		let mut class = CharClass::empty();
+
+		loop {
+			let lo = match self.cur() {
+				None      => return Err(bad_regex("this '[' is never closed", Span::point(start))),
+				Some(']') => break,
+				Some(_)   => self.parse_class_char()?,
+			};
+
+			// a '-' right before the ']' is just a '-', like in [a-].
+			if self.cur() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+				let dash = self.pos;
+				self.next();
+
+				if self.cur().is_none() {
+					return Err(bad_regex("this '[' is never closed", Span::point(start)));
+				}
+
+				let hi = self.parse_class_char()?;
+				class = class.union(&self.make_range(&lo, &hi, dash)?);
+			} else {
+				class = class.union(&lo);
+			}
+		}
+
+		self.next();
+
+		if negated {
+			Ok(Regex::Class(class.negate()))
+		} else if class.is_empty() {
+			Err(bad_regex("a character class has to have at least one character in it",
+				Span::new(start, self.pos)).with_note("to match a ']' character, write '\\]'"))
+		} else {
+			Ok(Regex::Class(class))
+		}
+	}
+
+	// one character inside [], which could be an escape.
+	fn parse_class_char(&mut self) -> Result<CharClass, Diagnostic> {
+		match self.cur() {
+			Some('\\') => self.parse_escape(true),
+			Some(c)    => { self.next(); Ok(CharClass::single(c)) }
+			None       => unreachable!(),
+		}
+	}
+
+	fn make_range(&self, lo: &CharClass, hi: &CharClass, dash: usize)
+	-> Result<CharClass, Diagnostic> {
+		match (lo.ranges(), hi.ranges()) {
+			(&[(lo, lo2)], &[(hi, hi2)]) if lo == lo2 && hi == hi2 => {
+				if lo <= hi {
+					Ok(CharClass::range(lo, hi))
+				} else {
+					Err(bad_regex(format!("the range {}-{} is backwards", escape_char(lo, true),
+						escape_char(hi, true)), Span::point(dash))
+						.with_note(format!("did you mean {}-{}?", escape_char(hi, true),
+						escape_char(lo, true))))
+				}
+			}
+
+			// something like [\d-z].
+			_ => Err(bad_regex("both ends of a range have to be single characters",
+				Span::point(dash))),
+		}
+	}
+
+	// '\' followed by something. \d, \w, and \s aren't one character, so this gives a class.
+	fn parse_escape(&mut self, in_class: bool) -> Result<CharClass, Diagnostic> {
+		let start = self.pos;
+		self.next();
+
+		let c = match self.cur() {
+			Some(c) => c,
+			None    => return Err(bad_regex("there's nothing after this '\\'",
+				Span::point(start)).with_note("to match a '\\' character, write '\\\\'")),
+		};
+
+		self.next();
+
+		Ok(match c {
+			'n' => CharClass::single('\n'),
+			't' => CharClass::single('\t'),
+			'r' => CharClass::single('\r'),
+			'd' => CharClass::digit(),
+			'w' => CharClass::word(),
+			's' => CharClass::space(),
+			'u' => CharClass::single(self.parse_unicode_escape(start)?),
+
+			// \b and friends mean something in real regexes, but not here. better to complain
+			// than to silently match a 'b'.
+			c if c.is_ascii_alphanumeric() => {
+				let what = if in_class { "inside []" } else { "in a regex" };
+
+				return Err(bad_regex(format!("'\\{}' doesn't mean anything {}", c, what),
+					Span::new(start, self.pos)));
+			}
+
+			c => CharClass::single(c),
+		})
+	}
+
+	// the {3bb} part of \u{3bb}.
+	fn parse_unicode_escape(&mut self, start: usize) -> Result<char, Diagnostic> {
+		let bad = |end| bad_regex("a '\\u' escape looks like '\\u{3bb}', with a hex number for \
+			the character", Span::new(start, end));
+
+		if self.cur() != Some('{') {
+			return Err(bad(self.pos));
+		}
+
+		self.next();
+		let digits_start = self.pos;
+
+		while self.cur().is_some_and(|c| c.is_ascii_hexdigit()) {
+			self.next();
+		}
+
+		let digits = self.chars[digits_start .. self.pos].iter().collect::<String>();
+
+		if self.cur() != Some('}') {
+			return Err(bad(self.pos));
+		}
+
+		self.next();
+
+		u32::from_str_radix(&digits, 16).ok().and_then(std::char::from_u32)
+			.ok_or_else(|| bad(self.pos))
+	}
+
+	// makes an error at the current character.
+	fn error(&self, message: impl Into<String>) -> Diagnostic {
+		bad_regex(message, Span::point(self.pos))
+	}
+}
+
+fn bad_regex(message: impl Into<String>, span: Span) -> Diagnostic {
+	Diagnostic::error(message, span).with_code(diagnostics::BAD_REGEX)
+}
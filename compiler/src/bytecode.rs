@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use diagnostics::Diagnostic;
+use ir::{ Cfg, Inst, Operand, UnOp };
+use toylang::BinOp;
+use vm::{ Instr, Module, VmError };
+
+// ------------------------------------------------------------------------------------------------
+// The VM back end
+// ------------------------------------------------------------------------------------------------
+
+/*
+Three-address code is made for register machines, but the VM is a stack machine. The simplest way
+to connect them is to treat every operand as a variable: to do `t3 = t1 + x`, push both operands,
+add, and pop the result into t3:
+
+	load %t1
+	load x
+	add
+	store %t3
+
+Temps become global variables with a `%` in front, which can't be in a real variable's name, so
+they never clash. That's a lot of loading and storing, and a real compiler for a stack machine would
+leave the values on the stack instead. But it's simple, and it works for any IR, even the kind with
+temps that live across jumps (like `&&` makes).
+
+The VM can only jump to a target that's part of the instruction, so it can't do a jump table. A
+jump table becomes the comparisons that it was supposed to avoid instead: `if index == 0 goto
+the first target`, and so on. It still works, it's just not any faster.
+
+Jumps are the same problem the assembler has: a jump to a label that hasn't been seen yet doesn't
+know where to go. So it's the same solution: emit the jump with a dummy target, and patch it at
+the end, once every label's offset is known.
+
+A module can only have 65,536 different constants and names (temps included), so a really huge
+program can be too big for it. That's an error, like a builtin that MIPS can't call.
+*/
+pub fn emit_bytecode(cfg: &Cfg) -> Result<Module, Diagnostic> {
+	emit(cfg).map_err(Diagnostic::from)
+}
+
+fn emit(cfg: &Cfg) -> Result<Module, VmError> {
+	let mut module = Module::new();
+	let mut labels = HashMap::new();
+	let mut fixups = vec![];
+
+	for inst in cfg.instructions() {
+		match inst {
+			Inst::Bin { dst, op, lhs, rhs } => {
+				push(&mut module, lhs)?;
+				push(&mut module, rhs)?;
+				module.emit(bin_instr(*op));
+				store(&mut module, dst)?;
+			}
+
+			Inst::Un { dst, op, src } => {
+				push(&mut module, src)?;
+				module.emit(match op {
+					UnOp::Neg => Instr::Neg,
+					UnOp::Not => Instr::Not,
+				});
+				store(&mut module, dst)?;
+			}
+
+			Inst::Copy { dst, src } => {
+				push(&mut module, src)?;
+				store(&mut module, dst)?;
+			}
+
+			Inst::Call { dst, func, args } => {
+				for arg in args {
+					push(&mut module, arg)?;
+				}
+
+				let name = module.add_name(func)?;
+				module.emit(Instr::Call(name, args.len() as u8));
+				store(&mut module, dst)?;
+			}
+
+			Inst::Label(l) => { labels.insert(*l, module.here()); }
+
+			Inst::Jump(target) => {
+				fixups.push((module.emit(Instr::Jmp(0)), *target));
+			}
+
+			Inst::CondJump { cond, jump_if, target } => {
+				push(&mut module, cond)?;
+				let jump = if *jump_if { Instr::Jnz(0) } else { Instr::Jz(0) };
+				fixups.push((module.emit(jump), *target));
+			}
+
+			// lower() made sure the index is in range, so one of these always jumps.
+			Inst::JumpTable { index, targets } => {
+				for (i, target) in targets.iter().enumerate() {
+					push(&mut module, index)?;
+					push(&mut module, &Operand::Const(i as f64))?;
+					module.emit(Instr::Eq);
+					fixups.push((module.emit(Instr::Jnz(0)), *target));
+				}
+			}
+
+			Inst::Alloc { dst, len } => {
+				push(&mut module, len)?;
+				module.emit(Instr::Alloc);
+				store(&mut module, dst)?;
+			}
+
+			Inst::Load { dst, addr } => {
+				push(&mut module, addr)?;
+				module.emit(Instr::LoadM);
+				store(&mut module, dst)?;
+			}
+
+			Inst::Store { addr, src } => {
+				push(&mut module, addr)?;
+				push(&mut module, src)?;
+				module.emit(Instr::StoreM);
+			}
+
+			Inst::BoundsCheck { index, len } => {
+				push(&mut module, index)?;
+				push(&mut module, len)?;
+				module.emit(Instr::Check);
+			}
+
+			Inst::Return(Some(val)) => {
+				push(&mut module, val)?;
+				module.emit(Instr::Ret);
+			}
+
+			Inst::Return(None) => { module.emit(Instr::Halt); }
+		}
+	}
+
+	for (offset, label) in fixups {
+		module.patch_jump(offset, labels[&label]);
+	}
+
+	Ok(module)
+}
+
+fn bin_instr(op: BinOp) -> Instr {
+	match op {
+		BinOp::Add => Instr::Add,
+		BinOp::Sub => Instr::Sub,
+		BinOp::Mul => Instr::Mul,
+		BinOp::Div => Instr::Div,
+		BinOp::Mod => Instr::Mod,
+		BinOp::Lt  => Instr::Lt,
+		BinOp::Le  => Instr::Le,
+		BinOp::Gt  => Instr::Gt,
+		BinOp::Ge  => Instr::Ge,
+		BinOp::Eq  => Instr::Eq,
+		BinOp::Ne  => Instr::Ne,
+
+		// lower() turns these into jumps.
+		BinOp::And | BinOp::Or => panic!("the IR should never have a '{}' instruction", op),
+	}
+}
+
+fn push(module: &mut Module, op: &Operand) -> Result<(), VmError> {
+	let instr = match op {
+		Operand::Const(c) => Instr::Push(module.add_const(*c)?),
+		_                 => Instr::Load(module.add_name(&var_name(op))?),
+	};
+
+	module.emit(instr);
+	Ok(())
+}
+
+fn store(module: &mut Module, op: &Operand) -> Result<(), VmError> {
+	let name = module.add_name(&var_name(op))?;
+	module.emit(Instr::Store(name));
+	Ok(())
+}
+
+fn var_name(op: &Operand) -> String {
+	match op {
+		Operand::Var(name) => name.clone(),
+		Operand::Temp(t)   => format!("%{}", t),
+		Operand::Const(..) => panic!("a constant isn't a variable"),
+	}
+}
+
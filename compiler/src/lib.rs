@@ -0,0 +1,395 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use diagnostics::{ Diagnostic, FileId, Severity, SourceManager, Span };
+use ir::{ Cfg, OptReport, PassManager };
+use semantic::{ Lint, TExp, TExpKind, TProgram, TStmt, TStmtKind, Type };
+use toylang::Env;
+
+/*
+This is the whole thing: a compiler, from source code to assembly, made of the other examples.
+Each phase is one of the crates you've already seen, and this crate just hands the output of each
+one to the next:
+
+	source code
+	  --lex-->        tokens          (toylang, with includes spliced in)
+	  --parse-->      AST             (toylang)
+	  --resolve-->    which name is which symbol  (semantic)
+	  --lint-->       warnings about things that look like mistakes (semantic)
+	  --typecheck-->  typed AST, with the type of every expression (semantic)
+	  --lower-->      three-address code  (ir)
+	  --optimize-->   better three-address code (ir, one pass at a time)
+	  --codegen-->    MIPS assembly (mips.rs), or bytecode for the vm crate (bytecode.rs)
+
+The *front end* (lexing through type checking) is where all the errors are found. If a program
+makes it past the front end, it's correct, and the rest of the phases can't fail. That's why the
+later phases in the other crates don't report errors: they trust the front end. (The front end can
+give warnings too, but those don't stop anything; they're handed back separately from the errors.)
+
+(There's one exception: strings. The interpreter can run programs with strings in them, but every
+operand in the IR is a number, so there's nowhere to put one. to_ir() checks for them before
+lowering, so you get an error instead of a crash.)
+
+`--emit` stops the pipeline early and shows what one phase made, which is the best way to see
+what each phase does. See main.rs.
+*/
+
+mod bytecode;
+mod mips;
+
+pub use crate::bytecode::*;
+pub use crate::mips::*;
+
+// ------------------------------------------------------------------------------------------------
+// Options
+// ------------------------------------------------------------------------------------------------
+
+// Which phase's output to show.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Emit {
+	Tokens,
+	Ast,
+	Ir,
+	IrAfter(&'static str), // the IR after each time this optimization pass ran.
+	Asm,
+}
+
+impl FromStr for Emit {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(pass) = s.strip_prefix("ir-after=") {
+			return ir::find_pass(pass)
+				.map(|pass| Emit::IrAfter(pass.name))
+				.ok_or_else(|| format!("invalid --emit '{}' (there's no pass named '{}')", s,
+					pass));
+		}
+
+		match s {
+			"tokens" => Ok(Emit::Tokens),
+			"ast"    => Ok(Emit::Ast),
+			"ir"     => Ok(Emit::Ir),
+			"asm"    => Ok(Emit::Asm),
+			_        => Err(format!("invalid --emit '{}' (use tokens, ast, ir, ir-after=<pass>, \
+				or asm)", s)),
+		}
+	}
+}
+
+// What the assembly is for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Target {
+	Mips,
+	Vm,
+}
+
+impl FromStr for Target {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"mips" => Ok(Target::Mips),
+			"vm"   => Ok(Target::Vm),
+			_      => Err(format!("invalid --target '{}' (use mips or vm)", s)),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Options {
+	pub emit:     Emit,
+	pub target:   Target,
+	pub optimize: bool,
+	pub passes:   Vec<&'static str>, // the IR optimization passes, in the order they run.
+	pub allow:    Vec<Lint>,         // the kinds of warnings not to give.
+}
+
+impl Default for Options {
+	fn default() -> Self {
+		Options { emit: Emit::Asm, target: Target::Mips, optimize: true,
+			passes: ir::DEFAULT_PASSES.to_vec(), allow: vec![] }
+	}
+}
+
+impl Options {
+	// Looks through the command-line arguments for `--emit=<what>`, `--target=<what>`,
+	// `--allow=<warning>` (which can be given more than once), `--passes=<pass>,<pass>...`, and
+	// `--no-opt`. Anything it doesn't know is left alone.
+	pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+		let mut ret = Options::default();
+
+		for arg in args {
+			if let Some(emit) = arg.strip_prefix("--emit=") {
+				ret.emit = emit.parse()?;
+			} else if let Some(target) = arg.strip_prefix("--target=") {
+				ret.target = target.parse()?;
+			} else if let Some(lint) = arg.strip_prefix("--allow=") {
+				ret.allow.push(lint.parse()?);
+			} else if let Some(passes) = arg.strip_prefix("--passes=") {
+				ret.passes = parse_passes(passes)?;
+			} else if arg == "--no-opt" {
+				ret.optimize = false;
+			}
+		}
+
+		// so that `--emit=ir-after=cse --no-opt` is caught now, and not after the front end ran.
+		ret.pass_manager()?;
+		Ok(ret)
+	}
+
+	// The IR optimization passes to run. With `--no-opt`, there are none.
+	pub fn pass_manager(&self) -> Result<PassManager, String> {
+		let mut ret = if self.optimize {
+			PassManager::with_passes(&self.passes)?
+		} else {
+			PassManager::new()
+		};
+
+		if let Emit::IrAfter(pass) = self.emit {
+			ret.snapshot_after(pass)?;
+		}
+
+		Ok(ret)
+	}
+}
+
+// `--passes=` with nothing after it means no passes.
+fn parse_passes(s: &str) -> Result<Vec<&'static str>, String> {
+	s.split(',')
+		.filter(|name| !name.is_empty())
+		.map(|name| ir::find_pass(name).map(|pass| pass.name)
+			.ok_or_else(|| format!("invalid --passes: there's no pass named '{}'", name)))
+		.collect()
+}
+
+// ------------------------------------------------------------------------------------------------
+// The pipeline
+// ------------------------------------------------------------------------------------------------
+
+// Runs the pipeline as far as `options.emit` says, and gives back the text of what that phase
+// made. The errors' spans are global positions in `sources`, so show them with
+// sources.render_all(). The same goes for the warnings, which are put in `warnings` even if there
+// are errors too.
+pub fn compile(sources: &mut SourceManager, file: FileId, options: &Options,
+warnings: &mut Vec<Diagnostic>) -> Result<String, Vec<Diagnostic>> {
+	let allow = &options.allow;
+	let mut front_end = |sources: &mut SourceManager| check(sources, file, allow, warnings);
+	let passes = options.pass_manager()
+		.map_err(|e| vec![Diagnostic::new(Severity::Error, e, None)])?;
+
+	match options.emit {
+		Emit::Tokens => show_tokens(sources, file),
+		Emit::Ast    => Ok(front_end(sources)?.to_string()),
+		Emit::Ir     => Ok(to_ir(&front_end(sources)?, &passes)?.0.to_string()),
+
+		Emit::IrAfter(pass) => {
+			let (_, report) = to_ir(&front_end(sources)?, &passes)?;
+			let mut out = String::new();
+
+			for (run, cfg) in report.snapshots(pass) {
+				writeln!(out, "after {}, round {}: {}\n{}", run.pass, run.round,
+					run.result.summary, cfg).unwrap();
+			}
+
+			Ok(out)
+		}
+
+		Emit::Asm    => {
+			let (cfg, _) = to_ir(&front_end(sources)?, &passes)?;
+
+			match options.target {
+				Target::Mips => emit_mips(&cfg).map_err(|e| vec![e]),
+				Target::Vm   => {
+					let module = to_bytecode(&cfg, options.optimize).map_err(|e| vec![e])?;
+					Ok(vm::disassemble(&module).expect("the code generator made bad bytecode"))
+				}
+			}
+		}
+	}
+}
+
+// The front end: lexes, parses, resolves, and type checks the file (and whatever it includes).
+// If this gives a program, it's a correct one: the typed AST that the type checker built. The
+// warnings (except the kinds in `allow`) are put in `warnings`; they're found as soon as the names
+// are resolved, so a program with type errors can have warnings too.
+pub fn check(sources: &mut SourceManager, file: FileId, allow: &[Lint],
+warnings: &mut Vec<Diagnostic>) -> Result<TProgram, Vec<Diagnostic>> {
+	let program = toylang::compile_file(sources, file)?;
+	let resolved = semantic::resolve(&program, &Env::new())?;
+	warnings.extend(semantic::lint(&program, &resolved, allow));
+	semantic::typecheck(&program, &resolved)
+}
+
+// The middle: lowers a checked program to IR, and runs the optimization passes on it. Gives back
+// what the passes did too. Gives an error if the program has strings in it, since the IR can't
+// hold them.
+pub fn to_ir(program: &TProgram, passes: &PassManager)
+-> Result<(Cfg, OptReport), Vec<Diagnostic>> {
+	let mut strings = vec![];
+
+	for stmt in &program.stmts {
+		find_strings(stmt, &mut strings);
+	}
+
+	if let Some(&span) = strings.first() {
+		return Err(vec![Diagnostic::error("the compiler can't compile strings yet", span)
+			.with_code(diagnostics::UNSUPPORTED_BY_TARGET)
+			.with_note("the IR only has numbers; `toylang eval` can run this program instead")]);
+	}
+
+	let mut cfg = Cfg::new(&ir::lower(program));
+	let report = passes.run(&mut cfg);
+	Ok((cfg, report))
+}
+
+// Puts the span of every string literal in the statement into `found`. Every string starts out
+// as a literal, so if there are none of those, there are no strings.
+fn find_strings(stmt: &TStmt, found: &mut Vec<Span>) {
+	match &stmt.kind {
+		TStmtKind::Assign { value, .. } => find_strings_in(value, found),
+		TStmtKind::Exp(exp) => find_strings_in(exp, found),
+
+		TStmtKind::While { cond, body } => {
+			find_strings_in(cond, found);
+			body.iter().for_each(|stmt| find_strings(stmt, found));
+		}
+
+		TStmtKind::If { cond, then_body, else_body } => {
+			find_strings_in(cond, found);
+			find_strings(then_body, found);
+			else_body.iter().for_each(|stmt| find_strings(stmt, found));
+		}
+
+		TStmtKind::Block(stmts) => stmts.iter().for_each(|stmt| find_strings(stmt, found)),
+		TStmtKind::Break | TStmtKind::Continue => {}
+	}
+}
+
+fn find_strings_in(exp: &TExp, found: &mut Vec<Span>) {
+	use TExpKind::*;
+
+	match &exp.kind {
+		Str(..) => found.push(exp.span),
+
+		Int(..) | Float(..) | Bool(..) | Var(..) => {}
+		Negate(lhs) | Not(lhs) | Len(lhs) => find_strings_in(lhs, found),
+
+		// a loop, so a long chain of operators can't use up the stack.
+		Binary { .. } => {
+			let (first, ops) = exp.left_chain();
+			find_strings_in(first, found);
+			ops.into_iter().for_each(|(_, _, rhs)| find_strings_in(rhs, found));
+		}
+
+		Call  { args, .. } => args.iter().for_each(|arg| find_strings_in(arg, found)),
+		Array (elems)      => elems.iter().for_each(|elem| find_strings_in(elem, found)),
+
+		Index { array, index } => {
+			find_strings_in(array, found);
+			find_strings_in(index, found);
+		}
+
+		Match { scrutinee, arms } => {
+			find_strings_in(scrutinee, found);
+			arms.iter().for_each(|arm| find_strings_in(&arm.value, found));
+		}
+	}
+}
+
+// The VM back end: compiles the IR to bytecode, and runs the peephole optimizer on it if
+// `optimize` is true. Gives an error if the program is too big for a bytecode module.
+pub fn to_bytecode(cfg: &Cfg, optimize: bool) -> Result<vm::Module, Diagnostic> {
+	let module = emit_bytecode(cfg)?;
+
+	if optimize {
+		let (module, _) = vm::peephole(&module).expect("the code generator made bad bytecode");
+		Ok(module)
+	} else {
+		Ok(module)
+	}
+}
+
+// Shows `val`, the number that the VM gave back for the program, the way `toylang eval` would show
+// it. The VM only has numbers, so a bool comes back as 1 or 0; its type in the typed AST is what
+// says it should be shown as true or false.
+pub fn show_result(program: &TProgram, val: f64) -> String {
+	match result_type(program, &program.stmts) {
+		Some(Type::Bool) => (val != 0.0).to_string(),
+		_                => val.to_string(),
+	}
+}
+
+// The type of the last statement's value, which is what the program gives back. This follows
+// ir::lower(), so it's None wherever that doesn't give a value. (An if/else whose two branches
+// give different types is None too, since we can't tell which one ran.)
+fn result_type(program: &TProgram, stmts: &[TStmt]) -> Option<Type> {
+	let stmt = stmts.last()?;
+
+	match &stmt.kind {
+		TStmtKind::Assign { var, .. } => Some(program.vars[var].clone()),
+		TStmtKind::Exp(exp) => Some(exp.ty.clone()),
+		TStmtKind::Block(stmts) => result_type(program, stmts),
+
+		TStmtKind::If { then_body, else_body: Some(else_body), .. } => {
+			let then_ty = result_type(program, std::slice::from_ref(then_body))?;
+			let else_ty = result_type(program, std::slice::from_ref(else_body))?;
+			Some(then_ty).filter(|ty| *ty == else_ty)
+		}
+
+		TStmtKind::If { .. } | TStmtKind::While { .. } | TStmtKind::Break
+			| TStmtKind::Continue => None,
+	}
+}
+
+// Every token, one per line, with the file and line it came from.
+fn show_tokens(sources: &mut SourceManager, file: FileId) -> Result<String, Vec<Diagnostic>> {
+	let (tokens, errors) = toylang::lex_with_includes(sources, file);
+
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	let mut out = String::new();
+
+	for tok in &tokens {
+		let (file, span) = sources.localize(tok.span);
+		let file = sources.file(file);
+		let (line, col) = file.line_col(span.start);
+		let place = format!("{}:{}:{}", file.name(), line, col);
+		writeln!(out, "{:<24} {:?}", place, tok.kind).unwrap();
+	}
+
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use diagnostics::SourceFile;
+
+	use super::*;
+
+	fn compile_str(src: &str, options: &Options) -> Result<String, Vec<Diagnostic>> {
+		let mut sources = SourceManager::new();
+		let file = sources.add(SourceFile::new("<test>", src));
+		compile(&mut sources, file, options, &mut vec![])
+	}
+
+	// constant propagation used to go around forever once it found a NaN, since NaN != NaN.
+	#[test]
+	fn nan_constants_converge() {
+		let srcs = ["0.0 / 0.0", "let x = 0 / 0\nx + 1", "let a = [3 % 0, 1]\na[0]"];
+		let passes = [None, Some("constprop")];
+
+		for src in &srcs {
+			for pass in &passes {
+				let options = Options {
+					emit: Emit::Ir,
+					passes: pass.map_or(ir::DEFAULT_PASSES.to_vec(), |pass| vec![pass]),
+					..Options::default()
+				};
+
+				assert!(compile_str(src, &options).is_ok(), "{:?} didn't compile", src);
+			}
+		}
+	}
+}
@@ -0,0 +1,309 @@
+use std::fmt::Write;
+use std::process::exit;
+
+use colored::Colorize;
+use rustyline::{ Editor, error::ReadlineError };
+
+use diagnostics::{ ColorChoice, Diagnostic, FileId, Severity, SourceFile, SourceManager };
+use compiler::*;
+use semantic::{ TProgram, TStmtKind };
+
+// `compiler file.calc` compiles a file all the way to MIPS assembly, and prints it. You can run
+// the output in MARS or SPIM. `--emit` stops earlier and shows what one phase made instead:
+//
+//     --emit=tokens  the tokens (with the included files' tokens spliced in)
+//     --emit=ast     the typed AST, with the type of every expression
+//     --emit=ir      the three-address code
+//     --emit=ir-after=<pass>
+//                    the three-address code right after an optimization pass, every time it ran
+//     --emit=asm     the assembly (the default)
+//
+// `--target=vm` makes the assembly for the vm crate's stack machine instead of MIPS, and
+// `--no-opt` turns off the optimizations, so you can see what they did. `--passes=constprop,dce`
+// picks which optimization passes run, and in what order (the default is
+// `--passes=constprop,cse,peephole,dce`). They keep going around until nothing changes.
+//
+// Warnings are shown, but they don't stop anything. `--allow=unused-variable` turns that kind of
+// warning off (so do `--allow=unused-parameter` and `--allow=shadowing`), and the unused ones
+// can be turned off for one variable by starting its name with `_`.
+//
+// `compiler run file.calc` compiles the file to bytecode and runs it on the VM, and `compiler
+// build file.calc file.tbc` saves the bytecode, so it can be run with `vm run file.tbc`.
+//
+// With no file, it's interactive. Type some code, and it compiles it and runs it on the VM. Or
+// put a phase in front of it to see what that phase makes instead, like `:ir x = 1 + 2` (type
+// `:help` to see them all). Each line is a whole program by itself, so variables don't stick
+// around from one line to the next. The flags above work here too, so `compiler --target=vm`
+// makes `:asm` show bytecode assembly.
+
+const USAGE: &str = "usage: compiler <file> [--emit=<phase>] [--target=mips|vm]
+       compiler run <file> [--trace]
+       compiler build <file> <file.tbc>
+       compiler [--target=mips|vm]     (interactive)
+(<phase> is tokens, ast, ir, ir-after=<pass>, or asm. all of them also take
+[--color=always|never|auto], [--allow=<warning>], [--no-opt], and [--passes=<pass>,<pass>...])";
+
+fn main() {
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+	let args = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+	let trace = std::env::args().any(|a| a == "--trace");
+
+	let options = match Options::from_args(std::env::args()) {
+		Ok(options) => options,
+		Err(e)      => {
+			eprintln!("error: {}", e);
+			exit(2);
+		}
+	};
+
+	let mut sources = SourceManager::new();
+
+	let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		[]                   => { repl(&options, color); Ok(()) }
+		[path]               => load(&mut sources, path)
+			.and_then(|file| show(&mut sources, file, &options, color)),
+		["run", path]        => load(&mut sources, path)
+			.and_then(|file| run(&mut sources, file, &options, trace, color)),
+		["build", path, dst] => load(&mut sources, path)
+			.and_then(|file| build(&mut sources, file, &options, dst, color)),
+		_                    => {
+			eprintln!("{}", USAGE);
+			exit(2);
+		}
+	};
+
+	// like the other examples, 1 means the program had errors, and 2 means a file couldn't be
+	// read/written.
+	if let Err(code) = result {
+		exit(code);
+	}
+}
+
+fn load(sources: &mut SourceManager, path: &str) -> Result<FileId, i32> {
+	sources.load(path).map_err(|e| {
+		eprintln!("error: could not read '{}': {}", path, e);
+		2
+	})
+}
+
+fn show(sources: &mut SourceManager, file: FileId, options: &Options, color: ColorChoice)
+-> Result<(), i32> {
+	let mut warnings = vec![];
+	let out = compile(sources, file, options, &mut warnings);
+	warn(sources, &warnings, color);
+	print!("{}", out.map_err(|errs| report(sources, &errs, color))?);
+	Ok(())
+}
+
+fn run(sources: &mut SourceManager, file: FileId, options: &Options, trace: bool,
+color: ColorChoice) -> Result<(), i32> {
+	let (program, module) = bytecode(sources, file, options, color)?;
+	let mut machine = vm::Machine::new();
+	machine.trace = trace;
+
+	// the program gives back the value of its last statement, like toylang's run() does.
+	match machine.run(&module) {
+		Ok(Some(val)) => println!("{}", show_result(&program, val)),
+		Ok(None)      => {}
+		Err(e)        => return Err(report(sources, &[Diagnostic::from(e)], color)),
+	}
+
+	Ok(())
+}
+
+fn build(sources: &mut SourceManager, file: FileId, options: &Options, dst: &str,
+color: ColorChoice) -> Result<(), i32> {
+	let (_, module) = bytecode(sources, file, options, color)?;
+
+	std::fs::write(dst, module.to_bytes()).map_err(|e| {
+		eprintln!("error: could not write '{}': {}", dst, e);
+		2
+	})
+}
+
+// gives back the checked program too, since it says how to show the value that the module gives.
+fn bytecode(sources: &mut SourceManager, file: FileId, options: &Options, color: ColorChoice)
+-> Result<(TProgram, vm::Module), i32> {
+	let mut warnings = vec![];
+	let program = check(sources, file, &options.allow, &mut warnings);
+	warn(sources, &warnings, color);
+
+	let program = program.map_err(|errs| report(sources, &errs, color))?;
+	let passes = options.pass_manager().map_err(|e| {
+		eprintln!("error: {}", e);
+		2
+	})?;
+
+	let (cfg, _) = to_ir(&program, &passes).map_err(|errs| report(sources, &errs, color))?;
+	let module = to_bytecode(&cfg, options.optimize).map_err(|e| report(sources, &[e], color))?;
+	Ok((program, module))
+}
+
+fn report(sources: &SourceManager, errs: &[Diagnostic], color: ColorChoice) -> i32 {
+	eprint!("{}", sources.render_all(errs, color));
+	1
+}
+
+// unlike report(), this doesn't give an exit code, since warnings aren't failures.
+fn warn(sources: &SourceManager, warnings: &[Diagnostic], color: ColorChoice) {
+	eprint!("{}", sources.render_all(warnings, color));
+}
+
+// ------------------------------------------------------------------------------------------------
+// Interactive mode
+// ------------------------------------------------------------------------------------------------
+
+// The commands, besides the --emit phases (`:ir-after=<pass>` works too).
+const REPL_HELP: &str = "\
+<code>          compiles the code, runs it on the VM, and shows its value
+:tokens <code>  shows the tokens
+:ast <code>     shows the typed AST
+:type <code>    shows the type of each expression, and of each variable that's set
+:ir <code>      shows the three-address code, after the optimization passes
+:ir-after=<pass> <code>
+                shows the three-address code after each time that pass ran
+:asm <code>     shows the assembly
+:help           shows this";
+
+fn repl(options: &Options, color: ColorChoice) {
+	let mut rl = Editor::<()>::new();
+
+	println!("{}", "------------------------------------------------------------".bright_blue());
+	println!("{}", "Type some code, like 1 + 2 * sqrt(pi), to compile and run it.".bright_blue());
+	println!("{}", ":help lists the commands for seeing each phase. Ctrl+C quits.".bright_blue());
+	println!("{}", "------------------------------------------------------------".bright_blue());
+
+	loop {
+		match rl.readline(">> ") {
+			Ok(line) => {
+				if line.trim().is_empty() {
+					continue;
+				}
+
+				rl.add_history_entry(line.as_str());
+				print!("{}", repl_line(line.trim(), options, color));
+			}
+			Err(ReadlineError::Interrupted) => break,
+			Err(ReadlineError::Eof) => break,
+			Err(err) => {
+				println!("Error: {:?}", err);
+				break
+			}
+		}
+	}
+}
+
+// gives what the REPL prints for the line, so that the tests can see it too.
+fn repl_line(line: &str, options: &Options, color: ColorChoice) -> String {
+	// `:ast x + 1` is the command "ast", and the code "x + 1".
+	let (command, code) = match line.strip_prefix(':') {
+		Some(rest) => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+		None       => ("", line),
+	};
+
+	// the rest of the commands are named after the phases, like --emit.
+	let emit = match command {
+		"help"      => return format!("{}\n\n", REPL_HELP),
+		"" | "type" => options.emit,
+		_           => match command.parse() {
+			Ok(emit) => emit,
+			Err(_)   => return format!("there's no :{} command. :help lists them.\n\n", command),
+		},
+	};
+
+	if code.trim().is_empty() {
+		return format!(":{} needs some code after it, like :{} 1 + 2\n\n", command, command);
+	}
+
+	let mut sources = SourceManager::new();
+	let file = sources.add(SourceFile::new("<input>", code));
+	let mut warnings = vec![];
+
+	let result = match command {
+		""     => run_code(&mut sources, file, options, &mut warnings),
+		"type" => check(&mut sources, file, &options.allow, &mut warnings)
+			.map(|program| show_types(&program, &sources)),
+		_      => compile(&mut sources, file, &Options { emit, ..options.clone() }, &mut warnings),
+	};
+
+	let mut out = sources.render_all(&warnings, color);
+
+	match result {
+		Ok(text)  => writeln!(out, "{}", text).unwrap(),
+		Err(errs) => writeln!(out, "{}", sources.render_all(&errs, color)).unwrap(),
+	}
+
+	out
+}
+
+// like `compiler run`, but it gives back the value instead of printing it.
+fn run_code(sources: &mut SourceManager, file: FileId, options: &Options,
+warnings: &mut Vec<Diagnostic>) -> Result<String, Vec<Diagnostic>> {
+	let program = check(sources, file, &options.allow, warnings)?;
+	let passes = options.pass_manager()
+		.map_err(|e| vec![Diagnostic::new(Severity::Error, e, None)])?;
+
+	let (cfg, _) = to_ir(&program, &passes)?;
+	let module = to_bytecode(&cfg, options.optimize).map_err(|e| vec![e])?;
+
+	match vm::Machine::new().run(&module) {
+		Ok(val) => Ok(val.map(|val| format!("{}\n", show_result(&program, val)))
+			.unwrap_or_default()),
+		Err(e)  => Err(vec![Diagnostic::from(e)]),
+	}
+}
+
+// each expression statement with its type, like `x * 2: int`, and each variable that's set,
+// like `x: float`. that's the variable's type, which isn't always its value's: `let x = 5` makes
+// x an int, but if it's also set to 1.5 later, it's a float.
+fn show_types(program: &TProgram, sources: &SourceManager) -> String {
+	let mut out = String::new();
+
+	for stmt in &program.stmts {
+		match &stmt.kind {
+			TStmtKind::Exp(exp) => {
+				let (file, span) = sources.localize(exp.span);
+				let text = sources.file(file).span_text(span);
+				writeln!(out, "{}: {}", text, exp.ty).unwrap();
+			}
+
+			TStmtKind::Assign { var, .. } => {
+				let name = &program.symbols.get(*var).name;
+				writeln!(out, "{}: {}", name, program.vars[var]).unwrap();
+			}
+
+			_ => {}
+		}
+	}
+
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn repl(line: &str) -> String {
+		repl_line(line, &Options::default(), ColorChoice::Never)
+	}
+
+	// spans count codepoints, and 'é' is two bytes, so the text has to be found by codepoints.
+	#[test]
+	fn repl_types_of_non_ascii_code() {
+		assert_eq!(repl(":type \"éé\""), "\"éé\": string\n\n");
+		assert_eq!(repl(":type \"é\"; 1 + 2"), "\"é\": string\n1 + 2: int\n\n");
+	}
+
+	// the VM gives bools back as 1 and 0, but they should look like they do in `toylang eval`.
+	#[test]
+	fn repl_shows_bools_as_bools() {
+		assert_eq!(repl("let x = 3; x > 2"), "true\n\n");
+		assert_eq!(repl("1 > 2 || false"), "false\n\n");
+		assert_eq!(repl("let _b = 1 < 2"), "true\n\n");
+		assert_eq!(repl("if 1 < 2 { 1 > 0 } else { false }"), "true\n\n");
+		assert_eq!(repl("1 + 2"), "3\n\n");
+	}
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use diagnostics::{ Diagnostic, Severity };
+use ir::{ allocate_registers, Allocation, Cfg, Inst, Label, Liveness, Location, Operand, UnOp };
+use toylang::{ BinOp, Env, Value };
+
+// ------------------------------------------------------------------------------------------------
+// The MIPS back end
+// ------------------------------------------------------------------------------------------------
+
+/*
+Every value in the toy language is an f64, so this uses the MIPS floating-point coprocessor, and
+its *double-precision* instructions (the ones ending in `.d`, like `add.d`). A double takes two
+32-bit float registers, so only the even ones can be used: $f4 means the pair $f4 and $f5.
+
+- temps go in the float registers that the register allocator (from the ir crate) gives them.
+  the ones it spills go in memory, at labels like `spill_0`.
+- variables and constants go in memory too (in the .data section, as `var_x` and `const_0`), and
+  are loaded into a *scratch* register whenever they're used. MIPS can't do math on memory, or on
+  a constant.
+- some registers are never given to temps:
+	- $f0 and $f2 are scratch registers for the operands of an instruction.
+	- $f14 is a scratch register for the result, if it doesn't go in a register.
+	- $f12 is used for the more complicated instructions, and for printing the result.
+	- $f30 is always 0.0, for comparing against. (and $f31, since it's a double.)
+
+Booleans are 1.0 and 0.0, like everywhere else. The FPU's compare instructions (`c.lt.d` and
+friends) set a condition flag instead of giving a value, and `bc1t`/`bc1f` jump if it's true or
+false, so turning a comparison into a 1.0 or 0.0 takes a jump.
+
+A jump table (from a `match`) is a list of label addresses in the .data section, as `table_0` and
+so on. Jumping through it is integer work, so the index gets converted to an int and moved over to
+$t0, and $t1 holds the table's address. Those are the only integer registers this uses, besides
+the ones for syscalls.
+
+Arrays are memory from the `sbrk` syscall (9), which is MIPS's malloc. An address is a double too,
+like every other value, so a load or store converts it to an int in $t0 first, the same way a jump
+table's index is. If a bounds check fails, it jumps to `bounds_error`, which prints a message and
+exits with an error.
+
+At the end, the program prints the value it gives back, and exits. This works with MARS and SPIM,
+with delayed branching turned off (which is the default for both).
+*/
+
+// The float registers that can hold temps.
+pub const MIPS_FLOAT_REGS: &[&str] = &[
+	"$f4", "$f6", "$f8", "$f10", "$f16", "$f18", "$f20", "$f22", "$f24", "$f26", "$f28",
+];
+
+const ZERO: &str = "$f30";
+const LHS:  &str = "$f0";
+const RHS:  &str = "$f2";
+const DST:  &str = "$f14";
+const TMP:  &str = "$f12";
+
+// Gives an error if the program calls a builtin that MIPS can't do (like `sin`).
+pub fn emit_mips(cfg: &Cfg) -> Result<String, Diagnostic> {
+	let alloc = allocate_registers(cfg, &Liveness::new(cfg), MIPS_FLOAT_REGS);
+	let mut gen = MipsGen { alloc, text: String::new(), consts: vec![], vars: BTreeSet::new(),
+		tables: vec![], num_labels: 0, bounds_error: false };
+
+	gen.line(&format!(".globl {}", cfg.name));
+	gen.text.push_str(&format!("{}:\n", cfg.name));
+	gen.inst("mtc1", &["$zero", ZERO]);
+	gen.inst("mtc1", &["$zero", "$f31"]);
+
+	for inst in cfg.instructions() {
+		gen.emit(inst)?;
+	}
+
+	Ok(gen.finish())
+}
+
+struct MipsGen {
+	alloc:      Allocation,
+	text:       String,
+	consts:     Vec<f64>,        // const_0, const_1...
+	vars:       BTreeSet<String>,
+	tables:     Vec<Vec<Label>>, // table_0, table_1...
+	num_labels: usize,           // for the labels that aren't in the IR.
+	bounds_error: bool,          // did anything jump to bounds_error?
+}
+
+impl MipsGen {
+	fn emit(&mut self, inst: &Inst) -> Result<(), Diagnostic> {
+		match inst {
+			Inst::Bin { dst, op, lhs, rhs } => {
+				let lhs = self.read(lhs, LHS);
+				let rhs = self.read(rhs, RHS);
+				let d = self.dst(dst);
+				self.bin(*op, &d, &lhs, &rhs);
+				self.write(dst, &d);
+			}
+
+			Inst::Un { dst, op, src } => {
+				let src = self.read(src, LHS);
+				let d = self.dst(dst);
+
+				match op {
+					UnOp::Neg => self.inst("neg.d", &[&d, &src]),
+					UnOp::Not => {
+						self.inst("c.eq.d", &[&src, ZERO]);
+						self.flag_to_value(&d, true);
+					}
+				}
+
+				self.write(dst, &d);
+			}
+
+			Inst::Copy { dst, src } => {
+				// if the destination is in memory, the value can be stored straight there.
+				let src = self.read(src, LHS);
+				let d = self.dst(dst);
+
+				if d == DST {
+					self.write(dst, &src);
+				} else if d != src {
+					self.inst("mov.d", &[&d, &src]);
+				}
+			}
+
+			Inst::Call { dst, func, args } => {
+				let args = args.iter().zip(&[LHS, RHS])
+					.map(|(arg, scratch)| self.read(arg, scratch))
+					.collect::<Vec<_>>();
+				let d = self.dst(dst);
+
+				match (func.as_str(), &args[..]) {
+					("sqrt", [a])   => self.inst("sqrt.d", &[&d, a]),
+					("abs",  [a])   => self.inst("abs.d", &[&d, a]),
+					("min",  [a, b]) => self.min_max(&d, a, b, true),
+					("max",  [a, b]) => self.min_max(&d, a, b, false),
+					_ => return Err(Diagnostic::new(Severity::Error,
+						format!("the MIPS back end can't call '{}'", func), None)
+						.with_code(diagnostics::UNSUPPORTED_BY_TARGET)
+						.with_note("MIPS only has instructions for sqrt, abs, min, and max")
+						.with_note("the VM can call any builtin; try --target=vm")),
+				}
+
+				self.write(dst, &d);
+			}
+
+			Inst::Label(l) => self.text.push_str(&format!("{}:\n", l)),
+			Inst::Jump(l)  => self.inst("j", &[&l.to_string()]),
+
+			// c.eq.d sets the flag if the condition is *false* (0.0).
+			Inst::CondJump { cond, jump_if, target } => {
+				let cond = self.read(cond, LHS);
+				self.inst("c.eq.d", &[&cond, ZERO]);
+				self.inst(if *jump_if { "bc1f" } else { "bc1t" }, &[&target.to_string()]);
+			}
+
+			// each entry is a 4-byte address, so entry i is at table + i * 4. the index is
+			// always a whole number, so it can be converted to an int without rounding.
+			Inst::JumpTable { index, targets } => {
+				let index = self.read(index, LHS);
+				let table = format!("table_{}", self.tables.len());
+				self.tables.push(targets.clone());
+
+				self.inst("cvt.w.d", &[TMP, &index]);
+				self.inst("mfc1", &["$t0", TMP]);
+				self.inst("sll", &["$t0", "$t0", "2"]);
+				self.inst("la", &["$t1", &table]);
+				self.inst("addu", &["$t0", "$t0", "$t1"]);
+				self.inst("lw", &["$t0", "0($t0)"]);
+				self.inst("jr", &["$t0"]);
+			}
+
+			// sbrk takes the number of bytes in $a0, and gives the address in $v0. there's room
+			// for the length, too, which goes first.
+			Inst::Alloc { dst, len } => {
+				let len = self.read(len, LHS);
+				self.inst("cvt.w.d", &[TMP, &len]);
+				self.inst("mfc1", &["$t0", TMP]);
+				self.inst("addiu", &["$t0", "$t0", "1"]);
+				self.inst("sll", &["$a0", "$t0", "3"]);
+				self.inst("li", &["$v0", "9"]);
+				self.line("syscall");
+				self.inst("s.d", &[&len, "0($v0)"]);
+				self.inst("addiu", &["$v0", "$v0", "8"]);
+
+				let d = self.dst(dst);
+				self.inst("mtc1", &["$v0", TMP]);
+				self.inst("cvt.d.w", &[&d, TMP]);
+				self.write(dst, &d);
+			}
+
+			Inst::Load { dst, addr } => {
+				let addr = self.read(addr, LHS);
+				self.address(&addr);
+				let d = self.dst(dst);
+				self.inst("l.d", &[&d, "0($t0)"]);
+				self.write(dst, &d);
+			}
+
+			Inst::Store { addr, src } => {
+				let addr = self.read(addr, LHS);
+				let src = self.read(src, RHS);
+				self.address(&addr);
+				self.inst("s.d", &[&src, "0($t0)"]);
+			}
+
+			// the index has to be >= 0, < len, and a whole number (which it is if truncating it
+			// doesn't change it).
+			Inst::BoundsCheck { index, len } => {
+				let index = self.read(index, LHS);
+				let len = self.read(len, RHS);
+				self.bounds_error = true;
+
+				self.inst("c.lt.d", &[&index, ZERO]);
+				self.inst("bc1t", &["bounds_error"]);
+				self.inst("c.le.d", &[&len, &index]);
+				self.inst("bc1t", &["bounds_error"]);
+				self.inst("trunc.w.d", &[TMP, &index]);
+				self.inst("cvt.d.w", &[TMP, TMP]);
+				self.inst("c.eq.d", &[TMP, &index]);
+				self.inst("bc1f", &["bounds_error"]);
+			}
+
+			Inst::Return(val) => {
+				if let Some(val) = val {
+					// print_double (syscall 3) prints $f12, and print_char (11) prints $a0.
+					let val = self.read(val, TMP);
+
+					if val != TMP {
+						self.inst("mov.d", &[TMP, &val]);
+					}
+
+					self.inst("li", &["$v0", "3"]);
+					self.line("syscall");
+					self.inst("li", &["$v0", "11"]);
+					self.inst("li", &["$a0", "10"]);
+					self.line("syscall");
+				}
+
+				// exit (syscall 10).
+				self.inst("li", &["$v0", "10"]);
+				self.line("syscall");
+			}
+		}
+
+		Ok(())
+	}
+
+	fn bin(&mut self, op: BinOp, d: &str, lhs: &str, rhs: &str) {
+		match op {
+			BinOp::Add => self.inst("add.d", &[d, lhs, rhs]),
+			BinOp::Sub => self.inst("sub.d", &[d, lhs, rhs]),
+			BinOp::Mul => self.inst("mul.d", &[d, lhs, rhs]),
+			BinOp::Div => self.inst("div.d", &[d, lhs, rhs]),
+
+			// there's no instruction for this, so it's lhs - trunc(lhs / rhs) * rhs, which is
+			// what Rust's % does for floats. trunc.w.d goes through a 32-bit int, so this only
+			// works when the quotient fits in one.
+			BinOp::Mod => {
+				self.inst("div.d", &[TMP, lhs, rhs]);
+				self.inst("trunc.w.d", &[TMP, TMP]);
+				self.inst("cvt.d.w", &[TMP, TMP]);
+				self.inst("mul.d", &[TMP, TMP, rhs]);
+				self.inst("sub.d", &[d, lhs, TMP]);
+			}
+
+			// there's only "less than," "less or equal," and "equal," so > and >= swap the
+			// operands, and != is == with the answer flipped.
+			BinOp::Lt => { self.inst("c.lt.d", &[lhs, rhs]); self.flag_to_value(d, true); }
+			BinOp::Le => { self.inst("c.le.d", &[lhs, rhs]); self.flag_to_value(d, true); }
+			BinOp::Gt => { self.inst("c.lt.d", &[rhs, lhs]); self.flag_to_value(d, true); }
+			BinOp::Ge => { self.inst("c.le.d", &[rhs, lhs]); self.flag_to_value(d, true); }
+			BinOp::Eq => { self.inst("c.eq.d", &[lhs, rhs]); self.flag_to_value(d, true); }
+			BinOp::Ne => { self.inst("c.eq.d", &[lhs, rhs]); self.flag_to_value(d, false); }
+
+			// lower() turns these into jumps.
+			BinOp::And | BinOp::Or => panic!("the IR should never have a '{}' instruction", op),
+		}
+	}
+
+	// puts the address in `addr` into $t0, so it can be loaded from or stored to.
+	fn address(&mut self, addr: &str) {
+		self.inst("cvt.w.d", &[TMP, addr]);
+		self.inst("mfc1", &["$t0", TMP]);
+	}
+
+	// after a compare, puts 1.0 in `d` if the flag is `when`, and 0.0 if not.
+	fn flag_to_value(&mut self, d: &str, when: bool) {
+		let done = self.new_label();
+		let one = self.constant(1.0);
+		self.inst("l.d", &[d, &one]);
+		self.inst(if when { "bc1t" } else { "bc1f" }, &[&done]);
+		self.inst("mov.d", &[d, ZERO]);
+		self.text.push_str(&format!("{}:\n", done));
+	}
+
+	fn min_max(&mut self, d: &str, a: &str, b: &str, min: bool) {
+		// d = a, unless b is smaller (or bigger).
+		let done = self.new_label();
+		self.inst("mov.d", &[TMP, a]);
+
+		if min {
+			self.inst("c.le.d", &[a, b]);
+		} else {
+			self.inst("c.le.d", &[b, a]);
+		}
+
+		self.inst("bc1t", &[&done]);
+		self.inst("mov.d", &[TMP, b]);
+		self.text.push_str(&format!("{}:\n", done));
+		self.inst("mov.d", &[d, TMP]);
+	}
+
+	// gives the register that the operand's value is in, loading it into `scratch` if it's not
+	// in a register already.
+	fn read(&mut self, op: &Operand, scratch: &str) -> String {
+		let addr = match op {
+			Operand::Const(c) => self.constant(*c),
+			Operand::Var(v)   => self.var(v),
+			Operand::Temp(t)  => match self.alloc.location(*t) {
+				Location::Reg(r)   => return r.clone(),
+				Location::Stack(s) => format!("spill_{}", s),
+			},
+		};
+
+		self.inst("l.d", &[scratch, &addr]);
+		scratch.into()
+	}
+
+	// the register to put the result in. if the destination isn't a register, the result goes in
+	// DST, and write() stores it.
+	fn dst(&self, op: &Operand) -> String {
+		match op {
+			Operand::Temp(t) => match self.alloc.location(*t) {
+				Location::Reg(r) => r.clone(),
+				Location::Stack(..) => DST.into(),
+			},
+
+			_ => DST.into(),
+		}
+	}
+
+	fn write(&mut self, op: &Operand, reg: &str) {
+		let addr = match op {
+			Operand::Var(v)   => self.var(v),
+			Operand::Temp(t)  => match self.alloc.location(*t) {
+				Location::Reg(..)  => return,
+				Location::Stack(s) => format!("spill_{}", s),
+			},
+			Operand::Const(..) => panic!("writing to a constant"),
+		};
+
+		self.inst("s.d", &[reg, &addr]);
+	}
+
+	fn constant(&mut self, c: f64) -> String {
+		let i = match self.consts.iter().position(|k| k.to_bits() == c.to_bits()) {
+			Some(i) => i,
+			None    => { self.consts.push(c); self.consts.len() - 1 }
+		};
+
+		format!("const_{}", i)
+	}
+
+	fn var(&mut self, name: &str) -> String {
+		self.vars.insert(name.into());
+		format!("var_{}", name)
+	}
+
+	fn new_label(&mut self) -> String {
+		self.num_labels += 1;
+		format!("_{}", self.num_labels)
+	}
+
+	fn inst(&mut self, mnemonic: &str, operands: &[&str]) {
+		self.line(&format!("{:<10}{}", mnemonic, operands.join(", ")));
+	}
+
+	fn line(&mut self, text: &str) {
+		writeln!(self.text, "\t{}", text.trim_end()).unwrap();
+	}
+
+	// puts the .data section (the variables, spill slots, constants, and jump tables) before the
+	// code.
+	fn finish(mut self) -> String {
+		// print_string (syscall 4) prints the message at $a0, and exit2 (17) exits with $a0.
+		if self.bounds_error {
+			self.text.push_str("bounds_error:\n");
+			self.inst("li", &["$v0", "4"]);
+			self.inst("la", &["$a0", "bounds_message"]);
+			self.line("syscall");
+			self.inst("li", &["$v0", "17"]);
+			self.inst("li", &["$a0", "1"]);
+			self.line("syscall");
+		}
+
+		let mut out = String::from(".data\n");
+		let env = Env::new();
+
+		// the builtin constants (like pi) start with their values; everything else is 0.
+		for var in &self.vars {
+			let val = env.get(var).as_ref().and_then(Value::as_num).unwrap_or(0.0);
+			writeln!(out, "var_{}: .double {}", var, double(val)).unwrap();
+		}
+
+		for slot in 0 .. self.alloc.num_slots {
+			writeln!(out, "spill_{}: .double 0.0", slot).unwrap();
+		}
+
+		for (i, c) in self.consts.iter().enumerate() {
+			writeln!(out, "const_{}: .double {}", i, double(*c)).unwrap();
+		}
+
+		for (i, targets) in self.tables.iter().enumerate() {
+			let targets = targets.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+			writeln!(out, "table_{}: .word {}", i, targets.join(", ")).unwrap();
+		}
+
+		// this goes last, since the doubles have to be at addresses that are multiples of 8.
+		if self.bounds_error {
+			out.push_str("bounds_message: .asciiz \"error: index out of bounds\\n\"\n");
+		}
+
+		out.push_str("\n.text\n");
+		out.push_str(&self.text);
+		out
+	}
+}
+
+// the way the assemblers want to see numbers. {:?} always has a '.', so it can't be mistaken for
+// an int.
+fn double(val: f64) -> String {
+	match val {
+		v if v.is_nan()                  => "NaN".into(),
+		v if v == f64::INFINITY          => "Infinity".into(),
+		v if v == f64::NEG_INFINITY      => "-Infinity".into(),
+		v                                => format!("{:?}", v),
+	}
+}
@@ -0,0 +1,1046 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+// ------------------------------------------------------------------------------------------------
+// ErrorCode
+// ------------------------------------------------------------------------------------------------
+
+/*
+Every kind of error gets a code like E0102, just like rustc's errors (try `rustc --explain E0382`).
+(So do the few warnings, like E0206; a warning's code is in the group of the phase that finds it.)
+The codes are *stable*: once a code means something, it always means that, even if the wording of
+the message changes. That way, assignments can say "your compiler must report E0102 here," and
+tests can check for exact codes instead of matching on message text.
+
+The codes are grouped by compiler phase:
+
+	E00xx: lexical errors
+	E01xx: syntax (parsing) errors
+	E02xx: semantic errors (names, scopes, etc.)
+	E03xx: type errors
+	E04xx: runtime errors
+	E05xx: bytecode and assembly errors (for the vm crate)
+	E06xx: grammar and regex errors (for the grammar and automata crates)
+	E07xx: code generation errors (for the compiler crate)
+
+To add a code, add a constant *and* an entry in the CODES table at the bottom. Never reuse or
+renumber an old code.
+*/
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct ErrorCode(pub u16);
+
+impl Display for ErrorCode {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "E{:04}", self.0)
+	}
+}
+
+impl ErrorCode {
+	// Turns something like "E0102" back into an ErrorCode, if it's a code we know about.
+	pub fn parse(s: &str) -> Option<ErrorCode> {
+		let num = s.strip_prefix('E').or_else(|| s.strip_prefix('e'))?.parse().ok()?;
+		let code = ErrorCode(num);
+		code.info().map(|info| info.code)
+	}
+
+	// A one-line summary of what this code means. Anyone can make an ErrorCode(9999), so this
+	// gives None for a code that isn't in the CODES table.
+	pub fn title(self) -> Option<&'static str> {
+		self.info().map(|info| info.title)
+	}
+
+	fn info(self) -> Option<&'static CodeInfo> {
+		CODES.iter().find(|info| info.code == self)
+	}
+}
+
+// The long explanation of an error code, with an example. This is what a `--explain` flag shows.
+// Like title(), it gives None for a code we don't know about.
+pub fn explain(code: ErrorCode) -> Option<&'static str> {
+	code.info().map(|info| info.explanation)
+}
+
+// Every code we know about, in order.
+pub fn all_codes() -> impl Iterator<Item = ErrorCode> {
+	CODES.iter().map(|info| info.code)
+}
+
+// ------------------------------------------------------------------------------------------------
+// The codes
+// ------------------------------------------------------------------------------------------------
+
+// Lexical errors
+pub const INVALID_CHAR:           ErrorCode = ErrorCode(1);
+pub const INT_OUT_OF_RANGE:       ErrorCode = ErrorCode(2);
+pub const UNTERMINATED_STRING:    ErrorCode = ErrorCode(3);
+pub const INCLUDE_FAILED:         ErrorCode = ErrorCode(4);
+pub const RECURSIVE_INCLUDE:      ErrorCode = ErrorCode(5);
+pub const INVALID_ESCAPE:         ErrorCode = ErrorCode(6);
+
+// Syntax errors
+pub const EXPECTED_EXPRESSION:    ErrorCode = ErrorCode(100);
+pub const EXPECTED_LPAREN:        ErrorCode = ErrorCode(101);
+pub const EXPECTED_RPAREN:        ErrorCode = ErrorCode(102);
+pub const EXPECTED_EOF:           ErrorCode = ErrorCode(103);
+pub const EXPECTED_NAME:          ErrorCode = ErrorCode(104);
+pub const EXPECTED_ASSIGN:        ErrorCode = ErrorCode(105);
+pub const EXPECTED_STMT_END:      ErrorCode = ErrorCode(106);
+pub const UNEXPECTED_TOKEN:       ErrorCode = ErrorCode(107);
+pub const EXPECTED_FILE_NAME:     ErrorCode = ErrorCode(108);
+pub const EXPECTED_LBRACE:        ErrorCode = ErrorCode(109);
+pub const EXPECTED_RBRACE:        ErrorCode = ErrorCode(110);
+pub const UNMATCHED_RBRACE:       ErrorCode = ErrorCode(111);
+pub const EXPECTED_ARROW:         ErrorCode = ErrorCode(112);
+pub const EXPECTED_PATTERN:       ErrorCode = ErrorCode(113);
+pub const EXPECTED_ARM_END:       ErrorCode = ErrorCode(114);
+pub const ELSE_WITHOUT_IF:        ErrorCode = ErrorCode(115);
+pub const EXPECTED_RBRACKET:      ErrorCode = ErrorCode(116);
+pub const TOO_DEEPLY_NESTED:      ErrorCode = ErrorCode(117);
+pub const TOO_MANY_OPERATORS:     ErrorCode = ErrorCode(118);
+
+// Semantic errors
+pub const UNDEFINED_NAME:         ErrorCode = ErrorCode(200);
+pub const DUPLICATE_DEFINITION:   ErrorCode = ErrorCode(201);
+pub const USE_BEFORE_DEFINITION:  ErrorCode = ErrorCode(202);
+pub const BREAK_OUTSIDE_LOOP:     ErrorCode = ErrorCode(203);
+pub const UNREACHABLE_ARM:        ErrorCode = ErrorCode(204);
+pub const NON_EXHAUSTIVE_MATCH:   ErrorCode = ErrorCode(205);
+pub const UNUSED_VARIABLE:        ErrorCode = ErrorCode(206); // a warning
+pub const UNUSED_PARAMETER:       ErrorCode = ErrorCode(207); // a warning
+pub const SHADOWED_NAME:          ErrorCode = ErrorCode(208); // a warning
+
+// Type errors
+pub const TYPE_MISMATCH:          ErrorCode = ErrorCode(300);
+pub const FUNCTION_AS_VALUE:      ErrorCode = ErrorCode(301);
+pub const CANT_INFER_TYPE:        ErrorCode = ErrorCode(302);
+pub const CANT_CHECK_FUNCTION:    ErrorCode = ErrorCode(303);
+
+// Runtime errors
+pub const NOT_A_FUNCTION:         ErrorCode = ErrorCode(400);
+pub const WRONG_ARG_COUNT:        ErrorCode = ErrorCode(401);
+pub const STACK_UNDERFLOW:        ErrorCode = ErrorCode(402);
+pub const STACK_OVERFLOW:         ErrorCode = ErrorCode(403);
+pub const NO_MATCHING_ARM:        ErrorCode = ErrorCode(404);
+pub const WRONG_VALUE_TYPE:       ErrorCode = ErrorCode(405);
+pub const INDEX_OUT_OF_BOUNDS:    ErrorCode = ErrorCode(406);
+pub const BAD_ADDRESS:            ErrorCode = ErrorCode(407);
+pub const NO_VALUE:               ErrorCode = ErrorCode(408);
+
+// Bytecode and assembly errors
+pub const UNKNOWN_MNEMONIC:       ErrorCode = ErrorCode(500);
+pub const BAD_OPERAND:            ErrorCode = ErrorCode(501);
+pub const UNDEFINED_LABEL:        ErrorCode = ErrorCode(502);
+pub const DUPLICATE_LABEL:        ErrorCode = ErrorCode(503);
+pub const INVALID_BYTECODE:       ErrorCode = ErrorCode(504);
+pub const TABLE_FULL:             ErrorCode = ErrorCode(505);
+
+// Grammar and regex errors
+pub const BAD_GRAMMAR:            ErrorCode = ErrorCode(600);
+pub const LEFT_RECURSION:         ErrorCode = ErrorCode(601);
+pub const LR_CONFLICT:            ErrorCode = ErrorCode(602);
+pub const BAD_REGEX:              ErrorCode = ErrorCode(603);
+
+// Code generation errors
+pub const UNSUPPORTED_BY_TARGET:  ErrorCode = ErrorCode(700);
+
+struct CodeInfo {
+	code:        ErrorCode,
+	title:       &'static str,
+	explanation: &'static str,
+}
+
+static CODES: &[CodeInfo] = &[
+	CodeInfo {
+		code: INVALID_CHAR,
+		title: "invalid character",
+		explanation:
+"A character was found that can't start (or continue) any token.
+
+Example:
+
+	(add 1 $)
+
+'$' isn't part of any token in the language. This error also happens when a number runs right
+into a letter, like `123abc`: that isn't a number followed by an identifier; it's an error.",
+	},
+	CodeInfo {
+		code: INT_OUT_OF_RANGE,
+		title: "integer literal out of range",
+		explanation:
+"An integer literal is too big to fit in 64 bits.
+
+Example:
+
+	99999999999999999999
+
+The biggest integer literal allowed is 9223372036854775807 (that's 2^63 - 1).",
+	},
+	CodeInfo {
+		code: UNTERMINATED_STRING,
+		title: "unterminated string literal",
+		explanation:
+"A string literal was started with `\"`, but the line (or the file) ended before the closing `\"`.
+
+Example:
+
+	include \"helpers.calc
+
+String literals can't span more than one line.",
+	},
+	CodeInfo {
+		code: INCLUDE_FAILED,
+		title: "couldn't include a file",
+		explanation:
+"An `include` names a file that couldn't be read. Maybe it doesn't exist, or it's misspelled.
+
+Example:
+
+	include \"hepers.calc\"
+
+The file name is relative to the folder of the file that has the `include` in it, not the folder
+you ran the program from.",
+	},
+	CodeInfo {
+		code: RECURSIVE_INCLUDE,
+		title: "a file includes itself",
+		explanation:
+"A file includes itself, either directly or through other files. Including it would never end.
+
+Example, in a.calc:
+
+	include \"b.calc\"
+
+and in b.calc:
+
+	include \"a.calc\"
+
+Move the code that both files need into a third file, and include that from both.",
+	},
+	CodeInfo {
+		code: INVALID_ESCAPE,
+		title: "invalid escape sequence",
+		explanation:
+"A string literal has a `\\` followed by a character that doesn't make an escape sequence.
+
+Example:
+
+	path = \"C:\\games\"
+
+The only escape sequences are `\\n` (a new line), `\\t` (a tab), `\\\"` (a quote), and `\\\\` (a
+backslash). To put a backslash in a string, write it twice: `\"C:\\\\games\"`.",
+	},
+	CodeInfo {
+		code: EXPECTED_EXPRESSION,
+		title: "expected an expression",
+		explanation:
+"The parser was expecting an expression (like a number, a name, or something in parentheses),
+but found something else.
+
+Example:
+
+	x + * y
+
+After the `+`, there has to be an expression for the right-hand side, but there's a `*`.",
+	},
+	CodeInfo {
+		code: EXPECTED_LPAREN,
+		title: "expected '('",
+		explanation:
+"The parser was expecting a left parenthesis.
+
+Example (in the Lisp dialect):
+
+	add 1 2)
+
+A list has to start with `(`.",
+	},
+	CodeInfo {
+		code: EXPECTED_RPAREN,
+		title: "expected ')'",
+		explanation:
+"A parenthesized expression or argument list was never closed.
+
+Example:
+
+	(x + 1
+
+Every `(` needs a matching `)`. Check for missing parentheses, or for something in between that
+doesn't belong there.",
+	},
+	CodeInfo {
+		code: EXPECTED_EOF,
+		title: "expected end of input",
+		explanation:
+"The parser finished parsing a complete program, but there was more stuff after it.
+
+Example:
+
+	x y
+
+`x` is a complete expression, so the parser stops there, and doesn't know what to do with `y`.
+Maybe there's an operator missing, like `x + y`?",
+	},
+	CodeInfo {
+		code: EXPECTED_NAME,
+		title: "expected a name",
+		explanation:
+"The parser was expecting a name (an identifier), but found something else.
+
+Example:
+
+	let 5 = x
+
+`let` has to be followed by the name of the variable being declared.",
+	},
+	CodeInfo {
+		code: EXPECTED_ASSIGN,
+		title: "expected '='",
+		explanation:
+"The parser was expecting an `=`.
+
+Example:
+
+	let x 5
+
+A `let` statement needs an `=` between the name and the value: `let x = 5`.",
+	},
+	CodeInfo {
+		code: EXPECTED_STMT_END,
+		title: "expected end of statement",
+		explanation:
+"A statement was complete, but it wasn't followed by the end of a line or a `;`.
+
+Example:
+
+	x = 1 y = 2
+
+Each statement goes on its own line, or they're separated by `;`, like `x = 1; y = 2`. This also
+happens when you try to assign to something that isn't a variable, like `1 = x`.",
+	},
+	CodeInfo {
+		code: UNEXPECTED_TOKEN,
+		title: "unexpected token",
+		explanation:
+"A parser made from a grammar (by the grammar crate) found a token that can't go there.
+
+Example, with the grammar `Sum -> num '+' Sum | num`:
+
+	1 + + 2
+
+After a `+`, the grammar says there has to be a `num`. The error lists every token that *could*
+have gone there, so you can compare it against the grammar.",
+	},
+	CodeInfo {
+		code: EXPECTED_FILE_NAME,
+		title: "expected a file name",
+		explanation:
+"An `include` has to be followed by the name of the file to include, in double quotes.
+
+Example:
+
+	include helpers.calc
+
+That should be `include \"helpers.calc\"`.",
+	},
+	CodeInfo {
+		code: EXPECTED_LBRACE,
+		title: "expected '{'",
+		explanation:
+"The body of a loop or an `if` has to be a block: statements inside `{` and `}`.
+
+Example:
+
+	while x < 10
+		x = x + 1
+
+That should be:
+
+	while x < 10 {
+		x = x + 1
+	}
+
+The `{` has to be on the same line as the `while`, since a newline would end the statement. The
+same goes for `if`, and after an `else` there has to be a `{` or another `if`.",
+	},
+	CodeInfo {
+		code: EXPECTED_RBRACE,
+		title: "expected '}'",
+		explanation:
+"A block was started with `{`, but the file ended before the `}` that closes it.
+
+Example:
+
+	while x < 10 {
+		x = x + 1
+
+Every `{` needs a matching `}`. If the block is long, it can be hard to see which one is missing;
+a code formatter (like `toylang fmt`) indents the code inside each block, which makes it easier.",
+	},
+	CodeInfo {
+		code: UNMATCHED_RBRACE,
+		title: "unmatched '}'",
+		explanation:
+"There's a `}` that doesn't close any block.
+
+Example:
+
+	while x < 10 {
+		x = x + 1
+	}
+	}
+
+Either there's one `}` too many, or the `{` that goes with it is missing.",
+	},
+	CodeInfo {
+		code: EXPECTED_ARROW,
+		title: "expected '=>'",
+		explanation:
+"Each arm of a `match` is a pattern, then `=>`, then the value to give if the pattern matches.
+
+Example:
+
+	match x {
+		1 -> 10
+		_ => 0
+	}
+
+The first arm should be `1 => 10`.",
+	},
+	CodeInfo {
+		code: EXPECTED_PATTERN,
+		title: "expected a pattern",
+		explanation:
+"The start of each arm of a `match` has to be a pattern: an integer (like `3` or `-1`), or `_`,
+which matches anything.
+
+Example:
+
+	match x {
+		y => 10
+		_ => 0
+	}
+
+A pattern can't be a variable, since that would be ambiguous: does `y` mean \"any value, now
+called y,\" or \"the value of y\"? To compare against a variable, use `==`.",
+	},
+	CodeInfo {
+		code: EXPECTED_ARM_END,
+		title: "expected ',' after a match arm",
+		explanation:
+"The arms of a `match` are separated by commas or newlines.
+
+Example:
+
+	match x { 1 => 10 _ => 0 }
+
+That should be `match x { 1 => 10, _ => 0 }`. Inside parentheses, newlines don't count (like
+anywhere else), so the arms of a `match` in parentheses have to be separated by commas.",
+	},
+	CodeInfo {
+		code: ELSE_WITHOUT_IF,
+		title: "'else' without an 'if'",
+		explanation:
+"A statement started with `else`, so there's no `if` for it to go with.
+
+Example:
+
+	if x < 0 {
+		y = -x
+	}
+	else {
+		y = x
+	}
+
+The newline after the `}` ended the `if` statement, so the `else` starts a new one. The `else` has
+to be on the same line as the `}`, like `} else {`.",
+	},
+	CodeInfo {
+		code: EXPECTED_RBRACKET,
+		title: "expected ']'",
+		explanation:
+"An array or an index was never closed.
+
+Example:
+
+	let a = [1, 2, 3
+	a[i + 1
+
+Every `[` needs a matching `]`. In an array, the values are separated by commas, so a missing
+comma can cause this too: in `[1 2]`, the parser expects a `]` after the 1.",
+	},
+	CodeInfo {
+		code: TOO_DEEPLY_NESTED,
+		title: "code is nested too deeply",
+		explanation:
+"Some code has too many levels of parentheses, brackets, blocks, `-` and `!`, or calls and indexing
+inside each other.
+
+Example:
+
+	((((((((((((((((((((((((((((((((((((((((((((((( ... 1 ... )))))))))))))))))))))))))))))))))
+
+The parser is recursive: each level of nesting is a Rust function call that uses some of the
+stack. Without a limit, code nested deeply enough (say, by a program that writes programs) would
+use all of it, and crash the whole process instead of giving an error. No one writes code nested
+this deeply by hand, so if you see this, split the expression up with some variables.
+
+A chain of operators like `1 + 2 + 3 + ...` doesn't count toward this; it has its own, much bigger
+limit (see E0118).",
+	},
+	CodeInfo {
+		code: TOO_MANY_OPERATORS,
+		title: "expression has too many operators",
+		explanation:
+"An expression has too many binary operators (like `+` or `&&`) in a row.
+
+Example:
+
+	1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + ... (1,000 more)
+
+The operators are left-associative, so that's `((1 + 1) + 1) + ...`: a tree that's as deep as the
+chain is long. The interpreter and compiler go through a chain like that with loops, but a few
+things still go through the tree by calling themselves, which uses some of the stack for each
+level. So the limit is there to keep a huge chain (say, from a program that writes programs) from
+crashing the whole process. It's well past anything written by hand; if you see this, split the
+expression up with some variables, like `a = 1 + 1 + ...` and then `a + 1 + ...`.",
+	},
+	CodeInfo {
+		code: UNDEFINED_NAME,
+		title: "undefined name",
+		explanation:
+"A name was used, but nothing with that name exists.
+
+Example:
+
+	radius * 2
+
+If there's no variable called `radius`, there's nothing to multiply. Check the spelling, and make
+sure the variable was given a value before it's used.",
+	},
+	CodeInfo {
+		code: DUPLICATE_DEFINITION,
+		title: "duplicate definition",
+		explanation:
+"Two things with the same name were defined in the same scope.
+
+Example:
+
+	let total = 0
+	let total = 10
+
+The second `let` tries to make a *new* variable called `total`, but there already is one. To
+change the existing variable, leave off the `let`: `total = 10`.",
+	},
+	CodeInfo {
+		code: USE_BEFORE_DEFINITION,
+		title: "used before definition",
+		explanation:
+"A variable was used before the statement that gives it a value.
+
+Example:
+
+	area = width * 2
+	let width = 5
+
+Statements run from top to bottom, so when the first line runs, `width` doesn't exist yet. Move
+the definition above the use.",
+	},
+	CodeInfo {
+		code: BREAK_OUTSIDE_LOOP,
+		title: "break or continue outside of a loop",
+		explanation:
+"`break` and `continue` can only be used inside the body of a loop.
+
+Example:
+
+	let x = 5
+	break
+
+`break` jumps out of the loop it's in, and `continue` jumps to the next time around it. Outside
+of a loop, there's nowhere for them to go.",
+	},
+	CodeInfo {
+		code: UNREACHABLE_ARM,
+		title: "unreachable match arm",
+		explanation:
+"An arm of a `match` can never be chosen, because an earlier arm matches everything it does.
+
+Examples:
+
+	match x {
+		1 => 10
+		1 => 20
+		_ => 0
+	}
+
+	match x {
+		_ => 0
+		1 => 10
+	}
+
+The arms are tried from top to bottom, and the first one that matches wins. In the first example,
+the second `1` arm never gets a chance; in the second, `_` matches everything, so nothing after it
+is ever tried.",
+	},
+	CodeInfo {
+		code: NON_EXHAUSTIVE_MATCH,
+		title: "match doesn't cover every value",
+		explanation:
+"A `match` has to have an arm for every possible value, so there's always something to give.
+
+Example:
+
+	match x {
+		1 => 10
+		2 => 20
+	}
+
+If x is 3, none of the arms match. There are too many ints to list them all, so a `match` on an
+int needs a `_` arm at the end, for everything else.",
+	},
+	CodeInfo {
+		code: UNUSED_VARIABLE,
+		title: "unused variable",
+		explanation:
+"(This is a warning, not an error, so the program still runs.)
+
+A variable was given a value, but the value is never used.
+
+Example:
+
+	let width = 5
+	let height = 10
+	area = width * width
+
+`height` is never used. Maybe it's left over from code that was deleted, or maybe `width * width`
+was supposed to be `width * height`. If it's on purpose, start the name with an underscore, like
+`_height`, and there won't be a warning. To turn this warning off everywhere, use
+`--allow=unused-variable`.",
+	},
+	CodeInfo {
+		code: UNUSED_PARAMETER,
+		title: "unused parameter",
+		explanation:
+"(This is a warning, not an error, so the program still runs.)
+
+A function has a parameter that its body never uses.
+
+Example:
+
+	let area = fn(width, height) { width * width }
+
+Whatever is passed for `height` is thrown away. If the function has to take it anyway (say, it's
+passed to something that always gives two arguments), start the name with an underscore, like
+`_height`, and there won't be a warning. To turn this warning off everywhere, use
+`--allow=unused-parameter`.",
+	},
+	CodeInfo {
+		code: SHADOWED_NAME,
+		title: "a name shadows another one",
+		explanation:
+"(This is a warning, not an error, so the program still runs.)
+
+A `let` or a parameter inside a function has the same name as something outside of it. Inside the
+function, the name means the new variable, so the outer one can't be used there anymore.
+
+Example:
+
+	let total = 0
+	let add = fn(x) {
+		let total = total + x
+		total
+	}
+
+This makes a new `total` every time `add` is called, and the outer `total` never changes. If
+that's what you meant to do, `=` instead of `let` changes the outer one. Builtins can be shadowed
+too: `let e = 5` means the constant `e` can't be used after it. To turn this warning off
+everywhere, use `--allow=shadowing`.",
+	},
+	CodeInfo {
+		code: TYPE_MISMATCH,
+		title: "mismatched types",
+		explanation:
+"A value of one type was used where a different type was needed.
+
+Examples:
+
+	1 + true
+	(a < b) * 2
+	let x = 5
+	x = false
+
+Arithmetic needs numbers, `&&`, `||`, and `!` need bools, and once a variable has been given a
+value of some type, it can only be given values of that type. (An int can be given to a float
+variable, though; it's converted.)",
+	},
+	CodeInfo {
+		code: FUNCTION_AS_VALUE,
+		title: "function used as a value",
+		explanation:
+"A function's name was used without calling it.
+
+Example:
+
+	x = sqrt + 1
+
+Builtin functions aren't values in this language, so the only thing you can do with one is call
+it, like `sqrt(4) + 1`. (Functions made with `fn` are values, but see E0303.)",
+	},
+	CodeInfo {
+		code: CANT_INFER_TYPE,
+		title: "can't tell the type",
+		explanation:
+"The type checker couldn't figure out the type of an expression.
+
+Example:
+
+	let a = []
+
+Every array has a type, like `[int]` (an array of ints), and the type checker figures it out
+from the values in it. An empty array has no values, so there's nothing to go on. Arrays can't be
+changed after they're made, so an empty one isn't very useful anyway.",
+	},
+	CodeInfo {
+		code: CANT_CHECK_FUNCTION,
+		title: "can't type check functions",
+		explanation:
+"The program makes a function with `fn`, and the type checker doesn't know how to check those yet.
+
+Example:
+
+	let double = fn(x) { x * 2 }
+
+To check this, the type checker would need a type for every function, like `fn(int) -> int`, and
+would have to figure out what type `x` is from how `double` is called. That's called *type
+inference*, and it's a big job. Until then, the compiler can't compile functions, but `toylang
+eval` can run the program, since the interpreter doesn't check types before running.",
+	},
+	CodeInfo {
+		code: NOT_A_FUNCTION,
+		title: "called something that isn't a function",
+		explanation:
+"Something was called like a function, but it isn't one.
+
+Example:
+
+	pi(10)
+
+`pi` is a number, and you can't call a number. (Maybe a `*` is missing, like `pi * (10)`?)",
+	},
+	CodeInfo {
+		code: WRONG_ARG_COUNT,
+		title: "wrong number of arguments",
+		explanation:
+"A function was called with too many or too few arguments.
+
+Example:
+
+	sqrt(2, 3)
+
+`sqrt` takes exactly one argument, but it was given two.",
+	},
+	CodeInfo {
+		code: STACK_UNDERFLOW,
+		title: "stack underflow",
+		explanation:
+"A virtual machine instruction tried to pop more values off the stack than there were.
+
+Example (in the vm crate's assembly language):
+
+	push 1
+	add
+
+`add` pops two values, but there's only one. A correct compiler never outputs code like this, so
+this usually means there's a bug in the compiler, or the bytecode was edited by hand.",
+	},
+	CodeInfo {
+		code: STACK_OVERFLOW,
+		title: "stack overflow",
+		explanation:
+"The virtual machine's stack got too big.
+
+Example (in the vm crate's assembly language):
+
+	top:
+	    push 1
+	    jmp top
+
+This pushes forever without popping anything. There's a limit on how big the stack can get, so
+that a mistake like this is an error instead of using up all your memory.
+
+The interpreter has a limit too, on how many function calls can be going on at once:
+
+	let f = fn(n) { f(n + 1) }
+	f(0)
+
+Every call is still going when it makes the next one, so they pile up forever.",
+	},
+	CodeInfo {
+		code: NO_MATCHING_ARM,
+		title: "no match arm matched",
+		explanation:
+"While running, none of the arms of a `match` matched the value.
+
+Example:
+
+	match 2.5 {
+		2 => 20
+		3 => 30
+	}
+
+The type checker (in the semantic crate) rejects this before it runs, since a `match` needs a `_`
+arm. But the interpreter doesn't run the type checker, so it can still happen there.",
+	},
+	CodeInfo {
+		code: WRONG_VALUE_TYPE,
+		title: "wrong type of value",
+		explanation:
+"While running, an operator or function was given a kind of value it can't work on, like a
+string where a number was needed.
+
+Example:
+
+	let name = \"world\"
+	greeting = \"hello \" + name + 1
+
+`+` can add two numbers or join two strings, but not one of each. Math functions like `sqrt` and
+operators like `-` and `*` only work on numbers. Conditions (of an `if` or a `while`), `&&`, `||`,
+and `!` only work on bools, so `if count { ... }` is an error when count is a number; write
+`if count != 0 { ... }` instead.
+
+The type checker (in the semantic crate) finds these before the program runs, but the interpreter
+doesn't run the type checker, so it finds them while running.",
+	},
+	CodeInfo {
+		code: INDEX_OUT_OF_BOUNDS,
+		title: "index out of bounds",
+		explanation:
+"While running, an array was indexed with a number that isn't the index of one of its values.
+
+Example:
+
+	let a = [10, 20, 30]
+	a[3]
+
+The indexes start at 0, so the last one is `len(a) - 1`: here, that's 2. Negative indexes aren't
+allowed either. The type checker can't catch this, since it doesn't know what the index will be
+until the program runs, so it's checked every time an array is indexed. (C doesn't check, and
+just reads whatever is in memory past the end of the array. That's the cause of a lot of bugs,
+and a lot of security holes.)",
+	},
+	CodeInfo {
+		code: BAD_ADDRESS,
+		title: "bad memory address",
+		explanation:
+"While running bytecode, a `loadm` or `storem` was given an address that isn't in memory.
+
+Example:
+
+	push 800
+	loadm
+
+Only memory that `alloc` gave out can be used, and every value takes up 8 bytes, so the address
+has to be a multiple of 8. Compiled code never does this; it's only possible in handwritten
+assembly.",
+	},
+	CodeInfo {
+		code: NO_VALUE,
+		title: "function didn't give back a value",
+		explanation:
+"A function was called for its value, but its last statement didn't have one.
+
+Example:
+
+	let count_down = fn(n) {
+	    while n > 0 { n = n - 1 }
+	}
+	x = count_down(5)
+
+A function gives back the value of its last statement, like a block. Loops don't have a value,
+and neither does an `if` without an `else` (there'd be nothing to give when the condition is
+false), so put the value you want on its own line at the end, like `n`.",
+	},
+	CodeInfo {
+		code: UNKNOWN_MNEMONIC,
+		title: "unknown instruction",
+		explanation:
+"A line of assembly code starts with a word that isn't the name (*mnemonic*) of any instruction.
+
+Example:
+
+	push 1
+	psuh 2
+
+Check the spelling. Labels need a `:` after them, or they look like instructions.",
+	},
+	CodeInfo {
+		code: BAD_OPERAND,
+		title: "bad operand",
+		explanation:
+"An instruction in assembly code has the wrong number or the wrong kind of operands.
+
+Examples:
+
+	push x
+	call sqrt
+	add 1
+
+`push` needs a number, `call` needs a function name *and* how many arguments it takes, and `add`
+doesn't take any operands; it gets its inputs from the stack.",
+	},
+	CodeInfo {
+		code: UNDEFINED_LABEL,
+		title: "undefined label",
+		explanation:
+"A jump in assembly code goes to a label that isn't defined anywhere.
+
+Example:
+
+	jmp end
+
+If there's no line that says `end:`, there's nowhere to jump to.",
+	},
+	CodeInfo {
+		code: DUPLICATE_LABEL,
+		title: "duplicate label",
+		explanation:
+"The same label was defined twice in assembly code.
+
+Example:
+
+	loop:
+	    push 1
+	loop:
+	    jmp loop
+
+Which `loop` should the jump go to? Every label has to have a different name.",
+	},
+	CodeInfo {
+		code: INVALID_BYTECODE,
+		title: "invalid bytecode",
+		explanation:
+"A bytecode file or program is broken: it has the wrong header, ends in the middle of something,
+has a byte that isn't an opcode, or refers to a constant or name that doesn't exist.
+
+Compilers never output bytecode like this, so it was probably edited by hand (which is fine! but
+you have to be careful), or it isn't a bytecode file at all. `vm dis` shows what's in a file.",
+	},
+	CodeInfo {
+		code: TABLE_FULL,
+		title: "too many constants or names",
+		explanation:
+"A program needs more constants, or more names, than a bytecode module can hold.
+
+An instruction like `push` or `load` refers to its constant or name by its index in the module's
+table, and the index is only 2 bytes. So a module can have at most 65,536 different constants, and
+65,536 different names. (Names include the compiler's temporary variables, like `%t12`, so a very
+long program can run out of those first.)
+
+Programs written by hand never get close to this. If a program that writes programs made this one,
+have it put the numbers in an array, or split the program up into smaller ones.",
+	},
+	CodeInfo {
+		code: BAD_GRAMMAR,
+		title: "malformed grammar",
+		explanation:
+"A grammar description isn't written correctly, or a grammar uses a nonterminal that doesn't
+have any rules.
+
+Examples:
+
+	Exp Term '+' Exp
+	Exp -> '(' Exp ')
+
+The first rule is missing its `->`, and the second has a quote that's never closed. Each rule looks
+like `Name -> symbols | more symbols`, one per line.",
+	},
+	CodeInfo {
+		code: LEFT_RECURSION,
+		title: "left recursion",
+		explanation:
+"A recursive-descent parser was made from a *left-recursive* grammar, where a nonterminal can
+start with itself.
+
+Example:
+
+	Exp -> Exp '+' num | num
+
+To parse an Exp, the parser has to parse an Exp first, which means it has to parse an Exp first,
+which... never ends. Rewrite the grammar so the recursion is on the right, like:
+
+	Exp     -> num ExpTail
+	ExpTail -> '+' num ExpTail | ε
+
+(Bottom-up parsers, like LR parsers, don't have this problem.)",
+	},
+	CodeInfo {
+		code: LR_CONFLICT,
+		title: "LR conflict",
+		explanation:
+"An LR parser can't be made from a grammar, because in some state, on some token, the parser
+wouldn't know what to do: *shift* the token or *reduce* by a rule (a shift/reduce conflict), or
+which of two rules to reduce by (a reduce/reduce conflict).
+
+Example:
+
+	Exp -> Exp '+' Exp | num
+
+After seeing `1 + 2` with another `+` coming next, the parser could reduce `1 + 2` to an Exp
+(making `+` left-associative), or shift the `+` (making it right-associative). The grammar doesn't
+say which, because it's *ambiguous*. Rewrite it so there's only one way to parse things:
+
+	Exp -> Exp '+' num | num
+
+Some conflicts come from the parser not looking ahead enough, rather than ambiguity. A more
+powerful kind of LR parser (like LALR(1) or LR(1)) might be able to handle those grammars.",
+	},
+	CodeInfo {
+		code: BAD_REGEX,
+		title: "malformed regex",
+		explanation:
+"A regular expression isn't written correctly.
+
+Examples:
+
+	(a|b*
+	*abc
+	[z-a]
+
+The first has a '(' that's never closed. In the second, '*' means \"zero or more of the thing
+before me,\" but there's nothing before it; to match an actual '*' character, write '\\*'. In
+the third, ranges have to go from the lower character to the higher one, so it should be [a-z].",
+	},
+	CodeInfo {
+		code: UNSUPPORTED_BY_TARGET,
+		title: "not supported by this target",
+		explanation:
+"The program is correct, but the code generator can't turn some part of it into code for the
+machine it's compiling for.
+
+Example (compiling for MIPS):
+
+	sin(pi / 2)
+
+MIPS has instructions for some math, like square roots, but not for sine or cosine. A real
+compiler would call a math library for these, but the MIPS code this makes doesn't have one. Try
+compiling for a different target (like the VM, which can call any builtin function), or write the
+program without that feature.",
+	},
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unknown_codes_have_no_info() {
+		for code in all_codes() {
+			assert!(code.title().is_some() && explain(code).is_some(), "{}", code);
+			assert_eq!(ErrorCode::parse(&code.to_string()), Some(code));
+		}
+
+		assert_eq!(ErrorCode(9999).title(), None);
+		assert_eq!(explain(ErrorCode(9999)), None);
+		assert_eq!(ErrorCode::parse("E9999"), None);
+	}
+}
@@ -0,0 +1,84 @@
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+// ------------------------------------------------------------------------------------------------
+// ColorChoice
+// ------------------------------------------------------------------------------------------------
+
+/*
+Colors are great in a terminal, but if the output is going to a file or another program (like
+`toylang file.txt 2> errors.txt`, or an autograder), the escape codes show up as garbage. So by
+default (Auto) we only use colors if stderr is actually a terminal. (It's stderr that matters,
+since that's where all the diagnostics are printed: `toylang file.txt > out.txt` should still show
+colored errors on the screen.) Like rustc and cargo, the user can override that with
+--color=always or --color=never. We also respect the NO_COLOR environment variable
+(https://no-color.org).
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorChoice {
+	Always,
+	Never,
+	#[default]
+	Auto,
+}
+
+impl ColorChoice {
+	pub fn should_color(self) -> bool {
+		match self {
+			ColorChoice::Always => true,
+			ColorChoice::Never  => false,
+			ColorChoice::Auto   =>
+				std::env::var_os("NO_COLOR").is_none() && std::io::stderr().is_terminal(),
+		}
+	}
+
+	// Looks through the command-line arguments for a `--color=<when>` flag. If there isn't one,
+	// gives Auto, and if there's more than one, the last one wins. A typo like `--color=nevr` is an
+	// error, since quietly ignoring it would give colors to someone who asked not to get them.
+	// Use it like ColorChoice::from_args(std::env::args()).
+	pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+		let mut ret = ColorChoice::default();
+
+		for arg in args {
+			if let Some(when) = arg.strip_prefix("--color=") {
+				ret = when.parse()?;
+			}
+		}
+
+		Ok(ret)
+	}
+}
+
+// FromStr is what makes "always".parse::<ColorChoice>() work.
+impl FromStr for ColorChoice {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"always" => Ok(ColorChoice::Always),
+			"never"  => Ok(ColorChoice::Never),
+			"auto"   => Ok(ColorChoice::Auto),
+			_        => Err(format!("invalid color choice '{}' (use always, never, or auto)", s)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn from_args(args: &[&str]) -> Result<ColorChoice, String> {
+		ColorChoice::from_args(args.iter().map(|&arg| arg.to_string()))
+	}
+
+	#[test]
+	fn invalid_choices_are_errors() {
+		assert_eq!(from_args(&["prog", "file"]), Ok(ColorChoice::Auto));
+		assert_eq!(from_args(&["prog", "--color=never"]), Ok(ColorChoice::Never));
+		assert_eq!(from_args(&["prog", "--color=never", "--color=always"]),
+			Ok(ColorChoice::Always));
+		assert!(from_args(&["prog", "--color=nevr"]).unwrap_err().contains("'nevr'"));
+		assert!(from_args(&["prog", "--color="]).is_err());
+	}
+}
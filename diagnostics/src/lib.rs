@@ -0,0 +1,152 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+// This crate doesn't lex or parse anything itself. It's the "error message" part of a compiler,
+// shared by all the language examples, so they can all report errors the same (nice) way.
+
+mod codes;
+mod color;
+mod render;
+mod source;
+mod source_manager;
+
+pub use crate::codes::*;
+pub use crate::color::*;
+pub use crate::render::*;
+pub use crate::source::*;
+pub use crate::source_manager::*;
+
+// ------------------------------------------------------------------------------------------------
+// Span
+// ------------------------------------------------------------------------------------------------
+
+// A range of characters in the source code. Like the spans on lexing_toy's tokens, these are
+// *codepoint* indexes, not byte indexes. `end` is exclusive, so Span { start: 3, end: 5 } covers
+// the characters at indexes 3 and 4.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct Span {
+	pub start: usize,
+	pub end:   usize,
+}
+
+impl Span {
+	pub fn new(start: usize, end: usize) -> Self {
+		Span { start, end }
+	}
+
+	// A span covering just the one character at `loc`.
+	pub fn point(loc: usize) -> Self {
+		Span { start: loc, end: loc + 1 }
+	}
+
+	// A span that covers both this span and `other`, and everything in between.
+	pub fn to(self, other: Span) -> Self {
+		Span { start: self.start.min(other.start), end: self.end.max(other.end) }
+	}
+
+	pub fn len(&self) -> usize {
+		self.end.saturating_sub(self.start)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl Display for Span {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}..{}", self.start, self.end)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Severity
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+	Note,
+	Warning,
+	Error,
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Severity::Note    => write!(f, "note"),
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error   => write!(f, "error"),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Diagnostic
+// ------------------------------------------------------------------------------------------------
+
+/*
+"Diagnostic" is the fancy compiler word for "error or warning message." Every error in every phase
+of the compiler (lexing, parsing, semantic analysis...) gets turned into one of these, so that the
+code that *shows* errors to the user only has to be written once.
+
+The span is optional, because some errors (like "the file is empty") aren't *at* any particular
+place in the source. The code is optional too, but every error in these examples has one (see
+codes.rs).
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub code:     Option<ErrorCode>,
+	pub message:  String,
+	pub span:     Option<Span>,
+	pub notes:    Vec<String>,
+}
+
+impl Diagnostic {
+	pub fn new(severity: Severity, message: impl Into<String>, span: Option<Span>) -> Self {
+		Diagnostic { severity, code: None, message: message.into(), span, notes: vec![] }
+	}
+
+	pub fn error(message: impl Into<String>, span: Span) -> Self {
+		Self::new(Severity::Error, message, Some(span))
+	}
+
+	pub fn warning(message: impl Into<String>, span: Span) -> Self {
+		Self::new(Severity::Warning, message, Some(span))
+	}
+
+	// Adds a note to the end. Returns self so you can chain these, like
+	// Diagnostic::error(...).with_note("...").with_note("...")
+	pub fn with_note(mut self, note: impl Into<String>) -> Self {
+		self.notes.push(note.into());
+		self
+	}
+
+	pub fn with_code(mut self, code: ErrorCode) -> Self {
+		self.code = Some(code);
+		self
+	}
+
+	pub fn with_span(mut self, span: Span) -> Self {
+		self.span = Some(span);
+		self
+	}
+
+	pub fn is_error(&self) -> bool {
+		self.severity == Severity::Error
+	}
+}
+
+// Display shows the message without any source code; use render() to get the fancy version.
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}", self.severity)?;
+
+		if let Some(code) = self.code {
+			write!(f, "[{}]", code)?;
+		}
+
+		write!(f, ": {}", self.message)
+	}
+}
+
+impl std::error::Error for Diagnostic {}
@@ -0,0 +1,25 @@
+use diagnostics::*;
+
+// `cargo run -- E0102` explains that error code, like `rustc --explain E0102`.
+// `cargo run` with no arguments lists all the codes.
+fn main() {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+
+	if args.is_empty() {
+		for code in all_codes() {
+			println!("{}: {}", code, code.title().unwrap_or_default());
+		}
+
+		return;
+	}
+
+	for arg in &args {
+		let code = ErrorCode::parse(arg);
+
+		match (code, code.and_then(ErrorCode::title), code.and_then(explain)) {
+			(Some(code), Some(title), Some(explanation)) =>
+				println!("{}: {}\n\n{}\n", code, title, explanation),
+			_ => println!("'{}' isn't an error code I know about.\n", arg),
+		}
+	}
+}
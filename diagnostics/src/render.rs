@@ -0,0 +1,155 @@
+use std::fmt::Write;
+
+use crate::{ ColorChoice, Diagnostic, Severity, SourceFile };
+
+// ------------------------------------------------------------------------------------------------
+// Rendering diagnostics
+// ------------------------------------------------------------------------------------------------
+
+/*
+This turns a Diagnostic into the kind of message you've seen from rustc, like:
+
+	error[E0001]: invalid character '$'
+	 --> <input>:1:6
+	  |
+	1 | (foo $ bar)
+	  |      ^
+	  = note: identifiers can only contain letters, digits, and underscores
+
+The tricky part is lining up the ^^^ under the right characters. Tabs would mess that up (a tab
+is one character, but shows up as several columns), so we replace them with spaces.
+*/
+
+const TAB_WIDTH: usize = 4;
+
+impl Diagnostic {
+	// Renders without any colors.
+	pub fn render(&self, source: &SourceFile) -> String {
+		self.render_with(source, false)
+	}
+
+	// Renders with colors, if `color` says so (see color.rs).
+	pub fn render_colored(&self, source: &SourceFile, color: ColorChoice) -> String {
+		self.render_with(source, color.should_color())
+	}
+
+	fn render_with(&self, source: &SourceFile, color: bool) -> String {
+		// this closure wraps some text in a color, or doesn't, depending on `color`.
+		let paint = |text: &str, style: Style| -> String {
+			if color { style.paint(text) } else { text.to_string() }
+		};
+
+		let sev_style = Style::for_severity(self.severity);
+
+		// the "error[E0001]" part.
+		let header = match self.code {
+			Some(code) => format!("{}[{}]", self.severity, code),
+			None       => self.severity.to_string(),
+		};
+
+		// writing to a String can't fail, so all the unwrap()s below are fine.
+		let mut out = String::new();
+		writeln!(out, "{}{}", paint(&header, sev_style),
+			paint(&format!(": {}", self.message), Style::Bold)).unwrap();
+
+		// the width of the line number "gutter" on the left depends on how big the line number is.
+		let gutter = match self.span {
+			Some(span) => source.line_col(span.start).0.to_string().len(),
+			None       => 0,
+		};
+
+		let pad = " ".repeat(gutter);
+
+		if let Some(span) = self.span {
+			let (line, col) = source.line_col(span.start);
+			let text = source.line_text(line);
+
+			writeln!(out, "{}{} {}:{}:{}", pad, paint("-->", Style::Gutter),
+				source.name(), line, col).unwrap();
+			writeln!(out, "{}", paint(&format!("{} |", pad), Style::Gutter)).unwrap();
+			writeln!(out, "{} {}", paint(&format!("{} |", line), Style::Gutter),
+				expand_tabs(&text)).unwrap();
+
+			// if the span goes past the end of the line, only underline to the end of the line.
+			// and always underline at least one character, even for empty spans (like "expected
+			// something here at the end of the file").
+			let line_len = text.chars().count();
+			let start    = (col - 1).min(line_len);
+			let end      = (start + span.len()).min(line_len).max(start + 1);
+
+			let before: String = text.chars().take(start).collect();
+			let marked: String = text.chars().skip(start).take(end - start).collect();
+
+			writeln!(out, "{} {}{}", paint(&format!("{} |", pad), Style::Gutter),
+				" ".repeat(width(&before)), paint(&"^".repeat(width(&marked).max(1)), sev_style))
+				.unwrap();
+		}
+
+		for note in &self.notes {
+			writeln!(out, "{} {} {}", pad, paint("= note:", Style::Note), note).unwrap();
+		}
+
+		out
+	}
+}
+
+// Renders all the diagnostics, one after another, with a blank line between each.
+pub fn render_all(diags: &[Diagnostic], source: &SourceFile) -> String {
+	render_all_colored(diags, source, ColorChoice::Never)
+}
+
+pub fn render_all_colored(diags: &[Diagnostic], source: &SourceFile, color: ColorChoice)
+-> String {
+	diags.iter().map(|d| d.render_colored(source, color)).collect::<Vec<_>>().join("\n")
+}
+
+fn expand_tabs(s: &str) -> String {
+	s.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+// how many columns wide the text is on screen, after tabs are expanded.
+fn width(s: &str) -> usize {
+	s.chars().map(|c| if c == '\t' { TAB_WIDTH } else { 1 }).sum()
+}
+
+// ------------------------------------------------------------------------------------------------
+// ANSI colors
+// ------------------------------------------------------------------------------------------------
+
+/*
+Terminals change colors when they see special "escape sequences" in the text. They start with the
+ESC character (\x1b), then '[', then some numbers, then 'm'. For example, "\x1b[31m" means "make
+the text red from here on," and "\x1b[0m" means "go back to normal." The lexing_toy example uses
+the colored crate to do this, but it's simple enough to do ourselves.
+*/
+
+#[derive(Debug, Clone, Copy)]
+enum Style {
+	Error,   // bold red
+	Warning, // bold yellow
+	Note,    // bold cyan
+	Gutter,  // bold blue, like rustc's line numbers and arrows
+	Bold,
+}
+
+impl Style {
+	fn for_severity(severity: Severity) -> Style {
+		match severity {
+			Severity::Error   => Style::Error,
+			Severity::Warning => Style::Warning,
+			Severity::Note    => Style::Note,
+		}
+	}
+
+	fn paint(self, text: &str) -> String {
+		let code = match self {
+			Style::Error   => "1;31",
+			Style::Warning => "1;33",
+			Style::Note    => "1;36",
+			Style::Gutter  => "1;34",
+			Style::Bold    => "1",
+		};
+
+		format!("\x1b[{}m{}\x1b[0m", code, text)
+	}
+}
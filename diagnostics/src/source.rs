@@ -0,0 +1,86 @@
+use crate::Span;
+
+// ------------------------------------------------------------------------------------------------
+// SourceFile
+// ------------------------------------------------------------------------------------------------
+
+// The text of a source file, along with what we need to turn a codepoint index into a line and
+// column number for error messages.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+	name:        String,
+	text:        String,
+	chars:       Vec<char>,
+	line_starts: Vec<usize>, // the codepoint index where each line starts.
+}
+
+impl SourceFile {
+	// `name` is what's shown in error messages: a filename, or something like "<input>".
+	pub fn new(name: impl Into<String>, text: impl Into<String>) -> Self {
+		let text  = text.into();
+		let chars = text.chars().collect::<Vec<_>>();
+
+		// the first line starts at 0, and every other line starts right after a '\n'.
+		let mut line_starts = vec![0];
+		line_starts.extend(chars.iter().enumerate()
+			.filter(|(_, &c)| c == '\n')
+			.map(|(i, _)| i + 1));
+
+		SourceFile { name: name.into(), text, chars, line_starts }
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	// how many codepoints long the file is.
+	pub fn len(&self) -> usize {
+		self.chars.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.chars.is_empty()
+	}
+
+	pub fn num_lines(&self) -> usize {
+		self.line_starts.len()
+	}
+
+	// Converts a codepoint index into a 1-based (line, column) pair, like editors show.
+	// Indexes past the end are treated as being at the end.
+	pub fn line_col(&self, loc: usize) -> (usize, usize) {
+		let loc = loc.min(self.chars.len());
+
+		// binary_search gives Ok(i) if loc is exactly the start of line i, and Err(i) if it
+		// would be inserted at i, meaning it's in line i - 1.
+		let line = match self.line_starts.binary_search(&loc) {
+			Ok(line)  => line,
+			Err(line) => line - 1,
+		};
+
+		(line + 1, loc - self.line_starts[line] + 1)
+	}
+
+	// The text that a span covers. (A Span counts codepoints, not bytes, so slicing text() with
+	// it would give the wrong text, or panic, as soon as there's an 'é' or something before it.)
+	// Spans past the end are cut off at the end.
+	pub fn span_text(&self, span: Span) -> String {
+		let end = span.end.min(self.chars.len());
+		self.chars[span.start.min(end) .. end].iter().collect()
+	}
+
+	// The text of the given 1-based line, without the newline at the end.
+	pub fn line_text(&self, line: usize) -> String {
+		let start = self.line_starts[line - 1];
+		let end = match self.line_starts.get(line) {
+			Some(next) => next - 1, // don't include the '\n'.
+			None       => self.chars.len(),
+		};
+
+		self.chars[start .. end].iter().filter(|&&c| c != '\r').collect()
+	}
+}
@@ -0,0 +1,132 @@
+use std::io;
+use std::path::Path;
+
+use crate::{ ColorChoice, Diagnostic, SourceFile, Span };
+
+// ------------------------------------------------------------------------------------------------
+// SourceManager
+// ------------------------------------------------------------------------------------------------
+
+/*
+A Span is just two numbers, so it can't say *which* file it's in. Once a program is more than one
+file, that's a problem: is 10..15 in main.calc or in the file it included?
+
+The trick (which rustc and clang both use) is to give every file its own range of *global*
+positions, one after another, as if all the files were glued together into one big string:
+
+	main.calc    (30 chars): 0 ..= 30
+	helper.calc  (12 chars): 31 ..= 43
+	...
+
+Each file's range is one longer than the file, so that a span at the very end of a file (like
+"expected ')' here" at the end of input) still belongs to that file and not the next one.
+
+So a Span is still just two numbers, and nothing else in the compiler has to change. Only the code
+that *shows* an error has to ask the SourceManager which file the span is in, and where in that
+file.
+*/
+
+// Which file in a SourceManager. These are only meaningful for the manager that gave them out.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FileId(usize);
+
+#[derive(Debug, Clone)]
+struct ManagedFile {
+	file:        SourceFile,
+	start:       usize,        // the global position of the file's first character.
+	included_at: Option<Span>, // the (global) span of the `include` that brought this file in.
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SourceManager {
+	files: Vec<ManagedFile>,
+}
+
+impl SourceManager {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Adds a file, and gives it the next range of global positions.
+	pub fn add(&mut self, file: SourceFile) -> FileId {
+		let start = self.files.last().map_or(0, |last| last.start + last.file.len() + 1);
+		self.files.push(ManagedFile { file, start, included_at: None });
+		FileId(self.files.len() - 1)
+	}
+
+	// Reads a file from disk and adds it. Its name is the path.
+	pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<FileId> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string(path)?;
+		Ok(self.add(SourceFile::new(path.display().to_string(), text)))
+	}
+
+	// Remembers that `file` was included by the code at `at`, so that errors in it can say so.
+	pub fn set_included_at(&mut self, file: FileId, at: Span) {
+		self.files[file.0].included_at = Some(at);
+	}
+
+	pub fn file(&self, id: FileId) -> &SourceFile {
+		&self.files[id.0].file
+	}
+
+	pub fn included_at(&self, id: FileId) -> Option<Span> {
+		self.files[id.0].included_at
+	}
+
+	// The global position of the file's first character. Add this to a span from lexing the
+	// file's text, to get a span that means the same thing everywhere.
+	pub fn start(&self, id: FileId) -> usize {
+		self.files[id.0].start
+	}
+
+	// Which file a global position is in. Panics if there are no files.
+	pub fn file_at(&self, pos: usize) -> FileId {
+		assert!(!self.files.is_empty(), "the SourceManager doesn't have any files");
+
+		// the last file that starts at or before pos. (like SourceFile::line_col.)
+		let i = match self.files.binary_search_by_key(&pos, |f| f.start) {
+			Ok(i)  => i,
+			Err(i) => i - 1,
+		};
+
+		FileId(i)
+	}
+
+	// Turns a global span into the file it's in, and the span within that file.
+	pub fn localize(&self, span: Span) -> (FileId, Span) {
+		let id = self.file_at(span.start);
+		let start = self.start(id);
+		(id, Span::new(span.start - start, span.end - start))
+	}
+
+	// Like Diagnostic::render_colored(), but for a diagnostic with a global span. It's shown with
+	// the file it's in, and a note for each `include` that led to that file.
+	pub fn render(&self, diag: &Diagnostic, color: ColorChoice) -> String {
+		let span = match diag.span {
+			Some(span) if !self.files.is_empty() => span,
+
+			// without a span, it doesn't matter which file it's rendered with.
+			_ => return diag.render_colored(&SourceFile::new("", ""), color),
+		};
+
+		let (mut id, local) = self.localize(span);
+		let file = self.file(id);
+		let mut diag = diag.clone().with_span(local);
+
+		while let Some(at) = self.included_at(id) {
+			let (parent, local) = self.localize(at);
+			let (line, col) = self.file(parent).line_col(local.start);
+			diag = diag.with_note(format!("in the file included from {}:{}:{}",
+				self.file(parent).name(), line, col));
+			id = parent;
+		}
+
+		diag.render_colored(file, color)
+	}
+
+	// Like render_all_colored(), but with render() above.
+	pub fn render_all(&self, diags: &[Diagnostic], color: ColorChoice) -> String {
+		diags.iter().map(|d| self.render(d, color)).collect::<Vec<_>>().join("\n")
+	}
+}
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// errors are fine; the fuzzer is looking for panics.
+fuzz_target!(|data: &[u8]| {
+	let _ = parsing_math::fuzz_parse_math(data);
+});
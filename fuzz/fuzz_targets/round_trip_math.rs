@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use parsing_math::{ AstNode, ToSource };
+
+// the random ASTs are always ones the parser could give, so they must all make it back.
+fuzz_target!(|ast: AstNode| {
+	assert!(parsing_math::round_trips(&ast), "{:?} didn't round-trip through {}", ast,
+		ast.to_source());
+});
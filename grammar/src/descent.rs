@@ -0,0 +1,177 @@
+use std::collections::{ BTreeSet, HashSet };
+
+use diagnostics::{ Diagnostic, Severity };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The recursive-descent interpreter
+// ------------------------------------------------------------------------------------------------
+
+/*
+A hand-written recursive-descent parser has one function per nonterminal, and each function
+decides which alternative to use by looking at the next token. This does the same thing, but
+instead of code for each nonterminal, it reads the rules out of the grammar.
+
+Deciding which alternative to use is the tricky part. A hand-written parser (or an LL(1) parser)
+looks ahead one token and *predicts* the right one. This one doesn't try to be clever: it tries
+*every* alternative, and keeps every way that works. That's called *backtracking*. So parsing a
+nonterminal gives back a list of (tree, where it ended) pairs, one for each way it could match.
+For example, with
+
+	Exp -> Term '+' Exp | Term
+
+parsing an Exp at the start of `1 + 2` gives two answers: one that matched all of `1 + 2`, and
+one that only matched `1`. It's the next thing in the rule that decides which one is useful.
+
+The upside is that it works for any grammar without left recursion (see below), even ones that
+need lots of lookahead. The downside is that it can be really slow on big inputs (exponentially
+slow, for some grammars). That's fine for trying out toy grammars, but it's why real parsers
+compute FIRST sets and predict instead.
+
+If the input can be parsed in more than one way (the grammar is *ambiguous*), the first way wins,
+trying the alternatives of each rule in the order they're written.
+
+If a nonterminal can start with itself (like `Exp -> Exp '+' Term`), parsing an Exp means first
+parsing an Exp, at the same place, forever. That's *left recursion*, and recursive descent can't
+handle it. This parser notices when it's about to do that, and gives an error instead of looping.
+*/
+
+pub struct RecursiveDescent<'g> {
+	grammar: &'g Grammar,
+}
+
+impl<'g> RecursiveDescent<'g> {
+	// Fails if the grammar has any of the problems Grammar::check() looks for.
+	pub fn new(grammar: &'g Grammar) -> Result<Self, Vec<Diagnostic>> {
+		let errs = grammar.check();
+
+		if errs.is_empty() {
+			Ok(RecursiveDescent { grammar })
+		} else {
+			Err(errs)
+		}
+	}
+
+	// Tokenizes the source with tokenize(), then parses it.
+	pub fn parse_str(&self, src: &str) -> Result<ParseTree, Vec<Diagnostic>> {
+		let tokens = tokenize(self.grammar, src)?;
+		self.parse(&tokens).map_err(|e| vec![e])
+	}
+
+	// Parses the tokens, which must end with an EOF token, as the grammar's start symbol.
+	pub fn parse(&self, tokens: &[Token]) -> Result<ParseTree, Diagnostic> {
+		let mut p = Parser {
+			grammar:  self.grammar,
+			tokens,
+			active:   HashSet::new(),
+			furthest: 0,
+			expected: BTreeSet::new(),
+		};
+
+		let eof = tokens.len() - 1;
+		let results = p.nonterminal(&self.grammar.start, 0)?;
+
+		// only a parse that used up all the tokens counts.
+		for (tree, end) in results {
+			if end == eof {
+				return Ok(tree);
+			} else {
+				p.expect(EOF, end);
+			}
+		}
+
+		Err(p.error())
+	}
+}
+
+// the state for one call to parse().
+struct Parser<'g, 't> {
+	grammar: &'g Grammar,
+	tokens:  &'t [Token],
+
+	// the nonterminals being parsed right now, and where they started. if one comes up again at
+	// the same place, that's left recursion.
+	active: HashSet<(&'g str, usize)>,
+
+	// for the error message: the furthest any attempt got, and what it wanted to see there.
+	furthest: usize,
+	expected: BTreeSet<&'g str>,
+}
+
+// the ways that something can match: the tree for each, and the position after it.
+type Matches<T> = Result<Vec<(T, usize)>, Diagnostic>;
+
+impl<'g, 't> Parser<'g, 't> {
+	fn nonterminal(&mut self, nt: &'g str, pos: usize) -> Matches<ParseTree> {
+		if !self.active.insert((nt, pos)) {
+			return Err(Diagnostic::new(Severity::Error,
+				format!("the grammar is left-recursive: '{}' can start with itself", nt), None)
+				.with_code(diagnostics::LEFT_RECURSION)
+				.with_note("recursive descent can't handle left recursion. rewrite the rule so \
+					that the recursion is on the right instead"));
+		}
+
+		let mut ret = vec![];
+
+		for prod in self.grammar.productions_for(nt) {
+			for (children, end) in self.sequence(&prod.rhs, pos)? {
+				ret.push((ParseTree::Node { name: nt.into(), children }, end));
+			}
+		}
+
+		self.active.remove(&(nt, pos));
+		Ok(ret)
+	}
+
+	// every way that the symbols can match, one after another, starting at pos.
+	fn sequence(&mut self, syms: &'g [Symbol], pos: usize) -> Matches<Vec<ParseTree>> {
+		let (first, rest) = match syms.split_first() {
+			Some(split) => split,
+			None        => return Ok(vec![(vec![], pos)]),
+		};
+
+		let mut ret = vec![];
+
+		for (tree, mid) in self.symbol(first, pos)? {
+			for (mut trees, end) in self.sequence(rest, mid)? {
+				trees.insert(0, tree.clone());
+				ret.push((trees, end));
+			}
+		}
+
+		Ok(ret)
+	}
+
+	fn symbol(&mut self, sym: &'g Symbol, pos: usize) -> Matches<ParseTree> {
+		match sym {
+			Symbol::Nonterminal(nt) => self.nonterminal(nt, pos),
+
+			Symbol::Terminal(kind) if self.tokens[pos].kind == *kind =>
+				Ok(vec![(ParseTree::Leaf(self.tokens[pos].clone()), pos + 1)]),
+
+			Symbol::Terminal(kind) => {
+				self.expect(kind, pos);
+				Ok(vec![])
+			}
+		}
+	}
+
+	// remembers that `kind` could have gone at `pos`, if that's the furthest we've gotten.
+	fn expect(&mut self, kind: &'g str, pos: usize) {
+		if pos > self.furthest {
+			self.furthest = pos;
+			self.expected.clear();
+		}
+
+		if pos == self.furthest {
+			self.expected.insert(kind);
+		}
+	}
+
+	// the error is at the furthest place any alternative got to, since that's probably where the
+	// mistake is.
+	fn error(&self) -> Diagnostic {
+		unexpected_token(&self.tokens[self.furthest], self.expected.iter().copied())
+	}
+}
@@ -0,0 +1,529 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult, Write };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// EBNF
+// ------------------------------------------------------------------------------------------------
+
+/*
+The grammars in this crate only have plain rules: a list of alternatives, each a list of symbols.
+That's all a parser needs, but it's not the nicest way for a *person* to read a grammar. Repetition
+has to be written with recursion, and "optional" has to be written as an ε alternative:
+
+	Args     -> Exp ArgsTail | ε
+	ArgsTail -> ',' Exp ArgsTail | ε
+
+*EBNF* (extended BNF) adds a few operators from regexes to say those things directly:
+
+	x*       zero or more x's
+	x+       one or more x's
+	x?       an optional x
+	(x | y)  grouping
+
+So that grammar becomes:
+
+	Args:     (Exp ArgsTail)?
+	ArgsTail: (',' Exp)*
+
+This is the notation used in the comments in toylang's parser.rs, and in most language specs.
+
+Ebnf::new() rewrites each nonterminal's rules into EBNF, by looking for a few patterns:
+
+	A -> A x | y                ==>   A: y x*          (left recursion)
+	A -> x A | y                ==>   A: x* y          (right recursion)
+	A -> x y | x z              ==>   A: x (y | z)     (a common prefix)
+	A -> x z | y z              ==>   A: (x | y) z     (a common suffix)
+	A -> x | ε                  ==>   A: x?
+	A -> x x*                   ==>   A: x+
+
+It's the same language either way; each rewrite is just a different way of writing the same set
+of strings. It doesn't rewrite *across* nonterminals (so ArgsTail is still there), since each one
+might be used in more than one place.
+
+And then it can draw them as *railroad diagrams* (see to_svg()), which might be the most readable
+way to write a grammar. JSON's website (json.org) explains the whole language with them.
+*/
+
+// The right side of an EBNF rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagram {
+	Empty,                     // ε
+	Terminal(String),
+	Nonterminal(String),
+	Sequence(Vec<Diagram>),    // x y z
+	Choice(Vec<Diagram>),      // x | y | z
+	Optional(Box<Diagram>),    // x?
+	ZeroOrMore(Box<Diagram>),  // x*
+	OneOrMore(Box<Diagram>),   // x+
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ebnf {
+	pub rules: Vec<(String, Diagram)>, // each nonterminal and its right side, in grammar order.
+}
+
+impl Ebnf {
+	pub fn new(grammar: &Grammar) -> Self {
+		let rules = grammar.nonterminals().into_iter().map(|nt| {
+			let alts = grammar.productions_for(nt).map(|p| p.rhs.clone()).collect();
+			(nt.to_string(), rewrite(nt, alts))
+		});
+
+		Ebnf { rules: rules.collect() }
+	}
+}
+
+fn rewrite(nt: &str, alts: Vec<Vec<Symbol>>) -> Diagram {
+	let me = n(nt);
+
+	// left recursion.
+	let (rec, base): (Vec<_>, Vec<_>) = alts.iter().cloned()
+		.partition(|alt| alt.first() == Some(&me));
+
+	if !rec.is_empty() && !base.is_empty() {
+		let rec = rec.into_iter().map(|alt| alt[1 ..].to_vec()).collect();
+		return sequence(vec![choice(base), zero_or_more(choice(rec))]);
+	}
+
+	// right recursion. `A -> x A | x` is better as `x A?` than `x* x`, though.
+	let nonempty = alts.iter().filter(|alt| !alt.is_empty()).collect::<Vec<_>>();
+
+	if nonempty.len() < 2 || common_prefix(&nonempty) == 0 {
+		let (rec, base): (Vec<_>, Vec<_>) = alts.iter().cloned()
+			.partition(|alt| alt.len() > 1 && alt.last() == Some(&me));
+
+		if !rec.is_empty() && !base.is_empty() {
+			let rec = rec.into_iter().map(|alt| alt[.. alt.len() - 1].to_vec()).collect();
+			return sequence(vec![zero_or_more(choice(rec)), choice(base)]);
+		}
+	}
+
+	choice(alts)
+}
+
+// the alternatives, with common prefixes and suffixes factored out, and ε turned into `?`.
+fn choice(alts: Vec<Vec<Symbol>>) -> Diagram {
+	let optional = alts.iter().any(|alt| alt.is_empty());
+	let alts = alts.into_iter().filter(|alt| !alt.is_empty()).collect::<Vec<_>>();
+
+	let ret = match &alts[..] {
+		[]    => Diagram::Empty,
+		[one] => symbols(one),
+
+		_ => {
+			let refs = alts.iter().collect::<Vec<_>>();
+			let (prefix, suffix) = (common_prefix(&refs), common_suffix(&refs));
+
+			if prefix > 0 {
+				let rest = alts.iter().map(|alt| alt[prefix ..].to_vec()).collect();
+				sequence(vec![symbols(&alts[0][.. prefix]), choice(rest)])
+			} else if suffix > 0 {
+				let rest = alts.iter().map(|alt| alt[.. alt.len() - suffix].to_vec()).collect();
+				let first = &alts[0];
+				sequence(vec![choice(rest), symbols(&first[first.len() - suffix ..])])
+			} else {
+				Diagram::Choice(alts.iter().map(|alt| symbols(alt)).collect())
+			}
+		}
+	};
+
+	if optional { self::optional(ret) } else { ret }
+}
+
+fn common_prefix(alts: &[&Vec<Symbol>]) -> usize {
+	let shortest = alts.iter().map(|alt| alt.len()).min().unwrap_or(0);
+	(0 .. shortest).take_while(|&i| alts.iter().all(|alt| alt[i] == alts[0][i])).count()
+}
+
+fn common_suffix(alts: &[&Vec<Symbol>]) -> usize {
+	let shortest = alts.iter().map(|alt| alt.len()).min().unwrap_or(0);
+	(1 ..= shortest)
+		.take_while(|&i| alts.iter().all(|alt| alt[alt.len() - i] == alts[0][alts[0].len() - i]))
+		.count()
+}
+
+fn symbols(syms: &[Symbol]) -> Diagram {
+	sequence(syms.iter().map(|sym| match sym {
+		Symbol::Terminal(name)    => Diagram::Terminal(name.clone()),
+		Symbol::Nonterminal(name) => Diagram::Nonterminal(name.clone()),
+	}).collect())
+}
+
+// these make diagrams, simplifying them as they go.
+
+fn sequence(items: Vec<Diagram>) -> Diagram {
+	let mut ret: Vec<Diagram> = vec![];
+
+	for item in items {
+		match item {
+			Diagram::Empty           => {}
+			Diagram::Sequence(inner) => ret.extend(inner),
+			_                        => ret.push(item),
+		}
+
+		// `x x*` is `x+`. x might be a sequence, so it's more than one item back.
+		if let Some(Diagram::ZeroOrMore(body)) = ret.last() {
+			let body_items = match &**body {
+				Diagram::Sequence(items) => items.clone(),
+				other                    => vec![other.clone()],
+			};
+
+			let len = ret.len() - 1;
+
+			if len >= body_items.len() && ret[len - body_items.len() .. len] == body_items[..] {
+				let body = body.clone();
+				ret.truncate(len - body_items.len());
+				ret.push(Diagram::OneOrMore(body));
+			}
+		}
+	}
+
+	match ret.len() {
+		0 => Diagram::Empty,
+		1 => ret.pop().unwrap(),
+		_ => Diagram::Sequence(ret),
+	}
+}
+
+fn optional(d: Diagram) -> Diagram {
+	match d {
+		Diagram::Empty | Diagram::Optional(..) | Diagram::ZeroOrMore(..) => d,
+		Diagram::OneOrMore(body) => Diagram::ZeroOrMore(body),
+		_                        => Diagram::Optional(Box::new(d)),
+	}
+}
+
+fn zero_or_more(d: Diagram) -> Diagram {
+	match d {
+		Diagram::Empty => d,
+		Diagram::Optional(body) | Diagram::ZeroOrMore(body) | Diagram::OneOrMore(body) =>
+			Diagram::ZeroOrMore(body),
+		_ => Diagram::ZeroOrMore(Box::new(d)),
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Printing EBNF
+// ------------------------------------------------------------------------------------------------
+
+impl Diagram {
+	// `|` is the loosest, then sequences, then the postfix operators. so `(x | y) z` and
+	// `(x y)*` need the parentheses.
+	fn precedence(&self) -> u8 {
+		match self {
+			Diagram::Choice(..)   => 0,
+			Diagram::Sequence(..) => 1,
+			_                     => 2,
+		}
+	}
+
+	fn fmt_in(&self, f: &mut Formatter, outer: u8) -> FmtResult {
+		if self.precedence() < outer {
+			write!(f, "(")?;
+			self.fmt_in(f, 0)?;
+			return write!(f, ")");
+		}
+
+		match self {
+			Diagram::Empty             => write!(f, "ε"),
+			Diagram::Terminal(name)    => write!(f, "{}", terminal_name(name)),
+			Diagram::Nonterminal(name) => write!(f, "{}", name),
+			Diagram::Optional(body)    => { body.fmt_in(f, 2)?; write!(f, "?") }
+			Diagram::ZeroOrMore(body)  => { body.fmt_in(f, 2)?; write!(f, "*") }
+			Diagram::OneOrMore(body)   => { body.fmt_in(f, 2)?; write!(f, "+") }
+
+			Diagram::Sequence(items) => {
+				for (i, item) in items.iter().enumerate() {
+					if i > 0 { write!(f, " ")?; }
+					item.fmt_in(f, 2)?;
+				}
+
+				Ok(())
+			}
+
+			Diagram::Choice(alts) => {
+				for (i, alt) in alts.iter().enumerate() {
+					if i > 0 { write!(f, " | ")?; }
+					alt.fmt_in(f, 1)?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
+
+impl Display for Diagram {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		self.fmt_in(f, 0)
+	}
+}
+
+// Written like the grammar in toylang's parser.rs. Choices that are too long for one line are
+// continued on the next one, starting with `|`.
+impl Display for Ebnf {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		const MAX_LINE: usize = 100;
+		let width = self.rules.iter().map(|(nt, _)| nt.chars().count() + 1).max().unwrap_or(0);
+
+		for (nt, rhs) in &self.rules {
+			let mut line = format!("{:<w$}", format!("{}:", nt), w = width);
+
+			let alts = match rhs {
+				Diagram::Choice(alts) => alts.iter().map(|alt| alt.to_string()).collect(),
+				_                     => vec![rhs.to_string()],
+			};
+
+			for (i, alt) in alts.iter().enumerate() {
+				if i == 0 {
+					line.push(' ');
+				} else if line.chars().count() + alt.chars().count() + 3 > MAX_LINE {
+					writeln!(f, "{}", line)?;
+					line = format!("{:<w$} | ", "", w = width);
+				} else {
+					line.push_str(" | ");
+				}
+
+				line.push_str(alt);
+			}
+
+			writeln!(f, "{}", line)?;
+		}
+
+		Ok(())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Railroad diagrams
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *railroad diagram* draws a rule as a track, like a train set. Start at the left, follow any path
+the track allows to the right end, and the boxes you pass are what has to be in the input, in that
+order. Terminals are in round boxes, and nonterminals are in square ones (click on them to go to
+their diagram). Choices split the track, and repetition is a loop that goes back around.
+
+Drawing one is a little layout problem. Each part of a diagram has a *width*, and a height *above*
+and *below* the track that goes through it. Those are worked out from the inside out: a sequence
+is as wide as all of its parts put together, a choice is as tall as all of its alternatives
+stacked up, and so on. Then each part can be drawn at any (x, y), where y is where the track is.
+
+This makes SVG (scalable vector graphics), which any web browser can show. It's XML, with elements
+like <rect> and <text>, and <path>s, which are little drawing programs: `M 10 20` moves to (10, 20),
+`H 50` draws a line to x = 50, `V 80` draws one to y = 80, and `a 10 10 0 0 1 10 10` draws a
+quarter circle. In SVG, y goes *down*.
+*/
+
+const CHAR_W: i32 = 8;  // about how wide one character is, in the font used.
+const BOX_H:  i32 = 22; // how tall a box is.
+const PAD:    i32 = 8;  // between the text in a box and its sides.
+const GAP:    i32 = 10; // the length of track between two things in a sequence.
+const R:      i32 = 10; // the radius of the curves.
+const VGAP:   i32 = 8;  // between the alternatives of a choice, and around loops.
+const MARGIN: i32 = 10;
+
+const STYLE: &str = "
+	path { stroke: #333; stroke-width: 2; fill: none; }
+	rect { stroke: #333; stroke-width: 2; fill: #fdf6d8; }
+	rect.nt { fill: #dcecf8; }
+	text { font: 13px monospace; text-anchor: middle; dominant-baseline: central; }
+	text.name { font-weight: bold; text-anchor: start; }
+";
+
+impl Diagram {
+	// optional and zero-or-more are drawn as choices, with a track that skips the body.
+	fn for_drawing(&self) -> Diagram {
+		match self {
+			Diagram::Optional(body) =>
+				Diagram::Choice(vec![Diagram::Empty, (**body).clone()]),
+			Diagram::ZeroOrMore(body) =>
+				Diagram::Choice(vec![Diagram::Empty, Diagram::OneOrMore(body.clone())]),
+			_ => self.clone(),
+		}
+	}
+
+	fn width(&self) -> i32 {
+		match self.for_drawing() {
+			Diagram::Empty => 0,
+			Diagram::Terminal(name) | Diagram::Nonterminal(name) => box_width(&name),
+			Diagram::Sequence(items) =>
+				items.iter().map(Diagram::width).sum::<i32>() + GAP * (items.len() as i32 - 1),
+			Diagram::Choice(alts)   => alts.iter().map(Diagram::width).max().unwrap() + 4 * R,
+			Diagram::OneOrMore(body) => body.width() + 4 * R,
+			_ => unreachable!(),
+		}
+	}
+
+	fn above(&self) -> i32 {
+		match self.for_drawing() {
+			Diagram::Empty => 0,
+			Diagram::Terminal(..) | Diagram::Nonterminal(..) => BOX_H / 2,
+			Diagram::Sequence(items) => items.iter().map(Diagram::above).max().unwrap(),
+			Diagram::Choice(alts)    => alts[0].above(),
+			Diagram::OneOrMore(body) => body.above(),
+			_ => unreachable!(),
+		}
+	}
+
+	fn below(&self) -> i32 {
+		match self.for_drawing() {
+			Diagram::Empty => 0,
+			Diagram::Terminal(..) | Diagram::Nonterminal(..) => BOX_H / 2,
+			Diagram::Sequence(items) => items.iter().map(Diagram::below).max().unwrap(),
+			Diagram::Choice(alts)    => {
+				let offsets = choice_offsets(&alts);
+				offsets.last().unwrap() + alts.last().unwrap().below()
+			}
+			Diagram::OneOrMore(body) => loop_offset(&body),
+			_ => unreachable!(),
+		}
+	}
+
+	// draws it with the track coming in at (x, y) on the left, and going out on the right.
+	fn draw(&self, out: &mut String, x: i32, y: i32) {
+		match self.for_drawing() {
+			Diagram::Empty => {}
+			Diagram::Terminal(name)    => draw_box(out, &name, x, y, None),
+			Diagram::Nonterminal(name) => draw_box(out, &name, x, y, Some(&name)),
+
+			Diagram::Sequence(items) => {
+				let mut x = x;
+
+				for (i, item) in items.iter().enumerate() {
+					if i > 0 {
+						track(out, format!("M{} {} h{}", x, y, GAP));
+						x += GAP;
+					}
+
+					item.draw(out, x, y);
+					x += item.width();
+				}
+			}
+
+			Diagram::Choice(alts) => {
+				let inner = alts.iter().map(Diagram::width).max().unwrap();
+				let (left, right) = (x + 2 * R, x + 2 * R + inner);
+
+				for (alt, offset) in alts.iter().zip(choice_offsets(&alts)) {
+					let alt_y = y + offset;
+
+					// the first one is on the main track. the others branch off of it, and come
+					// back to it at the end.
+					if offset == 0 {
+						track(out, format!("M{} {} H{}", x, y, left));
+						track(out, format!("M{} {} H{}", right, y, right + 2 * R));
+					} else {
+						track(out, format!("M{} {} a{r} {r} 0 0 1 {r} {r} V{} \
+							a{r} {r} 0 0 0 {r} {r}", x, y, alt_y - R, r = R));
+						track(out, format!("M{} {} a{r} {r} 0 0 0 {r} -{r} V{} \
+							a{r} {r} 0 0 1 {r} -{r}", right, alt_y, y + R, r = R));
+					}
+
+					alt.draw(out, left, alt_y);
+						if alt.width() < inner {
+						track(out, format!("M{} {} H{}", left + alt.width(), alt_y, right));
+					}
+				}
+			}
+
+			// the body is on the main track, and the loop goes back under it.
+			Diagram::OneOrMore(body) => {
+				let (left, right) = (x + 2 * R, x + 2 * R + body.width());
+				let loop_y = y + loop_offset(&body);
+
+				track(out, format!("M{} {} H{}", x, y, left));
+				body.draw(out, left, y);
+				track(out, format!("M{} {} H{}", right, y, right + 2 * R));
+				track(out, format!("M{} {} a{r} {r} 0 0 1 {r} {r} V{} a{r} {r} 0 0 1 -{r} {r} H{} \
+					a{r} {r} 0 0 1 -{r} -{r} V{} a{r} {r} 0 0 1 {r} -{r}",
+					right, y, loop_y - R, left, y + R, r = R));
+			}
+
+			_ => unreachable!(),
+		}
+	}
+}
+
+// how far below the main track each alternative's track is. the first one *is* the main track.
+fn choice_offsets(alts: &[Diagram]) -> Vec<i32> {
+	let mut ret = vec![0];
+
+	for i in 1 .. alts.len() {
+		let gap = alts[i - 1].below() + VGAP + alts[i].above();
+		// there has to be room for the curves down from the main track.
+		let gap = if i == 1 { gap.max(2 * R) } else { gap };
+		ret.push(ret[i - 1] + gap);
+	}
+
+	ret
+}
+
+// how far below the main track the loop of a OneOrMore goes.
+fn loop_offset(body: &Diagram) -> i32 {
+	(body.below() + VGAP).max(2 * R)
+}
+
+fn box_width(name: &str) -> i32 {
+	name.chars().count() as i32 * CHAR_W + 2 * PAD
+}
+
+// a terminal has round corners; a nonterminal has square ones, and links to its own diagram.
+fn draw_box(out: &mut String, name: &str, x: i32, y: i32, link: Option<&str>) {
+	let (w, text) = (box_width(name), escape(name));
+
+	match link {
+		Some(nt) => {
+			writeln!(out, "<a href=\"#{}\"><rect class=\"nt\" x=\"{}\" y=\"{}\" width=\"{}\" \
+				height=\"{}\"/>", escape(nt), x, y - BOX_H / 2, w, BOX_H).unwrap();
+			writeln!(out, "<text x=\"{}\" y=\"{}\">{}</text></a>", x + w / 2, y, text).unwrap();
+		}
+
+		None => {
+			writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>",
+				x, y - BOX_H / 2, w, BOX_H, BOX_H / 2).unwrap();
+			writeln!(out, "<text x=\"{}\" y=\"{}\">{}</text>", x + w / 2, y, text).unwrap();
+		}
+	}
+}
+
+fn track(out: &mut String, path: String) {
+	writeln!(out, "<path d=\"{}\"/>", path).unwrap();
+}
+
+// <, >, &, and quotes mean things in XML.
+fn escape(s: &str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+impl Ebnf {
+	// One SVG picture with a railroad diagram for every rule, one under another. Save it to a
+	// .svg file and open it in a web browser.
+	pub fn to_svg(&self) -> String {
+		let mut body = String::new();
+		let (mut width, mut top) = (0, MARGIN);
+
+		for (nt, rhs) in &self.rules {
+			// the name, and then the track, with a bar at each end.
+			let y = top + 24 + rhs.above();
+			let start = MARGIN + 2 * R;
+			let end = start + rhs.width();
+
+			writeln!(body, "<g id=\"{}\">", escape(nt)).unwrap();
+			writeln!(body, "<text class=\"name\" x=\"{}\" y=\"{}\">{}</text>", MARGIN, top + 8,
+				escape(nt)).unwrap();
+			track(&mut body, format!("M{m} {} v16 M{m} {} H{}", y - 8, y, start, m = MARGIN));
+			rhs.draw(&mut body, start, y);
+			track(&mut body, format!("M{} {} h{r} m0 -8 v16", end, y, r = 2 * R));
+			body.push_str("</g>\n");
+
+			width = width.max(end + 2 * R + MARGIN);
+			top = y + rhs.below() + 2 * MARGIN;
+		}
+
+		format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+			viewBox=\"0 0 {w} {h}\">\n<style>{}</style>\n{}</svg>\n",
+			STYLE, body, w = width, h = top)
+	}
+}
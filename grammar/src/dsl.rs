@@ -0,0 +1,191 @@
+use diagnostics::{ Diagnostic, Severity, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Reading grammars from text
+// ------------------------------------------------------------------------------------------------
+
+/*
+Grammars can be made with the builder (see Grammar::new()), but it's much nicer to write them the
+way they're written on paper. The grammar of grammars is:
+
+	Grammar:     (Rule? Newline)*
+	Rule:        Name '->' Alts
+	             | '|' Alts                   (more alternatives for the rule on the line above)
+	Alts:        Alt ('|' Alt)*
+	Alt:         Symbol* | 'ε'
+	Symbol:      Name | Quoted
+	Name:        (<alphanumeric> | '_')+
+	Quoted:      "'" <anything but "'">+ "'"  (or with double quotes)
+	Comment:     '#' <anything but Newline>*
+
+The arrow can also be written '→'. The first rule's nonterminal is the start symbol. A name that
+has a rule anywhere in the grammar is a nonterminal, and anything else is a terminal. So a long
+rule can be split across lines, like:
+
+	Exp -> Term '+' Exp
+	     | Term
+
+and writing `Exp -> a` and `Exp -> b` on two lines is the same as `Exp -> a | b`.
+*/
+
+pub fn parse_grammar(src: &str) -> Result<Grammar, Vec<Diagnostic>> {
+	let (words, mut errors) = words(src);
+	let mut rules: Vec<(String, Vec<Word>)> = vec![];
+
+	for line in words.split(|w| w.kind == WordKind::Newline) {
+		match line {
+			[] => {}
+
+			// a continuation. it goes with the rule above it.
+			[bar, rest @ ..] if bar.kind == WordKind::Bar => match rules.last_mut() {
+				Some((_, rhs)) => { rhs.push(bar.clone()); rhs.extend_from_slice(rest); }
+				None => errors.push(bad_grammar("there's no rule above this for it to go with",
+					bar.span)),
+			},
+
+			[lhs, arrow, rhs @ ..]
+			if lhs.kind == WordKind::Name && arrow.kind == WordKind::Arrow =>
+				rules.push((lhs.text.clone(), rhs.to_vec())),
+
+			[first, ..] => errors.push(bad_grammar("expected a rule, like 'Name -> symbols'",
+				first.span)),
+		}
+	}
+
+	for (_, rhs) in &rules {
+		for w in rhs.iter().filter(|w| w.kind == WordKind::Arrow) {
+			errors.push(bad_grammar("a rule can only have one '->'", w.span)
+				.with_note("each rule goes on its own line"));
+		}
+	}
+
+	if rules.is_empty() && errors.is_empty() {
+		errors.push(Diagnostic::new(Severity::Error, "the grammar has no rules", None)
+			.with_code(diagnostics::BAD_GRAMMAR));
+	}
+
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	// now that every rule has been seen, names can be sorted into terminals and nonterminals.
+	let nts = rules.iter().map(|(lhs, _)| lhs.clone()).collect::<Vec<_>>();
+	let mut grammar = Grammar::new(&rules[0].0);
+
+	for (lhs, rhs) in &rules {
+		for alt in rhs.split(|w| w.kind == WordKind::Bar) {
+			let rhs = alt.iter().filter_map(|w| match w.kind {
+				WordKind::Name if nts.contains(&w.text) => Some(n(&w.text)),
+				WordKind::Name | WordKind::Quoted       => Some(t(&w.text)),
+				_                                       => None, // ε
+			});
+
+			grammar = grammar.rule(lhs, rhs.collect());
+		}
+	}
+
+	Ok(grammar)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordKind {
+	Name,
+	Quoted,
+	Arrow,
+	Bar,
+	Epsilon,
+	Newline,
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+	kind: WordKind,
+	text: String, // for Quoted, this doesn't have the quotes.
+	span: Span,
+}
+
+// a little lexer for grammars.
+fn words(src: &str) -> (Vec<Word>, Vec<Diagnostic>) {
+	let chars = src.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut words = vec![];
+	let mut errors = vec![];
+
+	while pos < chars.len() {
+		let start = pos;
+
+		let kind = match chars[pos] {
+			'\n' => { pos += 1; WordKind::Newline }
+			'|'  => { pos += 1; WordKind::Bar }
+			'→'  => { pos += 1; WordKind::Arrow }
+			'ε'  => { pos += 1; WordKind::Epsilon }
+			'-' if chars.get(pos + 1) == Some(&'>') => { pos += 2; WordKind::Arrow }
+
+			'#' => {
+				while pos < chars.len() && chars[pos] != '\n' {
+					pos += 1;
+				}
+
+				continue;
+			}
+
+			c if c.is_whitespace() => {
+				pos += 1;
+				continue;
+			}
+
+			c if c.is_alphanumeric() || c == '_' => {
+				while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+					pos += 1;
+				}
+
+				WordKind::Name
+			}
+
+			q @ '\'' | q @ '"' => {
+				pos += 1;
+
+				while pos < chars.len() && chars[pos] != q && chars[pos] != '\n' {
+					pos += 1;
+				}
+
+				if chars.get(pos) != Some(&q) {
+					errors.push(bad_grammar("this quote is never closed", Span::point(start)));
+					continue;
+				}
+
+				pos += 1;
+
+				if pos - start == 2 {
+					errors.push(bad_grammar("a terminal can't be empty", Span::new(start, pos))
+						.with_note("to write an empty alternative, use ε or leave it empty"));
+					continue;
+				}
+
+				WordKind::Quoted
+			}
+
+			c => {
+				errors.push(bad_grammar(format!("'{}' can't go in a grammar", c.escape_debug()),
+					Span::point(pos)).with_note("quote it to make it a terminal"));
+				pos += 1;
+				continue;
+			}
+		};
+
+		let text = match kind {
+			WordKind::Quoted => chars[start + 1 .. pos - 1].iter().collect(),
+			_                => chars[start .. pos].iter().collect(),
+		};
+
+		words.push(Word { kind, text, span: Span::new(start, pos) });
+	}
+
+	(words, errors)
+}
+
+fn bad_grammar(message: impl Into<String>, span: Span) -> Diagnostic {
+	Diagnostic::error(message, span).with_code(diagnostics::BAD_GRAMMAR)
+}
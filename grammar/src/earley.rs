@@ -0,0 +1,487 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+use std::fmt::Write;
+
+use diagnostics::Diagnostic;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The Earley parser
+// ------------------------------------------------------------------------------------------------
+
+/*
+Recursive descent can't handle left recursion, and LR parsers can't handle conflicts. An *Earley
+parser* can handle *any* context-free grammar: left-recursive, ambiguous, whatever. That makes it
+great for trying out a grammar before massaging it into a shape that a faster parser can handle.
+
+It uses items like an LR parser does, but each one also remembers where it started (its
+*origin*). There's one set of items for each position in the input (the *chart*), and set k holds
+everything the parser might be in the middle of, after seeing k tokens. Each item in set k is
+handled one of three ways, depending on what's after its dot:
+
+	predict:  a nonterminal B. The parser might be at the start of a B, so add an item for each of
+	          B's productions, with the dot at the start and origin k.
+	scan:     a terminal t. If token k is a t, add the item, with the dot moved past t, to set k+1.
+	complete: nothing (the dot is at the end of A -> ... •). An A was just finished! So every item
+	          in the A's origin set that was waiting for an A (with the dot before it) moves its dot
+	          past the A, and goes in set k.
+
+If set n (after all n tokens) has a complete item for the start symbol with origin 0, the input
+parses. Left recursion is no problem: predicting the same item twice just does nothing, since
+each set only holds one of each item.
+
+Nullable nonterminals need one extra trick (from Aycock and Horspool): when predicting a nullable
+B, also move the dot past B right away, since B might match nothing at all.
+*/
+
+pub struct EarleyParser<'g> {
+	grammar:   &'g Grammar,
+	sets:      GrammarSets,
+	// if true, the chart is printed after parsing.
+	pub trace: bool,
+}
+
+// An item, like an LrItem, plus where it started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EarleyItem {
+	prod:   usize, // an index into Grammar::productions.
+	dot:    usize,
+	origin: usize,
+}
+
+impl EarleyItem {
+	fn advance(self) -> Self {
+		EarleyItem { dot: self.dot + 1, ..self }
+	}
+}
+
+// What an Earley parse gives back.
+#[derive(Debug, Clone)]
+pub enum EarleyParse {
+	// There was only one way to parse it.
+	Tree(ParseTree),
+	// There was more than one way to parse it, and here they all are.
+	Ambiguous(ParseForest),
+}
+
+impl<'g> EarleyParser<'g> {
+	// Fails if the grammar has any of the problems Grammar::check() looks for.
+	pub fn new(grammar: &'g Grammar) -> Result<Self, Vec<Diagnostic>> {
+		let errs = grammar.check();
+
+		if errs.is_empty() {
+			Ok(EarleyParser { grammar, sets: GrammarSets::new(grammar), trace: false })
+		} else {
+			Err(errs)
+		}
+	}
+
+	// Tokenizes the source with tokenize(), then parses it.
+	pub fn parse_str(&self, src: &str) -> Result<EarleyParse, Vec<Diagnostic>> {
+		let tokens = tokenize(self.grammar, src)?;
+		self.parse(&tokens).map_err(|e| vec![e])
+	}
+
+	// Parses the tokens, which must end with an EOF token, as the grammar's start symbol.
+	pub fn parse(&self, tokens: &[Token]) -> Result<EarleyParse, Diagnostic> {
+		let n = tokens.len() - 1; // not counting the EOF token.
+		let chart = self.fill_chart(tokens);
+
+		if self.trace {
+			print!("{}", self.chart_string(&chart, tokens));
+		}
+
+		if !self.accepts(&chart[n]) {
+			return Err(self.error(&chart, tokens));
+		}
+
+		let forest = ParseForest::new(self.grammar, tokens, &chart);
+
+		if forest.num_trees() == Some(1) {
+			Ok(EarleyParse::Tree(forest.first_tree()))
+		} else {
+			Ok(EarleyParse::Ambiguous(forest))
+		}
+	}
+
+	fn fill_chart(&self, tokens: &[Token]) -> Vec<Vec<EarleyItem>> {
+		let n = tokens.len() - 1;
+		let mut chart = vec![Chart::default(); n + 1];
+
+		for (prod, p) in self.grammar.productions.iter().enumerate() {
+			if p.lhs == self.grammar.start {
+				chart[0].add(EarleyItem { prod, dot: 0, origin: 0 });
+			}
+		}
+
+		for k in 0 ..= n {
+			let mut i = 0;
+
+			// set k can grow while we're going through it, so this can't be a for loop.
+			while i < chart[k].items.len() {
+				let item = chart[k].items[i];
+
+				match self.next_symbol(item) {
+					// predict
+					Some(Symbol::Nonterminal(b)) => {
+						for (prod, p) in self.grammar.productions.iter().enumerate() {
+							if p.lhs == *b {
+								chart[k].add(EarleyItem { prod, dot: 0, origin: k });
+							}
+						}
+
+						if self.sets.is_nullable(b) {
+							chart[k].add(item.advance());
+						}
+					}
+
+					// scan
+					Some(Symbol::Terminal(t)) => {
+						if k < n && tokens[k].kind == *t {
+							chart[k + 1].add(item.advance());
+						}
+					}
+
+					// complete
+					None => {
+						let lhs = &self.grammar.productions[item.prod].lhs;
+
+						let waiting = chart[item.origin].items.iter()
+							.filter(|&&w| matches!(self.next_symbol(w),
+								Some(Symbol::Nonterminal(b)) if b == lhs))
+							.map(|w| w.advance())
+							.collect::<Vec<_>>();
+
+						for w in waiting {
+							chart[k].add(w);
+						}
+					}
+				}
+
+				i += 1;
+			}
+		}
+
+		chart.into_iter().map(|c| c.items).collect()
+	}
+
+	// does the set have a complete item for the start symbol, that started at the beginning?
+	fn accepts(&self, set: &[EarleyItem]) -> bool {
+		set.iter().any(|&item| item.origin == 0 && self.is_complete(item) &&
+			self.grammar.productions[item.prod].lhs == self.grammar.start)
+	}
+
+	fn next_symbol(&self, item: EarleyItem) -> Option<&Symbol> {
+		self.grammar.productions[item.prod].rhs.get(item.dot)
+	}
+
+	fn is_complete(&self, item: EarleyItem) -> bool {
+		self.next_symbol(item).is_none()
+	}
+
+	// the error is at the last set that has anything in it, since that's as far as the parser got.
+	fn error(&self, chart: &[Vec<EarleyItem>], tokens: &[Token]) -> Diagnostic {
+		let k = (0 .. chart.len()).rev().find(|&k| !chart[k].is_empty()).unwrap_or(0);
+
+		let mut expected = vec![];
+
+		for &item in &chart[k] {
+			if let Some(Symbol::Terminal(t)) = self.next_symbol(item) {
+				if !expected.contains(&t.as_str()) {
+					expected.push(t);
+				}
+			}
+		}
+
+		// if the start symbol could have ended here, the end of the input could have gone here.
+		if self.accepts(&chart[k]) {
+			expected.push(EOF);
+		}
+
+		expected.sort_unstable();
+		expected.dedup();
+		unexpected_token(&tokens[k], expected.into_iter())
+	}
+
+	fn chart_string(&self, chart: &[Vec<EarleyItem>], tokens: &[Token]) -> String {
+		let mut out = String::new();
+
+		for (k, set) in chart.iter().enumerate() {
+			match tokens.get(k) {
+				Some(t) if t.kind != EOF => writeln!(out, "Set {} (next: {}):", k, t).unwrap(),
+				_                        => writeln!(out, "Set {} (at the end):", k).unwrap(),
+			}
+
+			for &item in set {
+				let p = &self.grammar.productions[item.prod];
+				let mut syms = p.rhs.iter().map(|sym| sym.to_string()).collect::<Vec<_>>();
+				syms.insert(item.dot, "•".into());
+				writeln!(out, "    {:<40} from {}", format!("{} -> {}", p.lhs, syms.join(" ")),
+					item.origin).unwrap();
+			}
+		}
+
+		out
+	}
+}
+
+// one set in the chart. the items are in the order they were added, and the HashSet is for quickly
+// checking if something is already there.
+#[derive(Debug, Clone, Default)]
+struct Chart {
+	items: Vec<EarleyItem>,
+	seen:  HashSet<EarleyItem>,
+}
+
+impl Chart {
+	fn add(&mut self, item: EarleyItem) {
+		if self.seen.insert(item) {
+			self.items.push(item);
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Parse forests
+// ------------------------------------------------------------------------------------------------
+
+/*
+An ambiguous grammar can give a *lot* of parse trees for one input. `1 + 2 + 3 + 4` with
+`Exp -> Exp '+' Exp | num` has 5 of them, and the number grows *exponentially* with the length
+of the input. So instead of making every tree, the parser makes a *shared parse forest*: one node
+for each nonterminal that matched each part of the input (like "Exp matched tokens 0 to 3"), and
+each node has a list of the *alternatives*: the different ways that it matched. Children point at
+other nodes by their keys, so every way of parsing `1 + 2` can share the same node for it.
+
+A node with more than one alternative is where the ambiguity is.
+*/
+
+// A nonterminal that matched tokens[start .. end].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ForestKey {
+	pub start: usize,
+	pub end:   usize,
+	pub nt:    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForestChild {
+	Token(usize), // an index into ParseForest::tokens.
+	Node(ForestKey),
+}
+
+// One way a node matched: by which production, and what each symbol on its right side matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForestAlt {
+	pub prod:     usize,
+	pub children: Vec<ForestChild>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseForest {
+	pub root:        ForestKey,
+	pub nodes:       BTreeMap<ForestKey, Vec<ForestAlt>>,
+	pub productions: Vec<Production>,
+	pub tokens:      Vec<Token>,
+}
+
+impl ParseForest {
+	// builds the forest from the chart of a successful parse.
+	fn new(grammar: &Grammar, tokens: &[Token], chart: &[Vec<EarleyItem>]) -> Self {
+		// every complete item is a nonterminal that matched some part of the input.
+		let mut matched = HashSet::new();
+
+		for (end, set) in chart.iter().enumerate() {
+			for item in set {
+				let p = &grammar.productions[item.prod];
+
+				if item.dot == p.rhs.len() {
+					matched.insert((item.prod, item.origin, end));
+				}
+			}
+		}
+
+		let spans = matched.iter()
+			.map(|&(prod, start, end)| (grammar.productions[prod].lhs.as_str(), start, end))
+			.collect::<HashSet<_>>();
+
+		let root = ForestKey { start: 0, end: chart.len() - 1, nt: grammar.start.clone() };
+
+		let mut ret = ParseForest {
+			root:        root.clone(),
+			nodes:       BTreeMap::new(),
+			productions: grammar.productions.clone(),
+			tokens:      tokens.to_vec(),
+		};
+
+		// starting from the root, find every way each node matched, and then do the same for each
+		// of their children.
+		let mut todo = vec![root];
+
+		while let Some(key) = todo.pop() {
+			if ret.nodes.contains_key(&key) {
+				continue;
+			}
+
+			let mut alts = vec![];
+
+			for (prod, p) in grammar.productions.iter().enumerate() {
+				if p.lhs == key.nt && matched.contains(&(prod, key.start, key.end)) {
+					for children in ret.splits(&spans, &p.rhs, key.start, key.end) {
+						alts.push(ForestAlt { prod, children });
+					}
+				}
+			}
+
+			for alt in &alts {
+				for child in &alt.children {
+					if let ForestChild::Node(child) = child {
+						todo.push(child.clone());
+					}
+				}
+			}
+
+			ret.nodes.insert(key, alts);
+		}
+
+		ret
+	}
+
+	// every way that the symbols can match exactly tokens[start .. end].
+	fn splits(&self, spans: &HashSet<(&str, usize, usize)>, rhs: &[Symbol], start: usize,
+	end: usize) -> Vec<Vec<ForestChild>> {
+		let (first, rest) = match rhs.split_first() {
+			Some(split) => split,
+			None        => return if start == end { vec![vec![]] } else { vec![] },
+		};
+
+		let mut ret = vec![];
+
+		match first {
+			Symbol::Terminal(t) => {
+				if start < end && self.tokens[start].kind == *t {
+					for mut children in self.splits(spans, rest, start + 1, end) {
+						children.insert(0, ForestChild::Token(start));
+						ret.push(children);
+					}
+				}
+			}
+
+			Symbol::Nonterminal(nt) => {
+				for mid in start ..= end {
+					if spans.contains(&(nt.as_str(), start, mid)) {
+						for mut children in self.splits(spans, rest, mid, end) {
+							let key = ForestKey { start, end: mid, nt: nt.clone() };
+							children.insert(0, ForestChild::Node(key));
+							ret.push(children);
+						}
+					}
+				}
+			}
+		}
+
+		ret
+	}
+
+	// How many different parse trees are in the forest, or None if there are infinitely many.
+	// (That can happen with grammars like `A -> A | a`, where A can be itself over and over.)
+	pub fn num_trees(&self) -> Option<u64> {
+		self.count(&self.root, &mut HashMap::new())
+	}
+
+	// the map holds the counts we've already figured out, and None for nodes we're in the middle
+	// of counting. coming back to one of those is a cycle.
+	fn count(&self, key: &ForestKey, memo: &mut HashMap<ForestKey, Option<u64>>) -> Option<u64> {
+		match memo.get(key) {
+			Some(&Some(count)) => return Some(count),
+			Some(None)         => return None,
+			None               => {}
+		}
+
+		memo.insert(key.clone(), None);
+		let mut total: u64 = 0;
+
+		for alt in &self.nodes[key] {
+			let mut ways: u64 = 1;
+
+			for child in &alt.children {
+				if let ForestChild::Node(child) = child {
+					ways = ways.saturating_mul(self.count(child, memo)?);
+				}
+			}
+
+			total = total.saturating_add(ways);
+		}
+
+		memo.insert(key.clone(), Some(total));
+		Some(total)
+	}
+
+	// The parse tree you get by always picking the first alternative that works.
+	pub fn first_tree(&self) -> ParseTree {
+		self.tree(&self.root, &mut HashSet::new()).unwrap()
+	}
+
+	// None if every alternative leads back to a node we're already in (a cycle).
+	fn tree(&self, key: &ForestKey, visiting: &mut HashSet<ForestKey>) -> Option<ParseTree> {
+		if !visiting.insert(key.clone()) {
+			return None;
+		}
+
+		let ret = self.nodes[key].iter().find_map(|alt| {
+			let children = alt.children.iter().map(|child| match child {
+				ForestChild::Token(i) => Some(ParseTree::Leaf(self.tokens[*i].clone())),
+				ForestChild::Node(k)  => self.tree(k, visiting),
+			});
+
+			let children = children.collect::<Option<Vec<_>>>()?;
+			Some(ParseTree::Node { name: key.nt.clone(), children })
+		});
+
+		visiting.remove(key);
+		ret
+	}
+
+	// Every node that matched in more than one way, biggest first.
+	pub fn ambiguities(&self) -> Vec<&ForestKey> {
+		let mut ret = self.nodes.iter().filter(|(_, alts)| alts.len() > 1).map(|(key, _)| key)
+			.collect::<Vec<_>>();
+		ret.sort_by_key(|k| (k.start, std::cmp::Reverse(k.end)));
+		ret
+	}
+
+	// Explains where the ambiguity is: for each ambiguous node, the different ways it matched,
+	// with the part of the input each child matched in brackets.
+	pub fn report(&self) -> String {
+		let mut out = String::new();
+
+		match self.num_trees() {
+			Some(n) => writeln!(out, "'{}' is ambiguous: it can be parsed {} ways.",
+				self.text(&self.root), n).unwrap(),
+			None    => writeln!(out, "'{}' is ambiguous: it can be parsed infinitely many ways.",
+				self.text(&self.root)).unwrap(),
+		}
+
+		for key in self.ambiguities() {
+			writeln!(out, "\n{} can match '{}' like:", key.nt, self.text(key)).unwrap();
+
+			for alt in &self.nodes[key] {
+				let children = alt.children.iter().map(|child| match child {
+					ForestChild::Token(i) => format!("[{}]", self.tokens[*i].text),
+					ForestChild::Node(k)  => format!("[{}]", self.text(k)),
+				});
+
+				writeln!(out, "    {:<30} {}", self.productions[alt.prod].to_string(),
+					children.collect::<Vec<_>>().join(" ")).unwrap();
+			}
+		}
+
+		out
+	}
+
+	// the source text of the tokens a node matched.
+	fn text(&self, key: &ForestKey) -> String {
+		self.tokens[key.start .. key.end].iter().map(|t| t.text.as_str()).collect::<Vec<_>>()
+			.join(" ")
+	}
+}
@@ -0,0 +1,230 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Severity };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Symbol
+// ------------------------------------------------------------------------------------------------
+
+// One thing on the right side of a rule. A terminal's name is the *kind* of token it matches,
+// like "num" or "+".
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Symbol {
+	Terminal(String),
+	Nonterminal(String),
+}
+
+impl Symbol {
+	pub fn name(&self) -> &str {
+		match self {
+			Symbol::Terminal(name) | Symbol::Nonterminal(name) => name,
+		}
+	}
+
+	pub fn is_terminal(&self) -> bool {
+		matches!(self, Symbol::Terminal(..))
+	}
+}
+
+// shorthands for making grammars with the builder, like `vec![t("("), n("Exp"), t(")")]`.
+pub fn t(name: &str) -> Symbol {
+	Symbol::Terminal(name.into())
+}
+
+pub fn n(name: &str) -> Symbol {
+	Symbol::Nonterminal(name.into())
+}
+
+impl Display for Symbol {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Symbol::Terminal(name) => write!(f, "{}", terminal_name(name)),
+			Symbol::Nonterminal(name) => write!(f, "{}", name),
+		}
+	}
+}
+
+// terminals that aren't words (like '+') are quoted, so they can be read back in, and so they
+// stand out. the end of the input is always written $.
+pub fn terminal_name(name: &str) -> String {
+	if name == EOF || is_word(name) {
+		name.into()
+	} else {
+		format!("'{}'", name)
+	}
+}
+
+pub(crate) fn is_word(s: &str) -> bool {
+	!s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// ------------------------------------------------------------------------------------------------
+// Production
+// ------------------------------------------------------------------------------------------------
+
+// One alternative of a rule, like `Exp -> Term '+' Exp`. If `rhs` is empty, it's an ε-production.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Production {
+	pub lhs: String,
+	pub rhs: Vec<Symbol>,
+}
+
+impl Display for Production {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{} ->", self.lhs)?;
+
+		if self.rhs.is_empty() {
+			write!(f, " ε")
+		} else {
+			self.rhs.iter().try_for_each(|sym| write!(f, " {}", sym))
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Grammar
+// ------------------------------------------------------------------------------------------------
+
+// A context-free grammar. The productions are kept in the order they were written, since the
+// parsers try alternatives in that order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+	pub start:       String,
+	pub productions: Vec<Production>,
+}
+
+impl Grammar {
+	// An empty grammar. Add rules with rule(), like:
+	//
+	//     Grammar::new("List")
+	//         .rule("List", vec![t("("), n("Items"), t(")")])
+	//         .rule("Items", vec![t("id"), n("Items")])
+	//         .rule("Items", vec![])
+	pub fn new(start: &str) -> Self {
+		Grammar { start: start.into(), productions: vec![] }
+	}
+
+	// Adds one alternative for `lhs`.
+	pub fn rule(mut self, lhs: &str, rhs: Vec<Symbol>) -> Self {
+		self.productions.push(Production { lhs: lhs.into(), rhs });
+		self
+	}
+
+	// The alternatives for one nonterminal, in order.
+	pub fn productions_for<'g>(&'g self, nt: &'g str) -> impl Iterator<Item = &'g Production> {
+		self.productions.iter().filter(move |p| p.lhs == nt)
+	}
+
+	// Every nonterminal that has a rule, in the order their first rules were written.
+	pub fn nonterminals(&self) -> Vec<&str> {
+		let mut ret = vec![];
+
+		for p in &self.productions {
+			if !ret.contains(&p.lhs.as_str()) {
+				ret.push(p.lhs.as_str());
+			}
+		}
+
+		ret
+	}
+
+	// Every terminal used anywhere in the grammar, in the order they first appear.
+	pub fn terminals(&self) -> Vec<&str> {
+		let mut ret = vec![];
+
+		for sym in self.productions.iter().flat_map(|p| &p.rhs) {
+			if sym.is_terminal() && !ret.contains(&sym.name()) {
+				ret.push(sym.name());
+			}
+		}
+
+		ret
+	}
+
+	// Finds mistakes that make the grammar unusable: a start symbol with no rules, a nonterminal
+	// that's used but never given any rules, or one that can never finish matching. (Grammars read
+	// by parse_grammar() can't have the second problem, but ones made with the builder can.)
+	pub fn check(&self) -> Vec<Diagnostic> {
+		let nts = self.nonterminals();
+		let mut errs = vec![];
+
+		if !nts.contains(&self.start.as_str()) {
+			errs.push(grammar_error(format!("the start symbol '{}' has no rules", self.start)));
+		}
+
+		for p in &self.productions {
+			for sym in &p.rhs {
+				if let Symbol::Nonterminal(name) = sym {
+					if !nts.contains(&name.as_str()) {
+						errs.push(grammar_error(format!("the nonterminal '{}' has no rules", name))
+							.with_note(format!("it's used in: {}", p)));
+					}
+				}
+			}
+		}
+
+		for nt in self.unproductive() {
+			errs.push(grammar_error(format!("the nonterminal '{}' can't match anything", nt))
+				.with_note("every one of its rules uses a nonterminal like that, so matching it \
+					never ends"));
+		}
+
+		errs
+	}
+
+	// The nonterminals that can't match any list of tokens at all, like S in `S -> A S`, because
+	// every rule for them leads back into another one like that. Parsers can loop forever on them:
+	// the SLR parser would reduce `A -> ε` over and over, waiting for S to be done. Like the
+	// nullable set, this is found by iterating: a nonterminal is *productive* if one of its rules
+	// has only terminals and productive nonterminals in it, and the rest aren't.
+	fn unproductive(&self) -> Vec<&str> {
+		let nts = self.nonterminals();
+		let mut productive: Vec<&str> = vec![];
+		let mut changed = true;
+
+		while changed {
+			changed = false;
+
+			for p in &self.productions {
+				if !productive.contains(&p.lhs.as_str()) && p.rhs.iter().all(|sym| match sym {
+					Symbol::Terminal(..)       => true,
+					// one with no rules has its own error already.
+					Symbol::Nonterminal(name) =>
+						productive.contains(&name.as_str()) || !nts.contains(&name.as_str()),
+				}) {
+					productive.push(&p.lhs);
+					changed = true;
+				}
+			}
+		}
+
+		nts.into_iter().filter(|nt| !productive.contains(nt)).collect()
+	}
+}
+
+// it's written back out the same way parse_grammar() reads it, so you can read it back in.
+impl Display for Grammar {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		let nts = self.nonterminals();
+		let width = nts.iter().map(|nt| nt.chars().count()).max().unwrap_or(0);
+
+		for nt in nts {
+			let alts = self.productions_for(nt).map(|p| match &p.rhs[..] {
+				[] => "ε".to_string(),
+				rhs => rhs.iter().map(|sym| sym.to_string()).collect::<Vec<_>>().join(" "),
+			});
+
+			writeln!(f, "{:<w$} -> {}", nt, alts.collect::<Vec<_>>().join(" | "), w = width)?;
+		}
+
+		Ok(())
+	}
+}
+
+// grammar errors don't have a span when they're about the grammar itself, rather than the text
+// it was read from.
+fn grammar_error(message: String) -> Diagnostic {
+	Diagnostic::new(Severity::Error, message, None).with_code(diagnostics::BAD_GRAMMAR)
+}
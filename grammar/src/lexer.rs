@@ -0,0 +1,172 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Tokens
+// ------------------------------------------------------------------------------------------------
+
+// The kind of the token at the end of every token list. It's also the terminal that means "the
+// end of the input" in FIRST/FOLLOW sets and LR tables.
+pub const EOF: &str = "$";
+
+// The kinds of tokens that aren't spelled the same every time.
+pub const NUM: &str = "num";
+pub const ID:  &str = "id";
+
+// Unlike toylang's tokens, the kind is a string, since the grammar decides what kinds there are.
+// It's the name of the terminal it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+	pub kind: String,
+	pub text: String,
+	pub span: Span,
+}
+
+impl Display for Token {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		if self.kind == self.text {
+			write!(f, "{}", terminal_name(&self.kind))
+		} else {
+			write!(f, "{} '{}'", self.kind, self.text)
+		}
+	}
+}
+
+// The error for when a parser finds `token`, but only the `expected` kinds could go there.
+pub(crate) fn unexpected_token<'a>(token: &Token, expected: impl Iterator<Item = &'a str>)
+-> Diagnostic {
+	let expected = expected.map(describe).collect::<Vec<_>>();
+	let found = if token.kind == EOF { describe(EOF) } else { format!("'{}'", token.text) };
+
+	// if nothing could have gone here, there's nothing to list.
+	let message = match &expected[..] {
+		[]     => format!("unexpected {}", found),
+		[one]  => format!("expected {}, but found {}", one, found),
+		[a, b] => format!("expected {} or {}, but found {}", a, b, found),
+		_      => format!("expected one of {}, but found {}", expected.join(", "), found),
+	};
+
+	Diagnostic::error(message, token.span).with_code(diagnostics::UNEXPECTED_TOKEN)
+}
+
+// a kind of token, the way it's said in an error message.
+fn describe(kind: &str) -> String {
+	if kind == EOF { "the end of the input".into() } else { terminal_name(kind) }
+}
+
+// ------------------------------------------------------------------------------------------------
+// The lexer
+// ------------------------------------------------------------------------------------------------
+
+/*
+A parser made from a grammar still needs tokens. Writing a new lexer for every grammar would
+defeat the point, so this is one lexer that works well enough for most toy grammars. It splits the
+source into:
+
+- numbers like `12` or `3.5`, which are `num` tokens.
+- words like `x` or `while`. If the grammar uses the word as a terminal (like `while`), it's a
+  keyword, and its kind is the word itself. Otherwise, it's an `id` token.
+- punctuation, which has to be a terminal in the grammar (like '+' or '<='). This uses maximal
+  munch, so if the grammar has both '<' and '<=', then "<=" is one '<=' token.
+
+Whitespace (including newlines) is skipped. The last token is always an EOF token.
+*/
+pub fn tokenize(grammar: &Grammar, src: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+	let terminals = grammar.terminals();
+
+	// longest first, for maximal munch.
+	let mut punctuation = terminals.iter().filter(|t| !is_word(t)).collect::<Vec<_>>();
+	punctuation.sort_by_key(|t| std::cmp::Reverse(t.chars().count()));
+
+	let chars = src.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut tokens = vec![];
+	let mut errors = vec![];
+
+	while pos < chars.len() {
+		let start = pos;
+		let c = chars[pos];
+
+		let kind = if c.is_whitespace() {
+			pos += 1;
+			continue;
+		} else if c.is_alphabetic() || c == '_' {
+			while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+				pos += 1;
+			}
+
+			let word = chars[start .. pos].iter().collect::<String>();
+
+			if word != ID && word != NUM && terminals.contains(&word.as_str()) {
+				word
+			} else {
+				ID.into()
+			}
+		} else if c.is_ascii_digit() {
+			while pos < chars.len() && chars[pos].is_ascii_digit() {
+				pos += 1;
+			}
+
+			if pos + 1 < chars.len() && chars[pos] == '.' && chars[pos + 1].is_ascii_digit() {
+				pos += 1;
+
+				while pos < chars.len() && chars[pos].is_ascii_digit() {
+					pos += 1;
+				}
+			}
+
+			NUM.into()
+		} else {
+			let found = punctuation.iter().find(|p| {
+				let p = p.chars().collect::<Vec<_>>();
+				chars[pos ..].starts_with(&p)
+			});
+
+			match found {
+				Some(p) => {
+					pos += p.chars().count();
+					p.to_string()
+				}
+
+				None => {
+					errors.push(Diagnostic::error(format!("invalid character '{}'",
+						c.escape_debug()), Span::point(pos)).with_code(diagnostics::INVALID_CHAR)
+						.with_note("the grammar doesn't have any terminal that starts with it"));
+					pos += 1;
+					continue;
+				}
+			}
+		};
+
+		let text = chars[start .. pos].iter().collect();
+		tokens.push(Token { kind, text, span: Span::new(start, pos) });
+	}
+
+	tokens.push(Token { kind: EOF.into(), text: "".into(), span: Span::new(pos, pos) });
+
+	if errors.is_empty() {
+		Ok(tokens)
+	} else {
+		Err(errors)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unexpected_token_messages() {
+		let b = Token { kind: "b".into(), text: "b".into(), span: Span::point(0) };
+		let message = |expected: &[&str]| unexpected_token(&b, expected.iter().copied()).message;
+
+		assert_eq!(message(&[]), "unexpected 'b'");
+		assert_eq!(message(&[EOF]), "expected the end of the input, but found 'b'");
+		assert_eq!(message(&["a", "c"]), "expected a or c, but found 'b'");
+		assert_eq!(message(&["a", "c", EOF]),
+			"expected one of a, c, the end of the input, but found 'b'");
+	}
+}
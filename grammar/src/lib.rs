@@ -0,0 +1,39 @@
+/*
+The other parsing examples each have a parser written by hand for one particular language. That's
+how most real compilers do it, but it means that changing the language means changing the code.
+
+This crate goes the other way: you write down a *grammar*, and it makes the parser for you. A
+grammar is written the same way as in class:
+
+	Exp    -> Term '+' Exp | Term
+	Term   -> num | id | '(' Exp ')'
+
+Each rule has a *nonterminal* on the left of the arrow, and one or more *alternatives* on the
+right, separated by `|`. Every name that has a rule is a nonterminal; everything else (like `num`,
+or anything in quotes) is a *terminal*, which is a kind of token. An empty alternative (written
+`ε`, or just left empty) matches nothing.
+
+Tools that do this are called *parser generators*. Famous ones like yacc, bison, and ANTLR output
+source code for the parser. This one is simpler: it *interprets* the grammar, following the rules
+at runtime the same way a hand-written recursive-descent parser would follow its code.
+*/
+
+mod descent;
+mod diagram;
+mod dsl;
+mod earley;
+mod grammar;
+mod lexer;
+mod lr;
+mod sets;
+mod tree;
+
+pub use crate::descent::*;
+pub use crate::diagram::*;
+pub use crate::dsl::*;
+pub use crate::earley::*;
+pub use crate::grammar::*;
+pub use crate::lexer::*;
+pub use crate::lr::*;
+pub use crate::sets::*;
+pub use crate::tree::*;
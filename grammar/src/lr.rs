@@ -0,0 +1,533 @@
+use std::collections::{ BTreeMap, HashMap };
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Severity };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// LR(0) items and states
+// ------------------------------------------------------------------------------------------------
+
+/*
+An LR parser is a *bottom-up* parser, like parsing_math's. It reads tokens onto a stack (that's
+*shifting*), and whenever the top of the stack matches the right side of a rule, it can replace
+them with the rule's nonterminal (that's *reducing*). The hard part is knowing *when* to reduce,
+and which rule to reduce by. parsing_math decides that with precedence, written by hand. An LR
+parser decides it with a table, which is made from the grammar.
+
+The table is built from *items*. An item is a production with a dot somewhere in it, which marks
+how much of it the parser has seen so far:
+
+	Exp -> Exp • '+' Term
+
+means "I might be in the middle of an `Exp '+' Term`, and I've seen the Exp so far." When the dot
+gets to the end, the whole right side has been seen, and the parser can reduce.
+
+A *state* is a set of items: all the things the parser might be in the middle of at some point.
+If an item has the dot right before a nonterminal, like `Exp -> • Term`, then the parser might
+also be at the start of anything that nonterminal could be, so items for all of the nonterminal's
+productions, with the dot at the start, go in the state too. That's called the *closure*.
+
+Moving the dot past a symbol X in every item that has X after the dot (and taking the closure of
+that) gives the state the parser goes to after seeing an X. That's *goto*. Starting from the
+closure of `S' -> • S` and following every goto gives every state, and that's the *LR(0)
+automaton*. (S' is a new start symbol, so that the parser knows it's done when it reduces by the
+first rule. That's called *augmenting* the grammar.)
+*/
+
+// A production with a dot in it. `prod` is an index into Lr0Automaton::productions, and `dot`
+// is how many symbols of the right side are before the dot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LrItem {
+	pub prod: usize,
+	pub dot:  usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrState {
+	// the kernel items come first, then the ones added by the closure.
+	pub items:       Vec<LrItem>,
+	pub transitions: Vec<(Symbol, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lr0Automaton {
+	// the augmented grammar's productions. productions[0] is S' -> S.
+	pub productions: Vec<Production>,
+	pub states:      Vec<LrState>,
+}
+
+impl Lr0Automaton {
+	pub fn new(grammar: &Grammar) -> Self {
+		let start = Production { lhs: format!("{}'", grammar.start), rhs: vec![n(&grammar.start)] };
+		let productions = Some(start).into_iter().chain(grammar.productions.iter().cloned());
+		let mut ret = Lr0Automaton { productions: productions.collect(), states: vec![] };
+
+		// states are found by their kernels, since the closure is the same for the same kernel.
+		let mut known = HashMap::new();
+		let first = vec![LrItem { prod: 0, dot: 0 }];
+		known.insert(first.clone(), 0);
+		ret.states.push(LrState { items: ret.closure(first), transitions: vec![] });
+
+		// the states vector is also the worklist: each new state gets processed when we get to it.
+		let mut i = 0;
+
+		while i < ret.states.len() {
+			for sym in ret.symbols_after_dot(i) {
+				let kernel = ret.states[i].items.iter()
+					.filter(|&&item| ret.next_symbol(item) == Some(&sym))
+					.map(|&item| LrItem { prod: item.prod, dot: item.dot + 1 })
+					.collect::<Vec<_>>();
+
+				let mut key = kernel.clone();
+				key.sort();
+
+				let target = match known.get(&key) {
+					Some(&target) => target,
+					None => {
+						let target = ret.states.len();
+						known.insert(key, target);
+						let items = ret.closure(kernel);
+						ret.states.push(LrState { items, transitions: vec![] });
+						target
+					}
+				};
+
+				ret.states[i].transitions.push((sym, target));
+			}
+
+			i += 1;
+		}
+
+		ret
+	}
+
+	// The symbol right after the dot, or None if the dot is at the end.
+	pub fn next_symbol(&self, item: LrItem) -> Option<&Symbol> {
+		self.productions[item.prod].rhs.get(item.dot)
+	}
+
+	pub fn is_complete(&self, item: LrItem) -> bool {
+		self.next_symbol(item).is_none()
+	}
+
+	// Shows an item like `Exp -> Exp • '+' Term`.
+	pub fn item_string(&self, item: LrItem) -> String {
+		let prod = &self.productions[item.prod];
+		let mut syms = prod.rhs.iter().map(|sym| sym.to_string()).collect::<Vec<_>>();
+		syms.insert(item.dot, "•".into());
+		format!("{} -> {}", prod.lhs, syms.join(" "))
+	}
+
+	fn closure(&self, kernel: Vec<LrItem>) -> Vec<LrItem> {
+		let mut items = kernel;
+		let mut i = 0;
+
+		while i < items.len() {
+			if let Some(Symbol::Nonterminal(nt)) = self.next_symbol(items[i]) {
+				for (prod, p) in self.productions.iter().enumerate() {
+					let item = LrItem { prod, dot: 0 };
+
+					if p.lhs == *nt && !items.contains(&item) {
+						items.push(item);
+					}
+				}
+			}
+
+			i += 1;
+		}
+
+		items
+	}
+
+	// every symbol that comes after a dot in a state, in the order they first appear.
+	fn symbols_after_dot(&self, state: usize) -> Vec<Symbol> {
+		let mut ret = vec![];
+
+		for &item in &self.states[state].items {
+			if let Some(sym) = self.next_symbol(item) {
+				if !ret.contains(sym) {
+					ret.push(sym.clone());
+				}
+			}
+		}
+
+		ret
+	}
+}
+
+impl Display for Lr0Automaton {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (i, state) in self.states.iter().enumerate() {
+			writeln!(f, "State {}:", i)?;
+
+			for &item in &state.items {
+				writeln!(f, "    {}", self.item_string(item))?;
+			}
+
+			for (sym, target) in &state.transitions {
+				writeln!(f, "    on {} go to {}", sym, target)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// SLR tables
+// ------------------------------------------------------------------------------------------------
+
+/*
+The parsing table has two parts:
+
+- ACTION[state, terminal] says what to do when the next token is that terminal: shift it and go
+  to some state, reduce by some production, or *accept* (the whole input was parsed).
+- GOTO[state, nonterminal] says which state to go to after reducing to that nonterminal.
+
+The shifts and gotos come straight from the automaton's transitions. The reduces are the tricky
+part. If a state has a complete item like `Term -> Factor •`, when should it reduce? An LR(0)
+parser reduces no matter what the next token is, but that doesn't work for most grammars. An
+*SLR* (simple LR) parser only reduces if the next token is in FOLLOW(Term), since if it isn't,
+there's no way a Term could end here.
+
+If some entry in ACTION gets two different actions, that's a *conflict*, and the grammar isn't
+SLR. Like yacc and bison, the table keeps the first action it got (shifts are added before
+reduces, so shifts win), but the conflicts are kept so they can be reported.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+	Shift(usize),  // push the token, and go to this state.
+	Reduce(usize), // pop the right side of this production, and push its left side.
+	Accept,
+}
+
+impl Display for Action {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Action::Shift(state) => write!(f, "s{}", state),
+			Action::Reduce(prod) => write!(f, "r{}", prod),
+			Action::Accept       => write!(f, "acc"),
+		}
+	}
+}
+
+// Two or more actions that ended up in the same ACTION entry. The first one is what's in the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+	pub state:    usize,
+	pub terminal: String,
+	pub actions:  Vec<Action>,
+}
+
+impl Conflict {
+	pub fn is_shift_reduce(&self) -> bool {
+		self.actions.iter().any(|a| matches!(a, Action::Shift(..)))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlrTable {
+	pub automaton: Lr0Automaton,
+	pub action:    Vec<BTreeMap<String, Action>>,
+	pub goto:      Vec<BTreeMap<String, usize>>,
+	pub conflicts: Vec<Conflict>,
+
+	// the column orders for the table.
+	terminals:    Vec<String>,
+	nonterminals: Vec<String>,
+}
+
+impl SlrTable {
+	pub fn new(grammar: &Grammar) -> Self {
+		let automaton = Lr0Automaton::new(grammar);
+		let sets = GrammarSets::new(grammar);
+		let num_states = automaton.states.len();
+
+		let terminals = grammar.terminals().into_iter().chain(Some(EOF)).map(String::from);
+		let nonterminals = grammar.nonterminals().into_iter().map(String::from);
+
+		let mut ret = SlrTable {
+			action:       vec![BTreeMap::new(); num_states],
+			goto:         vec![BTreeMap::new(); num_states],
+			conflicts:    vec![],
+			terminals:    terminals.collect(),
+			nonterminals: nonterminals.collect(),
+			automaton,
+		};
+
+		for s in 0 .. num_states {
+			for (sym, target) in ret.automaton.states[s].transitions.clone() {
+				match sym {
+					Symbol::Terminal(t)     => ret.add_action(s, &t, Action::Shift(target)),
+					Symbol::Nonterminal(nt) => { ret.goto[s].insert(nt, target); }
+				}
+			}
+
+			for item in ret.automaton.states[s].items.clone() {
+				if !ret.automaton.is_complete(item) {
+					continue;
+				}
+
+				if item.prod == 0 {
+					ret.add_action(s, EOF, Action::Accept);
+				} else {
+					let lhs = &ret.automaton.productions[item.prod].lhs;
+
+					for t in sets.follow[lhs].clone() {
+						ret.add_action(s, &t, Action::Reduce(item.prod));
+					}
+				}
+			}
+		}
+
+		ret
+	}
+
+	fn add_action(&mut self, state: usize, terminal: &str, action: Action) {
+		let old = match self.action[state].get(terminal) {
+			Some(&old) if old != action => old,
+			Some(..) => return,
+			None => {
+				self.action[state].insert(terminal.into(), action);
+				return;
+			}
+		};
+
+		let existing = self.conflicts.iter_mut()
+			.find(|c| c.state == state && c.terminal == terminal);
+
+		match existing {
+			Some(c) if !c.actions.contains(&action) => c.actions.push(action),
+			Some(..) => {}
+			None => self.conflicts.push(Conflict {
+				state,
+				terminal: terminal.into(),
+				actions: vec![old, action],
+			}),
+		}
+	}
+
+	// An error for each conflict, with the items in that state, so you can see where it came from.
+	pub fn conflict_errors(&self) -> Vec<Diagnostic> {
+		self.conflicts.iter().map(|c| {
+			let kind = if c.is_shift_reduce() { "shift/reduce" } else { "reduce/reduce" };
+			let actions = c.actions.iter().map(|&a| self.describe(a)).collect::<Vec<_>>();
+
+			let mut d = Diagnostic::new(Severity::Error, format!("{} conflict in state {} on {}",
+				kind, c.state, terminal_name(&c.terminal)), None)
+				.with_code(diagnostics::LR_CONFLICT)
+				.with_note(format!("the parser could {}", actions.join(", or ")));
+
+			for &item in &self.automaton.states[c.state].items {
+				let item = self.automaton.item_string(item);
+				d = d.with_note(format!("state {} has: {}", c.state, item));
+			}
+
+			d
+		}).collect()
+	}
+
+	// an action, said in words.
+	fn describe(&self, action: Action) -> String {
+		match action {
+			Action::Shift(s)  => format!("shift and go to state {}", s),
+			Action::Reduce(p) => format!("reduce by {}", self.automaton.productions[p]),
+			Action::Accept    => "accept".into(),
+		}
+	}
+}
+
+// shows the numbered productions (for the r's), and then the table, with conflicts like "s4/r2".
+impl Display for SlrTable {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (i, p) in self.automaton.productions.iter().enumerate() {
+			writeln!(f, "{:>3}: {}", i, p)?;
+		}
+
+		writeln!(f)?;
+
+		let mut rows = vec![];
+
+		let header = Some("State".to_string()).into_iter()
+			.chain(self.terminals.iter().map(|t| terminal_name(t)))
+			.chain(Some("|".into()))
+			.chain(self.nonterminals.iter().cloned());
+		rows.push(header.collect::<Vec<_>>());
+
+		for s in 0 .. self.action.len() {
+			let actions = self.terminals.iter().map(|t| {
+				match self.conflicts.iter().find(|c| c.state == s && c.terminal == *t) {
+					Some(c) =>
+						c.actions.iter().map(|a| a.to_string()).collect::<Vec<_>>().join("/"),
+					None    => self.action[s].get(t).map(|a| a.to_string()).unwrap_or_default(),
+				}
+			});
+
+			let gotos = self.nonterminals.iter()
+				.map(|nt| self.goto[s].get(nt).map(|g| g.to_string()).unwrap_or_default());
+
+			let row = Some(s.to_string()).into_iter().chain(actions).chain(Some("|".into()))
+				.chain(gotos);
+			rows.push(row.collect());
+		}
+
+		let widths = (0 .. rows[0].len())
+			.map(|col| rows.iter().map(|r| r[col].chars().count()).max().unwrap())
+			.collect::<Vec<_>>();
+
+		for row in &rows {
+			let line = row.iter().zip(&widths).map(|(cell, &w)| format!("{:<w$}", cell, w = w))
+				.collect::<Vec<_>>().join(" ");
+			writeln!(f, "{}", line.trim_end())?;
+		}
+
+		Ok(())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The shift-reduce parser
+// ------------------------------------------------------------------------------------------------
+
+/*
+With the table, the parser itself is short. It keeps a stack of states (and, alongside it, a
+stack of the trees for the symbols it's seen), and repeats:
+
+- look up ACTION[top state, next token].
+- shift: push the token and the new state.
+- reduce by A -> β: pop one entry for each symbol in β, then look up GOTO[new top state, A], and
+  push A and that state.
+- accept: done!
+- empty: syntax error. Every terminal that *does* have an action in this state could have gone
+  there, which makes for a good error message.
+
+Turn on `trace` to see each step as it happens.
+*/
+
+pub struct SlrParser<'g> {
+	grammar:   &'g Grammar,
+	table:     SlrTable,
+	// if true, every step is printed, with the stack and the rest of the input.
+	pub trace: bool,
+}
+
+impl<'g> SlrParser<'g> {
+	// Fails if the grammar has any of the problems Grammar::check() looks for, or isn't SLR.
+	pub fn new(grammar: &'g Grammar) -> Result<Self, Vec<Diagnostic>> {
+		let errs = grammar.check();
+
+		if !errs.is_empty() {
+			return Err(errs);
+		}
+
+		let table = SlrTable::new(grammar);
+
+		if table.conflicts.is_empty() {
+			Ok(SlrParser { grammar, table, trace: false })
+		} else {
+			Err(table.conflict_errors())
+		}
+	}
+
+	pub fn table(&self) -> &SlrTable {
+		&self.table
+	}
+
+	// Tokenizes the source with tokenize(), then parses it.
+	pub fn parse_str(&self, src: &str) -> Result<ParseTree, Vec<Diagnostic>> {
+		let tokens = tokenize(self.grammar, src)?;
+		self.parse(&tokens).map_err(|e| vec![e])
+	}
+
+	// Parses the tokens, which must end with an EOF token, as the grammar's start symbol.
+	pub fn parse(&self, tokens: &[Token]) -> Result<ParseTree, Diagnostic> {
+		let mut states = vec![0];
+		let mut trees: Vec<ParseTree> = vec![];
+		let mut pos = 0;
+
+		if self.trace {
+			println!("{:<40} {:<24} Action", "Stack", "Input");
+		}
+
+		loop {
+			let state = *states.last().unwrap();
+			let token = &tokens[pos];
+
+			let action = match self.table.action[state].get(&token.kind) {
+				Some(&action) => action,
+				None => {
+					let expected = self.table.action[state].keys().map(String::as_str);
+					return Err(unexpected_token(token, expected));
+				}
+			};
+
+			self.trace_step(&states, &trees, &tokens[pos ..], action);
+
+			match action {
+				Action::Shift(next) => {
+					states.push(next);
+					trees.push(ParseTree::Leaf(token.clone()));
+					pos += 1;
+				}
+
+				Action::Reduce(prod) => {
+					let prod = &self.table.automaton.productions[prod];
+					let len = trees.len() - prod.rhs.len();
+					let children = trees.split_off(len);
+					states.truncate(len + 1);
+
+					let top = *states.last().unwrap();
+					states.push(self.table.goto[top][&prod.lhs]);
+					trees.push(ParseTree::Node { name: prod.lhs.clone(), children });
+				}
+
+				Action::Accept => return Ok(trees.pop().unwrap()),
+			}
+		}
+	}
+
+	fn trace_step(&self, states: &[usize], trees: &[ParseTree], input: &[Token], action: Action) {
+		if !self.trace {
+			return;
+		}
+
+		// the stack is shown like "0 Exp 1 '+' 6": each state, with the symbol that got there.
+		let mut stack = states[0].to_string();
+
+		for (tree, state) in trees.iter().zip(&states[1 ..]) {
+			let sym = match tree {
+				ParseTree::Leaf(token)       => terminal_name(&token.kind),
+				ParseTree::Node { name, .. } => name.clone(),
+			};
+
+			stack += &format!(" {} {}", sym, state);
+		}
+
+		let input = input.iter().map(|t| if t.kind == EOF { EOF } else { &t.text })
+			.collect::<Vec<_>>().join(" ");
+
+		let action = match action {
+			Action::Shift(s)  => format!("shift, go to {}", s),
+			Action::Reduce(p) => format!("reduce {}", self.table.automaton.productions[p]),
+			Action::Accept    => "accept".into(),
+		};
+
+		println!("{:<40} {:<24} {}", stack, input, action);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// S can't match anything, so the parser used to reduce `A -> ε` forever on any input.
+	#[test]
+	fn unproductive_nonterminals_are_rejected() {
+		let grammar = parse_grammar("S -> A S\nA -> ε").unwrap();
+		let errs = SlrParser::new(&grammar).err().unwrap();
+		assert!(errs[0].message.contains("'S' can't match anything"));
+	}
+}
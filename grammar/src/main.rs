@@ -0,0 +1,269 @@
+use std::process::exit;
+
+use diagnostics::{ render_all, render_all_colored, ColorChoice, Diagnostic, SourceFile };
+use grammar::*;
+
+// `cargo run` shows some examples. `cargo run -- lang.grammar input.txt` reads a grammar from a
+// file, and parses another file with it, so you can try out your own grammars. `cargo run -- sets
+// lang.grammar` shows the nullable, FIRST, and FOLLOW sets for a grammar. `cargo run -- slr
+// lang.grammar` shows its SLR parsing table, and if you give it an input file too, it parses it
+// with the SLR parser and shows every step. `cargo run -- earley lang.grammar input.txt` parses
+// with the Earley parser, which works with any grammar, and explains any ambiguity it finds.
+// `cargo run -- ebnf lang.grammar` rewrites a grammar in EBNF, and `cargo run -- railroad
+// lang.grammar > lang.svg` draws railroad diagrams of it.
+
+const USAGE: &str = "usage: grammar [<file.grammar> <input file>] [--color=always|never|auto]
+       grammar sets <file.grammar>
+       grammar slr <file.grammar> [<input file>]
+       grammar earley <file.grammar> <input file>
+       grammar ebnf <file.grammar>
+       grammar railroad <file.grammar>";
+
+fn main() {
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+	let args = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+
+	let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		[]               => { demo(); Ok(()) }
+		["sets", path]   => read_grammar(path, color).map(|g| print!("{}", GrammarSets::new(&g))),
+		["slr", path]    => read_grammar(path, color).map(|g| print!("{}", SlrTable::new(&g))),
+		["slr", path, input] => slr_file(path, input, color),
+		["earley", path, input] => earley_file(path, input, color),
+		["ebnf", path]   => read_grammar(path, color).map(|g| print!("{}", Ebnf::new(&g))),
+		["railroad", path] => read_grammar(path, color)
+			.map(|g| print!("{}", Ebnf::new(&g).to_svg())),
+		[path, input]    => parse_file(path, input, color),
+		_                => {
+			eprintln!("{}", USAGE);
+			exit(2);
+		}
+	};
+
+	// 1 means the grammar or input had errors, and 2 means a file couldn't be read.
+	if let Err(code) = result {
+		exit(code);
+	}
+}
+
+fn read_grammar(path: &str, color: ColorChoice) -> Result<Grammar, i32> {
+	let text = read(path)?;
+
+	parse_grammar(&text).map_err(|errs| {
+		eprint!("{}", render_all_colored(&errs, &SourceFile::new(path, &text), color));
+		1
+	})
+}
+
+fn parse_file(grammar_path: &str, input_path: &str, color: ColorChoice) -> Result<(), i32> {
+	let grammar = read_grammar(grammar_path, color)?;
+	let input = read(input_path)?;
+
+	let show = |errs: &[Diagnostic], path: &str, text: &str| {
+		eprint!("{}", render_all_colored(errs, &SourceFile::new(path, text), color));
+		1
+	};
+
+	let parser = RecursiveDescent::new(&grammar).map_err(|e| show(&e, grammar_path, ""))?;
+	let tree = parser.parse_str(&input).map_err(|e| show(&e, input_path, &input))?;
+	print!("{}", tree);
+	Ok(())
+}
+
+fn slr_file(grammar_path: &str, input_path: &str, color: ColorChoice) -> Result<(), i32> {
+	let grammar = read_grammar(grammar_path, color)?;
+	let input = read(input_path)?;
+
+	let show = |errs: &[Diagnostic], path: &str, text: &str| {
+		eprint!("{}", render_all_colored(errs, &SourceFile::new(path, text), color));
+		1
+	};
+
+	let mut parser = SlrParser::new(&grammar).map_err(|e| show(&e, grammar_path, ""))?;
+	parser.trace = true;
+	parser.parse_str(&input).map_err(|e| show(&e, input_path, &input))?;
+	Ok(())
+}
+
+fn earley_file(grammar_path: &str, input_path: &str, color: ColorChoice) -> Result<(), i32> {
+	let grammar = read_grammar(grammar_path, color)?;
+	let input = read(input_path)?;
+
+	let show = |errs: &[Diagnostic], path: &str, text: &str| {
+		eprint!("{}", render_all_colored(errs, &SourceFile::new(path, text), color));
+		1
+	};
+
+	let parser = EarleyParser::new(&grammar).map_err(|e| show(&e, grammar_path, ""))?;
+
+	match parser.parse_str(&input).map_err(|e| show(&e, input_path, &input))? {
+		EarleyParse::Tree(tree)        => print!("{}", tree),
+		EarleyParse::Ambiguous(forest) => print!("{}\n{}", forest.report(), forest.first_tree()),
+	}
+
+	Ok(())
+}
+
+fn read(path: &str) -> Result<String, i32> {
+	std::fs::read_to_string(path).map_err(|e| {
+		eprintln!("error: could not read '{}': {}", path, e);
+		2
+	})
+}
+
+fn demo() {
+	text_demo();
+	builder_demo();
+	bad_grammar_demo();
+	sets_demo();
+	slr_demo();
+	earley_demo();
+	ebnf_demo();
+}
+
+fn text_demo() {
+	let src = "
+		Exp    -> Term '+' Exp | Term
+		Term   -> Factor '*' Term | Factor
+		Factor -> num | id | '(' Exp ')'
+	";
+
+	let grammar = parse_grammar(src).unwrap();
+	println!("{}", grammar);
+
+	let parser = RecursiveDescent::new(&grammar).unwrap();
+
+	// the last one has a mistake.
+	for input in &["2 * (x + 1)", "2 * (x + )"] {
+		println!("{}\n", input);
+		show_parse(&parser, input);
+	}
+}
+
+fn builder_demo() {
+	// parsing_lisp's language, without writing a parser for it.
+	let grammar = Grammar::new("Exp")
+		.rule("Exp",  vec![t("id")])
+		.rule("Exp",  vec![t("num")])
+		.rule("Exp",  vec![t("("), n("List"), t(")")])
+		.rule("List", vec![n("Exp"), n("List")])
+		.rule("List", vec![]);
+
+	println!("{}", grammar);
+	show_parse(&RecursiveDescent::new(&grammar).unwrap(), "(add 3 (sub x y))");
+
+	// the builder can make grammars with mistakes in them that parse_grammar() can't.
+	let broken = Grammar::new("Exp").rule("Exp", vec![t("("), n("Lsit"), t(")")]);
+	let errs = RecursiveDescent::new(&broken).err().unwrap();
+	println!("{}", render_all(&errs, &SourceFile::new("<grammar>", "")));
+}
+
+fn bad_grammar_demo() {
+	// left recursion is fine in a grammar, but recursive descent can't handle it.
+	let grammar = parse_grammar("Exp -> Exp '+' num | num").unwrap();
+	println!("{}", grammar);
+	show_parse(&RecursiveDescent::new(&grammar).unwrap(), "1 + 2");
+
+	// and a grammar that's written wrong.
+	let src = "Exp Term '+' Exp\nTerm -> '(' Exp ')\n| num -> id";
+	println!("{}\n", src);
+	print!("{}", render_all(&parse_grammar(src).unwrap_err(),
+		&SourceFile::new("<grammar>", src)));
+}
+
+fn show_parse(parser: &RecursiveDescent, input: &str) {
+	show_result(parser.parse_str(input), input);
+}
+
+fn show_result(result: Result<ParseTree, Vec<Diagnostic>>, input: &str) {
+	match result {
+		Ok(tree)  => println!("{}", tree),
+		Err(errs) => println!("{}", render_all(&errs, &SourceFile::new("<input>", input))),
+	}
+}
+
+fn sets_demo() {
+	// the expression grammar from text_demo, rewritten the way LL(1) parsers like it, with the
+	// repetition done by nullable "tail" nonterminals.
+	let grammar = parse_grammar("
+		Exp        -> Term ExpTail
+		ExpTail    -> '+' Term ExpTail | ε
+		Term       -> Factor TermTail
+		TermTail   -> '*' Factor TermTail | ε
+		Factor     -> num | id | '(' Exp ')'
+	").unwrap();
+
+	println!("{}\n{}", grammar, GrammarSets::new(&grammar));
+}
+
+fn slr_demo() {
+	// LR parsers are fine with left recursion, so this is the grammar from text_demo, but with
+	// left-associative operators.
+	let grammar = parse_grammar("
+		Exp    -> Exp '+' Term | Term
+		Term   -> Term '*' Factor | Factor
+		Factor -> num | id | '(' Exp ')'
+	").unwrap();
+
+	let mut parser = SlrParser::new(&grammar).unwrap();
+	println!("{}\n{}", parser.table().automaton, parser.table());
+
+	parser.trace = true;
+	show_result(parser.parse_str("2 * (x + 1)"), "2 * (x + 1)");
+	show_result(parser.parse_str("1 + * 2"), "1 + * 2");
+
+	// an ambiguous grammar always has conflicts.
+	let grammar = parse_grammar("Exp -> Exp '+' Exp | Exp '*' Exp | num").unwrap();
+	let errs = SlrParser::new(&grammar).err().unwrap();
+	println!("{}", SlrTable::new(&grammar));
+	println!("{}", render_all(&errs, &SourceFile::new("<grammar>", "")));
+}
+
+fn earley_demo() {
+	// the ambiguous grammar that the SLR parser couldn't handle.
+	let grammar = parse_grammar("Exp -> Exp '+' Exp | Exp '*' Exp | num").unwrap();
+	let mut parser = EarleyParser::new(&grammar).unwrap();
+
+	for input in &["1 + 2 * 3", "1 + 2 + 3 + 4"] {
+		match parser.parse_str(input).unwrap() {
+			EarleyParse::Tree(tree)        => println!("{}", tree),
+			EarleyParse::Ambiguous(forest) => println!("{}", forest.report()),
+		}
+	}
+
+	// left recursion and ε are fine too. with tracing on, it shows the chart.
+	let grammar = parse_grammar("
+		List  -> List Item | ε
+		Item  -> id | num
+	").unwrap();
+	parser = EarleyParser::new(&grammar).unwrap();
+	parser.trace = true;
+
+	match parser.parse_str("x 1 y") {
+		Ok(EarleyParse::Tree(tree)) => println!("\n{}", tree),
+		Ok(EarleyParse::Ambiguous(forest)) => println!("\n{}", forest.report()),
+		Err(errs) => println!("{}", render_all(&errs, &SourceFile::new("<input>", "x 1 y"))),
+	}
+}
+
+fn ebnf_demo() {
+	// the grammars from sets_demo and slr_demo are the same language, and in EBNF, the tails and
+	// the left recursion both turn into repetition.
+	let ll = parse_grammar("
+		Exp        -> Term ExpTail
+		ExpTail    -> '+' Term ExpTail | ε
+		Term       -> Factor TermTail
+		TermTail   -> '*' Factor TermTail | ε
+		Factor     -> num | id | '(' Exp ')'
+	").unwrap();
+
+	let lr = parse_grammar("
+		Exp    -> Exp '+' Term | Term
+		Term   -> Term '*' Factor | Factor
+		Factor -> num | id | '(' Exp ')' | '-' Factor
+	").unwrap();
+
+	println!("{}\n{}", Ebnf::new(&ll), Ebnf::new(&lr));
+}
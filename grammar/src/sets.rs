@@ -0,0 +1,193 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Nullable, FIRST, and FOLLOW
+// ------------------------------------------------------------------------------------------------
+
+/*
+These three things are what parser generators need to know about a grammar before they can make a
+parser that doesn't have to backtrack. For a nonterminal A:
+
+- A is *nullable* if it can match nothing at all (the empty string, ε).
+- FIRST(A) is the set of terminals that can come first in something A matches. If the next token
+  isn't in FIRST(A), there's no point trying to parse an A.
+- FOLLOW(A) is the set of terminals that can come right after an A, anywhere in any program. $
+  (the end of the input) is in FOLLOW(start), since the start symbol is followed by nothing.
+
+FOLLOW matters because of nullable nonterminals: if A can match nothing, then the next token
+might not be part of A at all, but something that comes after it.
+
+Some books put ε in FIRST(A) when A is nullable. Here, nullable is kept separately instead, so
+that FIRST only ever has real terminals in it.
+
+All three are computed the same way: start with nothing, and apply the rules over and over until
+nothing changes. Each rule can only ever *add* things, and there are only so many terminals, so it
+has to stop eventually. (That's a *fixed point*, just like liveness in the ir crate.)
+
+	nullable: A is nullable if it has a production A -> X1 X2 ... Xn where every Xi is nullable.
+	          (That includes A -> ε, where there aren't any Xs.)
+	FIRST:    for every production A -> X1 X2 ... Xn, FIRST(A) gets FIRST(X1). If X1 is nullable,
+	          it also gets FIRST(X2), and so on until one isn't nullable. (FIRST of a terminal is
+	          just the terminal.)
+	FOLLOW:   for every production A -> ... B β (where B is a nonterminal and β is whatever comes
+	          after it), FOLLOW(B) gets FIRST(β). If all of β is nullable, then whatever follows
+	          A can also follow B, so FOLLOW(B) gets FOLLOW(A) too.
+*/
+
+// The grammar should pass Grammar::check() first. (If it doesn't, nonterminals with no rules are
+// treated as matching nothing, not even ε.)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarSets {
+	pub nullable: BTreeSet<String>,
+	pub first:    BTreeMap<String, BTreeSet<String>>,
+	pub follow:   BTreeMap<String, BTreeSet<String>>,
+
+	// the nonterminals in the grammar's order, for the table.
+	order: Vec<String>,
+}
+
+impl GrammarSets {
+	pub fn new(grammar: &Grammar) -> Self {
+		let order = grammar.nonterminals().into_iter().map(String::from).collect::<Vec<_>>();
+		let empty = order.iter().map(|nt| (nt.clone(), BTreeSet::new()));
+		let empty = empty.collect::<BTreeMap<_, _>>();
+
+		let mut ret = GrammarSets {
+			nullable: BTreeSet::new(),
+			first:    empty.clone(),
+			follow:   empty,
+			order,
+		};
+
+		ret.compute_nullable(grammar);
+		ret.compute_first(grammar);
+		ret.compute_follow(grammar);
+		ret
+	}
+
+	pub fn is_nullable(&self, nt: &str) -> bool {
+		self.nullable.contains(nt)
+	}
+
+	// Can this whole sequence of symbols match nothing?
+	pub fn is_nullable_seq(&self, syms: &[Symbol]) -> bool {
+		syms.iter().all(|sym| match sym {
+			Symbol::Terminal(..)    => false,
+			Symbol::Nonterminal(nt) => self.is_nullable(nt),
+		})
+	}
+
+	// The terminals that can come first in something this sequence of symbols matches.
+	pub fn first_of(&self, syms: &[Symbol]) -> BTreeSet<String> {
+		let mut ret = BTreeSet::new();
+
+		for sym in syms {
+			match sym {
+				Symbol::Terminal(t) => {
+					ret.insert(t.clone());
+					break;
+				}
+
+				Symbol::Nonterminal(nt) => {
+					ret.extend(self.first.get(nt).into_iter().flatten().cloned());
+
+					if !self.is_nullable(nt) {
+						break;
+					}
+				}
+			}
+		}
+
+		ret
+	}
+
+	fn compute_nullable(&mut self, grammar: &Grammar) {
+		let mut changed = true;
+
+		while changed {
+			changed = false;
+
+			for p in &grammar.productions {
+				if !self.is_nullable(&p.lhs) && self.is_nullable_seq(&p.rhs) {
+					self.nullable.insert(p.lhs.clone());
+					changed = true;
+				}
+			}
+		}
+	}
+
+	fn compute_first(&mut self, grammar: &Grammar) {
+		let mut changed = true;
+
+		while changed {
+			changed = false;
+
+			for p in &grammar.productions {
+				let first = self.first_of(&p.rhs);
+				changed |= add_all(self.first.entry(p.lhs.clone()).or_default(), first);
+			}
+		}
+	}
+
+	fn compute_follow(&mut self, grammar: &Grammar) {
+		self.follow.entry(grammar.start.clone()).or_default().insert(EOF.into());
+		let mut changed = true;
+
+		while changed {
+			changed = false;
+
+			for p in &grammar.productions {
+				for (i, sym) in p.rhs.iter().enumerate() {
+					if let Symbol::Nonterminal(b) = sym {
+						let rest = &p.rhs[i + 1 ..];
+						let mut new = self.first_of(rest);
+
+						if self.is_nullable_seq(rest) {
+							new.extend(self.follow.get(&p.lhs).into_iter().flatten().cloned());
+						}
+
+						changed |= add_all(self.follow.entry(b.clone()).or_default(), new);
+					}
+				}
+			}
+		}
+	}
+}
+
+// puts everything from `new` into `set`, and says whether that changed it.
+fn add_all(set: &mut BTreeSet<String>, new: BTreeSet<String>) -> bool {
+	let before = set.len();
+	set.extend(new);
+	set.len() != before
+}
+
+// shows the sets as a table, with one row per nonterminal.
+impl Display for GrammarSets {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		let show = |set: &BTreeSet<String>| {
+			set.iter().map(|t| terminal_name(t)).collect::<Vec<_>>().join(" ")
+		};
+
+		let rows = self.order.iter().map(|nt| {
+			let nullable = if self.is_nullable(nt) { "yes" } else { "no" };
+			[nt.clone(), nullable.into(), show(&self.first[nt]), show(&self.follow[nt])]
+		});
+
+		let header = ["Nonterminal", "Nullable", "FIRST", "FOLLOW"].map(String::from);
+		let rows = Some(header).into_iter().chain(rows).collect::<Vec<_>>();
+
+		let widths = (0 .. 4).map(|col| rows.iter().map(|r| r[col].chars().count()).max().unwrap())
+			.collect::<Vec<_>>();
+
+		for row in &rows {
+			let line = row.iter().zip(&widths).map(|(cell, &w)| format!("{:<w$}", cell, w = w))
+				.collect::<Vec<_>>().join("  ");
+			writeln!(f, "{}", line.trim_end())?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,60 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::Span;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Parse trees
+// ------------------------------------------------------------------------------------------------
+
+/*
+A hand-written parser can build an AST with exactly the node types the language needs. A parser
+made from a grammar doesn't know what any of the rules *mean*, so all it can build is a *parse
+tree* (also called a *concrete syntax tree*): one Node for every nonterminal that was parsed, with
+its name, and one Leaf for every token. Every token is in there, even the parentheses.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+	Node { name: String, children: Vec<ParseTree> },
+	Leaf(Token),
+}
+
+impl ParseTree {
+	// The span of all the tokens in the tree. A node that matched ε has no tokens, so no span.
+	pub fn span(&self) -> Option<Span> {
+		match self {
+			ParseTree::Leaf(token) => Some(token.span),
+			ParseTree::Node { children, .. } =>
+				children.iter().filter_map(|c| c.span()).reduce(|a, b| a.to(b)),
+		}
+	}
+
+	// All the tokens in the tree, in order.
+	pub fn tokens(&self) -> Vec<&Token> {
+		match self {
+			ParseTree::Leaf(token) => vec![token],
+			ParseTree::Node { children, .. } => children.iter().flat_map(|c| c.tokens()).collect(),
+		}
+	}
+
+	fn fmt_indented(&self, f: &mut Formatter, depth: usize) -> FmtResult {
+		let indent = "  ".repeat(depth);
+
+		match self {
+			ParseTree::Leaf(token) => writeln!(f, "{}{}", indent, token),
+
+			ParseTree::Node { name, children } => {
+				writeln!(f, "{}{}", indent, name)?;
+				children.iter().try_for_each(|c| c.fmt_indented(f, depth + 1))
+			}
+		}
+	}
+}
+
+// shows the tree with each child indented under its parent.
+impl Display for ParseTree {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		self.fmt_indented(f, 0)
+	}
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult, Write };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Basic blocks
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *basic block* is a run of instructions that always execute together, start to finish: control
+can only come in at the top, and only leave at the bottom. So there are no labels in the middle
+of a block (nothing can jump into it), and no jumps in the middle (nothing can leave early).
+
+The *control-flow graph* (CFG) has the blocks as its nodes, and an edge from block A to block B if
+B can run right after A. That's either because A ends with a jump to B, or because A just runs
+off its end and "falls through" into B. The blocks that can run right after A are its
+*successors*, and the blocks that A can run right after are its *predecessors*.
+
+Splitting the code into blocks is simple. The first instruction of each block (its *leader*) is:
+
+- the first instruction in the function;
+- any label (something might jump there);
+- the instruction after a jump or return (whatever was before it doesn't fall through into it).
+
+Almost every optimization works on the CFG instead of the plain list of instructions, because
+"what could have happened before this point?" is a question about the paths through the graph.
+*/
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct BlockId(pub usize);
+
+impl Display for BlockId {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "B{}", self.0)
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock {
+	// if the block starts with a label, it's the first instruction, just like in the IrFunc.
+	pub insts: Vec<Inst>,
+	pub preds: Vec<BlockId>,
+	pub succs: Vec<BlockId>,
+}
+
+impl BasicBlock {
+	pub fn label(&self) -> Option<Label> {
+		match self.insts.first() {
+			Some(Inst::Label(l)) => Some(*l),
+			_                    => None,
+		}
+	}
+}
+
+// Does control never go on to the next instruction after this one?
+fn ends_block(inst: &Inst) -> bool {
+	matches!(inst,
+		Inst::Jump(..) | Inst::CondJump { .. } | Inst::JumpTable { .. } | Inst::Return(..))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Cfg
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct Cfg {
+	pub name:   String,
+	pub blocks: Vec<BasicBlock>, // blocks[0] is the entry block.
+}
+
+impl Cfg {
+	pub fn new(func: &IrFunc) -> Cfg {
+		Cfg::from_code(&func.name, &func.code)
+	}
+
+	// Builds a CFG from a list of instructions. Optimizations use this to rebuild the graph
+	// after they've changed the code.
+	pub fn from_code(name: &str, code: &[Inst]) -> Cfg {
+		// first, split the code up at the leaders.
+		let mut blocks = vec![BasicBlock::default()];
+
+		for inst in code {
+			let cur = blocks.last_mut().unwrap();
+
+			if matches!(inst, Inst::Label(..)) && !cur.insts.is_empty() {
+				blocks.push(BasicBlock::default());
+			}
+
+			blocks.last_mut().unwrap().insts.push(inst.clone());
+
+			if ends_block(inst) {
+				blocks.push(BasicBlock::default());
+			}
+		}
+
+		// that leaves an empty block at the end if the last instruction was a jump or return.
+		if blocks.len() > 1 && blocks.last().unwrap().insts.is_empty() {
+			blocks.pop();
+		}
+
+		let mut ret = Cfg { name: name.into(), blocks };
+		ret.add_edges();
+		ret
+	}
+
+	fn add_edges(&mut self) {
+		let label_blocks = self.blocks.iter().enumerate()
+			.filter_map(|(i, b)| b.label().map(|l| (l, BlockId(i))))
+			.collect::<HashMap<_, _>>();
+
+		for i in 0 .. self.blocks.len() {
+			let next = if i + 1 < self.blocks.len() { Some(BlockId(i + 1)) } else { None };
+
+			let succs = match self.blocks[i].insts.last() {
+				Some(Inst::Jump(target))          => vec![label_blocks[target]],
+				Some(Inst::Return(..))            => vec![],
+				// the fallthrough comes first, then where it jumps to.
+				Some(Inst::CondJump { target, .. }) => {
+					let mut succs = next.into_iter().collect::<Vec<_>>();
+
+					if !succs.contains(&label_blocks[target]) {
+						succs.push(label_blocks[target]);
+					}
+
+					succs
+				}
+				// several entries of a table can go to the same place.
+				Some(Inst::JumpTable { targets, .. }) => {
+					let mut succs = vec![];
+
+					for target in targets {
+						if !succs.contains(&label_blocks[target]) {
+							succs.push(label_blocks[target]);
+						}
+					}
+
+					succs
+				}
+				_                                 => next.into_iter().collect(),
+			};
+
+			for &succ in &succs {
+				self.blocks[succ.0].preds.push(BlockId(i));
+			}
+
+			self.blocks[i].succs = succs;
+		}
+	}
+
+	pub fn entry(&self) -> BlockId {
+		BlockId(0)
+	}
+
+	pub fn block(&self, id: BlockId) -> &BasicBlock {
+		&self.blocks[id.0]
+	}
+
+	pub fn block_mut(&mut self, id: BlockId) -> &mut BasicBlock {
+		&mut self.blocks[id.0]
+	}
+
+	pub fn ids(&self) -> impl Iterator<Item = BlockId> {
+		(0 .. self.blocks.len()).map(BlockId)
+	}
+
+	// Puts all the instructions back into one list, in block order. Since the blocks are in the
+	// same order as the code they came from, all the fallthroughs still work.
+	pub fn instructions(&self) -> impl Iterator<Item = &Inst> {
+		self.blocks.iter().flat_map(|b| b.insts.iter())
+	}
+
+	pub fn num_instructions(&self) -> usize {
+		self.blocks.iter().map(|b| b.insts.len()).sum()
+	}
+
+	/*
+	DOT output (see rust_trees for more about DOT). Each block is a box with its instructions in
+	it. The two edges out of a conditional jump are labeled with which way they go.
+
+	"\l" is a weird DOT thing that means "end this line and left-justify it."
+	*/
+	pub fn to_dot(&self) -> String {
+		let mut out = String::new();
+		writeln!(out, "digraph {} {{", self.name).unwrap();
+		out.push_str("\tnode [shape=box, fontname=monospace];\n");
+
+		for id in self.ids() {
+			let mut label = format!("{}:\\l", id);
+
+			for inst in &self.block(id).insts {
+				match inst {
+					Inst::Label(..) => write!(label, "{}\\l", escape(&inst.to_string())).unwrap(),
+					_ => write!(label, "    {}\\l", escape(&inst.to_string())).unwrap(),
+				}
+			}
+
+			writeln!(out, "\t{} [label=\"{}\"];", id, label).unwrap();
+		}
+
+		for id in self.ids() {
+			let block = self.block(id);
+
+			for &succ in &block.succs {
+				match block.insts.last() {
+					Some(Inst::CondJump { target, jump_if, .. }) => {
+						let jumps = self.block(succ).label() == Some(*target);
+						let taken = if jumps { *jump_if } else { !*jump_if };
+						writeln!(out, "\t{} -> {} [label=\"{}\"];", id, succ, taken).unwrap();
+					}
+
+					_ => writeln!(out, "\t{} -> {};", id, succ).unwrap(),
+				}
+			}
+		}
+
+		out.push_str("}\n");
+		out
+	}
+}
+
+// quotes and backslashes have to be escaped inside DOT strings.
+fn escape(s: &str) -> String {
+	s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// A text listing of the blocks, with each one's predecessors and successors.
+impl Display for Cfg {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		writeln!(f, "{}:", self.name)?;
+
+		for id in self.ids() {
+			let block = self.block(id);
+			writeln!(f, "{}  (preds: {}; succs: {})", id, list(&block.preds), list(&block.succs))?;
+
+			for inst in &block.insts {
+				match inst {
+					Inst::Label(..) => writeln!(f, "{}", inst)?,
+					_               => writeln!(f, "    {}", inst)?,
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn list(ids: &[BlockId]) -> String {
+	if ids.is_empty() {
+		"none".into()
+	} else {
+		ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+	}
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Constant propagation
+// ------------------------------------------------------------------------------------------------
+
+/*
+If the code says `x = 5` and then `y = x * 2`, and nothing changes x in between, then y is 10, and
+the compiler can figure that out. There are two parts to that:
+
+- constant *propagation*: replacing uses of x with 5, since that's what x is.
+- constant *folding*: replacing `5 * 2` with 10, since that's what it is.
+
+Each one makes more of the other possible, so they go together.
+
+"Nothing changes x in between" is the hard part once there's control flow. If the code branches,
+and one side sets x = 5, and the other side sets x = 6, then after they join back together, x
+could be either one, so it isn't a constant anymore. This is a *forward dataflow analysis*
+(liveness went backward), and what we know about each temp or variable is one of:
+
+	Unknown:     we haven't seen it get a value yet.
+	Const(k):    every way of getting here gives it the value k.
+	NotConst:    it could have different values (or it comes from outside, like an input).
+
+When two paths meet, what's known about each value is combined with the *meet* operator:
+
+	Unknown  meet  anything   = anything
+	Const(k) meet  Const(k)   = Const(k)
+	Const(k) meet  Const(j)   = NotConst      (when k != j)
+	NotConst meet  anything   = NotConst
+
+and just like liveness, it keeps going around until nothing changes. Then it goes through the
+code once more, replacing every use of a constant with the constant itself, folding any
+instruction whose operands are all constants, and simplifying any conditional jump whose
+condition is a constant: it becomes a `goto` if it always jumps, or is deleted if it never does.
+
+That last one can leave blocks that can never run, and folding leaves behind copies into temps
+that nothing reads anymore. Dead code elimination cleans those up, which is why they run together.
+*/
+
+#[derive(Debug, Clone, Copy)]
+enum Value {
+	Unknown,
+	Const(f64),
+	NotConst,
+}
+
+// constants are compared by their bits, like in meet(). `0 / 0` is NaN, and NaN != NaN, so with
+// the usual float ==, a state with a NaN in it would never equal itself, and the loop below would
+// never stop.
+impl PartialEq for Value {
+	fn eq(&self, other: &Value) -> bool {
+		use Value::*;
+
+		match (self, other) {
+			(Const(a), Const(b)) => a.to_bits() == b.to_bits(),
+			(Unknown, Unknown)   => true,
+			(NotConst, NotConst) => true,
+			_                    => false,
+		}
+	}
+}
+
+impl Value {
+	fn meet(self, other: Value) -> Value {
+		use Value::*;
+
+		match (self, other) {
+			(Unknown, v) | (v, Unknown)                       => v,
+			(Const(a), Const(b)) if a.to_bits() == b.to_bits() => Const(a),
+			_                                                 => NotConst,
+		}
+	}
+}
+
+// temps and variables are both tracked. Operand can't be a HashMap key (f64 isn't Eq), so here's
+// a key type without the constants.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+	Temp(Temp),
+	Var(String),
+}
+
+fn key(op: &Operand) -> Option<Key> {
+	match op {
+		Operand::Temp(t) => Some(Key::Temp(*t)),
+		Operand::Var(v)  => Some(Key::Var(v.clone())),
+		Operand::Const(..) => None,
+	}
+}
+
+// what we know at some point in the code. anything not in the map is Unknown.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct State(HashMap<Key, Value>);
+
+impl State {
+	fn get(&self, op: &Operand) -> Value {
+		match op {
+			Operand::Const(k) => Value::Const(*k),
+			_                 => self.0.get(&key(op).unwrap()).copied().unwrap_or(Value::Unknown),
+		}
+	}
+
+	fn meet(&self, other: &State) -> State {
+		let mut ret = self.clone();
+
+		for (k, v) in &other.0 {
+			let old = ret.0.get(k).copied().unwrap_or(Value::Unknown);
+			ret.0.insert(k.clone(), old.meet(*v));
+		}
+
+		ret
+	}
+
+	// updates the state to what it is after `inst` runs.
+	fn transfer(&mut self, inst: &Inst) {
+		let value = match inst {
+			Inst::Bin { op, lhs, rhs, .. } => match (self.get(lhs), self.get(rhs)) {
+				(Value::Const(a), Value::Const(b)) => Value::Const(op.eval(a, b)),
+				(Value::NotConst, _) | (_, Value::NotConst) => Value::NotConst,
+				_ => Value::Unknown,
+			},
+
+			Inst::Un { op, src, .. } => match self.get(src) {
+				Value::Const(a) => Value::Const(op.eval(a)),
+				v               => v,
+			},
+
+			Inst::Copy { src, .. } => self.get(src),
+
+			// the IR doesn't know what the builtin functions do, so it can't fold them. it doesn't
+			// know what's in memory either, or where alloc will put things.
+			Inst::Call { .. } | Inst::Load { .. } | Inst::Alloc { .. } => Value::NotConst,
+
+			_ => return,
+		};
+
+		self.0.insert(key(inst.dst().unwrap()).unwrap(), value);
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConstPropStats {
+	pub uses_replaced:       usize, // operands that were replaced by constants.
+	pub insts_folded:        usize, // instructions that were computed at compile time.
+	pub branches_simplified: usize, // conditional jumps that were always or never taken.
+}
+
+impl ConstPropStats {
+	pub fn changed(&self) -> bool {
+		self.uses_replaced > 0 || self.insts_folded > 0 || self.branches_simplified > 0
+	}
+}
+
+impl Display for ConstPropStats {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "replaced {} uses with constants, folded {} instructions, simplified {} branches",
+			self.uses_replaced, self.insts_folded, self.branches_simplified)
+	}
+}
+
+pub fn propagate_constants(cfg: &mut Cfg) -> ConstPropStats {
+	let ins = analyze(cfg);
+	let mut stats = ConstPropStats::default();
+
+	for id in cfg.ids() {
+		let mut state = ins[id.0].clone();
+		let block = cfg.block_mut(id);
+		let mut new_insts = vec![];
+
+		for inst in &block.insts {
+			// propagate...
+			let rewritten = inst.map_uses(|op| match (op, state.get(op)) {
+				(Operand::Const(..), _) => op.clone(),
+				(_, Value::Const(k))    => { stats.uses_replaced += 1; Operand::Const(k) }
+				_                       => op.clone(),
+			});
+
+			state.transfer(inst);
+
+			// ...and fold.
+			let folded = match &rewritten {
+				Inst::Bin { dst, .. } | Inst::Un { dst, .. } => match state.get(dst) {
+					Value::Const(k) => {
+						stats.insts_folded += 1;
+						Inst::Copy { dst: dst.clone(), src: Operand::Const(k) }
+					}
+					_ => rewritten,
+				},
+
+				Inst::CondJump { cond: Operand::Const(k), jump_if, target } => {
+					stats.branches_simplified += 1;
+
+					if (*k != 0.0) == *jump_if {
+						Inst::Jump(*target)
+					} else {
+						continue; // it never jumps, so it does nothing.
+					}
+				}
+
+				// if it's out of range, the range check before this always jumps away, and this
+				// can never run. so leave it alone.
+				Inst::JumpTable { index: Operand::Const(k), targets }
+				if *k >= 0.0 && (*k as usize) < targets.len() => {
+					stats.branches_simplified += 1;
+					Inst::Jump(targets[*k as usize])
+				}
+
+				_ => rewritten,
+			};
+
+			new_insts.push(folded);
+		}
+
+		block.insts = new_insts;
+	}
+
+	// the edges out of simplified branches are different now.
+	if stats.branches_simplified > 0 {
+		let code = cfg.instructions().cloned().collect::<Vec<_>>();
+		*cfg = Cfg::from_code(&cfg.name, &code);
+	}
+
+	stats
+}
+
+// the dataflow analysis. gives what's known at the start of each block.
+fn analyze(cfg: &Cfg) -> Vec<State> {
+	// variables come from outside the function, so they're NotConst when it starts.
+	let mut entry = State::default();
+
+	for inst in cfg.instructions() {
+		for op in inst.uses().into_iter().chain(inst.dst()) {
+			if let Operand::Var(..) = op {
+				entry.0.insert(key(op).unwrap(), Value::NotConst);
+			}
+		}
+	}
+
+	let n = cfg.blocks.len();
+	let mut ins  = vec![State::default(); n];
+	let mut outs = vec![State::default(); n];
+	let mut changed = true;
+
+	while changed {
+		changed = false;
+
+		for id in cfg.ids() {
+			let block = cfg.block(id);
+			let mut state = block.preds.iter().fold(State::default(), |s, p| s.meet(&outs[p.0]));
+
+			if id == cfg.entry() {
+				state = state.meet(&entry);
+			}
+
+			ins[id.0] = state.clone();
+
+			for inst in &block.insts {
+				state.transfer(inst);
+			}
+
+			if state != outs[id.0] {
+				outs[id.0] = state;
+				changed = true;
+			}
+		}
+	}
+
+	ins
+}
@@ -0,0 +1,164 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use toylang::BinOp;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Common subexpression elimination
+// ------------------------------------------------------------------------------------------------
+
+/*
+If the code computes `a * b` twice, and neither a nor b changed in between, the second one is
+going to get the same answer as the first one. So instead of computing it again, it can just copy
+whatever the first one put its answer in:
+
+	t1 = a * b                  t1 = a * b
+	...                  ==>    ...
+	t5 = a * b                  t5 = t1
+
+`a * b` is a *common subexpression*, and this is *common subexpression elimination* (CSE). The
+lowering makes a lot of these by itself. Every `a[i]` loads the array's length (for the bounds
+check), and so does every `len(a)`, so `a[i] + len(a)` computes `a - 8` and loads from it twice.
+
+This version only looks inside one basic block at a time (it's *local* CSE), so it goes through
+each block from top to bottom, remembering which expressions are *available*: already computed,
+with the answer still sitting in some temp or variable. An expression stops being available when:
+
+- one of its operands is written. after `a = a + 1`, the old `a * b` isn't `a * b` anymore.
+- the temp or variable holding its answer is written, since the answer isn't in there anymore.
+- it's a load, and something writes to memory. `*t2` after `*t2 = 5` might not be what it was
+  before! Even a store to some other address might change it, since two different temps can hold
+  the same address. Figuring out when they can't is called *alias analysis*, and it's hard, so
+  this just assumes that any store (or alloc, which writes the length) changes all of memory.
+
+Calls aren't eliminated, even though every builtin is a pure function right now, since that won't
+always be true. Bounds checks aren't either. (A second `check i < t2` with nothing changed in
+between could go, but it has no dst, so it doesn't fit this pass very well.)
+
+CSE leaves copies behind (`t5 = t1`), and after that, t5 is just another name for t1. So when it
+compares expressions, it looks through the copies in the block: after `t9 = t4`, `*t9` is the same
+as `*t4`. It doesn't change the code to use t1 instead of t5, though. That's *copy propagation*,
+and with that, DCE could delete the copies too. It's the next pass to write!
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CseStats {
+	pub replaced: usize, // how many instructions were replaced with copies...
+	pub loads:    usize, // ...and how many of those were loads.
+}
+
+impl CseStats {
+	pub fn changed(&self) -> bool {
+		self.replaced > 0
+	}
+}
+
+impl Display for CseStats {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "replaced {} repeated computations with copies ({} of them loads)",
+			self.replaced, self.loads)
+	}
+}
+
+// The right side of an instruction that CSE can eliminate.
+#[derive(Debug, PartialEq)]
+enum Exp {
+	Bin(BinOp, Operand, Operand),
+	Un(UnOp, Operand),
+	Load(Operand),
+}
+
+impl Exp {
+	fn of(inst: &Inst) -> Option<Exp> {
+		match inst {
+			Inst::Bin { op, lhs, rhs, .. } => Some(Exp::Bin(*op, lhs.clone(), rhs.clone())),
+			Inst::Un { op, src, .. }       => Some(Exp::Un(*op, src.clone())),
+			Inst::Load { addr, .. }        => Some(Exp::Load(addr.clone())),
+			_                              => None,
+		}
+	}
+
+	fn reads(&self, op: &Operand) -> bool {
+		match self {
+			Exp::Bin(_, lhs, rhs) => lhs == op || rhs == op,
+			Exp::Un(_, src)       => src == op,
+			Exp::Load(addr)       => addr == op,
+		}
+	}
+
+	// `b * a` is the same as `a * b`. (that's true even for floats.)
+	fn same_as(&self, other: &Exp) -> bool {
+		match (self, other) {
+			(Exp::Bin(op, a, b), Exp::Bin(op2, c, d)) if op == op2 && is_commutative(*op) =>
+				(a == c && b == d) || (a == d && b == c),
+			_ => self == other,
+		}
+	}
+}
+
+fn is_commutative(op: BinOp) -> bool {
+	use BinOp::*;
+	matches!(op, Add | Mul | Eq | Ne | And | Or)
+}
+
+pub fn eliminate_common_subexpressions(cfg: &mut Cfg) -> CseStats {
+	let mut stats = CseStats::default();
+
+	for id in cfg.ids() {
+		// each available expression, and where its answer is...
+		let mut available: Vec<(Exp, Operand)> = vec![];
+		// ...and each copy in the block, as (dst, src), so that `*t9` is recognized as `*t4`
+		// after `t9 = t4`.
+		let mut copies: Vec<(Operand, Operand)> = vec![];
+
+		for inst in &mut cfg.block_mut(id).insts {
+			let mut exp = Exp::of(&inst.map_uses(|op| original(&copies, op)));
+
+			if let (Some(e), Some(dst)) = (&exp, inst.dst()) {
+				if let Some((_, holder)) = available.iter().find(|(a, _)| a.same_as(e)) {
+					stats.replaced += 1;
+					stats.loads += matches!(e, Exp::Load(..)) as usize;
+					*inst = Inst::Copy { dst: dst.clone(), src: holder.clone() };
+					exp = None; // the answer is in holder already.
+				}
+			}
+
+			// the copy still writes dst, so it has to make things unavailable too.
+			if let Some(dst) = inst.dst() {
+				available.retain(|(e, holder)| holder != dst && !e.reads(dst));
+				copies.retain(|(d, s)| d != dst && s != dst);
+			}
+
+			if matches!(inst, Inst::Store { .. } | Inst::Alloc { .. }) {
+				available.retain(|(e, _)| !matches!(e, Exp::Load(..)));
+			}
+
+			// after `i = i + 1`, i doesn't hold `i + 1`; it holds (old i) + 1, which can't be
+			// written down anymore.
+			if let (Some(exp), Some(dst)) = (exp, inst.dst()) {
+				if !exp.reads(dst) {
+					available.push((exp, dst.clone()));
+				}
+			}
+
+			if let Inst::Copy { dst, src } = inst {
+				let src = original(&copies, src);
+
+				if src != *dst {
+					copies.push((dst.clone(), src));
+				}
+			}
+		}
+	}
+
+	stats
+}
+
+// what `op` is a copy of, or just op if it isn't a copy.
+fn original(copies: &[(Operand, Operand)], op: &Operand) -> Operand {
+	match copies.iter().find(|(dst, _)| dst == op) {
+		Some((_, src)) => src.clone(),
+		None           => op.clone(),
+	}
+}
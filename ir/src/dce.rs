@@ -0,0 +1,136 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Dead code elimination
+// ------------------------------------------------------------------------------------------------
+
+/*
+*Dead code* is code that can be deleted without changing what the program does. There are two
+kinds, and this pass removes both:
+
+- *unreachable* blocks, which can never run, because there's no path to them from the entry
+  block. These are found by starting at the entry and following the edges of the CFG; any block
+  that isn't reached is unreachable.
+
+- *dead instructions*, which write a temp that isn't live afterwards. Nothing ever reads what
+  they computed, so why compute it? The liveness analysis says exactly which temps are live.
+
+Deleting one dead instruction can make another one dead. In
+
+	t1 = a * b
+	t2 = t1 + 1     (t2 is never used)
+
+only t2's instruction is dead at first, but once it's gone, nothing reads t1 either. So it keeps
+redoing the liveness analysis and deleting things until there's nothing left to delete.
+
+This only works because most of our instructions have no *side effects* besides writing their
+dst. Writing a variable isn't dead (variables are always live), and every builtin function just
+computes a value. If there were a `print()` function, a call to it could never be deleted, even
+if nothing used what it returned!
+
+The instructions that *do* have side effects are the ones that don't write a temp: a store
+changes memory, and a bounds check can stop the program. Since they have no dst, they're never
+dead. A load or an alloc nobody uses can go, though.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DceStats {
+	pub insts_before:       usize,
+	pub insts_after:        usize,
+	pub blocks_before:      usize,
+	pub blocks_after:       usize,
+	pub dead_insts:         usize, // how many instructions were removed for being dead...
+	pub unreachable_blocks: usize, // ...and how many blocks were removed for being unreachable.
+}
+
+impl DceStats {
+	pub fn changed(&self) -> bool {
+		self.dead_insts > 0 || self.unreachable_blocks > 0
+	}
+}
+
+impl Display for DceStats {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "removed {} dead instructions and {} unreachable blocks \
+			(instructions: {} -> {}, blocks: {} -> {})",
+			self.dead_insts, self.unreachable_blocks,
+			self.insts_before, self.insts_after, self.blocks_before, self.blocks_after)
+	}
+}
+
+pub fn eliminate_dead_code(cfg: &mut Cfg) -> DceStats {
+	let mut stats = DceStats {
+		insts_before:  cfg.num_instructions(),
+		blocks_before: cfg.blocks.len(),
+		..Default::default()
+	};
+
+	// unreachable blocks go first, since the code in them might be the only thing reading some
+	// temps, keeping them live.
+	stats.unreachable_blocks = remove_unreachable_blocks(cfg);
+
+	loop {
+		let removed = remove_dead_insts(cfg, &Liveness::new(cfg));
+
+		if removed == 0 {
+			break;
+		}
+
+		stats.dead_insts += removed;
+	}
+
+	stats.insts_after  = cfg.num_instructions();
+	stats.blocks_after = cfg.blocks.len();
+	stats
+}
+
+// gives how many blocks were removed.
+fn remove_unreachable_blocks(cfg: &mut Cfg) -> usize {
+	// a depth-first search from the entry block.
+	let mut reached = vec![false; cfg.blocks.len()];
+	let mut to_visit = vec![cfg.entry()];
+
+	while let Some(id) = to_visit.pop() {
+		if !reached[id.0] {
+			reached[id.0] = true;
+			to_visit.extend(&cfg.block(id).succs);
+		}
+	}
+
+	let num_unreachable = reached.iter().filter(|&&r| !r).count();
+
+	if num_unreachable > 0 {
+		// rather than carefully fixing up all the block ids and edges, it's easier to just make
+		// a new CFG from the code that's left.
+		let code = cfg.ids()
+			.filter(|id| reached[id.0])
+			.flat_map(|id| cfg.block(id).insts.iter().cloned())
+			.collect::<Vec<_>>();
+
+		*cfg = Cfg::from_code(&cfg.name, &code);
+	}
+
+	num_unreachable
+}
+
+// gives how many instructions were removed.
+fn remove_dead_insts(cfg: &mut Cfg, liveness: &Liveness) -> usize {
+	let mut removed = 0;
+
+	for id in cfg.ids() {
+		let block = cfg.block_mut(id);
+		let mut i = 0;
+
+		// `i` counts the original instructions, so it lines up with the liveness info.
+		block.insts.retain(|inst| {
+			let dead = matches!(written_temp(inst), Some(t) if !liveness.is_live_after(id, i, t));
+			i += 1;
+			removed += dead as usize;
+			!dead
+		});
+	}
+
+	removed
+}
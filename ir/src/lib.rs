@@ -0,0 +1,43 @@
+/*
+An *intermediate representation* (IR) is the compiler's own language: lower-level than the AST,
+but not tied to any real CPU. The front end (lexer, parser, semantic checks) turns source code
+into IR, the optimizer turns IR into better IR, and the back end turns IR into machine code.
+
+	AST --lower--> IR --optimize--> IR --codegen--> assembly
+
+This crate's IR is *three-address code* (TAC). Each instruction does one thing, with at most three
+"addresses" (operands) in it: two inputs and one output. So a nested expression like
+
+	x = a * b + c
+
+becomes a flat list of steps, with *temporaries* (t1, t2...) holding the in-between values:
+
+	t1 = a * b
+	t2 = t1 + c
+	x = t2
+
+There are no nested expressions and no precedence to worry about anymore. Control flow is done
+with labels and jumps (basically gotos), like in assembly.
+*/
+
+mod cfg;
+mod constprop;
+mod cse;
+mod dce;
+mod liveness;
+mod lower;
+mod optimize;
+mod peephole;
+mod regalloc;
+mod tac;
+
+pub use crate::cfg::*;
+pub use crate::constprop::*;
+pub use crate::cse::*;
+pub use crate::dce::*;
+pub use crate::liveness::*;
+pub use crate::lower::*;
+pub use crate::optimize::*;
+pub use crate::peephole::*;
+pub use crate::regalloc::*;
+pub use crate::tac::*;
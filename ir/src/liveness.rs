@@ -0,0 +1,178 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Liveness analysis
+// ------------------------------------------------------------------------------------------------
+
+/*
+A temp is *live* at some point in the code if the value in it might be read later. If it's not
+live (it's *dead*), whatever is in it doesn't matter anymore, so its register can be reused for
+something else, and an instruction that writes a temp that's dead afterwards can be deleted.
+
+Only temps are tracked. Variables from the source program are kept in memory, and their values
+can be seen after the program ends, so they're always considered live.
+
+Liveness is a *dataflow analysis*, and it goes *backwards*, because whether a temp is live depends
+on what comes *after*. For each block B:
+
+	use[B] = temps that B reads before writing them
+	def[B] = temps that B writes
+	live_out[B] = the union of live_in[S] for every successor S of B
+	live_in[B]  = use[B] + (live_out[B] - def[B])
+
+In words: a temp is live coming into B if B reads it, or if it's live after B and B doesn't
+overwrite it. Loops in the CFG make these equations refer to each other in a circle, so we start
+with every set empty and keep recomputing them until nothing changes (a *fixed point*). Since the
+sets only ever grow, and there are only so many temps, that has to happen eventually.
+
+Once we know what's live at the end of each block, walking backwards through the block one
+instruction at a time gives what's live between each pair of instructions:
+
+	live before = (live after - what it writes) + what it reads
+*/
+
+pub type TempSet = BTreeSet<Temp>; // a BTreeSet so they print in order.
+
+#[derive(Debug, Clone)]
+pub struct Liveness {
+	// for each block, what's live before each instruction, plus one more at the end for what's
+	// live after the last instruction. so live[b][0] is live_in[b], and the last is live_out[b].
+	live: Vec<Vec<TempSet>>,
+}
+
+impl Liveness {
+	pub fn new(cfg: &Cfg) -> Liveness {
+		let n = cfg.blocks.len();
+		let (uses, defs): (Vec<_>, Vec<_>) = cfg.blocks.iter().map(use_def).unzip();
+		let mut live_in  = vec![TempSet::new(); n];
+		let mut live_out = vec![TempSet::new(); n];
+		let mut changed  = true;
+
+		while changed {
+			changed = false;
+
+			// it'd work in any order, but going backwards means a block's successors have usually
+			// been done already, so it gets to the fixed point in fewer passes.
+			for b in (0 .. n).rev() {
+				let out = cfg.blocks[b].succs.iter()
+					.flat_map(|s| live_in[s.0].iter().copied())
+					.collect::<TempSet>();
+
+				let mut inn = uses[b].clone();
+				inn.extend(out.difference(&defs[b]));
+
+				if inn != live_in[b] || out != live_out[b] {
+					live_in[b]  = inn;
+					live_out[b] = out;
+					changed     = true;
+				}
+			}
+		}
+
+		// now the per-instruction sets.
+		let live = cfg.blocks.iter().zip(live_out).map(|(block, out)| {
+			let mut sets = vec![out];
+
+			for inst in block.insts.iter().rev() {
+				let mut set = sets.last().unwrap().clone();
+
+				if let Some(t) = written_temp(inst) {
+					set.remove(&t);
+				}
+
+				set.extend(read_temps(inst));
+				sets.push(set);
+			}
+
+			sets.reverse();
+			sets
+		}).collect();
+
+		Liveness { live }
+	}
+
+	pub fn live_in(&self, b: BlockId) -> &TempSet {
+		&self.live[b.0][0]
+	}
+
+	pub fn live_out(&self, b: BlockId) -> &TempSet {
+		self.live[b.0].last().unwrap()
+	}
+
+	// What's live right before instruction `i` of block `b` runs.
+	pub fn live_before(&self, b: BlockId, i: usize) -> &TempSet {
+		&self.live[b.0][i]
+	}
+
+	// What's live right after instruction `i` of block `b` runs.
+	pub fn live_after(&self, b: BlockId, i: usize) -> &TempSet {
+		&self.live[b.0][i + 1]
+	}
+
+	pub fn is_live_after(&self, b: BlockId, i: usize, t: Temp) -> bool {
+		self.live_after(b, i).contains(&t)
+	}
+
+	// A listing of the CFG with what's live after every instruction written next to it.
+	pub fn report(&self, cfg: &Cfg) -> String {
+		let mut out = String::new();
+		writeln!(out, "{}:", cfg.name).unwrap();
+
+		for id in cfg.ids() {
+			writeln!(out, "{}  (live in: {})", id, set_str(self.live_in(id))).unwrap();
+
+			for (i, inst) in cfg.block(id).insts.iter().enumerate() {
+				let text = match inst {
+					Inst::Label(..) => inst.to_string(),
+					_               => format!("    {}", inst),
+				};
+
+				writeln!(out, "{:<32} live: {}", text, set_str(self.live_after(id, i))).unwrap();
+			}
+		}
+
+		out
+	}
+}
+
+// The temp an instruction writes to, if any.
+pub fn written_temp(inst: &Inst) -> Option<Temp> {
+	inst.dst().and_then(|dst| dst.as_temp())
+}
+
+// The temps an instruction reads.
+pub fn read_temps(inst: &Inst) -> impl Iterator<Item = Temp> + '_ {
+	inst.uses().into_iter().filter_map(|op| op.as_temp())
+}
+
+fn use_def(block: &BasicBlock) -> (TempSet, TempSet) {
+	let mut uses = TempSet::new();
+	let mut defs = TempSet::new();
+
+	for inst in &block.insts {
+		// an instruction reads its operands before it writes its result, so `t1 = t1 + 1` uses
+		// t1 even though it also defines it.
+		for t in read_temps(inst) {
+			if !defs.contains(&t) {
+				uses.insert(t);
+			}
+		}
+
+		if let Some(t) = written_temp(inst) {
+			defs.insert(t);
+		}
+	}
+
+	(uses, defs)
+}
+
+fn set_str(set: &TempSet) -> String {
+	if set.is_empty() {
+		"-".into()
+	} else {
+		set.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ")
+	}
+}
@@ -0,0 +1,480 @@
+use semantic::{ SymbolId, SymbolTable, TArm, TExp, TExpKind, TProgram, TStmt, TStmtKind };
+use toylang::{ BinOp, Pattern };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Lowering
+// ------------------------------------------------------------------------------------------------
+
+/*
+*Lowering* turns the AST into IR. It's a lot like the evaluator in toylang: a recursive walk over
+the tree. But where eval() gives back the *value* of an expression, lower_exp() outputs the
+instructions that *compute* the value, and gives back the operand where the value will be. So for
+`a * b + c`:
+
+- lower_exp(a) gives Var(a), and lower_exp(b) gives Var(b). no instructions needed!
+- lower_exp(a * b) outputs `t1 = a * b` and gives Temp(t1).
+- lower_exp(c) gives Var(c).
+- lower_exp(a * b + c) outputs `t2 = t1 + c` and gives Temp(t2).
+
+Lowering works on the *typed* AST that the type checker makes (see typed.rs in the semantic
+crate), not the parser's AST. The only way to get one of those is to pass the checker, so lowering
+doesn't report errors; there can't be any. Every variable in it is a SymbolId, and it becomes an
+IR variable with the symbol's name.
+*/
+
+// The whole program becomes a function named "main" which returns the value of the last
+// statement, like toylang::run() does.
+pub fn lower(program: &TProgram) -> IrFunc {
+	let mut l = Lowerer { func: IrFunc::new("main"), symbols: &program.symbols, loops: vec![] };
+	let last = l.lower_stmts(&program.stmts);
+	l.func.push(Inst::Return(last));
+	l.func
+}
+
+struct Lowerer<'p> {
+	func:    IrFunc,
+	symbols: &'p SymbolTable,
+
+	// the (top, end) labels of the loops we're inside of, innermost last. `break` jumps to the
+	// innermost end, and `continue` jumps to the innermost top.
+	loops:   Vec<(Label, Label)>,
+}
+
+impl Lowerer<'_> {
+	// gives the operand that holds the last statement's value (if it has one).
+	fn lower_stmts(&mut self, stmts: &[TStmt]) -> Option<Operand> {
+		let mut last = None;
+
+		for stmt in stmts {
+			last = self.lower_stmt(stmt);
+		}
+
+		last
+	}
+
+	// gives the operand that holds the statement's value. loops, `break`, and `continue` don't
+	// have one, and neither does an if without an else.
+	fn lower_stmt(&mut self, stmt: &TStmt) -> Option<Operand> {
+		match &stmt.kind {
+			TStmtKind::Assign { var, value } => {
+				let src = self.lower_exp(value);
+				let dst = self.var(*var);
+				self.func.push(Inst::Copy { dst: dst.clone(), src });
+				Some(dst)
+			}
+
+			TStmtKind::Exp(exp) => Some(self.lower_exp(exp)),
+			TStmtKind::Block(stmts) => self.lower_stmts(stmts),
+
+			TStmtKind::While { cond, body } => { self.lower_while(cond, body); None }
+
+			TStmtKind::If { cond, then_body, else_body } =>
+				self.lower_if(cond, then_body, else_body.as_deref()),
+
+			TStmtKind::Break => {
+				let (_, end) = *self.loops.last().expect("'break' outside of a loop");
+				self.func.push(Inst::Jump(end));
+				None
+			}
+
+			TStmtKind::Continue => {
+				let (top, _) = *self.loops.last().expect("'continue' outside of a loop");
+				self.func.push(Inst::Jump(top));
+				None
+			}
+		}
+	}
+
+	/*
+	A loop is a jump backwards. The condition is checked at the top, and if it's false, we jump
+	past the end:
+
+	L1:
+		t1 = cond
+		ifFalse t1 goto L2
+		(the body)
+		goto L1
+	L2:
+
+	`break` is `goto L2`, and `continue` is `goto L1`. The code after a `break` in the same block
+	can never run, but that's fine; it ends up in a basic block with no predecessors.
+	*/
+	fn lower_while(&mut self, cond: &TExp, body: &[TStmt]) {
+		let top = self.func.new_label();
+		let end = self.func.new_label();
+		self.func.push(Inst::Label(top));
+
+		let cond = self.lower_exp(cond);
+		self.func.push(Inst::CondJump { cond, jump_if: false, target: end });
+
+		self.loops.push((top, end));
+		self.lower_stmts(body);
+		self.loops.pop();
+
+		self.func.push(Inst::Jump(top));
+		self.func.push(Inst::Label(end));
+	}
+
+	/*
+	An if is a jump forwards, over the block that shouldn't run:
+
+		t1 = cond
+		ifFalse t1 goto L1
+		(the then block)
+		goto L2
+	L1:
+		(the else part)
+	L2:
+
+	Without an else, there's nothing to jump over at the end of the then block, so it's only
+	`ifFalse t1 goto L1`, the then block, and L1. An `else if` is an if in the else part, so it
+	makes the same code again, inside.
+
+	Like a match, the if's value goes in the same temp whichever block runs. If one of the blocks
+	doesn't have a value (like if it ends with a loop), neither does the if.
+	*/
+	fn lower_if(&mut self, cond: &TExp, then_body: &TStmt, else_body: Option<&TStmt>)
+	-> Option<Operand> {
+		let cond = self.lower_exp(cond);
+		let other = self.func.new_label();
+		self.func.push(Inst::CondJump { cond, jump_if: false, target: other });
+
+		let else_body = match else_body {
+			Some(else_body) => else_body,
+			None            => {
+				self.lower_stmt(then_body);
+				self.func.push(Inst::Label(other));
+				return None;
+			}
+		};
+
+		let end = self.func.new_label();
+		let dst = self.func.new_temp();
+		let then_val = self.lower_stmt(then_body);
+
+		if let Some(src) = &then_val {
+			self.func.push(Inst::Copy { dst: dst.into(), src: src.clone() });
+		}
+
+		self.func.push(Inst::Jump(end));
+		self.func.push(Inst::Label(other));
+		let else_val = self.lower_stmt(else_body);
+
+		if let Some(src) = &else_val {
+			self.func.push(Inst::Copy { dst: dst.into(), src: src.clone() });
+		}
+
+		self.func.push(Inst::Label(end));
+		then_val.and(else_val).map(|_| dst.into())
+	}
+
+	fn lower_exp(&mut self, exp: &TExp) -> Operand {
+		use TExpKind::*;
+
+		match &exp.kind {
+			// bools are 1 and 0, just like in the evaluator.
+			Int   (val) => Operand::Const(*val as f64),
+			Float (val) => Operand::Const(*val),
+			Bool  (val) => Operand::Const(if *val { 1.0 } else { 0.0 }),
+			Var   (id)  => self.var(*id),
+
+			Negate(lhs) => self.lower_unary(UnOp::Neg, lhs),
+			Not   (lhs) => self.lower_unary(UnOp::Not, lhs),
+
+			Binary { .. } => self.lower_chain(exp),
+
+			Match { scrutinee, arms } => self.lower_match(scrutinee, arms),
+			Array (elems) => self.lower_array(elems),
+
+			Index { array, index } => {
+				let array = self.lower_exp(array);
+				let index = self.lower_exp(index);
+				self.lower_index(array, index)
+			}
+
+			// every operand is a number, so there's nowhere to put a string. the compiler crate
+			// checks for these before lowering.
+			Str(..) => panic!("lowering a string, which the IR can't hold"),
+
+			// len() isn't really a function; it's a load. (see lower_array().)
+			Len(array) => {
+				let array = self.lower_exp(array);
+				self.lower_len(array)
+			}
+
+			Call { func, args } => {
+				let func = self.symbols.get(*func).name.clone();
+
+				// arguments are evaluated left to right.
+				let args = args.iter().map(|arg| self.lower_exp(arg)).collect();
+				let dst = self.func.new_temp();
+				self.func.push(Inst::Call { dst: dst.into(), func, args });
+				dst.into()
+			}
+		}
+	}
+
+	// a chain of operators, like `a + b * c - d`. it's lowered with a loop, so that a long one
+	// can't use up the stack (see TExp::left_chain()). each operator's lhs is the chain so far.
+	fn lower_chain(&mut self, exp: &TExp) -> Operand {
+		let (first, ops) = exp.left_chain();
+		let mut lhs = self.lower_exp(first);
+
+		for (_, op, rhs) in ops {
+			lhs = if op == BinOp::And || op == BinOp::Or {
+				self.lower_short_circuit(op, lhs, rhs)
+			} else {
+				let rhs = self.lower_exp(rhs);
+				let dst = self.func.new_temp();
+				self.func.push(Inst::Bin { dst: dst.into(), op, lhs, rhs });
+				dst.into()
+			};
+		}
+
+		lhs
+	}
+
+	// a variable is named after its symbol. the type checker doesn't allow functions yet, so all
+	// the variables are in the same scope, and no two of them have the same name.
+	fn var(&self, id: SymbolId) -> Operand {
+		Operand::Var(self.symbols.get(id).name.clone())
+	}
+
+	/*
+	This is where the IR has to start talking about *memory*. Every operand is one number, and an
+	array is lots of numbers, so an array can't be an operand. Instead, its values go in memory,
+	one after another, and the operand holds their *address*: where in memory the first one is.
+	Each value is 8 bytes (a double), so value k is at address + 8 * k. `[a, b, c]` becomes:
+
+		t1 = alloc 3
+		*t1 = a
+		t2 = t1 + 8
+		*t2 = b
+		t3 = t1 + 16
+		*t3 = c
+
+	`*t2 = b` means "store b at the address in t2", and `t = *t2` means "load from the address in
+	t2," like in C. alloc also stores the length just *before* the values, at address - 8, so that
+	len() and bounds checking can find it. That's how an array "knows" its length.
+
+	Since arrays can't be changed, copying one just copies its address, and both variables share
+	the same memory. Nothing ever frees it, either; a real compiler would need a garbage collector,
+	or Rust-style ownership, to know when it's safe to.
+	*/
+	fn lower_array(&mut self, elems: &[TExp]) -> Operand {
+		let base = self.func.new_temp();
+		let len = Operand::Const(elems.len() as f64);
+		self.func.push(Inst::Alloc { dst: base.into(), len });
+
+		for (k, elem) in elems.iter().enumerate() {
+			let src = self.lower_exp(elem);
+
+			let addr = if k == 0 {
+				base.into()
+			} else {
+				let addr = self.func.new_temp();
+				let (lhs, rhs) = (base.into(), Operand::Const(8.0 * k as f64));
+				self.func.push(Inst::Bin { dst: addr.into(), op: BinOp::Add, lhs, rhs });
+				addr.into()
+			};
+
+			self.func.push(Inst::Store { addr, src });
+		}
+
+		base.into()
+	}
+
+	/*
+	`a[i]` is the *address computation* a + 8 * i, and then a load from there:
+
+		t1 = a - 8
+		t2 = *t1         (the length)
+		check i < t2
+		t3 = i * 8
+		t4 = a + t3
+		t5 = *t4
+
+	The check is the bounds check: without it, a[100] would happily load whatever is in memory
+	100 values after a[0], which is what C does. It stops the program unless i is a whole number
+	from 0 to t2 - 1, like the evaluator's index out of bounds error.
+	*/
+	fn lower_index(&mut self, array: Operand, index: Operand) -> Operand {
+		let len = self.lower_len(array.clone());
+		self.func.push(Inst::BoundsCheck { index: index.clone(), len });
+
+		let offset = self.func.new_temp();
+		let (lhs, rhs) = (index, Operand::Const(8.0));
+		self.func.push(Inst::Bin { dst: offset.into(), op: BinOp::Mul, lhs, rhs });
+
+		let addr = self.func.new_temp();
+		let (lhs, rhs) = (array, offset.into());
+		self.func.push(Inst::Bin { dst: addr.into(), op: BinOp::Add, lhs, rhs });
+
+		let dst = self.func.new_temp();
+		self.func.push(Inst::Load { dst: dst.into(), addr: addr.into() });
+		dst.into()
+	}
+
+	// the length is just before the values.
+	fn lower_len(&mut self, array: Operand) -> Operand {
+		let len_addr = self.func.new_temp();
+		let (lhs, rhs) = (array, Operand::Const(8.0));
+		self.func.push(Inst::Bin { dst: len_addr.into(), op: BinOp::Sub, lhs, rhs });
+
+		let dst = self.func.new_temp();
+		self.func.push(Inst::Load { dst: dst.into(), addr: len_addr.into() });
+		dst.into()
+	}
+
+	fn lower_unary(&mut self, op: UnOp, lhs: &TExp) -> Operand {
+		let src = self.lower_exp(lhs);
+		let dst = self.func.new_temp();
+		self.func.push(Inst::Un { dst: dst.into(), op, src });
+		dst.into()
+	}
+
+	/*
+	A match picks one of its arms by comparing the value against each pattern. Each arm's value
+	is computed in its own piece of code, which puts it in the same temp and jumps to the end:
+
+		t1 = x
+		(pick an arm, and jump to its label)
+	L2:
+		t2 = (first arm's value)
+		goto L1
+	L3:
+		t2 = (second arm's value)
+		goto L1
+		...
+	L1:
+
+	There are two ways to "pick an arm." The simple way is a chain of comparisons, one per
+	pattern, like a chain of if-elses:
+
+		t3 = t1 == 10
+		if t3 goto L2
+		t4 = t1 == 20
+		if t4 goto L3
+		goto L4           (the `_` arm)
+
+	That takes longer the more arms there are. But if the patterns are *dense* - like 1, 2, 3, 4,
+	5 - the value can be used as an index into a *jump table*, a list of labels. Subtract the
+	smallest pattern so the first one is 0, check that it's in range, and go straight there:
+
+		t3 = t1 - 1
+		t4 = t3 < 0
+		if t4 goto L4
+		t5 = t3 > 4
+		if t5 goto L4
+		goto [L2, L3, L5, L6, L7][t3]
+
+	That takes the same time no matter how many arms there are, which is what a `switch` in C
+	usually compiles to. Any holes in the range (say, if there were no 4) go to the `_` arm.
+	*/
+	fn lower_match(&mut self, scrutinee: &TExp, arms: &[TArm]) -> Operand {
+		let val = self.lower_exp(scrutinee);
+		let dst = self.func.new_temp();
+		let end = self.func.new_label();
+
+		// the type checker makes sure there's a `_` arm, and nothing after it. if two arms have
+		// the same pattern, the first one wins, just like in the evaluator.
+		let labels = arms.iter().map(|_| self.func.new_label()).collect::<Vec<_>>();
+		let default = arms.iter().position(|arm| arm.pattern == Pattern::Wildcard)
+			.map(|i| labels[i])
+			.expect("lowering a match with no `_` arm");
+
+		let mut cases = Vec::<(i64, Label)>::new();
+
+		for (arm, &label) in arms.iter().zip(&labels) {
+			match arm.pattern {
+				Pattern::Int(k) if !cases.iter().any(|&(c, _)| c == k) => cases.push((k, label)),
+				Pattern::Int(..) => {}
+				Pattern::Wildcard => break,
+			}
+		}
+
+		if is_dense(&cases) {
+			self.lower_jump_table(val, &cases, default);
+		} else {
+			for &(k, label) in &cases {
+				let cond = self.func.new_temp();
+				let (lhs, rhs) = (val.clone(), Operand::Const(k as f64));
+				self.func.push(Inst::Bin { dst: cond.into(), op: BinOp::Eq, lhs, rhs });
+				self.func.push(Inst::CondJump { cond: cond.into(), jump_if: true, target: label });
+			}
+
+			self.func.push(Inst::Jump(default));
+		}
+
+		for (arm, &label) in arms.iter().zip(&labels) {
+			self.func.push(Inst::Label(label));
+			let src = self.lower_exp(&arm.value);
+			self.func.push(Inst::Copy { dst: dst.into(), src });
+			self.func.push(Inst::Jump(end));
+		}
+
+		self.func.push(Inst::Label(end));
+		dst.into()
+	}
+
+	fn lower_jump_table(&mut self, val: Operand, cases: &[(i64, Label)], default: Label) {
+		let min = cases.iter().map(|&(k, _)| k).min().unwrap();
+		let max = cases.iter().map(|&(k, _)| k).max().unwrap();
+
+		let index = self.func.new_temp();
+		let (lhs, rhs) = (val, Operand::Const(min as f64));
+		self.func.push(Inst::Bin { dst: index.into(), op: BinOp::Sub, lhs, rhs });
+
+		for (op, limit) in [(BinOp::Lt, 0), (BinOp::Gt, max - min)] {
+			let cond = self.func.new_temp();
+			let (lhs, rhs) = (index.into(), Operand::Const(limit as f64));
+			self.func.push(Inst::Bin { dst: cond.into(), op, lhs, rhs });
+			self.func.push(Inst::CondJump { cond: cond.into(), jump_if: true, target: default });
+		}
+
+		let targets = (min ..= max)
+			.map(|k| cases.iter().find(|&&(c, _)| c == k).map_or(default, |&(_, label)| label))
+			.collect();
+
+		self.func.push(Inst::JumpTable { index: index.into(), targets });
+	}
+
+	/*
+	`a && b` only evaluates b if a is true. (If a is false, the answer is false no matter what b
+	is.) Even though the evaluator doesn't bother with this, it's how C, Java, Rust, etc. all
+	work, and it's the first place where lowering needs jumps:
+
+		t1 = a
+		ifFalse t1 goto L1    (for ||, this is `if t1 goto L1`)
+		t1 = b
+	L1:
+
+	so t1 is a, unless a was true, in which case it's b. `src` is a, already lowered.
+	*/
+	fn lower_short_circuit(&mut self, op: BinOp, src: Operand, rhs: &TExp) -> Operand {
+		let dst = self.func.new_temp();
+		let end = self.func.new_label();
+		self.func.push(Inst::Copy { dst: dst.into(), src });
+		self.func.push(Inst::CondJump { cond: dst.into(), jump_if: op == BinOp::Or, target: end });
+
+		let src = self.lower_exp(rhs);
+		self.func.push(Inst::Copy { dst: dst.into(), src });
+		self.func.push(Inst::Label(end));
+		dst.into()
+	}
+}
+
+// A jump table is worth it when there are enough cases, and they don't leave too many holes.
+fn is_dense(cases: &[(i64, Label)]) -> bool {
+	const MIN_CASES: usize = 4;
+
+	if cases.len() < MIN_CASES {
+		return false;
+	}
+
+	let min = cases.iter().map(|&(k, _)| k).min().unwrap();
+	let max = cases.iter().map(|&(k, _)| k).max().unwrap();
+	max.checked_sub(min).is_some_and(|range| range < 2 * cases.len() as i64)
+}
@@ -0,0 +1,170 @@
+use diagnostics::{ render_all, Diagnostic, SourceFile };
+use ir::*;
+use semantic::TProgram;
+use toylang::Env;
+
+fn main() {
+	lowering_demo();
+	regalloc_demo();
+	dce_demo();
+	constprop_demo();
+	cse_demo();
+	pass_manager_demo();
+}
+
+fn lowering_demo() {
+	let programs = [
+		"x = a * b + c",
+		"let r = 2.5\narea = pi * pow(r, 2)\n-area",
+		"ok = x >= 0 && x < 10 || x == -1",
+		"i = 0\nwhile i < n {\n\ti = i + 1\n\tif i % 2 == 1 { continue }\n\tif i > 10 { break }\n}",
+		"if x < 0 {\n\ty = -x\n} else if x > 10 {\n\ty = 10\n} else {\n\ty = x\n}",
+		// a few far-apart patterns become a chain of comparisons...
+		"let n = 7\nmatch n { 1 => a, 100 => b, _ => c }",
+		// ...and lots of close-together ones become a jump table.
+		"let d = 2\nmatch d {\n\t1 => 31\n\t2 => 28\n\t3 => 31\n\t4 => 30\n\t6 => 30\n\t_ => 0\n}",
+		// arrays live in memory, so indexing them is an address computation and a load.
+		"let i = 1\nlet a = [x, 2, 3]\ny = a[i] + len(a)",
+	];
+
+	for src in &programs {
+		println!("{}\n", src);
+
+		match check(src) {
+			Ok(program) => {
+				let func = lower(&program);
+				print!("{}\n{}", func, Cfg::new(&func));
+			}
+
+			Err(errs)   => print!("{}", render_all(&errs, &SourceFile::new("<demo>", *src))),
+		}
+
+		println!("------------------------------------------------------------------------");
+	}
+
+	let cfg = compile(programs[2]);
+	println!("{}", Liveness::new(&cfg).report(&cfg));
+
+	// paste this into a Graphviz viewer to see it!
+	println!("{}", cfg.to_dot());
+}
+
+fn regalloc_demo() {
+	// with only two registers, something has to be spilled in the second one.
+	let programs = [
+		"ok = x >= 0 && x < 10 || x == -1",
+		"y = (a + b) * ((c + d) * (f + g))",
+	];
+
+	for src in &programs {
+		let cfg = compile(src);
+		let alloc = allocate_registers(&cfg, &Liveness::new(&cfg), &MIPS_TEMP_REGS[.. 2]);
+		println!("{}\n\n{}", src, alloc.listing(&cfg));
+	}
+}
+
+fn dce_demo() {
+	// only the last statement's value is returned, so the first two are computed for nothing.
+	let mut cfg = compile("a * b + c\nsqrt(a) > 2\nx = a + 1\nx * 2");
+	println!("before:\n{}", cfg);
+
+	// optimize() runs every pass, including DCE.
+	println!("{}\n", optimize(&mut cfg));
+	println!("after:\n{}", cfg);
+
+	// the lowering never makes unreachable code, so here's some by hand.
+	let mut func = IrFunc::new("unreachable");
+	let (t1, t2) = (func.new_temp(), func.new_temp());
+	let (l1, l2) = (func.new_label(), func.new_label());
+	func.push(Inst::Jump(l2));
+	func.push(Inst::Label(l1));
+	func.push(Inst::Bin { dst: t1.into(), op: toylang::BinOp::Mul, lhs: Operand::Const(2.0),
+		rhs: Operand::Var("a".into()) });
+	func.push(Inst::Jump(l1));
+	func.push(Inst::Label(l2));
+	func.push(Inst::Copy { dst: t2.into(), src: Operand::Var("a".into()) });
+	func.push(Inst::Return(Some(t2.into())));
+
+	let mut cfg = Cfg::new(&func);
+	println!("before:\n{}", cfg);
+	let stats = eliminate_dead_code(&mut cfg);
+	println!("after:\n{}\n{}", cfg, stats);
+}
+
+fn constprop_demo() {
+	// r is always 2, so `r > 1` is always true, and the && doesn't need to check it.
+	let mut cfg = compile("let r = 2\nbig = r > 1 && x > 0\n(1 + 2) * r");
+	println!("before:\n{}", cfg);
+
+	let stats = propagate_constants(&mut cfg);
+	println!("after constant propagation:\n{}\n{}\n", cfg, stats);
+
+	// ...and now there's a bunch of dead code. every pass together, until nothing changes:
+	let mut cfg = compile("let r = 2\nbig = r > 1 && x > 0\n(1 + 2) * r");
+
+	println!("{}\n", optimize(&mut cfg));
+	println!("after:\n{}", cfg);
+}
+
+fn cse_demo() {
+	// `x * b` is computed twice, and so is the array's length (once for the bounds check, and
+	// once for len(arr)). making `other` stores to memory, though, so the second arr[0] has to be
+	// loaded again. CSE can't tell that it's a different array.
+	let src = "let arr = [x, 2, 3]\ny = arr[0] + len(arr) + x * b\nlet other = [y]\n\
+		z = arr[0] + x * b";
+	let mut cfg = compile(src);
+	println!("{}\n\nbefore:\n{}", src, cfg);
+
+	let stats = eliminate_common_subexpressions(&mut cfg);
+	println!("after CSE:\n{}\n{}\n", cfg, stats);
+
+	let stats = peephole(&mut cfg);
+	println!("after peephole:\n{}\n{}\n", cfg, stats);
+}
+
+fn pass_manager_demo() {
+	let src = "let r = 2\nbig = r > 1 && x > 0\ny = x * r + x * 2";
+
+	// the passes can go in any order, and the same one can go in more than once. without a
+	// fixpoint, it only does one round, so whatever the last dce made possible doesn't happen.
+	let mut passes = PassManager::with_passes(&["dce", "constprop", "peephole", "dce"]).unwrap();
+	passes.fixpoint = false;
+	passes.snapshot_after("constprop").unwrap();
+
+	let mut cfg = compile(src);
+	let report = passes.run(&mut cfg);
+
+	for (run, snapshot) in report.snapshots("constprop") {
+		println!("after {} (round {}):\n{}", run.pass, run.round, snapshot);
+	}
+
+	println!("{}\n", report);
+
+	// the default passes, to a fixpoint.
+	let mut cfg = compile(src);
+	println!("{}\n\n{}", optimize(&mut cfg), cfg);
+
+	match PassManager::with_passes(&["constprop", "inline"]) {
+		Ok(_)  => unreachable!(),
+		Err(e) => println!("error: {}", e),
+	}
+}
+
+// the demo programs use some variables that they never set, like a and x, to stand for the
+// program's inputs. checking them with those in the Env makes them constants, like pi is.
+fn check(src: &str) -> Result<TProgram, Vec<Diagnostic>> {
+	let mut env = Env::new();
+
+	for name in &["a", "b", "c", "d", "f", "g", "n", "x"] {
+		env.set(name, 0.0);
+	}
+
+	let program = toylang::compile(src)?;
+	let resolved = semantic::resolve(&program, &env)?;
+	semantic::typecheck(&program, &resolved)
+}
+
+// the demo programs are all correct, so this doesn't bother reporting errors.
+fn compile(src: &str) -> Cfg {
+	Cfg::new(&lower(&check(src).unwrap()))
+}
@@ -0,0 +1,261 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Passes
+// ------------------------------------------------------------------------------------------------
+
+// An optimization pass changes the CFG, and says what it did. `changed` is false if it didn't
+// find anything to do.
+#[derive(Debug, Clone)]
+pub struct PassResult {
+	pub changed: bool,
+	pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+	pub name:  &'static str,
+	pub about: &'static str, // what it does, in a few words.
+	pub run:   fn(&mut Cfg) -> PassResult,
+}
+
+// Every optimization pass there is, so they can be looked up by name.
+pub const PASSES: &[Pass] = &[
+	Pass { name: "constprop", about: "constant propagation and folding", run: run_constprop },
+	Pass { name: "cse",       about: "common subexpression elimination", run: run_cse },
+	Pass { name: "peephole",  about: "small local rewrites",             run: run_peephole },
+	Pass { name: "dce",       about: "dead code elimination",            run: run_dce },
+];
+
+// The passes that optimize() runs, in order. DCE goes last, since it cleans up after the others.
+pub const DEFAULT_PASSES: &[&str] = &["constprop", "cse", "peephole", "dce"];
+
+// A PassManager gives up after this many rounds, in case two passes keep undoing each other.
+pub const MAX_ROUNDS: usize = 10;
+
+pub fn find_pass(name: &str) -> Option<&'static Pass> {
+	PASSES.iter().find(|pass| pass.name == name)
+}
+
+fn unknown_pass(name: &str) -> String {
+	let names = PASSES.iter().map(|pass| pass.name).collect::<Vec<_>>();
+	format!("there's no pass named '{}' (use {})", name, names.join(", "))
+}
+
+fn run_constprop(cfg: &mut Cfg) -> PassResult {
+	let stats = propagate_constants(cfg);
+	PassResult { changed: stats.changed(), summary: stats.to_string() }
+}
+
+fn run_cse(cfg: &mut Cfg) -> PassResult {
+	let stats = eliminate_common_subexpressions(cfg);
+	PassResult { changed: stats.changed(), summary: stats.to_string() }
+}
+
+fn run_peephole(cfg: &mut Cfg) -> PassResult {
+	let stats = peephole(cfg);
+	PassResult { changed: stats.changed(), summary: stats.to_string() }
+}
+
+fn run_dce(cfg: &mut Cfg) -> PassResult {
+	let stats = eliminate_dead_code(cfg);
+	PassResult { changed: stats.changed(), summary: stats.to_string() }
+}
+
+// ------------------------------------------------------------------------------------------------
+// PassManager
+// ------------------------------------------------------------------------------------------------
+
+/*
+Optimizations enable each other. Constant propagation can turn `if c goto L` into `goto L`, which
+makes the code after it unreachable, so DCE can delete it. And DCE deleting things can mean fewer
+ways to get to some block, so that more things are constant there. So one round of every pass
+isn't enough: by default, this keeps doing rounds until a whole round goes by where nothing
+changes. That's called running them to a *fixpoint*.
+
+Which passes run, and in what order, matters too, and it's something to experiment with. A
+PassManager is that choice, written down: a list of passes (by name), and whether to go to a
+fixpoint or just do one round. Real compilers have these too; LLVM's `opt -passes=...` is one.
+
+It also keeps track of what each pass did every time it ran, and can save a copy of the IR right
+after some of them (a *snapshot*), so you can see exactly what each pass changed. That's what the
+compiler's `--emit=ir-after=<pass>` shows.
+*/
+
+#[derive(Debug, Clone)]
+pub struct PassManager {
+	pub passes:    Vec<&'static Pass>, // in the order they run.
+	pub fixpoint:  bool,               // keep doing rounds until nothing changes?
+	pub snapshots: Vec<&'static str>,  // the passes to save the IR after.
+}
+
+// Every pass in DEFAULT_PASSES, to a fixpoint.
+impl Default for PassManager {
+	fn default() -> Self {
+		PassManager::with_passes(DEFAULT_PASSES).unwrap()
+	}
+}
+
+impl PassManager {
+	// No passes at all, so running it does nothing.
+	pub fn new() -> Self {
+		PassManager { passes: vec![], fixpoint: true, snapshots: vec![] }
+	}
+
+	// The passes with these names, in this order. The same one can be in there more than once.
+	pub fn with_passes(names: &[&str]) -> Result<Self, String> {
+		let mut ret = PassManager::new();
+
+		for name in names {
+			ret.add(name)?;
+		}
+
+		Ok(ret)
+	}
+
+	// Puts a pass on the end of the list.
+	pub fn add(&mut self, name: &str) -> Result<(), String> {
+		let pass = find_pass(name).ok_or_else(|| unknown_pass(name))?;
+		self.passes.push(pass);
+		Ok(())
+	}
+
+	// Asks for a snapshot of the IR after every time this pass runs.
+	pub fn snapshot_after(&mut self, name: &str) -> Result<(), String> {
+		let pass = find_pass(name).ok_or_else(|| unknown_pass(name))?;
+
+		if !self.passes.iter().any(|p| p.name == pass.name) {
+			return Err(format!("the '{}' pass isn't being run, so there's no IR after it",
+				pass.name));
+		}
+
+		self.snapshots.push(pass.name);
+		Ok(())
+	}
+
+	// Runs the passes on the CFG, in rounds (or just one round, if `fixpoint` is false).
+	pub fn run(&self, cfg: &mut Cfg) -> OptReport {
+		let mut report = OptReport::default();
+		let max_rounds = if self.fixpoint { MAX_ROUNDS } else { 1 };
+
+		while report.rounds < max_rounds {
+			report.rounds += 1;
+			let mut changed = false;
+
+			for pass in &self.passes {
+				let insts_before = cfg.num_instructions();
+				let result = (pass.run)(cfg);
+				changed |= result.changed;
+
+				report.runs.push(PassRun {
+					pass:  pass.name,
+					round: report.rounds,
+					insts_before,
+					insts_after: cfg.num_instructions(),
+					snapshot: if self.snapshots.contains(&pass.name) { Some(cfg.clone()) }
+						else { None },
+					result,
+				});
+			}
+
+			if !changed {
+				report.converged = true;
+				break;
+			}
+		}
+
+		report
+	}
+}
+
+// Runs the passes in DEFAULT_PASSES until none of them change anything.
+pub fn optimize(cfg: &mut Cfg) -> OptReport {
+	PassManager::default().run(cfg)
+}
+
+// ------------------------------------------------------------------------------------------------
+// OptReport
+// ------------------------------------------------------------------------------------------------
+
+// One time that one pass ran.
+#[derive(Debug, Clone)]
+pub struct PassRun {
+	pub pass:         &'static str,
+	pub round:        usize, // starting at 1.
+	pub result:       PassResult,
+	pub insts_before: usize,
+	pub insts_after:  usize,
+	pub snapshot:     Option<Cfg>, // the IR after it ran, if a snapshot was asked for.
+}
+
+// Everything a PassManager did.
+#[derive(Debug, Clone, Default)]
+pub struct OptReport {
+	pub runs:      Vec<PassRun>, // in the order they ran.
+	pub rounds:    usize,
+	pub converged: bool, // did the last round change nothing? (if not, it ran out of rounds.)
+}
+
+// The totals for one pass, over every time it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassStats {
+	pub pass:          &'static str,
+	pub runs:          usize,
+	pub changed:       usize, // how many of the runs changed something.
+	pub insts_removed: usize,
+}
+
+impl OptReport {
+	// The totals for each pass, in the order they first ran.
+	pub fn stats(&self) -> Vec<PassStats> {
+		let mut ret: Vec<PassStats> = vec![];
+
+		for run in &self.runs {
+			let i = match ret.iter().position(|s| s.pass == run.pass) {
+				Some(i) => i,
+				None    => {
+					ret.push(PassStats { pass: run.pass, runs: 0, changed: 0, insts_removed: 0 });
+					ret.len() - 1
+				}
+			};
+
+			ret[i].runs += 1;
+			ret[i].changed += run.result.changed as usize;
+			ret[i].insts_removed += run.insts_before.saturating_sub(run.insts_after);
+		}
+
+		ret
+	}
+
+	// Every snapshot of the IR after the pass with this name.
+	pub fn snapshots(&self, pass: &str) -> impl Iterator<Item = (&PassRun, &Cfg)> {
+		let pass = pass.to_string();
+		self.runs.iter()
+			.filter(move |run| run.pass == pass)
+			.filter_map(|run| run.snapshot.as_ref().map(|cfg| (run, cfg)))
+	}
+}
+
+// What every pass did every time, and then the totals.
+impl Display for OptReport {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for run in &self.runs {
+			writeln!(f, "round {} {:<10} {}", run.round, run.pass, run.result.summary)?;
+		}
+
+		writeln!(f, "\n{:<10} {:>4} {:>8} {:>14}", "pass", "runs", "changed", "insts removed")?;
+
+		for s in self.stats() {
+			writeln!(f, "{:<10} {:>4} {:>8} {:>14}", s.pass, s.runs, s.changed, s.insts_removed)?;
+		}
+
+		if self.converged {
+			write!(f, "nothing changed in round {}, so it stopped", self.rounds)
+		} else {
+			write!(f, "stopped after {} round{} without reaching a fixpoint", self.rounds,
+				if self.rounds == 1 { "" } else { "s" })
+		}
+	}
+}
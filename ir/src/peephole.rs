@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use toylang::BinOp;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Peephole optimization
+// ------------------------------------------------------------------------------------------------
+
+/*
+This is the same idea as the vm crate's peephole optimizer (see peephole.rs there): look at one or
+two instructions at a time, and replace patterns that are silly with something better. It's for
+IR instead of bytecode, so the patterns are different:
+
+	rule            before                  after
+	--------------  ----------------------  -------------------------
+	identity        t1 = x - 0              t1 = x
+	                t1 = x * 1              t1 = x      (or 1 * x)
+	                t1 = x / 1              t1 = x
+	self-copy       x = x                   (nothing)
+	jump-to-next    goto L; L:              L:
+	                if c goto L; L:         L:          (it goes to L either way!)
+
+Where's `x + 0`? That's *not* always x, for floats. If x is -0.0 (which is a real float value,
+different from 0.0), `-0.0 + 0` is 0.0. `x - 0` is fine, though: `-0.0 - 0` is -0.0. And `x * 0`
+isn't always 0, because infinity times 0 is NaN. Rules like these that are true for math but not
+for floats are a classic source of optimizer bugs. (The vm's peephole optimizer does `push 0; add`
+anyway; it's not as careful.)
+
+Constant folding isn't here, since constant propagation does it. The patterns mostly come from the
+other passes: CSE can make `x = x`, and once constant propagation and DCE have deleted the code
+between a jump and its label, the jump doesn't go anywhere it wasn't going already.
+*/
+
+// What the optimizer did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeepholeStats {
+	pub fired: BTreeMap<&'static str, usize>, // how many times each rule was used.
+}
+
+impl PeepholeStats {
+	pub fn changed(&self) -> bool {
+		!self.fired.is_empty()
+	}
+}
+
+impl Display for PeepholeStats {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		if self.fired.is_empty() {
+			return write!(f, "no rules fired");
+		}
+
+		let fired = self.fired.iter()
+			.map(|(rule, count)| format!("{} fired {} time{}", rule, count,
+				if *count == 1 { "" } else { "s" }))
+			.collect::<Vec<_>>();
+
+		write!(f, "{}", fired.join(", "))
+	}
+}
+
+pub fn peephole(cfg: &mut Cfg) -> PeepholeStats {
+	let mut stats = PeepholeStats::default();
+	let mut code = cfg.instructions().cloned().collect::<Vec<_>>();
+
+	// removing a jump can put another jump right before its label, so keep going.
+	loop {
+		let mut new_code = vec![];
+		let mut changed = false;
+
+		for (i, inst) in code.iter().enumerate() {
+			match rewrite(inst, code.get(i + 1)) {
+				Some((rule, new_inst)) => {
+					*stats.fired.entry(rule).or_insert(0) += 1;
+					new_code.extend(new_inst);
+					changed = true;
+				}
+
+				None => new_code.push(inst.clone()),
+			}
+		}
+
+		code = new_code;
+
+		if !changed {
+			break;
+		}
+	}
+
+	// jumps might be gone, so the edges are different.
+	if stats.changed() {
+		*cfg = Cfg::from_code(&cfg.name, &code);
+	}
+
+	stats
+}
+
+// if a rule matches `inst` (and the one after it), gives the rule's name and what to replace
+// `inst` with.
+fn rewrite(inst: &Inst, next: Option<&Inst>) -> Option<(&'static str, Option<Inst>)> {
+	use BinOp::*;
+
+	match inst {
+		Inst::Bin { dst, op, lhs, rhs } => {
+			let src = match op {
+				Sub       if is(rhs, 0.0) => lhs,
+				Mul | Div if is(rhs, 1.0) => lhs,
+				Mul       if is(lhs, 1.0) => rhs,
+				_                         => return None,
+			};
+
+			Some(("identity", Some(Inst::Copy { dst: dst.clone(), src: src.clone() })))
+		}
+
+		Inst::Copy { dst, src } if dst == src => Some(("self-copy", None)),
+
+		Inst::Jump(target) | Inst::CondJump { target, .. }
+		if next == Some(&Inst::Label(*target)) => Some(("jump-to-next", None)),
+
+		_ => None,
+	}
+}
+
+// is `op` exactly the constant k? this compares the bits, since -0.0 == 0.0 is true, but `x - -0.0`
+// isn't always x.
+fn is(op: &Operand, k: f64) -> bool {
+	op.as_const().map(f64::to_bits) == Some(k.to_bits())
+}
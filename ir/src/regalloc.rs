@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult, Write };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Locations
+// ------------------------------------------------------------------------------------------------
+
+/*
+The IR can have as many temps as it wants, but a real CPU only has a few registers. *Register
+allocation* decides where each temp goes: in a register if possible, or in a *stack slot* (a
+spot in memory) if there aren't enough registers. Putting a temp in memory is called *spilling*
+it, and it's slow, since every use of it has to load it from memory first.
+
+Two temps can share a register as long as they're never live at the same time. That's what the
+liveness analysis is for.
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Location {
+	Reg(String),
+	Stack(usize), // which stack slot. each spilled temp gets its own.
+}
+
+impl Display for Location {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Location::Reg(r)   => write!(f, "{}", r),
+			Location::Stack(s) => write!(f, "stack[{}]", s),
+		}
+	}
+}
+
+// The temporary registers on MIPS, which is what the course's code generation targets.
+pub const MIPS_TEMP_REGS: &[&str] = &[
+	"$t0", "$t1", "$t2", "$t3", "$t4", "$t5", "$t6", "$t7", "$t8", "$t9",
+];
+
+// ------------------------------------------------------------------------------------------------
+// Live intervals
+// ------------------------------------------------------------------------------------------------
+
+/*
+Linear scan doesn't use the full liveness information. It numbers all the instructions in order
+(across all the blocks), and simplifies each temp's liveness down to one *interval*: the first
+and last instruction where it's live. Any holes in the middle are ignored, which wastes a little,
+but makes allocation really fast (it's used in JIT compilers for exactly this reason).
+
+Loops are handled by the liveness analysis: a temp that's used on the next trip around a loop is
+live all the way to the end of the loop, so its interval covers the whole loop.
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Interval {
+	pub temp:  Temp,
+	pub start: usize, // the number of the instruction where it's first written or live.
+	pub end:   usize, // the number of the last instruction where it's read or live.
+}
+
+// The intervals, sorted by where they start.
+pub fn live_intervals(cfg: &Cfg, liveness: &Liveness) -> Vec<Interval> {
+	let mut intervals: HashMap<Temp, Interval> = HashMap::new();
+	let mut pos = 0;
+
+	for id in cfg.ids() {
+		for (i, inst) in cfg.block(id).insts.iter().enumerate() {
+			let temps = liveness.live_before(id, i).iter()
+				.chain(liveness.live_after(id, i))
+				.copied()
+				.chain(written_temp(inst));
+
+			for temp in temps {
+				let int = intervals.entry(temp).or_insert(Interval { temp, start: pos, end: pos });
+				int.end = pos;
+			}
+
+			pos += 1;
+		}
+	}
+
+	let mut ret = intervals.into_values().collect::<Vec<_>>();
+	ret.sort_by_key(|int| (int.start, int.temp));
+	ret
+}
+
+// ------------------------------------------------------------------------------------------------
+// Linear scan
+// ------------------------------------------------------------------------------------------------
+
+/*
+Linear scan register allocation (Poletto and Sarkar, 1999) goes through the intervals in order of
+where they start, keeping a list of the *active* intervals: the ones that have registers right now.
+
+For each interval:
+
+1. any active intervals that end before this one starts are done with their registers, so those
+   registers go back in the free pool. ("before" includes ending *on* the instruction where this
+   one starts: in `t2 = t1 + 1`, t1 is read before t2 is written, so they can share a register.)
+2. if there's a free register, this interval gets it.
+3. otherwise, something has to be spilled. The heuristic is to spill whichever interval ends
+   *last* (this one, or an active one), because that frees up a register for the longest time.
+*/
+
+#[derive(Debug, Clone)]
+pub struct Allocation {
+	pub intervals: Vec<Interval>,
+	pub locations: HashMap<Temp, Location>,
+	pub num_slots: usize, // how many stack slots are needed.
+}
+
+impl Allocation {
+	pub fn location(&self, t: Temp) -> &Location {
+		&self.locations[&t]
+	}
+
+	// A table of where each temp went, then the code with every temp replaced by its location.
+	pub fn listing(&self, cfg: &Cfg) -> String {
+		let mut out = String::new();
+		out.push_str("temp  interval  location\n");
+
+		for int in &self.intervals {
+			let range = format!("{}-{}", int.start, int.end);
+			let temp = int.temp.to_string();
+			writeln!(out, "{:<5} {:<9} {}", temp, range, self.location(int.temp)).unwrap();
+		}
+
+		writeln!(out, "\n{}:", cfg.name).unwrap();
+
+		for (pos, inst) in cfg.instructions().enumerate() {
+			// labels stick out to the left, like usual.
+			if let Inst::Label(..) = inst {
+				writeln!(out, "{:>3} {}", pos, inst).unwrap();
+				continue;
+			}
+
+			// just for printing, pretend the locations are variables.
+			let allocated = inst.map_operands(|op| match op {
+				Operand::Temp(t) => Operand::Var(self.location(*t).to_string()),
+				_                => op.clone(),
+			});
+
+			let text = format!("{:>3}  {}", pos, inst);
+			writeln!(out, "{:<32} {}", text, allocated).unwrap();
+		}
+
+		out
+	}
+}
+
+// Assigns each temp to one of `regs`, or to a stack slot if there aren't enough.
+pub fn allocate_registers(cfg: &Cfg, liveness: &Liveness, regs: &[&str]) -> Allocation {
+	let intervals = live_intervals(cfg, liveness);
+	let mut locations = HashMap::new();
+	let mut num_slots = 0;
+
+	// the free registers, reversed so pop() gives them in order ($t0 first, and so on).
+	let mut free = regs.iter().rev().map(|r| r.to_string()).collect::<Vec<_>>();
+	// the active intervals, kept sorted by where they end.
+	let mut active: Vec<Interval> = vec![];
+
+	for &int in &intervals {
+		// 1. expire old intervals.
+		while !active.is_empty() && active[0].end <= int.start {
+			let done = active.remove(0);
+
+			if let Some(Location::Reg(r)) = locations.get(&done.temp) {
+				free.push(r.clone());
+			}
+		}
+
+		if let Some(reg) = free.pop() {
+			// 2. there's a free register.
+			locations.insert(int.temp, Location::Reg(reg));
+			add_active(&mut active, int);
+		} else {
+			// 3. spill the one that ends last.
+			let slot = Location::Stack(num_slots);
+			num_slots += 1;
+
+			match active.last() {
+				Some(&last) if last.end > int.end => {
+					// take its register, and it goes on the stack instead.
+					let reg = locations.insert(last.temp, slot).unwrap();
+					locations.insert(int.temp, reg);
+					active.pop();
+					add_active(&mut active, int);
+				}
+
+				_ => { locations.insert(int.temp, slot); }
+			}
+		}
+	}
+
+	Allocation { intervals, locations, num_slots }
+}
+
+fn add_active(active: &mut Vec<Interval>, int: Interval) {
+	let i = active.iter().position(|a| a.end > int.end).unwrap_or(active.len());
+	active.insert(i, int);
+}
@@ -0,0 +1,298 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use toylang::BinOp;
+
+// ------------------------------------------------------------------------------------------------
+// Temps, labels, and operands
+// ------------------------------------------------------------------------------------------------
+
+// A temporary: a compiler-made variable that holds the result of one step of a computation.
+// There's no limit to how many there can be; the register allocator deals with that later.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Temp(pub usize);
+
+impl Display for Temp {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "t{}", self.0)
+	}
+}
+
+// A place in the code that can be jumped to.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Label(pub usize);
+
+impl Display for Label {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "L{}", self.0)
+	}
+}
+
+// One of the "addresses" in three-address code. Temps and variables can be written to (they can
+// be the `dst` of an instruction); constants can only be read.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+	Const(f64),
+	Var(String), // a variable from the source program.
+	Temp(Temp),
+}
+
+impl Operand {
+	pub fn as_temp(&self) -> Option<Temp> {
+		match self {
+			Operand::Temp(t) => Some(*t),
+			_                => None,
+		}
+	}
+
+	pub fn as_const(&self) -> Option<f64> {
+		match self {
+			Operand::Const(c) => Some(*c),
+			_                 => None,
+		}
+	}
+}
+
+impl Display for Operand {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Operand::Const(c) => write!(f, "{}", c),
+			Operand::Var(v)   => write!(f, "{}", v),
+			Operand::Temp(t)  => write!(f, "{}", t),
+		}
+	}
+}
+
+impl From<Temp> for Operand {
+	fn from(t: Temp) -> Operand {
+		Operand::Temp(t)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Instructions
+// ------------------------------------------------------------------------------------------------
+
+// The binary operators are the same as the AST's, so we just use toylang's BinOp.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UnOp {
+	Neg,
+	Not,
+}
+
+impl UnOp {
+	pub fn eval(self, val: f64) -> f64 {
+		match self {
+			UnOp::Neg => -val,
+			UnOp::Not => if val == 0.0 { 1.0 } else { 0.0 },
+		}
+	}
+}
+
+impl Display for UnOp {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			UnOp::Neg => write!(f, "-"),
+			UnOp::Not => write!(f, "!"),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Inst {
+	// dst = lhs op rhs
+	Bin { dst: Operand, op: BinOp, lhs: Operand, rhs: Operand },
+	// dst = op src
+	Un { dst: Operand, op: UnOp, src: Operand },
+	// dst = src
+	Copy { dst: Operand, src: Operand },
+	// dst = call func(args...)
+	Call { dst: Operand, func: String, args: Vec<Operand> },
+	// target:
+	Label(Label),
+	// goto target
+	Jump(Label),
+	// if cond goto target      (when `jump_if` is true)
+	// ifFalse cond goto target (when `jump_if` is false)
+	// if it doesn't jump, it goes on to the next instruction.
+	CondJump { cond: Operand, jump_if: bool, target: Label },
+	// goto targets[index]
+	// index has to be a whole number from 0 to targets.len() - 1. lower() checks that with
+	// CondJumps before it, since the code that this becomes doesn't check.
+	JumpTable { index: Operand, targets: Vec<Label> },
+	// dst = alloc len
+	// makes room for an array of `len` values, and gives its address. the length goes in the 8
+	// bytes just before the address, so that `*(dst - 8)` is len. (see lower_array().)
+	Alloc { dst: Operand, len: Operand },
+	// dst = *addr
+	Load { dst: Operand, addr: Operand },
+	// *addr = src
+	Store { addr: Operand, src: Operand },
+	// check index < len
+	// stops the program with an error unless index is a whole number from 0 to len - 1.
+	BoundsCheck { index: Operand, len: Operand },
+	// return value
+	Return(Option<Operand>),
+}
+
+impl Inst {
+	// The operand this instruction writes to, if any.
+	pub fn dst(&self) -> Option<&Operand> {
+		use Inst::*;
+
+		match self {
+			Bin { dst, .. } | Un { dst, .. } | Copy { dst, .. } | Call { dst, .. } => Some(dst),
+			Alloc { dst, .. } | Load { dst, .. } => Some(dst),
+			Label(..) | Jump(..) | CondJump { .. } | JumpTable { .. } | Return(..) => None,
+			Store { .. } | BoundsCheck { .. } => None,
+		}
+	}
+
+	// The operands this instruction reads from.
+	pub fn uses(&self) -> Vec<&Operand> {
+		use Inst::*;
+
+		match self {
+			Bin { lhs, rhs, .. }      => vec![lhs, rhs],
+			Un { src, .. }            => vec![src],
+			Copy { src, .. }          => vec![src],
+			Call { args, .. }         => args.iter().collect(),
+			CondJump { cond, .. }     => vec![cond],
+			JumpTable { index, .. }   => vec![index],
+			Alloc { len, .. }         => vec![len],
+			Load { addr, .. }         => vec![addr],
+			Store { addr, src }       => vec![addr, src],
+			BoundsCheck { index, len } => vec![index, len],
+			Return(Some(val))         => vec![val],
+			Label(..) | Jump(..) | Return(None) => vec![],
+		}
+	}
+}
+
+impl Inst {
+	// A copy of this instruction with every operand it reads *or* writes replaced by f(operand).
+	pub fn map_operands(&self, mut f: impl FnMut(&Operand) -> Operand) -> Inst {
+		// both closures want to use f, so they take turns borrowing it through a RefCell.
+		let f = std::cell::RefCell::new(&mut f);
+		self.map(|op| (f.borrow_mut())(op), |op| (f.borrow_mut())(op))
+	}
+
+	// Like map_operands(), but only the operands it reads are replaced; the dst stays the same.
+	pub fn map_uses(&self, f: impl FnMut(&Operand) -> Operand) -> Inst {
+		self.map(|op| op.clone(), f)
+	}
+
+	fn map<D, F>(&self, mut dst_f: D, mut f: F) -> Inst
+	where D: FnMut(&Operand) -> Operand, F: FnMut(&Operand) -> Operand {
+		use Inst::*;
+
+		match self {
+			Bin { dst, op, lhs, rhs } =>
+				Bin { dst: dst_f(dst), op: *op, lhs: f(lhs), rhs: f(rhs) },
+			Un { dst, op, src }       => Un { dst: dst_f(dst), op: *op, src: f(src) },
+			Copy { dst, src }         => Copy { dst: dst_f(dst), src: f(src) },
+			Call { dst, func, args }  =>
+				Call { dst: dst_f(dst), func: func.clone(), args: args.iter().map(f).collect() },
+			CondJump { cond, jump_if, target } =>
+				CondJump { cond: f(cond), jump_if: *jump_if, target: *target },
+			JumpTable { index, targets } =>
+				JumpTable { index: f(index), targets: targets.clone() },
+			Alloc { dst, len }        => Alloc { dst: dst_f(dst), len: f(len) },
+			Load { dst, addr }        => Load { dst: dst_f(dst), addr: f(addr) },
+			Store { addr, src }       => Store { addr: f(addr), src: f(src) },
+			BoundsCheck { index, len } => BoundsCheck { index: f(index), len: f(len) },
+			Return(Some(val))         => Return(Some(f(val))),
+			Label(..) | Jump(..) | Return(None) => self.clone(),
+		}
+	}
+}
+
+impl Display for Inst {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use Inst::*;
+
+		match self {
+			Bin { dst, op, lhs, rhs } => write!(f, "{} = {} {} {}", dst, lhs, op, rhs),
+			Un { dst, op, src }       => write!(f, "{} = {}{}", dst, op, src),
+			Copy { dst, src }         => write!(f, "{} = {}", dst, src),
+			Call { dst, func, args }  => {
+				let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+				write!(f, "{} = call {}({})", dst, func, args.join(", "))
+			}
+			Label(l)                  => write!(f, "{}:", l),
+			Jump(l)                   => write!(f, "goto {}", l),
+			CondJump { cond, jump_if: true,  target } => write!(f, "if {} goto {}", cond, target),
+			CondJump { cond, jump_if: false, target } =>
+				write!(f, "ifFalse {} goto {}", cond, target),
+			JumpTable { index, targets } => {
+				let targets = targets.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+				write!(f, "goto [{}][{}]", targets.join(", "), index)
+			}
+			Alloc { dst, len }        => write!(f, "{} = alloc {}", dst, len),
+			Load { dst, addr }        => write!(f, "{} = *{}", dst, addr),
+			Store { addr, src }       => write!(f, "*{} = {}", addr, src),
+			BoundsCheck { index, len } => write!(f, "check {} < {}", index, len),
+			Return(Some(val))         => write!(f, "return {}", val),
+			Return(None)              => write!(f, "return"),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// IrFunc
+// ------------------------------------------------------------------------------------------------
+
+// A list of instructions that can be called, plus the counters for making new temps and labels.
+// The toy language doesn't have user-defined functions yet, so a whole program is one IrFunc.
+#[derive(Debug, Clone)]
+pub struct IrFunc {
+	pub name:   String,
+	pub code:   Vec<Inst>,
+	num_temps:  usize,
+	num_labels: usize,
+}
+
+impl IrFunc {
+	pub fn new(name: &str) -> Self {
+		IrFunc { name: name.into(), code: vec![], num_temps: 0, num_labels: 0 }
+	}
+
+	// Temps and labels are numbered starting at 1, because that's how everyone writes them.
+	pub fn new_temp(&mut self) -> Temp {
+		self.num_temps += 1;
+		Temp(self.num_temps)
+	}
+
+	pub fn new_label(&mut self) -> Label {
+		self.num_labels += 1;
+		Label(self.num_labels)
+	}
+
+	// How many temps have been made. They're numbered 1 to this.
+	pub fn num_temps(&self) -> usize {
+		self.num_temps
+	}
+
+	pub fn num_labels(&self) -> usize {
+		self.num_labels
+	}
+
+	pub fn push(&mut self, inst: Inst) {
+		self.code.push(inst);
+	}
+}
+
+// The text dump: labels at the left edge, and instructions indented under them, like assembly.
+impl Display for IrFunc {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		writeln!(f, "{}:", self.name)?;
+
+		for inst in &self.code {
+			match inst {
+				Inst::Label(..) => writeln!(f, "{}", inst)?,
+				_               => writeln!(f, "    {}", inst)?,
+			}
+		}
+
+		Ok(())
+	}
+}
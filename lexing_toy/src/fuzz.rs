@@ -0,0 +1,31 @@
+use arbitrary::Unstructured;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Fuzzing
+// ------------------------------------------------------------------------------------------------
+
+/*
+See parsing_lisp's fuzz.rs for what fuzzing is. A lexer's input is just text, so the fuzzer's
+random bytes can go right in (as long as they're turned into a string first). With the "fuzz"
+feature on, Token and TokenKind also #[derive(Arbitrary)], so other fuzzers can make random token
+streams.
+
+The "123abc" and "integer out of range" checks in lex() were found by hand. Those are exactly
+the kind of edge case a fuzzer is good at finding.
+*/
+
+// This must never panic, no matter what `data` is. It can only give an Ok or an Err. It runs the
+// generated lexer too, since that has to survive the same inputs, but gives lex()'s answer.
+pub fn fuzz_lex(data: &[u8]) -> Result<Vec<Token>, LexError> {
+	let source = String::from_utf8_lossy(data);
+	let _ = lex_generated(&source);
+	lex(&source)
+}
+
+// Span comes from the diagnostics crate, which doesn't know about Arbitrary, so Token uses this to
+// make a random one. like the rest of a random token, it doesn't have to make sense.
+pub(crate) fn arbitrary_span(u: &mut Unstructured) -> arbitrary::Result<Span> {
+	Ok(Span::new(u.arbitrary()?, u.arbitrary()?))
+}
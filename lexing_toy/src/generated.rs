@@ -0,0 +1,77 @@
+use automata::DfaLexer;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The same lexer, generated from regexes
+// ------------------------------------------------------------------------------------------------
+
+/*
+lex() is written by hand from the token grammar at the top of lib.rs. This does the same job, but
+the lexer is *generated* by the automata crate from regexes for the same tokens: it turns them
+into one minimized DFA, and runs it with maximal munch. Compare how much shorter this is!
+
+The order of the rules is the same as the indexes in the match in lex_generated() below.
+*/
+const RULES: &[(&str, &str)] = &[
+	("LParen", "\\("),
+	("RParen", "\\)"),
+	("Plus",   "\\+"),
+	("Minus",  "-"),
+	("Times",  "\\*"),
+	("Divide", "/"),
+	("Modulo", "%"),
+	("Id",     "[a-zA-Z_][a-zA-Z0-9_]*"),
+	("IntLit", "\\d+"),
+];
+
+const WHITESPACE: &str = "[ \t\n]+";
+
+// Gives the same tokens and errors as lex(), except that identifiers can only have ASCII letters
+// (a regex can't easily say "any Unicode letter").
+pub fn lex_generated(source: &str) -> Result<Vec<Token>, LexError> {
+	// making the lexer isn't free, but it's fast enough for an example.
+	let lexer = DfaLexer::new(RULES, WHITESPACE).expect("the rules' regexes are wrong");
+	let chars = source.chars().collect::<Vec<_>>();
+
+	let lexemes = lexer.lex(source)
+		.map_err(|err| LexError::InvalidChar { loc: err.loc, c: err.c })?;
+
+	let mut ret = vec![];
+
+	for lexeme in lexemes {
+		let (start, end) = (lexeme.span.start, lexeme.span.end);
+
+		let kind = match lexeme.rule {
+			0 => TokenKind::LParen,
+			1 => TokenKind::RParen,
+			2 => TokenKind::Plus,
+			3 => TokenKind::Minus,
+			4 => TokenKind::Times,
+			5 => TokenKind::Divide,
+			6 => TokenKind::Modulo,
+			7 => TokenKind::Id(lexeme.text),
+
+			8 => {
+				// the same two checks that lex() does by hand, since regexes can't do either:
+				// "123abc" is an error (maximal munch would make it 123 then abc),
+				if let Some(&c) = chars.get(end).filter(|c| c.is_alphabetic()) {
+					return Err(LexError::InvalidChar { loc: end, c });
+				}
+
+				// and the number has to fit in an i64.
+				match lexeme.text.parse::<i64>() {
+					Ok(value) => TokenKind::IntLit(value),
+					Err(..)   => return Err(LexError::IntOutOfRange { start, end }),
+				}
+			}
+
+			_ => unreachable!(),
+		};
+
+		ret.push(Token::new(lexeme.span, kind));
+	}
+
+	ret.push(Token::new(Span::new(chars.len(), chars.len()), TokenKind::Eof));
+	Ok(ret)
+}
@@ -1,6 +1,19 @@
 
 use std::fmt::{ Display, Formatter, Result as FmtResult };
 
+use diagnostics::{ Diagnostic, Span, INT_OUT_OF_RANGE, INVALID_CHAR };
+
+mod generated;
+
+pub use crate::generated::*;
+
+// this module is only compiled when the "fuzz" feature is turned on.
+#[cfg(feature = "fuzz")]
+mod fuzz;
+
+#[cfg(feature = "fuzz")]
+pub use crate::fuzz::*;
+
 // ------------------------------------------------------------------------------------------------
 // Token type
 // ------------------------------------------------------------------------------------------------
@@ -10,36 +23,51 @@ Token grammar:
 
 LParen:  '('
 RParen:  ')'
+Plus:    '+'
+Minus:   '-'
+Times:   '*'
+Divide:  '/'
+Modulo:  '%'
 Id:      IdStart IdCont*
 IdStart: <alphabetic> | '_'
 IdCont:  IdStart | Digit
 IntLit:  Digit+
-Token:   LParen | RParen | Id | IntLit
+Token:   LParen | RParen | Plus | Minus | Times | Divide | Modulo | Id | IntLit
 
 Whitespace: ' ' | '\t' | '\n'
 Program:    (Whitespace? Token)* Whitespace? Eof
 */
 
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub enum TokenKind {
 	Eof,
 	LParen,
 	RParen,
+	Plus,
+	Minus,
+	Times,
+	Divide,
+	Modulo,
 	Id(String),
 	IntLit(i64),
 }
 
+// the span is *where* in the source code the token is: the codepoint index where it starts, and
+// the one just after it ends. so errors about a token (like a parser's) can point right at it.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub struct Token {
-	pub loc:  usize, // the codepoint index in the source code.
+	#[cfg_attr(feature = "fuzz", arbitrary(with = crate::fuzz::arbitrary_span))]
+	pub span: Span,
 	pub kind: TokenKind,
 }
 
 impl Token {
 	// Self is a "magical type" that can be used in impl blocks, and refers to the type
 	// that the impl is attached to. Here it means "Token".
-	pub fn new(loc: usize, kind: TokenKind) -> Self {
-		Token { loc, kind }
+	pub fn new(span: Span, kind: TokenKind) -> Self {
+		Token { span, kind }
 	}
 }
 
@@ -47,29 +75,49 @@ impl Token {
 // LexError type
 // ------------------------------------------------------------------------------------------------
 
+// like Token, these remember *where* the error happened, so it can be pointed out to the user.
 #[derive(Debug)]
 pub enum LexError {
-	InvalidChar(char),
-	IntOutOfRange,
+	InvalidChar   { loc: usize, c: char },
+	IntOutOfRange { start: usize, end: usize },
 }
 
 impl Display for LexError {
 	fn fmt(&self, f: &mut Formatter) -> FmtResult {
 		match self {
-			LexError::InvalidChar(c) => write!(f, "invalid character '{}'", c.escape_debug()),
-			LexError::IntOutOfRange  => write!(f, "integer out of range"),
+			LexError::InvalidChar { c, .. } => write!(f, "invalid character '{}'", c.escape_debug()),
+			LexError::IntOutOfRange { .. }  => write!(f, "integer out of range"),
 		}
 	}
 }
 
 impl std::error::Error for LexError {}
 
+// this lets you write `let d: Diagnostic = err.into();` (or use ? in a function that returns
+// Diagnostic errors), so the error can be shown with the source code it came from.
+impl From<LexError> for Diagnostic {
+	fn from(err: LexError) -> Diagnostic {
+		let (span, code) = match err {
+			LexError::InvalidChar { loc, .. }     => (Span::point(loc), INVALID_CHAR),
+			LexError::IntOutOfRange { start, end } => (Span::new(start, end), INT_OUT_OF_RANGE),
+		};
+
+		let diag = Diagnostic::error(err.to_string(), span).with_code(code);
+
+		match err {
+			LexError::IntOutOfRange { .. } =>
+				diag.with_note(format!("integers can be at most {}", i64::MAX)),
+			_ => diag,
+		}
+	}
+}
+
 // ------------------------------------------------------------------------------------------------
 // The lexer algorithm
 // ------------------------------------------------------------------------------------------------
 
-fn is_ident_start(c: char) -> bool { c.is_alphabetic() || c == '_' }
-fn is_ident_cont (c: char) -> bool { is_ident_start(c) || c.is_ascii_digit() }
+pub fn is_ident_start(c: char) -> bool { c.is_alphabetic() || c == '_' }
+pub fn is_ident_cont (c: char) -> bool { is_ident_start(c) || c.is_ascii_digit() }
 
 /*
 Result<R, E> is how functions return errors in Rust. R is the return type if it succeeds; E is the
@@ -96,65 +144,87 @@ pub fn lex(source: &str) -> Result<Vec<Token>, LexError> {
 				pos += 1;
 			}
 
-			// LParen
-			'(' => {
-				ret.push(Token::new(pos, TokenKind::LParen));
-				pos += 1;
-			}
-
-			// RParen
-			')' => {
-				ret.push(Token::new(pos, TokenKind::RParen));
-				pos += 1;
-			}
-
 			// Id
 			c if is_ident_start(c) => {
 				let start = pos;
-				let mut s = String::new();
-
-				while pos < source.len() && is_ident_cont(source[pos]) {
-					s.push(source[pos]);
-					pos += 1;
-				}
-
-				ret.push(Token::new(start, TokenKind::Id(s)));
+				let s = lex_ident(&source, &mut pos);
+				ret.push(Token::new(Span::new(start, pos), TokenKind::Id(s)));
 			}
 
 			// IntLit
 			c if c.is_ascii_digit() => {
+				// lex_int() gives an error for things like "123abc", and the ? returns it from
+				// this function right away.
 				let start = pos;
-				let mut num = String::new();
-
-				while pos < source.len() && source[pos].is_ascii_digit() {
-					num.push(source[pos]);
-					pos += 1;
-				}
-
-				// this rule makes things like "123abc" invalid. this is actually
-				// a lookahead because we're just checking the next character without
-				// making it part of this token.
-				if pos < source.len() && source[pos].is_alphabetic() {
-					return Err(LexError::InvalidChar(source[pos]));
-				}
-
-				// some rules, like "can't exceed the capacity of a 64-bit integer," can't
-				// be encoded in the grammar rules and have to be checked manually.
-				match i64::from_str_radix(&num, 10) {
-					Ok(value) => {
-						ret.push(Token::new(start, TokenKind::IntLit(value)));
-					}
-
-					Err(..) => return Err(LexError::IntOutOfRange),
-				}
+				let value = lex_int(&source, &mut pos)?;
+				ret.push(Token::new(Span::new(start, pos), TokenKind::IntLit(value)));
 			}
 
-			c => return Err(LexError::InvalidChar(c))
+			// all the one-character tokens.
+			c => {
+				let kind = match c {
+					'(' => TokenKind::LParen,
+					')' => TokenKind::RParen,
+					'+' => TokenKind::Plus,
+					'-' => TokenKind::Minus,
+					'*' => TokenKind::Times,
+					'/' => TokenKind::Divide,
+					'%' => TokenKind::Modulo,
+					_   => return Err(LexError::InvalidChar { loc: pos, c }),
+				};
+
+				ret.push(Token::new(Span::point(pos), kind));
+				pos += 1;
+			}
 		}
 	}
 
-	ret.push(Token::new(pos, TokenKind::Eof));
+	// Eof has nothing in it, so its span is empty.
+	ret.push(Token::new(Span::new(pos, pos), TokenKind::Eof));
 
 	// we indicate success by returning an Ok(..) value.
 	Ok(ret)
+}
+
+/*
+The Id and IntLit rules are split out into their own functions, so that other lexers can use them
+too (toylang's does). Each one starts at `pos`, which has to be at the first character of the
+token, and moves `pos` past the end of it.
+
+`&mut usize` is a *mutable reference*: the function can change the caller's variable through it,
+with `*pos += 1`.
+*/
+
+// Id. `source[*pos]` has to be an IdStart.
+pub fn lex_ident(source: &[char], pos: &mut usize) -> String {
+	let mut s = String::new();
+
+	while *pos < source.len() && is_ident_cont(source[*pos]) {
+		s.push(source[*pos]);
+		*pos += 1;
+	}
+
+	s
+}
+
+// IntLit. `source[*pos]` has to be a digit.
+pub fn lex_int(source: &[char], pos: &mut usize) -> Result<i64, LexError> {
+	let start = *pos;
+	let mut num = String::new();
+
+	while *pos < source.len() && source[*pos].is_ascii_digit() {
+		num.push(source[*pos]);
+		*pos += 1;
+	}
+
+	// this rule makes things like "123abc" invalid. this is actually
+	// a lookahead because we're just checking the next character without
+	// making it part of this token.
+	if *pos < source.len() && source[*pos].is_alphabetic() {
+		return Err(LexError::InvalidChar { loc: *pos, c: source[*pos] });
+	}
+
+	// some rules, like "can't exceed the capacity of a 64-bit integer," can't
+	// be encoded in the grammar rules and have to be checked manually.
+	num.parse::<i64>().map_err(|_| LexError::IntOutOfRange { start, end: *pos })
 }
\ No newline at end of file
@@ -1,9 +1,22 @@
+use std::process::exit;
+
 use colored::Colorize;
 use rustyline::{ Editor, KeyEvent, KeyCode, Modifiers, Cmd, error::ReadlineError };
 
+use diagnostics::{ ColorChoice, Diagnostic, SourceFile };
 use lexing_toy::*;
 
 fn main() {
+	// you can run this like `cargo run -- --color=never` to turn off colors in error messages.
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+
+	// `cargo run -- --generated` uses the lexer generated from regexes instead of the one
+	// written by hand. they should give the same tokens!
+	let generated = std::env::args().any(|a| a == "--generated");
+
 	let mut rl = Editor::<()>::new();
 	rl.bind_sequence(KeyEvent(KeyCode::Tab, Modifiers::NONE),   Cmd::Insert(1, "\t".into()));
 	rl.bind_sequence(KeyEvent(KeyCode::Down, Modifiers::SHIFT), Cmd::Insert(1, "\n".into()));
@@ -20,7 +33,7 @@ fn main() {
 		match line {
 			Ok(line) => {
 				rl.add_history_entry(line.as_str());
-				show_tokens(&line);
+				show_tokens(&line, color, generated);
 			},
 			Err(ReadlineError::Interrupted) => break,
 			Err(ReadlineError::Eof) => break,
@@ -34,8 +47,10 @@ fn main() {
 	println!("byeeeeee!");
 }
 
-fn show_tokens(line: &str) {
-	match lex(line) {
+fn show_tokens(line: &str, color: ColorChoice, generated: bool) {
+	let result = if generated { lex_generated(line) } else { lex(line) };
+
+	match result {
 		Ok(tokens) => {
 			println!("{} ", "Tokens:".green());
 
@@ -43,7 +58,11 @@ fn show_tokens(line: &str) {
 				println!("   {:?}", t);
 			}
 		}
-		Err(e) => println!("{} {}", "error:".red(), e),
+		Err(e) => {
+			// turning the LexError into a Diagnostic lets us show where the error is.
+			let source = SourceFile::new("<input>", line);
+			print!("{}", Diagnostic::from(e).render_colored(&source, color));
+		}
 	}
 
 	println!();
@@ -0,0 +1,49 @@
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion, Throughput };
+use parsing_lisp::*;
+
+// ------------------------------------------------------------------------------------------------
+// Benchmarks
+// ------------------------------------------------------------------------------------------------
+
+/*
+`cargo bench` runs these, using the criterion crate. It runs parse() over and over until it has a
+good estimate of how long it takes, and it remembers the results, so the next run tells you if it
+got faster or slower.
+
+The input is a big list of little function calls with long-ish names, like
+`(program (add_numbers variable_0 0) (add_numbers variable_1 1) ...)`. Most of the tokens are Ids,
+which is where cloning tokens used to hurt: the parser used to clone the current token (and so
+the String inside it) every time it looked at it, which was two or three times per token.
+*/
+
+// about 5 tokens per call.
+fn make_tokens(calls: usize) -> Vec<Token> {
+	let mut ret = vec![Token::LParen, Token::Id("program".into())];
+
+	for i in 0 .. calls {
+		ret.push(Token::LParen);
+		ret.push(Token::Id("add_numbers".into()));
+		ret.push(Token::Id(format!("variable_{}", i)));
+		ret.push(Token::IntLit(i as i64));
+		ret.push(Token::RParen);
+	}
+
+	ret.push(Token::RParen);
+	ret
+}
+
+fn bench_parse(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse");
+
+	for &calls in &[100, 10_000, 100_000] {
+		let tokens = make_tokens(calls);
+		group.throughput(Throughput::Elements(tokens.len() as u64));
+		group.bench_with_input(BenchmarkId::from_parameter(tokens.len()), &tokens,
+			|b, tokens| b.iter(|| parse(tokens).unwrap()));
+	}
+
+	group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
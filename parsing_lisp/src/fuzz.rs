@@ -0,0 +1,65 @@
+use arbitrary::{ Arbitrary, Result as ArbResult, Unstructured };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Fuzzing
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *fuzzer* (like cargo-fuzz) runs a function millions of times on random inputs, looking for any
+input that makes it crash. Completely random bytes are a bad way to test a parser, since almost
+none of them look anything like a program. The arbitrary crate fixes that: it turns the fuzzer's
+random bytes into random *values* of our types, so the parser gets random token streams instead.
+
+Token gets a #[derive(Arbitrary)], so any token can show up anywhere. That's what we want for
+finding crashes: the parser has to give an error for garbage, not panic.
+
+AstNode's Arbitrary is written by hand, because it should only make ASTs that the parser could
+actually give back: every Exp has at least one thing in it, numbers aren't negative (the lexer can't
+make negative IntLits), and identifiers are real identifiers.
+*/
+
+// This must never panic, no matter what `data` is. It can only give an Ok or an Err.
+pub fn fuzz_parse_lisp(data: &[u8]) -> Result<Box<AstNode>, ParseError> {
+	let tokens = Vec::<Token>::arbitrary_take_rest(Unstructured::new(data)).unwrap_or_default();
+	parse(&tokens)
+}
+
+// without a limit, a random AST could be nested thousands of levels deep.
+const MAX_DEPTH: usize = 6;
+
+impl<'a> Arbitrary<'a> for AstNode {
+	fn arbitrary(u: &mut Unstructured<'a>) -> ArbResult<Self> {
+		arbitrary_exp(u, MAX_DEPTH)
+	}
+}
+
+fn arbitrary_exp(u: &mut Unstructured, depth: usize) -> ArbResult<AstNode> {
+	// at the maximum depth (or when the fuzzer's bytes run out), only Ids and Nums.
+	let can_nest = depth > 0 && !u.is_empty();
+
+	Ok(match u.int_in_range(0 ..= if can_nest { 2 } else { 1 })? {
+		0 => AstNode::Id(arbitrary_ident(u)?),
+		1 => AstNode::Num(u.int_in_range(0 ..= i64::MAX)?),
+		_ => {
+			let len = u.int_in_range(1 ..= 4)?;
+			let exps = (0 .. len).map(|_| arbitrary_exp(u, depth - 1).map(Box::new));
+			AstNode::Exp(exps.collect::<ArbResult<_>>()?)
+		}
+	})
+}
+
+// a random name that lexes as an Id.
+fn arbitrary_ident(u: &mut Unstructured) -> ArbResult<String> {
+	const START: &[char] = &['a', 'b', 'f', 'x', 'y', 'z', 'A', 'Q', '_'];
+	const CONT:  &[char] = &['a', 'e', 'r', 'x', 'Z', '_', '0', '7', '9'];
+
+	let mut ret = u.choose(START)?.to_string();
+
+	for _ in 0 .. u.int_in_range(0 ..= 4)? {
+		ret.push(*u.choose(CONT)?);
+	}
+
+	Ok(ret)
+}
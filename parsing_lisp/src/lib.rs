@@ -1,6 +1,18 @@
 
 use std::fmt::{ Debug, Display, Formatter, Result as FmtResult };
 
+use diagnostics::{ Diagnostic, ErrorCode, Severity };
+
+mod to_source;
+pub use crate::to_source::*;
+
+// this module is only compiled when the "fuzz" feature is turned on.
+#[cfg(feature = "fuzz")]
+mod fuzz;
+
+#[cfg(feature = "fuzz")]
+pub use crate::fuzz::*;
+
 // ------------------------------------------------------------------------------------------------
 // Token type
 // ------------------------------------------------------------------------------------------------
@@ -10,6 +22,7 @@ These tokens would be produced by a lexer... IF WE HAD ONE
 Well, another example has one. Maybe you can put the two examples together!
 */
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub enum Token {
 	Eof,
 	LParen,
@@ -51,7 +64,7 @@ readable, we just write them explicitly.
 	Eof: <'<eof>' token from lexing phase>
 */
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum AstNode {
 	Id(String),
 	Num(i64),
@@ -116,6 +129,76 @@ impl Display for ParseError {
 
 impl std::error::Error for ParseError {}
 
+impl ParseError {
+	// the stable error code for this error (see the diagnostics crate).
+	pub fn code(&self) -> ErrorCode {
+		use ParseError::*;
+
+		match self {
+			ExpectedExpression => diagnostics::EXPECTED_EXPRESSION,
+			ExpectedLParen     => diagnostics::EXPECTED_LPAREN,
+			ExpectedRParen     => diagnostics::EXPECTED_RPAREN,
+			ExpectedEof        => diagnostics::EXPECTED_EOF,
+		}
+	}
+}
+
+// Our tokens don't know where they came from in the source code, so neither do these errors. The
+// Diagnostic doesn't get a span, and it'll be shown without any source code.
+impl From<ParseError> for Diagnostic {
+	fn from(err: ParseError) -> Diagnostic {
+		Diagnostic::new(Severity::Error, err.to_string(), None).with_code(err.code())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Tracing
+// ------------------------------------------------------------------------------------------------
+
+/*
+When you change a grammar and suddenly something doesn't parse, it helps to see *exactly* what the
+parser did. parse_traced() reports every time the parser starts trying a rule (with the token it's
+looking at), and every time it finishes one (and whether it worked). Since each rule is a function,
+and rules call other rules, that's a tree, and Display indents each event by how deep it is:
+
+	Program at #0 '('
+	  Exp at #0 '('
+	    ParenExp at #0 '('
+	      Exp at #1 'add'
+	      Exp ok, now at #2
+	      ...
+
+That's the *derivation* of the input: which rule matched which tokens, from the top down.
+*/
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TraceEvent<'t> {
+	// the parser is starting to parse `rule`, looking at `token` (which is tokens[pos]).
+	Enter { rule: &'static str, depth: usize, pos: usize, token: &'t Token },
+
+	// the parser finished `rule`, and is now at tokens[pos]. `ok` is false if it was an error.
+	Exit { rule: &'static str, depth: usize, pos: usize, ok: bool },
+}
+
+impl Display for TraceEvent<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			TraceEvent::Enter { rule, depth, pos, token } => {
+				let token = match token {
+					Token::Eof => "end of input".to_string(),
+					t          => format!("'{}'", t),
+				};
+
+				write!(f, "{:indent$}{} at #{} {}", "", rule, pos, token, indent = depth * 2)
+			}
+
+			TraceEvent::Exit { rule, depth, pos, ok } => {
+				let result = if *ok { "ok" } else { "FAILED" };
+				write!(f, "{:indent$}{} {}, now at #{}", "", rule, result, pos, indent = depth * 2)
+			}
+		}
+	}
+}
+
 // ------------------------------------------------------------------------------------------------
 // The recursive descent parser
 // ------------------------------------------------------------------------------------------------
@@ -125,18 +208,27 @@ impl std::error::Error for ParseError {}
 type ParseResult = Result<Box<AstNode>, ParseError>;
 
 pub fn parse(tokens: &[Token]) -> ParseResult {
-	let mut p = Parser::new(tokens);
+	let mut p = Parser::new(tokens, None);
+	p.parse_program()
+}
+
+// Like parse(), but it calls `tracer` every time it starts and finishes a grammar rule. See
+// TraceEvent below.
+pub fn parse_traced(tokens: &[Token], tracer: &mut dyn FnMut(&TraceEvent)) -> ParseResult {
+	let mut p = Parser::new(tokens, Some(tracer));
 	p.parse_program()
 }
 
-struct Parser<'t> {
+struct Parser<'t, 'f> {
 	tokens: &'t [Token],
 	pos:    usize,
+	tracer: Option<&'f mut dyn FnMut(&TraceEvent)>,
+	depth:  usize, // how many rules deep we are, for the tracer.
 }
 
-impl<'t> Parser<'t> {
-	fn new(tokens: &'t [Token]) -> Self {
-		Parser { tokens, pos: 0 }
+impl<'t, 'f> Parser<'t, 'f> {
+	fn new(tokens: &'t [Token], tracer: Option<&'f mut dyn FnMut(&TraceEvent)>) -> Self {
+		Parser { tokens, pos: 0, tracer, depth: 0 }
 	}
 
 	fn next(&mut self) {
@@ -144,50 +236,81 @@ impl<'t> Parser<'t> {
 		self.pos += 1;
 	}
 
-	fn cur(&self) -> Token {
-		if self.pos < self.tokens.len() {
-			self.tokens[self.pos].clone()
-		} else {
-			Token::Eof
+	// this gives a *reference* to the token, instead of a copy. cloning a Token::Id means cloning
+	// its String, which allocates memory, and the parser looks at the current token a *lot*.
+	// the reference lives as long as the tokens do ('t), not just as long as the borrow of self,
+	// so it's fine to call self.next() while holding onto it.
+	fn cur(&self) -> &'t Token {
+		self.tokens.get(self.pos).unwrap_or(&Token::Eof)
+	}
+
+	// runs one grammar rule's parsing code, and tells the tracer (if there is one) when it starts
+	// and finishes. parse() has no tracer, so this just calls `rule_fn`.
+	fn rule(&mut self, rule: &'static str, rule_fn: fn(&mut Self) -> ParseResult) -> ParseResult {
+		if self.tracer.is_none() {
+			return rule_fn(self);
+		}
+
+		let (depth, pos, token) = (self.depth, self.pos, self.cur());
+		self.trace(&TraceEvent::Enter { rule, depth, pos, token });
+
+		self.depth += 1;
+		let ret = rule_fn(self);
+		self.depth -= 1;
+
+		let ok = ret.is_ok();
+		self.trace(&TraceEvent::Exit { rule, depth, pos: self.pos, ok });
+		ret
+	}
+
+	fn trace(&mut self, event: &TraceEvent) {
+		if let Some(tracer) = &mut self.tracer {
+			tracer(event);
 		}
 	}
 
 	// Program: Exp Eof
 	fn parse_program(&mut self) -> ParseResult {
-		let ret = self.parse_exp()?;
-		self.expect_eof()?;
-		Ok(ret)
+		self.rule("Program", |p| {
+			let ret = p.parse_exp()?;
+			p.expect_eof()?;
+			Ok(ret)
+		})
 	}
 
 	// Exp: Id | Num | ParenExp
 	fn parse_exp(&mut self) -> ParseResult {
 		use Token::*;
 
-		match self.cur() {
-			Id(s)     => { self.next(); Ok(AstNode::id(&s)) }
-			IntLit(i) => { self.next(); Ok(AstNode::num(i)) }
-			LParen    => self.parse_paren_exp(),
-			_         => Err(ParseError::ExpectedExpression),
-		}
+		self.rule("Exp", |p| {
+			match p.cur() {
+				Id(s)     => { p.next(); Ok(AstNode::id(s)) }
+				IntLit(i) => { p.next(); Ok(AstNode::num(*i)) }
+				LParen    => p.parse_paren_exp(),
+				_         => Err(ParseError::ExpectedExpression),
+			}
+		})
 	}
 
 	// ParenExp: '(' Exp+ ')'
 	fn parse_paren_exp(&mut self) -> ParseResult {
-		// Note the use of ? here. It means, "if expect_lparen() returned an error, then return
-		// that error; otherwise, carry on as usual."
-		self.expect_lparen()?;
+		self.rule("ParenExp", |p| {
+			// Note the use of ? here. It means, "if expect_lparen() returned an error, then
+			// return that error; otherwise, carry on as usual."
+			p.expect_lparen()?;
 
-		let mut exps = Vec::new();
-		exps.push(self.parse_exp()?); // and here
+			let mut exps = Vec::new();
+			exps.push(p.parse_exp()?); // and here
 
-		while self.cur() != Token::RParen {
-			exps.push(self.parse_exp()?); // and here
-		}
+			while *p.cur() != Token::RParen {
+				exps.push(p.parse_exp()?); // and here
+			}
 
-		self.expect_rparen()?; // and here!
+			p.expect_rparen()?; // and here!
 
-		// and if we made it to the end of this method, everything is Ok()!
-		Ok(AstNode::exp(exps))
+			// and if we made it to the end of this method, everything is Ok()!
+			Ok(AstNode::exp(exps))
+		})
 	}
 
 	// () is Rust's void.
@@ -1,4 +1,5 @@
 
+use diagnostics::{ Diagnostic, SourceFile };
 use parsing_lisp::*;
 
 fn main() {
@@ -44,12 +45,26 @@ fn id(s: &str) -> Token {
 // &[Token] is to Vec<Token> as &str is to String.
 // &[Token] is a slice type, meaning this function can accept any type which can be
 // sliced (including Vecs and arrays).
+//
+// `cargo run -- --trace` also shows every grammar rule the parser tried, and where.
 fn parse_it(tokens: &[Token]) {
 	show_tokens(&tokens);
 
-	match parse(&tokens) {
-		Ok(ast)  => println!("AST: {:#?}", ast),
-		Err(err) => println!("parse error: {}", err),
+	let result = if std::env::args().any(|a| a == "--trace") {
+		parse_traced(tokens, &mut |event| println!("{}", event))
+	} else {
+		parse(tokens)
+	};
+
+	match result {
+		Ok(ast) => {
+			println!("AST: {:#?}", ast);
+			println!("Source: {}", ast.to_source());
+			assert!(round_trips(&ast));
+		}
+
+		// there's no source code to show, so this just shows the message (and any notes).
+		Err(err) => print!("{}", Diagnostic::from(err).render(&SourceFile::new("<tokens>", ""))),
 	}
 
 	println!();
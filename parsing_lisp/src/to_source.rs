@@ -0,0 +1,87 @@
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Turning ASTs back into source code
+// ------------------------------------------------------------------------------------------------
+
+/*
+The Debug output shows how something was parsed, but it isn't code. to_source() gives back code,
+and that code parses into the exact same AST: `parse(to_source(ast)) == ast`. For this language
+that's pretty easy, since the parentheses *are* the structure. Every Exp gets one pair, and the
+things inside are separated by single spaces. Numbers are plain decimal, with no leading zeros or
+'+' (but with a '-' if they're negative).
+
+The parsing_math example has the same trait, and there it's a lot more interesting, because that
+language has precedence.
+
+round_trips() checks that property, and the fuzz folder has a fuzzer that checks it for lots of
+random ASTs. It only holds for ASTs that the parser could give back, though: an Exp with nothing
+in it, or an Id like "hello world", don't have any source code that parses into them.
+*/
+pub trait ToSource {
+	fn to_source(&self) -> String;
+}
+
+impl ToSource for AstNode {
+	fn to_source(&self) -> String {
+		match self {
+			AstNode::Id(id) => id.clone(),
+			AstNode::Num(i) => i.to_string(),
+			AstNode::Exp(exps) => {
+				let exps = exps.iter().map(|e| e.to_source()).collect::<Vec<_>>();
+				format!("({})", exps.join(" "))
+			}
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Round trips
+// ------------------------------------------------------------------------------------------------
+
+// This crate doesn't have a real lexer (see the Token type), but checking a round trip needs one.
+// This is just enough of one to read what to_source() writes. It gives None if there's a
+// character that can't be part of any token.
+pub fn tokenize(src: &str) -> Option<Vec<Token>> {
+	let chars = src.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut ret = vec![];
+
+	while pos < chars.len() {
+		let start = pos;
+		pos += 1;
+
+		ret.push(match chars[start] {
+			c if c.is_whitespace() => continue,
+			'(' => Token::LParen,
+			')' => Token::RParen,
+
+			c if c.is_alphabetic() || c == '_' => {
+				while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+					pos += 1;
+				}
+
+				Token::Id(chars[start .. pos].iter().collect())
+			}
+
+			// digits, maybe with a '-' in front.
+			c if c.is_ascii_digit() || c == '-' => {
+				while pos < chars.len() && chars[pos].is_ascii_digit() {
+					pos += 1;
+				}
+
+				Token::IntLit(chars[start .. pos].iter().collect::<String>().parse().ok()?)
+			}
+
+			_ => return None,
+		});
+	}
+
+	Some(ret)
+}
+
+// Does this AST turn into source code that parses back into the same AST?
+pub fn round_trips(ast: &AstNode) -> bool {
+	let parsed = tokenize(&ast.to_source()).and_then(|tokens| parse(&tokens).ok());
+	parsed.is_some_and(|parsed| *parsed == *ast)
+}
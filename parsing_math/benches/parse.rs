@@ -0,0 +1,53 @@
+use criterion::{ criterion_group, criterion_main, BenchmarkId, Criterion, Throughput };
+use parsing_math::*;
+
+// ------------------------------------------------------------------------------------------------
+// Benchmarks
+// ------------------------------------------------------------------------------------------------
+
+/*
+`cargo bench` runs these, using the criterion crate. It runs parse_exp() over and over until it
+has a good estimate of how long it takes, and it remembers the results, so the next run tells you
+if it got faster or slower.
+
+The input is one long expression like `-total_0 * scale(value_0) + total_1 * scale(value_1) ...`.
+Most of the tokens are Ids, which is where cloning tokens used to hurt: the parser used to clone
+the current token (and so the String inside it) every time it looked at it.
+*/
+
+// 8 tokens per term (counting the + or - before it).
+fn make_tokens(terms: usize) -> Vec<Token> {
+	let mut ret = vec![];
+
+	for i in 0 .. terms {
+		if i > 0 {
+			ret.push(if i % 2 == 0 { Token::Plus } else { Token::Minus });
+		}
+
+		ret.push(Token::Minus);
+		ret.push(Token::Id(format!("total_{}", i)));
+		ret.push(Token::Times);
+		ret.push(Token::Id("scale".into()));
+		ret.push(Token::LParen);
+		ret.push(Token::Id(format!("value_{}", i)));
+		ret.push(Token::RParen);
+	}
+
+	ret
+}
+
+fn bench_parse(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse_exp");
+
+	for &terms in &[100, 10_000, 100_000] {
+		let tokens = make_tokens(terms);
+		group.throughput(Throughput::Elements(tokens.len() as u64));
+		group.bench_with_input(BenchmarkId::from_parameter(tokens.len()), &tokens,
+			|b, tokens| b.iter(|| parse_exp(tokens).unwrap()));
+	}
+
+	group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
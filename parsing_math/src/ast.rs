@@ -6,7 +6,8 @@ use std::fmt::{ Display, Formatter, Result as FmtResult };
 // AstNode
 // ------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone)]
+// PartialEq is so that to_source() can check that an AST parses back into the same AST.
+#[derive(Debug, Clone, PartialEq)]
 pub enum AstNode {
 	Const  { val: f64 },
 	Ident  { name: String },
@@ -76,7 +77,8 @@ impl AstNode {
 // BinOp
 // ------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub enum BinOp {
 	Add, Sub, Mul, Div, Mod
 }
@@ -0,0 +1,69 @@
+use arbitrary::{ Arbitrary, Result as ArbResult, Unstructured };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Fuzzing
+// ------------------------------------------------------------------------------------------------
+
+/*
+See parsing_lisp's fuzz.rs for what this is all about. It's the same idea here: Token and BinOp
+#[derive(Arbitrary)], so the parser gets completely random token streams, and AstNode's Arbitrary
+is written by hand so it only makes ASTs the parser could give back. (Constants are never negative,
+since -5 is a Negate around a 5.)
+*/
+
+// This must never panic, no matter what `data` is. It can only give an Ok or an Err.
+pub fn fuzz_parse_math(data: &[u8]) -> Result<Box<AstNode>, ParseError> {
+	let tokens = Vec::<Token>::arbitrary_take_rest(Unstructured::new(data)).unwrap_or_default();
+	parse_exp(&tokens)
+}
+
+// without a limit, a random AST could be nested thousands of levels deep.
+const MAX_DEPTH: usize = 6;
+
+impl<'a> Arbitrary<'a> for AstNode {
+	fn arbitrary(u: &mut Unstructured<'a>) -> ArbResult<Self> {
+		arbitrary_exp(u, MAX_DEPTH)
+	}
+}
+
+fn arbitrary_exp(u: &mut Unstructured, depth: usize) -> ArbResult<AstNode> {
+	// at the maximum depth (or when the fuzzer's bytes run out), only constants and names.
+	let can_nest = depth > 0 && !u.is_empty();
+
+	Ok(match u.int_in_range(0 ..= if can_nest { 4 } else { 1 })? {
+		0 => {
+			let val = f64::arbitrary(u)?.abs();
+			AstNode::Const { val: if val.is_finite() { val } else { 0.0 } }
+		}
+
+		1 => AstNode::Ident { name: arbitrary_ident(u)? },
+		2 => AstNode::Negate { lhs: Box::new(arbitrary_exp(u, depth - 1)?) },
+
+		3 => AstNode::Binary {
+			op:  BinOp::arbitrary(u)?,
+			lhs: Box::new(arbitrary_exp(u, depth - 1)?),
+			rhs: Box::new(arbitrary_exp(u, depth - 1)?),
+		},
+
+		_ => AstNode::Call {
+			callee: Box::new(arbitrary_exp(u, depth - 1)?),
+			arg:    Box::new(arbitrary_exp(u, depth - 1)?),
+		},
+	})
+}
+
+// a random name that would lex as an identifier.
+fn arbitrary_ident(u: &mut Unstructured) -> ArbResult<String> {
+	const START: &[char] = &['a', 'b', 'f', 'x', 'y', 'z', 'A', 'Q', '_'];
+	const CONT:  &[char] = &['a', 'e', 'r', 'x', 'Z', '_', '0', '7', '9'];
+
+	let mut ret = u.choose(START)?.to_string();
+
+	for _ in 0 .. u.int_in_range(0 ..= 4)? {
+		ret.push(*u.choose(CONT)?);
+	}
+
+	Ok(ret)
+}
@@ -1,6 +1,9 @@
 
 use std::fmt::{ Display, Formatter, Result as FmtResult };
 
+use diagnostics::{ Diagnostic, ErrorCode, Severity, Span };
+use pratt::{ Assoc, Builder, OpTable };
+
 // this line says that the "ast" module *exists*...
 mod ast;
 
@@ -8,11 +11,22 @@ mod ast;
 // uses this crate sees all those things as well.
 pub use crate::ast::*;
 
+mod to_source;
+pub use crate::to_source::*;
+
+// this module is only compiled when the "fuzz" feature is turned on.
+#[cfg(feature = "fuzz")]
+mod fuzz;
+
+#[cfg(feature = "fuzz")]
+pub use crate::fuzz::*;
+
 // ------------------------------------------------------------------------------------------------
 // Token
 // ------------------------------------------------------------------------------------------------
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
 pub enum Token {
 	Eof,
 	LParen,
@@ -58,52 +72,35 @@ enum Precedence {
 	// from LOWEST to HIGHEST. This is the opposite order from how it's
 	// shown on the slides!!
 	// ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
-	None, // A special value lower than any real precedence.
-
 	Add,  // + and -
 	Mul,  // *, /, and %
+	Neg,  // unary -
+	Call, // f(x)
 
-	// we don't need to list unary operators here, because they
-	// are handled separately from binary operators.
+	// unary and postfix operators are listed too, since the pratt crate handles all three kinds
+	// the same way. -f(x) is -(f(x)), since Call is higher than Neg, and -a * b is (-a) * b,
+	// since Neg is higher than Mul.
 }
 
-impl Precedence {
-	// This is how you put a constant "inside" a type, so this can be
-	// accessed as `Precedence::MIN` elsewhere.
-	const MIN : Precedence = Precedence::Add;
-
-	// a more english-y way of testing if self >= other.
-	fn is_at_least(&self, other: Precedence) -> bool {
-		*self >= other
-	}
-
-	// same but for self > other.
-	fn is_higher_than(&self, other: Precedence) -> bool {
-		*self > other
-	}
+// all the operators in the language. this is the *only* place that knows about precedence!
+// wait -- isn't the Minus token used for both subtraction AND negation? yes, but the parser only
+// looks in the prefix operators before an operand, and in the infix ones after it.
+fn op_table() -> OpTable<Token, Precedence> {
+	use Token::*;
+
+	OpTable::new()
+		.infix(Plus,     Precedence::Add, Assoc::Left)
+		.infix(Minus,    Precedence::Add, Assoc::Left)
+		.infix(Times,    Precedence::Mul, Assoc::Left)
+		.infix(Divide,   Precedence::Mul, Assoc::Left)
+		.infix(Modulo,   Precedence::Mul, Assoc::Left)
+		.prefix(Minus,   Precedence::Neg)
+		.postfix(LParen, Precedence::Call)
 }
 
 impl Token {
-	// the Precedence of this token (or Precedence::None if it's not an operator).
-	fn precedence(&self) -> Precedence {
-		use Token::*;
-
-		match self {
-			// wait -- isn't the Minus token used for both subtraction AND negation?
-			// yes, but due to the way the algorithm works, this method will only be
-			// called in cases where it's unambiguously being used as a subtraction.
-
-			Plus | Minus            => Precedence::Add,
-			Times | Divide | Modulo => Precedence::Mul,
-
-			// all other tokens have no precedence. this is how the expression
-			// parser knows when to stop parsing.
-			_ => Precedence::None,
-		}
-	}
-
 	// returns the BinOp enumeration value this token corresponds to. panics if it's
-	// not an operator token. this just simplifies the code in parse_binops.
+	// not an operator token. this just simplifies the code in AstBuilder::infix.
 	fn to_binop(&self) -> BinOp {
 		use Token::*;
 
@@ -119,163 +116,141 @@ impl Token {
 }
 
 // ------------------------------------------------------------------------------------------------
-// The bottom-up expression parser
+// ParseError
 // ------------------------------------------------------------------------------------------------
 
-// I'm keeping the code smaller by having the parser give a String for errors;
-// this is bad design, don't follow my lead here lolol
-type ParseResult = Result<Box<AstNode>, String>;
-
-pub fn parse_exp(tokens: &[Token]) -> ParseResult {
-	let mut p = Parser::new(tokens);
-	let ret = p.parse_exp()?;
-	p.expect_eof()?;
-	Ok(ret)
-}
-
-struct Parser<'t> {
-	tokens: &'t [Token],
-	pos:    usize,
+// This parser used to just give a String for errors, which was easy but bad design: there's no
+// way to tell *where* the error was, or turn it into anything else. So here's a real error type.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+	pub token_index: usize,     // the index of the token the parser was looking at.
+	pub code:        ErrorCode, // the stable error code (see the diagnostics crate).
+	pub message:     String,
 }
 
-impl<'t> Parser<'t> {
-	fn new(tokens: &'t [Token]) -> Self {
-		Parser { tokens, pos: 0 }
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}", self.message)
 	}
+}
 
-	fn next(&mut self) {
-		assert!(self.pos < self.tokens.len());
-		self.pos += 1;
-	}
+impl std::error::Error for ParseError {}
 
-	fn cur(&self) -> Token {
-		if self.pos < self.tokens.len() {
-			self.tokens[self.pos].clone()
-		} else {
-			Token::Eof
-		}
+// Our tokens don't know where they came from in the source code, so the best we can do is say
+// which token it was.
+impl From<ParseError> for Diagnostic {
+	fn from(err: ParseError) -> Diagnostic {
+		Diagnostic::new(Severity::Error, err.message, None)
+			.with_code(err.code)
+			.with_note(format!("at token #{}", err.token_index + 1))
 	}
+}
 
-	// Exp: Term (BinOp Term)*
-	fn parse_exp(&mut self) -> ParseResult {
-		// this line just does the first Term in the rule,
-		let lhs = self.parse_term()?;
+impl ParseError {
+	// But whoever lexed them might know (lexing_toy's tokens have spans), and then the error can
+	// point right at the token. `spans` has the span of each token that parse_exp() was given.
+	pub fn to_diagnostic(&self, spans: &[Span]) -> Diagnostic {
+		// if the tokens ran out without an Eof, the error is after the last one.
+		let span = spans.get(self.token_index).copied()
+			.or_else(|| spans.last().map(|last| Span::new(last.end, last.end)));
 
-		// and this does the (BinOp Term)*.
-		self.parse_binops(lhs, Precedence::MIN)
+		Diagnostic::new(Severity::Error, self.message.clone(), span).with_code(self.code)
 	}
+}
 
-	// what's really cool about this algorithm is that we can add more operators,
-	// change precedence levels etc. and this code doesn't change at all!
-	fn parse_binops(&mut self, mut lhs: Box<AstNode>, min_prec: Precedence) -> ParseResult {
-		// for tokens which are binary operators, .precedence() returns their precedence.
-		// so this loop is indirectly saying, "while we are looking at a binary operator."
-		// for tokens that aren't operators, .precedence() will return Precedence::None,
-		// which is always less than min_prec, causing the loop to terminate.
-		while self.cur().precedence().is_at_least(min_prec) {
-			let op = self.cur();
-
-			// parse the rhs, but we don't actually know if it's *our* rhs, or the next
-			// operator's lhs!
-			self.next();
-			let mut rhs = self.parse_term()?;
-
-			// this is a 'while' instead of an 'if', because there could be a decreasing
-			// chain of higher-precedence operators here. it can't happen with our piddly
-			// two levels of precedence, but it can happen in more complex grammars, like
-			// a < b ** c * d + e, which should parse as (a < (((b ** c) * d) + e)).
-			// ** is higher than * is higher than + is higher than <.
-			while self.cur().precedence().is_higher_than(op.precedence()) {
-				rhs = self.parse_binops(rhs, self.cur().precedence())?;
-			}
+// ------------------------------------------------------------------------------------------------
+// The bottom-up expression parser
+// ------------------------------------------------------------------------------------------------
 
-			// glob the lhs and rhs together into an AST node!
-			lhs = AstNode::bin(lhs, op.to_binop(), rhs);
-		}
+/*
+This used to have its own precedence-climbing loop, but that's now in the pratt crate, so that
+other parsers can use it too. What's left here is the part that's specific to this language: the
+operator table above, and an AstBuilder that says what the operands are and what AST nodes to make.
+*/
 
-		// when done, the lhs variable contains the parsed expression tree.
-		Ok(lhs)
-	}
+type ParseResult = Result<Box<AstNode>, ParseError>;
+type Parser<'t> = pratt::Parser<'t, Token, Precedence>;
 
-	// Term: UnaryOp* PrimaryExp PostfixOp*
-	fn parse_term(&mut self) -> ParseResult {
-		// this match is for unary operators. there's only one in this language.
-		match self.cur() {
-			Token::Minus => {
-				self.next();
-				// negation is right-associative, so we have to recurse to get the operand.
-				// recursion is a Spicy Loop, so this handles UnaryOp*.
-				let operand = self.parse_term()?;
-				Ok(AstNode::neg(operand))
-			}
+pub fn parse_exp(tokens: &[Token]) -> ParseResult {
+	let table = op_table();
+	let mut p = Parser::new(&table, tokens);
+	let ret = p.parse_exp(&mut AstBuilder)?;
+	expect_eof(&p)?;
+	Ok(ret)
+}
 
-			_ => {
-				// PrimaryExp
-				let pri = self.parse_primary()?;
-				// PostfixOp*
-				self.parse_postfix(pri)
-			}
-		}
-	}
+struct AstBuilder;
+
+impl Builder<Token, Precedence> for AstBuilder {
+	type Node = Box<AstNode>;
+	type Error = ParseError;
 
 	// PrimaryExp: IdExp | NumExp | ParenExp
-	fn parse_primary(&mut self) -> ParseResult {
-		match self.cur() {
+	fn primary(&mut self, p: &mut Parser) -> ParseResult {
+		match cur(p) {
 			// IdExp: <Token::Id>
-			Token::Id(name) => { self.next(); Ok(AstNode::id(&name)) }
+			Token::Id(name) => { p.next(); Ok(AstNode::id(name)) }
 
 			// NumExp: <Token::NumLit>
-			Token::NumLit(val) => { self.next(); Ok(AstNode::num(val)) }
+			Token::NumLit(val) => { p.next(); Ok(AstNode::num(*val)) }
 
 			// ParenExp: '(' Exp ')'
 			Token::LParen => {
-				self.next();
-				let ret = self.parse_exp()?;
-				self.expect_rparen()?;
+				p.next();
+				let ret = p.parse_exp(self)?;
+				expect_rparen(p)?;
 				Ok(ret)
 			}
 
-			t => Err(format!(
+			t => Err(error(p, diagnostics::EXPECTED_EXPRESSION, format!(
 				"expected an identifier, number, or parenthesized expression, not '{}'", t
-			))
+			)))
 		}
 	}
 
-	// PostfixOp: CallOp
-	fn parse_postfix(&mut self, mut lhs: Box<AstNode>) -> ParseResult {
-		// this loop implements the whole PostfixOp* part of the Term rule.
-		loop {
-			match self.cur() {
-				// this language only has function calls with exactly one argument.
-				// CallOp: '(' Exp ')'
-				Token::LParen => {
-					self.next();
-					let arg = self.parse_exp()?;
-					self.expect_rparen()?;
-					lhs = AstNode::call(lhs, arg);
-				}
-
-				// this is not an error case; there just might not be any postfix
-				// operator here. this breaks out of the loop; you cannot break
-				// out of a match in Rust.
-				_ => break,
-			}
-		}
+	// glob the lhs and rhs together into an AST node!
+	fn infix(&mut self, op: &Token, lhs: Box<AstNode>, rhs: Box<AstNode>) -> ParseResult {
+		Ok(AstNode::bin(lhs, op.to_binop(), rhs))
+	}
 
-		Ok(lhs)
+	// there's only one unary operator in this language.
+	fn prefix(&mut self, _op: &Token, operand: Box<AstNode>) -> ParseResult {
+		Ok(AstNode::neg(operand))
 	}
 
-	fn expect_rparen(&mut self) -> Result<(), String> {
-		match self.cur() {
-			Token::RParen => { self.next(); Ok(()) }
-			_             => Err("expected a right parenthesis".into()),
-		}
+	// and only one postfix operator: function calls with exactly one argument.
+	// CallOp: '(' Exp ')'
+	fn postfix(&mut self, p: &mut Parser, _op: &Token, lhs: Box<AstNode>) -> ParseResult {
+		// the '(' has already been skipped.
+		let arg = p.parse_exp(self)?;
+		expect_rparen(p)?;
+		Ok(AstNode::call(lhs, arg))
 	}
+}
 
-	fn expect_eof(&mut self) -> Result<(), String> {
-		match self.cur() {
-			Token::Eof => Ok(()),
-			_          => Err("expected eof (there's extra stuff after the expression)".into()),
-		}
+// the pratt parser gives None at the end of the tokens, but it's easier to match on an Eof token.
+// this gives a reference, since cloning a Token::Id would allocate a copy of its name every time.
+fn cur<'t>(p: &Parser<'t>) -> &'t Token {
+	p.cur().unwrap_or(&Token::Eof)
+}
+
+fn expect_rparen(p: &mut Parser) -> Result<(), ParseError> {
+	match cur(p) {
+		Token::RParen => { p.next(); Ok(()) }
+		_             => Err(error(p, diagnostics::EXPECTED_RPAREN,
+			"expected a right parenthesis")),
+	}
+}
+
+fn expect_eof(p: &Parser) -> Result<(), ParseError> {
+	match cur(p) {
+		Token::Eof => Ok(()),
+		_          => Err(error(p, diagnostics::EXPECTED_EOF,
+			"expected eof (there's extra stuff after the expression)")),
 	}
-}
\ No newline at end of file
+}
+
+// makes an error at the current token.
+fn error(p: &Parser, code: ErrorCode, message: impl Into<String>) -> ParseError {
+	ParseError { token_index: p.pos(), code, message: message.into() }
+}
@@ -1,4 +1,5 @@
 
+use diagnostics::{ Diagnostic, SourceFile };
 use parsing_math::*;
 
 fn main() {
@@ -56,8 +57,15 @@ fn parse_it(tokens: &[Token]) {
 	show_tokens(&tokens);
 
 	match parse_exp(&tokens) {
-		Ok(ast)  => println!("AST: {}", ast),
-		Err(err) => println!("parse error: {}", err),
+		Ok(ast) => {
+			println!("AST: {}", ast);
+			// this only puts in the parentheses that are needed.
+			println!("Source: {}", ast.to_source());
+			assert!(round_trips(&ast));
+		}
+
+		// there's no source code to show, so this just shows the message (and any notes).
+		Err(err) => print!("{}", Diagnostic::from(err).render(&SourceFile::new("<tokens>", ""))),
 	}
 
 	println!();
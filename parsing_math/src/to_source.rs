@@ -0,0 +1,150 @@
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Turning ASTs back into source code
+// ------------------------------------------------------------------------------------------------
+
+/*
+AstNode's Display puts parentheses around *everything*, like `((a + b) * c)`, so you can see how
+it was parsed. to_source() is the opposite: it only puts parentheses where they're *needed*, so the
+code would parse back into the exact same AST. That's the job of a "pretty printer", and it has to
+know the precedence and associativity of every operator (the same table that the parser uses):
+
+- a child with *lower* precedence than its parent needs parentheses: `(a + b) * c`.
+- these operators are all left-associative, so `a - b - c` means `(a - b) - c`. so the *right*
+  side also needs parentheses if it has the *same* precedence: `a - (b - c)`.
+- the callee of a call needs them for anything lower than a call: `(-f)(x)`, but not `-f(x)`.
+
+Numbers are written with Rust's Display for f64, which gives the shortest text that reads back
+as the same number, and never uses an exponent: 5.0 is `5`, and 0.1 is `0.1`. A Const is never
+negative in an AST that came from the parser, since `-5` is a Negate around a 5.
+
+So for any AST the parser could make, `parse(to_source(ast)) == ast`. round_trips() checks that,
+and the fuzz folder has a fuzzer that checks it for lots of random ASTs.
+*/
+pub trait ToSource {
+	fn to_source(&self) -> String;
+}
+
+impl ToSource for AstNode {
+	fn to_source(&self) -> String {
+		let mut out = String::new();
+		self.write_source(&mut out);
+		out
+	}
+}
+
+impl AstNode {
+	// None means it's a constant or name, which never needs parentheses.
+	fn precedence(&self) -> Option<Precedence> {
+		match self {
+			AstNode::Const { .. } | AstNode::Ident { .. } => None,
+			AstNode::Negate { .. } => Some(Precedence::Neg),
+			AstNode::Call { .. }   => Some(Precedence::Call),
+
+			AstNode::Binary { op, .. } => Some(match op {
+				BinOp::Add | BinOp::Sub              => Precedence::Add,
+				BinOp::Mul | BinOp::Div | BinOp::Mod => Precedence::Mul,
+			}),
+		}
+	}
+
+	fn write_source(&self, out: &mut String) {
+		match self {
+			AstNode::Const { val }  => out.push_str(&val.to_string()),
+			AstNode::Ident { name } => out.push_str(name),
+
+			AstNode::Negate { lhs } => {
+				out.push('-');
+				lhs.write_operand(out, Precedence::Neg, false);
+			}
+
+			AstNode::Binary { op, lhs, rhs } => {
+				let prec = self.precedence().unwrap();
+				lhs.write_operand(out, prec, false);
+				out.push_str(&format!(" {} ", op));
+				rhs.write_operand(out, prec, true);
+			}
+
+			AstNode::Call { callee, arg } => {
+				callee.write_operand(out, Precedence::Call, false);
+				out.push('(');
+				arg.write_source(out);
+				out.push(')');
+			}
+		}
+	}
+
+	// writes this node as an operand of an operator with the given precedence, with parentheses
+	// if they're needed. `strict` means it needs them even for the *same* precedence.
+	fn write_operand(&self, out: &mut String, parent: Precedence, strict: bool) {
+		let needs_parens = match self.precedence() {
+			None       => false,
+			Some(prec) => prec < parent || (strict && prec == parent),
+		};
+
+		if needs_parens {
+			out.push('(');
+			self.write_source(out);
+			out.push(')');
+		} else {
+			self.write_source(out);
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Round trips
+// ------------------------------------------------------------------------------------------------
+
+// This crate doesn't have a real lexer (the tokens in main.rs are written by hand), but checking
+// a round trip needs one. This is just enough of one to read what to_source() writes. It gives
+// None if there's a character that can't be part of any token.
+pub fn tokenize(src: &str) -> Option<Vec<Token>> {
+	let chars = src.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut ret = vec![];
+
+	while pos < chars.len() {
+		let start = pos;
+		pos += 1;
+
+		ret.push(match chars[start] {
+			c if c.is_whitespace() => continue,
+			'(' => Token::LParen,
+			')' => Token::RParen,
+			'+' => Token::Plus,
+			'-' => Token::Minus,
+			'*' => Token::Times,
+			'/' => Token::Divide,
+			'%' => Token::Modulo,
+
+			c if c.is_alphabetic() || c == '_' => {
+				while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+					pos += 1;
+				}
+
+				Token::Id(chars[start .. pos].iter().collect())
+			}
+
+			// digits, and maybe a '.' and more digits.
+			c if c.is_ascii_digit() => {
+				while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
+					pos += 1;
+				}
+
+				Token::NumLit(chars[start .. pos].iter().collect::<String>().parse().ok()?)
+			}
+
+			_ => return None,
+		});
+	}
+
+	Some(ret)
+}
+
+// Does this AST turn into source code that parses back into the same AST?
+pub fn round_trips(ast: &AstNode) -> bool {
+	let parsed = tokenize(&ast.to_source()).and_then(|tokens| parse_exp(&tokens).ok());
+	parsed.is_some_and(|parsed| *parsed == *ast)
+}
@@ -0,0 +1,233 @@
+// ------------------------------------------------------------------------------------------------
+// Pratt parsing
+// ------------------------------------------------------------------------------------------------
+
+/*
+parsing_math's expression parser has a really nice property: adding an operator, or changing its
+precedence, doesn't change the parsing code at all. Only the precedence table changes. This crate
+takes that idea all the way, so that the algorithm can be used for *any* language's expressions
+without copying it.
+
+There are two parts you give it:
+
+1. An OpTable, which lists the operator tokens and their precedences:
+   - *prefix* operators come before their operand, like the `-` in `-x`.
+   - *infix* (binary) operators go between two operands, like `+`. They're left-associative
+     (`a - b - c` is `(a - b) - c`) or right-associative (`a ^ b ^ c` is `a ^ (b ^ c)`).
+   - *postfix* operators come after their operand, like the `!` in `n!`. Function calls and array
+     indexing are postfix operators too: the `(` in `f(x)` is the operator, and the argument and
+     the `)` are parsed by your code (see Builder::postfix).
+
+2. A Builder, which says what to do with each thing that gets parsed. Usually, that's making an
+   AST node, but it could be anything, like evaluating the expression right away.
+
+The precedences can be any type that can be compared with < and >, like numbers, or an enum that
+derives PartialOrd and Ord (like parsing_math's Precedence). Higher precedences *bind tighter*:
+if * is higher than +, then `a + b * c` is `a + (b * c)`.
+
+The algorithm is called *Pratt parsing* (after Vaughan Pratt, who came up with it in 1973), and
+it's the same thing as *precedence climbing*. The idea is that parse_exp() is given the lowest
+precedence of operator that it's allowed to use. After it parses an operand, it keeps grabbing
+operators as long as they're at least that precedence. For each one, it parses the right-hand
+side by calling itself with the operator's precedence as the new minimum, so the right side only
+takes operators that bind tighter. When it finds an operator that binds looser, it stops, and the
+caller (which allowed lower precedences) gets to use it instead.
+*/
+
+// Which way a chain of the same operator groups.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Assoc {
+	Left,  // a - b - c  ==  (a - b) - c
+	Right, // a ^ b ^ c  ==  a ^ (b ^ c)
+}
+
+// The operators of a language, and their precedences. Operators are found by comparing tokens
+// with ==, so the tokens in the table are "example" tokens: OpTable::new().infix(Token::Plus, ...).
+#[derive(Debug, Clone)]
+pub struct OpTable<T, P> {
+	prefix:  Vec<(T, P)>,
+	infix:   Vec<(T, P, Assoc)>,
+	postfix: Vec<(T, P)>,
+}
+
+impl<T: PartialEq, P: Ord + Copy> Default for OpTable<T, P> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: PartialEq, P: Ord + Copy> OpTable<T, P> {
+	pub fn new() -> Self {
+		OpTable { prefix: vec![], infix: vec![], postfix: vec![] }
+	}
+
+	// these return self, so they can be chained: OpTable::new().prefix(...).infix(...) etc.
+	pub fn prefix(mut self, op: T, prec: P) -> Self {
+		self.prefix.push((op, prec));
+		self
+	}
+
+	pub fn infix(mut self, op: T, prec: P, assoc: Assoc) -> Self {
+		self.infix.push((op, prec, assoc));
+		self
+	}
+
+	pub fn postfix(mut self, op: T, prec: P) -> Self {
+		self.postfix.push((op, prec));
+		self
+	}
+
+	pub fn prefix_prec(&self, token: &T) -> Option<P> {
+		self.prefix.iter().find(|(op, _)| op == token).map(|&(_, prec)| prec)
+	}
+
+	pub fn infix_prec(&self, token: &T) -> Option<(P, Assoc)> {
+		self.infix.iter().find(|(op, ..)| op == token).map(|&(_, prec, assoc)| (prec, assoc))
+	}
+
+	pub fn postfix_prec(&self, token: &T) -> Option<P> {
+		self.postfix.iter().find(|(op, _)| op == token).map(|&(_, prec)| prec)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Builder
+// ------------------------------------------------------------------------------------------------
+
+// What the parser calls when it parses something. `Node` is what parsing gives back (like
+// Box<AstNode>), and `Error` is your parse error type.
+pub trait Builder<T, P> {
+	type Node;
+	type Error;
+
+	// Parses an operand that isn't an operator, like a number, a name, or a parenthesized
+	// expression. This is where you say what the *atoms* of the language are. It's also where
+	// errors like "expected an expression" come from, since nothing else matched.
+	//
+	// To parse a whole expression inside this one (like in parentheses), use p.parse_exp(self).
+	fn primary(&mut self, p: &mut Parser<T, P>) -> Result<Self::Node, Self::Error>;
+
+	// Called after parsing an infix operator and both of its operands.
+	fn infix(&mut self, op: &T, lhs: Self::Node, rhs: Self::Node)
+	-> Result<Self::Node, Self::Error>;
+
+	// Called after parsing a prefix operator and its operand. You only need this if the OpTable
+	// has prefix operators.
+	fn prefix(&mut self, op: &T, operand: Self::Node) -> Result<Self::Node, Self::Error> {
+		let _ = (op, operand);
+		panic!("the OpTable has prefix operators, but the Builder doesn't implement prefix()");
+	}
+
+	// Called after parsing an operand, and the postfix operator token after it. The operator has
+	// already been skipped, so this can parse anything that comes after it, like the arguments
+	// and ')' of a function call. You only need this if the OpTable has postfix operators.
+	fn postfix(&mut self, p: &mut Parser<T, P>, op: &T, operand: Self::Node)
+	-> Result<Self::Node, Self::Error> {
+		let _ = (p, op, operand);
+		panic!("the OpTable has postfix operators, but the Builder doesn't implement postfix()");
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Parser
+// ------------------------------------------------------------------------------------------------
+
+// The lowest precedence of operator that a parse_exp() call can use. Left-associative operators
+// make their right sides use *only* higher precedences, so that's `strict`.
+#[derive(Debug, Clone, Copy)]
+struct MinPrec<P> {
+	prec:   P,
+	strict: bool,
+}
+
+impl<P: Ord + Copy> MinPrec<P> {
+	fn allows(min: Option<Self>, prec: P) -> bool {
+		match min {
+			None                                     => true,
+			Some(MinPrec { prec: m, strict: true })  => prec > m,
+			Some(MinPrec { prec: m, strict: false }) => prec >= m,
+		}
+	}
+}
+
+// Holds the tokens and where we are in them. The Builder uses this to look at and skip tokens.
+pub struct Parser<'a, T, P> {
+	table:  &'a OpTable<T, P>,
+	tokens: &'a [T],
+	pos:    usize,
+}
+
+impl<'a, T: PartialEq, P: Ord + Copy> Parser<'a, T, P> {
+	pub fn new(table: &'a OpTable<T, P>, tokens: &'a [T]) -> Self {
+		Parser { table, tokens, pos: 0 }
+	}
+
+	// The token we're looking at, or None if we're at the end.
+	pub fn cur(&self) -> Option<&'a T> {
+		self.tokens.get(self.pos)
+	}
+
+	// Moves to the next token.
+	pub fn next(&mut self) {
+		assert!(self.pos < self.tokens.len());
+		self.pos += 1;
+	}
+
+	// The index of the token we're looking at.
+	pub fn pos(&self) -> usize {
+		self.pos
+	}
+
+	// Parses a whole expression, using any operators in the table.
+	pub fn parse_exp<B: Builder<T, P>>(&mut self, b: &mut B) -> Result<B::Node, B::Error> {
+		self.parse_prec(b, None)
+	}
+
+	fn parse_prec<B: Builder<T, P>>(&mut self, b: &mut B, min: Option<MinPrec<P>>)
+	-> Result<B::Node, B::Error> {
+		// first, a prefix operator and its operand, or a primary.
+		let mut lhs = match self.cur().and_then(|t| self.table.prefix_prec(t).map(|p| (t, p))) {
+			Some((op, prec)) => {
+				self.next();
+				// the operand can only have operators that bind tighter than this one, so in
+				// `-a + b`, the - only gets the a. prefix operators are right-associative,
+				// though, so `- - a` works.
+				let operand = self.parse_prec(b, Some(MinPrec { prec, strict: false }))?;
+				b.prefix(op, operand)?
+			}
+
+			None => b.primary(self)?,
+		};
+
+		// then, as many postfix and infix operators as we're allowed to use.
+		while let Some(op) = self.cur() {
+			if let Some(prec) = self.table.postfix_prec(op) {
+				if !MinPrec::allows(min, prec) {
+					break;
+				}
+
+				self.next();
+				lhs = b.postfix(self, op, lhs)?;
+			} else if let Some((prec, assoc)) = self.table.infix_prec(op) {
+				if !MinPrec::allows(min, prec) {
+					break;
+				}
+
+				self.next();
+
+				// here's where associativity happens. for a left-associative operator, the right
+				// side can't have the same operator in it, so `a - b - c` stops after the b and
+				// the *loop* gets the second -. for a right-associative one, it can, so the
+				// *recursive call* gets the second ^ in `a ^ b ^ c`.
+				let strict = assoc == Assoc::Left;
+				let rhs = self.parse_prec(b, Some(MinPrec { prec, strict }))?;
+				lhs = b.infix(op, lhs, rhs)?;
+			} else {
+				// not an operator, so the expression is over.
+				break;
+			}
+		}
+
+		Ok(lhs)
+	}
+}
@@ -0,0 +1,140 @@
+use pratt::*;
+
+// ------------------------------------------------------------------------------------------------
+// A calculator
+// ------------------------------------------------------------------------------------------------
+
+/*
+The parser doesn't have to make an AST. This Builder *evaluates* each operator as soon as it's
+parsed, so parse_exp() gives back the answer. It also shows off the parts that parsing_math
+doesn't use: a right-associative operator (^, for exponents) and a postfix operator (!, for
+factorial).
+*/
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+	Num(f64),
+	Op(char),
+}
+
+fn op_table() -> OpTable<Token, u32> {
+	use Token::Op;
+
+	// with numbers for precedences, bigger numbers bind tighter.
+	OpTable::new()
+		.infix(Op('+'),   10, Assoc::Left)
+		.infix(Op('-'),   10, Assoc::Left)
+		.infix(Op('*'),   20, Assoc::Left)
+		.infix(Op('/'),   20, Assoc::Left)
+		.prefix(Op('-'),  30)
+		.infix(Op('^'),   40, Assoc::Right)
+		.postfix(Op('!'), 50)
+}
+
+struct Calculator;
+
+impl Builder<Token, u32> for Calculator {
+	type Node = f64;
+	type Error = String;
+
+	fn primary(&mut self, p: &mut Parser<Token, u32>) -> Result<f64, String> {
+		match p.cur() {
+			Some(Token::Num(val)) => {
+				p.next();
+				Ok(*val)
+			}
+
+			Some(Token::Op('(')) => {
+				p.next();
+				let ret = p.parse_exp(self)?;
+
+				match p.cur() {
+					Some(Token::Op(')')) => { p.next(); Ok(ret) }
+					_                    => Err(format!("expected ')' at token #{}", p.pos() + 1)),
+				}
+			}
+
+			_ => Err(format!("expected a number or '(' at token #{}", p.pos() + 1)),
+		}
+	}
+
+	fn infix(&mut self, op: &Token, lhs: f64, rhs: f64) -> Result<f64, String> {
+		match op {
+			Token::Op('+') => Ok(lhs + rhs),
+			Token::Op('-') => Ok(lhs - rhs),
+			Token::Op('*') => Ok(lhs * rhs),
+			Token::Op('/') => Ok(lhs / rhs),
+			Token::Op('^') => Ok(lhs.powf(rhs)),
+			_              => unreachable!(),
+		}
+	}
+
+	fn prefix(&mut self, _op: &Token, operand: f64) -> Result<f64, String> {
+		Ok(-operand)
+	}
+
+	fn postfix(&mut self, _p: &mut Parser<Token, u32>, _op: &Token, operand: f64)
+	-> Result<f64, String> {
+		if operand < 0.0 || operand.fract() != 0.0 {
+			return Err(format!("can't take the factorial of {}", operand));
+		}
+
+		Ok((1 ..= operand as u64).map(|i| i as f64).product())
+	}
+}
+
+// a very quick and dirty lexer: every character that isn't a digit or a space is an operator.
+fn lex(src: &str) -> Vec<Token> {
+	let mut ret = vec![];
+	let mut chars = src.chars().peekable();
+
+	while let Some(c) = chars.next() {
+		if c.is_whitespace() {
+			continue;
+		} else if let Some(digit) = c.to_digit(10) {
+			let mut val = digit as f64;
+
+			while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+				val = val * 10.0 + digit as f64;
+				chars.next();
+			}
+
+			ret.push(Token::Num(val));
+		} else {
+			ret.push(Token::Op(c));
+		}
+	}
+
+	ret
+}
+
+fn calculate(src: &str) {
+	let table = op_table();
+	let tokens = lex(src);
+	let mut p = Parser::new(&table, &tokens);
+
+	let result = p.parse_exp(&mut Calculator).and_then(|val| match p.cur() {
+		None    => Ok(val),
+		Some(_) => Err(format!("extra stuff after the expression at token #{}", p.pos() + 1)),
+	});
+
+	match result {
+		Ok(val)  => println!("{:<16} = {}", src, val),
+		Err(err) => println!("{:<16} : error: {}", src, err),
+	}
+}
+
+fn main() {
+	calculate("1 + 2 * 3");
+	calculate("(1 + 2) * 3");
+	calculate("10 - 4 - 3");    // left-associative: (10 - 4) - 3
+	calculate("2 ^ 3 ^ 2");     // right-associative: 2 ^ (3 ^ 2)
+	calculate("-2 ^ 2");        // ^ is higher than unary -, so -(2 ^ 2)
+	calculate("3! + 1");
+	calculate("-3!");           // ! is higher than unary -, so -(3!)
+	calculate("(1 + 2)! ^ 2");
+	calculate("(-3)!");
+	calculate("1 + * 2");
+	calculate("(1 + 2");
+	calculate("1 2");
+}
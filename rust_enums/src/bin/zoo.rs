@@ -0,0 +1,96 @@
+use std::io::{ self, BufRead, Write };
+
+use rust_enums::*;
+
+// An interactive version of the zoo. Run it with `cargo run --bin zoo`.
+fn main() {
+	println!("Welcome to the zoo! Type 'help' for a list of commands.");
+
+	let mut animals: Vec<Animal> = vec![];
+	let stdin = io::stdin();
+
+	loop {
+		print!("zoo> ");
+		// print! doesn't flush stdout on its own, so the prompt wouldn't show up without this.
+		io::stdout().flush().unwrap();
+
+		let mut line = String::new();
+
+		// read_line returns Ok(0) at the end of the input (e.g. if you press Ctrl+D).
+		match stdin.lock().read_line(&mut line) {
+			Ok(0) | Err(..) => break,
+			Ok(..)          => {}
+		}
+
+		match parse_command(&line) {
+			Ok(Command::Quit) => break,
+			Ok(cmd)           => run_command(cmd, &mut animals),
+			Err(e)            => println!("error: {}", e),
+		}
+	}
+
+	println!("byeeeeee!");
+}
+
+fn run_command(cmd: Command, animals: &mut Vec<Animal>) {
+	match cmd {
+		Command::Add(animal) => {
+			println!("Added {} as #{}.", animal.describe(), animals.len() + 1);
+			animals.push(animal);
+		}
+
+		Command::List => {
+			if animals.is_empty() {
+				println!("The zoo is empty. Try 'add cat 12 Tuxedo'.");
+			}
+
+			for (i, a) in animals.iter().enumerate() {
+				println!("  #{}: {}", i + 1, a.describe());
+			}
+		}
+
+		// .get() returns None for out-of-range indexes instead of crashing like animals[i] does.
+		// checked_sub returns None if n is 0, which would otherwise underflow.
+		Command::Speak(n) => match n.checked_sub(1).and_then(|i| animals.get(i)) {
+			Some(a) => { print!("#{} says ", n); a.speak(); }
+			None    => println!("there's no animal #{}", n),
+		},
+
+		Command::Heaviest => {
+			// f32 isn't Ord (because of NaN), so we have to use max_by with partial_cmp.
+			let heaviest = animals.iter()
+				.max_by(|a, b| a.weight().partial_cmp(&b.weight()).unwrap());
+
+			match heaviest {
+				Some(a) => println!("The heaviest animal is {}.", a.describe()),
+				None    => println!("The zoo is empty."),
+			}
+		}
+
+		Command::Simulate(ticks) => {
+			let log = simulate(animals, ticks);
+
+			if log.is_empty() {
+				println!("Nothing happened.");
+			}
+
+			for event in log {
+				println!("  {}", event);
+			}
+		}
+
+		Command::Help => {
+			println!("Commands:");
+			println!("  add cat <weight> <pattern>");
+			println!("  add dog <weight> <loudness>");
+			println!("  add camel <weight> <humps>");
+			println!("  list");
+			println!("  speak <number>");
+			println!("  heaviest");
+			println!("  simulate <ticks>");
+			println!("  quit");
+		}
+
+		Command::Quit => unreachable!(),
+	}
+}
@@ -0,0 +1,138 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::{ full_water, Animal, AnimalKind, CamelData, CatData, DogData };
+
+// ------------------------------------------------------------------------------------------------
+// AnimalError
+// ------------------------------------------------------------------------------------------------
+
+// All the ways an animal can be nonsense.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AnimalError {
+	MissingWeight,
+	MissingKind,
+	BadWeight(f32),
+	EmptyPattern,
+	BadLoudness(i32),
+	BadHumps(i32),
+}
+
+impl Display for AnimalError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use AnimalError::*;
+
+		match self {
+			MissingWeight  => write!(f, "the animal needs a weight"),
+			MissingKind    => write!(f, "the animal needs a kind (cat, dog, or camel)"),
+			BadWeight(w)   => write!(f, "weight must be a positive number, not {}", w),
+			EmptyPattern   => write!(f, "a cat's pattern can't be empty"),
+			BadLoudness(l) => write!(f, "loudness must be from 0 to 100, not {}", l),
+			BadHumps(h)    => write!(f, "camels have 1 or 2 humps, not {}", h),
+		}
+	}
+}
+
+impl std::error::Error for AnimalError {}
+
+// ------------------------------------------------------------------------------------------------
+// AnimalBuilder
+// ------------------------------------------------------------------------------------------------
+
+/*
+The "builder pattern" is a common way to make objects that have a lot of optional or interacting
+parts. Instead of a constructor with a bunch of arguments, you make a builder, call methods on it
+to set each part, and then call build() at the end:
+
+	let tux = Animal::builder().weight(12.0).cat("Tuxedo").build()?;
+
+Each setter takes `self` (not `&mut self`) and returns it, which is what lets you chain the calls.
+build() is the only place where the whole thing is checked, and it returns a Result. Since the
+fields of Animal (and of the data inside it) aren't pub outside this crate, the builder and the
+zoo loader are the only ways to make one, so there's no way to get your hands on an invalid Animal.
+*/
+
+#[derive(Debug, Default)]
+pub struct AnimalBuilder {
+	weight: Option<f32>,
+	kind:   Option<AnimalKind>,
+}
+
+impl AnimalBuilder {
+	pub fn weight(mut self, weight: f32) -> Self {
+		self.weight = Some(weight);
+		self
+	}
+
+	pub fn cat(mut self, pattern: &str) -> Self {
+		self.kind = Some(AnimalKind::Cat(CatData { pattern: pattern.into(), napping: false }));
+		self
+	}
+
+	pub fn dog(mut self, loudness: i32) -> Self {
+		self.kind = Some(AnimalKind::Dog(DogData { loudness }));
+		self
+	}
+
+	pub fn camel(mut self, num_humps: i32) -> Self {
+		self.kind = Some(AnimalKind::Camel(CamelData { num_humps, water: full_water() }));
+		self
+	}
+
+	pub fn build(self) -> Result<Animal, AnimalError> {
+		let animal = Animal {
+			weight: self.weight.ok_or(AnimalError::MissingWeight)?,
+			kind:   self.kind.ok_or(AnimalError::MissingKind)?,
+		};
+
+		animal.validate()?;
+		Ok(animal)
+	}
+}
+
+impl Animal {
+	pub fn builder() -> AnimalBuilder {
+		AnimalBuilder::default()
+	}
+
+	// Checks that this animal makes sense. The builder and the zoo loader both use this, so
+	// the rules only have to be written once.
+	pub fn validate(&self) -> Result<(), AnimalError> {
+		// !(x > 0.0) is not the same as (x <= 0.0), because of NaN! NaN > 0.0 and NaN <= 0.0
+		// are both false. this way, NaN is rejected too.
+		if !(self.weight > 0.0 && self.weight.is_finite()) {
+			return Err(AnimalError::BadWeight(self.weight));
+		}
+
+		match &self.kind {
+			AnimalKind::Cat(cat) if cat.pattern.trim().is_empty() =>
+				Err(AnimalError::EmptyPattern),
+			AnimalKind::Dog(dog) if !(0 ..= 100).contains(&dog.loudness) =>
+				Err(AnimalError::BadLoudness(dog.loudness)),
+			AnimalKind::Camel(camel) if !(1 ..= 2).contains(&camel.num_humps) =>
+				Err(AnimalError::BadHumps(camel.num_humps)),
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn loudness_is_out_of_100() {
+		for &loudness in &[0, 50, 100] {
+			let dog = Animal::builder().weight(43.0).dog(loudness).build().unwrap();
+			assert_eq!(dog.as_dog().unwrap().loudness(), loudness);
+		}
+
+		for &loudness in &[-1, 101, 99999] {
+			assert_eq!(Animal::builder().weight(43.0).dog(loudness).build().unwrap_err(),
+				AnimalError::BadLoudness(loudness));
+		}
+
+		// the zoo loader uses the same rules.
+		let json = r#"[ { "kind": "dog", "weight": 43.0, "loudness": 99999 } ]"#;
+		assert!(crate::parse_zoo(json).is_err());
+	}
+}
@@ -0,0 +1,229 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::{ Animal, AnimalError };
+
+// ------------------------------------------------------------------------------------------------
+// Command tokens
+// ------------------------------------------------------------------------------------------------
+
+/*
+The zoo program reads commands like `add cat 12 Tuxedo`. Even something this small is easier to
+deal with if we lex it first and parse the tokens after, just like a real language. This lexer is
+a simplified version of the one in lexing_toy; go read that one for the full explanation.
+
+Token grammar:
+
+Word:   <alphabetic> <alphabetic | digit | '_' | '-'>*
+Number: Digit+ ('.' Digit+)?
+Token:  Word | Number
+
+Whitespace: ' ' | '\t' | '\r' | '\n'
+*/
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CmdToken {
+	Word(String),
+	Number(f64),
+}
+
+impl Display for CmdToken {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			CmdToken::Word(w)   => write!(f, "{}", w),
+			CmdToken::Number(n) => write!(f, "{}", n),
+		}
+	}
+}
+
+fn is_word_cont(c: char) -> bool { c.is_alphanumeric() || c == '_' || c == '-' }
+
+pub fn tokenize_command(line: &str) -> Result<Vec<CmdToken>, CommandError> {
+	let chars = line.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut ret = vec![];
+
+	while pos < chars.len() {
+		match chars[pos] {
+			' ' | '\t' | '\r' | '\n' => pos += 1,
+
+			c if c.is_alphabetic() => {
+				let mut s = String::new();
+
+				while pos < chars.len() && is_word_cont(chars[pos]) {
+					s.push(chars[pos]);
+					pos += 1;
+				}
+
+				ret.push(CmdToken::Word(s));
+			}
+
+			c if c.is_ascii_digit() => {
+				let mut s = String::new();
+
+				// a '.' is only part of the number if there's a digit after it, and there hasn't
+				// been one already. so `1.5.3` is 1.5, and then an invalid '.'.
+				while pos < chars.len() && (chars[pos].is_ascii_digit() || (chars[pos] == '.' &&
+					!s.contains('.') && chars.get(pos + 1).is_some_and(|c| c.is_ascii_digit()))) {
+					s.push(chars[pos]);
+					pos += 1;
+				}
+
+				let n = s.parse().map_err(|_| CommandError::InvalidNumber(s))?;
+				ret.push(CmdToken::Number(n));
+			}
+
+			c => return Err(CommandError::InvalidChar(c)),
+		}
+	}
+
+	Ok(ret)
+}
+
+// ------------------------------------------------------------------------------------------------
+// Commands
+// ------------------------------------------------------------------------------------------------
+
+/*
+Command grammar (Number means a CmdToken::Number, and quoted words are Words with that text):
+
+Command:  Add | List | Speak | Heaviest | Simulate | Help | Quit
+Add:      'add' ('cat' Number Word | 'dog' Number Number | 'camel' Number Number)
+List:     'list'
+Speak:    'speak' Number
+Heaviest: 'heaviest'
+Simulate: 'simulate' Number
+Help:     'help'
+Quit:     'quit'
+*/
+
+#[derive(Debug)]
+pub enum Command {
+	Add(Animal),
+	List,
+	Speak(usize), // 1-based, as shown by List.
+	Heaviest,
+	Simulate(u32), // how many ticks.
+	Help,
+	Quit,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CommandError {
+	InvalidChar(char),
+	InvalidNumber(String),
+	Empty,
+	UnknownCommand(String),
+	UnknownAnimal(String),
+	ExpectedNumber(&'static str),
+	ExpectedWord(&'static str),
+	ExtraStuff(String),
+	InvalidAnimal(AnimalError),
+}
+
+impl Display for CommandError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use CommandError::*;
+
+		match self {
+			InvalidChar(c)     => write!(f, "invalid character '{}'", c.escape_debug()),
+			InvalidNumber(s)   => write!(f, "invalid number '{}'", s),
+			Empty              => write!(f, "type a command (or 'help')"),
+			UnknownCommand(s)  => write!(f, "unknown command '{}' (try 'help')", s),
+			UnknownAnimal(s)   => write!(f, "unknown kind of animal '{}'", s),
+			ExpectedNumber(of) => write!(f, "expected a number for the {}", of),
+			ExpectedWord(of)   => write!(f, "expected a word for the {}", of),
+			ExtraStuff(s)      => write!(f, "unexpected '{}' at end of command", s),
+			InvalidAnimal(e)   => write!(f, "{}", e),
+		}
+	}
+}
+
+impl std::error::Error for CommandError {}
+
+pub fn parse_command(line: &str) -> Result<Command, CommandError> {
+	let tokens = tokenize_command(line)?;
+	let mut p = CmdParser { tokens: &tokens, pos: 0 };
+	let ret = p.parse_command()?;
+
+	// like the Eof token in the real parsers, this makes sure nothing was left over.
+	match p.tokens.get(p.pos) {
+		None    => Ok(ret),
+		Some(t) => Err(CommandError::ExtraStuff(t.to_string())),
+	}
+}
+
+// this is a tiny recursive descent parser, like the one in parsing_lisp.
+struct CmdParser<'t> {
+	tokens: &'t [CmdToken],
+	pos:    usize,
+}
+
+impl<'t> CmdParser<'t> {
+	fn parse_command(&mut self) -> Result<Command, CommandError> {
+		let name = match self.tokens.first() {
+			Some(CmdToken::Word(w)) => w.to_lowercase(),
+			Some(t)                 => return Err(CommandError::UnknownCommand(t.to_string())),
+			None                    => return Err(CommandError::Empty),
+		};
+
+		self.pos += 1;
+
+		match name.as_str() {
+			"add"      => self.parse_add(),
+			"list"     => Ok(Command::List),
+			"speak"    => Ok(Command::Speak(self.number("animal number")? as usize)),
+			"heaviest" => Ok(Command::Heaviest),
+			"simulate" => Ok(Command::Simulate(self.number("number of ticks")? as u32)),
+			"help"     => Ok(Command::Help),
+			"quit"     => Ok(Command::Quit),
+			_          => Err(CommandError::UnknownCommand(name)),
+		}
+	}
+
+	fn parse_add(&mut self) -> Result<Command, CommandError> {
+		let kind = self.word("kind of animal")?.to_lowercase();
+
+		let builder = match kind.as_str() {
+			"cat" | "dog" | "camel" => Animal::builder().weight(self.number("weight")? as f32),
+			_                       => return Err(CommandError::UnknownAnimal(kind)),
+		};
+
+		let builder = match kind.as_str() {
+			"cat" => builder.cat(&self.word("pattern")?),
+			"dog" => builder.dog(self.number("loudness")? as i32),
+			_     => builder.camel(self.number("number of humps")? as i32),
+		};
+
+		// the builder checks the numbers for us, e.g. "add camel 500 7" is an error.
+		builder.build()
+			.map(Command::Add)
+			.map_err(CommandError::InvalidAnimal)
+	}
+
+	// `of` describes what the number is for, for the error message.
+	fn number(&mut self, of: &'static str) -> Result<f64, CommandError> {
+		match self.tokens.get(self.pos) {
+			Some(CmdToken::Number(n)) => { self.pos += 1; Ok(*n) }
+			_                         => Err(CommandError::ExpectedNumber(of)),
+		}
+	}
+
+	fn word(&mut self, of: &'static str) -> Result<String, CommandError> {
+		match self.tokens.get(self.pos) {
+			Some(CmdToken::Word(w)) => { self.pos += 1; Ok(w.clone()) }
+			_                       => Err(CommandError::ExpectedWord(of)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn numbers_have_at_most_one_dot() {
+		assert_eq!(tokenize_command("1.5"), Ok(vec![CmdToken::Number(1.5)]));
+		assert_eq!(tokenize_command("1.5.3"), Err(CommandError::InvalidChar('.')));
+		assert!(parse_command("add dog 1.5.3 4").is_err());
+	}
+}
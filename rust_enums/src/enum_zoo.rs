@@ -0,0 +1,191 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use serde::{ Deserialize, Serialize };
+
+// ------------------------------------------------------------------------------------------------
+// The enum version
+// ------------------------------------------------------------------------------------------------
+
+/*
+Here, the set of animals is *closed*: all the kinds of animals are listed in one enum, and every
+method that cares about the kind of animal uses a match to decide what to do. Compare this to the
+trait_zoo module, which does the same thing with traits.
+*/
+
+// Each enum variant is allowed to have data associated with it. Here, each variant holds a struct
+// with that animal's data. It's a little more typing than putting the fields right in the variant
+// (like `Cat { pattern: String }`), but it means we can hand out a reference to *just* the cat
+// data, which is what as_cat() and friends do below.
+// Serialize and Deserialize come from the serde crate, and let these be saved to and loaded from
+// files (see load.rs). The #[serde] lines control what the file format looks like: the variant is
+// chosen by a "kind" field whose value is the variant's name in lowercase, like "cat".
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum AnimalKind {
+	Cat(CatData),
+	Dog(DogData),
+	Camel(CamelData),
+}
+
+// #[serde(default)] means the field can be left out of a zoo file, and it'll get a default value.
+// napping and water are changed by the simulation (see simulate.rs).
+//
+// The fields are pub(crate), so that code outside this crate can look at them (with the methods
+// below) but can't change them. Otherwise, anyone could take a valid dog and set its loudness to
+// -5, and all the checking in validate() would be for nothing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CatData {
+	pub(crate) pattern: String,
+	#[serde(default)]
+	pub(crate) napping: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DogData {
+	pub(crate) loudness: i32, // from 0 to 100.
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CamelData {
+	pub(crate) num_humps: i32,
+	#[serde(default = "full_water")]
+	pub(crate) water: f32, // how full the humps are, in percent.
+}
+
+impl CatData {
+	pub fn pattern(&self) -> &str { &self.pattern }
+	pub fn napping(&self) -> bool { self.napping }
+}
+
+impl DogData {
+	pub fn loudness(&self) -> i32 { self.loudness }
+}
+
+impl CamelData {
+	pub fn num_humps(&self) -> i32 { self.num_humps }
+	pub fn water(&self)     -> f32 { self.water }
+}
+
+pub(crate) fn full_water() -> f32 { 100.0 }
+
+// The Animal struct holds common things, as well as the 'kind' field, which holds
+// the varying part (the per-animal data).
+// #[serde(flatten)] puts the kind's fields right next to "weight" instead of in a nested object.
+// #[serde(try_from)] makes serde load an UncheckedAnimal first, and then check it with TryFrom
+// (see load.rs). that way, a zoo file can't contain a camel with 7 humps.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(try_from = "crate::load::UncheckedAnimal")]
+pub struct Animal {
+	pub(crate) weight: f32,
+	#[serde(flatten)]
+	pub(crate) kind:   AnimalKind,
+}
+
+// The error returned when you try to do a cat thing to a dog (or whatever).
+#[derive(Debug)]
+pub struct WrongAnimal {
+	pub expected: &'static str,
+	pub found:    &'static str,
+}
+
+impl Display for WrongAnimal {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "expected a {}, but this is a {}", self.expected, self.found)
+	}
+}
+
+impl std::error::Error for WrongAnimal {}
+
+impl Animal {
+	// (To make an Animal, use Animal::builder(); see builder.rs.)
+
+	// Methods that work on any kind of animal.
+	pub fn weight(&self) -> f32 {
+		self.weight
+	}
+
+	pub fn kind(&self) -> &AnimalKind {
+		&self.kind
+	}
+
+	pub fn speak(&self) {
+		// this makes the code in the match a little shorter and easier to read.
+		use AnimalKind::*;
+
+		match &self.kind {
+			// the .. in these patterns says "I don't care, whatever". all we care
+			// about here is the "discriminant" - the thing that says what animal it is.
+			Cat(..)   => println!("meow!"),
+			Dog(..)   => println!("woof!"),
+			Camel(..) => println!("ghhghgh!"),
+		}
+	}
+
+	// this time we *do* care about what's inside each variant, so we give names to the fields.
+	pub fn describe(&self) -> String {
+		use AnimalKind::*;
+
+		match &self.kind {
+			Cat(CatData { pattern, .. }) =>
+				format!("a {}-pound {} cat", self.weight, pattern),
+			Dog(DogData { loudness }) =>
+				format!("a {}-pound dog (loudness {})", self.weight, loudness),
+			Camel(CamelData { num_humps, .. }) =>
+				format!("a {}-pound camel with {} humps", self.weight, num_humps),
+		}
+	}
+
+	// the name of this kind of animal, for messages.
+	pub fn kind_name(&self) -> &'static str {
+		match &self.kind {
+			AnimalKind::Cat(..)   => "cat",
+			AnimalKind::Dog(..)   => "dog",
+			AnimalKind::Camel(..) => "camel",
+		}
+	}
+
+	// These "accessors" are how you ask "is this a cat? if so, let me see its cat data."
+	// They return Option, so the caller *has* to deal with the "no, it isn't" case - the
+	// compiler won't let you use the &CatData without checking first. This is the Rust version
+	// of a downcast, like `if (a instanceof Cat) { Cat c = (Cat)a; ... }` in Java.
+	pub fn as_cat(&self) -> Option<&CatData> {
+		match &self.kind {
+			AnimalKind::Cat(cat) => Some(cat),
+			_                    => None,
+		}
+	}
+
+	pub fn as_dog(&self) -> Option<&DogData> {
+		match &self.kind {
+			AnimalKind::Dog(dog) => Some(dog),
+			_                    => None,
+		}
+	}
+
+	pub fn as_camel(&self) -> Option<&CamelData> {
+		match &self.kind {
+			AnimalKind::Camel(camel) => Some(camel),
+			_                        => None,
+		}
+	}
+
+	// A method that only works on cats. This is the *right* way to do it: if it's not a cat,
+	// we return an error, and the caller decides what to do about it.
+	pub fn try_play_with_yarn(&self) -> Result<(), WrongAnimal> {
+		// ok_or turns the Option into a Result, so we can use ? on it.
+		let cat = self.as_cat().ok_or(WrongAnimal { expected: "cat", found: self.kind_name() })?;
+		println!("The {} cat plays with some yarn. Reoowwww", cat.pattern);
+		Ok(())
+	}
+
+	// The *wrong* way, kept around for contrast: if it's not a cat, this crashes the whole
+	// program. Panicking is for bugs, not for things the caller could reasonably get wrong.
+	pub fn play_with_yarn(&self) {
+		if let AnimalKind::Cat(CatData { pattern, .. }) = &self.kind {
+			println!("The {} cat plays with some yarn. Reoowwww", pattern);
+		} else {
+			// this just crashes.
+			panic!("play_with_yarn called on a non-cat");
+		}
+	}
+}
@@ -0,0 +1,22 @@
+// The same zoo of animals, written two different ways. Read enum_zoo.rs first, then trait_zoo.rs.
+mod enum_zoo;
+mod trait_zoo;
+
+// making valid animals for the enum version.
+mod builder;
+
+// loading the enum version from a file.
+mod load;
+
+// parsing commands for the interactive zoo (src/bin/zoo.rs).
+mod command;
+
+// making time pass in the zoo.
+mod simulate;
+
+pub use crate::builder::*;
+pub use crate::command::*;
+pub use crate::enum_zoo::*;
+pub use crate::load::*;
+pub use crate::simulate::*;
+pub use crate::trait_zoo::*;
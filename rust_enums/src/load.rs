@@ -0,0 +1,86 @@
+use std::convert::TryFrom;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+use std::path::{ Path, PathBuf };
+
+use serde::Deserialize;
+
+use crate::{ Animal, AnimalError, AnimalKind };
+
+// ------------------------------------------------------------------------------------------------
+// ZooError
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum ZooError {
+	// couldn't read the file at all (doesn't exist, no permission, etc.)
+	Io   { path: PathBuf, err: std::io::Error },
+	// the file was read, but its contents were wrong.
+	Json { path: PathBuf, err: serde_json::Error },
+}
+
+impl Display for ZooError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			ZooError::Io { path, err } =>
+				write!(f, "couldn't read '{}': {}", path.display(), err),
+
+			// serde_json's errors already say what went wrong and where, like
+			// "unknown variant `cow`, expected one of `cat`, `dog`, `camel` at line 3 column 20"
+			ZooError::Json { path, err } =>
+				write!(f, "in '{}': {}", path.display(), err),
+		}
+	}
+}
+
+impl std::error::Error for ZooError {}
+
+// ------------------------------------------------------------------------------------------------
+// Loading
+// ------------------------------------------------------------------------------------------------
+
+/*
+The #[derive(Serialize, Deserialize)] on Animal and AnimalKind did most of the work here. serde
+writes the code that turns JSON into our types, including checking that every field is there and
+that the "kind" is one that we know about. A zoo file looks like this:
+
+	[
+		{ "kind": "cat", "weight": 12.0, "pattern": "Tuxedo" },
+		{ "kind": "dog", "weight": 43.0, "loudness": 100 }
+	]
+*/
+
+// `impl AsRef<Path>` means you can pass a &str, a String, a PathBuf... anything path-like.
+pub fn load_zoo(path: impl AsRef<Path>) -> Result<Vec<Animal>, ZooError> {
+	let path = path.as_ref();
+
+	// map_err changes the error type, so we can use ? to return a ZooError.
+	let text = std::fs::read_to_string(path)
+		.map_err(|err| ZooError::Io { path: path.into(), err })?;
+
+	parse_zoo(&text).map_err(|err| ZooError::Json { path: path.into(), err })
+}
+
+// The part of load_zoo that doesn't touch the filesystem, in case you already have the JSON.
+pub fn parse_zoo(json: &str) -> Result<Vec<Animal>, serde_json::Error> {
+	serde_json::from_str(json)
+}
+
+// This is what serde actually reads from the file. It has the same fields as Animal, but it might
+// be nonsense. The TryFrom impl turns it into a real Animal, or fails with an AnimalError, which
+// serde then turns into a serde_json::Error with the line and column where the animal was.
+#[derive(Deserialize)]
+pub(crate) struct UncheckedAnimal {
+	weight: f32,
+	#[serde(flatten)]
+	kind:   AnimalKind,
+}
+
+impl TryFrom<UncheckedAnimal> for Animal {
+	type Error = AnimalError;
+
+	fn try_from(unchecked: UncheckedAnimal) -> Result<Self, Self::Error> {
+		let animal = Animal { weight: unchecked.weight, kind: unchecked.kind };
+		animal.validate()?;
+		Ok(animal)
+	}
+}
@@ -1,101 +1,156 @@
+use rust_enums::*;
 
-// Each enum variant is allowed to have data associated with it, like its own
-// mini-struct.
-#[derive(Debug)]
-enum AnimalKind {
-	Cat   { pattern: String },
-	Dog   { loudness: i32   },
-	Camel { num_humps: i32  },
+fn main() {
+	println!("================================ enum version ================================");
+	enum_demo();
+	println!();
+	println!("============================ trait object version ============================");
+	trait_demo();
+	println!();
+	println!("============================ loading from a file ============================");
+	load_demo();
+	println!();
+	println!("=============================== time passes... ==============================");
+	simulate_demo();
 }
 
-// The Animal struct holds common things, as well as the 'kind' field, which holds
-// the varying part (the per-animal data).
-#[derive(Debug)]
-struct Animal {
-	weight: f32,
-	kind:   AnimalKind,
-}
+fn enum_demo() {
+	// Let's make some animals!
+	// build() returns a Result, because you could ask for something silly. we know these are
+	// fine, so unwrap() is OK here.
+	let animals = vec![
+		Animal::builder().weight(12.0).cat("Tuxedo").build().unwrap(), // who could THIS be?
+		Animal::builder().weight(43.0).dog(100).build().unwrap(),      // as loud as it gets. ow
+		Animal::builder().weight(1567.0).camel(2).build().unwrap(),    // it's a Bactrian
+	];
 
-impl Animal {
-	// Constructors for each kind of animal.
-	fn new_cat(weight: f32, pattern: &str) -> Animal {
-		Animal {
-			weight,
-			kind: AnimalKind::Cat {
-				pattern: pattern.into()
-			}
-		}
-	}
+	// {:#?} debug-prints things out with newlines and indentation.
+	println!("My animals: {:#?}", animals);
 
-	fn new_dog(weight: f32, loudness: i32) -> Animal {
-		Animal {
-			weight,
-			kind: AnimalKind::Dog { loudness }
-		}
-	}
+	// Let's sum up all their weights!
+	let total_weight: f32 = animals.iter().map(|a| a.weight()).sum();
+	println!("All the animals together weigh {} pounds.", total_weight);
 
-	fn new_camel(weight: f32, num_humps: i32) -> Animal {
-		Animal {
-			weight,
-			kind: AnimalKind::Camel { num_humps }
-		}
+	// Let's make them speak!
+	println!("Let's see what they have to say: ");
+	for a in &animals {
+		print!("  {} says ", a.describe());
+		a.speak();
 	}
 
-	// Methods that work on any kind of animal.
-	fn weight(&self) -> f32 {
-		self.weight
-	}
+	// And finally, a cat-specific method.
+	print!("Time to play: ");
+	animals[0].play_with_yarn();
 
-	fn speak(&self) {
-		// this makes the code in the match a little shorter and easier to read.
-		use AnimalKind::*;
+	// Uncommenting this will crash the program!
+	// animals[1].play_with_yarn();
+
+	// The builder won't let us make nonsense animals.
+	let nonsense = vec![
+		Animal::builder().weight(-5.0).dog(10).build(),
+		Animal::builder().weight(700.0).camel(7).build(),
+		Animal::builder().weight(43.0).dog(99999).build(),
+		Animal::builder().cat("Calico").build(),
+		Animal::builder().weight(10.0).build(),
+	];
+
+	for result in nonsense {
+		match result {
+			Ok(a)  => println!("Somehow made {}", a.describe()),
+			Err(e) => println!("Can't make that animal: {}", e),
+		}
+	}
 
-		match &self.kind {
-			// the .. in these patterns says "I don't care, whatever". all we care
-			// about here is the "discriminant" - the thing that says what animal it is.
-			Cat { .. }   => println!("meow!"),
-			Dog { .. }   => println!("woof!"),
-			Camel { .. } => println!("ghhghgh!"),
+	// This version doesn't crash; it gives us an error that we can handle.
+	for a in &animals {
+		if let Err(e) = a.try_play_with_yarn() {
+			println!("Can't play: {}", e);
 		}
 	}
 
-	// A method that only works on cats.
-	fn play_with_yarn(&self) {
-		if let AnimalKind::Cat { pattern } = &self.kind {
-			println!("The {} cat plays with some yarn. Reoowwww", pattern);
-		} else {
-			// this just crashes.
-			panic!("play_with_yarn called on a non-cat");
+	// And we can ask each animal whether it's a particular kind.
+	for a in &animals {
+		match a.as_dog() {
+			Some(dog) => println!("Found a dog with loudness {}!", dog.loudness()),
+			None      => println!("That's not a dog, that's a {}.", a.kind_name()),
 		}
 	}
 }
 
-fn main() {
-	// Let's make some animals!
-	let animals = vec![
-		Animal::new_cat(12.0, "Tuxedo"), // who could THIS be?
-		Animal::new_dog(43.0, 99999),    // very loud. ow
-		Animal::new_camel(1567.0, 2),    // it's a Bactrian
+fn trait_demo() {
+	// Box<dyn AnimalTrait> means "a pointer to *some* type that implements AnimalTrait."
+	// Each one can be a different struct, which is what lets us put them in the same Vec.
+	let animals: Vec<Box<dyn AnimalTrait>> = vec![
+		Box::new(Cat::new(12.0, "Tuxedo")),
+		Box::new(Dog::new(43.0, 100)),
+		Box::new(Camel::new(1567.0, 2)),
 	];
 
-	// {:#?} debug-prints things out with newlines and indentation.
 	println!("My animals: {:#?}", animals);
 
-	// Let's sum up all their weights!
+	// This code is identical to the enum version! The method calls just work differently:
+	// they look up which struct's weight() to call at runtime ("dynamic dispatch").
 	let total_weight: f32 = animals.iter().map(|a| a.weight()).sum();
 	println!("All the animals together weigh {} pounds.", total_weight);
 
-	// Let's make them speak!
 	println!("Let's see what they have to say: ");
 	for a in &animals {
-		print!("  ");
+		print!("  {} says ", a.describe());
 		a.speak();
 	}
 
-	// And finally, a cat-specific method.
+	// Here's the catch. animals[0] is a dyn AnimalTrait, not a Cat, so this doesn't compile:
+	// animals[0].play_with_yarn();
+
+	// We can only call it on something we *know* is a Cat.
 	print!("Time to play: ");
-	animals[0].play_with_yarn();
+	let cat = Cat::new(12.0, "Tuxedo");
+	cat.play_with_yarn();
+}
 
-	// Uncommenting this will crash the program!
-	// animals[1].play_with_yarn();
+fn load_demo() {
+	// `cargo run` runs the program in this crate's folder, which is where zoo.json is.
+	match load_zoo("zoo.json") {
+		Ok(animals) => {
+			println!("Loaded {} animals from zoo.json:", animals.len());
+
+			for a in &animals {
+				println!("  {}", a.describe());
+			}
+		}
+
+		Err(e) => println!("error: {}", e),
+	}
+
+	// and here are some of the mistakes serde catches for us.
+	let bad_zoos = [
+		r#"[ { "kind": "cow", "weight": 1200.0 } ]"#,
+		r#"[ { "kind": "dog", "weight": 43.0 } ]"#,
+		r#"[ { "kind": "camel", "weight": "heavy", "num_humps": 1 } ]"#,
+		r#"[ { "kind": "camel", "weight": 700.0, "num_humps": 7 } ]"#,
+	];
+
+	for json in bad_zoos.iter() {
+		println!("{}", json);
+
+		match parse_zoo(json) {
+			Ok(animals) => println!("  => somehow loaded {:?}", animals),
+			Err(e)      => println!("  => error: {}", e),
+		}
+	}
+}
+
+fn simulate_demo() {
+	let mut animals = vec![
+		Animal::builder().weight(12.0).cat("Tuxedo").build().unwrap(),
+		Animal::builder().weight(20.0).dog(30).build().unwrap(),
+		Animal::builder().weight(1567.0).camel(2).build().unwrap(),
+	];
+
+	for event in simulate(&mut animals, 8) {
+		println!("{}", event);
+	}
+
+	// the simulation changed the animals.
+	println!("After all that: {:#?}", animals);
 }
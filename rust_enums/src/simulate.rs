@@ -0,0 +1,154 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::{ Animal, AnimalKind };
+
+// ------------------------------------------------------------------------------------------------
+// Events
+// ------------------------------------------------------------------------------------------------
+
+// Something that happened during the simulation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event {
+	pub tick:   u32,
+	pub animal: usize, // index into the zoo.
+	pub kind:   EventKind,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum EventKind {
+	FellAsleep,
+	WokeUp,
+	Barked { loud: bool },
+	WaterLow { percent: f32 },
+	OutOfWater,
+}
+
+impl Display for Event {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use EventKind::*;
+
+		write!(f, "[tick {}] #{} ", self.tick, self.animal + 1)?;
+
+		match &self.kind {
+			FellAsleep            => write!(f, "curls up for a nap. zzz"),
+			WokeUp                => write!(f, "wakes up and stretches."),
+			Barked { loud: true } => write!(f, "barks: WOOF WOOF WOOF!!!"),
+			Barked { .. }         => write!(f, "barks: woof."),
+			WaterLow { percent }  => write!(f, "is getting thirsty ({}% water left).", percent),
+			OutOfWater            => write!(f, "is out of water! someone get this camel a drink."),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The simulation
+// ------------------------------------------------------------------------------------------------
+
+/*
+Each "tick" is one step of time. On every tick, every animal gets a chance to do something,
+depending on what kind of animal it is:
+
+- cats fall asleep and wake up at random.
+- dogs bark, and louder dogs are more likely to bark (loudness is out of 100).
+- camels use up the water in their humps. more humps = the water lasts longer.
+
+The interesting part is *how* this is written: `match &mut animal.kind` gives us a mutable
+reference to the data inside the variant, so we can change a cat's `napping` field right there.
+*/
+
+// Runs the simulation for the given number of ticks, changing the animals as it goes, and returns
+// a log of everything that happened. Uses the same random seed every time, so it always does the
+// same thing; use simulate_seeded to get something different.
+pub fn simulate(zoo: &mut [Animal], ticks: u32) -> Vec<Event> {
+	simulate_seeded(zoo, ticks, 1622)
+}
+
+pub fn simulate_seeded(zoo: &mut [Animal], ticks: u32, seed: u64) -> Vec<Event> {
+	let mut rng = Rng::new(seed);
+	let mut log = vec![];
+
+	for tick in 1 ..= ticks {
+		for (i, animal) in zoo.iter_mut().enumerate() {
+			if let Some(kind) = tick_animal(animal, &mut rng) {
+				log.push(Event { tick, animal: i, kind });
+			}
+		}
+	}
+
+	log
+}
+
+// One tick for one animal. Returns the event that happened, if any.
+fn tick_animal(animal: &mut Animal, rng: &mut Rng) -> Option<EventKind> {
+	match &mut animal.kind {
+		AnimalKind::Cat(cat) => {
+			if cat.napping {
+				// naps are long.
+				if rng.chance(0.3) {
+					cat.napping = false;
+					return Some(EventKind::WokeUp);
+				}
+			} else if rng.chance(0.5) {
+				cat.napping = true;
+				return Some(EventKind::FellAsleep);
+			}
+
+			None
+		}
+
+		AnimalKind::Dog(dog) => {
+			// validate() made sure it's from 0 to 100.
+			if rng.chance(dog.loudness as f64 / 100.0) {
+				Some(EventKind::Barked { loud: dog.loudness > 50 })
+			} else {
+				None
+			}
+		}
+
+		AnimalKind::Camel(camel) => {
+			if camel.water <= 0.0 {
+				return None;
+			}
+
+			let before = camel.water;
+			camel.water = (camel.water - 20.0 / camel.num_humps.max(1) as f32).max(0.0);
+
+			if camel.water == 0.0 {
+				Some(EventKind::OutOfWater)
+			} else if before > 50.0 && camel.water <= 50.0 {
+				Some(EventKind::WaterLow { percent: camel.water })
+			} else {
+				None
+			}
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Random numbers
+// ------------------------------------------------------------------------------------------------
+
+// A tiny "xorshift" pseudo-random number generator. The rand crate is what you'd use for real,
+// but this is enough for a zoo and it's kinda neat that it's only a few lines.
+struct Rng(u64);
+
+impl Rng {
+	fn new(seed: u64) -> Self {
+		// xorshift gets stuck at 0 forever, so don't let the seed be 0.
+		Rng(seed.max(1))
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	// returns true with the given probability (0.0 = never, 1.0 = always).
+	fn chance(&mut self, probability: f64) -> bool {
+		// the top 53 bits make a nice uniformly-distributed f64 in [0, 1).
+		let r = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+		r < probability
+	}
+}
@@ -0,0 +1,96 @@
+// ------------------------------------------------------------------------------------------------
+// The trait object version
+// ------------------------------------------------------------------------------------------------
+
+/*
+Here's the same zoo, but the set of animals is *open*: each animal is its own struct, and they all
+implement the AnimalTrait trait. Anyone (even code in another crate!) can make a new kind of animal
+by writing a struct and implementing the trait, without changing this file at all.
+
+The tradeoffs, compared to the enum version:
+
+- Adding a new *animal* is easy here (one new struct + impl), but hard with the enum (every match
+  has to be updated... though the compiler will tell you about every one you missed).
+- Adding a new *method* is easy with the enum (one new method with a match), but hard here (every
+  struct needs a new method in its impl).
+- With the enum, you can always find out what kind of animal you have with a match. Here, once you
+  have a `dyn AnimalTrait`, you've "forgotten" what struct it is. That's why play_with_yarn isn't
+  part of the trait: there's no good way to call it on a `dyn AnimalTrait`.
+
+In a compiler, the AST is usually a closed set (the language's grammar doesn't change at runtime),
+and we add new passes over it all the time, which is why ASTs are usually enums in Rust.
+*/
+
+// every animal has to provide these methods.
+pub trait AnimalTrait: std::fmt::Debug {
+	fn weight(&self) -> f32;
+	fn speak(&self);
+	fn describe(&self) -> String;
+}
+
+#[derive(Debug)]
+pub struct Cat {
+	pub weight:  f32,
+	pub pattern: String,
+}
+
+#[derive(Debug)]
+pub struct Dog {
+	pub weight:   f32,
+	pub loudness: i32,
+}
+
+#[derive(Debug)]
+pub struct Camel {
+	pub weight:    f32,
+	pub num_humps: i32,
+}
+
+impl Cat {
+	pub fn new(weight: f32, pattern: &str) -> Cat {
+		Cat { weight, pattern: pattern.into() }
+	}
+
+	// Cats are the only ones who can do this, so it's a regular method, not a trait method.
+	pub fn play_with_yarn(&self) {
+		println!("The {} cat plays with some yarn. Reoowwww", self.pattern);
+	}
+}
+
+impl Dog {
+	pub fn new(weight: f32, loudness: i32) -> Dog {
+		Dog { weight, loudness }
+	}
+}
+
+impl Camel {
+	pub fn new(weight: f32, num_humps: i32) -> Camel {
+		Camel { weight, num_humps }
+	}
+}
+
+// Notice how the code for each animal is all together here, whereas in the enum version, the
+// code for each *method* is all together instead.
+impl AnimalTrait for Cat {
+	fn weight(&self) -> f32 { self.weight }
+	fn speak(&self) { println!("meow!") }
+	fn describe(&self) -> String {
+		format!("a {}-pound {} cat", self.weight, self.pattern)
+	}
+}
+
+impl AnimalTrait for Dog {
+	fn weight(&self) -> f32 { self.weight }
+	fn speak(&self) { println!("woof!") }
+	fn describe(&self) -> String {
+		format!("a {}-pound dog (loudness {})", self.weight, self.loudness)
+	}
+}
+
+impl AnimalTrait for Camel {
+	fn weight(&self) -> f32 { self.weight }
+	fn speak(&self) { println!("ghhghgh!") }
+	fn describe(&self) -> String {
+		format!("a {}-pound camel with {} humps", self.weight, self.num_humps)
+	}
+}
@@ -0,0 +1,145 @@
+use std::fmt::{ Debug, Display, Formatter, Result as FmtResult };
+
+use crate::{ Node, TreeMap };
+
+// ------------------------------------------------------------------------------------------------
+// Paths and edits
+// ------------------------------------------------------------------------------------------------
+
+// Which child to go to from a node.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Side {
+	Left,
+	Right,
+}
+
+// How to get to a node from the root: e.g. [Left, Right] means "the root's left child's right
+// child." An empty path is the root itself.
+pub type TreePath = Vec<Side>;
+
+// One difference between two trees.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TreeEdit<T> {
+	Inserted { path: TreePath, value: T },
+	Removed  { path: TreePath, value: T },
+	Changed  { path: TreePath, old: T, new: T },
+}
+
+impl<T: Debug> Display for TreeEdit<T> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use TreeEdit::*;
+
+		match self {
+			Inserted { path, value } => write!(f, "inserted {:?} at {}", value, PathFmt(path)),
+			Removed  { path, value } => write!(f, "removed {:?} at {}", value, PathFmt(path)),
+			Changed  { path, old, new } =>
+				write!(f, "changed {:?} to {:?} at {}", old, new, PathFmt(path)),
+		}
+	}
+}
+
+// A little wrapper so paths can be printed like "root.left.right". We can't implement
+// Display on Vec<Side> directly, because neither Display nor Vec is from this crate.
+struct PathFmt<'a>(&'a [Side]);
+
+impl Display for PathFmt<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "root")?;
+
+		for side in self.0 {
+			match side {
+				Side::Left  => write!(f, ".left")?,
+				Side::Right => write!(f, ".right")?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The diff algorithm
+// ------------------------------------------------------------------------------------------------
+
+/*
+This is a *structural* diff: it walks both trees at the same time, comparing the nodes that are in
+the same position. It doesn't try to be clever about finding the smallest set of edits (that's a
+much harder problem), so if a whole subtree got shifted over, you'll see lots of changes.
+
+The edits describe how to turn `self` into `other`. So if you call expected.diff(&actual), then
+"inserted" means "this is in the actual tree, but shouldn't be," and "removed" means "this should
+be in the actual tree, but isn't."
+*/
+
+impl<T: PartialEq + Clone> Node<T> {
+	pub fn diff(&self, other: &Node<T>) -> Vec<TreeEdit<T>> {
+		let mut edits = vec![];
+		diff_nodes(Some(self), Some(other), &mut vec![], &mut edits);
+		edits
+	}
+}
+
+impl<K: Ord + Clone, V: PartialEq + Clone> TreeMap<K, V> {
+	pub fn diff(&self, other: &TreeMap<K, V>) -> Vec<TreeEdit<(K, V)>> {
+		let mut edits = vec![];
+		diff_nodes(self.root(), other.root(), &mut vec![], &mut edits);
+		edits
+	}
+}
+
+// `path` is the path to these two nodes. we push onto it when going down and pop when coming back
+// up, so there's only ever one Vec for the whole walk.
+fn diff_nodes<T: PartialEq + Clone>(old: Option<&Node<T>>, new: Option<&Node<T>>,
+path: &mut TreePath, edits: &mut Vec<TreeEdit<T>>) {
+	match (old, new) {
+		(None, None) => {}
+
+		// a whole subtree is missing from one tree, so every value in it was inserted/removed.
+		(None, Some(new)) => {
+			each_node(new, path, &mut |path, value| {
+				edits.push(TreeEdit::Inserted { path: path.clone(), value: value.clone() });
+			});
+		}
+
+		(Some(old), None) => {
+			each_node(old, path, &mut |path, value| {
+				edits.push(TreeEdit::Removed { path: path.clone(), value: value.clone() });
+			});
+		}
+
+		(Some(old), Some(new)) => {
+			if old.value != new.value {
+				edits.push(TreeEdit::Changed {
+					path: path.clone(),
+					old:  old.value.clone(),
+					new:  new.value.clone(),
+				});
+			}
+
+			path.push(Side::Left);
+			diff_nodes(old.left.as_deref(), new.left.as_deref(), path, edits);
+			path.pop();
+
+			path.push(Side::Right);
+			diff_nodes(old.right.as_deref(), new.right.as_deref(), path, edits);
+			path.pop();
+		}
+	}
+}
+
+// calls f on every node in the subtree in pre-order (node, then left, then right).
+fn each_node<T>(node: &Node<T>, path: &mut TreePath, f: &mut impl FnMut(&TreePath, &T)) {
+	f(path, &node.value);
+
+	if let Some(left) = &node.left {
+		path.push(Side::Left);
+		each_node(left, path, f);
+		path.pop();
+	}
+
+	if let Some(right) = &node.right {
+		path.push(Side::Right);
+		each_node(right, path, f);
+		path.pop();
+	}
+}
@@ -0,0 +1,84 @@
+use std::fmt::{ Display, Write };
+
+use crate::{ Node, TreeMap };
+
+// ------------------------------------------------------------------------------------------------
+// Graphviz DOT output
+// ------------------------------------------------------------------------------------------------
+
+/*
+DOT is the text format used by Graphviz (https://graphviz.org) to describe graphs. You can paste
+the output of to_dot() into an online viewer like https://dreampuf.github.io/GraphvizOnline to see
+a picture of your tree, or run `dot -Tpng tree.dot -o tree.png` if you have Graphviz installed.
+
+There's one annoying thing: Graphviz doesn't know about "left" and "right" children. If a node only
+has a right child, it'll happily draw that child directly underneath, or even on the left! To fix
+that, whenever a child is missing, we put an invisible placeholder node in its place, so the real
+child gets pushed over to the correct side.
+*/
+
+impl<T: Display> Node<T> {
+	pub fn to_dot(&self) -> String {
+		to_dot(Some(self), |value| value.to_string())
+	}
+}
+
+impl<K: Ord + Display, V: Display> TreeMap<K, V> {
+	pub fn to_dot(&self) -> String {
+		to_dot(self.root(), |(k, v)| format!("{}: {}", k, v))
+	}
+}
+
+// `label` is a closure that turns a node's value into the text shown in its circle.
+fn to_dot<T>(root: Option<&Node<T>>, label: impl Fn(&T) -> String) -> String {
+	let mut out = String::new();
+	out.push_str("digraph tree {\n");
+	out.push_str("\tnode [shape=circle];\n");
+
+	if let Some(root) = root {
+		let mut next_id = 0;
+		write_node(&mut out, root, &label, &mut next_id);
+	}
+
+	out.push_str("}\n");
+	out
+}
+
+// Writes this node and its subtree, and returns the name that was given to this node.
+fn write_node<T>(out: &mut String, node: &Node<T>, label: &impl Fn(&T) -> String,
+next_id: &mut usize) -> String {
+	let name = format!("n{}", next_id);
+	*next_id += 1;
+
+	// writeln! works on Strings too, as long as you `use std::fmt::Write`. writing to a String
+	// can't fail, so unwrap() is fine here.
+	writeln!(out, "\t{} [label=\"{}\"];", name, escape(&label(&node.value))).unwrap();
+
+	// leaves don't need placeholders; there's nothing to push around.
+	if node.left.is_none() && node.right.is_none() {
+		return name;
+	}
+
+	for child in [&node.left, &node.right].iter() {
+		match child {
+			Some(child) => {
+				let child_name = write_node(out, child, label, next_id);
+				writeln!(out, "\t{} -> {};", name, child_name).unwrap();
+			}
+
+			None => {
+				let placeholder = format!("n{}", next_id);
+				*next_id += 1;
+				writeln!(out, "\t{} [label=\"\", style=invis];", placeholder).unwrap();
+				writeln!(out, "\t{} -> {} [style=invis];", name, placeholder).unwrap();
+			}
+		}
+	}
+
+	name
+}
+
+// quotes and backslashes have to be escaped inside DOT strings.
+fn escape(s: &str) -> String {
+	s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,32 @@
+// these lines say that these modules *exist*...
+mod diff;
+mod dot;
+mod tree_map;
+
+// and these re-export everything in them, so users of this crate see it too.
+pub use crate::diff::*;
+pub use crate::tree_map::*;
+
+// ------------------------------------------------------------------------------------------------
+// Node
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug)]
+pub struct Node<T> {
+	// Option<> makes it nullable (can be None); Box<> makes it a reference.
+	// It looks verbose, but you rarely have to actually type this type.
+	pub left:  Option<Box<Node<T>>>,
+	pub right: Option<Box<Node<T>>>,
+	pub value: T,
+}
+
+impl<T> Node<T> {
+	// A convenience constructor to make a new boxed node with no children.
+	pub fn new(value: T) -> Box<Self> {
+		Box::new(Self {
+			value,
+			left: None,
+			right: None,
+		})
+	}
+}
@@ -1,26 +1,80 @@
-#[derive(Debug)]
-struct Node<T> {
-	// Option<> makes it nullable (can be None); Box<> makes it a reference.
-	// It looks verbose, but you rarely have to actually type this type.
-	left:  Option<Box<Node<T>>>,
-	right: Option<Box<Node<T>>>,
-	value: T,
-}
-
-impl<T> Node<T> {
-	// A convenience constructor to make a new boxed node with no children.
-	fn new(value: T) -> Box<Self> {
-		Box::new(Self {
-			value,
-			left: None,
-			right: None,
-		})
-	}
-}
+use rust_trees::*;
 
 fn main() {
 	let mut a = Node::new(5);
 	a.left  = Some(Node::new(2));
 	a.right = Some(Node::new(7));
 	println!("{:#?}", a);
-}
\ No newline at end of file
+
+	// paste this into a Graphviz viewer to see it!
+	println!("{}", a.to_dot());
+
+	tree_map_demo();
+	diff_demo();
+}
+
+fn tree_map_demo() {
+	// type inference figures out that this is a TreeMap<&str, i32> from how we use it below.
+	let mut ages = TreeMap::new();
+
+	// insert gives back the old value if there was one.
+	println!("insert bob:   {:?}", ages.insert("bob", 30));
+	println!("insert alice: {:?}", ages.insert("alice", 25));
+	println!("insert carol: {:?}", ages.insert("carol", 41));
+	println!("insert bob:   {:?}", ages.insert("bob", 31));
+	println!("there are {} people.", ages.len());
+
+	println!("alice is {:?}", ages.get(&"alice"));
+	println!("dave is {:?}", ages.get(&"dave"));
+
+	println!("remove bob: {:?}", ages.remove(&"bob"));
+	println!("remove bob: {:?}", ages.remove(&"bob"));
+	println!("there are {} people.", ages.len());
+	println!("{}", ages.to_dot());
+
+	// the entry API is great for counting things.
+	let mut counts = TreeMap::new();
+
+	for word in "the cat and the dog and the bird".split(' ') {
+		*counts.entry(word).or_insert(0) += 1;
+	}
+
+	println!("the: {:?}, and: {:?}, cat: {:?}",
+		counts.get(&"the"), counts.get(&"and"), counts.get(&"cat"));
+
+	// iterating gives the keys in sorted order, and .rev() gives them backwards.
+	print!("  forwards:");
+	for (word, count) in &counts {
+		print!(" {}={}", word, count);
+	}
+	println!();
+
+	print!(" backwards:");
+	for (word, count) in counts.iter().rev() {
+		print!(" {}={}", word, count);
+	}
+	println!();
+
+	// this consumes the map; we get to keep the keys and values.
+	let words: Vec<&str> = counts.into_iter().map(|(word, _)| word).collect();
+	println!("     words: {:?}", words);
+}
+
+fn diff_demo() {
+	// what we expected to get...
+	let mut expected = Node::new(5);
+	expected.left  = Some(Node::new(2));
+	expected.right = Some(Node::new(7));
+	expected.right.as_mut().unwrap().right = Some(Node::new(9));
+
+	// ...and what we actually got.
+	let mut actual = Node::new(5);
+	actual.left  = Some(Node::new(3));
+	actual.left.as_mut().unwrap().left = Some(Node::new(1));
+	actual.right = Some(Node::new(7));
+
+	println!("differences:");
+	for edit in expected.diff(&actual) {
+		println!("  {}", edit);
+	}
+}
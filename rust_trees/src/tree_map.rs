@@ -0,0 +1,372 @@
+use std::cmp::Ordering;
+
+use crate::Node;
+
+// ------------------------------------------------------------------------------------------------
+// TreeMap
+// ------------------------------------------------------------------------------------------------
+
+/*
+A TreeMap is a binary search tree (BST) where every node holds a (key, value) pair. For every node,
+all the keys in its left subtree are less than its key, and all the keys in its right subtree are
+greater. That's what lets us find a key without looking at every node.
+
+The interface is modeled on std's BTreeMap, so once you understand this, you understand the most
+important parts of that too. (BTreeMap is a *B-tree*, which keeps itself balanced; ours doesn't, so
+inserting keys in sorted order gives you a very sad, very long linked list.)
+
+`K: Ord` means "K can be any type, as long as its values can be put in order." We need that to
+decide whether to go left or right.
+*/
+
+// this is just a shorthand for the type of a "link" to a child node.
+type Link<K, V> = Option<Box<Node<(K, V)>>>;
+
+#[derive(Debug)]
+pub struct TreeMap<K: Ord, V> {
+	root: Link<K, V>,
+	len:  usize,
+}
+
+// Default lets you write TreeMap::default(), and lets TreeMaps be fields of other
+// #[derive(Default)] structs.
+impl<K: Ord, V> Default for TreeMap<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Ord, V> TreeMap<K, V> {
+	pub fn new() -> Self {
+		TreeMap { root: None, len: 0 }
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	// Finding a key is a loop that goes left or right at each node until it finds
+	// the key or falls off the bottom of the tree.
+	pub fn get(&self, key: &K) -> Option<&V> {
+		let mut cur = &self.root;
+
+		while let Some(node) = cur {
+			match key.cmp(&node.value.0) {
+				Ordering::Less    => cur = &node.left,
+				Ordering::Greater => cur = &node.right,
+				Ordering::Equal   => return Some(&node.value.1),
+			}
+		}
+
+		None
+	}
+
+	pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+		match Self::find_slot(&mut self.root, key) {
+			Some(node) => Some(&mut node.value.1),
+			None       => None,
+		}
+	}
+
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.get(key).is_some()
+	}
+
+	// Like BTreeMap, inserting a key that's already there replaces its value and gives
+	// you back the old one.
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		let slot = Self::find_slot(&mut self.root, &key);
+
+		match slot {
+			// std::mem::replace puts the new value in and hands us the old one. we can't
+			// just "move out" of a &mut, because that would leave a hole behind.
+			Some(node) => Some(std::mem::replace(&mut node.value.1, value)),
+
+			None => {
+				*slot = Some(Node::new((key, value)));
+				self.len += 1;
+				None
+			}
+		}
+	}
+
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		let slot = Self::find_slot(&mut self.root, key);
+
+		// .take() moves the node out of the slot, leaving None behind.
+		let mut node = slot.take()?;
+		self.len -= 1;
+
+		// now we have to fill the hole we left in the tree.
+		*slot = match (node.left.take(), node.right.take()) {
+			// zero or one children: the child (if any) just moves up.
+			(None, child) | (child, None) => child,
+
+			// two children: the smallest node in the right subtree is bigger than
+			// everything on the left and smaller than everything else on the right,
+			// so it can take the removed node's place.
+			(left, Some(right)) => {
+				let mut right = Some(right);
+				let mut successor = Self::take_min(&mut right);
+				successor.left  = left;
+				successor.right = right;
+				Some(successor)
+			}
+		};
+
+		Some(node.value.1)
+	}
+
+	// The root node, if the map isn't empty. This is pub(crate), which means the other
+	// modules in this crate can use it, but users of the crate can't.
+	pub(crate) fn root(&self) -> Option<&Node<(K, V)>> {
+		self.root.as_deref()
+	}
+
+	// Iterates over the (key, value) pairs in order of increasing key. Because this gives a
+	// DoubleEndedIterator, .rev() gives them in decreasing order.
+	pub fn iter(&self) -> Iter<'_, K, V> {
+		Iter::new(&self.root, self.len)
+	}
+
+	// Gets an Entry for the given key, which lets you look up a key and insert it if it's
+	// missing *without* searching the tree twice. It's used like:
+	//     *map.entry(word).or_insert(0) += 1;
+	pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+		let slot = Self::find_slot(&mut self.root, &key);
+		Entry { slot, key, len: &mut self.len }
+	}
+
+	// Finds the "slot" (the link from a parent, or the root) where this key lives or
+	// *would* live. If the key is in the tree, the slot is Some; if not, it's the None
+	// where we'd have to put a new node.
+	fn find_slot<'a>(mut slot: &'a mut Link<K, V>, key: &K) -> &'a mut Link<K, V> {
+		loop {
+			// we figure out which way to go *before* moving slot, because the borrow
+			// checker doesn't like it when we look into a slot and then replace it while
+			// still looking.
+			let go_left = match slot {
+				None       => return slot,
+				Some(node) => match key.cmp(&node.value.0) {
+					Ordering::Equal   => return slot,
+					Ordering::Less    => true,
+					Ordering::Greater => false,
+				}
+			};
+
+			// unwrap() can't fail, because we just saw that it was Some.
+			let node = slot.as_mut().unwrap();
+			slot = if go_left { &mut node.left } else { &mut node.right };
+		}
+	}
+
+	// Removes the node with the smallest key from the (non-empty) subtree and returns it.
+	fn take_min(mut slot: &mut Link<K, V>) -> Box<Node<(K, V)>> {
+		while slot.as_ref().unwrap().left.is_some() {
+			slot = &mut slot.as_mut().unwrap().left;
+		}
+
+		// the smallest node may still have a right child, which moves up.
+		let mut min = slot.take().unwrap();
+		*slot = min.right.take();
+		min
+	}
+
+	// The mirror image of take_min.
+	fn take_max(mut slot: &mut Link<K, V>) -> Box<Node<(K, V)>> {
+		while slot.as_ref().unwrap().right.is_some() {
+			slot = &mut slot.as_mut().unwrap().right;
+		}
+
+		let mut max = slot.take().unwrap();
+		*slot = max.left.take();
+		max
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Entry
+// ------------------------------------------------------------------------------------------------
+
+// The 'a lifetime says "an Entry can't outlive the TreeMap it came from." It holds onto the
+// slot where the key is (or would be), so it can fill it in later.
+pub struct Entry<'a, K: Ord, V> {
+	slot: &'a mut Link<K, V>,
+	key:  K,
+	len:  &'a mut usize,
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	// If the key is missing, inserts `default`. Either way, gives you the value in the map.
+	pub fn or_insert(self, default: V) -> &'a mut V {
+		self.or_insert_with(|| default)
+	}
+
+	// Same, but only calls `f` to make the value if it's actually needed.
+	pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+		if self.slot.is_none() {
+			*self.slot = Some(Node::new((self.key, f())));
+			*self.len += 1;
+		}
+
+		&mut self.slot.as_mut().unwrap().value.1
+	}
+
+	// Modifies the value if the key is present. Returns the entry so you can chain
+	// .or_insert() after it.
+	pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+		if let Some(node) = self.slot.as_mut() {
+			f(&mut node.value.1);
+		}
+
+		self
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Iter (borrowing, in-order)
+// ------------------------------------------------------------------------------------------------
+
+/*
+The easy way to visit a tree in order is recursion: visit left, visit self, visit right. But an
+iterator has to *stop* after each item and pick up where it left off on the next call to next(),
+and you can't pause in the middle of a recursive call. So instead, we keep the recursion's "to do
+list" in an explicit stack.
+
+The front stack holds the nodes on the path to the next-smallest key which we haven't visited yet.
+The back stack is the mirror image, for next_back(). The two ends would eventually run into each
+other, so we also count how many items are left, and stop when it hits 0.
+*/
+
+pub struct Iter<'a, K, V> {
+	front:     Vec<&'a Node<(K, V)>>,
+	back:      Vec<&'a Node<(K, V)>>,
+	remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+	fn new(root: &'a Option<Box<Node<(K, V)>>>, len: usize) -> Self {
+		let mut ret = Iter { front: vec![], back: vec![], remaining: len };
+		ret.push_left_edge(root);
+		ret.push_right_edge(root);
+		ret
+	}
+
+	// pushes the node and all its left descendants; the last one pushed is the smallest.
+	fn push_left_edge(&mut self, mut link: &'a Option<Box<Node<(K, V)>>>) {
+		while let Some(node) = link {
+			self.front.push(node);
+			link = &node.left;
+		}
+	}
+
+	fn push_right_edge(&mut self, mut link: &'a Option<Box<Node<(K, V)>>>) {
+		while let Some(node) = link {
+			self.back.push(node);
+			link = &node.right;
+		}
+	}
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+	type Item = (&'a K, &'a V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+
+		// the node on top of the stack has had its whole left subtree visited already,
+		// so it's next. after it come the nodes in its right subtree.
+		let node = self.front.pop()?;
+		self.push_left_edge(&node.right);
+		self.remaining -= 1;
+		Some((&node.value.0, &node.value.1))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+
+		let node = self.back.pop()?;
+		self.push_right_edge(&node.left);
+		self.remaining -= 1;
+		Some((&node.value.0, &node.value.1))
+	}
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+// this is what makes `for (k, v) in &map` work.
+impl<'a, K: Ord, V> IntoIterator for &'a TreeMap<K, V> {
+	type Item     = (&'a K, &'a V);
+	type IntoIter = Iter<'a, K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// IntoIter (consuming)
+// ------------------------------------------------------------------------------------------------
+
+/*
+This iterator *consumes* the map, giving you ownership of each key and value. Since we own the
+nodes now, we don't need stacks at all: we just pull the smallest (or largest) node out of the
+tree each time, using the same helpers that remove() uses. The tree shrinks as we go, and when it
+runs out, so does the iterator. No recursion needed!
+*/
+
+pub struct IntoIter<K: Ord, V> {
+	map: TreeMap<K, V>,
+}
+
+impl<K: Ord, V> Iterator for IntoIter<K, V> {
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.map.root.as_ref()?;
+		self.map.len -= 1;
+		Some(TreeMap::take_min(&mut self.map.root).value)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.map.len, Some(self.map.len))
+	}
+}
+
+impl<K: Ord, V> DoubleEndedIterator for IntoIter<K, V> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.map.root.as_ref()?;
+		self.map.len -= 1;
+		Some(TreeMap::take_max(&mut self.map.root).value)
+	}
+}
+
+impl<K: Ord, V> ExactSizeIterator for IntoIter<K, V> {}
+
+// and this is what makes `for (k, v) in map` work.
+impl<K: Ord, V> IntoIterator for TreeMap<K, V> {
+	type Item     = (K, V);
+	type IntoIter = IntoIter<K, V>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIter { map: self }
+	}
+}
@@ -0,0 +1,15 @@
+// Semantic analysis is everything the compiler checks *after* parsing: does every name refer to
+// something, do the types make sense, and so on. These passes work on toylang's AST. (lint.rs is
+// the odd one out: it only gives warnings, for programs that are allowed but look like mistakes.)
+
+mod lint;
+mod resolve;
+mod symbols;
+mod typed;
+mod types;
+
+pub use crate::lint::*;
+pub use crate::resolve::*;
+pub use crate::symbols::*;
+pub use crate::typed::*;
+pub use crate::types::*;
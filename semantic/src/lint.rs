@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+use std::str::FromStr;
+
+use diagnostics::{ Diagnostic, ErrorCode, Span };
+use toylang::{ AstKind, AstNode, Program, Stmt, StmtKind };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Lints
+// ------------------------------------------------------------------------------------------------
+
+/*
+An error means the program is wrong. A *warning* means the program is allowed, but it has something
+in it that probably isn't what you meant. The program still runs (and still compiles), but it's
+worth a look. Checks like these are often called *lints*, after an old C tool named `lint` that
+picked the "fluff" out of programs that the compiler let through.
+
+This pass finds three kinds:
+
+- unused variables: `let x = 5`, and then x is never used. Maybe it's left over from code that
+  was deleted, or maybe some other variable was used by mistake where x was supposed to go.
+- unused parameters: the same thing, for a function's parameters.
+- shadowing: a `let` or a parameter inside a function with the same name as something outside of
+  it, which can't be used inside the function anymore. That's confusing to read, and if it was
+  supposed to change the outer variable, `=` (not `let`) is what does that.
+
+Each kind of warning can be turned off by passing it in `allow` (the compiler's `--allow=shadowing`
+flag does that). The unused warnings can also be turned off for just one variable, by starting its
+name with an underscore, like `_unused`. That's what Rust does.
+
+It has to run after resolution, since "is x used?" means "is there a name anywhere that the
+resolver said refers to *this* x?" The symbol table already knows what each symbol shadows (see
+SymbolTable::define), so that one's just a matter of asking it.
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Lint {
+	UnusedVariable,
+	UnusedParameter,
+	Shadowing,
+}
+
+impl Lint {
+	// the code that the warning has, which can also be used to allow it.
+	pub fn code(self) -> ErrorCode {
+		match self {
+			Lint::UnusedVariable  => diagnostics::UNUSED_VARIABLE,
+			Lint::UnusedParameter => diagnostics::UNUSED_PARAMETER,
+			Lint::Shadowing       => diagnostics::SHADOWED_NAME,
+		}
+	}
+}
+
+impl Display for Lint {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Lint::UnusedVariable  => write!(f, "unused-variable"),
+			Lint::UnusedParameter => write!(f, "unused-parameter"),
+			Lint::Shadowing       => write!(f, "shadowing"),
+		}
+	}
+}
+
+// either the name, like "unused-variable", or the code, like "E0206".
+impl FromStr for Lint {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let all = [Lint::UnusedVariable, Lint::UnusedParameter, Lint::Shadowing];
+
+		all.iter().copied()
+			.find(|lint| lint.to_string() == s || Some(lint.code()) == ErrorCode::parse(s))
+			.ok_or_else(|| format!("invalid warning '{}' (use unused-variable, unused-parameter, \
+				or shadowing)", s))
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The lint pass
+// ------------------------------------------------------------------------------------------------
+
+// Gives all the warnings for a program that resolved without errors, in the order their symbols
+// were defined. Any kind of warning in `allow` is left out.
+pub fn lint(program: &Program, resolved: &Resolved, allow: &[Lint]) -> Vec<Diagnostic> {
+	let mut l = Linter { resolved, used: HashSet::new(), params: HashSet::new() };
+
+	for stmt in &program.stmts {
+		l.visit_stmt(stmt);
+	}
+
+	let mut warnings = vec![];
+
+	for (id, sym) in resolved.symbols.iter() {
+		// builtins are never unused, and they're never shadowing anything, either.
+		let span = match sym.span {
+			Some(span) if sym.kind == SymbolKind::Variable => span,
+			_ => continue,
+		};
+
+		let is_param = l.params.contains(&span);
+
+		if !l.used.contains(&id) && !sym.name.starts_with('_') {
+			warnings.push(unused_warning(sym, span, is_param));
+		}
+
+		if let Some(outer) = sym.shadows {
+			warnings.push(shadow_warning(sym, resolved.symbols.get(outer), span, is_param));
+		}
+	}
+
+	warnings.retain(|w| !allow.iter().any(|lint| w.code == Some(lint.code())));
+	warnings
+}
+
+fn unused_warning(sym: &Symbol, span: Span, is_param: bool) -> Diagnostic {
+	let (what, code) = if is_param {
+		("parameter", diagnostics::UNUSED_PARAMETER)
+	} else {
+		("variable", diagnostics::UNUSED_VARIABLE)
+	};
+
+	Diagnostic::warning(format!("unused {} '{}'", what, sym.name), span)
+		.with_code(code)
+		.with_note(format!("if that's on purpose, call it '_{}' instead", sym.name))
+}
+
+fn shadow_warning(sym: &Symbol, outer: &Symbol, span: Span, is_param: bool) -> Diagnostic {
+	let warning = match outer.kind {
+		SymbolKind::Variable => Diagnostic::warning(format!("'{}' shadows a variable outside of \
+			this function", sym.name), span)
+			.with_note(if is_param {
+				format!("the outer '{}' can't be used in this function; rename the parameter to \
+					use both", sym.name)
+			} else {
+				format!("the outer '{}' can't be used after this; to change it instead, use '=' \
+					without 'let'", sym.name)
+			}),
+
+		SymbolKind::Constant => Diagnostic::warning(format!("'{}' shadows the builtin constant \
+			'{}'", sym.name, outer.name), span),
+
+		SymbolKind::Function { .. } => Diagnostic::warning(format!("'{}' shadows the builtin \
+			function '{}'", sym.name, outer.name), span),
+	};
+
+	warning.with_code(diagnostics::SHADOWED_NAME)
+}
+
+struct Linter<'r> {
+	resolved: &'r Resolved,
+	used:     HashSet<SymbolId>, // every symbol that some name refers to.
+	params:   HashSet<Span>,     // the span of every parameter, to tell them apart from variables.
+}
+
+impl Linter<'_> {
+	// only an Ident is a *use* of a variable. the names in `let x = ...` and `x = ...` are in the
+	// resolver's side table too, but they give x a value; they don't use it.
+	fn visit_stmt(&mut self, stmt: &Stmt) {
+		use StmtKind::*;
+
+		match &stmt.kind {
+			Let { value, .. } | Assign { value, .. } => self.visit_exp(value),
+			Exp { exp } => self.visit_exp(exp),
+
+			While { cond, body } => {
+				self.visit_exp(cond);
+				body.iter().for_each(|stmt| self.visit_stmt(stmt));
+			}
+
+			If { cond, then_body, else_body } => {
+				self.visit_exp(cond);
+				self.visit_stmt(then_body);
+				else_body.iter().for_each(|stmt| self.visit_stmt(stmt));
+			}
+
+			Block { stmts } => stmts.iter().for_each(|stmt| self.visit_stmt(stmt)),
+			Break | Continue => {}
+		}
+	}
+
+	fn visit_exp(&mut self, node: &AstNode) {
+		use AstKind::*;
+
+		match &node.kind {
+			IntLit { .. } | FloatLit { .. } | BoolLit { .. } | StrLit { .. } | Error => {}
+
+			Ident { .. } => {
+				if let Some(&id) = self.resolved.names.get(&node.span) {
+					self.used.insert(id);
+				}
+			}
+
+			Negate { lhs } | Not { lhs } => self.visit_exp(lhs),
+
+			Binary { .. } => {
+				// a loop, so a long chain of operators can't use up the stack.
+				let (first, ops) = node.left_chain();
+				self.visit_exp(first);
+				ops.into_iter().for_each(|(_, _, rhs)| self.visit_exp(rhs));
+			}
+
+			Call { callee, args } => {
+				self.visit_exp(callee);
+				args.iter().for_each(|arg| self.visit_exp(arg));
+			}
+
+			Array { elems }        => elems.iter().for_each(|elem| self.visit_exp(elem)),
+			Index { array, index } => { self.visit_exp(array); self.visit_exp(index); }
+
+			Match { scrutinee, arms } => {
+				self.visit_exp(scrutinee);
+				arms.iter().for_each(|arm| self.visit_exp(&arm.value));
+			}
+
+			Func { params, body } => {
+				self.params.extend(params.iter().map(|param| param.span));
+				body.iter().for_each(|stmt| self.visit_stmt(stmt));
+			}
+		}
+	}
+}
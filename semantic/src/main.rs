@@ -0,0 +1,155 @@
+use diagnostics::{ render_all, SourceFile };
+use semantic::*;
+use toylang::{ compile, Env };
+
+fn main() {
+	let good = "let r = 2.5
+area = pi * pow(r, 2)
+r = r + 1
+let e = area * 2";
+
+	// one of each kind of error.
+	let bad = "let total = 0
+total = total + count
+let total = 10
+y = x * 2
+let x = 5
+break";
+
+	// these all resolve fine, but the types are wrong.
+	let badly_typed = "let a = 3
+let b = 4.5
+c = 1 + true
+d = (a < b) * 2
+a = 0.5
+f = !a || sqrt(true)
+while a { a = a - 1 }";
+
+	// the arms of a match are checked against each other, too.
+	let bad_matches = "let n = 2
+size = match n {
+	0 => 0.5
+	1 => 1
+	1 => 2
+	_ => 3
+	4 => 4
+}
+yes = match n { 0 => false, _ => 1 }
+match size { 1 => 1 }";
+
+	// strings can be joined and compared with other strings, but not mixed with numbers.
+	let strings = "let name = \"world\"
+hello = \"hello, \" + name
+sorted = name < \"zebra\"
+shout = hello + 1
+less = name < 5
+neg = -name";
+
+	// an if's condition has to be a bool, and its blocks are checked like any others.
+	let ifs = "let n = 7
+if n % 2 == 0 {
+	kind = \"even\"
+} else if n {
+	kind = 1
+}";
+
+	// an array's values all have to be the same type, and only arrays can be indexed.
+	let arrays = "let a = [1, 2.5, 3]
+let grid = [[1, 2], [3, 4]]
+corner = grid[1][0] + len(a)
+mixed = [1, true]
+empty = []
+bad = a[0.5] + corner[0]
+same = a == a";
+
+	// these are all allowed, but look like mistakes, so they're warnings instead of errors.
+	let warnings = "let total = 0
+let unused = 5
+let _on_purpose = 6
+let e = 2.5
+let add = fn(x, y) {
+	let total = total + x
+	total
+}
+let scale = fn(total) { total * e }
+add(1, 2) + scale(3)";
+
+	for src in &[good, bad, badly_typed, bad_matches, strings, ifs, arrays, warnings] {
+		println!("{}\n", src);
+		analyze(src);
+		println!("------------------------------------------------------------------------");
+	}
+}
+
+fn analyze(src: &str) {
+	let source = SourceFile::new("<demo>", src);
+
+	// resolution only makes sense on a program that parsed, so compile() comes first.
+	let program = match compile(src) {
+		Ok(program) => program,
+		Err(errs)   => { print!("{}", render_all(&errs, &source)); return; }
+	};
+
+	let resolved = match resolve(&program, &Env::new()) {
+		Ok(resolved) => resolved,
+		Err(errs)    => { print!("{}", render_all(&errs, &source)); return; }
+	};
+
+	show_resolution(&resolved, &source);
+
+	let warnings = lint(&program, &resolved, &[]);
+
+	if !warnings.is_empty() {
+		print!("\n{}", render_all(&warnings, &source));
+	}
+
+	// and types only make sense once we know what every name refers to.
+	match typecheck(&program, &resolved) {
+		Ok(typed) => show_types(&typed),
+		Err(errs) => print!("\n{}", render_all(&errs, &source)),
+	}
+}
+
+fn show_resolution(resolved: &Resolved, source: &SourceFile) {
+	println!("symbols:");
+
+	for (id, sym) in resolved.symbols.iter() {
+		let place = match sym.span {
+			Some(span) => {
+				let (line, col) = source.line_col(span.start);
+				format!("defined at {}:{}", line, col)
+			}
+
+			None => "builtin".into(),
+		};
+
+		let (id, kind) = (id.to_string(), sym.kind.to_string());
+		println!("  {:>3} {:<6} {:<12} ({})", id, sym.name, kind, place);
+	}
+
+	// sort them by where they are in the code, so it reads top to bottom.
+	let mut names = resolved.names.iter().collect::<Vec<_>>();
+	names.sort_by_key(|(span, _)| span.start);
+
+	println!("\nnames:");
+
+	for (span, id) in names {
+		let (l, c) = source.line_col(span.start);
+		println!("  {}:{:<3} {:<6} -> {}", l, c, resolved.symbols.get(*id).name, id);
+	}
+}
+
+// the typed AST has the type of every expression on it, and its names are SymbolIds (which match
+// the ones in the symbols list above).
+fn show_types(program: &TProgram) {
+	print!("\ntyped AST:\n{}", program);
+
+	let mut vars = program.vars.iter().collect::<Vec<_>>();
+	vars.sort_by_key(|(id, _)| id.0);
+
+	println!("\nvariables:");
+
+	for (id, ty) in vars {
+		println!("  {:<24} : {}", program.symbols.get(*id).name, ty);
+	}
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use diagnostics::{ Diagnostic, Span };
+use toylang::{ AstKind, AstNode, Env, Program, Stmt, StmtKind };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Resolution
+// ------------------------------------------------------------------------------------------------
+
+/*
+Name resolution (also called "scope checking" or "binding") is the first semantic-analysis pass.
+It walks the AST and, for every place a name appears, figures out which symbol it means. Along the
+way, it catches these errors:
+
+- undefined names: `y + 1` when there's no `y` anywhere.
+- use before definition: `y + 1` on line 1 when `let y = 5` is on line 2. At runtime, this is the
+  same error as an undefined name, but we can give a much more helpful message.
+- duplicate definitions: `let x = 1` and then `let x = 2` in the same scope. (`x = 2` is fine;
+  that changes the existing x.)
+- `break` or `continue` outside of a loop. That's not about names at all, but this pass already
+  walks every statement and knows how deep in loops it is, so it's the easiest place to check.
+  (A loop's body can't `break` out of a function that's called in it, so a function's body
+  starts back at 0 loops deep.)
+
+A function's body is a new scope, with its parameters in it, so they can't be seen outside of it,
+and two parameters with the same name are a duplicate definition. To let a function call itself,
+`let f = fn...` defines f *before* resolving the function. (Two functions that call each other
+can't be resolved, since the first one would use the second before it's defined.)
+
+The results are a SymbolTable and a *side table* that maps the span of each name in the AST to
+its SymbolId. Why a side table instead of putting the id in the AST? The AST belongs to the toylang
+crate and doesn't know this pass exists. Every AST node has a different span, so spans work fine
+as keys.
+*/
+
+#[derive(Debug, Clone)]
+pub struct Resolved {
+	pub symbols: SymbolTable,
+	pub names:   HashMap<Span, SymbolId>, // the span of each name -> what it refers to.
+}
+
+impl Resolved {
+	// What the name at this span refers to.
+	pub fn symbol_at(&self, span: Span) -> Option<&Symbol> {
+		self.names.get(&span).map(|&id| self.symbols.get(id))
+	}
+}
+
+// The builtin constants and functions in `env` are defined first, so the program can use them.
+pub fn resolve(program: &Program, env: &Env) -> Result<Resolved, Vec<Diagnostic>> {
+	let mut r = Resolver { symbols: SymbolTable::new(), names: HashMap::new(), errors: vec![],
+		later: HashMap::new(), loop_depth: 0 };
+
+	for (name, _) in env.vars() {
+		r.symbols.define(&name, SymbolKind::Constant, None).unwrap();
+	}
+
+	for (name, builtin) in env.builtins() {
+		r.symbols.define(name, SymbolKind::Function { arity: builtin.arity }, None).unwrap();
+	}
+
+	// the builtins get their own scope, so the program can make a variable named `e` without it
+	// being a duplicate definition.
+	r.symbols.push_scope();
+	r.resolve_block(&program.stmts);
+
+	if r.errors.is_empty() {
+		Ok(Resolved { symbols: r.symbols, names: r.names })
+	} else {
+		Err(r.errors)
+	}
+}
+
+struct Resolver {
+	symbols: SymbolTable,
+	names:   HashMap<Span, SymbolId>,
+	errors:  Vec<Diagnostic>,
+
+	// the names that the statements in the current block define, and where. this is how we can
+	// tell "used before definition" apart from "undefined."
+	later:   HashMap<String, Span>,
+
+	// how many loops the current statement is inside of.
+	loop_depth: usize,
+}
+
+impl Resolver {
+	// the statements of one scope: the whole program, or a function's body. `{ }` blocks and loop
+	// bodies don't make a new one, so the names they define are visible after them.
+	fn resolve_block(&mut self, stmts: &[Stmt]) {
+		let outer = std::mem::replace(&mut self.later, collect_definitions(stmts));
+
+		for stmt in stmts {
+			self.resolve_stmt(stmt);
+		}
+
+		self.later = outer;
+	}
+
+	fn resolve_stmt(&mut self, stmt: &Stmt) {
+		use StmtKind::*;
+
+		match &stmt.kind {
+			// the value is resolved *before* the name is defined, so in `let x = x + 1`, the
+			// `x + 1` means some other x (or is an error). functions are the exception, so they
+			// can call themselves.
+			Let { name, name_span, value } => {
+				let is_func = matches!(value.kind, AstKind::Func { .. });
+
+				if !is_func {
+					self.resolve_exp(value);
+				}
+
+				match self.symbols.define(name, SymbolKind::Variable, Some(*name_span)) {
+					Ok(id) => { self.names.insert(*name_span, id); }
+					Err(existing) => {
+						self.names.insert(*name_span, existing);
+						self.errors.push(duplicate_error(name, *name_span));
+					}
+				}
+
+				if is_func {
+					self.resolve_exp(value);
+				}
+			}
+
+			// assigning to a name that doesn't exist yet defines it, like in the interpreter.
+			Assign { name, name_span, value } => {
+				let is_func = matches!(value.kind, AstKind::Func { .. });
+
+				if !is_func {
+					self.resolve_exp(value);
+				}
+
+				let id = match self.symbols.lookup(name) {
+					Some(id) => id,
+					None     => self.symbols.define(name, SymbolKind::Variable, Some(*name_span))
+						.expect("name was just looked up and not found"),
+				};
+
+				self.names.insert(*name_span, id);
+
+				if is_func {
+					self.resolve_exp(value);
+				}
+			}
+
+			Exp { exp } => self.resolve_exp(exp),
+
+			While { cond, body } => {
+				self.resolve_exp(cond);
+				self.loop_depth += 1;
+
+				for stmt in body {
+					self.resolve_stmt(stmt);
+				}
+
+				self.loop_depth -= 1;
+			}
+
+			If { cond, then_body, else_body } => {
+				self.resolve_exp(cond);
+				self.resolve_stmt(then_body);
+
+				if let Some(else_body) = else_body {
+					self.resolve_stmt(else_body);
+				}
+			}
+
+			Block { stmts } => {
+				for stmt in stmts {
+					self.resolve_stmt(stmt);
+				}
+			}
+
+			Break | Continue if self.loop_depth == 0 => {
+				let what = if matches!(stmt.kind, Break) { "break" } else { "continue" };
+				self.errors.push(Diagnostic::error(format!("'{}' outside of a loop", what),
+					stmt.span).with_code(diagnostics::BREAK_OUTSIDE_LOOP));
+			}
+
+			Break | Continue => {}
+		}
+	}
+
+	fn resolve_exp(&mut self, node: &AstNode) {
+		use AstKind::*;
+
+		match &node.kind {
+			IntLit { .. } | FloatLit { .. } | BoolLit { .. } | StrLit { .. } | Error => {}
+
+			Ident  { name } => self.resolve_name(name, node.span),
+			Negate { lhs }  => self.resolve_exp(lhs),
+			Not    { lhs }  => self.resolve_exp(lhs),
+
+			Binary { .. } => {
+				// a loop, so a long chain of operators can't use up the stack.
+				let (first, ops) = node.left_chain();
+				self.resolve_exp(first);
+				ops.into_iter().for_each(|(_, _, rhs)| self.resolve_exp(rhs));
+			}
+
+			Call { callee, args } => {
+				self.resolve_exp(callee);
+
+				for arg in args {
+					self.resolve_exp(arg);
+				}
+			}
+
+			Array { elems } => {
+				for elem in elems {
+					self.resolve_exp(elem);
+				}
+			}
+
+			Index { array, index } => { self.resolve_exp(array); self.resolve_exp(index); }
+
+			Match { scrutinee, arms } => {
+				self.resolve_exp(scrutinee);
+
+				for arm in arms {
+					self.resolve_exp(&arm.value);
+				}
+			}
+
+			Func { params, body } => {
+				let outer_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+				self.symbols.push_scope();
+
+				for param in params {
+					match self.symbols.define(&param.name, SymbolKind::Variable, Some(param.span)) {
+						Ok(id) => { self.names.insert(param.span, id); }
+						Err(existing) => {
+							self.names.insert(param.span, existing);
+							self.errors.push(Diagnostic::error(
+								format!("'{}' is already a parameter", param.name), param.span)
+								.with_code(diagnostics::DUPLICATE_DEFINITION));
+						}
+					}
+				}
+
+				self.resolve_block(body);
+				self.symbols.pop_scope();
+				self.loop_depth = outer_loop_depth;
+			}
+		}
+	}
+
+	fn resolve_name(&mut self, name: &str, span: Span) {
+		if let Some(id) = self.symbols.lookup(name) {
+			self.names.insert(span, id);
+			return;
+		}
+
+		let err = match self.later.get(name) {
+			Some(def) if def.start > span.start => {
+				let note = format!("'{}' is given a value later; move that up above here", name);
+				Diagnostic::error(format!("'{}' is used before it is defined", name), span)
+					.with_code(diagnostics::USE_BEFORE_DEFINITION)
+					.with_note(note)
+			}
+
+			_ => Diagnostic::error(format!("undefined variable '{}'", name), span)
+				.with_code(diagnostics::UNDEFINED_NAME),
+		};
+
+		self.errors.push(err);
+	}
+}
+
+fn duplicate_error(name: &str, span: Span) -> Diagnostic {
+	Diagnostic::error(format!("'{}' is already defined", name), span)
+		.with_code(diagnostics::DUPLICATE_DEFINITION)
+		.with_note(format!("to change the existing '{}', leave off the 'let'", name))
+}
+
+// the first place each name is defined in these statements (including the ones inside loops, ifs,
+// and blocks, since they're in the same scope).
+fn collect_definitions(stmts: &[Stmt]) -> HashMap<String, Span> {
+	let mut ret = HashMap::new();
+	collect_into(stmts, &mut ret);
+	ret
+}
+
+fn collect_into(stmts: &[Stmt], ret: &mut HashMap<String, Span>) {
+	for stmt in stmts {
+		match &stmt.kind {
+			StmtKind::Let { name, name_span, .. } | StmtKind::Assign { name, name_span, .. } => {
+				ret.entry(name.clone()).or_insert(*name_span);
+			}
+
+			StmtKind::While { body: stmts, .. } | StmtKind::Block { stmts } =>
+				collect_into(stmts, ret),
+
+			StmtKind::If { then_body, else_body, .. } => {
+				collect_into(std::slice::from_ref(then_body), ret);
+
+				if let Some(else_body) = else_body {
+					collect_into(std::slice::from_ref(else_body), ret);
+				}
+			}
+
+			StmtKind::Exp { .. } | StmtKind::Break | StmtKind::Continue => {}
+		}
+	}
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::Span;
+
+// ------------------------------------------------------------------------------------------------
+// Symbols
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *symbol* is one thing that a name can refer to. The important thing is that a name and a symbol
+are not the same: in this Rust code,
+
+	let x = 1;
+	{ let x = 2; }
+
+there's one name (`x`) but two symbols, because they're two different variables. The resolver's
+job is to figure out which symbol each use of a name means.
+
+Each symbol gets a SymbolId, which is just its index in the table. Later passes use the id
+instead of the name, so they never have to think about scopes again.
+*/
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct SymbolId(pub usize);
+
+impl Display for SymbolId {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "#{}", self.0)
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolKind {
+	Variable,                  // made by the program, with `let` or `=`.
+	Constant,                  // a builtin constant, like `pi`.
+	Function { arity: usize }, // a builtin function, like `sqrt`.
+}
+
+impl Display for SymbolKind {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			SymbolKind::Variable           => write!(f, "variable"),
+			SymbolKind::Constant           => write!(f, "constant"),
+			SymbolKind::Function { arity } => write!(f, "function/{}", arity),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+	pub name:  String,
+	pub kind:  SymbolKind,
+	pub span:  Option<Span>, // where it was defined, or None for builtins.
+	pub depth: usize,        // how many scopes deep it was defined. 0 is the global scope.
+
+	// the symbol in an outer scope that had the same name when this one was defined, if there was
+	// one. inside this one's scope, that one can't be used anymore.
+	pub shadows: Option<SymbolId>,
+}
+
+// ------------------------------------------------------------------------------------------------
+// SymbolTable
+// ------------------------------------------------------------------------------------------------
+
+/*
+The scopes are a stack. Looking up a name starts at the top (innermost) scope and works its way
+down to the global scope at the bottom, so an inner `x` *shadows* an outer one.
+
+When a scope is popped, its names go away, but the symbols themselves stay in `symbols` forever,
+so that later passes can still ask about them by id.
+*/
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+	symbols: Vec<Symbol>,
+	scopes:  Vec<HashMap<String, SymbolId>>,
+}
+
+impl Default for SymbolTable {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl SymbolTable {
+	// A table with just the (empty) global scope.
+	pub fn new() -> Self {
+		SymbolTable { symbols: vec![], scopes: vec![HashMap::new()] }
+	}
+
+	pub fn push_scope(&mut self) {
+		self.scopes.push(HashMap::new());
+	}
+
+	pub fn pop_scope(&mut self) {
+		assert!(self.scopes.len() > 1, "can't pop the global scope");
+		self.scopes.pop();
+	}
+
+	// 0 is the global scope.
+	pub fn depth(&self) -> usize {
+		self.scopes.len() - 1
+	}
+
+	// Adds a symbol to the innermost scope. If that scope already has something with this name,
+	// it's not added, and you get the *existing* symbol's id as the error. If an outer scope has
+	// something with this name, that's fine, but the new symbol remembers that it shadows it.
+	pub fn define(&mut self, name: &str, kind: SymbolKind, span: Option<Span>)
+	-> Result<SymbolId, SymbolId> {
+		if let Some(&existing) = self.scopes.last().unwrap().get(name) {
+			return Err(existing);
+		}
+
+		let id = SymbolId(self.symbols.len());
+		let shadows = self.lookup(name);
+		self.symbols.push(Symbol { name: name.into(), kind, span, depth: self.depth(), shadows });
+		self.scopes.last_mut().unwrap().insert(name.into(), id);
+		Ok(id)
+	}
+
+	// Finds the symbol this name refers to, starting from the innermost scope.
+	pub fn lookup(&self, name: &str) -> Option<SymbolId> {
+		self.scopes.iter().rev().find_map(|scope| scope.get(name).copied())
+	}
+
+	// Same, but only looks in the innermost scope.
+	pub fn lookup_local(&self, name: &str) -> Option<SymbolId> {
+		self.scopes.last().unwrap().get(name).copied()
+	}
+
+	pub fn get(&self, id: SymbolId) -> &Symbol {
+		&self.symbols[id.0]
+	}
+
+	pub fn len(&self) -> usize {
+		self.symbols.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.symbols.is_empty()
+	}
+
+	// Every symbol ever defined, including ones whose scopes are gone.
+	pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+		self.symbols.iter().enumerate().map(|(i, sym)| (SymbolId(i), sym))
+	}
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::Span;
+use toylang::{ BinOp, Pattern, Value };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The typed AST
+// ------------------------------------------------------------------------------------------------
+
+/*
+The parser's AST is what the programmer *wrote*, and it can have anything in it: undefined
+names, `1 + true`, function values the type checker can't handle yet. The type checker reads that
+tree, and if the program is correct, it builds a second one: a *typed AST*, which is what the
+program *means*. The differences:
+
+- every expression has its type right on it (`ty`), instead of in a side table that has to be
+  looked up by span.
+- names are gone. a variable is its SymbolId, so `x` in one place and `x` in another can't get
+  mixed up, and later phases never have to think about scopes. (the symbol table is in the
+  TProgram, for when the name is needed, like for printing.)
+- calls are to a builtin's SymbolId, not to an expression, and `len(a)` is its own kind of node,
+  since it's not really a function call (see lower.rs in the ir crate).
+- `let x = ...` and `x = ...` are both Assign, since resolving them already decided which x
+  they're about. that was the only difference between them.
+- there are no Error nodes or functions, since the checker doesn't allow them.
+
+Real compilers do this too. rustc has its AST, then a "HIR" (high-level IR) with the names
+resolved, then a "THIR" (typed HIR) that's built after type checking. The point of having two trees
+is that the later phases (like lowering, in the ir crate) can only be given the typed one, and the
+only way to get one is to pass the type checker. So it's impossible for them to see a program that
+wasn't checked, and they don't have to handle cases that the checker already ruled out.
+*/
+
+#[derive(Debug, Clone)]
+pub struct TProgram {
+	pub stmts:   Vec<TStmt>,
+	pub symbols: SymbolTable,
+	pub vars:    HashMap<SymbolId, Type>, // the type of each variable.
+}
+
+#[derive(Debug, Clone)]
+pub struct TStmt {
+	pub span: Span,
+	pub kind: TStmtKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum TStmtKind {
+	Assign { var: SymbolId, value: TExp },
+	Exp    (TExp),
+	While  { cond: TExp, body: Vec<TStmt> },
+	If     { cond: TExp, then_body: Box<TStmt>, else_body: Option<Box<TStmt>> },
+	Block  (Vec<TStmt>),
+	Break,
+	Continue,
+}
+
+#[derive(Debug, Clone)]
+pub struct TExp {
+	pub span: Span,
+	pub ty:   Type,
+	pub kind: TExpKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum TExpKind {
+	Int    (i64),
+	Float  (f64),
+	Bool   (bool),
+	Str    (String),
+	Var    (SymbolId), // a variable or a builtin constant.
+	Negate (Box<TExp>),
+	Not    (Box<TExp>),
+	Binary { op: BinOp, lhs: Box<TExp>, rhs: Box<TExp> },
+	Call   { func: SymbolId, args: Vec<TExp> }, // always a builtin function.
+	Len    (Box<TExp>),
+	Array  (Vec<TExp>),
+	Index  { array: Box<TExp>, index: Box<TExp> },
+	Match  { scrutinee: Box<TExp>, arms: Vec<TArm> },
+}
+
+impl TExp {
+	// the same as AstNode::left_chain(): the operand at the bottom left of a chain of operators,
+	// and then each operator on the way back up, so the chain can be gone through with a loop.
+	pub fn left_chain(&self) -> (&TExp, Vec<(&TExp, BinOp, &TExp)>) {
+		let mut exp = self;
+		let mut ops = vec![];
+
+		while let TExpKind::Binary { op, lhs, rhs } = &exp.kind {
+			ops.push((exp, *op, &**rhs));
+			exp = lhs;
+		}
+
+		ops.reverse();
+		(exp, ops)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct TArm {
+	pub pattern: Pattern,
+	pub value:   TExp,
+}
+
+// ------------------------------------------------------------------------------------------------
+// Printing
+// ------------------------------------------------------------------------------------------------
+
+// This looks like the AST's Display, but every expression has its type after it, and every name
+// has its SymbolId, like `(x#10:int + 1.5:float):float`. That's a lot to read, but it's exactly
+// what's in the tree.
+impl Display for TProgram {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for stmt in &self.stmts {
+			fmt_stmt(stmt, &self.symbols, f, 0)?;
+			writeln!(f)?;
+		}
+
+		Ok(())
+	}
+}
+
+fn fmt_stmt(stmt: &TStmt, symbols: &SymbolTable, f: &mut Formatter, indent: usize) -> FmtResult {
+	use TStmtKind::*;
+
+	match &stmt.kind {
+		Assign { var, value } => {
+			write!(f, "{}{} = ", symbols.get(*var).name, var)?;
+			fmt_exp(value, symbols, f)
+		}
+
+		Exp   (exp)   => fmt_exp(exp, symbols, f),
+		Block (stmts) => fmt_block(stmts, symbols, f, indent),
+		Break         => write!(f, "break"),
+		Continue      => write!(f, "continue"),
+
+		While { cond, body } => {
+			write!(f, "while ")?;
+			fmt_exp(cond, symbols, f)?;
+			write!(f, " ")?;
+			fmt_block(body, symbols, f, indent)
+		}
+
+		If { cond, then_body, else_body } => {
+			write!(f, "if ")?;
+			fmt_exp(cond, symbols, f)?;
+			write!(f, " ")?;
+			fmt_stmt(then_body, symbols, f, indent)?;
+
+			if let Some(else_body) = else_body {
+				write!(f, " else ")?;
+				fmt_stmt(else_body, symbols, f, indent)?;
+			}
+
+			Ok(())
+		}
+	}
+}
+
+fn fmt_block(stmts: &[TStmt], symbols: &SymbolTable, f: &mut Formatter, indent: usize)
+-> FmtResult {
+	if stmts.is_empty() {
+		return write!(f, "{{}}");
+	}
+
+	writeln!(f, "{{")?;
+
+	for stmt in stmts {
+		write!(f, "{}", "\t".repeat(indent + 1))?;
+		fmt_stmt(stmt, symbols, f, indent + 1)?;
+		writeln!(f)?;
+	}
+
+	write!(f, "{}}}", "\t".repeat(indent))
+}
+
+fn fmt_exp(exp: &TExp, symbols: &SymbolTable, f: &mut Formatter) -> FmtResult {
+	use TExpKind::*;
+
+	match &exp.kind {
+		Int   (val) => write!(f, "{}", val)?,
+		Float (val) => write!(f, "{:?}", val)?,
+		Bool  (val) => write!(f, "{}", val)?,
+		Str   (val) => write!(f, "{}", Value::from(val.as_str()).to_code())?,
+		Var   (id)  => write!(f, "{}{}", symbols.get(*id).name, id)?,
+
+		Negate(lhs) => {
+			write!(f, "-(")?;
+			fmt_exp(lhs, symbols, f)?;
+			write!(f, ")")?;
+		}
+
+		Not(lhs) => {
+			write!(f, "!(")?;
+			fmt_exp(lhs, symbols, f)?;
+			write!(f, ")")?;
+		}
+
+		Binary { .. } => {
+			// like `((a + b):int - c):int`, but with a loop. each `)` gets its operator's type.
+			let (first, ops) = exp.left_chain();
+			write!(f, "{}", "(".repeat(ops.len()))?;
+			fmt_exp(first, symbols, f)?;
+
+			for (node, op, rhs) in ops {
+				write!(f, " {} ", op)?;
+				fmt_exp(rhs, symbols, f)?;
+				write!(f, "):{}", node.ty)?;
+			}
+
+			return Ok(());
+		}
+
+		Call { func, args } => {
+			write!(f, "{}{}(", symbols.get(*func).name, func)?;
+			fmt_list(args, symbols, f)?;
+			write!(f, ")")?;
+		}
+
+		Len(array) => {
+			write!(f, "len(")?;
+			fmt_exp(array, symbols, f)?;
+			write!(f, ")")?;
+		}
+
+		Array(elems) => {
+			write!(f, "[")?;
+			fmt_list(elems, symbols, f)?;
+			write!(f, "]")?;
+		}
+
+		Index { array, index } => {
+			write!(f, "(")?;
+			fmt_exp(array, symbols, f)?;
+			write!(f, "[")?;
+			fmt_exp(index, symbols, f)?;
+			write!(f, "])")?;
+		}
+
+		Match { scrutinee, arms } => {
+			write!(f, "(match ")?;
+			fmt_exp(scrutinee, symbols, f)?;
+			write!(f, " {{")?;
+
+			for (i, arm) in arms.iter().enumerate() {
+				write!(f, "{} {} => ", if i > 0 { "," } else { "" }, arm.pattern)?;
+				fmt_exp(&arm.value, symbols, f)?;
+			}
+
+			write!(f, " }})")?;
+		}
+	}
+
+	write!(f, ":{}", exp.ty)
+}
+
+fn fmt_list(exps: &[TExp], symbols: &SymbolTable, f: &mut Formatter) -> FmtResult {
+	for (i, exp) in exps.iter().enumerate() {
+		if i > 0 {
+			write!(f, ", ")?;
+		}
+
+		fmt_exp(exp, symbols, f)?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,580 @@
+use std::collections::{ HashMap, HashSet };
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Span };
+use toylang::{ AstKind, AstNode, BinOp, MatchArm, Pattern, Program, Stmt, StmtKind };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Type
+// ------------------------------------------------------------------------------------------------
+
+// An array's type says what type its values are, so the type has a type inside it, and that can be
+// an array too: `[[1, 2], [3]]` is a `[[int]]`. That's why this can't be Copy; the inner type has
+// to be in a Box, since a Type can't contain a whole Type directly.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum Type {
+	Int,
+	Float,
+	Bool,
+	Str,
+	Array(Box<Type>),
+}
+
+impl Type {
+	pub fn is_numeric(&self) -> bool {
+		matches!(self, Type::Int | Type::Float)
+	}
+
+	// can a value of type `self` be stored in a variable of type `target`? an int can go
+	// anywhere a float can (it gets converted), but not the other way around, since that
+	// would lose the fraction. the same goes for arrays of them, since arrays can't be changed:
+	// there's no way to put a float into a `[float]` that's really a `[int]`.
+	pub fn fits_in(&self, target: &Type) -> bool {
+		match (self, target) {
+			(Type::Array(a), Type::Array(b)) => a.fits_in(b),
+			(Type::Int, Type::Float)         => true,
+			_                                => self == target,
+		}
+	}
+}
+
+impl Display for Type {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Type::Int      => write!(f, "int"),
+			Type::Float    => write!(f, "float"),
+			Type::Bool     => write!(f, "bool"),
+			Type::Str      => write!(f, "string"),
+			Type::Array(t) => write!(f, "[{}]", t),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The type checker
+// ------------------------------------------------------------------------------------------------
+
+/*
+Type checking comes after name resolution, because to know the type of `x`, you have to know
+*which* x it is. The rules:
+
+- `1` is an int, `1.5` is a float, `true` is a bool, and `"hi"` is a string.
+- arithmetic (+ - * / %) needs two numbers. int with int gives an int; if either one is a float,
+  you get a float. `1 + true` is an error. the exception is `/`, which always gives a float, since
+  `7 / 2` is 3.5 when the program runs. (if it were an int, `a[7 / 2]` would pass the check that
+  the index is an int, and then fail when it ran.)
+- `+` also joins two strings, and gives a string. but a string and a number can't be mixed, so
+  `"x" + 1` is an error.
+- comparisons (< <= > >=) need two numbers or two strings, and give a bool. == and != work on
+  two numbers, two bools, or two strings.
+- && || and ! need bools and give a bool, so `(a < b) + 1` and `!5` are errors.
+- a variable's type is the type of the first value given to it. after that, it can only be given
+  values of that type (or an int, if it's a float).
+- the builtin constants are floats, and the builtin functions take and give floats.
+- a `while` loop's or an `if`'s condition has to be a bool, so `while 1 { }` is an error.
+- a `match` has to be on an int, since the patterns are ints. its arms all have to give the same
+  type (but ints and floats can be mixed, and give a float, like in arithmetic).
+- the values in an array all have to be the same type, the same way. `[1, 2.5]` is a `[float]`.
+  an empty array is an error, since there's no way to tell what type it is.
+- only arrays can be indexed, and the index has to be an int. `a[i]` is the type of a's values.
+- `len()` takes an array, and gives an int. no operators work on arrays, not even `==`.
+- functions made with `fn` don't have a type yet, so they're always an error. (giving them one
+  means figuring out each parameter's type from how the function is used, which is a whole
+  algorithm of its own.)
+
+The interpreter only checks the values it's actually given, one operation at a time, so it's
+possible to write a program that runs fine but doesn't type check, like `x = 1; x = true`. (And
+the other way around: `if false { 1 + "a" }` never runs the `+`, so the interpreter doesn't mind.)
+
+If the program is correct, the result is a typed AST (see typed.rs): a new tree with the type of
+every expression on it. That's what the later phases (like lowering, in the ir crate) work on.
+*/
+
+pub fn typecheck(program: &Program, resolved: &Resolved) -> Result<TProgram, Vec<Diagnostic>> {
+	let mut c = Checker { resolved, vars: HashMap::new(), errors: vec![] };
+	let stmts = c.check_stmts(&program.stmts);
+
+	// if any part of the tree couldn't be built, there's an error that says why.
+	match stmts {
+		Some(stmts) if c.errors.is_empty() =>
+			Ok(TProgram { stmts, symbols: resolved.symbols.clone(), vars: c.vars }),
+		_ => Err(c.errors),
+	}
+}
+
+struct Checker<'r> {
+	resolved: &'r Resolved,
+	vars:     HashMap<SymbolId, Type>, // the type of each variable.
+	errors:   Vec<Diagnostic>,
+}
+
+/*
+Each check_ method gives back the typed version of what it checked, or None if there was an
+error in it. That way, one mistake doesn't cause a pile of errors about every expression around
+it. But a None doesn't stop the checking: the parts of a statement or expression after the broken
+one are still checked, so that all of their errors are found too.
+*/
+impl<'r> Checker<'r> {
+	// checks every statement, even if one of them has an error.
+	fn check_stmts(&mut self, stmts: &[Stmt]) -> Option<Vec<TStmt>> {
+		let checked = stmts.iter().map(|stmt| self.check_stmt(stmt)).collect::<Vec<_>>();
+		checked.into_iter().collect()
+	}
+
+	fn check_stmt(&mut self, stmt: &Stmt) -> Option<TStmt> {
+		let kind = match &stmt.kind {
+			StmtKind::Let { name_span, value, .. } | StmtKind::Assign { name_span, value, .. } =>
+				self.check_assign(*name_span, value)?,
+
+			StmtKind::Exp { exp } => TStmtKind::Exp(self.check_exp(exp)?),
+
+			StmtKind::While { cond, body } => {
+				let cond = self.check_cond(cond, "while");
+				let body = self.check_stmts(body);
+				TStmtKind::While { cond: cond?, body: body? }
+			}
+
+			StmtKind::If { cond, then_body, else_body } => {
+				let cond = self.check_cond(cond, "if");
+				let then_body = self.check_stmt(then_body);
+				let else_body = else_body.as_ref().map(|else_body| self.check_stmt(else_body));
+
+				TStmtKind::If {
+					cond:      cond?,
+					then_body: Box::new(then_body?),
+					else_body: match else_body {
+						Some(else_body) => Some(Box::new(else_body?)),
+						None            => None,
+					},
+				}
+			}
+
+			StmtKind::Block { stmts } => TStmtKind::Block(self.check_stmts(stmts)?),
+			StmtKind::Break           => TStmtKind::Break,
+			StmtKind::Continue        => TStmtKind::Continue,
+		};
+
+		Some(TStmt { span: stmt.span, kind })
+	}
+
+	// a while loop's or an if's condition.
+	fn check_cond(&mut self, cond: &AstNode, op: &str) -> Option<TExp> {
+		let cond = self.check_exp(cond)?;
+		self.expect(&cond, Type::Bool, op)?;
+		Some(cond)
+	}
+
+	fn check_assign(&mut self, name_span: Span, value: &AstNode) -> Option<TStmtKind> {
+		let id = self.resolved.names[&name_span];
+		let name = &self.resolved.symbols.get(id).name;
+		let value = self.check_exp(value)?;
+
+		// the builtin constants (like pi) are floats, so they can only be given floats.
+		let var_ty = match self.resolved.symbols.get(id).kind {
+			SymbolKind::Variable => self.vars.get(&id).cloned(),
+			_                    => Some(Type::Float),
+		};
+
+		match var_ty {
+			// the first assignment decides the variable's type.
+			None => { self.vars.insert(id, value.ty.clone()); }
+
+			Some(var_ty) if !value.ty.fits_in(&var_ty) => {
+				let err = mismatch(value.span, format!("expected {}, found {}", var_ty, value.ty))
+					.with_note(format!("'{}' is {} {} because of its first assignment",
+						name, article(&var_ty), var_ty));
+				self.errors.push(err);
+				return None;
+			}
+
+			Some(_) => {}
+		}
+
+		Some(TStmtKind::Assign { var: id, value })
+	}
+
+	fn check_exp(&mut self, node: &AstNode) -> Option<TExp> {
+		use AstKind::*;
+
+		let (kind, ty) = match &node.kind {
+			IntLit   { val }  => (TExpKind::Int(*val), Type::Int),
+			FloatLit { val }  => (TExpKind::Float(*val), Type::Float),
+			BoolLit  { val }  => (TExpKind::Bool(*val), Type::Bool),
+			StrLit   { val }  => (TExpKind::Str(val.clone()), Type::Str),
+			Ident    { .. }   => self.check_ident(node)?,
+
+			Negate { lhs } => {
+				let lhs = self.check_exp(lhs)?;
+				let ty = self.expect_numeric(&lhs, "-")?;
+				(TExpKind::Negate(Box::new(lhs)), ty)
+			}
+
+			Not { lhs } => {
+				let lhs = self.check_exp(lhs)?;
+				let ty = self.expect(&lhs, Type::Bool, "!")?;
+				(TExpKind::Not(Box::new(lhs)), ty)
+			}
+
+			Binary { .. } => {
+				let exp = self.check_chain(node)?;
+				(exp.kind, exp.ty)
+			}
+
+			Call { callee, args } => self.check_call(node, callee, args)?,
+			Array { elems } => self.check_array(node, elems)?,
+			Index { array, index } => self.check_index(array, index)?,
+			Match { scrutinee, arms } => self.check_match(node, scrutinee, arms)?,
+
+			// the body isn't checked either, since the parameters have no types.
+			Func { .. } => {
+				self.errors.push(Diagnostic::error("the type checker can't check functions yet",
+					node.span)
+					.with_code(diagnostics::CANT_CHECK_FUNCTION)
+					.with_note("`toylang eval` can run this program, since the interpreter doesn't \
+						check types"));
+				return None;
+			}
+
+			// only parse_partial() makes these, and the parser reported an error for each one. but
+			// that error might not have been passed along, and there's no typed version of one.
+			Error => {
+				self.errors.push(Diagnostic::error("can't check code that has a syntax error",
+					node.span));
+				return None;
+			}
+		};
+
+		Some(TExp { span: node.span, ty, kind })
+	}
+
+	// a chain of operators, like `a + b * c - d`, checked with a loop so that a long one can't use
+	// up the stack (see AstNode::left_chain()). every operand gets checked, even after one of them
+	// has an error, but then the whole chain is None.
+	fn check_chain(&mut self, node: &AstNode) -> Option<TExp> {
+		let (first, ops) = node.left_chain();
+		let mut exp = self.check_exp(first);
+
+		for (node, op, rhs) in ops {
+			let rhs = self.check_exp(rhs);
+
+			exp = match (exp, rhs) {
+				(Some(lhs), Some(rhs)) => {
+					let ty = self.check_binary(op, &lhs, &rhs);
+					let kind = TExpKind::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) };
+					ty.map(|ty| TExp { span: node.span, ty, kind })
+				}
+				_ => None,
+			};
+		}
+
+		exp
+	}
+
+	fn check_ident(&mut self, node: &AstNode) -> Option<(TExpKind, Type)> {
+		let id = *self.resolved.names.get(&node.span)?;
+		let sym = self.resolved.symbols.get(id);
+
+		let ty = match sym.kind {
+			// this is only None if the variable's first assignment had an error.
+			SymbolKind::Variable => self.vars.get(&id).cloned()?,
+			SymbolKind::Constant => Type::Float,
+
+			SymbolKind::Function { .. } => {
+				self.errors.push(Diagnostic::error(
+					format!("'{}' is a function, so it has to be called", sym.name), node.span)
+					.with_code(diagnostics::FUNCTION_AS_VALUE));
+				return None;
+			}
+		};
+
+		Some((TExpKind::Var(id), ty))
+	}
+
+	fn check_binary(&mut self, op: BinOp, lhs: &TExp, rhs: &TExp) -> Option<Type> {
+		let op_str = op.to_string();
+		let (l, r) = (&lhs.ty, &rhs.ty);
+
+		if op == BinOp::Add && (*l == Type::Str || *r == Type::Str) {
+			if l == r {
+				Some(Type::Str)
+			} else {
+				self.errors.push(mismatch(lhs.span.to(rhs.span), format!(
+					"can't use '+' on {} {} and {} {}", article(l), l, article(r), r))
+					.with_note("'+' can add two numbers or join two strings, but not one of each"));
+				None
+			}
+		} else if op.is_arithmetic() {
+			let l = self.expect_numeric(lhs, &op_str);
+			let r = self.expect_numeric(rhs, &op_str);
+
+			match (l?, r?) {
+				(Type::Int, Type::Int) if op != BinOp::Div => Some(Type::Int),
+				_                                          => Some(Type::Float),
+			}
+		} else if op.is_logical() {
+			let l = self.expect(lhs, Type::Bool, &op_str);
+			let r = self.expect(rhs, Type::Bool, &op_str);
+			l?; r?;
+			Some(Type::Bool)
+		} else if op == BinOp::Eq || op == BinOp::Ne || *l == Type::Str || *r == Type::Str {
+			// the two sides just have to be the same kind of thing. (two strings can be compared
+			// with < and friends too, alphabetically.) arrays can't be compared at all.
+			if let (Type::Array(..), Type::Array(..)) = (l, r) {
+				self.errors.push(mismatch(lhs.span.to(rhs.span), format!("can't use '{}' on arrays",
+					op)).with_note("compare their values one at a time, in a loop"));
+				None
+			} else if l.is_numeric() && r.is_numeric() || l == r {
+				Some(Type::Bool)
+			} else {
+				self.errors.push(mismatch(rhs.span, format!("can't compare {} with {}", l, r))
+					.with_note(format!("the left side of the '{}' is {} {}", op, article(l), l)));
+				None
+			}
+		} else {
+			let l = self.expect_numeric(lhs, &op_str);
+			let r = self.expect_numeric(rhs, &op_str);
+			l?; r?;
+			Some(Type::Bool)
+		}
+	}
+
+	// the same errors the interpreter finds while running, but found before running.
+	fn check_call(&mut self, node: &AstNode, callee: &AstNode, args: &[AstNode])
+	-> Option<(TExpKind, Type)> {
+		let checked_args = args.iter().map(|arg| self.check_exp(arg)).collect::<Vec<_>>();
+
+		let function = match &callee.kind {
+			AstKind::Ident { .. } => self.resolved.names.get(&callee.span).copied(),
+			_                     => None,
+		};
+
+		let (id, name, arity) = match function.map(|id| (id, self.resolved.symbols.get(id))) {
+			Some((id, Symbol { name, kind: SymbolKind::Function { arity }, .. })) =>
+				(id, name, *arity),
+
+			_ => {
+				let what = match self.check_exp(callee) {
+					Some(callee) => format!("{} {}", article(&callee.ty), callee.ty),
+					None         => return None,
+				};
+
+				self.errors.push(Diagnostic::error(
+					format!("only functions can be called, not {}", what), callee.span)
+					.with_code(diagnostics::NOT_A_FUNCTION));
+				return None;
+			}
+		};
+
+		if args.len() != arity {
+			self.errors.push(Diagnostic::error(format!("'{}' takes {} argument{}, but was given {}",
+				name, arity, if arity == 1 { "" } else { "s" }, args.len()), node.span)
+				.with_code(diagnostics::WRONG_ARG_COUNT));
+			return None;
+		}
+
+		// len() is the one builtin that doesn't take numbers. (see toylang's BuiltinFn.)
+		if name == "len" {
+			let array = checked_args.into_iter().next()??;
+
+			return match array.ty {
+				Type::Array(..) => Some((TExpKind::Len(Box::new(array)), Type::Int)),
+				ty              => {
+					self.errors.push(mismatch(array.span,
+						format!("expected an array, found {}", ty))
+						.with_note("'len' gives how many values are in an array"));
+					None
+				}
+			};
+		}
+
+		// every builtin takes floats, and ints get converted.
+		let mut ok = true;
+
+		for arg in &checked_args {
+			ok &= arg.as_ref().and_then(|arg| self.expect_numeric(arg, name)).is_some();
+		}
+
+		let args = checked_args.into_iter().collect::<Option<Vec<_>>>()?;
+		if ok { Some((TExpKind::Call { func: id, args }, Type::Float)) } else { None }
+	}
+
+	fn check_match(&mut self, node: &AstNode, scrutinee: &AstNode, arms: &[MatchArm])
+	-> Option<(TExpKind, Type)> {
+		let checked = self.check_exp(scrutinee)
+			.and_then(|s| self.expect(&s, Type::Int, "match").map(|_| s));
+
+		self.check_arms(node, scrutinee, arms);
+
+		let (values, ty) = self.check_same_type(arms.iter().map(|arm| &arm.value),
+			|first| format!("the arms before this one give {} {}", article(first), first))?;
+
+		let arms = arms.iter().zip(values)
+			.map(|(arm, value)| TArm { pattern: arm.pattern, value })
+			.collect();
+
+		Some((TExpKind::Match { scrutinee: Box::new(checked?), arms }, ty))
+	}
+
+	fn check_array(&mut self, node: &AstNode, elems: &[AstNode]) -> Option<(TExpKind, Type)> {
+		if elems.is_empty() {
+			self.errors.push(Diagnostic::error("can't tell what type of array this is", node.span)
+				.with_code(diagnostics::CANT_INFER_TYPE)
+				.with_note("it's empty, so there are no values to tell from"));
+			return None;
+		}
+
+		let (elems, elem) = self.check_same_type(elems.iter(),
+			|first| format!("the values before this one make it an array of {}s", first))?;
+		Some((TExpKind::Array(elems), Type::Array(Box::new(elem))))
+	}
+
+	fn check_index(&mut self, array: &AstNode, index: &AstNode) -> Option<(TExpKind, Type)> {
+		let (array, index) = (self.check_exp(array), self.check_exp(index));
+		let index = index.and_then(|i| self.expect(&i, Type::Int, "[]").map(|_| i));
+		let array = array?;
+
+		let ty = match &array.ty {
+			Type::Array(elem) => (**elem).clone(),
+			ty                => {
+				self.errors.push(mismatch(array.span, format!("expected an array, found {}", ty))
+					.with_note("only arrays can be indexed"));
+				return None;
+			}
+		};
+
+		Some((TExpKind::Index { array: Box::new(array), index: Box::new(index?) }, ty))
+	}
+
+	// checks each expression, and gives the type that they can all be: ints and floats can be
+	// mixed, and give a float, like in arithmetic. `note` says what the ones before a mismatched
+	// one were, given the type so far.
+	fn check_same_type<'a>(&mut self, exps: impl Iterator<Item = &'a AstNode>,
+	note: impl Fn(&Type) -> String) -> Option<(Vec<TExp>, Type)> {
+		let mut ret = None::<Type>;
+		let mut checked = vec![];
+		let mut ok = true;
+
+		for exp in exps {
+			let exp = match self.check_exp(exp) {
+				Some(exp) => exp,
+				None      => { ok = false; continue; }
+			};
+
+			ret = match ret {
+				None => Some(exp.ty.clone()),
+				Some(first) if exp.ty.fits_in(&first) => Some(first),
+				Some(first) if first.fits_in(&exp.ty) => Some(exp.ty.clone()),
+
+				Some(first) => {
+					self.errors.push(mismatch(exp.span,
+						format!("expected {}, found {}", first, exp.ty))
+						.with_note(note(&first)));
+					ok = false;
+					Some(first)
+				}
+			};
+
+			checked.push(exp);
+		}
+
+		if ok { Some((checked, ret?)) } else { None }
+	}
+
+	/*
+	The arms are tried from top to bottom, so an arm is unreachable if the ones above it already
+	match everything it does: either the same number, or a `_`. And there has to be *some* arm
+	for every value, or the match has nothing to give. An int can be any of billions of values,
+	so listing them all isn't an option; only a `_` arm covers them all.
+
+	This is the simplest possible *exhaustiveness check*. In a language like Rust, where patterns
+	can be nested (like `Some((1, _))`), this takes a real algorithm.
+	*/
+	fn check_arms(&mut self, node: &AstNode, scrutinee: &AstNode, arms: &[MatchArm]) {
+		let mut seen = HashSet::new();
+		let mut wildcard = false;
+
+		for arm in arms {
+			let note = match arm.pattern {
+				_ if wildcard => "the '_' arm above it matches everything".to_string(),
+				Pattern::Int(val) if !seen.insert(val) =>
+					format!("an arm above it already matches {}", val),
+				Pattern::Int(_) => continue,
+				Pattern::Wildcard => { wildcard = true; continue; }
+			};
+
+			self.errors.push(Diagnostic::error("this arm will never be chosen", arm.pattern_span)
+				.with_code(diagnostics::UNREACHABLE_ARM)
+				.with_note(note));
+		}
+
+		if !wildcard {
+			let span = Span::new(node.span.start, scrutinee.span.end);
+			let msg = "this match doesn't have an arm for every value";
+			self.errors.push(Diagnostic::error(msg, span)
+				.with_code(diagnostics::NON_EXHAUSTIVE_MATCH)
+				.with_note("add a `_ => ...` arm at the end for the values the others miss"));
+		}
+	}
+
+	fn expect(&mut self, exp: &TExp, expected: Type, op: &str) -> Option<Type> {
+		if exp.ty == expected {
+			Some(expected)
+		} else {
+			self.errors.push(mismatch(exp.span, format!("expected {}, found {}", expected, exp.ty))
+				.with_note(format!("'{}' needs {} {}", op, article(&expected), expected)));
+			None
+		}
+	}
+
+	fn expect_numeric(&mut self, exp: &TExp, op: &str) -> Option<Type> {
+		if exp.ty.is_numeric() {
+			Some(exp.ty.clone())
+		} else {
+			self.errors.push(mismatch(exp.span, format!("expected a number, found {}", exp.ty))
+				.with_note(format!("'{}' only works on numbers (ints and floats)", op)));
+			None
+		}
+	}
+}
+
+fn mismatch(span: Span, message: String) -> Diagnostic {
+	Diagnostic::error(message, span).with_code(diagnostics::TYPE_MISMATCH)
+}
+
+// "an int", "a float", "a bool". an array is "an [int]", since that's read "an array of ints."
+fn article(ty: &Type) -> &'static str {
+	match ty {
+		Type::Int | Type::Array(..) => "an",
+		_                           => "a",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use toylang::{ compile, Env };
+
+	use super::*;
+
+	fn check(src: &str) -> Result<TProgram, Vec<Diagnostic>> {
+		let program = compile(src).unwrap();
+		let resolved = resolve(&program, &Env::new()).unwrap();
+		typecheck(&program, &resolved)
+	}
+
+	// `7 / 2` is 3.5 when it runs, so it can't be an int.
+	#[test]
+	fn int_division_is_float() {
+		let program = check("7 / 2").unwrap();
+
+		match &program.stmts[0].kind {
+			TStmtKind::Exp(exp) => assert_eq!(exp.ty, Type::Float),
+			_                   => unreachable!(),
+		}
+
+		assert!(check("let x = 7 / 2\nmatch x { 3 => 1, _ => 2 }").is_err());
+		assert!(check("let a = [1, 2]\na[3 / 2]").is_err());
+		assert!(check("let x = 7 % 2\nmatch x { 1 => 1, _ => 2 }").is_ok());
+	}
+}
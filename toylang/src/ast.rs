@@ -0,0 +1,435 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use std::rc::Rc;
+
+use diagnostics::Span;
+
+use crate::quote;
+
+// This is parsing_math's AST, with a few changes:
+//   - every node knows its Span, so errors found *after* parsing can point at the code.
+//   - calls can have any number of arguments, like `max(a, b)`.
+//   - there are arrays, like `[1, 2, 3]`, and indexing, like `a[0]`.
+//   - functions are values, like `fn(x) { x + 1 }`, so a function body (a list of statements) can
+//     be inside an expression.
+//   - a program is a list of statements, not just one expression, and some statements (like
+//     while loops) have statements inside them.
+
+// ------------------------------------------------------------------------------------------------
+// Program and Stmt
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+	pub stmts: Vec<Stmt>,
+}
+
+impl Display for Program {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for stmt in &self.stmts {
+			writeln!(f, "{}", stmt)?;
+		}
+
+		Ok(())
+	}
+}
+
+// Same trick as AstNode below: the span is on the struct, and the enum is the part that differs.
+#[derive(Debug, Clone)]
+pub struct Stmt {
+	pub span: Span,
+	pub kind: StmtKind,
+}
+
+/*
+`let x = 5` and `x = 5` both give x the value 5, whether x existed before or not. The difference
+is what you *meant*: `let` says "this is a new variable," and plain `=` says "change this
+variable." Outside of functions there's only one scope (every variable is global), so they do the
+same thing there. Inside a function, `let` makes a new variable that only the function can see,
+and `=` changes whichever existing variable that name refers to, even one outside the function.
+
+`while cond { ... }` runs the statements in the braces over and over, as long as cond is true.
+`break` leaves the loop early, and `continue` skips the rest of the body and goes back to checking
+the condition. A block (`{ ... }` on its own) just groups statements together. Neither one makes a
+new scope, so a variable set inside a loop is still there after it. Only function bodies make new
+scopes.
+
+`if cond { ... } else { ... }` runs the first block if cond is true, and the second one if it's
+false. The `else` part is optional. The two parts are Block statements (not just lists of
+statements, like a loop's body) because the `else` part can be another `if` instead, which is how
+`else if` works: `if a { ... } else if b { ... } else { ... }` is an `if` whose `else` part is an
+`if` whose `else` part is a block.
+*/
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+	Let      { name: String, name_span: Span, value: Box<AstNode> },
+	Assign   { name: String, name_span: Span, value: Box<AstNode> },
+	Exp      { exp: Box<AstNode> },
+	While    { cond: Box<AstNode>, body: Vec<Stmt> },
+	If       { cond: Box<AstNode>, then_body: Box<Stmt>, else_body: Option<Box<Stmt>> },
+	Block    { stmts: Vec<Stmt> },
+	Break,
+	Continue,
+}
+
+impl Display for Stmt {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		self.fmt_indented(f, 0)
+	}
+}
+
+impl Stmt {
+	pub fn new(span: Span, kind: StmtKind) -> Stmt {
+		Stmt { span, kind }
+	}
+
+	// the statements inside a block are indented one tab more than the block.
+	fn fmt_indented(&self, f: &mut Formatter, indent: usize) -> FmtResult {
+		use StmtKind::*;
+
+		match &self.kind {
+			Let      { name, value, .. } => write!(f, "let {} = {}", name, value),
+			Assign   { name, value, .. } => write!(f, "{} = {}", name, value),
+			Exp      { exp }             => write!(f, "{}", exp),
+			Break                        => write!(f, "break"),
+			Continue                     => write!(f, "continue"),
+			Block    { stmts }           => fmt_block(stmts, f, indent),
+			While    { cond, body }      => {
+				write!(f, "while {} ", cond)?;
+				fmt_block(body, f, indent)
+			}
+			If       { cond, then_body, else_body } => {
+				write!(f, "if {} ", cond)?;
+				then_body.fmt_indented(f, indent)?;
+
+				if let Some(else_body) = else_body {
+					write!(f, " else ")?;
+					else_body.fmt_indented(f, indent)?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
+
+fn fmt_block(stmts: &[Stmt], f: &mut Formatter, indent: usize) -> FmtResult {
+	if stmts.is_empty() {
+		return write!(f, "{{}}");
+	}
+
+	writeln!(f, "{{")?;
+
+	for stmt in stmts {
+		write!(f, "{}", "\t".repeat(indent + 1))?;
+		stmt.fmt_indented(f, indent + 1)?;
+		writeln!(f)?;
+	}
+
+	write!(f, "{}}}", "\t".repeat(indent))
+}
+
+// ------------------------------------------------------------------------------------------------
+// AstNode
+// ------------------------------------------------------------------------------------------------
+
+// Putting the span on every node would mean adding a `span` field to every variant of the enum.
+// Instead, the node is a struct with the span, and the enum (AstKind) is the part that differs.
+#[derive(Debug, Clone)]
+pub struct AstNode {
+	pub span: Span,
+	pub kind: AstKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum AstKind {
+	IntLit   { val: i64 },
+	FloatLit { val: f64 },
+	BoolLit  { val: bool },
+	StrLit   { val: String },
+	Ident    { name: String },
+	Negate   { lhs: Box<AstNode> },
+	Not      { lhs: Box<AstNode> },
+	Binary   { op: BinOp, lhs: Box<AstNode>, rhs: Box<AstNode> },
+	Call     { callee: Box<AstNode>, args: Vec<AstNode> },
+
+	// `[1, 2, 3]` makes a new array, and `a[i]` gets the value at index i of array a. arrays
+	// can't be changed once they're made, so there's no `a[i] = x`.
+	Array    { elems: Vec<AstNode> },
+	Index    { array: Box<AstNode>, index: Box<AstNode> },
+
+	// the arms are tried from top to bottom, and the first one whose pattern matches the
+	// scrutinee (the value being matched on) gives the value of the whole thing.
+	Match    { scrutinee: Box<AstNode>, arms: Vec<MatchArm> },
+
+	// `fn(a, b) { a + b }` makes a new function. like a block, its value is the value of its last
+	// statement. the body is in an Rc so that every closure made from this code can share it
+	// instead of copying it (see eval.rs).
+	Func     { params: Vec<Param>, body: Rc<[Stmt]> },
+
+	// where parse_partial() had to give up. parse() never gives a tree with these in it.
+	Error,
+}
+
+impl Display for AstNode {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use AstKind::*;
+
+		match &self.kind {
+			IntLit   { val }          => write!(f, "{}", val),
+			FloatLit { val }          => write!(f, "{:?}", val),
+			BoolLit  { val }          => write!(f, "{}", val),
+			StrLit   { val }          => write!(f, "{}", quote(val)),
+			Ident    { name }         => write!(f, "{}", name),
+			Negate   { lhs }          => write!(f, "-({})", lhs),
+			Not      { lhs }          => write!(f, "!({})", lhs),
+			Binary   { .. }           => {
+				// like `(((a + b) - c) + d)`. see left_chain() for why it's a loop.
+				let (first, ops) = self.left_chain();
+				write!(f, "{}{}", "(".repeat(ops.len()), first)?;
+
+				for (_, op, rhs) in ops {
+					write!(f, " {} {})", op, rhs)?;
+				}
+
+				Ok(())
+			}
+			Error                     => write!(f, "<error>"),
+			Call     { callee, args } => {
+				write!(f, "({}(", callee)?;
+
+				for (i, arg) in args.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+
+					write!(f, "{}", arg)?;
+				}
+
+				write!(f, "))")
+			}
+			Array    { elems }        => {
+				write!(f, "[")?;
+
+				for (i, elem) in elems.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+
+					write!(f, "{}", elem)?;
+				}
+
+				write!(f, "]")
+			}
+			Index    { array, index } => write!(f, "({}[{}])", array, index),
+			Match    { scrutinee, arms } => {
+				write!(f, "(match {} {{", scrutinee)?;
+
+				for (i, arm) in arms.iter().enumerate() {
+					write!(f, "{} {} => {}", if i > 0 { "," } else { "" }, arm.pattern, arm.value)?;
+				}
+
+				write!(f, " }})")
+			}
+			Func     { params, body } => {
+				write!(f, "(fn(")?;
+
+				for (i, param) in params.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+
+					write!(f, "{}", param.name)?;
+				}
+
+				write!(f, ") {{")?;
+
+				for (i, stmt) in body.iter().enumerate() {
+					write!(f, "{} {}", if i > 0 { ";" } else { "" }, stmt)?;
+				}
+
+				write!(f, " }})")
+			}
+		}
+	}
+}
+
+impl AstNode {
+	pub fn new(span: Span, kind: AstKind) -> Box<AstNode> {
+		Box::new(AstNode { span, kind })
+	}
+
+	pub fn int(span: Span, val: i64) -> Box<AstNode> {
+		Self::new(span, AstKind::IntLit { val })
+	}
+
+	pub fn float(span: Span, val: f64) -> Box<AstNode> {
+		Self::new(span, AstKind::FloatLit { val })
+	}
+
+	pub fn bool(span: Span, val: bool) -> Box<AstNode> {
+		Self::new(span, AstKind::BoolLit { val })
+	}
+
+	pub fn str(span: Span, val: &str) -> Box<AstNode> {
+		Self::new(span, AstKind::StrLit { val: val.into() })
+	}
+
+	pub fn id(span: Span, name: &str) -> Box<AstNode> {
+		Self::new(span, AstKind::Ident { name: name.into() })
+	}
+
+	pub fn error(span: Span) -> Box<AstNode> {
+		Self::new(span, AstKind::Error)
+	}
+
+	// for the rest, the span is calculated from the operands. `op_span` is the span of the
+	// operator token itself.
+	pub fn neg(op_span: Span, lhs: Box<AstNode>) -> Box<AstNode> {
+		Self::new(op_span.to(lhs.span), AstKind::Negate { lhs })
+	}
+
+	pub fn not(op_span: Span, lhs: Box<AstNode>) -> Box<AstNode> {
+		Self::new(op_span.to(lhs.span), AstKind::Not { lhs })
+	}
+
+	pub fn bin(lhs: Box<AstNode>, op: BinOp, rhs: Box<AstNode>) -> Box<AstNode> {
+		Self::new(lhs.span.to(rhs.span), AstKind::Binary { op, lhs, rhs })
+	}
+
+	// `end` is the span of the closing ')'.
+	pub fn call(callee: Box<AstNode>, args: Vec<AstNode>, end: Span) -> Box<AstNode> {
+		Self::new(callee.span.to(end), AstKind::Call { callee, args })
+	}
+
+	// `start` and `end` are the spans of the '[' and ']'.
+	pub fn array(start: Span, elems: Vec<AstNode>, end: Span) -> Box<AstNode> {
+		Self::new(start.to(end), AstKind::Array { elems })
+	}
+
+	// `end` is the span of the closing ']'.
+	pub fn index(array: Box<AstNode>, index: Box<AstNode>, end: Span) -> Box<AstNode> {
+		Self::new(array.span.to(end), AstKind::Index { array, index })
+	}
+
+	// `start` is the span of the 'match', and `end` is the span of the closing '}'.
+	pub fn match_exp(start: Span, scrutinee: Box<AstNode>, arms: Vec<MatchArm>, end: Span)
+	-> Box<AstNode> {
+		Self::new(start.to(end), AstKind::Match { scrutinee, arms })
+	}
+
+	// `start` is the span of the 'fn', and `end` is the span of the closing '}'.
+	pub fn func(start: Span, params: Vec<Param>, body: Vec<Stmt>, end: Span) -> Box<AstNode> {
+		Self::new(start.to(end), AstKind::Func { params, body: body.into() })
+	}
+
+	/*
+	The operators are left-associative, so a chain of them like `a + b - c + d` makes a tree that
+	leans to the left: the lhs of the last `+` is `a + b - c`, whose lhs is `a + b`, and so on.
+	A function that goes through the tree by calling itself on both sides would go as many calls
+	deep as the chain is long, and a program that writes programs can easily make a chain of
+	thousands. That could use up the whole stack.
+
+	So this gives the chain as a list instead, which can be gone through with a loop: the operand
+	at the bottom left (`a`), and then each operator on the way back up, with its node and its
+	rhs (`a + b`, then `a + b - c`, and then the whole thing). For anything but a Binary node,
+	the list is empty.
+	*/
+	pub fn left_chain(&self) -> (&AstNode, Vec<(&AstNode, BinOp, &AstNode)>) {
+		let mut node = self;
+		let mut ops = vec![];
+
+		while let AstKind::Binary { op, lhs, rhs } = &node.kind {
+			ops.push((node, *op, &**rhs));
+			node = lhs;
+		}
+
+		ops.reverse();
+		(node, ops)
+	}
+}
+
+// A function's parameter. the span is for errors like "x is already a parameter".
+#[derive(Debug, Clone)]
+pub struct Param {
+	pub name: String,
+	pub span: Span,
+}
+
+// ------------------------------------------------------------------------------------------------
+// MatchArm
+// ------------------------------------------------------------------------------------------------
+
+/*
+A pattern is what goes on the left side of the `=>`. For now there are only two kinds: an integer,
+which matches that one number, and `_` (the *wildcard*), which matches anything. Patterns can't be
+variables or expressions, so every pattern is known at compile time. That's what lets the semantic
+crate check that no arm is unreachable, and lets the ir crate turn a match into a jump table.
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Pattern {
+	Int(i64),
+	Wildcard,
+}
+
+impl Display for Pattern {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Pattern::Int(val) => write!(f, "{}", val),
+			Pattern::Wildcard => write!(f, "_"),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+	pub pattern:      Pattern,
+	pub pattern_span: Span,
+	pub value:        AstNode,
+}
+
+// ------------------------------------------------------------------------------------------------
+// BinOp
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BinOp {
+	Add, Sub, Mul, Div, Mod,
+	Lt, Le, Gt, Ge, Eq, Ne,
+	And, Or,
+}
+
+impl BinOp {
+	pub fn is_arithmetic(self) -> bool {
+		matches!(self, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod)
+	}
+
+	pub fn is_comparison(self) -> bool {
+		matches!(self, BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::Eq | BinOp::Ne)
+	}
+
+	pub fn is_logical(self) -> bool {
+		matches!(self, BinOp::And | BinOp::Or)
+	}
+}
+
+impl Display for BinOp {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use BinOp::*;
+		match self {
+			Add => write!(f, "+"),
+			Sub => write!(f, "-"),
+			Mul => write!(f, "*"),
+			Div => write!(f, "/"),
+			Mod => write!(f, "%"),
+			Lt  => write!(f, "<"),
+			Le  => write!(f, "<="),
+			Gt  => write!(f, ">"),
+			Ge  => write!(f, ">="),
+			Eq  => write!(f, "=="),
+			Ne  => write!(f, "!="),
+			And => write!(f, "&&"),
+			Or  => write!(f, "||"),
+		}
+	}
+}
@@ -0,0 +1,588 @@
+use diagnostics::Diagnostic;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Typed CST nodes
+// ------------------------------------------------------------------------------------------------
+
+/*
+A SyntaxNode could be anything, so using one means a lot of "find the first child that's an Id
+token" code. These types wrap SyntaxNodes of one kind each, with methods named after the parts of
+that grammar rule, so you can say `let_stmt.name()` and `binary.rhs()`.
+
+They're only *views*: they don't hold any data of their own, so making one is cheap, and
+`.syntax()` gets you back to the SyntaxNode (and from there to the trivia, spans, and so on).
+
+Since the tree might be for broken code, most of the methods give an Option. In `let = 5`, the
+LetStmt has no name, so name() gives None.
+*/
+
+pub trait CstNode: Sized {
+	// Gives None if the node isn't the right kind.
+	fn cast(node: SyntaxNode) -> Option<Self>;
+	fn syntax(&self) -> &SyntaxNode;
+}
+
+// most of the typed nodes are just a SyntaxNode of one kind, so this writes those for us.
+macro_rules! typed_node {
+	($name:ident, $kind:ident) => {
+		#[derive(Debug, Clone, PartialEq)]
+		pub struct $name(SyntaxNode);
+
+		impl CstNode for $name {
+			fn cast(node: SyntaxNode) -> Option<Self> {
+				if node.kind() == SyntaxKind::$kind { Some($name(node)) } else { None }
+			}
+
+			fn syntax(&self) -> &SyntaxNode {
+				&self.0
+			}
+		}
+	};
+}
+
+typed_node!(ProgramNode,  Program);
+typed_node!(LetNode,      LetStmt);
+typed_node!(AssignNode,   AssignStmt);
+typed_node!(ExpStmtNode,  ExpStmt);
+typed_node!(WhileNode,    WhileStmt);
+typed_node!(IfNode,       IfStmt);
+typed_node!(BlockNode,    Block);
+typed_node!(BreakNode,    BreakStmt);
+typed_node!(ContinueNode, ContinueStmt);
+typed_node!(LiteralNode,  Literal);
+typed_node!(NameNode,     Name);
+typed_node!(ParenNode,    ParenExp);
+typed_node!(PrefixNode,   PrefixExp);
+typed_node!(BinaryNode,   BinaryExp);
+typed_node!(CallNode,     CallExp);
+typed_node!(ArrayNode,    ArrayExp);
+typed_node!(IndexNode,    IndexExp);
+typed_node!(MatchNode,    MatchExp);
+typed_node!(ArmNode,      MatchArm);
+typed_node!(PatternNode,  Pattern);
+typed_node!(FuncNode,     FuncExp);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtNode {
+	Let(LetNode),
+	Assign(AssignNode),
+	Exp(ExpStmtNode),
+	While(WhileNode),
+	If(IfNode),
+	Block(BlockNode),
+	Break(BreakNode),
+	Continue(ContinueNode),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpNode {
+	Literal(LiteralNode),
+	Name(NameNode),
+	Paren(ParenNode),
+	Prefix(PrefixNode),
+	Binary(BinaryNode),
+	Call(CallNode),
+	Array(ArrayNode),
+	Index(IndexNode),
+	Match(MatchNode),
+	Func(FuncNode),
+}
+
+impl CstNode for StmtNode {
+	fn cast(node: SyntaxNode) -> Option<Self> {
+		match node.kind() {
+			SyntaxKind::LetStmt      => Some(StmtNode::Let(LetNode(node))),
+			SyntaxKind::AssignStmt   => Some(StmtNode::Assign(AssignNode(node))),
+			SyntaxKind::ExpStmt      => Some(StmtNode::Exp(ExpStmtNode(node))),
+			SyntaxKind::WhileStmt    => Some(StmtNode::While(WhileNode(node))),
+			SyntaxKind::IfStmt       => Some(StmtNode::If(IfNode(node))),
+			SyntaxKind::Block        => Some(StmtNode::Block(BlockNode(node))),
+			SyntaxKind::BreakStmt    => Some(StmtNode::Break(BreakNode(node))),
+			SyntaxKind::ContinueStmt => Some(StmtNode::Continue(ContinueNode(node))),
+			_                        => None,
+		}
+	}
+
+	fn syntax(&self) -> &SyntaxNode {
+		match self {
+			StmtNode::Let(n)      => n.syntax(),
+			StmtNode::Assign(n)   => n.syntax(),
+			StmtNode::Exp(n)      => n.syntax(),
+			StmtNode::While(n)    => n.syntax(),
+			StmtNode::If(n)       => n.syntax(),
+			StmtNode::Block(n)    => n.syntax(),
+			StmtNode::Break(n)    => n.syntax(),
+			StmtNode::Continue(n) => n.syntax(),
+		}
+	}
+}
+
+impl CstNode for ExpNode {
+	fn cast(node: SyntaxNode) -> Option<Self> {
+		match node.kind() {
+			SyntaxKind::Literal   => Some(ExpNode::Literal(LiteralNode(node))),
+			SyntaxKind::Name      => Some(ExpNode::Name(NameNode(node))),
+			SyntaxKind::ParenExp  => Some(ExpNode::Paren(ParenNode(node))),
+			SyntaxKind::PrefixExp => Some(ExpNode::Prefix(PrefixNode(node))),
+			SyntaxKind::BinaryExp => Some(ExpNode::Binary(BinaryNode(node))),
+			SyntaxKind::CallExp   => Some(ExpNode::Call(CallNode(node))),
+			SyntaxKind::ArrayExp  => Some(ExpNode::Array(ArrayNode(node))),
+			SyntaxKind::IndexExp  => Some(ExpNode::Index(IndexNode(node))),
+			SyntaxKind::MatchExp  => Some(ExpNode::Match(MatchNode(node))),
+			SyntaxKind::FuncExp   => Some(ExpNode::Func(FuncNode(node))),
+			_                     => None,
+		}
+	}
+
+	fn syntax(&self) -> &SyntaxNode {
+		match self {
+			ExpNode::Literal(n) => n.syntax(),
+			ExpNode::Name(n)    => n.syntax(),
+			ExpNode::Paren(n)   => n.syntax(),
+			ExpNode::Prefix(n)  => n.syntax(),
+			ExpNode::Binary(n)  => n.syntax(),
+			ExpNode::Call(n)    => n.syntax(),
+			ExpNode::Array(n)   => n.syntax(),
+			ExpNode::Index(n)   => n.syntax(),
+			ExpNode::Match(n)   => n.syntax(),
+			ExpNode::Func(n)    => n.syntax(),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Accessors
+// ------------------------------------------------------------------------------------------------
+
+// the first child token of this kind.
+fn token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+	node.tokens().into_iter().find(|t| t.kind() == kind)
+}
+
+// the child nodes that are expressions. (Error nodes aren't.)
+fn exps(node: &SyntaxNode) -> Vec<ExpNode> {
+	node.children().into_iter().filter_map(ExpNode::cast).collect()
+}
+
+fn first_exp(node: &SyntaxNode) -> Option<ExpNode> {
+	exps(node).into_iter().next()
+}
+
+impl ProgramNode {
+	// Parses the source into a CST. See parse_cst().
+	pub fn parse(src: &str) -> (ProgramNode, Vec<Diagnostic>) {
+		let (root, errors) = parse_cst(src);
+		(ProgramNode(root), errors)
+	}
+
+	pub fn stmts(&self) -> Vec<StmtNode> {
+		self.0.children().into_iter().filter_map(StmtNode::cast).collect()
+	}
+
+	// The exact source code that was parsed.
+	pub fn text(&self) -> String {
+		self.0.text()
+	}
+}
+
+impl LetNode {
+	pub fn name(&self)  -> Option<SyntaxToken> { token(&self.0, SyntaxKind::Id) }
+	pub fn value(&self) -> Option<ExpNode>     { first_exp(&self.0) }
+}
+
+impl AssignNode {
+	pub fn name(&self)  -> Option<SyntaxToken> { token(&self.0, SyntaxKind::Id) }
+	pub fn value(&self) -> Option<ExpNode>     { first_exp(&self.0) }
+}
+
+impl ExpStmtNode {
+	pub fn exp(&self) -> Option<ExpNode> { first_exp(&self.0) }
+}
+
+impl WhileNode {
+	pub fn cond(&self) -> Option<ExpNode> { first_exp(&self.0) }
+
+	// None if the '{' was missing.
+	pub fn body(&self) -> Option<BlockNode> {
+		self.0.children().into_iter().find_map(BlockNode::cast)
+	}
+}
+
+impl IfNode {
+	pub fn cond(&self) -> Option<ExpNode> { first_exp(&self.0) }
+
+	// None if the '{' was missing.
+	pub fn then_body(&self) -> Option<BlockNode> {
+		self.0.children().into_iter().find_map(BlockNode::cast)
+	}
+
+	// the 'else' token, if there is one.
+	pub fn else_token(&self) -> Option<SyntaxToken> { token(&self.0, SyntaxKind::Else) }
+
+	// the Block or IfStmt after the 'else'. None if there's no 'else', or nothing after it.
+	pub fn else_body(&self) -> Option<StmtNode> {
+		let after = self.else_token()?.span().end;
+		self.0.children().into_iter()
+			.filter(|n| n.span().start >= after)
+			.find_map(StmtNode::cast)
+	}
+}
+
+impl BlockNode {
+	pub fn stmts(&self) -> Vec<StmtNode> {
+		self.0.children().into_iter().filter_map(StmtNode::cast).collect()
+	}
+}
+
+impl LiteralNode {
+	// the IntLit, FloatLit, StrLit, True, or False token. the parser never makes an empty
+	// Literal.
+	pub fn token(&self) -> SyntaxToken { self.0.tokens()[0].clone() }
+}
+
+impl NameNode {
+	pub fn ident(&self) -> SyntaxToken { self.0.tokens()[0].clone() }
+}
+
+impl ParenNode {
+	pub fn inner(&self) -> Option<ExpNode> { first_exp(&self.0) }
+}
+
+impl PrefixNode {
+	// the '-' or '!'.
+	pub fn op(&self)      -> SyntaxToken     { self.0.tokens()[0].clone() }
+	pub fn operand(&self) -> Option<ExpNode> { first_exp(&self.0) }
+}
+
+impl BinaryNode {
+	// the operator token. it's the only token that's a direct child of the BinaryExp.
+	pub fn op(&self) -> SyntaxToken {
+		self.0.tokens()[0].clone()
+	}
+
+	pub fn binop(&self) -> BinOp {
+		match self.op().kind() {
+			SyntaxKind::Plus      => BinOp::Add,
+			SyntaxKind::Minus     => BinOp::Sub,
+			SyntaxKind::Times     => BinOp::Mul,
+			SyntaxKind::Divide    => BinOp::Div,
+			SyntaxKind::Modulo    => BinOp::Mod,
+			SyntaxKind::Less      => BinOp::Lt,
+			SyntaxKind::LessEq    => BinOp::Le,
+			SyntaxKind::Greater   => BinOp::Gt,
+			SyntaxKind::GreaterEq => BinOp::Ge,
+			SyntaxKind::EqEq      => BinOp::Eq,
+			SyntaxKind::NotEq     => BinOp::Ne,
+			SyntaxKind::AndAnd    => BinOp::And,
+			SyntaxKind::OrOr      => BinOp::Or,
+			k                     => panic!("a BinaryExp with a {:?} operator", k),
+		}
+	}
+
+	// these look on either side of the operator, so that if one side is an Error node, the other
+	// side isn't mistaken for it.
+	pub fn lhs(&self) -> Option<ExpNode> {
+		let op = self.op().span();
+		exps(&self.0).into_iter().find(|e| e.syntax().span().end <= op.start)
+	}
+
+	pub fn rhs(&self) -> Option<ExpNode> {
+		let op = self.op().span();
+		exps(&self.0).into_iter().find(|e| e.syntax().span().start >= op.end)
+	}
+}
+
+impl CallNode {
+	pub fn callee(&self) -> Option<ExpNode> { first_exp(&self.0) }
+
+	pub fn arg_list(&self) -> SyntaxNode {
+		self.0.children().into_iter().find(|n| n.kind() == SyntaxKind::ArgList)
+			.expect("the parser never makes a CallExp without an ArgList")
+	}
+
+	pub fn args(&self) -> Vec<ExpNode> { exps(&self.arg_list()) }
+}
+
+impl ArrayNode {
+	pub fn elems(&self) -> Vec<ExpNode> { exps(&self.0) }
+}
+
+impl IndexNode {
+	// the '[' after the array. an array literal's '[' is inside its own node, so this is the only
+	// one that's a direct child of the IndexExp.
+	pub fn lbracket(&self) -> Option<SyntaxToken> { token(&self.0, SyntaxKind::LBracket) }
+
+	// like BinaryNode, these look on either side of the '['.
+	pub fn array(&self) -> Option<ExpNode> {
+		let lbracket = self.lbracket()?.span();
+		exps(&self.0).into_iter().find(|e| e.syntax().span().end <= lbracket.start)
+	}
+
+	pub fn index(&self) -> Option<ExpNode> {
+		let lbracket = self.lbracket()?.span();
+		exps(&self.0).into_iter().find(|e| e.syntax().span().start >= lbracket.end)
+	}
+}
+
+impl MatchNode {
+	pub fn scrutinee(&self) -> Option<ExpNode> { first_exp(&self.0) }
+
+	pub fn arms(&self) -> Vec<ArmNode> {
+		self.0.children().into_iter().filter_map(ArmNode::cast).collect()
+	}
+}
+
+impl ArmNode {
+	// the parser only makes a MatchArm if the pattern is there.
+	pub fn pattern(&self) -> PatternNode {
+		self.0.children().into_iter().find_map(PatternNode::cast)
+			.expect("the parser never makes a MatchArm without a Pattern")
+	}
+
+	pub fn value(&self) -> Option<ExpNode> { first_exp(&self.0) }
+}
+
+impl PatternNode {
+	// None if the number is too big.
+	pub fn pattern(&self) -> Option<Pattern> {
+		let tokens = self.0.tokens();
+
+		match tokens.last()?.kind() {
+			SyntaxKind::IntLit => {
+				let val = tokens.last()?.text().parse::<i64>().ok()?;
+				let negative = tokens[0].kind() == SyntaxKind::Minus;
+				Some(Pattern::Int(if negative { -val } else { val }))
+			}
+
+			_ => Some(Pattern::Wildcard),
+		}
+	}
+}
+
+impl FuncNode {
+	// None if the '(' was missing.
+	pub fn param_list(&self) -> Option<SyntaxNode> {
+		self.0.children().into_iter().find(|n| n.kind() == SyntaxKind::ParamList)
+	}
+
+	// the parameters' Id tokens.
+	pub fn params(&self) -> Vec<SyntaxToken> {
+		self.param_list().map_or(vec![], |list| {
+			list.tokens().into_iter().filter(|t| t.kind() == SyntaxKind::Id).collect()
+		})
+	}
+
+	// None if anything before the '{' was wrong.
+	pub fn body(&self) -> Option<BlockNode> {
+		self.0.children().into_iter().find_map(BlockNode::cast)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// From CST to AST
+// ------------------------------------------------------------------------------------------------
+
+/*
+The CST has everything the AST has, so the AST can be made from it. That's what compilers built
+this way do: the parser only makes the CST, and the AST (or whatever the next phase wants) is made
+from that. The spans come out the same as parse()'s, since CST nodes don't start or end with
+trivia.
+
+This gives None if the tree has anything missing or any Error nodes, so only use it on trees that
+parsed without errors. It also gives None for an IncludeStmt, since that has no AST; the tokens of
+the included file go there instead (see include.rs).
+*/
+impl ProgramNode {
+	pub fn to_ast(&self) -> Option<Program> {
+		Some(Program { stmts: stmts_to_ast(&self.0, &self.stmts())? })
+	}
+}
+
+// the statements in a Program or Block. `parent` is that Program or Block.
+fn stmts_to_ast(parent: &SyntaxNode, stmts: &[StmtNode]) -> Option<Vec<Stmt>> {
+	let no_ast = |n: &SyntaxNode| matches!(n.kind(), SyntaxKind::Error | SyntaxKind::IncludeStmt);
+
+	if parent.children().iter().any(no_ast) {
+		return None;
+	}
+
+	stmts.iter().map(StmtNode::to_ast).collect()
+}
+
+impl BlockNode {
+	pub fn to_ast(&self) -> Option<Vec<Stmt>> {
+		token(&self.0, SyntaxKind::RBrace)?;
+		stmts_to_ast(&self.0, &self.stmts())
+	}
+}
+
+impl StmtNode {
+	pub fn to_ast(&self) -> Option<Stmt> {
+		let kind = match self {
+			StmtNode::Let(s) => {
+				token(s.syntax(), SyntaxKind::Assign)?;
+				let name = s.name()?;
+				let value = s.value()?.to_ast()?;
+				StmtKind::Let { name: name.text().into(), name_span: name.span(), value }
+			}
+
+			StmtNode::Assign(s) => {
+				let name = s.name()?;
+				let value = s.value()?.to_ast()?;
+				StmtKind::Assign { name: name.text().into(), name_span: name.span(), value }
+			}
+
+			StmtNode::Exp(s) => StmtKind::Exp { exp: s.exp()?.to_ast()? },
+
+			StmtNode::While(s) => {
+				let cond = s.cond()?.to_ast()?;
+				let body = s.body()?.to_ast()?;
+				StmtKind::While { cond, body }
+			}
+
+			StmtNode::If(s) => {
+				let cond = s.cond()?.to_ast()?;
+				let then = s.then_body()?;
+				let then_body = Box::new(Stmt::new(then.syntax().span(),
+					StmtKind::Block { stmts: then.to_ast()? }));
+
+				let else_body = match s.else_token() {
+					Some(..) => Some(Box::new(s.else_body()?.to_ast()?)),
+					None     => None,
+				};
+
+				StmtKind::If { cond, then_body, else_body }
+			}
+
+			StmtNode::Block(s)     => StmtKind::Block { stmts: s.to_ast()? },
+			StmtNode::Break(..)    => StmtKind::Break,
+			StmtNode::Continue(..) => StmtKind::Continue,
+		};
+
+		Some(Stmt::new(self.syntax().span(), kind))
+	}
+}
+
+impl ExpNode {
+	pub fn to_ast(&self) -> Option<Box<AstNode>> {
+		let span = self.syntax().span();
+
+		Some(match self {
+			ExpNode::Literal(lit) => {
+				let tok = lit.token();
+
+				match tok.kind() {
+					SyntaxKind::IntLit   => AstNode::int(span, tok.text().parse().ok()?),
+					SyntaxKind::FloatLit => AstNode::float(span, tok.text().parse().ok()?),
+					SyntaxKind::StrLit   => AstNode::str(span, &string_value(tok.text())?),
+					SyntaxKind::True     => AstNode::bool(span, true),
+					_                    => AstNode::bool(span, false),
+				}
+			}
+
+			ExpNode::Name(name) => AstNode::id(span, name.ident().text()),
+
+			ExpNode::Paren(paren) => {
+				token(paren.syntax(), SyntaxKind::RParen)?;
+
+				// like parse(), the parens aren't in the AST, but they're in the span.
+				let mut inner = paren.inner()?.to_ast()?;
+				inner.span = span;
+				inner
+			}
+
+			ExpNode::Prefix(prefix) => {
+				let operand = prefix.operand()?.to_ast()?;
+
+				match prefix.op().kind() {
+					SyntaxKind::Minus => AstNode::neg(prefix.op().span(), operand),
+					_                 => AstNode::not(prefix.op().span(), operand),
+				}
+			}
+
+			// a chain of operators is turned into a tree with a loop, for the same reason as in
+			// AstNode::left_chain(): `chain` goes from this one down the left side.
+			ExpNode::Binary(bin) => {
+				let mut chain = vec![bin.clone()];
+
+				while let Some(ExpNode::Binary(lhs)) = chain.last().unwrap().lhs() {
+					chain.push(lhs);
+				}
+
+				let mut ret = chain.last().unwrap().lhs()?.to_ast()?;
+
+				for bin in chain.iter().rev() {
+					ret = AstNode::bin(ret, bin.binop(), bin.rhs()?.to_ast()?);
+				}
+
+				ret
+			}
+
+			ExpNode::Call(call) => {
+				let arg_list = call.arg_list();
+				let rparen = token(&arg_list, SyntaxKind::RParen)?;
+				let args = exp_list_to_ast(&arg_list, &call.args())?;
+				AstNode::call(call.callee()?.to_ast()?, args, rparen.span())
+			}
+
+			ExpNode::Array(array) => {
+				let lbracket = token(array.syntax(), SyntaxKind::LBracket)?;
+				let rbracket = token(array.syntax(), SyntaxKind::RBracket)?;
+				let elems = exp_list_to_ast(array.syntax(), &array.elems())?;
+				AstNode::array(lbracket.span(), elems, rbracket.span())
+			}
+
+			ExpNode::Index(index) => {
+				let rbracket = token(index.syntax(), SyntaxKind::RBracket)?;
+				AstNode::index(index.array()?.to_ast()?, index.index()?.to_ast()?,
+					rbracket.span())
+			}
+
+			ExpNode::Match(m) => {
+				let no_ast = |n: &SyntaxNode| n.kind() == SyntaxKind::Error;
+				token(m.syntax(), SyntaxKind::LBrace)?;
+				let rbrace = token(m.syntax(), SyntaxKind::RBrace)?;
+
+				if m.syntax().children().iter().any(no_ast) {
+					return None;
+				}
+
+				let arms = m.arms().iter().map(|arm| {
+					let pattern = arm.pattern();
+
+					Some(MatchArm {
+						pattern:      pattern.pattern()?,
+						pattern_span: pattern.syntax().span(),
+						value:        *arm.value()?.to_ast()?,
+					})
+				}).collect::<Option<Vec<_>>>()?;
+
+				let start = token(m.syntax(), SyntaxKind::Match)?.span();
+				AstNode::match_exp(start, m.scrutinee()?.to_ast()?, arms, rbrace.span())
+			}
+
+			ExpNode::Func(func) => {
+				let start = token(func.syntax(), SyntaxKind::Fn)?.span();
+				let body = func.body()?;
+				let params = func.params().iter()
+					.map(|p| Param { name: p.text().into(), span: p.span() })
+					.collect();
+				AstNode::func(start, params, body.to_ast()?, body.syntax().span())
+			}
+		})
+	}
+}
+
+// the expressions in an ArgList or ArrayExp. `list` is that node.
+fn exp_list_to_ast(list: &SyntaxNode, exps: &[ExpNode]) -> Option<Vec<AstNode>> {
+	let commas = list.tokens().iter().filter(|t| t.kind() == SyntaxKind::Comma).count();
+
+	// in `f(1,)`, the argument after the comma is missing.
+	if (commas > 0 || !exps.is_empty()) && commas + 1 != exps.len() {
+		return None;
+	}
+
+	exps.iter().map(|exp| exp.to_ast().map(|e| *e)).collect()
+}
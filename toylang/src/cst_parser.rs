@@ -0,0 +1,826 @@
+use diagnostics::{ Diagnostic, ErrorCode };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The lossless parser
+// ------------------------------------------------------------------------------------------------
+
+/*
+This parses the same grammar as parse() (see parser.rs), but it builds a concrete syntax tree
+(see syntax.rs) from lex_lossless()'s tokens, instead of an AST from lex()'s. Two things are
+different about it:
+
+- Trivia go into the tree too. The parser skips over them when it's deciding what to do, and
+  whenever it adds a token to the tree, any trivia before that token get added first. A node is
+  never started right before trivia, so trivia between two statements end up in the Program, and
+  trivia between the `+` and the `b` of `a + b` end up in the BinaryExp.
+
+- It *always* gives a tree, even for broken code, because editor tools have to work on code that
+  you're halfway through typing. When something is missing (like the `)` in `f(1`), it's just not
+  in the tree. When something is there that shouldn't be, it goes into an Error node. Either way,
+  every token ends up *somewhere*, so `text()` always gives back the source.
+
+It reports the same errors as parse(): the first one in each statement. (After one error, the
+rest of a broken statement usually causes a pile of confusing follow-on errors.) That includes
+code nested more than MAX_NESTING levels deep, or with more than MAX_OPERATORS operators in a
+row, which it stops at the same place. It doesn't report errors on Invalid tokens, because
+lex_lossless() already did. The lexer's and parser's errors are mixed together, in the order they
+are in the file.
+*/
+pub fn parse_cst(src: &str) -> (SyntaxNode, Vec<Diagnostic>) {
+	let (mut tokens, mut errors) = lex_lossless(src);
+	let eof = tokens.pop().expect("lex_lossless() always gives an Eof token");
+
+	let mut p = CstParser {
+		chars: src.chars().collect(),
+		tokens,
+		eof,
+		pos: 0,
+		builder: GreenBuilder::new(),
+		errors: vec![],
+		stmt_has_error: false,
+		nesting: 0,
+		operators: 0,
+	};
+
+	p.parse_program();
+	errors.extend(p.errors);
+	errors.sort_by_key(|e| e.span.map(|span| span.start));
+	(SyntaxNode::new_root(p.builder.finish()), errors)
+}
+
+struct CstParser {
+	chars:   Vec<char>,
+	tokens:  Vec<Token>, // all of them, trivia too, but not the Eof.
+	eof:     Token,
+	pos:     usize,      // the next token to go into the tree (which might be trivia).
+	builder: GreenBuilder,
+	errors:  Vec<Diagnostic>,
+
+	// has an error been reported for the statement being parsed?
+	stmt_has_error: bool,
+
+	// how many terms and blocks deep we are, and how many binary operators deep, counted the same
+	// way as parse().
+	nesting:   usize,
+	operators: usize,
+}
+
+impl CstParser {
+	// the nth token after the trivia at `pos`, not counting trivia. past the end, it's the Eof.
+	fn peek(&self, n: usize) -> &Token {
+		self.tokens[self.pos ..].iter()
+			.filter(|t| !t.kind.is_trivia())
+			.nth(n)
+			.unwrap_or(&self.eof)
+	}
+
+	fn cur(&self) -> &Token {
+		self.peek(0)
+	}
+
+	fn at(&self, kind: TokenKind) -> bool {
+		self.cur().kind == kind
+	}
+
+	fn at_stmt_end(&self) -> bool {
+		matches!(self.cur().kind, TokenKind::Newline | TokenKind::Semi | TokenKind::RBrace |
+			TokenKind::Eof)
+	}
+
+	// adds the token at `pos` to the tree.
+	fn push_token(&mut self) {
+		let tok = &self.tokens[self.pos];
+		let text = self.chars[tok.span.start .. tok.span.end].iter().collect::<String>();
+		self.builder.token(SyntaxKind::from_token(&tok.kind), &text);
+		self.pos += 1;
+	}
+
+	fn eat_trivia(&mut self) {
+		while self.pos < self.tokens.len() && self.tokens[self.pos].kind.is_trivia() {
+			self.push_token();
+		}
+	}
+
+	// adds the current token to the tree (and the trivia before it), and moves past it.
+	fn bump(&mut self) {
+		self.eat_trivia();
+
+		if self.pos < self.tokens.len() {
+			self.push_token();
+		}
+	}
+
+	fn start_node(&mut self, kind: SyntaxKind) {
+		self.eat_trivia();
+		self.builder.start_node(kind);
+	}
+
+	fn finish_node(&mut self) {
+		self.builder.finish_node();
+	}
+
+	fn checkpoint(&mut self) -> Checkpoint {
+		self.eat_trivia();
+		self.builder.checkpoint()
+	}
+
+	// Program: Sep* (Stmt (Sep+ Stmt)*)? Sep* Eof
+	fn parse_program(&mut self) {
+		// trivia at the very beginning go in the Program, so start it before eating them.
+		self.builder.start_node(SyntaxKind::Program);
+
+		loop {
+			while matches!(self.cur().kind, TokenKind::Newline | TokenKind::Semi) {
+				self.bump();
+			}
+
+			if self.at(TokenKind::Eof) {
+				break;
+			}
+
+			self.parse_stmt();
+		}
+
+		self.eat_trivia();
+		self.finish_node();
+	}
+
+	// Stmt: LetStmt | AssignStmt | IncludeStmt | WhileStmt | IfStmt | Block | 'break' | 'continue'
+	//     | Exp
+	fn parse_stmt(&mut self) {
+		self.stmt_has_error = false;
+
+		match (&self.cur().kind, &self.peek(1).kind) {
+			// LetStmt: 'let' Id '=' Exp
+			(TokenKind::Let, _) => {
+				self.start_node(SyntaxKind::LetStmt);
+				self.bump();
+				self.expect_name();
+				self.expect(TokenKind::Assign, diagnostics::EXPECTED_ASSIGN, "'='");
+
+				// like parse(), without a name and '=', the value isn't parsed. (it could be a
+				// function, whose body would report errors of its own.)
+				if !self.stmt_has_error {
+					self.parse_exp();
+				} else if !self.at_stmt_end() {
+					self.start_node(SyntaxKind::Error);
+					self.skip_to_stmt_end();
+					self.finish_node();
+				}
+
+				self.finish_node();
+			}
+
+			// AssignStmt: Id '=' Exp
+			(TokenKind::Id(..), TokenKind::Assign) => {
+				self.start_node(SyntaxKind::AssignStmt);
+				self.bump();
+				self.bump();
+				self.parse_exp();
+				self.finish_node();
+			}
+
+			// IncludeStmt: 'include' StrLit
+			(TokenKind::Include, _) => {
+				self.parse_include();
+				return;
+			}
+
+			// WhileStmt: 'while' Exp Block
+			(TokenKind::While, _) => {
+				self.start_node(SyntaxKind::WhileStmt);
+				self.bump();
+				self.parse_exp();
+
+				if self.at(TokenKind::LBrace) {
+					self.parse_block();
+				} else {
+					self.report_missing_lbrace("loop", "while");
+				}
+
+				self.finish_node();
+			}
+
+			(TokenKind::If, _)     => self.parse_if(),
+			(TokenKind::LBrace, _) => self.parse_block(),
+
+			(TokenKind::Break, _) => {
+				self.start_node(SyntaxKind::BreakStmt);
+				self.bump();
+				self.finish_node();
+			}
+
+			(TokenKind::Continue, _) => {
+				self.start_node(SyntaxKind::ContinueStmt);
+				self.bump();
+				self.finish_node();
+			}
+
+			// a block stops at its '}', so this one doesn't close anything. it goes in an Error
+			// node with the rest of the statement.
+			(TokenKind::RBrace, _) => {
+				self.report(self.error(diagnostics::UNMATCHED_RBRACE,
+					"this '}' doesn't close any block"));
+				self.start_node(SyntaxKind::Error);
+				self.bump();
+				self.skip_to_stmt_end();
+				self.finish_node();
+			}
+
+			// an else always comes right after an if's block, so this one doesn't go with one.
+			(TokenKind::Else, _) => {
+				self.report(self.error(diagnostics::ELSE_WITHOUT_IF,
+					"this 'else' doesn't go with any 'if'")
+					.with_note("the 'else' has to be on the same line as the '}' before it"));
+				self.start_node(SyntaxKind::Error);
+				self.bump();
+				self.skip_to_stmt_end();
+				self.finish_node();
+			}
+
+			_ => {
+				self.start_node(SyntaxKind::ExpStmt);
+				self.parse_exp();
+				self.finish_node();
+			}
+		}
+
+		self.expect_stmt_end();
+	}
+
+	// Block: '{' Sep* (Stmt (Sep+ Stmt)*)? Sep* '}'
+	//
+	// the current token must be the '{'.
+	fn parse_block(&mut self) {
+		self.nested(|p| {
+			let outer_has_error = p.stmt_has_error;
+			p.start_node(SyntaxKind::Block);
+			p.bump();
+
+			loop {
+				while matches!(p.cur().kind, TokenKind::Newline | TokenKind::Semi) {
+					p.bump();
+				}
+
+				if p.at(TokenKind::RBrace) || p.at(TokenKind::Eof) {
+					break;
+				}
+
+				p.parse_stmt();
+			}
+
+			// the statements inside reported their own errors. now it's back to the statement
+			// that the block is part of.
+			p.stmt_has_error = outer_has_error;
+
+			if p.at(TokenKind::RBrace) {
+				p.bump();
+			} else {
+				p.report(p.error(diagnostics::EXPECTED_RBRACE,
+					format!("expected '}}' at the end of the block, not '{}'", p.cur().kind))
+					.with_note("every '{' needs a '}' to go with it"));
+			}
+
+			p.finish_node();
+		});
+	}
+
+	// IfStmt: 'if' Exp Block ('else' (IfStmt | Block))?
+	//
+	// the current token must be the 'if'. an `else if` is an IfStmt inside this one.
+	fn parse_if(&mut self) {
+		self.start_node(SyntaxKind::IfStmt);
+		self.bump();
+		self.parse_exp();
+
+		if !self.at(TokenKind::LBrace) {
+			self.report_missing_lbrace("if", "if");
+		} else {
+			self.parse_block();
+
+			if self.at(TokenKind::Else) {
+				self.bump();
+
+				match self.cur().kind {
+					TokenKind::LBrace => self.parse_block(),
+					TokenKind::If     => self.nested(|p| p.parse_if()),
+					_                 => self.report(self.error(diagnostics::EXPECTED_LBRACE,
+						format!("expected '{{' or 'if' after 'else', not '{}'", self.cur().kind))),
+				}
+			}
+		}
+
+		self.finish_node();
+	}
+
+	// `what` is the thing the condition belongs to, and `keyword` is what starts it.
+	fn report_missing_lbrace(&mut self, what: &str, keyword: &str) {
+		let err = self.error(diagnostics::EXPECTED_LBRACE,
+			format!("expected '{{' after the {}'s condition, not '{}'", what, self.cur().kind));
+
+		self.report(if self.at(TokenKind::Newline) {
+			err.with_note(format!("the '{{' has to be on the same line as the '{}'", keyword))
+		} else {
+			err
+		});
+	}
+
+	// these go in the tree like any other statement, but like parse(), this reports an error for
+	// them, since there's no file to include them from. to_ast() gives None for them.
+	fn parse_include(&mut self) {
+		let start = self.cur().span;
+		self.start_node(SyntaxKind::IncludeStmt);
+		self.bump();
+
+		let name = match self.cur().kind {
+			TokenKind::StrLit(..) => {
+				let span = self.cur().span;
+				self.bump();
+				Some(span)
+			}
+
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_FILE_NAME,
+					format!("expected a file name in double quotes, not '{}'", self.cur().kind)));
+				None
+			}
+		};
+
+		self.finish_node();
+
+		if let (Some(name), true) = (name, self.at_stmt_end()) {
+			self.report(Diagnostic::error("can't include files here", start.to(name))
+				.with_code(diagnostics::INCLUDE_FAILED)
+				.with_note("only a program that's run from a file (like with `toylang eval`) can \
+					include other files"));
+		}
+
+		self.expect_stmt_end();
+	}
+
+	// anything left before the separator goes in an Error node after the statement.
+	fn expect_stmt_end(&mut self) {
+		if self.at_stmt_end() {
+			return;
+		}
+
+		let err = self.error(diagnostics::EXPECTED_STMT_END,
+			format!("expected end of statement, not '{}'", self.cur().kind));
+
+		self.report(if self.at(TokenKind::Assign) {
+			err.with_note("only variables can be assigned to, like `x = 5`")
+		} else {
+			err.with_note("put statements on separate lines, or separate them with ';'")
+		});
+
+		self.start_node(SyntaxKind::Error);
+		self.skip_to_stmt_end();
+		self.finish_node();
+	}
+
+	// adds tokens to the tree until the end of the statement. like parse(), any blocks are
+	// skipped over whole, separators and all.
+	fn skip_to_stmt_end(&mut self) {
+		let mut depth = 0;
+
+		while !self.at(TokenKind::Eof) {
+			match self.cur().kind {
+				_ if depth == 0 && self.at_stmt_end() => break,
+				TokenKind::LBrace => depth += 1,
+				TokenKind::RBrace => depth -= 1,
+				_                 => {}
+			}
+
+			self.bump();
+		}
+	}
+
+	// goes one level deeper. if that's more than MAX_NESTING levels, it gives the same error as
+	// parse(), and puts the rest of the statement in an Error node (so that nothing is left to go
+	// deeper).
+	fn deeper(&mut self) -> bool {
+		if self.nesting < MAX_NESTING {
+			self.nesting += 1;
+			return true;
+		}
+
+		self.report(self.error(diagnostics::TOO_DEEPLY_NESTED,
+			format!("this code is nested more than {} levels deep", MAX_NESTING))
+			.with_note("split it up into smaller pieces with some variables"));
+		self.start_node(SyntaxKind::Error);
+		self.skip_to_stmt_end();
+		self.finish_node();
+		false
+	}
+
+	// like deeper(), but for a binary operator, which has its own, much bigger limit.
+	fn more_operators(&mut self) -> bool {
+		if self.operators < MAX_OPERATORS {
+			self.operators += 1;
+			return true;
+		}
+
+		self.report(self.error(diagnostics::TOO_MANY_OPERATORS,
+			format!("this expression has more than {} operators in a row", MAX_OPERATORS))
+			.with_note("split it up into smaller pieces with some variables"));
+		self.start_node(SyntaxKind::Error);
+		self.skip_to_stmt_end();
+		self.finish_node();
+		false
+	}
+
+	// runs `parse_fn` one level deeper, unless that's too deep.
+	fn nested(&mut self, parse_fn: impl FnOnce(&mut Self)) {
+		let outer = self.nesting;
+
+		if self.deeper() {
+			parse_fn(self);
+		}
+
+		self.nesting = outer;
+	}
+
+	fn parse_exp(&mut self) {
+		self.parse_binops(Precedence::None);
+	}
+
+	// Exp: Term (BinOp Term)*
+	//
+	// this is the same precedence climbing as parse(), but written the other common way: the
+	// right side of an operator is parsed with only operators of *higher* precedence, so that
+	// `a - b - c` stops after `b`, and the loop makes that the left side of the second `-`.
+	fn parse_binops(&mut self, min_prec: Precedence) {
+		let outer = self.operators;
+		let start = self.checkpoint();
+		self.parse_term();
+
+		loop {
+			let prec = self.cur().kind.precedence();
+
+			// like parse(), each operator puts everything before it one level deeper. (the Error
+			// node can't go in the BinaryExp, since a BinaryExp always has its operator.)
+			if !prec.is_higher_than(min_prec) || !self.more_operators() {
+				break;
+			}
+
+			self.builder.start_node_at(start, SyntaxKind::BinaryExp);
+			self.bump();
+			self.parse_binops(prec);
+			self.finish_node();
+		}
+
+		self.operators = outer;
+	}
+
+	// Term: ('-' | '!')* PrimaryExp PostfixOp*
+	//
+	// like in parse(), this is where the nesting is counted.
+	fn parse_term(&mut self) {
+		self.nested(|p| {
+			if p.at(TokenKind::Minus) || p.at(TokenKind::Not) {
+				p.start_node(SyntaxKind::PrefixExp);
+				p.bump();
+				p.parse_term();
+				p.finish_node();
+				return;
+			}
+
+			let start = p.checkpoint();
+			p.parse_primary();
+
+			loop {
+				let is_postfix = p.at(TokenKind::LParen) || p.at(TokenKind::LBracket);
+
+				if !is_postfix || !p.deeper() {
+					break;
+				}
+
+				if p.at(TokenKind::LParen) {
+					// CallOp: '(' (Exp (',' Exp)*)? ')'
+					p.builder.start_node_at(start, SyntaxKind::CallExp);
+					p.start_node(SyntaxKind::ArgList);
+					p.parse_exp_list(TokenKind::RParen, diagnostics::EXPECTED_RPAREN, "')'");
+					p.finish_node();
+					p.finish_node();
+				} else {
+					// IndexOp: '[' Exp ']'
+					p.builder.start_node_at(start, SyntaxKind::IndexExp);
+					p.bump();
+					p.parse_exp();
+					p.expect(TokenKind::RBracket, diagnostics::EXPECTED_RBRACKET, "']'");
+					p.finish_node();
+				}
+			}
+		});
+	}
+
+	// the '(' or '[', the expressions separated by commas, and the `close` token. an ArgList and
+	// an ArrayExp look the same, except for which brackets they have.
+	fn parse_exp_list(&mut self, close: TokenKind, code: ErrorCode, what: &str) {
+		self.bump();
+
+		if !self.at(close.clone()) {
+			self.parse_exp();
+
+			while self.at(TokenKind::Comma) {
+				self.bump();
+				self.parse_exp();
+			}
+		}
+
+		self.expect(close, code, what);
+	}
+
+	// PrimaryExp: Id | IntLit | FloatLit | StrLit | 'true' | 'false' | '(' Exp ')' | ArrayExp
+	//     | MatchExp | FuncExp
+	fn parse_primary(&mut self) {
+		let kind = match self.cur().kind {
+			TokenKind::Id(..) => SyntaxKind::Name,
+
+			TokenKind::IntLit(..) | TokenKind::FloatLit(..) | TokenKind::StrLit(..) |
+			TokenKind::True | TokenKind::False => SyntaxKind::Literal,
+
+			TokenKind::LParen => {
+				self.start_node(SyntaxKind::ParenExp);
+				self.bump();
+				self.parse_exp();
+				self.expect(TokenKind::RParen, diagnostics::EXPECTED_RPAREN, "')'");
+				self.finish_node();
+				return;
+			}
+
+			// ArrayExp: '[' (Exp (',' Exp)*)? ']'
+			TokenKind::LBracket => {
+				self.start_node(SyntaxKind::ArrayExp);
+				self.parse_exp_list(TokenKind::RBracket, diagnostics::EXPECTED_RBRACKET, "']'");
+				self.finish_node();
+				return;
+			}
+
+			TokenKind::Match => {
+				self.parse_match();
+				return;
+			}
+
+			TokenKind::Fn => {
+				self.parse_func();
+				return;
+			}
+
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_EXPRESSION, format!(
+					"expected an identifier, number, or parenthesized expression, not '{}'",
+					self.cur().kind)));
+
+				// these can end an expression, so leave them for whatever is around it. (a '{'
+				// might be a loop's body.) a statement will put any leftovers into an Error node.
+				if !self.at_stmt_end() && !self.at(TokenKind::RParen) &&
+					!self.at(TokenKind::RBracket) && !self.at(TokenKind::Comma) &&
+					!self.at(TokenKind::LBrace) {
+					self.start_node(SyntaxKind::Error);
+					self.bump();
+					self.finish_node();
+				}
+
+				return;
+			}
+		};
+
+		self.start_node(kind);
+		self.bump();
+		self.finish_node();
+	}
+
+	// MatchExp: 'match' Exp '{' ArmSep* (MatchArm (ArmSep+ MatchArm)*)? ArmSep* '}'
+	//
+	// like parse(), a bad arm (and anything extra after an arm) goes in an Error node.
+	fn parse_match(&mut self) {
+		self.start_node(SyntaxKind::MatchExp);
+		self.bump();
+		self.parse_exp();
+
+		if !self.at(TokenKind::LBrace) {
+			let err = self.error(diagnostics::EXPECTED_LBRACE,
+				format!("expected '{{' after the match's value, not '{}'", self.cur().kind));
+
+			self.report(if self.at(TokenKind::Newline) {
+				err.with_note("the '{' has to be on the same line as the 'match'")
+			} else {
+				err
+			});
+
+			self.finish_node();
+			return;
+		}
+
+		self.bump();
+
+		loop {
+			while self.at(TokenKind::Comma) || self.at(TokenKind::Newline) {
+				self.bump();
+			}
+
+			if self.at(TokenKind::RBrace) || self.at(TokenKind::Eof) {
+				break;
+			}
+
+			self.parse_arm();
+
+			if !self.at_arm_end() {
+				self.report(self.error(diagnostics::EXPECTED_ARM_END,
+					format!("expected ',' or a new line after the match arm, not '{}'",
+					self.cur().kind)));
+				self.start_node(SyntaxKind::Error);
+				self.skip_to_arm_end();
+				self.finish_node();
+			}
+		}
+
+		if self.at(TokenKind::RBrace) {
+			self.bump();
+		} else {
+			self.report(self.error(diagnostics::EXPECTED_RBRACE,
+				format!("expected '}}' at the end of the match, not '{}'", self.cur().kind))
+				.with_note("every '{' needs a '}' to go with it"));
+		}
+
+		self.finish_node();
+	}
+
+	// FuncExp: 'fn' ParamList Block
+	// ParamList: '(' (Id (',' Id)*)? ')'
+	//
+	// like parse(), it stops at the first thing that's wrong before the body, and leaves the rest
+	// for the statement to put in an Error node.
+	fn parse_func(&mut self) {
+		self.start_node(SyntaxKind::FuncExp);
+		self.bump();
+
+		if !self.at(TokenKind::LParen) {
+			self.report(self.error(diagnostics::EXPECTED_LPAREN,
+				format!("expected '(' after 'fn', not '{}'", self.cur().kind)));
+			self.finish_node();
+			return;
+		}
+
+		self.start_node(SyntaxKind::ParamList);
+		self.bump();
+
+		if !self.at(TokenKind::RParen) {
+			loop {
+				if let TokenKind::Id(..) = self.cur().kind {
+					self.bump();
+				} else {
+					self.report(self.error(diagnostics::EXPECTED_NAME,
+						format!("expected a parameter name, not '{}'", self.cur().kind)));
+					self.finish_node();
+					self.finish_node();
+					return;
+				}
+
+				if !self.at(TokenKind::Comma) {
+					break;
+				}
+
+				self.bump();
+			}
+		}
+
+		let has_rparen = self.at(TokenKind::RParen);
+		self.expect(TokenKind::RParen, diagnostics::EXPECTED_RPAREN, "')'");
+		self.finish_node();
+
+		if !has_rparen {
+			self.finish_node();
+			return;
+		}
+
+		if self.at(TokenKind::LBrace) {
+			self.parse_block();
+		} else {
+			let err = self.error(diagnostics::EXPECTED_LBRACE, format!(
+				"expected '{{' after the function's parameters, not '{}'", self.cur().kind));
+
+			self.report(if self.at(TokenKind::Newline) {
+				err.with_note("the '{' has to be on the same line as the ')'")
+			} else {
+				err
+			});
+		}
+
+		self.finish_node();
+	}
+
+	// MatchArm: Pattern '=>' Exp
+	//
+	// if the pattern or the '=>' is bad, the whole arm is an Error node instead.
+	fn parse_arm(&mut self) {
+		let start = self.checkpoint();
+
+		if self.parse_pattern() {
+			if self.at(TokenKind::FatArrow) {
+				self.builder.start_node_at(start, SyntaxKind::MatchArm);
+				self.bump();
+				self.parse_exp();
+				self.finish_node();
+				return;
+			}
+
+			self.report(self.error(diagnostics::EXPECTED_ARROW,
+				format!("expected '=>' after the pattern, not '{}'", self.cur().kind)));
+		}
+
+		self.builder.start_node_at(start, SyntaxKind::Error);
+		self.skip_to_arm_end();
+		self.finish_node();
+	}
+
+	// Pattern: '-'? IntLit | '_'
+	//
+	// gives false (and adds nothing to the tree) if there's no pattern here.
+	fn parse_pattern(&mut self) -> bool {
+		let ok = match (&self.cur().kind, &self.peek(1).kind) {
+			(TokenKind::IntLit(..), _) | (TokenKind::Minus, TokenKind::IntLit(..)) => true,
+			(TokenKind::Id(name), _) => name == "_",
+			_ => false,
+		};
+
+		if !ok {
+			let err = self.error(diagnostics::EXPECTED_PATTERN,
+				format!("expected a number or '_' for the pattern, not '{}'", self.cur().kind));
+
+			self.report(match self.cur().kind {
+				TokenKind::Id(..) => err.with_note("a pattern can't be a variable; use '_' to \
+					match anything, or '==' to compare with a variable"),
+				_ => err,
+			});
+
+			return false;
+		}
+
+		self.start_node(SyntaxKind::Pattern);
+
+		if self.at(TokenKind::Minus) {
+			self.bump();
+		}
+
+		self.bump();
+		self.finish_node();
+		true
+	}
+
+	fn at_arm_end(&self) -> bool {
+		matches!(self.cur().kind, TokenKind::Comma | TokenKind::Newline | TokenKind::RBrace |
+			TokenKind::Eof)
+	}
+
+	// adds tokens to the tree until the end of the match arm, skipping over blocks whole.
+	fn skip_to_arm_end(&mut self) {
+		let mut depth = 0;
+
+		while !self.at(TokenKind::Eof) {
+			match self.cur().kind {
+				_ if depth == 0 && self.at_arm_end() => break,
+				TokenKind::LBrace => depth += 1,
+				TokenKind::RBrace => depth -= 1,
+				_                 => {}
+			}
+
+			self.bump();
+		}
+	}
+
+	fn expect_name(&mut self) {
+		if let TokenKind::Id(..) = self.cur().kind {
+			self.bump();
+		} else {
+			self.report(self.error(diagnostics::EXPECTED_NAME,
+				format!("expected a variable name, not '{}'", self.cur().kind)));
+		}
+	}
+
+	// if the token isn't there, it's just missing from the tree.
+	fn expect(&mut self, kind: TokenKind, code: ErrorCode, what: &str) {
+		if self.at(kind) {
+			self.bump();
+		} else {
+			self.report(self.error(code, format!("expected {}, not '{}'", what,
+				self.cur().kind)));
+		}
+	}
+
+	// makes an error pointing at the current token.
+	fn error(&self, code: ErrorCode, message: impl Into<String>) -> Diagnostic {
+		Diagnostic::error(message, self.cur().span).with_code(code)
+	}
+
+	fn report(&mut self, err: Diagnostic) {
+		if !self.stmt_has_error && self.cur().kind != TokenKind::Invalid {
+			self.errors.push(err);
+		}
+
+		self.stmt_has_error = true;
+	}
+}
@@ -0,0 +1,948 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{ Debug, Display, Formatter, Result as FmtResult };
+use std::rc::Rc;
+
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// This is ast_math's evaluator, but with variables and functions, which means it needs somewhere
+// to look them up: the environment (Env).
+
+// ------------------------------------------------------------------------------------------------
+// Value
+// ------------------------------------------------------------------------------------------------
+
+/*
+Numbers are still f64s, even the integer literals. (The type checker in the semantic crate knows
+the difference between ints and floats, but this doesn't.) But strings and bools aren't numbers, so
+there is more than one kind of value, and the interpreter has to check which kind it has before it
+does anything with it: `"a" + "b"` joins the strings, but `"a" - "b"` and `"a" + 1` are errors.
+
+Bools are what the comparisons give, and what `if`, `while`, `&&`, `||`, and `!` need. Some
+languages let any value be a condition, and decide whether it's "truthy" (in C, 0 is false and
+everything else is true; in Python, so are "" and empty lists). Here, only a bool can be a
+condition, so `while x { ... }` is an error when x is a number; write `while x != 0 { ... }`. It's
+more typing, but it catches mistakes like `if x = 5` in languages that would allow it.
+
+An array holds any number of values, even other arrays, and they don't all have to be the same
+kind (though the type checker says they do). Arrays can't be changed once they're made, so when
+one is copied, like in `let b = a`, both variables can share the same values instead of copying
+every one of them. That's what the Rc does: it counts how many places are sharing the values, and
+frees them when the last one is gone.
+
+A function is a value too (see Closure below), so it can be put in a variable, passed to another
+function, or given back from one.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+	Num(f64),
+	Str(String),
+	Bool(bool),
+	Array(Rc<[Value]>),
+	Closure(Rc<Closure>),
+}
+
+impl Value {
+	// "a number", "a string", "a bool", "an array", or "a function", for error messages.
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			Value::Num(..)     => "a number",
+			Value::Str(..)     => "a string",
+			Value::Bool(..)    => "a bool",
+			Value::Array(..)   => "an array",
+			Value::Closure(..) => "a function",
+		}
+	}
+
+	// what the value would look like in code. unlike to_string(), strings get quotes (and escape
+	// sequences), so you can tell "5" from 5.
+	pub fn to_code(&self) -> String {
+		match self {
+			Value::Num(x)   => x.to_string(),
+			Value::Str(s)   => quote(s),
+			Value::Bool(b)  => b.to_string(),
+			Value::Array(a) => {
+				let elems = a.iter().map(Value::to_code).collect::<Vec<_>>();
+				format!("[{}]", elems.join(", "))
+			}
+
+			// the body could be huge, so it's left out.
+			Value::Closure(c) => format!("fn({}) {{ ... }}", c.params.join(", ")),
+		}
+	}
+
+	pub fn as_num(&self) -> Option<f64> {
+		match self {
+			Value::Num(x) => Some(*x),
+			_             => None,
+		}
+	}
+}
+
+// strings are shown without quotes, the way a `print` would show them. but the strings in an array
+// get their quotes, so you can tell `["a, b"]` from `["a", "b"]`.
+impl Display for Value {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			Value::Num(x)      => write!(f, "{}", x),
+			Value::Str(s)      => write!(f, "{}", s),
+			Value::Bool(b)     => write!(f, "{}", b),
+			Value::Array(..)   => write!(f, "{}", self.to_code()),
+			Value::Closure(..) => write!(f, "{}", self.to_code()),
+		}
+	}
+}
+
+impl From<f64> for Value {
+	fn from(x: f64) -> Self {
+		Value::Num(x)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(s: &str) -> Self {
+		Value::Str(s.into())
+	}
+}
+
+impl From<bool> for Value {
+	fn from(b: bool) -> Self {
+		Value::Bool(b)
+	}
+}
+
+impl From<Vec<Value>> for Value {
+	fn from(a: Vec<Value>) -> Self {
+		Value::Array(a.into())
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Closure
+// ------------------------------------------------------------------------------------------------
+
+/*
+A function can use variables from outside of it, like `n` here:
+
+	let make_adder = fn(n) { fn(x) { x + n } }
+	let add5 = make_adder(5)
+	add5(1)    # 6
+
+By the time add5 is called, make_adder has returned, so its `n` would be gone if variables only
+lived as long as the call that made them. So a function value is a *closure*: the function's code,
+plus the environment it was made in, which keeps those variables alive as long as the function is.
+
+The body is an Rc (it's the same one that's in the AST), so making a closure doesn't copy the
+code, and all the closures made from the same `fn` share it.
+*/
+pub struct Closure {
+	params: Vec<String>,
+	body:   Rc<[Stmt]>,
+	env:    Env,
+}
+
+// a closure's environment can have the closure itself in it (that's how a function calls itself),
+// so printing the environment would go on forever. only the parameters are printed.
+impl Debug for Closure {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		f.debug_struct("Closure").field("params", &self.params).finish_non_exhaustive()
+	}
+}
+
+// two closures are only equal if they're the *same* closure. comparing the code wouldn't be
+// enough, since the same code with different environments does different things.
+impl PartialEq for Closure {
+	fn eq(&self, other: &Closure) -> bool {
+		std::ptr::eq(self, other)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Env
+// ------------------------------------------------------------------------------------------------
+
+// A builtin function takes its (already-evaluated) arguments and gives back a result. The
+// evaluator checks that it's given exactly `arity` arguments, so `func` doesn't have to.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+	pub arity: usize,
+	pub func:  BuiltinFn,
+}
+
+// Almost every builtin is a math function, which takes numbers and gives a number. The evaluator
+// checks that the arguments are all numbers. `len` is the odd one out, since it takes an array,
+// so it's built into the evaluator instead of being a Rust function.
+#[derive(Debug, Clone, Copy)]
+pub enum BuiltinFn {
+	Math(fn(&[f64]) -> f64),
+	Len,
+}
+
+/*
+Variables and builtin functions live in separate maps, so you can have a variable named `max`
+without losing the `max` function.
+
+Every function call gets a new *scope* for its parameters and the variables it makes with `let`,
+and each scope points to its *parent*: the scope the function was made in. Looking up a name
+starts in the innermost scope and follows the parents out to the global scope. That's an
+*environment chain*, and it's why a function can see the variables around it.
+
+The scopes are shared, not copied: a closure and the code that made it both point to the same
+scope, so when one of them changes a variable, the other sees it. That's what the Rc is for, and
+since a shared value can't normally be changed in Rust, the variables are in a RefCell, which
+checks the borrowing rules while the program runs instead. Cloning an Env shares the scopes too.
+
+One thing Rc can't do is free a *cycle*. `let f = fn() { f() }` makes a closure that points to the
+global scope, which has f in it, which points to the closure... so neither one's count ever gets
+to 0, and they're never freed. That's only a problem for a long-running program that makes lots of
+closures; a real interpreter would use a garbage collector, which can find cycles that nothing
+else points to.
+*/
+#[derive(Debug, Clone)]
+pub struct Env {
+	scope: Rc<Scope>,
+	funcs: Rc<HashMap<String, Builtin>>,
+	depth: usize, // how many function calls deep the code using this Env is.
+}
+
+#[derive(Debug)]
+struct Scope {
+	vars:   RefCell<HashMap<String, Value>>,
+	parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+	fn new(parent: Option<Rc<Scope>>) -> Rc<Scope> {
+		Rc::new(Scope { vars: RefCell::new(HashMap::new()), parent })
+	}
+
+	// the innermost scope that has this variable.
+	fn find(self: &Rc<Scope>, name: &str) -> Option<&Rc<Scope>> {
+		let mut scope = self;
+
+		loop {
+			if scope.vars.borrow().contains_key(name) {
+				return Some(scope);
+			}
+
+			scope = scope.parent.as_ref()?;
+		}
+	}
+}
+
+// the most function calls that can be going on at once. each one uses up some of Rust's own stack,
+// so without a limit, a function that calls itself forever would crash the interpreter. it's
+// small because a debug build can use tens of kilobytes of stack for every call, and the main
+// thread only gets a few megabytes. tail calls don't count, since they don't use any (see
+// eval_call()).
+const MAX_CALL_DEPTH: usize = 100;
+
+impl Default for Env {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Env {
+	// An environment with the standard math constants and functions.
+	pub fn new() -> Self {
+		let mut ret = Self::empty();
+		ret.set("pi", std::f64::consts::PI);
+		ret.set("e",  std::f64::consts::E);
+		ret.add_builtin("sqrt", 1, |a| a[0].sqrt());
+		ret.add_builtin("abs",  1, |a| a[0].abs());
+		ret.add_builtin("sin",  1, |a| a[0].sin());
+		ret.add_builtin("cos",  1, |a| a[0].cos());
+		ret.add_builtin("min",  2, |a| a[0].min(a[1]));
+		ret.add_builtin("max",  2, |a| a[0].max(a[1]));
+		ret.add_builtin("pow",  2, |a| a[0].powf(a[1]));
+		Rc::make_mut(&mut ret.funcs).insert("len".into(),
+			Builtin { arity: 1, func: BuiltinFn::Len });
+		ret
+	}
+
+	// An environment with nothing in it at all.
+	pub fn empty() -> Self {
+		Env { scope: Scope::new(None), funcs: Rc::new(HashMap::new()), depth: 0 }
+	}
+
+	// Looks in every scope, from the innermost one out. The value is cloned, since the scope it's
+	// in could change after this returns.
+	pub fn get(&self, name: &str) -> Option<Value> {
+		self.scope.find(name).map(|scope| scope.vars.borrow()[name].clone())
+	}
+
+	// Makes (or replaces) a variable in the innermost scope. This is what `let` does.
+	pub fn set(&mut self, name: &str, val: impl Into<Value>) {
+		self.scope.vars.borrow_mut().insert(name.into(), val.into());
+	}
+
+	// Changes the variable in whichever scope has it, or makes a new one in the innermost scope if
+	// none of them do. This is what `=` does.
+	pub fn assign(&mut self, name: &str, val: impl Into<Value>) {
+		let scope = self.scope.find(name).unwrap_or(&self.scope);
+		scope.vars.borrow_mut().insert(name.into(), val.into());
+	}
+
+	// All the variables in the innermost scope and their values, sorted by name. (HashMap
+	// iterates in a random-looking order, which is confusing to look at.)
+	pub fn vars(&self) -> Vec<(String, Value)> {
+		let mut ret = self.scope.vars.borrow().iter()
+			.map(|(name, val)| (name.clone(), val.clone()))
+			.collect::<Vec<_>>();
+		ret.sort_by(|a, b| a.0.cmp(&b.0));
+		ret
+	}
+
+	// A new scope inside this one, for a function call.
+	fn call_scope(&self, depth: usize) -> Env {
+		Env { scope: Scope::new(Some(self.scope.clone())), funcs: self.funcs.clone(), depth }
+	}
+
+	pub fn get_builtin(&self, name: &str) -> Option<Builtin> {
+		self.funcs.get(name).copied()
+	}
+
+	// All the builtin functions, sorted by name.
+	pub fn builtins(&self) -> Vec<(&str, Builtin)> {
+		let mut ret = self.funcs.iter().map(|(name, b)| (name.as_str(), *b)).collect::<Vec<_>>();
+		ret.sort_by(|a, b| a.0.cmp(b.0));
+		ret
+	}
+
+	// Adds a math function.
+	pub fn add_builtin(&mut self, name: &str, arity: usize, func: fn(&[f64]) -> f64) {
+		Rc::make_mut(&mut self.funcs).insert(name.into(),
+			Builtin { arity, func: BuiltinFn::Math(func) });
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The evaluator
+// ------------------------------------------------------------------------------------------------
+
+// Runs every statement in order, and gives the value of the last one (or None if there are no
+// statements, or the last one has no value). Stops at the first error.
+pub fn run(program: &Program, env: &mut Env) -> Result<Option<Value>, Diagnostic> {
+	let mut last = None;
+
+	for stmt in &program.stmts {
+		last = exec(stmt, env)?;
+	}
+
+	Ok(last)
+}
+
+/*
+Runs one statement, and gives its value. Like in C, the value of an assignment is the value that
+was assigned. A block's value is the value of the last statement in it, and an if's value is the
+value of whichever block ran. Loops, `break`, and `continue` don't have values, and neither does
+an if without an `else`, since there would be nothing to give when the condition is false.
+
+`break` and `continue` have to get out of whatever statements they're in, all the way up to the
+loop, so running a statement gives a Flow that says whether to keep going. The semantic crate
+makes sure they're always in a loop, but the interpreter can run code that wasn't checked, so
+it's an error if one gets all the way out here.
+*/
+pub fn exec(stmt: &Stmt, env: &mut Env) -> Result<Option<Value>, Diagnostic> {
+	match exec_flow(stmt, env)? {
+		Flow::Next(val) => Ok(val),
+		Flow::Break     => Err(outside_loop(stmt.span, "break")),
+		Flow::Continue  => Err(outside_loop(stmt.span, "continue")),
+	}
+}
+
+// What to do after a statement.
+enum Flow {
+	Next(Option<Value>), // go on to the next statement. this was the statement's value.
+	Break,               // leave the loop.
+	Continue,            // go back to the top of the loop.
+}
+
+fn exec_flow(stmt: &Stmt, env: &mut Env) -> Result<Flow, Diagnostic> {
+	use StmtKind::*;
+
+	match &stmt.kind {
+		Let { name, value, .. } => {
+			let val = eval(value, env)?;
+			env.set(name, val.clone());
+			Ok(Flow::Next(Some(val)))
+		}
+
+		Assign { name, value, .. } => {
+			let val = eval(value, env)?;
+			env.assign(name, val.clone());
+			Ok(Flow::Next(Some(val)))
+		}
+
+		Exp      { exp }   => Ok(Flow::Next(Some(eval(exp, env)?))),
+		Block    { stmts } => exec_block(stmts, env),
+		Break              => Ok(Flow::Break),
+		Continue           => Ok(Flow::Continue),
+
+		While { cond, body } => {
+			while eval_bool(cond, env, "a while loop's condition")? {
+				if let Flow::Break = exec_block(body, env)? {
+					break;
+				}
+			}
+
+			Ok(Flow::Next(None))
+		}
+
+		// a break or continue in either block is passed on, like a block's.
+		If { cond, then_body, else_body: Some(else_body) } => {
+			if eval_bool(cond, env, "an if's condition")? {
+				exec_flow(then_body, env)
+			} else {
+				exec_flow(else_body, env)
+			}
+		}
+
+		If { cond, then_body, else_body: None } => {
+			if !eval_bool(cond, env, "an if's condition")? {
+				return Ok(Flow::Next(None));
+			}
+
+			match exec_flow(then_body, env)? {
+				Flow::Next(..) => Ok(Flow::Next(None)),
+				flow           => Ok(flow),
+			}
+		}
+	}
+}
+
+// stops early if a statement breaks or continues, and passes that on to the loop.
+fn exec_block(stmts: &[Stmt], env: &mut Env) -> Result<Flow, Diagnostic> {
+	let mut last = None;
+
+	for stmt in stmts {
+		match exec_flow(stmt, env)? {
+			Flow::Next(val) => last = val,
+			flow            => return Ok(flow),
+		}
+	}
+
+	Ok(Flow::Next(last))
+}
+
+fn outside_loop(span: Span, what: &str) -> Diagnostic {
+	Diagnostic::error(format!("'{}' outside of a loop", what), span)
+		.with_code(diagnostics::BREAK_OUTSIDE_LOOP)
+}
+
+pub fn eval(node: &AstNode, env: &Env) -> Result<Value, Diagnostic> {
+	use AstKind::*;
+
+	match &node.kind {
+		IntLit   { val }          => Ok(Value::Num(*val as f64)),
+		FloatLit { val }          => Ok(Value::Num(*val)),
+		BoolLit  { val }          => Ok(Value::Bool(*val)),
+		StrLit   { val }          => Ok(Value::Str(val.clone())),
+		Ident    { name }         => eval_ident(node, name, env),
+		Negate   { lhs }          => Ok(Value::Num(-eval_num(lhs, env, "'-'")?)),
+		Not      { lhs }          => Ok(Value::Bool(!eval_bool(lhs, env, "'!'")?)),
+		Binary   { .. }           => eval_chain(node, env),
+		Call     { callee, args } => eval_call(node, callee, args, env),
+		Array    { elems }        => Ok(elems.iter().map(|elem| eval(elem, env))
+			.collect::<Result<Vec<_>, _>>()?.into()),
+		Index    { array, index } => eval_index(array, index, env),
+		Match    { scrutinee, arms } => eval_match(scrutinee, arms, env),
+
+		// the closure gets the *current* environment, so it can see the variables around it.
+		Func     { params, body } => Ok(Value::Closure(Rc::new(Closure {
+			params: params.iter().map(|p| p.name.clone()).collect(),
+			body:   body.clone(),
+			env:    env.clone(),
+		}))),
+
+		// only parse_partial() makes these, and its trees aren't meant to be run.
+		Error => Err(Diagnostic::error("can't run code that has a syntax error", node.span)),
+	}
+}
+
+// for the places that only numbers can go. `what` is the thing that needs a number.
+fn eval_num(node: &AstNode, env: &Env, what: &str) -> Result<f64, Diagnostic> {
+	match eval(node, env)? {
+		Value::Num(x) => Ok(x),
+		val           => Err(Diagnostic::error(
+			format!("{} needs a number, not {}", what, val.type_name()), node.span)
+			.with_code(diagnostics::WRONG_VALUE_TYPE)),
+	}
+}
+
+// for conditions, and the operands of the logical operators.
+fn eval_bool(node: &AstNode, env: &Env, what: &str) -> Result<bool, Diagnostic> {
+	expect_bool(node, eval(node, env)?, what)
+}
+
+// like eval_bool(), but for a value that's already been evaluated. `node` is what it came from.
+fn expect_bool(node: &AstNode, val: Value, what: &str) -> Result<bool, Diagnostic> {
+	match val {
+		Value::Bool(b) => Ok(b),
+		val            => {
+			let mut err = Diagnostic::error(
+				format!("{} needs a bool, not {}", what, val.type_name()), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE);
+
+			if let Value::Num(..) = val {
+				err = err.with_note("numbers aren't true or false; compare it, like `x != 0`");
+			}
+
+			Err(err)
+		}
+	}
+}
+
+fn eval_ident(node: &AstNode, name: &str, env: &Env) -> Result<Value, Diagnostic> {
+	match env.get(name) {
+		Some(val) => Ok(val),
+		None => {
+			let mut err = Diagnostic::error(format!("undefined variable '{}'", name), node.span)
+				.with_code(diagnostics::UNDEFINED_NAME);
+
+			if env.get_builtin(name).is_some() {
+				err = err.with_note(format!("'{}' is a function, so it has to be called", name));
+			}
+
+			Err(err)
+		}
+	}
+}
+
+/*
+A call can be to a closure or to a builtin. Builtins aren't values (they're Rust functions, not
+code in this language), so they can only be called by name. A variable that holds a closure wins
+over a builtin with the same name, but a variable that holds anything else doesn't, so
+`let max = 5` doesn't break `max(1, 2)`.
+
+A call in *tail position* is the very last thing its function does, like the `loop(...)` here:
+
+	let loop = fn(n, total) { if n == 0 { total } else { loop(n - 1, total + n) } }
+
+Once it's been called, the caller has nothing left to do except give back whatever it gave back.
+So there's no reason to keep the caller around while it runs: the call can *replace* the caller
+instead. That's *tail-call optimization*, and it means a loop written with recursion like the one
+above can go around a million times without running out of stack.
+
+The interpreter does it with a *trampoline*. The last statement of a body is run by exec_tail(),
+which doesn't make a tail call itself; it gives back a Tail::Call that says which closure to call
+with which arguments. Then the loop in eval_call() throws away the finished call's scope and runs
+the new one, and keeps going until a body gives back a value instead of another call. Rust's stack
+never grows, however many tail calls there are, and it doesn't matter whether they're to the same
+function or a different one (like two functions that take turns calling each other).
+
+Only calls that the caller still has to do something with (like the `n * fact(n - 1)` in a
+factorial) use up stack, so only those count toward MAX_CALL_DEPTH.
+*/
+fn eval_call(node: &AstNode, callee: &AstNode, args: &[AstNode], env: &Env)
+-> Result<Value, Diagnostic> {
+	let mut tail = start_call(node, callee, args, env, env.depth + 1)?;
+
+	loop {
+		match tail {
+			Tail::Value(val) => return Ok(val),
+			Tail::Call(call) => tail = run_call(call)?,
+		}
+	}
+}
+
+// What the last statement of a function's body (or a call to a builtin) turned out to be.
+enum Tail {
+	Value(Value), // it's done, and this is the value.
+	Call(Call),   // it's a tail call, which still has to be run.
+}
+
+// A call to a closure that's ready to run: the arguments have been evaluated and put in `env`.
+struct Call {
+	func: Rc<Closure>,
+	env:  Env,
+	what: String, // "'f'" or "this function", for error messages.
+	span: Span,   // the call's span, for error messages.
+}
+
+// Figures out what's being called, and evaluates the arguments in `env`. A builtin is called right
+// away, but a closure's body isn't run yet; that's up to the caller. `depth` is the depth that the
+// body will run at: one deeper than `env` for a normal call, but the same for a tail call, since
+// it replaces the function that's making it.
+fn start_call(node: &AstNode, callee: &AstNode, args: &[AstNode], env: &Env, depth: usize)
+-> Result<Tail, Diagnostic> {
+	let (func, what) = match &callee.kind {
+		AstKind::Ident { name } => match env.get(name) {
+			Some(Value::Closure(c)) => (c, format!("'{}'", name)),
+
+			Some(val) if env.get_builtin(name).is_none() =>
+				return Err(Diagnostic::error(format!("'{}' is {}, not a function", name,
+					val.type_name()), callee.span).with_code(diagnostics::NOT_A_FUNCTION)),
+
+			_ => return Ok(Tail::Value(call_builtin(node, callee, name, args, env)?)),
+		},
+
+		_ => match eval(callee, env)? {
+			Value::Closure(c) => (c, "this function".to_string()),
+			val               =>
+				return Err(Diagnostic::error(format!("can't call {}", val.type_name()),
+					callee.span).with_code(diagnostics::NOT_A_FUNCTION)),
+		},
+	};
+
+	check_arg_count(node, &what, func.params.len(), args.len())?;
+
+	if depth > MAX_CALL_DEPTH {
+		return Err(Diagnostic::error("too many function calls at once", node.span)
+			.with_code(diagnostics::STACK_OVERFLOW)
+			.with_note(format!("only {} calls can be going on at once; does {} call itself \
+				forever?", MAX_CALL_DEPTH, what)));
+	}
+
+	// the arguments are evaluated in the caller's environment, but the body runs in a new scope
+	// inside the closure's.
+	let mut call_env = func.env.call_scope(depth);
+
+	for (param, arg) in func.params.iter().zip(args) {
+		call_env.set(param, eval(arg, env)?);
+	}
+
+	Ok(Tail::Call(Call { func, env: call_env, what, span: node.span }))
+}
+
+// Runs every statement of the body but the last one, and gives back what the last one turns out
+// to be, which might be another call.
+fn run_call(call: Call) -> Result<Tail, Diagnostic> {
+	let Call { func, mut env, what, span } = call;
+
+	let no_value = || Diagnostic::error(format!("{} didn't give back a value", what), span)
+		.with_code(diagnostics::NO_VALUE)
+		.with_note("a function gives back the value of its last statement, but loops (and ifs \
+			without an else) don't have one");
+
+	let (last, rest) = func.body.split_last().ok_or_else(no_value)?;
+
+	for stmt in rest {
+		exec(stmt, &mut env)?;
+	}
+
+	match exec_tail(last, &mut env)? {
+		Some(tail) => Ok(tail),
+		None       => Err(no_value()),
+	}
+}
+
+/*
+Runs a statement that's in tail position. That's the last statement of a function's body, and
+inside of that, the last statement of a block, both blocks of an if/else, and the expression of an
+expression statement. A call there isn't made; it's given back as a Tail::Call. Anything else is
+run the usual way. Like exec(), this gives None if the statement has no value.
+*/
+fn exec_tail(stmt: &Stmt, env: &mut Env) -> Result<Option<Tail>, Diagnostic> {
+	match &stmt.kind {
+		StmtKind::Exp { exp } => Ok(Some(eval_tail(exp, env)?)),
+
+		StmtKind::If { cond, then_body, else_body: Some(else_body) } => {
+			if eval_bool(cond, env, "an if's condition")? {
+				exec_tail(then_body, env)
+			} else {
+				exec_tail(else_body, env)
+			}
+		}
+
+		StmtKind::Block { stmts } => match stmts.split_last() {
+			Some((last, rest)) => {
+				for stmt in rest {
+					exec(stmt, env)?;
+				}
+
+				exec_tail(last, env)
+			}
+
+			None => Ok(None),
+		},
+
+		_ => Ok(exec(stmt, env)?.map(Tail::Value)),
+	}
+}
+
+// the expression version of exec_tail(). the arms of a match are in tail position too.
+fn eval_tail(node: &AstNode, env: &Env) -> Result<Tail, Diagnostic> {
+	match &node.kind {
+		AstKind::Call  { callee, args }    => start_call(node, callee, args, env, env.depth),
+		AstKind::Match { scrutinee, arms } => eval_tail(choose_arm(scrutinee, arms, env)?, env),
+		_                                  => Ok(Tail::Value(eval(node, env)?)),
+	}
+}
+
+fn check_arg_count(node: &AstNode, what: &str, arity: usize, given: usize)
+-> Result<(), Diagnostic> {
+	if given == arity {
+		return Ok(());
+	}
+
+	Err(Diagnostic::error(format!("{} takes {} argument{}, but was given {}", what, arity,
+		if arity == 1 { "" } else { "s" }, given), node.span)
+		.with_code(diagnostics::WRONG_ARG_COUNT))
+}
+
+fn call_builtin(node: &AstNode, callee: &AstNode, name: &str, args: &[AstNode], env: &Env)
+-> Result<Value, Diagnostic> {
+	let builtin = env.get_builtin(name).ok_or_else(|| Diagnostic::error(
+		format!("undefined function '{}'", name), callee.span)
+		.with_code(diagnostics::UNDEFINED_NAME))?;
+
+	let what = format!("'{}'", name);
+	check_arg_count(node, &what, builtin.arity, args.len())?;
+
+	match builtin.func {
+		BuiltinFn::Math(func) => {
+			let args = args.iter().map(|arg| eval_num(arg, env, &what))
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok(Value::Num(func(&args)))
+		}
+
+		BuiltinFn::Len => Ok(Value::Num(eval_array(&args[0], env, &what)?.len() as f64)),
+	}
+}
+
+fn eval_array(node: &AstNode, env: &Env, what: &str) -> Result<Rc<[Value]>, Diagnostic> {
+	match eval(node, env)? {
+		Value::Array(a) => Ok(a),
+		val             => Err(Diagnostic::error(
+			format!("{} needs an array, not {}", what, val.type_name()), node.span)
+			.with_code(diagnostics::WRONG_VALUE_TYPE)),
+	}
+}
+
+/*
+The indexes of an array start at 0, so the last one is `len(a) - 1`. Any other number is *out of
+bounds*: there's no value there. Some languages count from the end for negative indexes (in
+Python, `a[-1]` is the last value), but here, that's out of bounds too.
+
+The interpreter can't help but check this, since a Vec panics if you go past the end. But compiled
+code is just reading memory, and memory doesn't know where an array ends. So the compiler has to
+put the check in itself; see lower.rs in the ir crate.
+*/
+fn eval_index(array: &AstNode, index: &AstNode, env: &Env) -> Result<Value, Diagnostic> {
+	let a = eval_array(array, env, "indexing")?;
+	let i = eval_num(index, env, "an index")?;
+
+	if i.fract() != 0.0 {
+		return Err(Diagnostic::error(format!("an index has to be a whole number, not {}", i),
+			index.span).with_code(diagnostics::WRONG_VALUE_TYPE));
+	}
+
+	if i < 0.0 || i >= a.len() as f64 {
+		let note = match a.len() {
+			0   => "the array is empty, so there's nothing to index".to_string(),
+			len => format!("the array has {} value{}, so the indexes go from 0 to {}",
+				len, if len == 1 { "" } else { "s" }, len - 1),
+		};
+
+		return Err(Diagnostic::error(format!("index {} is out of bounds", i), index.span)
+			.with_code(diagnostics::INDEX_OUT_OF_BOUNDS)
+			.with_note(note));
+	}
+
+	Ok(a[i as usize].clone())
+}
+
+fn eval_match(scrutinee: &AstNode, arms: &[MatchArm], env: &Env) -> Result<Value, Diagnostic> {
+	eval(choose_arm(scrutinee, arms, env)?, env)
+}
+
+// gives back the value of the first arm that matches. the type checker makes sure there's always
+// a `_` arm, but this doesn't run the type checker, so it's possible for nothing to match.
+fn choose_arm<'a>(scrutinee: &AstNode, arms: &'a [MatchArm], env: &Env)
+-> Result<&'a AstNode, Diagnostic> {
+	let val = eval_num(scrutinee, env, "a match")?;
+
+	for arm in arms {
+		let matches = match arm.pattern {
+			Pattern::Int(k)   => val == k as f64,
+			Pattern::Wildcard => true,
+		};
+
+		if matches {
+			return Ok(&arm.value);
+		}
+	}
+
+	Err(Diagnostic::error(format!("none of the match's arms match {}", val), scrutinee.span)
+		.with_code(diagnostics::NO_MATCHING_ARM)
+		.with_note("add a `_ => ...` arm at the end to handle every other value"))
+}
+
+/*
+`&&` and `||` *short-circuit*: if the left side decides the answer, the right side isn't evaluated
+at all. `false && x` is false and `true || x` is true, whatever x is. That's not only faster, it
+means the left side can guard the right: in `n != 0 && total / n > 10`, the division only happens
+if n isn't 0. (The IR does the same thing with jumps; see lower.rs in the ir crate.)
+*/
+// a chain of operators, like `a + b * c - d`. it goes up the chain with a loop, so that a long one
+// can't use up the stack (see AstNode::left_chain()). each operator's lhs is the value so far.
+fn eval_chain(node: &AstNode, env: &Env) -> Result<Value, Diagnostic> {
+	let (first, ops) = node.left_chain();
+	let mut val = eval(first, env)?;
+	let mut lhs = first;
+
+	for (node, op, rhs) in ops {
+		val = if op.is_logical() {
+			eval_logical(op, lhs, val, rhs, env)?
+		} else {
+			eval_binary(node, op, val, eval(rhs, env)?)?
+		};
+
+		lhs = node;
+	}
+
+	Ok(val)
+}
+
+// `l` is the value of `lhs`. the rhs is only evaluated if it's needed.
+fn eval_logical(op: BinOp, lhs: &AstNode, l: Value, rhs: &AstNode, env: &Env)
+-> Result<Value, Diagnostic> {
+	let what = format!("'{}'", op);
+	let l = expect_bool(lhs, l, &what)?;
+
+	if l == (op == BinOp::Or) {
+		Ok(Value::Bool(l))
+	} else {
+		Ok(Value::Bool(eval_bool(rhs, env, &what)?))
+	}
+}
+
+/*
+Numbers work with every other operator, and the comparisons give bools. Strings work with two
+kinds:
+
+- `+` joins them together, so `"abc" + "def"` is `"abcdef"`. (Lots of languages do this; Python
+  and JavaScript do, and Java does it with String.)
+- the comparisons compare them alphabetically, sort of. It's really comparing the characters'
+  Unicode numbers, so all the capital letters come before all the lowercase ones: `"Z" < "a"`.
+
+Bools can only be compared with `==` and `!=`. (Is true greater than false? Some languages say
+so, but it's not a question that comes up much.) Arrays can't even do that, since the compiled
+code would have to compare them one value at a time, and it's simpler to write that loop yourself.
+
+Mixing two kinds of value is always an error. Some languages turn the number into a string
+(`"x" + 1` is `"x1"` in JavaScript), but that makes mistakes hard to notice.
+*/
+fn eval_binary(node: &AstNode, op: BinOp, lhs: Value, rhs: Value) -> Result<Value, Diagnostic> {
+	use BinOp::*;
+
+	match (lhs, rhs) {
+		(Value::Num(l), Value::Num(r)) if op.is_comparison() => Ok(compare(op, &l, &r).into()),
+		(Value::Num(l), Value::Num(r)) => Ok(Value::Num(op.eval(l, r))),
+		(Value::Str(l), Value::Str(r)) if op == Add => Ok(Value::Str(l + &r)),
+		(Value::Str(l), Value::Str(r)) if op.is_comparison() => Ok(compare(op, &l, &r).into()),
+		(Value::Bool(l), Value::Bool(r)) if op == Eq || op == Ne => Ok(compare(op, &l, &r).into()),
+
+		(Value::Str(..), Value::Str(..)) =>
+			Err(Diagnostic::error(format!("can't use '{}' on strings", op), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE)
+				.with_note("strings can be joined with '+', or compared, but that's it")),
+
+		(Value::Bool(..), Value::Bool(..)) =>
+			Err(Diagnostic::error(format!("can't use '{}' on bools", op), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE)
+				.with_note("bools can be compared with '==' and '!=', but that's it")),
+
+		(Value::Array(..), Value::Array(..)) =>
+			Err(Diagnostic::error(format!("can't use '{}' on arrays", op), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE)
+				.with_note("arrays can be indexed, like `a[0]`, but no operators work on them")),
+
+		(Value::Closure(..), Value::Closure(..)) =>
+			Err(Diagnostic::error(format!("can't use '{}' on functions", op), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE)
+				.with_note("functions can be called, but no operators work on them")),
+
+		(l, r) => {
+			let mut err = Diagnostic::error(format!("can't use '{}' on {} and {}",
+				op, l.type_name(), r.type_name()), node.span)
+				.with_code(diagnostics::WRONG_VALUE_TYPE);
+
+			if op == Add {
+				err = err.with_note(
+					"'+' can add two numbers or join two strings, but not one of each");
+			}
+
+			Err(err)
+		}
+	}
+}
+
+// `op` must be a comparison. numbers and strings can use all of them, but bools only get here with
+// `==` and `!=`.
+fn compare<T: PartialOrd>(op: BinOp, l: &T, r: &T) -> bool {
+	match op {
+		BinOp::Lt => l <  r,
+		BinOp::Le => l <= r,
+		BinOp::Gt => l >  r,
+		BinOp::Ge => l >= r,
+		BinOp::Eq => l == r,
+		BinOp::Ne => l != r,
+		_         => panic!("compare() called with '{}'", op),
+	}
+}
+
+/*
+The IR and the VM only have numbers, so they still do everything with f64s, bools too. Like in C,
+true is 1 and false is 0, and any number that isn't 0 counts as true. The type checker makes sure
+that can't be noticed: a program that passes it never uses a bool as a number, or the other way
+around.
+*/
+impl BinOp {
+	pub fn eval(&self, lhs: f64, rhs: f64) -> f64 {
+		use BinOp::*;
+
+		match self {
+			// the arithmetic is ast_math's, so the two evaluators can't disagree about it.
+			Add => ast_math::BinOp::Add.eval(lhs, rhs),
+			Sub => ast_math::BinOp::Sub.eval(lhs, rhs),
+			Mul => ast_math::BinOp::Mul.eval(lhs, rhs),
+			Div => ast_math::BinOp::Div.eval(lhs, rhs),
+			Mod => ast_math::BinOp::Mod.eval(lhs, rhs),
+			Lt  => from_bool(lhs <  rhs),
+			Le  => from_bool(lhs <= rhs),
+			Gt  => from_bool(lhs >  rhs),
+			Ge  => from_bool(lhs >= rhs),
+			Eq  => from_bool(lhs == rhs),
+			Ne  => from_bool(lhs != rhs),
+			And => from_bool(to_bool(lhs) && to_bool(rhs)),
+			Or  => from_bool(to_bool(lhs) || to_bool(rhs)),
+		}
+	}
+}
+
+fn from_bool(b: bool) -> f64 {
+	if b { 1.0 } else { 0.0 }
+}
+
+fn to_bool(x: f64) -> bool {
+	x != 0.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::compile;
+
+	// tests run on threads with small stacks, so the million calls to sum_to() in this sample
+	// would overflow right away if each tail call used some.
+	#[test]
+	fn tail_calls_use_no_stack() {
+		let program = compile(include_str!("../samples/tail_calls.calc")).unwrap();
+		let mut env = Env::new();
+		let mut vals = vec![];
+
+		// the values `toylang eval` would print.
+		for stmt in &program.stmts {
+			if let (StmtKind::Exp { .. }, Some(val)) = (&stmt.kind, exec(stmt, &mut env).unwrap()) {
+				vals.push(val.to_string());
+			}
+		}
+
+		assert_eq!(vals, ["500000500000", "true", "true", "0", "2432902008176640000"]);
+	}
+}
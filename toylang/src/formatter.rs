@@ -0,0 +1,728 @@
+use std::collections::HashMap;
+
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The formatter
+// ------------------------------------------------------------------------------------------------
+
+/*
+A code formatter (like rustfmt) reads code and prints it back out in a standard style, so that
+nobody has to argue about where the spaces go. The rules here are:
+
+- one statement per line (`x = 1; y = 2` gets split up).
+- the statements in a block are indented one tab more than the block. The `{` goes at the end of
+  the line it starts on, and the `}` goes on its own line, so `while x { x = x - 1 }` becomes three
+  lines. An empty block is just `{}`. An `else` goes between the `}` and the next `{`, like
+  `} else {`.
+- one space around binary operators and `=`, and after commas.
+- only the parentheses that are needed: `((a + b)) * c` becomes `(a + b) * c`, and `a + (b * c)`
+  becomes `a + b * c`.
+- comments are kept. A comment on its own line stays on its own line, and a comment at the end of a
+  line of code stays at the end of that line.
+- blank lines between statements are kept, but several in a row become just one.
+- a function's body is printed like a block (comments and all), with a `;` between its
+  statements. (Inside parentheses, newlines don't count, so the `;`s are what separate them there.)
+
+The parser throws the comments away, so we can't format just from the AST. Instead, we use the AST
+for the *code*, and lex_lossless() to find the comments and blank lines, and then figure out which
+statement each one belongs to. That gives a tree that knows about both (a FmtProgram). Comments
+and blank lines can also go right before a block's `}`, so blocks (and functions' bodies) have a
+place for those too.
+
+Running the formatter on its own output gives the same output; that's called being *idempotent*,
+and it's an important property for a formatter to have.
+
+The parser gives an error for `include "file"` (see parser.rs), since it has no file to include.
+But the formatter only formats one file, and it shouldn't paste in the others, so it ignores those
+errors. The parser makes an Error node for each include, and those are left exactly as they were.
+*/
+pub fn format(src: &str) -> Result<String, Vec<Diagnostic>> {
+	let (program, errors) = parse_partial(&lex(src)?);
+	let errors = errors.into_iter()
+		.filter(|e| e.code != Some(diagnostics::INCLUDE_FAILED))
+		.collect::<Vec<_>>();
+
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	let (tokens, _) = lex_lossless(src);
+	let chars = src.chars().collect::<Vec<_>>();
+	let tree = attach_trivia(&program, &tokens, &chars);
+
+	let mut out = String::new();
+	tree.print(&chars, &mut out);
+	Ok(out)
+}
+
+// ------------------------------------------------------------------------------------------------
+// The trivia-aware tree
+// ------------------------------------------------------------------------------------------------
+
+// A line that isn't code.
+#[derive(Debug, PartialEq)]
+enum Line {
+	Blank,
+	Comment(String),
+}
+
+struct FmtStmt<'a> {
+	leading:  Vec<Line>,      // the lines just before the statement.
+	stmt:     &'a Stmt,
+	trailing: Option<String>, // a comment at the end of the statement's (last) line.
+	body:     FmtBody,        // for a block or loop.
+	in_func:  bool,           // is it in a function's body? (those get a ';' between statements.)
+}
+
+impl<'a> FmtStmt<'a> {
+	// a statement with no trivia.
+	fn new(stmt: &'a Stmt, in_func: bool) -> Self {
+		FmtStmt { leading: vec![], stmt, trailing: None, body: FmtBody::default(), in_func }
+	}
+}
+
+// the trivia inside a block's (or a function's) braces: a comment after the '{', and the lines
+// just before the '}'.
+#[derive(Default)]
+struct FmtBody {
+	open:    Option<String>,
+	closing: Vec<Line>,
+}
+
+// `stmts` has every statement, even the ones inside blocks and functions, in the order they start
+// in the file. printing goes through the AST in the same order, so it can find each one's trivia.
+struct FmtProgram<'a> {
+	stmts:  Vec<FmtStmt<'a>>,
+	top:    usize,                   // how many of them are at the top level.
+	end:    Vec<Line>,               // lines after the last statement.
+	bodies: HashMap<usize, FmtBody>, // each function's body, by where the function starts.
+}
+
+// what a spot in the code is inside of.
+#[derive(Clone, Copy)]
+enum Scope {
+	Stmt(usize), // stmts[i] (its code, or one of its blocks).
+	Func(usize), // the body of the function that starts there, between its braces.
+}
+
+// Walks through all the tokens (trivia included), and hands each comment and blank line to the
+// statement (or function body) it belongs with.
+fn attach_trivia<'a>(program: &'a Program, tokens: &[Token], chars: &[char]) -> FmtProgram<'a> {
+	let mut flat = vec![];
+	flatten(&program.stmts, false, &mut flat);
+
+	let stmts = flat.iter().map(|&(stmt, _)| stmt).collect::<Vec<_>>();
+	let mut ret = flat.into_iter()
+		.map(|(stmt, in_func)| FmtStmt::new(stmt, in_func))
+		.collect::<Vec<_>>();
+
+	// where a comment inside statement i's code goes: above statement above[i]. that's usually i
+	// itself, but the parts of an if (its blocks, and an `else if`) are printed in the middle of
+	// the if, so the comments in an `else if`'s condition go above the first `if` instead. the
+	// same goes for a comment after the last '}': it's the whole if's trailing comment.
+	let mut above = (0 .. stmts.len()).collect::<Vec<_>>();
+
+	for (i, stmt) in stmts.iter().enumerate() {
+		if let StmtKind::If { then_body, else_body, .. } = &stmt.kind {
+			for part in std::iter::once(then_body).chain(else_body) {
+				let j = i + stmts[i ..].iter().position(|s| std::ptr::eq(*s, &**part))
+					.expect("flatten() puts the parts of an if after it");
+				above[j] = above[i];
+			}
+		}
+	}
+
+	// the blocks and loops, by where their '}' ends.
+	let closes = stmts.iter().enumerate()
+		.filter(|(_, stmt)| has_block(stmt))
+		.map(|(i, stmt)| (stmt.span.end, i))
+		.collect::<HashMap<_, _>>();
+
+	// the functions, by where their body's '{' starts. each one gives where the function starts
+	// (which is how its body is found when printing) and where its '}' ends.
+	let mut bodies = HashMap::new();
+	let mut func_opens = HashMap::new();
+
+	for func in stmts.iter().flat_map(|stmt| funcs_in(stmt)) {
+		let (open, close) = braces(func, tokens);
+		bodies.insert(func.span.start, FmtBody::default());
+		func_opens.insert(open.start, (func.span.start, close.end));
+	}
+
+	let mut pending      = vec![];       // lines waiting for the next statement to start.
+	let mut next         = 0;            // the next statement that hasn't started yet.
+	let mut scopes       = vec![];       // what we're in, innermost last, and where each ends.
+	let mut line_scope   = None::<Scope>; // what the code on this line belongs to, if any.
+	let mut newlines     = 0;            // how many newlines since the last code or comment.
+	let mut seen_content = false;        // so blank lines at the start of the file are dropped.
+
+	for tok in tokens {
+		while scopes.last().is_some_and(|&(_, end)| end <= tok.span.start) {
+			scopes.pop();
+		}
+
+		// the statement whose own code it's inside of, if any. (being in one of its blocks or
+		// functions doesn't count.)
+		let inside = match scopes.last() {
+			Some(&(Scope::Stmt(i), _)) if tok.span.start < code_end(stmts[i]) => Some(i),
+			_                                                                 => None,
+		};
+
+		match tok.kind {
+			// a newline inside a statement's code (like between the arms of a match) doesn't
+			// end a line as far as trivia are concerned, since the statement is printed its own
+			// way.
+			TokenKind::Newline if inside.is_some() => {}
+
+			TokenKind::Newline => {
+				newlines += 1;
+				line_scope = None;
+
+				// the first newline just ends the line; the second one means a blank line.
+				if newlines == 2 && seen_content {
+					pending.push(Line::Blank);
+				}
+			}
+
+			TokenKind::Whitespace | TokenKind::Eof => {}
+
+			TokenKind::Comment => {
+				let text = chars[tok.span.start .. tok.span.end].iter().collect::<String>();
+				let text = text.trim_end().to_string();
+
+				match (inside, line_scope) {
+					// a comment *inside* a statement (or a loop's condition) that was split
+					// across lines. the statement will be printed on one line, so the comment
+					// goes above it.
+					(Some(i), _) => ret[above[i]].leading.push(Line::Comment(text)),
+
+					// on the same line as a '{' that hasn't been closed yet.
+					(None, Some(Scope::Stmt(i)))
+						if has_block(stmts[i]) && tok.span.start < stmts[i].span.end =>
+						ret[i].body.open = Some(text),
+					(None, Some(Scope::Func(f))) => bodies.get_mut(&f).unwrap().open = Some(text),
+
+					(None, Some(Scope::Stmt(i))) => ret[above[i]].trailing = Some(text),
+					(None, None)                 => pending.push(Line::Comment(text)),
+				}
+
+				newlines = 0;
+				seen_content = true;
+			}
+
+			// a code token. if it's the first token of the next statement, that statement
+			// gets all the pending lines. if it's the '}' of a block or a function, that gets
+			// them.
+			_ => {
+				let closes_func = match scopes.last() {
+					Some(&(Scope::Func(f), end)) if end == tok.span.end => Some(f),
+					_                                                   => None,
+				};
+
+				if next < stmts.len() && tok.span.start == stmts[next].span.start {
+					ret[next].leading = std::mem::take(&mut pending);
+					scopes.push((Scope::Stmt(next), stmts[next].span.end));
+					line_scope = Some(Scope::Stmt(next));
+					next += 1;
+				} else if let Some(&(f, end)) = func_opens.get(&tok.span.start) {
+					scopes.push((Scope::Func(f), end));
+					line_scope = Some(Scope::Func(f));
+				} else if let Some(f) = closes_func {
+					bodies.get_mut(&f).unwrap().closing = std::mem::take(&mut pending);
+					scopes.pop();
+					line_scope = scopes.last().map(|&(scope, _)| scope);
+				} else if let (TokenKind::RBrace, Some(&i)) =
+					(&tok.kind, closes.get(&tok.span.end)) {
+					ret[i].body.closing = std::mem::take(&mut pending);
+					line_scope = Some(Scope::Stmt(i));
+				} else if tok.kind != TokenKind::Semi {
+					line_scope = scopes.last().map(|&(scope, _)| scope);
+				}
+
+				// a ';' is dropped, so a line with only a ';' on it is like a blank one.
+				if tok.kind != TokenKind::Semi {
+					newlines = 0;
+					seen_content = true;
+				}
+			}
+		}
+	}
+
+	// a blank line at the very end of the file isn't worth keeping.
+	if pending.last() == Some(&Line::Blank) {
+		pending.pop();
+	}
+
+	FmtProgram { stmts: ret, top: program.stmts.len(), end: pending, bodies }
+}
+
+// every statement, with the ones inside each block (and each function) right after it, and
+// whether it's in a function. the parts of an if (its blocks, and the if after an `else if`) are
+// statements too, so they get their own trivia.
+fn flatten<'a>(stmts: impl IntoIterator<Item = &'a Stmt>, in_func: bool,
+out: &mut Vec<(&'a Stmt, bool)>) {
+	for stmt in stmts {
+		out.push((stmt, in_func));
+
+		for func in funcs_in(stmt) {
+			if let AstKind::Func { body, .. } = &func.kind {
+				flatten(body.iter(), true, out);
+			}
+		}
+
+		if let StmtKind::If { then_body, else_body, .. } = &stmt.kind {
+			flatten(std::iter::once(&**then_body).chain(else_body.as_deref()), in_func, out);
+		} else if let Some(body) = block_of(stmt) {
+			flatten(body, in_func, out);
+		}
+	}
+}
+
+// the functions in the statement's own code (not the ones in its blocks), in the order they
+// start.
+fn funcs_in(stmt: &Stmt) -> Vec<&AstNode> {
+	let mut ret = vec![];
+
+	match &stmt.kind {
+		StmtKind::Let    { value, .. } |
+		StmtKind::Assign { value, .. } => find_funcs(value, &mut ret),
+		StmtKind::Exp    { exp }       => find_funcs(exp, &mut ret),
+		StmtKind::While  { cond, .. } |
+		StmtKind::If     { cond, .. }  => find_funcs(cond, &mut ret),
+		StmtKind::Block { .. } | StmtKind::Break | StmtKind::Continue => {}
+	}
+
+	ret
+}
+
+// the functions in the expression, but not the ones inside their bodies; those are in the body's
+// statements.
+fn find_funcs<'a>(node: &'a AstNode, out: &mut Vec<&'a AstNode>) {
+	use AstKind::*;
+
+	match &node.kind {
+		Func { .. } => out.push(node),
+
+		IntLit { .. } | FloatLit { .. } | StrLit { .. } | BoolLit { .. } | Ident { .. } |
+		Error => {}
+		Negate { lhs } | Not { lhs } => find_funcs(lhs, out),
+
+		Binary { .. } => {
+			let (first, ops) = node.left_chain();
+			find_funcs(first, out);
+			ops.iter().for_each(|&(_, _, rhs)| find_funcs(rhs, out));
+		}
+
+		Call { callee, args } => {
+			find_funcs(callee, out);
+			args.iter().for_each(|arg| find_funcs(arg, out));
+		}
+
+		Array { elems } => elems.iter().for_each(|elem| find_funcs(elem, out)),
+
+		Index { array, index } => {
+			find_funcs(array, out);
+			find_funcs(index, out);
+		}
+
+		Match { scrutinee, arms } => {
+			find_funcs(scrutinee, out);
+			arms.iter().for_each(|arm| find_funcs(&arm.value, out));
+		}
+	}
+}
+
+// the spans of a function's '{' and '}'. its span can have parentheses around it, but nothing
+// else, so they're the first '{' and the last '}' in it.
+fn braces(func: &AstNode, tokens: &[Token]) -> (Span, Span) {
+	let start = tokens.partition_point(|t| t.span.start < func.span.start);
+	let end = tokens.partition_point(|t| t.span.start < func.span.end);
+	let tokens = &tokens[start .. end];
+
+	let open = tokens.iter().find(|t| t.kind == TokenKind::LBrace);
+	let close = tokens.iter().rev().find(|t| t.kind == TokenKind::RBrace);
+	let (open, close) = open.zip(close).expect("format() only formats functions that parsed");
+	(open.span, close.span)
+}
+
+// the statements inside a block or loop.
+fn block_of(stmt: &Stmt) -> Option<&[Stmt]> {
+	match &stmt.kind {
+		StmtKind::While { body, .. } => Some(body),
+		StmtKind::Block { stmts }    => Some(stmts),
+		_                            => None,
+	}
+}
+
+fn has_block(stmt: &Stmt) -> bool {
+	block_of(stmt).is_some()
+}
+
+// where the statement's own code ends: for a loop or an if, that's the end of the condition, and
+// for a block, it's before the '{'. (the statements inside have their own comments.)
+fn code_end(stmt: &Stmt) -> usize {
+	match &stmt.kind {
+		StmtKind::While { cond, .. } |
+		StmtKind::If    { cond, .. } => cond.span.end,
+		StmtKind::Block { .. }       => stmt.span.start,
+		_                            => stmt.span.end,
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Printing
+// ------------------------------------------------------------------------------------------------
+
+impl<'a> FmtProgram<'a> {
+	fn print(&self, chars: &[char], out: &mut String) {
+		let mut next = 0;
+		self.print_stmts(self.top, 0, &mut next, chars, out);
+		print_lines(trim_blanks(&self.end, self.top == 0), 0, out);
+	}
+
+	// prints `count` statements, starting with stmts[*next]. the statements inside a block come
+	// right after it in `stmts`, so printing the block moves `next` past them too.
+	fn print_stmts(&self, count: usize, indent: usize, next: &mut usize, chars: &[char],
+	out: &mut String) {
+		for i in 0 .. count {
+			let stmt = &self.stmts[*next];
+			*next += 1;
+
+			// blank lines at the start of a block (or the file) are dropped.
+			let leading = match i {
+				0 => trim_blanks(&stmt.leading, true),
+				_ => &stmt.leading[..],
+			};
+
+			print_lines(leading, indent, out);
+			out.push_str(&"\t".repeat(indent));
+			self.print_stmt(stmt, indent, next, chars, out);
+
+			if stmt.in_func && i + 1 < count {
+				out.push(';');
+			}
+
+			if let Some(comment) = &stmt.trailing {
+				out.push(' ');
+				out.push_str(comment);
+			}
+
+			out.push('\n');
+		}
+	}
+
+	fn print_stmt(&self, stmt: &FmtStmt, indent: usize, next: &mut usize, chars: &[char],
+	out: &mut String) {
+		use StmtKind::*;
+
+		match &stmt.stmt.kind {
+			Let { name, value, .. } => {
+				out.push_str(&format!("let {} = ", name));
+				self.print_exp(value, indent, next, chars, out);
+			}
+
+			Assign { name, value, .. } => {
+				out.push_str(&format!("{} = ", name));
+				self.print_exp(value, indent, next, chars, out);
+			}
+
+			Exp { exp } => self.print_exp(exp, indent, next, chars, out),
+			Break       => out.push_str("break"),
+			Continue    => out.push_str("continue"),
+
+			While { cond, body } => {
+				out.push_str("while ");
+				self.print_exp(cond, indent, next, chars, out);
+				out.push(' ');
+				self.print_block(body.len(), &stmt.body, indent, next, chars, out);
+			}
+
+			If { cond, else_body, .. } => {
+				out.push_str("if ");
+				self.print_exp(cond, indent, next, chars, out);
+				out.push(' ');
+				self.print_if_part(indent, next, chars, out);
+
+				if else_body.is_some() {
+					out.push_str(" else ");
+					self.print_if_part(indent, next, chars, out);
+				}
+			}
+
+			Block { stmts } => self.print_block(stmts.len(), &stmt.body, indent, next, chars, out),
+		}
+	}
+
+	// prints stmts[*next], which is a block or the if after an `else`. (a comment after the last
+	// '}' went to the whole if, so print_stmts() prints it.)
+	fn print_if_part(&self, indent: usize, next: &mut usize, chars: &[char], out: &mut String) {
+		let part = &self.stmts[*next];
+		*next += 1;
+		self.print_stmt(part, indent, next, chars, out);
+	}
+
+	// prints a block (or a function's body) of `count` statements, starting with stmts[*next].
+	fn print_block(&self, count: usize, body: &FmtBody, indent: usize, next: &mut usize,
+	chars: &[char], out: &mut String) {
+		// a blank line between the last statement and a comment is kept, like anywhere else.
+		let closing = trim_blanks(&body.closing, count == 0);
+
+		if count == 0 && body.open.is_none() && closing.is_empty() {
+			out.push_str("{}");
+			return;
+		}
+
+		out.push('{');
+
+		if let Some(comment) = &body.open {
+			out.push(' ');
+			out.push_str(comment);
+		}
+
+		out.push('\n');
+		self.print_stmts(count, indent + 1, next, chars, out);
+		print_lines(closing, indent + 1, out);
+		out.push_str(&"\t".repeat(indent));
+		out.push('}');
+	}
+
+	// `indent` is how far the statement this is in is indented, for the arms of a match.
+	fn print_exp(&self, node: &AstNode, indent: usize, next: &mut usize, chars: &[char],
+	out: &mut String) {
+		use AstKind::*;
+
+		match &node.kind {
+			// literals are printed exactly as they were written, so `2.50` stays `2.50`, and
+			// escape sequences stay the way they were. the parser makes the span of a
+			// parenthesized expression include the parens (and any comments inside them), so lex
+			// the span again to find the literal's own token.
+			IntLit { .. } | FloatLit { .. } | StrLit { .. } => {
+				let text = &chars[node.span.start .. node.span.end];
+				let (tokens, _) = lex_lossless(&text.iter().collect::<String>());
+				let tok = tokens.iter()
+					.find(|t| !t.kind.is_trivia() && !matches!(t.kind, TokenKind::LParen |
+						TokenKind::RParen | TokenKind::Newline))
+					.expect("a literal's span has to have the literal in it");
+				out.extend(&text[tok.span.start .. tok.span.end]);
+			}
+
+			// format() only formats code that parsed (besides includes), but if it ever got
+			// broken code, the broken part is left exactly as it was.
+			Error => {
+				let text = chars[node.span.start .. node.span.end].iter().collect::<String>();
+				out.push_str(text.trim());
+			}
+
+			BoolLit { val } => out.push_str(if *val { "true" } else { "false" }),
+			Ident   { name } => out.push_str(name),
+
+			// -(a + b) needs its parens, and -(-a) is easier to read than --a.
+			Negate { lhs } => {
+				out.push('-');
+				let parens = matches!(lhs.kind, Binary { .. } | Negate { .. });
+				self.print_operand(lhs, parens, indent, next, chars, out);
+			}
+
+			Not { lhs } => {
+				out.push('!');
+				let parens = matches!(lhs.kind, Binary { .. });
+				self.print_operand(lhs, parens, indent, next, chars, out);
+			}
+
+			// this is where the parentheses get normalized. the lhs only needs them if its
+			// operator is *lower* precedence than this one, like in (a + b) * c. the rhs needs
+			// them if it's lower *or the same*, because these operators are left-associative:
+			// a - (b - c) is not the same as a - b - c.
+			//
+			// a long chain is printed with a loop (see AstNode::left_chain()). each operator's
+			// lhs is everything before it in the chain, so all of the lhs's '('s go at the start.
+			Binary { .. } => {
+				let (first, ops) = node.left_chain();
+				let lhs_parens = (0 .. ops.len())
+					.map(|i| i > 0 && op_precedence(ops[i - 1].1) < op_precedence(ops[i].1))
+					.collect::<Vec<_>>();
+
+				out.push_str(&"(".repeat(lhs_parens.iter().filter(|&&p| p).count()));
+				self.print_exp(first, indent, next, chars, out);
+
+				for (&(_, op, rhs), lhs_parens) in ops.iter().zip(lhs_parens) {
+					if lhs_parens {
+						out.push(')');
+					}
+
+					let rhs_parens = binary_precedence(rhs).is_some_and(|p| p <= op_precedence(op));
+					out.push_str(&format!(" {} ", op));
+					self.print_operand(rhs, rhs_parens, indent, next, chars, out);
+				}
+			}
+
+			Call { callee, args } => {
+				// `-f(x)` means -(f(x)), so calling anything but a simple expression needs
+				// parens.
+				let needs_parens =
+					matches!(callee.kind, Negate { .. } | Not { .. } | Binary { .. });
+				self.print_operand(callee, needs_parens, indent, next, chars, out);
+				out.push('(');
+				self.print_list(args, indent, next, chars, out);
+				out.push(')');
+			}
+
+			Array { elems } => {
+				out.push('[');
+				self.print_list(elems, indent, next, chars, out);
+				out.push(']');
+			}
+
+			// the same goes for indexing: `-a[i]` means -(a[i]).
+			Index { array, index } => {
+				let needs_parens =
+					matches!(array.kind, Negate { .. } | Not { .. } | Binary { .. });
+				self.print_operand(array, needs_parens, indent, next, chars, out);
+				out.push('[');
+				self.print_exp(index, indent, next, chars, out);
+				out.push(']');
+			}
+
+			// every arm gets its own line, with a comma after it. the comma is optional on its
+			// own line, but inside parentheses, newlines don't count, so the commas are what
+			// separate the arms there.
+			Match { scrutinee, arms } => {
+				out.push_str("match ");
+				self.print_exp(scrutinee, indent, next, chars, out);
+				out.push_str(" {\n");
+
+				for arm in arms {
+					let pattern = chars[arm.pattern_span.start .. arm.pattern_span.end].iter()
+						.filter(|c| !c.is_whitespace())
+						.collect::<String>();
+
+					out.push_str(&"\t".repeat(indent + 1));
+					out.push_str(&format!("{} => ", pattern));
+					self.print_exp(&arm.value, indent + 1, next, chars, out);
+					out.push_str(",\n");
+				}
+
+				out.push_str(&"\t".repeat(indent));
+				out.push('}');
+			}
+
+			// the body's statements come next in `stmts`, just like a block's.
+			Func { params, body } => {
+				let params = params.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+				out.push_str(&format!("fn({}) ", params.join(", ")));
+				let trivia = &self.bodies[&node.span.start];
+				self.print_block(body.len(), trivia, indent, next, chars, out);
+			}
+		}
+	}
+
+	// the arguments of a call, or the values of an array.
+	fn print_list(&self, exps: &[AstNode], indent: usize, next: &mut usize, chars: &[char],
+	out: &mut String) {
+
+		for (i, exp) in exps.iter().enumerate() {
+			if i > 0 {
+				out.push_str(", ");
+			}
+
+			self.print_exp(exp, indent, next, chars, out);
+		}
+	}
+
+	fn print_operand(&self, node: &AstNode, parens: bool, indent: usize, next: &mut usize,
+	chars: &[char], out: &mut String) {
+		if parens {
+			out.push('(');
+			self.print_exp(node, indent, next, chars, out);
+			out.push(')');
+		} else {
+			self.print_exp(node, indent, next, chars, out);
+		}
+	}
+}
+
+fn print_lines(lines: &[Line], indent: usize, out: &mut String) {
+	for line in lines {
+		if let Line::Comment(text) = line {
+			out.push_str(&"\t".repeat(indent));
+			out.push_str(text);
+		}
+
+		out.push('\n');
+	}
+}
+
+// without the blank lines at the end (and at the start, if `start` is true).
+fn trim_blanks(lines: &[Line], start: bool) -> &[Line] {
+	let start = match start {
+		true  => lines.iter().position(|l| *l != Line::Blank).unwrap_or(lines.len()),
+		false => 0,
+	};
+
+	let end = lines.iter().rposition(|l| *l != Line::Blank).map_or(start, |i| i + 1);
+	&lines[start .. end]
+}
+
+// the same precedence levels as the parser: higher numbers bind tighter.
+fn op_precedence(op: BinOp) -> u8 {
+	use BinOp::*;
+
+	match op {
+		Or                          => 1,
+		And                         => 2,
+		Lt | Le | Gt | Ge | Eq | Ne => 3,
+		Add | Sub                   => 4,
+		Mul | Div | Mod             => 5,
+	}
+}
+
+// the precedence of the node's operator, if it's a binary operation.
+fn binary_precedence(node: &AstNode) -> Option<u8> {
+	match &node.kind {
+		AstKind::Binary { op, .. } => Some(op_precedence(*op)),
+		_                          => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// formatting the output again has to give the same thing back.
+	fn check(src: &str, expected: &str) {
+		let out = format(src).unwrap();
+		assert_eq!(out, expected);
+		assert_eq!(format(&out).unwrap(), out, "not idempotent");
+	}
+
+	#[test]
+	fn comments_stay_in_function_bodies() {
+		check(
+			"let add = fn(a, b) { # the open\n\t# above\n\tlet s = a + b # after\n\n\
+				\ts\n\t# end\n} # out\n",
+			"let add = fn(a, b) { # the open\n\t# above\n\tlet s = a + b; # after\n\n\
+				\ts\n\t# end\n} # out\n",
+		);
+
+		check("let f = fn() {\n\t# nothing yet\n}\n", "let f = fn() {\n\t# nothing yet\n}\n");
+		check("x = f(fn(x) {\n\tx # one\n}, 2) # two\n", "x = f(fn(x) {\n\tx # one\n}, 2) # two\n");
+	}
+
+	// the ';' goes before the comment after an if, even when the if is in a function.
+	#[test]
+	fn comments_after_ifs_in_function_bodies() {
+		check(
+			"let f = fn() {\n\tif a { 1 } else { 2 } # which\n\t3\n}\n",
+			"let f = fn() {\n\tif a {\n\t\t1\n\t} else {\n\t\t2\n\t}; # which\n\t3\n}\n",
+		);
+	}
+
+	// a comment in the middle of a statement's code still goes above the statement.
+	#[test]
+	fn comments_inside_code_go_above() {
+		check("let r = f(1,\n\t# two\n\t2)\n", "# two\nlet r = f(1, 2)\n");
+	}
+}
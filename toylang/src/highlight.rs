@@ -0,0 +1,80 @@
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Syntax highlighting
+// ------------------------------------------------------------------------------------------------
+
+/*
+A syntax highlighter is just a lexer with a different output. Every token gets wrapped in a
+<span> with a CSS class that says what kind of token it is, and the web page's stylesheet decides
+what color each class is. For example, `let x = 5 # five` becomes:
+
+	<span class="tl-keyword">let</span> <span class="tl-ident">x</span> <span class="tl-op">=</span>
+	<span class="tl-number">5</span> <span class="tl-comment"># five</span>
+
+(all on one line). Whitespace isn't wrapped; it's output as-is, so put the result inside a
+<pre> to keep the spaces and newlines. highlight.css has some colors to start with.
+
+This uses lex_lossless(), so it never fails: characters that aren't valid tokens are still shown,
+with the "tl-error" class.
+*/
+pub fn highlight(src: &str) -> String {
+	let chars = src.chars().collect::<Vec<_>>();
+	let (tokens, _) = lex_lossless(src);
+	let mut ret = String::new();
+
+	for tok in &tokens {
+		let text = chars[tok.span.start .. tok.span.end].iter().collect::<String>();
+
+		match css_class(&tok.kind) {
+			Some(class) => {
+				ret.push_str(&format!("<span class=\"{}\">", class));
+				escape_html(&text, &mut ret);
+				ret.push_str("</span>");
+			}
+
+			None => escape_html(&text, &mut ret),
+		}
+	}
+
+	ret
+}
+
+// None means "don't wrap it in a span."
+fn css_class(kind: &TokenKind) -> Option<&'static str> {
+	use TokenKind::*;
+
+	match kind {
+		Let | True | False | Include | While |
+		Break | Continue | Match | If | Else |
+		Fn                                    => Some("tl-keyword"),
+		Id(..)                                => Some("tl-ident"),
+		IntLit(..) | FloatLit(..)             => Some("tl-number"),
+		StrLit(..)                            => Some("tl-string"),
+		Plus | Minus | Times | Divide | Modulo |
+		Assign | Less | LessEq | Greater |
+		GreaterEq | EqEq | NotEq | Not |
+		AndAnd | OrOr                         => Some("tl-op"),
+		LParen | RParen | LBrace | RBrace |
+		LBracket | RBracket | Comma | Semi |
+		FatArrow                              => Some("tl-punct"),
+		Comment                               => Some("tl-comment"),
+		Invalid                               => Some("tl-error"),
+		Whitespace | Newline | Eof            => None,
+	}
+}
+
+// Some characters mean something in HTML, so they have to be written differently. Otherwise,
+// `x < y` in a comment would start an HTML tag!
+fn escape_html(text: &str, out: &mut String) {
+	for c in text.chars() {
+		match c {
+			'&'  => out.push_str("&amp;"),
+			'<'  => out.push_str("&lt;"),
+			'>'  => out.push_str("&gt;"),
+			'"'  => out.push_str("&quot;"),
+			'\'' => out.push_str("&#39;"),
+			c    => out.push(c),
+		}
+	}
+}
@@ -0,0 +1,167 @@
+use std::path::{ Path, PathBuf };
+
+use diagnostics::{ Diagnostic, FileId, SourceManager, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Including other files
+// ------------------------------------------------------------------------------------------------
+
+/*
+`include "helpers.calc"` works just like C's #include: it's as if the whole text of helpers.calc
+were pasted in where the `include` is. Any variables it sets are set for the rest of the program.
+
+Like C, this happens *before* parsing, on the tokens. Each file is lexed on its own, and then each
+`include "file"` (two tokens, at the start of a statement) is replaced by that file's tokens. The
+parser never knows there was more than one file; it just gets one long list of tokens.
+
+But then, how does an error in helpers.calc say that it's in helpers.calc? That's what the
+SourceManager (in the diagnostics crate) is for. Every file gets its own range of positions, and
+each file's tokens are moved into its range, so every span in the program - in the tokens, the
+AST, and any errors - says which file it came from. Rendering the errors with the SourceManager
+shows the right file, and where it was included from:
+
+	error[E0200]: undefined variable 'raduis'
+	 --> helpers.calc:2:7
+	  |
+	2 | a = raduis * 2
+	  |     ^^^^^^
+	  = note: in the file included from main.calc:1:1
+
+File names are relative to the file that has the `include`. A file can be included more than
+once (it's just pasted in again), but it can't include itself, even through other files.
+*/
+
+// Compiles the file, and every file that it includes. The errors' spans are global positions in
+// `sources`, so show them with sources.render_all().
+pub fn compile_file(sources: &mut SourceManager, file: FileId) -> Result<Ast, Vec<Diagnostic>> {
+	let (tokens, errors) = lex_with_includes(sources, file);
+
+	if errors.is_empty() {
+		parse(&tokens)
+	} else {
+		Err(errors)
+	}
+}
+
+// Lexes the file, replacing each include with the included file's tokens, which are loaded into
+// `sources`. Like lex(), the tokens don't include trivia, and end with one Eof. Like
+// lex_lossless(), you get all the tokens it could make, even if there were errors.
+pub fn lex_with_includes(sources: &mut SourceManager, file: FileId)
+-> (Vec<Token>, Vec<Diagnostic>) {
+	let mut tokens = vec![];
+	let mut errors = vec![];
+	let mut includer = Includer { sources, stack: vec![] };
+
+	// the file the user gave doesn't have to be a real file (it could be typed into a REPL), so
+	// it's only on the stack if it is one.
+	let path = PathBuf::from(includer.sources.file(file).name());
+	includer.stack.extend(path.canonicalize().ok());
+
+	let eof = includer.lex_file(file, &mut tokens, &mut errors);
+	tokens.push(eof);
+	(tokens, errors)
+}
+
+struct Includer<'s> {
+	sources: &'s mut SourceManager,
+
+	// the files that are being included right now, so that a file including itself can be caught.
+	stack: Vec<PathBuf>,
+}
+
+impl Includer<'_> {
+	// adds the file's tokens to `tokens` (and any included files' tokens) and gives back its Eof.
+	fn lex_file(&mut self, file: FileId, tokens: &mut Vec<Token>, errors: &mut Vec<Diagnostic>)
+	-> Token {
+		let start = self.sources.start(file);
+		let (file_tokens, file_errors) = lex_lossless(self.sources.file(file).text());
+
+		// move everything into this file's range of positions.
+		let shift = |span: Span| Span::new(span.start + start, span.end + start);
+
+		let mut file_tokens = file_tokens.into_iter()
+			.filter(|t| !t.kind.is_trivia())
+			.map(|t| Token::new(shift(t.span), t.kind))
+			.collect::<Vec<_>>();
+
+		errors.extend(file_errors.into_iter().map(|e| {
+			let span = e.span.map(shift);
+			Diagnostic { span, ..e }
+		}));
+
+		let eof = file_tokens.pop().expect("lex_lossless() always gives an Eof token");
+		let mut i = 0;
+
+		while i < file_tokens.len() {
+			match self.include_at(&file_tokens, i) {
+				Some((name, span)) => {
+					self.include(file, &name, span, tokens, errors);
+					i += 2;
+				}
+
+				None => {
+					tokens.push(file_tokens[i].clone());
+					i += 1;
+				}
+			}
+		}
+
+		eof
+	}
+
+	// if tokens[i] starts an `include "file"` that's a whole statement, gives the file name and the
+	// span of the whole thing. anything else is left for the parser, which will complain about it.
+	fn include_at(&self, tokens: &[Token], i: usize) -> Option<(String, Span)> {
+		let is_sep = |t: &Token| matches!(t.kind, TokenKind::Newline | TokenKind::Semi);
+		let stmt_start = i == 0 || is_sep(&tokens[i - 1]);
+		let stmt_end = tokens.get(i + 2).is_none_or(is_sep);
+
+		match (&tokens[i].kind, tokens.get(i + 1).map(|t| &t.kind)) {
+			(TokenKind::Include, Some(TokenKind::StrLit(name))) if stmt_start && stmt_end =>
+				Some((name.clone(), tokens[i].span.to(tokens[i + 1].span))),
+			_ => None,
+		}
+	}
+
+	fn include(&mut self, from: FileId, name: &str, span: Span, tokens: &mut Vec<Token>,
+	errors: &mut Vec<Diagnostic>) {
+		// relative to the folder that `from` is in.
+		let dir = Path::new(self.sources.file(from).name()).parent().unwrap_or(Path::new(""));
+		let path = dir.join(name);
+
+		let canonical = match path.canonicalize() {
+			Ok(canonical) => canonical,
+			Err(e) => {
+				errors.push(include_failed(&path, span, &e));
+				return;
+			}
+		};
+
+		if self.stack.contains(&canonical) {
+			errors.push(Diagnostic::error(format!("'{}' includes itself", path.display()), span)
+				.with_code(diagnostics::RECURSIVE_INCLUDE)
+				.with_note("including it here would never end"));
+			return;
+		}
+
+		let file = match self.sources.load(&path) {
+			Ok(file) => file,
+			Err(e)   => {
+				errors.push(include_failed(&path, span, &e));
+				return;
+			}
+		};
+
+		self.sources.set_included_at(file, span);
+		self.stack.push(canonical);
+		self.lex_file(file, tokens, errors);
+		self.stack.pop();
+	}
+}
+
+fn include_failed(path: &Path, span: Span, e: &std::io::Error) -> Diagnostic {
+	Diagnostic::error(format!("couldn't include '{}': {}", path.display(), e), span)
+		.with_code(diagnostics::INCLUDE_FAILED)
+}
@@ -0,0 +1,507 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, Span };
+use lexing_toy::{ is_ident_cont, is_ident_start, lex_ident, lex_int, LexError };
+
+// This is the lexer from lexing_toy, plus floats, strings, and the rest of toylang's tokens.
+// Identifiers and integers are lexed by lexing_toy's own lex_ident() and lex_int(), and their
+// errors come from lexing_toy too. Go read that one first; this one only has comments on the
+// parts that are new.
+
+// ------------------------------------------------------------------------------------------------
+// Token type
+// ------------------------------------------------------------------------------------------------
+
+/*
+Token grammar:
+
+LParen:    '('
+RParen:    ')'
+LBrace:    '{'
+RBrace:    '}'
+LBracket:  '['
+RBracket:  ']'
+Comma:     ','
+Plus:      '+'
+Minus:     '-'
+Times:     '*'
+Divide:    '/'
+Modulo:    '%'
+Assign:    '='
+Semi:      ';'
+Less:      '<'
+LessEq:    '<='
+Greater:   '>'
+GreaterEq: '>='
+EqEq:      '=='
+NotEq:     '!='
+Not:       '!'
+AndAnd:    '&&'
+OrOr:      '||'
+FatArrow:  '=>'
+Newline:   '\n'
+Keyword:   'let' | 'true' | 'false' | 'include' | 'while' | 'break' | 'continue' | 'match' |
+           'if' | 'else' | 'fn'
+Id:        IdStart IdCont*       (but not a Keyword)
+IdStart:   <alphabetic> | '_'
+IdCont:    IdStart | Digit
+IntLit:    Digit+
+FloatLit:  Digit+ '.' Digit+
+StrLit:    '"' (Escape | <anything but '"', '\' or '\n'>)* '"'
+Escape:    '\' ('n' | 't' | '"' | '\')
+Token:     LParen | RParen | LBrace | RBrace | LBracket | RBracket | Comma | Plus | Minus |
+           Times | Divide | Modulo | Assign | Semi | Less | LessEq | Greater | GreaterEq | EqEq |
+           NotEq | Not | AndAnd | OrOr | FatArrow | Newline | Keyword | Id | IntLit | FloatLit |
+           StrLit
+
+Whitespace: (' ' | '\t' | '\r')+
+Comment:    '#' <anything but '\n'>*
+Trivia:     Whitespace | Comment
+Program:    (Trivia* Token)* Trivia* Eof
+
+Some tokens are two characters, like '<='. When the lexer sees '<', it has to peek at the next
+character to know which token it is. This is called *maximal munch*: always make the longest token
+you can, so `<=` is one LessEq token, not Less and then Assign. '=' is the start of three different
+tokens: '=', '==', and '=>'.
+
+Unlike lexing_toy, newlines end statements, so they are tokens. But a newline *inside* parentheses
+is treated as whitespace, so that you can split a long expression across lines, like:
+
+	total = (price * quantity
+		+ shipping)
+
+Python does the exact same thing. The square brackets of an array work the same way, so a long
+array can have one value per line. Braces are different: they hold statements (see the parser),
+so a newline inside braces is still a Newline token. (But braces inside parentheses are still
+inside parentheses, so there, newlines are whitespace.)
+
+String literals can't go past the end of the line. To put a newline (or a '"') in a string, use an
+*escape sequence*: a '\' and a letter that stands for the character you can't type there. The
+token's value is the string with the escape sequences already turned into the characters they
+stand for, so `"a\tb"` is a StrLit with 3 characters in it.
+*/
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenKind {
+	Eof,
+	LParen,
+	RParen,
+	LBrace,
+	RBrace,
+	LBracket,
+	RBracket,
+	Comma,
+	Plus,
+	Minus,
+	Times,
+	Divide,
+	Modulo,
+	Assign,
+	Semi,
+	Less,
+	LessEq,
+	Greater,
+	GreaterEq,
+	EqEq,
+	NotEq,
+	Not,
+	AndAnd,
+	OrOr,
+	FatArrow,
+	Newline,
+	Let,
+	True,
+	False,
+	Include,
+	While,
+	Break,
+	Continue,
+	Match,
+	If,
+	Else,
+	Fn,
+	Id(String),
+	IntLit(i64),
+	FloatLit(f64),
+	StrLit(String),
+
+	// these are only made by lex_lossless().
+	Whitespace,
+	Comment,
+	Invalid,
+}
+
+impl TokenKind {
+	pub fn is_trivia(&self) -> bool {
+		matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+	}
+}
+
+impl Display for TokenKind {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use TokenKind::*;
+
+		match self {
+			Eof         => write!(f, "<eof>"),
+			LParen      => write!(f, "("),
+			RParen      => write!(f, ")"),
+			LBrace      => write!(f, "{{"),
+			RBrace      => write!(f, "}}"),
+			LBracket    => write!(f, "["),
+			RBracket    => write!(f, "]"),
+			Comma       => write!(f, ","),
+			Plus        => write!(f, "+"),
+			Minus       => write!(f, "-"),
+			Times       => write!(f, "*"),
+			Divide      => write!(f, "/"),
+			Modulo      => write!(f, "%"),
+			Assign      => write!(f, "="),
+			Semi        => write!(f, ";"),
+			Less        => write!(f, "<"),
+			LessEq      => write!(f, "<="),
+			Greater     => write!(f, ">"),
+			GreaterEq   => write!(f, ">="),
+			EqEq        => write!(f, "=="),
+			NotEq       => write!(f, "!="),
+			Not         => write!(f, "!"),
+			AndAnd      => write!(f, "&&"),
+			OrOr        => write!(f, "||"),
+			FatArrow    => write!(f, "=>"),
+			Newline     => write!(f, "<newline>"),
+			Let         => write!(f, "let"),
+			True        => write!(f, "true"),
+			False       => write!(f, "false"),
+			Include     => write!(f, "include"),
+			While       => write!(f, "while"),
+			Break       => write!(f, "break"),
+			Continue    => write!(f, "continue"),
+			Match       => write!(f, "match"),
+			If          => write!(f, "if"),
+			Else        => write!(f, "else"),
+			Fn          => write!(f, "fn"),
+			Id(id)      => write!(f, "{}", id),
+			IntLit(i)   => write!(f, "{}", i),
+			FloatLit(x) => write!(f, "{:?}", x), // {:?} always shows the '.', even for 3.0
+			StrLit(s)   => write!(f, "{}", quote(s)),
+			Whitespace  => write!(f, "<whitespace>"),
+			Comment     => write!(f, "<comment>"),
+			Invalid     => write!(f, "<invalid>"),
+		}
+	}
+}
+
+// Instead of just the location where the token starts, we keep the whole span, so errors about
+// a token can underline the whole thing.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+	pub span: Span,
+	pub kind: TokenKind,
+}
+
+impl Token {
+	pub fn new(span: Span, kind: TokenKind) -> Self {
+		Token { span, kind }
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The lexer algorithm
+// ------------------------------------------------------------------------------------------------
+
+// Unlike lexing_toy, this doesn't stop at the first error. It skips the bad character and keeps
+// going, so you can see *all* the lexical errors at once. If there were any errors, you get all
+// of them instead of the tokens.
+//
+// The tokens this gives don't include whitespace or comments, since the parser doesn't care
+// about them. See lex_lossless() if you want those too.
+pub fn lex(source: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+	let (tokens, errors) = lex_lossless(source);
+
+	if errors.is_empty() {
+		Ok(tokens.into_iter().filter(|t| !t.kind.is_trivia()).collect())
+	} else {
+		Err(errors)
+	}
+}
+
+/*
+Whitespace and comments are called *trivia*: they don't mean anything to the parser, so the lexer
+usually throws them away. But some tools care about every character in the file. A syntax
+highlighter has to output the comments too, and a code formatter had better not delete them!
+
+So this gives *every* character in the source as part of some token: trivia are tokens too, and
+characters that would be errors become Invalid tokens (and you get the errors separately). If you
+glue the text of all the tokens back together, you get exactly the original source code.
+*/
+pub fn lex_lossless(source: &str) -> (Vec<Token>, Vec<Diagnostic>) {
+	let source = source.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+	let mut ret = vec![];
+	let mut errors = vec![];
+	let mut paren_depth: usize = 0;
+
+	while pos < source.len() {
+		let start = pos;
+
+		// for single-character tokens, we figure out the kind and fall through to the push at
+		// the bottom. longer tokens push themselves and `continue`.
+		let kind = match source[pos] {
+			// Newline is a real token (unless it's inside parens or brackets), so it's not
+			// counted here.
+			c if is_whitespace(c, paren_depth) => {
+				while pos < source.len() && is_whitespace(source[pos], paren_depth) {
+					pos += 1;
+				}
+
+				ret.push(Token::new(Span::new(start, pos), TokenKind::Whitespace));
+				continue;
+			}
+
+			'\n' => TokenKind::Newline,
+
+			// a comment goes up to the end of the line, but the newline isn't part of it.
+			'#' => {
+				while pos < source.len() && source[pos] != '\n' {
+					pos += 1;
+				}
+
+				ret.push(Token::new(Span::new(start, pos), TokenKind::Comment));
+				continue;
+			}
+
+			// too many ')' is the parser's problem, so saturating_sub just keeps the depth
+			// from going negative. brackets count as parens here; the parser is the one that
+			// checks that they match.
+			'(' => { paren_depth += 1; TokenKind::LParen }
+			')' => { paren_depth = paren_depth.saturating_sub(1); TokenKind::RParen }
+			'[' => { paren_depth += 1; TokenKind::LBracket }
+			']' => { paren_depth = paren_depth.saturating_sub(1); TokenKind::RBracket }
+			'{' => TokenKind::LBrace,
+			'}' => TokenKind::RBrace,
+			',' => TokenKind::Comma,
+			'+' => TokenKind::Plus,
+			'-' => TokenKind::Minus,
+			'*' => TokenKind::Times,
+			'/' => TokenKind::Divide,
+			'%' => TokenKind::Modulo,
+			';' => TokenKind::Semi,
+
+			// the two-character tokens. each of these moves pos past the first character, and
+			// the `pos += 1` at the bottom gets the second one.
+			'=' if next_is(&source, pos, '=') => { pos += 1; TokenKind::EqEq }
+			'=' if next_is(&source, pos, '>') => { pos += 1; TokenKind::FatArrow }
+			'!' if next_is(&source, pos, '=') => { pos += 1; TokenKind::NotEq }
+			'<' if next_is(&source, pos, '=') => { pos += 1; TokenKind::LessEq }
+			'>' if next_is(&source, pos, '=') => { pos += 1; TokenKind::GreaterEq }
+			'&' if next_is(&source, pos, '&') => { pos += 1; TokenKind::AndAnd }
+			'|' if next_is(&source, pos, '|') => { pos += 1; TokenKind::OrOr }
+
+			// and the one-character tokens that start the same way.
+			'=' => TokenKind::Assign,
+			'!' => TokenKind::Not,
+			'<' => TokenKind::Less,
+			'>' => TokenKind::Greater,
+
+			c if is_ident_start(c) => {
+				let s = lex_ident(&source, &mut pos);
+
+				let kind = match s.as_str() {
+					"let"      => TokenKind::Let,
+					"true"     => TokenKind::True,
+					"false"    => TokenKind::False,
+					"include"  => TokenKind::Include,
+					"while"    => TokenKind::While,
+					"break"    => TokenKind::Break,
+					"continue" => TokenKind::Continue,
+					"match"    => TokenKind::Match,
+					"if"       => TokenKind::If,
+					"else"     => TokenKind::Else,
+					"fn"       => TokenKind::Fn,
+					_          => TokenKind::Id(s),
+				};
+
+				ret.push(Token::new(Span::new(start, pos), kind));
+				continue;
+			}
+
+			c if c.is_ascii_digit() => {
+				let kind = match lex_number(&source, &mut pos) {
+					Ok(kind) => kind,
+					Err(e)   => { errors.push(e); TokenKind::Invalid }
+				};
+
+				ret.push(Token::new(Span::new(start, pos), kind));
+				continue;
+			}
+
+			'"' => {
+				let kind = match lex_string(&source, &mut pos) {
+					Ok(kind) => kind,
+					Err(e)   => { errors.push(e); TokenKind::Invalid }
+				};
+
+				ret.push(Token::new(Span::new(start, pos), kind));
+				continue;
+			}
+
+			c => {
+				errors.push(LexError::InvalidChar { loc: pos, c }.into());
+				TokenKind::Invalid
+			}
+		};
+
+		pos += 1;
+		ret.push(Token::new(Span::new(start, pos), kind));
+	}
+
+	ret.push(Token::new(Span::new(pos, pos), TokenKind::Eof));
+	(ret, errors)
+}
+
+// is the character after `pos` equal to `c`?
+fn next_is(source: &[char], pos: usize, c: char) -> bool {
+	source.get(pos + 1) == Some(&c)
+}
+
+fn is_whitespace(c: char, paren_depth: usize) -> bool {
+	match c {
+		' ' | '\t' | '\r' => true,
+		'\n'              => paren_depth > 0,
+		_                 => false,
+	}
+}
+
+// IntLit | FloatLit. `pos` is moved past the number (and past any letters stuck to the end of it,
+// if that's an error, so the lexer doesn't report them again).
+fn lex_number(source: &[char], pos: &mut usize) -> Result<TokenKind, Diagnostic> {
+	let start = *pos;
+	let mut end = start;
+
+	while end < source.len() && source[end].is_ascii_digit() {
+		end += 1;
+	}
+
+	// the '.' is only part of the number if there's a digit after it. this is two characters of
+	// lookahead! "3." is the int 3 followed by an invalid character '.'.
+	let is_float = end + 1 < source.len() && source[end] == '.' && source[end + 1].is_ascii_digit();
+
+	// ints are exactly lexing_toy's.
+	if !is_float {
+		return lex_int(source, pos).map(TokenKind::IntLit)
+			.map_err(|err| number_error(source, pos, err));
+	}
+
+	*pos = end + 1;
+
+	while *pos < source.len() && source[*pos].is_ascii_digit() {
+		*pos += 1;
+	}
+
+	// like lexing_toy's ints, "1.5abc" is invalid.
+	if *pos < source.len() && source[*pos].is_alphabetic() {
+		let err = LexError::InvalidChar { loc: *pos, c: source[*pos] };
+		return Err(number_error(source, pos, err));
+	}
+
+	// parsing a float can't fail with what's in the source; too-big floats become infinity.
+	let num = source[start .. *pos].iter().collect::<String>();
+	Ok(TokenKind::FloatLit(num.parse().unwrap()))
+}
+
+// lexing_toy's error for a number, as a Diagnostic. if it's a letter stuck to the end of the
+// number, `pos` is moved past the rest of the letters, so the lexer doesn't report them again.
+fn number_error(source: &[char], pos: &mut usize, err: LexError) -> Diagnostic {
+	if let LexError::IntOutOfRange { .. } = err {
+		return err.into();
+	}
+
+	while *pos < source.len() && is_ident_cont(source[*pos]) {
+		*pos += 1;
+	}
+
+	Diagnostic::from(err).with_note("numbers can't have letters right after them")
+}
+
+// StrLit. `pos` is moved past the closing '"', or if there isn't one, up to the end of the line
+// (but not past the newline, since that's still a token). A bad escape sequence doesn't stop it,
+// so that the rest of the string isn't lexed as code.
+fn lex_string(source: &[char], pos: &mut usize) -> Result<TokenKind, Diagnostic> {
+	let start = *pos;
+	let mut s = String::new();
+	let mut bad_escape = None;
+	*pos += 1;
+
+	while *pos < source.len() && source[*pos] != '"' && source[*pos] != '\n' {
+		if source[*pos] != '\\' {
+			s.push(source[*pos]);
+			*pos += 1;
+			continue;
+		}
+
+		match source.get(*pos + 1) {
+			Some('n')  => s.push('\n'),
+			Some('t')  => s.push('\t'),
+			Some('"')  => s.push('"'),
+			Some('\\') => s.push('\\'),
+
+			// a '\' right before the end of the line is an unterminated string, not a bad
+			// escape.
+			None | Some('\n') => { *pos += 1; break; }
+
+			Some(c) => {
+				let err = Diagnostic::error(
+					format!("invalid escape sequence '\\{}'", c.escape_debug()),
+					Span::new(*pos, *pos + 2))
+					.with_code(diagnostics::INVALID_ESCAPE)
+					.with_note("the escape sequences are \\n, \\t, \\\", and \\\\");
+				bad_escape.get_or_insert(err);
+			}
+		}
+
+		*pos += 2;
+	}
+
+	if *pos < source.len() && source[*pos] == '"' {
+		*pos += 1;
+
+		match bad_escape {
+			Some(err) => Err(err),
+			None      => Ok(TokenKind::StrLit(s)),
+		}
+	} else {
+		Err(Diagnostic::error("unterminated string", Span::new(start, *pos))
+			.with_code(diagnostics::UNTERMINATED_STRING)
+			.with_note("strings have to end with '\"' on the same line they started on"))
+	}
+}
+
+// The value of a StrLit token, from its text (quotes and all). Gives None if the text isn't a
+// valid string literal.
+pub(crate) fn string_value(text: &str) -> Option<String> {
+	let chars = text.chars().collect::<Vec<_>>();
+	let mut pos = 0;
+
+	match lex_string(&chars, &mut pos) {
+		Ok(TokenKind::StrLit(s)) if pos == chars.len() => Some(s),
+		_                                              => None,
+	}
+}
+
+// The opposite of lexing a StrLit: puts quotes around the string, and turns the characters that
+// need escape sequences back into them.
+pub(crate) fn quote(s: &str) -> String {
+	let mut ret = String::from("\"");
+
+	for c in s.chars() {
+		match c {
+			'\n'  => ret.push_str("\\n"),
+			'\t'  => ret.push_str("\\t"),
+			'"'   => ret.push_str("\\\""),
+			'\\'  => ret.push_str("\\\\"),
+			c     => ret.push(c),
+		}
+	}
+
+	ret.push('"');
+	ret
+}
@@ -0,0 +1,151 @@
+use diagnostics::Diagnostic;
+
+/*
+This is where the other examples come together. lexing_toy, parsing_math, and ast_math each show
+one phase of a compiler, and each one has what the next one needs: lexing_toy's tokens have spans
+(so parsing_math's errors can point at the code) and ast_math's evaluator knows about variables.
+eval_math() (in math.rs) plugs the three of them right into each other:
+
+	source code --lexing_toy--> tokens --parsing_math--> AST --ast_math--> value
+
+That's all arithmetic, though, and toylang is a whole language, with statements, functions,
+strings, and so on. So compile() and eval_str() below have the same phases, extended: the lexer
+uses lexing_toy's rules (and errors) for identifiers and integers, and the evaluator does its
+arithmetic with ast_math. The parser is toylang's own. parsing_math's stops at the first error
+and only knows about expressions, but toylang's has statements, keeps going after an error to
+find the rest, and puts a span on every AST node. Every phase reports its errors as Diagnostics.
+
+The test at the bottom of this file checks that the two pipelines agree on arithmetic, so if
+the parsers ever group something differently, you'll find out.
+*/
+
+mod ast;
+mod cst;
+mod cst_parser;
+mod eval;
+mod formatter;
+mod highlight;
+mod include;
+mod lexer;
+mod math;
+mod parser;
+mod reparse;
+mod syntax;
+
+pub use crate::ast::*;
+pub use crate::cst::*;
+pub use crate::cst_parser::*;
+pub use crate::eval::*;
+pub use crate::formatter::*;
+pub use crate::highlight::*;
+pub use crate::include::*;
+pub use crate::lexer::*;
+pub use crate::math::*;
+pub use crate::parser::*;
+pub use crate::reparse::*;
+pub use crate::syntax::*;
+
+// What a successfully compiled program looks like.
+pub type Ast = Program;
+
+// ------------------------------------------------------------------------------------------------
+// The pipeline
+// ------------------------------------------------------------------------------------------------
+
+// Lexes and parses the source code. If anything went wrong, you get all the errors that were
+// found; you can show them with diagnostics::render_all().
+pub fn compile(src: &str) -> Result<Ast, Vec<Diagnostic>> {
+	let tokens = lex(src)?;
+	parse(&tokens)
+}
+
+// Like compile(), but it always gives a program, even if there were errors. Parts that couldn't be
+// parsed are AstKind::Error nodes (see parse_partial()). The errors from the lexer and the parser
+// are mixed together, in the order they are in the file.
+pub fn compile_partial(src: &str) -> (Ast, Vec<Diagnostic>) {
+	compile_partial_with(src, parse_partial)
+}
+
+// Like compile_partial(), but it calls `tracer` for every grammar rule the parser tries. See
+// TraceEvent.
+pub fn compile_partial_traced(src: &str, tracer: &mut dyn FnMut(&TraceEvent))
+-> (Ast, Vec<Diagnostic>) {
+	compile_partial_with(src, |tokens| parse_partial_traced(tokens, tracer))
+}
+
+fn compile_partial_with(src: &str, parse: impl FnOnce(&[Token]) -> (Ast, Vec<Diagnostic>))
+-> (Ast, Vec<Diagnostic>) {
+	let (tokens, mut errors) = lex_lossless(src);
+	let tokens = tokens.into_iter().filter(|t| !t.kind.is_trivia()).collect::<Vec<_>>();
+	let (program, parse_errors) = parse(&tokens);
+
+	errors.extend(parse_errors);
+	errors.sort_by_key(|e| e.span.map(|span| span.start));
+	(program, errors)
+}
+
+// Compiles and runs the source code in the given environment, and gives the value of the last
+// statement. Any variables it sets stay in `env` afterwards.
+pub fn eval_str(src: &str, env: &mut Env) -> Result<Option<Value>, Vec<Diagnostic>> {
+	let ast = compile(src)?;
+	run(&ast, env).map_err(|e| vec![e])
+}
+
+#[cfg(test)]
+mod tests {
+	use diagnostics::Span;
+
+	use super::*;
+
+	#[test]
+	fn eval_str_agrees_with_eval_math() {
+		// - and / aren't associative, so if the two parsers grouped anything differently, the
+		// answers would be different.
+		let srcs = [
+			"1 + 2 * 3",
+			"10 - 4 - 3",
+			"100 / 10 / 5",
+			"2 * (3 + 4) / 5 - 1",
+			"-3 - -4 * 2",
+			"--5 + 1",
+			"-(2 - 8) / 3 * 7",
+			"17 % 5 * 3 - 10 % 4",
+			"x * x - y / 2",
+			"-(x % 4) + pi * y",
+		];
+
+		let mut env = Env::new();
+		env.set("x", 7.0);
+		env.set("y", 2.5);
+
+		for src in &srcs {
+			let expected = eval_math(src, &env).expect("eval_math() should do it");
+
+			match eval_str(src, &mut env) {
+				Ok(Some(Value::Num(val))) => assert_eq!(val.to_bits(), expected.to_bits(),
+					"{}: eval_str() gave {}, but eval_math() gave {}", src, val, expected),
+				other => panic!("{}: eval_str() gave {:?}", src, other),
+			}
+		}
+	}
+
+	#[test]
+	fn eval_math_errors_point_at_the_code() {
+		let mut env = Env::new();
+		env.set("s", "hi");
+
+		let error = |src: &str| {
+			let errors = eval_math(src, &env).expect_err("it should be an error");
+			assert_eq!(errors.len(), 1);
+			(errors[0].code, errors[0].span)
+		};
+
+		// from lexing_toy, parsing_math, and ast_math.
+		assert_eq!(error("1 + $"), (Some(diagnostics::INVALID_CHAR), Some(Span::new(4, 5))));
+		assert_eq!(error("1 + * 2"), (Some(diagnostics::EXPECTED_EXPRESSION),
+			Some(Span::new(4, 5))));
+		assert_eq!(error("(1 + 2"), (Some(diagnostics::EXPECTED_RPAREN), Some(Span::new(6, 6))));
+		assert_eq!(error("2 * nope"), (Some(diagnostics::UNDEFINED_NAME), Some(Span::new(4, 8))));
+		assert_eq!(error("1 + s"), (Some(diagnostics::WRONG_VALUE_TYPE), Some(Span::new(4, 5))));
+	}
+}
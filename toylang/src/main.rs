@@ -0,0 +1,364 @@
+use std::process::exit;
+use std::rc::Rc;
+
+use colored::Colorize;
+use rustyline::{ Editor, error::ReadlineError };
+
+use diagnostics::{ render_all_colored, ColorChoice, Diagnostic, SourceFile, SourceManager, Span };
+use toylang::*;
+
+// With no arguments, this is an interactive calculator. Type an expression or statement, and it
+// shows you what every phase of the pipeline did with it: the tokens, the AST, and the value.
+// Variables you set on one line stick around for the next ones.
+//
+// `toylang eval file.calc` runs a whole file instead, printing the value of each expression
+// statement (and each if with an else, since that picks a value too). If there are any errors,
+// it shows them and exits with a nonzero exit code, so it can be used by scripts (and
+// autograders). The file can `include "other.calc"` other files.
+//
+// `toylang highlight file.calc` prints the file as syntax-highlighted HTML, and `toylang fmt
+// file.calc` prints it nicely formatted.
+//
+// `toylang ast file.calc` prints the file's AST. Even if there are syntax errors, you get a tree,
+// with <error> wherever the parser had to give up. With `--trace`, it first shows every grammar
+// rule the parser tried, and where.
+//
+// `toylang cst file.calc` prints the file's concrete syntax tree, with every token and trivia.
+// `toylang reparse file.calc` pretends to edit the file in lots of ways, and checks that
+// reparse() always gives the same tree as parsing the edited file from scratch.
+
+const USAGE: &str = "usage: toylang [--color=always|never|auto]
+       toylang eval <file> [--keep-going] [--color=always|never|auto]
+       toylang highlight <file>
+       toylang fmt <file> [--color=always|never|auto]
+       toylang ast <file> [--trace] [--color=always|never|auto]
+       toylang cst <file> [--color=always|never|auto]
+       toylang reparse <file>";
+
+fn main() {
+	// like lexing_toy, `--color=never` turns off colors in error messages.
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+
+	// everything that isn't a --flag.
+	let args = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+	let keep_going = std::env::args().any(|a| a == "--keep-going");
+	let trace = std::env::args().any(|a| a == "--trace");
+
+	match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		[]                  => repl(color),
+		["eval", path]      => exit(eval_file(path, keep_going, color)),
+		["highlight", path] => exit(highlight_file(path)),
+		["fmt", path]       => exit(format_file(path, color)),
+		["ast", path]       => exit(ast_file(path, trace, color)),
+		["cst", path]       => exit(cst_file(path, color)),
+		["reparse", path]   => exit(check_reparse(path)),
+		_                   => {
+			eprintln!("{}", USAGE);
+			exit(2);
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Batch mode
+// ------------------------------------------------------------------------------------------------
+
+// Gives back the exit code: 0 if everything worked, 1 if the program had errors, and 2 if the
+// file couldn't even be read.
+fn eval_file(path: &str, keep_going: bool, color: ColorChoice) -> i32 {
+	let mut sources = SourceManager::new();
+
+	let file = match sources.load(path) {
+		Ok(file) => file,
+		Err(e)   => {
+			eprintln!("error: could not read '{}': {}", path, e);
+			return 2;
+		}
+	};
+
+	// the program can be spread across several files, so the SourceManager figures out which
+	// file each error is in.
+	let program = compile_file(&mut sources, file);
+	let report = |errs: &[Diagnostic]| eprint!("{}", sources.render_all(errs, color));
+
+	let program = match program {
+		Ok(program) => program,
+		Err(errs) => {
+			// without --keep-going, only the first error is shown.
+			report(if keep_going { &errs } else { &errs[..1] });
+			return 1;
+		}
+	};
+
+	let mut env = Env::new();
+	let mut failed = false;
+
+	for stmt in &program.stmts {
+		match exec(stmt, &mut env) {
+			Ok(val) => {
+				if let (StmtKind::Exp { .. } | StmtKind::If { .. }, Some(val)) = (&stmt.kind, val) {
+					println!("{}", val);
+				}
+			}
+
+			Err(e) => {
+				report(&[e]);
+				failed = true;
+
+				// with --keep-going, a statement with an error is skipped, and the rest still
+				// run. (any variable it would have set just doesn't get set.)
+				if !keep_going {
+					break;
+				}
+			}
+		}
+	}
+
+	if failed { 1 } else { 0 }
+}
+
+fn highlight_file(path: &str) -> i32 {
+	match read_file(path) {
+		Some(text) => {
+			println!("<pre class=\"toylang\">{}</pre>", highlight(&text));
+			0
+		}
+
+		None => 2,
+	}
+}
+
+fn format_file(path: &str, color: ColorChoice) -> i32 {
+	let text = match read_file(path) {
+		Some(text) => text,
+		None       => return 2,
+	};
+
+	match format(&text) {
+		Ok(formatted) => {
+			print!("{}", formatted);
+			0
+		}
+
+		Err(errs) => {
+			eprint!("{}", render_all_colored(&errs, &SourceFile::new(path, &text), color));
+			1
+		}
+	}
+}
+
+fn ast_file(path: &str, trace: bool, color: ColorChoice) -> i32 {
+	let text = match read_file(path) {
+		Some(text) => text,
+		None       => return 2,
+	};
+
+	let (program, errs) = if trace {
+		let result = compile_partial_traced(&text, &mut |event| println!("{}", event));
+		println!();
+		result
+	} else {
+		compile_partial(&text)
+	};
+	print!("{}", program);
+
+	if errs.is_empty() {
+		0
+	} else {
+		eprint!("{}", render_all_colored(&errs, &SourceFile::new(path, &text), color));
+		1
+	}
+}
+
+fn cst_file(path: &str, color: ColorChoice) -> i32 {
+	let text = match read_file(path) {
+		Some(text) => text,
+		None       => return 2,
+	};
+
+	let (program, errs) = ProgramNode::parse(&text);
+	print!("{}", program.syntax());
+
+	// this is the whole point of a CST: nothing is lost, even in code with errors.
+	assert_eq!(program.text(), text, "the CST should have all of the source code");
+
+	if !errs.is_empty() {
+		eprint!("{}", render_all_colored(&errs, &SourceFile::new(path, &text), color));
+		return 1;
+	}
+
+	// and the AST made from the CST should be the same one that the regular parser makes.
+	let ast = program.to_ast().expect("a CST with no errors should become an AST");
+	let expected = compile(&text).expect("parse_cst() and compile() should find the same errors");
+	assert_eq!(format!("{:?}", ast), format!("{:?}", expected));
+
+	println!();
+	print!("AST (made from the CST):\n{}", ast);
+	0
+}
+
+// Deletes each character of the file in turn and then types it back in, like someone editing in
+// an editor. Each time, the incremental reparse has to match a full parse exactly: the same tree
+// and the same errors.
+fn check_reparse(path: &str) -> i32 {
+	let text = match read_file(path) {
+		Some(text) => text,
+		None       => return 2,
+	};
+
+	let (tree, errs) = parse_cst(&text);
+	let mut edits = 0;
+	let mut reused = 0;
+	let mut total = 0;
+
+	for (i, c) in text.chars().enumerate() {
+		let delete = TextEdit::delete(Span::point(i));
+		let (deleted, deleted_errs) = reparse(&tree, &errs, &delete);
+		let retype = TextEdit::insert(i, c.to_string());
+		let (retyped, retyped_errs) = reparse(&deleted, &deleted_errs, &retype);
+
+		let steps = [
+			(&tree, &deleted, &deleted_errs, &delete),
+			(&deleted, &retyped, &retyped_errs, &retype),
+		];
+
+		for (before, after, after_errs, edit) in steps.iter() {
+			let (full, full_errs) = parse_cst(&edit.apply(&before.text()));
+
+			if after.green() != full.green() || *after_errs != &full_errs {
+				eprintln!("reparse() gave the wrong answer for {:?}!", edit);
+				return 1;
+			}
+
+			// how many statements' green nodes came straight from the old tree?
+			let old_nodes = before.children();
+
+			for node in after.children() {
+				total += 1;
+
+				if old_nodes.iter().any(|old| Rc::ptr_eq(old.green(), node.green())) {
+					reused += 1;
+				}
+			}
+
+			edits += 1;
+		}
+	}
+
+	println!("{} edits, and every reparse matched a full parse.", edits);
+
+	if total > 0 {
+		println!("{}% of the statements were reused from the old tree.", reused * 100 / total);
+	}
+
+	0
+}
+
+fn read_file(path: &str) -> Option<String> {
+	match std::fs::read_to_string(path) {
+		Ok(text) => Some(text),
+		Err(e)   => {
+			eprintln!("error: could not read '{}': {}", path, e);
+			None
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Interactive mode
+// ------------------------------------------------------------------------------------------------
+
+// The interpreter only has the first few phases. The compiler crate's REPL can show the rest.
+const REPL_HELP: &str = "\
+<code>  runs the code, and shows its tokens, AST, and value
+:vars   lists the variables
+:help   shows this
+(for the types, IR, and assembly, `cargo run` in the compiler folder has :type, :ir, and :asm)";
+
+fn repl(color: ColorChoice) {
+	let mut env = Env::new();
+
+	// rustyline gives us line editing (arrow keys etc.) and history (up/down) for free.
+	let mut rl = Editor::<()>::new();
+
+	println!("{}", "------------------------------------------------------------".bright_blue());
+	println!("{}", "Type an expression, like 1 + 2 * sqrt(pi), or a statement,".bright_blue());
+	println!("{}", "like x = 5. :help lists the commands. Ctrl+C quits.".bright_blue());
+	println!("{}", "------------------------------------------------------------".bright_blue());
+
+	loop {
+		match rl.readline(">> ") {
+			Ok(line) => {
+				if line.trim().is_empty() {
+					continue;
+				}
+
+				rl.add_history_entry(line.as_str());
+
+				if line.trim() == ":vars" {
+					show_vars(&env);
+				} else if line.trim() == ":help" {
+					println!("{}\n", REPL_HELP);
+				} else {
+					run_line(&line, &mut env, color);
+				}
+			}
+			Err(ReadlineError::Interrupted) => break,
+			Err(ReadlineError::Eof) => break,
+			Err(err) => {
+				println!("Error: {:?}", err);
+				break
+			}
+		}
+	}
+
+	println!("byeeeeee!");
+}
+
+fn run_line(line: &str, env: &mut Env, color: ColorChoice) {
+	// each phase only runs if the one before it worked.
+	let result = lex(line)
+		.and_then(|tokens| {
+			show_tokens(&tokens);
+			parse(&tokens)
+		})
+		.and_then(|program| {
+			for stmt in &program.stmts {
+				println!("{} {}", "AST:   ".green(), stmt);
+				let val = exec(stmt, env).map_err(|e| vec![e])?;
+
+				// loops don't have values, and neither does a block that ends with one.
+				match (&stmt.kind, val) {
+					(StmtKind::Exp { .. } | StmtKind::Block { .. }, Some(val)) =>
+						println!("{} {}", "Value: ".green(), val.to_code().bold()),
+					(StmtKind::Let { name, .. } | StmtKind::Assign { name, .. }, Some(val)) =>
+						println!("{} {} = {}", "Set:   ".green(), name, val.to_code().bold()),
+					_ => {}
+				}
+			}
+
+			Ok(())
+		});
+
+	if let Err(errs) = result {
+		print!("{}", render_all_colored(&errs, &SourceFile::new("<input>", line), color));
+	}
+
+	println!();
+}
+
+fn show_vars(env: &Env) {
+	for (name, val) in env.vars() {
+		println!("{} = {}", name, val.to_code());
+	}
+
+	println!();
+}
+
+fn show_tokens(tokens: &[Token]) {
+	let kinds = tokens.iter().map(|t| format!("{:?}", t.kind)).collect::<Vec<_>>();
+	println!("{} {}", "Tokens:".green(), kinds.join(" "));
+}
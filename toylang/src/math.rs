@@ -0,0 +1,105 @@
+use diagnostics::{ Diagnostic, Severity, Span };
+use lexing_toy::{ Token, TokenKind };
+
+use crate::Env;
+
+/*
+compile() and eval_str() are built on lexing_toy, parsing_math, and ast_math, but toylang is a
+whole language, so they add a lot to them. These two are *only* those three crates, plugged
+right into each other:
+
+	source code --lexing_toy--> tokens --parsing_math--> AST --ast_math--> value
+
+so they can only do what all three can: numbers, variables, + - * / %, unary -, and parentheses.
+The glue is everything in this file. It's short, since each crate has what the next one needs:
+lexing_toy's tokens have spans, so parsing_math's errors can point at the code, and ast_math's
+evaluator can ask for the values of variables.
+*/
+
+// Lexes and parses an arithmetic expression, and turns it into an ast_math AST.
+pub fn compile_math(src: &str) -> Result<Box<ast_math::AstNode>, Vec<Diagnostic>> {
+	let (ast, _) = parse_math(src)?;
+	to_ast_math(&ast).map_err(|e| vec![e])
+}
+
+// Compiles and evaluates an arithmetic expression. Its variables come from `env`, and they have
+// to be numbers.
+pub fn eval_math(src: &str, env: &Env) -> Result<f64, Vec<Diagnostic>> {
+	let (ast, tokens) = parse_math(src)?;
+	let ast = to_ast_math(&ast).map_err(|e| vec![e])?;
+
+	ast.eval_with(&|name| env.get(name).and_then(|val| val.as_num()))
+		.map_err(|name| vec![variable_error(&name, &tokens, env)])
+}
+
+// gives the tokens too, since ast_math's AST doesn't know where anything is.
+fn parse_math(src: &str) -> Result<(Box<parsing_math::AstNode>, Vec<Token>), Vec<Diagnostic>> {
+	let tokens = lexing_toy::lex(src).map_err(|e| vec![e.into()])?;
+	let spans = tokens.iter().map(|t| t.span).collect::<Vec<_>>();
+	let math_tokens = tokens.iter().map(|t| to_parsing_math(&t.kind)).collect::<Vec<_>>();
+
+	match parsing_math::parse_exp(&math_tokens) {
+		Ok(ast)  => Ok((ast, tokens)),
+		Err(err) => Err(vec![err.to_diagnostic(&spans)]),
+	}
+}
+
+// the two crates have the same tokens, except that parsing_math's numbers are all floats.
+fn to_parsing_math(kind: &TokenKind) -> parsing_math::Token {
+	use parsing_math::Token;
+
+	match kind {
+		TokenKind::Eof       => Token::Eof,
+		TokenKind::LParen    => Token::LParen,
+		TokenKind::RParen    => Token::RParen,
+		TokenKind::Plus      => Token::Plus,
+		TokenKind::Minus     => Token::Minus,
+		TokenKind::Times     => Token::Times,
+		TokenKind::Divide    => Token::Divide,
+		TokenKind::Modulo    => Token::Modulo,
+		TokenKind::Id(s)     => Token::Id(s.clone()),
+		TokenKind::IntLit(i) => Token::NumLit(*i as f64),
+	}
+}
+
+// and the two ASTs are the same, except that ast_math has no function calls.
+fn to_ast_math(node: &parsing_math::AstNode) -> Result<Box<ast_math::AstNode>, Diagnostic> {
+	use parsing_math::{ AstNode, BinOp };
+
+	Ok(match node {
+		AstNode::Const  { val }          => ast_math::AstNode::num(*val),
+		AstNode::Ident  { name }         => ast_math::AstNode::var(name),
+		AstNode::Negate { lhs }          => ast_math::AstNode::neg(to_ast_math(lhs)?),
+		AstNode::Binary { op, lhs, rhs } => {
+			let (lhs, rhs) = (to_ast_math(lhs)?, to_ast_math(rhs)?);
+
+			match op {
+				BinOp::Add => ast_math::AstNode::add(lhs, rhs),
+				BinOp::Sub => ast_math::AstNode::sub(lhs, rhs),
+				BinOp::Mul => ast_math::AstNode::mul(lhs, rhs),
+				BinOp::Div => ast_math::AstNode::div(lhs, rhs),
+				BinOp::Mod => ast_math::AstNode::mod_(lhs, rhs),
+			}
+		}
+
+		// parsing_math's AST doesn't have spans, so there's nothing to point at.
+		AstNode::Call { callee, .. } => return Err(Diagnostic::new(Severity::Error,
+			format!("can't call '{}', since ast_math has no functions", callee), None)
+			.with_note("eval_str() can, since it's the whole language")),
+	})
+}
+
+// ast_math only says which variable it couldn't get, so this finds where it is in the tokens.
+fn variable_error(name: &str, tokens: &[Token], env: &Env) -> Diagnostic {
+	let span = tokens.iter()
+		.find(|t| matches!(&t.kind, TokenKind::Id(id) if id == name))
+		.map_or(Span::default(), |t| t.span);
+
+	match env.get(name) {
+		Some(val) => Diagnostic::error(format!("'{}' is {}, not a number", name,
+			val.type_name()), span).with_code(diagnostics::WRONG_VALUE_TYPE),
+
+		None => Diagnostic::error(format!("undefined variable '{}'", name), span)
+			.with_code(diagnostics::UNDEFINED_NAME),
+	}
+}
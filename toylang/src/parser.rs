@@ -0,0 +1,1192 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, ErrorCode, Span };
+
+use crate::*;
+
+// This started out as parsing_math's parser, changed to work on toylang's tokens (which have
+// spans), and to give Diagnostics as errors. parsing_math's precedence climbing has since moved
+// into the pratt crate, so see that for how the algorithm works. It's written out by hand here,
+// since the tracer, the error recovery, and the nesting limit all have to be mixed into it. (The
+// test at the bottom of lib.rs checks that the two still parse arithmetic the same way.)
+
+// ------------------------------------------------------------------------------------------------
+// Precedence
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub(crate) enum Precedence {
+	// lowest to highest!
+	None,
+	Or,      // ||
+	And,     // &&
+	Compare, // <, <=, >, >=, ==, and !=
+	Add,     // + and -
+	Mul,     // *, /, and %
+}
+
+impl Precedence {
+	const MIN : Precedence = Precedence::Or;
+
+	fn is_at_least(&self, other: Precedence) -> bool {
+		*self >= other
+	}
+
+	pub(crate) fn is_higher_than(&self, other: Precedence) -> bool {
+		*self > other
+	}
+}
+
+impl TokenKind {
+	pub(crate) fn precedence(&self) -> Precedence {
+		use TokenKind::*;
+
+		match self {
+			OrOr                    => Precedence::Or,
+			AndAnd                  => Precedence::And,
+			Less | LessEq | Greater | GreaterEq |
+			EqEq | NotEq            => Precedence::Compare,
+			Plus | Minus            => Precedence::Add,
+			Times | Divide | Modulo => Precedence::Mul,
+			_                       => Precedence::None,
+		}
+	}
+
+	pub(crate) fn to_binop(&self) -> BinOp {
+		use TokenKind::*;
+
+		match self {
+			Plus      => BinOp::Add,
+			Minus     => BinOp::Sub,
+			Times     => BinOp::Mul,
+			Divide    => BinOp::Div,
+			Modulo    => BinOp::Mod,
+			Less      => BinOp::Lt,
+			LessEq    => BinOp::Le,
+			Greater   => BinOp::Gt,
+			GreaterEq => BinOp::Ge,
+			EqEq      => BinOp::Eq,
+			NotEq     => BinOp::Ne,
+			AndAnd    => BinOp::And,
+			OrOr      => BinOp::Or,
+			_         => panic!("to_binop() called on a {:?} token", self),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The parser
+// ------------------------------------------------------------------------------------------------
+
+/*
+Grammar:
+
+Program:  Sep* (Stmt (Sep+ Stmt)*)? Sep* Eof
+Sep:      Newline | ';'
+Stmt:     LetStmt | AssignStmt | IncludeStmt | WhileStmt | IfStmt | Block | 'break' | 'continue'
+        | Exp
+LetStmt:  'let' Id '=' Exp
+AssignStmt: Id '=' Exp
+IncludeStmt: 'include' StrLit
+WhileStmt: 'while' Exp Block
+IfStmt:   'if' Exp Block ('else' (IfStmt | Block))?
+Block:    '{' Sep* (Stmt (Sep+ Stmt)*)? Sep* '}'
+Exp:      Term (BinOp Term)*
+BinOp:    '||' | '&&' | '<' | '<=' | '>' | '>=' | '==' | '!=' | '+' | '-' | '*' | '/' | '%'
+Term:     ('-' | '!')* PrimaryExp PostfixOp*
+PostfixOp: CallOp | IndexOp
+CallOp:   '(' (Exp (',' Exp)*)? ')'
+IndexOp:  '[' Exp ']'
+PrimaryExp: Id | IntLit | FloatLit | StrLit | 'true' | 'false' | '(' Exp ')' | ArrayExp
+        | MatchExp | FuncExp
+ArrayExp: '[' (Exp (',' Exp)*)? ']'
+FuncExp:  'fn' ParamList Block
+ParamList: '(' (Id (',' Id)*)? ')'
+MatchExp: 'match' Exp '{' ArmSep* (MatchArm (ArmSep+ MatchArm)*)? ArmSep* '}'
+ArmSep:   ',' | Newline
+MatchArm: Pattern '=>' Exp
+Pattern:  '-'? IntLit | '_'
+
+The comparison operators are all one precedence level, and left-associative like the rest, so
+`a < b < c` means `(a < b) < c`. That parses, but it's nonsense; the type checker catches it.
+
+The postfix operators (calls and indexing) are tighter than anything else, so `-a[0]` means
+`-(a[0])`, and they go left to right, so `grid[1][2]` means `(grid[1])[2]`. A '[' is an ArrayExp
+where an expression starts, and an IndexOp after one, so `[1, 2][0]` is the array's first value.
+
+AssignStmt and Exp can both start with an Id, so to tell them apart, the parser peeks at the token
+*after* the Id to see if it's '='.
+
+A statement ends at a separator, but it can also end right before a '}', so that a whole loop
+can go on one line, like `while x < 10 { x = x + 1 }`. The '{' of a while loop has to be on the
+same line as the `while`, since a newline there would end the statement. The same goes for an
+`if`, and its `else` has to be on the same line as the '}' before it; otherwise the `if` statement
+has already ended, and the `else` is on its own.
+
+A `match` is an expression, so it can go anywhere an expression can, like `y = match x { ... }`.
+Its arms can be on separate lines, or separated by commas. `_` is an Id token, and only means
+"anything" when it's a pattern.
+
+A function is an expression too, like `let add = fn(a, b) { a + b }`. Its body is a Block, so the
+'{' has to be on the same line as the ')' of the parameters, like a loop's. (Inside parentheses,
+newlines don't count, so a function that's an argument, like `apply(fn(x) { x }, 5)`, has to
+separate its statements with ';'.)
+
+An IncludeStmt is never in the AST. When a program is loaded from a file, lex_with_includes() (in
+include.rs) replaces each one with the tokens of the file it names, so the parser never sees it.
+If the parser *does* see one, the code didn't come from a file, and it's an error.
+*/
+
+// `tokens` must end with an Eof token, like the ones lex() gives you.
+//
+// When a statement has a syntax error, the parser doesn't give up. It keeps going to the end of
+// that statement and then goes on to the next, so you get the errors for *every* bad statement at
+// once. (This is called "error recovery," and real compilers do a much fancier version of it.)
+pub fn parse(tokens: &[Token]) -> Result<Program, Vec<Diagnostic>> {
+	let (program, errors) = parse_partial(tokens);
+
+	if errors.is_empty() {
+		Ok(program)
+	} else {
+		Err(errors)
+	}
+}
+
+/*
+parse() throws the tree away if there were any errors, but an editor (or a formatter, or a type
+checker that wants to find *more* errors) needs a tree even for broken code. This gives one
+anyway, along with the errors. Wherever the parser had to give up, there's an Error node instead:
+
+- a missing or bad operand, like the `*` in `x + *` or the end of `x +`, is an Error node in the
+  place where the operand should have been. a missing ')' is just left out.
+- a statement that's broken before its expression, like `let = 5`, is an Exp statement with one
+  Error node covering the whole statement.
+- extra stuff after a statement, like the `y` in `x y`, is an Exp statement with one Error node
+  covering the extra stuff.
+- a '}' that doesn't close any block is an Exp statement with an Error node covering it and the
+  rest of its line.
+- a while loop without a '{' has an empty body. if a block is missing its '}', it goes to the end
+  of the file.
+- an if without a '{' has an empty block (with a zero-width span right after the condition) and
+  no else part. an else without a '{' or 'if' after it has no else part either.
+- an 'else' that starts a statement is an Exp statement with an Error node covering it and the rest
+  of its line.
+- a match arm with a bad pattern or without a '=>' is left out of the match, and so is anything
+  extra after an arm's value. a match without a '{' has no arms.
+
+When it throws away the rest of a bad statement, it skips over whole blocks, so that the '}' of
+`x + { y }` isn't mistaken for the end of some other block. A block's statements get their own
+errors, like the statements at the top level.
+
+It reports the same errors as parse(): the first one in each statement.
+
+The tokens can include Invalid tokens (like the ones lex_lossless() gives), and they turn into
+Error nodes too. There's no error reported for them, since the lexer already did that.
+*/
+pub fn parse_partial(tokens: &[Token]) -> (Program, Vec<Diagnostic>) {
+	Parser::new(tokens, None).parse_program()
+}
+
+// Like parse_partial(), but it calls `tracer` every time it starts and finishes a grammar rule.
+// See TraceEvent below.
+pub fn parse_partial_traced(tokens: &[Token], tracer: &mut dyn FnMut(&TraceEvent))
+-> (Program, Vec<Diagnostic>) {
+	Parser::new(tokens, Some(tracer)).parse_program()
+}
+
+/*
+When you change a grammar and suddenly something doesn't parse, it helps to see *exactly* what the
+parser did. With a tracer, the parser reports every time it starts trying a rule (with the token
+it's looking at), and every time it finishes one (with the token after it). Rules call other rules,
+so Display indents each event by how deep it is. For `-f(x)`, that's:
+
+	Stmt at '-' (0..1)
+	  Exp at '-' (0..1)
+	    Term at '-' (0..1)
+	      Term at 'f' (1..2)
+	        PrimaryExp at 'f' (1..2)
+	        PrimaryExp done, next is '('
+	        CallOp at '(' (2..3)
+	...
+
+The `(BinOp Term)*` part of Exp isn't a rule of its own, so `a + b` is just an Exp with two Terms
+in it.
+*/
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TraceEvent<'t> {
+	// the parser is starting to parse `rule`, looking at `token`.
+	Enter { rule: &'static str, depth: usize, token: &'t Token },
+
+	// the parser finished `rule`, and `next` is the token after it.
+	Exit { rule: &'static str, depth: usize, next: &'t Token },
+}
+
+impl Display for TraceEvent<'_> {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		match self {
+			TraceEvent::Enter { rule, depth, token } =>
+				write!(f, "{:indent$}{} at '{}' ({})", "", rule, token.kind, token.span,
+					indent = depth * 2),
+
+			TraceEvent::Exit { rule, depth, next } =>
+				write!(f, "{:indent$}{} done, next is '{}'", "", rule, next.kind,
+					indent = depth * 2),
+		}
+	}
+}
+
+struct Parser<'t, 'f> {
+	tokens: &'t [Token],
+	pos:    usize,
+	errors: Vec<Diagnostic>,
+
+	// has an error been reported for the statement being parsed?
+	stmt_has_error: bool,
+
+	tracer: Option<&'f mut dyn FnMut(&TraceEvent)>,
+	depth:  usize, // how many rules deep we are, for the tracer.
+
+	nesting:   usize, // how many terms and blocks deep we are. see nested().
+	operators: usize, // how many binary operators deep we are. see more_operators().
+}
+
+// Every level of parentheses, brackets, blocks, and `-` or `!` is a few more calls deep in the
+// parser, and each call uses some of the Rust stack. Without a limit, a program like `((((...` with
+// 100,000 parentheses (which a program that writes programs could easily make) would use all of it,
+// and crash the whole process instead of giving an error. Chains like `f()[0]()...` and `else if`
+// after `else if` count too: the parser doesn't recurse for those, but they make a tree that's
+// just as deep, and everything after the parser (like eval()) does. This is plenty for code
+// written by hand.
+pub(crate) const MAX_NESTING: usize = 100;
+
+// Binary operators make a deep tree too, since `1 + 2 + 3 + ...` is `((1 + 2) + 3) + ...`. But a
+// chain like that is normal code, so eval() and everything else after the parser goes through
+// one with a loop (see AstNode::left_chain()), and only a few things, like dropping the tree,
+// still recurse. So operators get a separate limit that's much bigger: this is how many there
+// can be in a row, counting the ones around the parentheses that an expression is inside of.
+pub(crate) const MAX_OPERATORS: usize = 1000;
+
+impl<'t, 'f> Parser<'t, 'f> {
+	fn new(tokens: &'t [Token], tracer: Option<&'f mut dyn FnMut(&TraceEvent)>) -> Self {
+		assert!(matches!(tokens.last(), Some(Token { kind: TokenKind::Eof, .. })),
+			"token list must end with Eof");
+		Parser { tokens, pos: 0, errors: vec![], stmt_has_error: false, tracer, depth: 0,
+			nesting: 0, operators: 0 }
+	}
+
+	// Program: Sep* (Stmt (Sep+ Stmt)*)? Sep* Eof
+	fn parse_program(mut self) -> (Program, Vec<Diagnostic>) {
+		let mut stmts = vec![];
+
+		self.skip_separators();
+
+		while self.cur().kind != TokenKind::Eof {
+			stmts.extend(self.parse_stmt());
+			self.skip_separators();
+		}
+
+		(Program { stmts }, self.errors)
+	}
+
+	fn next(&mut self) {
+		// never move past the Eof token, so cur() always has something to give.
+		if self.pos < self.tokens.len() - 1 {
+			self.pos += 1;
+		}
+	}
+
+	// unlike parsing_math, this gives a *reference* to the token instead of a clone.
+	fn cur(&self) -> &'t Token {
+		&self.tokens[self.pos]
+	}
+
+	// like cur(), but n tokens ahead.
+	fn peek(&self, n: usize) -> &'t Token {
+		&self.tokens[(self.pos + n).min(self.tokens.len() - 1)]
+	}
+
+	// the token before cur(), or cur() if it's the first.
+	fn prev(&self) -> &'t Token {
+		&self.tokens[self.pos.saturating_sub(1)]
+	}
+
+	// runs one grammar rule's parsing code, and tells the tracer (if there is one) when it starts
+	// and finishes. without a tracer, this just calls `rule_fn`.
+	fn rule<T>(&mut self, rule: &'static str, rule_fn: impl FnOnce(&mut Self) -> T) -> T {
+		if self.tracer.is_none() {
+			return rule_fn(self);
+		}
+
+		let (depth, token) = (self.depth, self.cur());
+		self.trace(&TraceEvent::Enter { rule, depth, token });
+
+		self.depth += 1;
+		let ret = rule_fn(self);
+		self.depth -= 1;
+
+		let next = self.cur();
+		self.trace(&TraceEvent::Exit { rule, depth, next });
+		ret
+	}
+
+	fn trace(&mut self, event: &TraceEvent) {
+		if let Some(tracer) = &mut self.tracer {
+			tracer(event);
+		}
+	}
+
+	// goes one level deeper. if that's more than MAX_NESTING levels, it gives an error, and throws
+	// away the rest of the statement (so that nothing is left to go deeper), giving the Error node
+	// for that instead.
+	fn deeper(&mut self) -> Result<(), Box<AstNode>> {
+		if self.nesting < MAX_NESTING {
+			self.nesting += 1;
+			return Ok(());
+		}
+
+		let start = self.cur().span;
+		self.report(self.error(diagnostics::TOO_DEEPLY_NESTED,
+			format!("this code is nested more than {} levels deep", MAX_NESTING))
+			.with_note("split it up into smaller pieces with some variables"));
+		Err(self.skip_to_separator(start))
+	}
+
+	// like deeper(), but for a binary operator, which has its own, much bigger limit.
+	fn more_operators(&mut self) -> Result<(), Box<AstNode>> {
+		if self.operators < MAX_OPERATORS {
+			self.operators += 1;
+			return Ok(());
+		}
+
+		let start = self.cur().span;
+		self.report(self.error(diagnostics::TOO_MANY_OPERATORS,
+			format!("this expression has more than {} operators in a row", MAX_OPERATORS))
+			.with_note("split it up into smaller pieces with some variables"));
+		Err(self.skip_to_separator(start))
+	}
+
+	// runs `parse_fn` one level deeper, or if that's too deep, gives what `too_deep` makes from
+	// the Error node.
+	fn nested<T>(&mut self, parse_fn: impl FnOnce(&mut Self) -> T,
+	too_deep: impl FnOnce(Box<AstNode>) -> T) -> T {
+		let outer = self.nesting;
+
+		let ret = match self.deeper() {
+			Ok(())   => parse_fn(self),
+			Err(err) => too_deep(err),
+		};
+
+		self.nesting = outer;
+		ret
+	}
+
+	fn is_separator(kind: &TokenKind) -> bool {
+		matches!(kind, TokenKind::Newline | TokenKind::Semi)
+	}
+
+	fn at_stmt_end(&self) -> bool {
+		matches!(self.cur().kind, TokenKind::Newline | TokenKind::Semi | TokenKind::RBrace |
+			TokenKind::Eof)
+	}
+
+	fn skip_separators(&mut self) {
+		while Self::is_separator(&self.cur().kind) {
+			self.next();
+		}
+	}
+
+	// used for error recovery: throws away the rest of a bad statement, and gives an Error node
+	// that covers what was thrown away (starting at `start`). any blocks in it are thrown away
+	// whole, separators and all.
+	fn skip_to_separator(&mut self, start: Span) -> Box<AstNode> {
+		let mut depth = 0;
+
+		while !(depth == 0 && self.at_stmt_end()) && self.cur().kind != TokenKind::Eof {
+			match self.cur().kind {
+				TokenKind::LBrace => depth += 1,
+				TokenKind::RBrace => depth -= 1,
+				_                 => {}
+			}
+
+			self.next();
+		}
+
+		AstNode::error(start.to(self.prev().span))
+	}
+
+	// Stmt: LetStmt | AssignStmt | IncludeStmt | WhileStmt | IfStmt | Block | 'break' | 'continue'
+	//     | Exp
+	//
+	// gives the statement, and then an Exp statement with an Error node if there was extra stuff
+	// after it.
+	fn parse_stmt(&mut self) -> Vec<Stmt> {
+		self.rule("Stmt", |p| {
+			p.stmt_has_error = false;
+			let start = p.cur().span;
+
+			let kind = match (&p.cur().kind, &p.peek(1).kind) {
+				// LetStmt: 'let' Id '=' Exp
+				(TokenKind::Let, _) => {
+					p.next();
+
+					match p.expect_name().and_then(|name| p.expect_assign().map(|_| name)) {
+						Some((name, name_span)) => {
+							let value = p.parse_exp();
+							StmtKind::Let { name, name_span, value }
+						}
+
+						// without a name and '=', there's nothing to put in a Let.
+						None => StmtKind::Exp { exp: p.skip_to_separator(start) },
+					}
+				}
+
+				// AssignStmt: Id '=' Exp
+				(TokenKind::Id(name), TokenKind::Assign) => {
+					let name_span = p.cur().span;
+					p.next();
+					p.next();
+					let value = p.parse_exp();
+					StmtKind::Assign { name: name.clone(), name_span, value }
+				}
+
+				// IncludeStmt: 'include' StrLit
+				(TokenKind::Include, _) => {
+					p.parse_include();
+					StmtKind::Exp { exp: p.skip_to_separator(start) }
+				}
+
+				// WhileStmt: 'while' Exp Block
+				(TokenKind::While, _) => {
+					p.next();
+					let cond = p.parse_exp();
+
+					let body = if p.cur().kind == TokenKind::LBrace {
+						p.parse_block()
+					} else {
+						p.report_missing_lbrace("loop", "while");
+						vec![]
+					};
+
+					StmtKind::While { cond, body }
+				}
+
+				(TokenKind::If, _) => p.parse_if(),
+
+				(TokenKind::LBrace, _)   => StmtKind::Block { stmts: p.parse_block() },
+				(TokenKind::Break, _)    => { p.next(); StmtKind::Break }
+				(TokenKind::Continue, _) => { p.next(); StmtKind::Continue }
+
+				// a block stops at its '}', so this one doesn't close anything.
+				(TokenKind::RBrace, _) => {
+					p.report(p.error(diagnostics::UNMATCHED_RBRACE,
+						"this '}' doesn't close any block"));
+					p.next();
+					StmtKind::Exp { exp: p.skip_to_separator(start) }
+				}
+
+				// an else always comes right after an if's block, so this one doesn't go with one.
+				(TokenKind::Else, _) => {
+					p.report(p.error(diagnostics::ELSE_WITHOUT_IF,
+						"this 'else' doesn't go with any 'if'")
+						.with_note("the 'else' has to be on the same line as the '}' before it"));
+					p.next();
+					StmtKind::Exp { exp: p.skip_to_separator(start) }
+				}
+
+				_ => StmtKind::Exp { exp: p.parse_exp() },
+			};
+
+			// the statement ends at the end of its value, or at the last token of it.
+			let end = match &kind {
+				StmtKind::Let { value, .. } | StmtKind::Assign { value, .. } => value.span,
+				StmtKind::Exp { exp } => exp.span,
+				_ => p.prev().span,
+			};
+
+			let mut ret = vec![Stmt::new(start.to(end), kind)];
+
+			if !p.expect_stmt_end() {
+				let extra = p.skip_to_separator(p.cur().span);
+				ret.push(Stmt::new(extra.span, StmtKind::Exp { exp: extra }));
+			}
+
+			ret
+		})
+	}
+
+	// Block: '{' Sep* (Stmt (Sep+ Stmt)*)? Sep* '}'
+	//
+	// cur() must be the '{'.
+	fn parse_block(&mut self) -> Vec<Stmt> {
+		self.rule("Block", |p| p.nested(|p| {
+			let outer_has_error = p.stmt_has_error;
+			let mut stmts = vec![];
+
+			p.next();
+			p.skip_separators();
+
+			while !matches!(p.cur().kind, TokenKind::RBrace | TokenKind::Eof) {
+				stmts.extend(p.parse_stmt());
+				p.skip_separators();
+			}
+
+			// the statements inside reported their own errors. now it's back to the statement
+			// that the block is part of.
+			p.stmt_has_error = outer_has_error;
+
+			if p.cur().kind == TokenKind::RBrace {
+				p.next();
+			} else {
+				p.report(p.error(diagnostics::EXPECTED_RBRACE,
+					format!("expected '}}' at the end of the block, not '{}'", p.cur().kind))
+					.with_note("every '{' needs a '}' to go with it"));
+			}
+
+			stmts
+		}, |_| vec![]))
+	}
+
+	// IfStmt: 'if' Exp Block ('else' (IfStmt | Block))?
+	//
+	// cur() must be the 'if'. an `else if` is an IfStmt inside this one, so this calls itself.
+	fn parse_if(&mut self) -> StmtKind {
+		self.rule("IfStmt", |p| {
+			p.next();
+			let cond = p.parse_exp();
+
+			if p.cur().kind != TokenKind::LBrace {
+				p.report_missing_lbrace("if", "if");
+				let then_body = Box::new(Stmt::new(Span::point(cond.span.end),
+					StmtKind::Block { stmts: vec![] }));
+				return StmtKind::If { cond, then_body, else_body: None };
+			}
+
+			let then_body = Box::new(p.parse_block_stmt());
+
+			if p.cur().kind != TokenKind::Else {
+				return StmtKind::If { cond, then_body, else_body: None };
+			}
+
+			p.next();
+
+			let else_body = match p.cur().kind {
+				TokenKind::LBrace => Some(Box::new(p.parse_block_stmt())),
+
+				TokenKind::If => {
+					let start = p.cur().span;
+					let kind = p.nested(|p| p.parse_if(), |err| StmtKind::Exp { exp: err });
+					Some(Box::new(Stmt::new(start.to(p.prev().span), kind)))
+				}
+
+				_ => {
+					p.report(p.error(diagnostics::EXPECTED_LBRACE,
+						format!("expected '{{' or 'if' after 'else', not '{}'", p.cur().kind)));
+					None
+				}
+			};
+
+			StmtKind::If { cond, then_body, else_body }
+		})
+	}
+
+	// a Block as a statement of its own, for the parts of an if. cur() must be the '{'.
+	fn parse_block_stmt(&mut self) -> Stmt {
+		let start = self.cur().span;
+		let stmts = self.parse_block();
+		Stmt::new(start.to(self.prev().span), StmtKind::Block { stmts })
+	}
+
+	// `what` is the thing the condition belongs to, and `keyword` is what starts it.
+	fn report_missing_lbrace(&mut self, what: &str, keyword: &str) {
+		let err = self.error(diagnostics::EXPECTED_LBRACE,
+			format!("expected '{{' after the {}'s condition, not '{}'", what, self.cur().kind));
+
+		self.report(if self.cur().kind == TokenKind::Newline {
+			err.with_note(format!("the '{{' has to be on the same line as the '{}'", keyword))
+		} else {
+			err
+		});
+	}
+
+	// always reports an error. see the comment above parse().
+	fn parse_include(&mut self) {
+		let start = self.cur().span;
+		self.next();
+
+		if let TokenKind::StrLit(..) = self.cur().kind {
+			self.next();
+
+			if self.expect_stmt_end() {
+				let span = start.to(self.prev().span);
+				self.report(Diagnostic::error("can't include files here", span)
+					.with_code(diagnostics::INCLUDE_FAILED)
+					.with_note("only a program that's run from a file (like with `toylang eval`) \
+						can include other files"));
+			}
+		} else {
+			self.report(self.error(diagnostics::EXPECTED_FILE_NAME,
+				format!("expected a file name in double quotes, not '{}'", self.cur().kind)));
+		}
+	}
+
+	fn parse_exp(&mut self) -> Box<AstNode> {
+		self.rule("Exp", |p| {
+			let lhs = p.parse_term();
+			p.parse_binops(lhs, Precedence::MIN)
+		})
+	}
+
+	fn parse_binops(&mut self, mut lhs: Box<AstNode>, min_prec: Precedence) -> Box<AstNode> {
+		let outer = self.operators;
+
+		while self.cur().kind.precedence().is_at_least(min_prec) {
+			let op = &self.cur().kind;
+
+			// each operator puts everything before it one level deeper in the tree.
+			if let Err(err) = self.more_operators() {
+				lhs = AstNode::bin(lhs, op.to_binop(), err);
+				break;
+			}
+
+			self.next();
+			let mut rhs = self.parse_term();
+
+			while self.cur().kind.precedence().is_higher_than(op.precedence()) {
+				rhs = self.parse_binops(rhs, self.cur().kind.precedence());
+			}
+
+			lhs = AstNode::bin(lhs, op.to_binop(), rhs);
+		}
+
+		self.operators = outer;
+		lhs
+	}
+
+	// every kind of nested expression has a Term inside it, so this is where the nesting is
+	// counted.
+	fn parse_term(&mut self) -> Box<AstNode> {
+		self.rule("Term", |p| p.nested(|p| {
+			let tok = p.cur();
+
+			match tok.kind {
+				TokenKind::Minus => {
+					p.next();
+					let operand = p.parse_term();
+					AstNode::neg(tok.span, operand)
+				}
+
+				TokenKind::Not => {
+					p.next();
+					let operand = p.parse_term();
+					AstNode::not(tok.span, operand)
+				}
+
+				_ => {
+					let pri = p.parse_primary();
+					p.parse_postfix(pri)
+				}
+			}
+		}, |err| err))
+	}
+
+	fn parse_primary(&mut self) -> Box<AstNode> {
+		self.rule("PrimaryExp", |p| {
+			let tok = p.cur();
+
+			match &tok.kind {
+				TokenKind::Id(name)      => { p.next(); AstNode::id(tok.span, name) }
+				TokenKind::IntLit(val)   => { p.next(); AstNode::int(tok.span, *val) }
+				TokenKind::FloatLit(val) => { p.next(); AstNode::float(tok.span, *val) }
+				TokenKind::StrLit(val)   => { p.next(); AstNode::str(tok.span, val) }
+				TokenKind::True          => { p.next(); AstNode::bool(tok.span, true) }
+				TokenKind::False         => { p.next(); AstNode::bool(tok.span, false) }
+
+				TokenKind::LParen => {
+					p.next();
+					let mut ret = p.parse_exp();
+					let end = p.expect_rparen().unwrap_or(ret.span);
+
+					// the parens aren't in the AST, but it's nicer if errors about this
+					// expression underline them too.
+					ret.span = tok.span.to(end);
+					ret
+				}
+
+				TokenKind::LBracket => p.parse_array(),
+				TokenKind::Match    => p.parse_match(),
+				TokenKind::Fn       => p.parse_func(),
+
+				t => {
+					p.report(p.error(diagnostics::EXPECTED_EXPRESSION, format!(
+						"expected an identifier, number, or parenthesized expression, not '{}'", t
+					)));
+
+					// these can end an expression, so leave them for whatever is around it. (a
+					// '{' might be a loop's body.) the Error node goes where the operand should
+					// have been.
+					if p.at_stmt_end() || matches!(t, TokenKind::RParen | TokenKind::RBracket |
+						TokenKind::Comma | TokenKind::LBrace) {
+						AstNode::error(Span::point(tok.span.start))
+					} else {
+						p.next();
+						AstNode::error(tok.span)
+					}
+				}
+			}
+		})
+	}
+
+	// ArrayExp: '[' (Exp (',' Exp)*)? ']'
+	//
+	// cur() must be the '['. this is the same as a CallOp's argument list, with brackets.
+	fn parse_array(&mut self) -> Box<AstNode> {
+		self.rule("ArrayExp", |p| {
+			let lbracket = p.cur().span;
+			p.next();
+
+			let mut elems = vec![];
+
+			if p.cur().kind != TokenKind::RBracket {
+				elems.push(*p.parse_exp());
+
+				while p.cur().kind == TokenKind::Comma {
+					p.next();
+					elems.push(*p.parse_exp());
+				}
+			}
+
+			let end = p.expect_rbracket()
+				.unwrap_or_else(|| elems.last().map_or(lbracket, |elem| elem.span));
+			AstNode::array(lbracket, elems, end)
+		})
+	}
+
+	// MatchExp: 'match' Exp '{' ArmSep* (MatchArm (ArmSep+ MatchArm)*)? ArmSep* '}'
+	//
+	// cur() must be the 'match'.
+	fn parse_match(&mut self) -> Box<AstNode> {
+		self.rule("MatchExp", |p| {
+			let start = p.cur().span;
+			p.next();
+			let scrutinee = p.parse_exp();
+			let mut arms = vec![];
+
+			if p.cur().kind != TokenKind::LBrace {
+				let err = p.error(diagnostics::EXPECTED_LBRACE,
+					format!("expected '{{' after the match's value, not '{}'", p.cur().kind));
+
+				p.report(if p.cur().kind == TokenKind::Newline {
+					err.with_note("the '{' has to be on the same line as the 'match'")
+				} else {
+					err
+				});
+
+				return AstNode::match_exp(start, scrutinee, arms, p.prev().span);
+			}
+
+			p.next();
+			p.skip_arm_separators();
+
+			while !matches!(p.cur().kind, TokenKind::RBrace | TokenKind::Eof) {
+				arms.extend(p.parse_arm());
+
+				if !p.at_arm_end() {
+					p.report(p.error(diagnostics::EXPECTED_ARM_END,
+						format!("expected ',' or a new line after the match arm, not '{}'",
+						p.cur().kind)));
+					p.skip_to_arm_end();
+				}
+
+				p.skip_arm_separators();
+			}
+
+			if p.cur().kind == TokenKind::RBrace {
+				p.next();
+			} else {
+				p.report(p.error(diagnostics::EXPECTED_RBRACE,
+					format!("expected '}}' at the end of the match, not '{}'", p.cur().kind))
+					.with_note("every '{' needs a '}' to go with it"));
+			}
+
+			AstNode::match_exp(start, scrutinee, arms, p.prev().span)
+		})
+	}
+
+	// FuncExp: 'fn' ParamList Block
+	// ParamList: '(' (Id (',' Id)*)? ')'
+	//
+	// cur() must be the 'fn'. if anything before the body is wrong, it gives up and gives a
+	// function with no body; the error means nobody will run it anyway.
+	fn parse_func(&mut self) -> Box<AstNode> {
+		self.rule("FuncExp", |p| {
+			let start = p.cur().span;
+			p.next();
+
+			let mut params = vec![];
+
+			if p.cur().kind != TokenKind::LParen {
+				p.report(p.error(diagnostics::EXPECTED_LPAREN,
+					format!("expected '(' after 'fn', not '{}'", p.cur().kind)));
+				return AstNode::func(start, params, vec![], p.prev().span);
+			}
+
+			p.next();
+
+			if p.cur().kind != TokenKind::RParen {
+				loop {
+					match &p.cur().kind {
+						TokenKind::Id(name) => {
+							params.push(Param { name: name.clone(), span: p.cur().span });
+							p.next();
+						}
+
+						t => {
+							p.report(p.error(diagnostics::EXPECTED_NAME,
+								format!("expected a parameter name, not '{}'", t)));
+							return AstNode::func(start, params, vec![], p.prev().span);
+						}
+					}
+
+					if p.cur().kind != TokenKind::Comma {
+						break;
+					}
+
+					p.next();
+				}
+			}
+
+			if p.expect_rparen().is_none() {
+				return AstNode::func(start, params, vec![], p.prev().span);
+			}
+
+			if p.cur().kind != TokenKind::LBrace {
+				let err = p.error(diagnostics::EXPECTED_LBRACE, format!(
+					"expected '{{' after the function's parameters, not '{}'", p.cur().kind));
+
+				p.report(if p.cur().kind == TokenKind::Newline {
+					err.with_note("the '{' has to be on the same line as the ')'")
+				} else {
+					err
+				});
+
+				return AstNode::func(start, params, vec![], p.prev().span);
+			}
+
+			let body = p.parse_block();
+			AstNode::func(start, params, body, p.prev().span)
+		})
+	}
+
+	// MatchArm: Pattern '=>' Exp
+	//
+	// if the pattern or the '=>' is bad, it skips the arm and gives None.
+	fn parse_arm(&mut self) -> Option<MatchArm> {
+		self.rule("MatchArm", |p| {
+			let pattern_span = p.cur().span;
+
+			let pattern = match p.parse_pattern() {
+				Some(pattern) if p.cur().kind == TokenKind::FatArrow => pattern,
+
+				Some(_) => {
+					p.report(p.error(diagnostics::EXPECTED_ARROW,
+						format!("expected '=>' after the pattern, not '{}'", p.cur().kind)));
+					p.skip_to_arm_end();
+					return None;
+				}
+
+				None => {
+					p.skip_to_arm_end();
+					return None;
+				}
+			};
+
+			let pattern_span = pattern_span.to(p.prev().span);
+			p.next();
+			let value = *p.parse_exp();
+			Some(MatchArm { pattern, pattern_span, value })
+		})
+	}
+
+	// Pattern: '-'? IntLit | '_'
+	fn parse_pattern(&mut self) -> Option<Pattern> {
+		let tok = self.cur();
+
+		match (&tok.kind, &self.peek(1).kind) {
+			(TokenKind::IntLit(val), _) => { self.next(); Some(Pattern::Int(*val)) }
+
+			(TokenKind::Minus, TokenKind::IntLit(val)) => {
+				self.next();
+				self.next();
+				Some(Pattern::Int(-val))
+			}
+
+			(TokenKind::Id(name), _) if name == "_" => { self.next(); Some(Pattern::Wildcard) }
+
+			(t, _) => {
+				let err = self.error(diagnostics::EXPECTED_PATTERN,
+					format!("expected a number or '_' for the pattern, not '{}'", t));
+
+				self.report(match t {
+					TokenKind::Id(..) => err.with_note("a pattern can't be a variable; use '_' to \
+						match anything, or '==' to compare with a variable"),
+					_ => err,
+				});
+
+				None
+			}
+		}
+	}
+
+	fn at_arm_end(&self) -> bool {
+		matches!(self.cur().kind, TokenKind::Comma | TokenKind::Newline | TokenKind::RBrace |
+			TokenKind::Eof)
+	}
+
+	fn skip_arm_separators(&mut self) {
+		while matches!(self.cur().kind, TokenKind::Comma | TokenKind::Newline) {
+			self.next();
+		}
+	}
+
+	// used for error recovery, like skip_to_separator(), but for one arm of a match.
+	fn skip_to_arm_end(&mut self) {
+		let mut depth = 0;
+
+		while self.cur().kind != TokenKind::Eof {
+			match self.cur().kind {
+				_ if depth == 0 && self.at_arm_end() => break,
+				TokenKind::LBrace => depth += 1,
+				TokenKind::RBrace => depth -= 1,
+				_                 => {}
+			}
+
+			self.next();
+		}
+	}
+
+	// PostfixOp*, where PostfixOp: CallOp | IndexOp
+	fn parse_postfix(&mut self, mut lhs: Box<AstNode>) -> Box<AstNode> {
+		let outer = self.nesting;
+
+		loop {
+			if !matches!(self.cur().kind, TokenKind::LParen | TokenKind::LBracket) {
+				self.nesting = outer;
+				return lhs;
+			}
+
+			// like the binary operators, each one puts the ones before it one level deeper.
+			if let Err(err) = self.deeper() {
+				self.nesting = outer;
+				return AstNode::error(lhs.span.to(err.span));
+			}
+
+			lhs = match self.cur().kind {
+				TokenKind::LParen => self.parse_call(lhs),
+				_                 => self.parse_index(lhs),
+			};
+		}
+	}
+
+	// CallOp: '(' (Exp (',' Exp)*)? ')'
+	fn parse_call(&mut self, callee: Box<AstNode>) -> Box<AstNode> {
+		self.rule("CallOp", |p| {
+			let lparen = p.cur().span;
+			p.next();
+
+			let mut args = vec![];
+
+			// an empty argument list is fine, so the first argument is optional...
+			if p.cur().kind != TokenKind::RParen {
+				args.push(*p.parse_exp());
+
+				// ...but after that, every comma has to be followed by another one.
+				while p.cur().kind == TokenKind::Comma {
+					p.next();
+					args.push(*p.parse_exp());
+				}
+			}
+
+			let end = p.expect_rparen()
+				.unwrap_or_else(|| args.last().map_or(lparen, |arg| arg.span));
+			AstNode::call(callee, args, end)
+		})
+	}
+
+	// IndexOp: '[' Exp ']'
+	fn parse_index(&mut self, array: Box<AstNode>) -> Box<AstNode> {
+		self.rule("IndexOp", |p| {
+			p.next();
+			let index = p.parse_exp();
+			let end = p.expect_rbracket().unwrap_or(index.span);
+			AstNode::index(array, index, end)
+		})
+	}
+
+	// gives back the span of the ')', or None if it's missing.
+	fn expect_rparen(&mut self) -> Option<Span> {
+		let tok = self.cur();
+
+		match tok.kind {
+			TokenKind::RParen => { self.next(); Some(tok.span) }
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_RPAREN,
+					format!("expected ')', not '{}'", tok.kind)));
+				None
+			}
+		}
+	}
+
+	// gives back the span of the ']', or None if it's missing.
+	fn expect_rbracket(&mut self) -> Option<Span> {
+		let tok = self.cur();
+
+		match tok.kind {
+			TokenKind::RBracket => { self.next(); Some(tok.span) }
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_RBRACKET,
+					format!("expected ']', not '{}'", tok.kind)));
+				None
+			}
+		}
+	}
+
+	fn expect_name(&mut self) -> Option<(String, Span)> {
+		let tok = self.cur();
+
+		match &tok.kind {
+			TokenKind::Id(name) => { self.next(); Some((name.clone(), tok.span)) }
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_NAME,
+					format!("expected a variable name, not '{}'", tok.kind)));
+				None
+			}
+		}
+	}
+
+	fn expect_assign(&mut self) -> Option<()> {
+		match self.cur().kind {
+			TokenKind::Assign => { self.next(); Some(()) }
+			_ => {
+				self.report(self.error(diagnostics::EXPECTED_ASSIGN,
+					format!("expected '=', not '{}'", self.cur().kind)));
+				None
+			}
+		}
+	}
+
+	// doesn't consume the separator (or '}'); parse_partial() skips those. gives false if there's
+	// something else here.
+	fn expect_stmt_end(&mut self) -> bool {
+		let err = match self.cur().kind {
+			TokenKind::Newline | TokenKind::Semi | TokenKind::RBrace | TokenKind::Eof =>
+				return true,
+
+			// `1 = 2` or `(x) = 2` end up here, since they don't look like assignments to
+			// parse_stmt(). so give a more helpful message for them.
+			TokenKind::Assign => self.error(diagnostics::EXPECTED_STMT_END,
+				"expected end of statement, not '='")
+				.with_note("only variables can be assigned to, like `x = 5`"),
+
+			_ => self.error(diagnostics::EXPECTED_STMT_END,
+				format!("expected end of statement, not '{}'", self.cur().kind))
+				.with_note("put statements on separate lines, or separate them with ';'"),
+		};
+
+		self.report(err);
+		false
+	}
+
+	// makes an error pointing at the current token.
+	fn error(&self, code: ErrorCode, message: impl Into<String>) -> Diagnostic {
+		Diagnostic::error(message, self.cur().span).with_code(code)
+	}
+
+	// only the first error in each statement is kept. after one error, the rest of a broken
+	// statement usually causes a pile of confusing follow-on errors.
+	fn report(&mut self, err: Diagnostic) {
+		if !self.stmt_has_error && self.cur().kind != TokenKind::Invalid {
+			self.errors.push(err);
+		}
+
+		self.stmt_has_error = true;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// both parsers have to give the same error for it, without overflowing the stack.
+	fn nesting_errors(src: &str) -> Vec<Diagnostic> {
+		let errors = compile(src).expect_err("it should be too deeply nested");
+		let (tree, cst_errors) = parse_cst(src);
+		assert_eq!(tree.text(), src);
+		assert_eq!(errors, cst_errors);
+		errors
+	}
+
+	#[test]
+	fn deep_nesting_is_an_error() {
+		let n = 100_000;
+
+		let srcs = [
+			format!("{}1{}", "(".repeat(n), ")".repeat(n)),
+			format!("{}1", "-".repeat(2 * n)),
+			format!("{}{}", "{".repeat(n), "}".repeat(n)),
+			format!("f{}", "()".repeat(n)),
+			format!("a{}", "[0]".repeat(n)),
+			format!("if x {{ 0 }}{}", " else if x { 0 }".repeat(n)),
+		];
+
+		// (a block's statements get their own errors, so the else-ifs can give one for the
+		// statement in the innermost block, and another for the if itself.)
+		for src in &srcs {
+			let errors = nesting_errors(src);
+			assert!(!errors.is_empty());
+			assert!(errors.iter().all(|e| e.code == Some(diagnostics::TOO_DEEPLY_NESTED)),
+				"{:?}", errors);
+		}
+
+		// the rest of the statement is skipped, but the statements after it are still parsed.
+		let errors = nesting_errors(&format!("{}\nlet = 5", srcs[0]));
+		assert_eq!(errors.len(), 2);
+		assert_eq!(errors[1].code, Some(diagnostics::EXPECTED_NAME));
+	}
+
+	#[test]
+	fn long_operator_chains_are_an_error() {
+		let n = 100_000;
+
+		for src in &[format!("1{}", "+1".repeat(n)), format!("true{}", " && true".repeat(n))] {
+			let errors = nesting_errors(src);
+			assert_eq!(errors.len(), 1);
+			assert_eq!(errors[0].code, Some(diagnostics::TOO_MANY_OPERATORS));
+			assert_eq!(errors[0].message,
+				format!("this expression has more than {} operators in a row", MAX_OPERATORS));
+		}
+
+		// the operators inside parentheses are still in a row with the ones outside them.
+		let half = "+1".repeat(MAX_OPERATORS / 2 + 1);
+		let errors = nesting_errors(&format!("1{} * (1{})", half, half));
+		assert_eq!(errors[0].code, Some(diagnostics::TOO_MANY_OPERATORS));
+	}
+
+	#[test]
+	fn nesting_up_to_the_limit_is_fine() {
+		let n = MAX_NESTING - 1;
+		let parens = format!("{}1{}", "(".repeat(n), ")".repeat(n));
+		let chain = format!("1{}", "+1".repeat(MAX_OPERATORS));
+		let blocks = format!("{}{}", "{".repeat(MAX_NESTING), "}".repeat(MAX_NESTING));
+
+		for (src, val) in &[(&parens, 1.0), (&chain, (MAX_OPERATORS + 1) as f64)] {
+			match eval_str(src, &mut Env::new()) {
+				Ok(Some(Value::Num(v))) => assert_eq!(v, *val),
+				other                   => panic!("{:?}", other),
+			}
+		}
+
+		assert!(compile(&blocks).is_ok());
+		assert!(parse_cst(&blocks).1.is_empty());
+		assert!(parse_cst(&chain).1.is_empty());
+	}
+}
@@ -0,0 +1,218 @@
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Incremental reparsing
+// ------------------------------------------------------------------------------------------------
+
+/*
+An editor reparses the file on every keystroke, so that errors and highlighting stay up to date.
+For a big file, parsing *everything* again each time is too slow. But one keystroke usually only
+changes one statement, and the green tree (see syntax.rs) doesn't know where it is in the file, so
+the green nodes for every other statement can be put into the new tree as they are.
+
+toylang makes this easy, because a Newline token at the top level always ends a statement, and
+the lexer is always outside any parentheses there (or the newline would have been whitespace). So
+each *line* of top-level code (everything up to and including one of those newlines) can be
+lexed and parsed on its own, and gives the same tree and errors as it does in the whole file.
+reparse() only parses the lines that the edit touched, and reuses the rest.
+
+There's one catch: if the edit leaves a '(' unclosed, like typing `f(` at the start of a line, then
+the newline at the end of the line is now inside parentheses, so it's whitespace, and the *next*
+line is now part of this one. When that happens, it gives up and parses everything from the
+edited line to the end of the file. An unclosed '{' is the same: the lines after it are now inside
+the block, so they're no longer top-level statements of their own.
+*/
+
+// Replaces the characters in `span` (which can be empty, for an insertion) with `text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+	pub span: Span,
+	pub text: String,
+}
+
+impl TextEdit {
+	pub fn new(span: Span, text: impl Into<String>) -> Self {
+		TextEdit { span, text: text.into() }
+	}
+
+	pub fn insert(at: usize, text: impl Into<String>) -> Self {
+		Self::new(Span::new(at, at), text)
+	}
+
+	pub fn delete(span: Span) -> Self {
+		Self::new(span, "")
+	}
+
+	// Gives the source code after the edit. The span is in characters, like all Spans.
+	pub fn apply(&self, src: &str) -> String {
+		let chars = src.chars().collect::<Vec<_>>();
+		let mut ret = chars[.. self.span.start].iter().collect::<String>();
+		ret.push_str(&self.text);
+		ret.extend(&chars[self.span.end ..]);
+		ret
+	}
+}
+
+// Gives the same thing as `parse_cst(&edit.apply(&old.text()))`, but faster. `old` and
+// `old_errors` must be what parse_cst() (or reparse()) gave for the source before the edit.
+pub fn reparse(old: &SyntaxNode, old_errors: &[Diagnostic], edit: &TextEdit)
+-> (SyntaxNode, Vec<Diagnostic>) {
+	assert_eq!(old.kind(), SyntaxKind::Program, "only a whole program can be reparsed");
+
+	let old_len = old.span().end;
+	let new_src = edit.apply(&old.text()).chars().collect::<Vec<_>>();
+	let new_len = new_src.len();
+
+	// each line is from one top-level newline up to and including the next. the last line goes
+	// to the end of the file, and might be empty.
+	let mut line_starts = vec![0];
+
+	for child in old.children_with_tokens() {
+		if child.kind() == SyntaxKind::Newline {
+			line_starts.push(child.span().end);
+		}
+	}
+
+	let line_of = |pos: usize| line_starts.iter().rposition(|&start| start <= pos).unwrap();
+	let line_end = |line: usize| line_starts.get(line + 1).copied().unwrap_or(old_len);
+
+	// the lines to reparse, and where they start and end in the old source.
+	let first = line_of(edit.span.start);
+	let mut last = line_of(edit.span.end);
+	let start = line_starts[first];
+	let (mut region, mut region_errors);
+
+	loop {
+		// everything after the region moves over by however much longer or shorter the edit made
+		// the source. so the region ends at old_end in the old source, and new_end in the new.
+		let old_end = line_end(last);
+		let new_end = old_end + new_len - old_len;
+		let text = new_src[start .. new_end].iter().collect::<String>();
+
+		let (tree, errors) = parse_cst(&text);
+		region = tree;
+		region_errors = errors;
+
+		// the region's last newline has to still be a Newline token, and not whitespace inside
+		// some parentheses that the edit left open.
+		let ends_ok = new_end == new_len ||
+			region.children_with_tokens().last().map(|c| c.kind()) == Some(SyntaxKind::Newline);
+
+		if ends_ok {
+			break;
+		}
+
+		// give up and go to the end of the file.
+		last = line_starts.len() - 1;
+	}
+
+	let old_end = line_end(last);
+
+	// the old Program's children before and after the region are reused as they are. line
+	// boundaries are always between two of the Program's children, so this cuts cleanly.
+	let old_children = old.children_with_tokens();
+	let before = old_children.iter().take_while(|c| c.span().start < start);
+	let after = old_children.iter().skip_while(|c| c.span().start < old_end);
+
+	let children = before.map(green_of)
+		.chain(region.green().children().iter().cloned())
+		.chain(after.map(green_of))
+		.collect();
+
+	let root = SyntaxNode::new_root(GreenNode::new(SyntaxKind::Program, children));
+
+	// each error is inside the line it's about (or at the end of the file, for the last line), so
+	// the errors for the reused lines are reused too, moved over like the code after the region.
+	let mut errors = old_errors.iter()
+		.filter(|e| e.span.is_none_or(|span| span.start < start))
+		.cloned()
+		.collect::<Vec<_>>();
+
+	errors.extend(region_errors.into_iter().map(|e| shift(e, start, 0)));
+
+	// (if the region goes to the end of the file, there's nothing after it. an error at the very
+	// end belongs to the last line, which was just reparsed.)
+	if old_end < old_len {
+		errors.extend(old_errors.iter()
+			.filter(|e| e.span.is_some_and(|span| span.start >= old_end))
+			.map(|e| shift(e.clone(), new_len, old_len)));
+	}
+
+	(root, errors)
+}
+
+fn green_of(element: &SyntaxElement) -> GreenElement {
+	match element {
+		SyntaxElement::Node(node)   => GreenElement::Node(node.green().clone()),
+		SyntaxElement::Token(token) => GreenElement::Token(token.green().clone()),
+	}
+}
+
+// moves the error's span by `add - sub`, without going negative in between.
+fn shift(mut err: Diagnostic, add: usize, sub: usize) -> Diagnostic {
+	err.span = err.span.map(|span| Span::new(span.start + add - sub, span.end + add - sub));
+	err
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SRC: &str = "let x = 10\nlet y = f(x,\n\t2)\nwhile x > 0 {\n\tx = x - 1\n}\ny + x\n";
+
+	// reparse() has to give exactly what a full parse of the edited text does.
+	fn check(src: &str, edit: TextEdit) {
+		let (old, old_errs) = parse_cst(src);
+		let (new, new_errs) = reparse(&old, &old_errs, &edit);
+		let (full, full_errs) = parse_cst(&edit.apply(src));
+
+		assert!(new.green() == full.green(), "different trees after {:?}", edit);
+		assert_eq!(new_errs, full_errs, "different errors after {:?}", edit);
+	}
+
+	fn span_of(needle: &str) -> Span {
+		let start = SRC[.. SRC.find(needle).unwrap()].chars().count();
+		Span::new(start, start + needle.chars().count())
+	}
+
+	#[test]
+	fn edits_inside_tokens() {
+		check(SRC, TextEdit::new(span_of("10"), "123"));
+		check(SRC, TextEdit::insert(span_of("while").start + 2, "x"));
+		check(SRC, TextEdit::delete(Span::point(span_of("> 0").start)));
+	}
+
+	#[test]
+	fn edits_across_statements() {
+		check(SRC, TextEdit::delete(span_of("10\nlet y")));
+		check(SRC, TextEdit::new(span_of("2)\nwhile"), "3) + (\nwhile"));
+		check(SRC, TextEdit::insert(span_of("while").start, "z = 1\nq = (\n"));
+		check(SRC, TextEdit::insert(span_of("while").start, "{\n"));
+		check(SRC, TextEdit::delete(span_of("}\n")));
+	}
+
+	#[test]
+	fn edits_at_the_ends() {
+		let len = SRC.chars().count();
+		check(SRC, TextEdit::insert(0, "let z = 1\n"));
+		check(SRC, TextEdit::insert(0, "("));
+		check(SRC, TextEdit::delete(Span::new(0, 4)));
+		check(SRC, TextEdit::insert(len, "z"));
+		check(SRC, TextEdit::insert(len, "\nz * 2"));
+		check(SRC, TextEdit::delete(Span::new(len - 6, len)));
+		check("", TextEdit::insert(0, "x = 1\n"));
+		check(SRC, TextEdit::delete(Span::new(0, len)));
+	}
+
+	// like `toylang reparse`: deleting every character in turn, and then typing it back in.
+	#[test]
+	fn every_character() {
+		for (i, c) in SRC.chars().enumerate() {
+			let deleted = TextEdit::delete(Span::point(i));
+			check(SRC, deleted.clone());
+			check(&deleted.apply(SRC), TextEdit::insert(i, c.to_string()));
+		}
+	}
+}
@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::fmt::{ Debug, Display, Formatter, Result as FmtResult };
+use std::rc::Rc;
+
+use diagnostics::Span;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Concrete syntax trees
+// ------------------------------------------------------------------------------------------------
+
+/*
+The AST throws away everything the evaluator doesn't need: parentheses, commas, whitespace,
+comments. That's what makes it *abstract*. But the formatter, the highlighter, and editor tools
+(like "rename this variable") need all of that, so they end up gluing lex_lossless()'s tokens back
+onto the AST by hand, like format() does.
+
+A *concrete syntax tree* (CST) keeps every token, trivia too, so you can always get the exact
+source code back from it: `cst.text() == source`. This is how rust-analyzer (with its "rowan"
+library), Roslyn, and Swift's compiler do it, and it comes in two layers:
+
+- The *green* tree is the actual data. A GreenNode has a kind and its children, and a GreenToken
+  has a kind and its text. Neither one knows *where* it is: no spans, no parent pointers. So they
+  can be shared: every `+` token with the same text is the same GreenToken, and a reparser can
+  reuse whole unchanged subtrees from an old tree in a new one.
+
+- The *red* tree (SyntaxNode and SyntaxToken) is a cursor on top of the green tree. Each red node
+  points to its green node, its parent, and its offset in the file, so it *can* tell you its span
+  and go up the tree. Red nodes are made as you walk down, and thrown away when you're done.
+
+Nodes and tokens both have a SyntaxKind. The token kinds are TokenKind without the values (the
+text is in the tree anyway), and the node kinds are the grammar's rules.
+
+The typed layer in cst.rs goes on top of this, so that code can say `let_stmt.name()` instead of
+"the first Id token child."
+*/
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SyntaxKind {
+	// tokens.
+	LParen,
+	RParen,
+	LBrace,
+	RBrace,
+	LBracket,
+	RBracket,
+	Comma,
+	Plus,
+	Minus,
+	Times,
+	Divide,
+	Modulo,
+	Assign,
+	Semi,
+	Less,
+	LessEq,
+	Greater,
+	GreaterEq,
+	EqEq,
+	NotEq,
+	Not,
+	AndAnd,
+	OrOr,
+	FatArrow,
+	Newline,
+	Let,
+	True,
+	False,
+	Include,
+	While,
+	Break,
+	Continue,
+	Match,
+	If,
+	Else,
+	Fn,
+	Id,
+	IntLit,
+	FloatLit,
+	StrLit,
+	Whitespace,
+	Comment,
+	Invalid,
+
+	// nodes.
+	Program,
+	LetStmt,      // 'let' Id '=' Exp
+	AssignStmt,   // Id '=' Exp
+	ExpStmt,      // Exp
+	IncludeStmt,  // 'include' StrLit
+	WhileStmt,    // 'while' Exp Block
+	IfStmt,       // 'if' Exp Block ('else' (IfStmt | Block))?
+	Block,        // '{' Stmt* '}' (on its own, or as a loop's or an if's body)
+	BreakStmt,    // 'break'
+	ContinueStmt, // 'continue'
+	Literal,      // IntLit | FloatLit | StrLit | 'true' | 'false'
+	Name,         // Id
+	ParenExp,     // '(' Exp ')'
+	PrefixExp,    // ('-' | '!') Exp
+	BinaryExp,    // Exp BinOp Exp
+	CallExp,      // Exp ArgList
+	ArgList,      // '(' (Exp (',' Exp)*)? ')'
+	ArrayExp,     // '[' (Exp (',' Exp)*)? ']'
+	IndexExp,     // Exp '[' Exp ']'
+	MatchExp,     // 'match' Exp '{' (MatchArm | ',' | Newline)* '}'
+	MatchArm,     // Pattern '=>' Exp
+	Pattern,      // '-'? IntLit | '_'
+	FuncExp,      // 'fn' ParamList Block
+	ParamList,    // '(' (Id (',' Id)*)? ')'
+	Error,        // tokens that the parser couldn't make sense of.
+}
+
+impl SyntaxKind {
+	pub fn from_token(kind: &TokenKind) -> SyntaxKind {
+		use TokenKind::*;
+
+		match kind {
+			LParen       => SyntaxKind::LParen,
+			RParen       => SyntaxKind::RParen,
+			LBrace       => SyntaxKind::LBrace,
+			RBrace       => SyntaxKind::RBrace,
+			LBracket     => SyntaxKind::LBracket,
+			RBracket     => SyntaxKind::RBracket,
+			Comma        => SyntaxKind::Comma,
+			Plus         => SyntaxKind::Plus,
+			Minus        => SyntaxKind::Minus,
+			Times        => SyntaxKind::Times,
+			Divide       => SyntaxKind::Divide,
+			Modulo       => SyntaxKind::Modulo,
+			Assign       => SyntaxKind::Assign,
+			Semi         => SyntaxKind::Semi,
+			Less         => SyntaxKind::Less,
+			LessEq       => SyntaxKind::LessEq,
+			Greater      => SyntaxKind::Greater,
+			GreaterEq    => SyntaxKind::GreaterEq,
+			EqEq         => SyntaxKind::EqEq,
+			NotEq        => SyntaxKind::NotEq,
+			Not          => SyntaxKind::Not,
+			AndAnd       => SyntaxKind::AndAnd,
+			OrOr         => SyntaxKind::OrOr,
+			FatArrow     => SyntaxKind::FatArrow,
+			Newline      => SyntaxKind::Newline,
+			Let          => SyntaxKind::Let,
+			True         => SyntaxKind::True,
+			False        => SyntaxKind::False,
+			Include      => SyntaxKind::Include,
+			While        => SyntaxKind::While,
+			Break        => SyntaxKind::Break,
+			Continue     => SyntaxKind::Continue,
+			Match        => SyntaxKind::Match,
+			If           => SyntaxKind::If,
+			Else         => SyntaxKind::Else,
+			Fn           => SyntaxKind::Fn,
+			Id(..)       => SyntaxKind::Id,
+			IntLit(..)   => SyntaxKind::IntLit,
+			FloatLit(..) => SyntaxKind::FloatLit,
+			StrLit(..)   => SyntaxKind::StrLit,
+			Whitespace   => SyntaxKind::Whitespace,
+			Comment      => SyntaxKind::Comment,
+			Invalid      => SyntaxKind::Invalid,
+			// Eof is zero characters long, so there's nothing to put in the tree.
+			Eof          => panic!("Eof tokens don't go in a syntax tree"),
+		}
+	}
+
+	pub fn is_trivia(self) -> bool {
+		matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+	}
+
+	pub fn is_node(self) -> bool {
+		use SyntaxKind::*;
+
+		matches!(self, Program | LetStmt | AssignStmt | ExpStmt | IncludeStmt | WhileStmt | IfStmt |
+			Block | BreakStmt | ContinueStmt | Literal | Name | ParenExp | PrefixExp | BinaryExp |
+			CallExp | ArgList | ArrayExp | IndexExp | MatchExp | MatchArm | Pattern | FuncExp |
+			ParamList | Error)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The green tree
+// ------------------------------------------------------------------------------------------------
+
+// Lengths are in characters, like Spans.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+	kind:     SyntaxKind,
+	text:     String,
+	text_len: usize,
+}
+
+impl GreenToken {
+	pub fn new(kind: SyntaxKind, text: &str) -> Rc<GreenToken> {
+		assert!(!kind.is_node(), "{:?} is not a token kind", kind);
+		Rc::new(GreenToken { kind, text: text.into(), text_len: text.chars().count() })
+	}
+
+	pub fn kind(&self) -> SyntaxKind { self.kind }
+	pub fn text(&self) -> &str { &self.text }
+	pub fn text_len(&self) -> usize { self.text_len }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+	kind:     SyntaxKind,
+	text_len: usize, // the total length of all the children, so spans are quick to figure out.
+	children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+	pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Rc<GreenNode> {
+		assert!(kind.is_node(), "{:?} is not a node kind", kind);
+		let text_len = children.iter().map(GreenElement::text_len).sum();
+		Rc::new(GreenNode { kind, text_len, children })
+	}
+
+	pub fn kind(&self) -> SyntaxKind { self.kind }
+	pub fn text_len(&self) -> usize { self.text_len }
+	pub fn children(&self) -> &[GreenElement] { &self.children }
+
+	// All the text of all the tokens in this node, glued together.
+	pub fn text(&self) -> String {
+		let mut out = String::new();
+		self.write_text(&mut out);
+		out
+	}
+
+	fn write_text(&self, out: &mut String) {
+		for child in &self.children {
+			match child {
+				GreenElement::Node(node)   => node.write_text(out),
+				GreenElement::Token(token) => out.push_str(&token.text),
+			}
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GreenElement {
+	Node(Rc<GreenNode>),
+	Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+	pub fn kind(&self) -> SyntaxKind {
+		match self {
+			GreenElement::Node(node)   => node.kind,
+			GreenElement::Token(token) => token.kind,
+		}
+	}
+
+	pub fn text_len(&self) -> usize {
+		match self {
+			GreenElement::Node(node)   => node.text_len,
+			GreenElement::Token(token) => token.text_len,
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Building green trees
+// ------------------------------------------------------------------------------------------------
+
+/*
+A parser builds the green tree from the top down, the same order it parses in: start_node() when a
+rule starts, token() for each token it consumes, and finish_node() when the rule is done.
+
+Left-recursive things like `a + b` are a problem: by the time the parser sees the `+`, it has
+already finished the node for `a`, and *that* should have been inside a BinaryExp. So the parser
+takes a checkpoint() before parsing `a`, and when it sees the `+`, start_node_at() goes back and
+starts the BinaryExp at the checkpoint, around everything after it.
+*/
+#[derive(Debug, Default)]
+pub struct GreenBuilder {
+	// for each unfinished node, its kind and where its children start in `children`.
+	parents:  Vec<(SyntaxKind, usize)>,
+	children: Vec<GreenElement>,
+
+	// so that tokens with the same kind and text are shared, instead of making a new one every
+	// time. a file has a lot of " " tokens!
+	cache: HashMap<(SyntaxKind, String), Rc<GreenToken>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint(usize);
+
+impl GreenBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn start_node(&mut self, kind: SyntaxKind) {
+		self.parents.push((kind, self.children.len()));
+	}
+
+	pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+		let token = self.cache.entry((kind, text.into()))
+			.or_insert_with(|| GreenToken::new(kind, text))
+			.clone();
+
+		self.children.push(GreenElement::Token(token));
+	}
+
+	// Puts an already-built node into the tree as it is, like a reparser does with a node that
+	// didn't change.
+	pub fn node(&mut self, node: Rc<GreenNode>) {
+		self.children.push(GreenElement::Node(node));
+	}
+
+	pub fn finish_node(&mut self) {
+		let (kind, first) = self.parents.pop().expect("finish_node() without start_node()");
+		let children = self.children.split_off(first);
+		self.children.push(GreenElement::Node(GreenNode::new(kind, children)));
+	}
+
+	pub fn checkpoint(&self) -> Checkpoint {
+		Checkpoint(self.children.len())
+	}
+
+	// Starts a node whose first child is whatever was added right after the checkpoint.
+	pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+		let Checkpoint(first) = checkpoint;
+		assert!(first <= self.children.len(), "checkpoint is inside a finished node");
+		self.parents.push((kind, first));
+	}
+
+	// Gives the root. Every started node must be finished, and there must be exactly one root.
+	pub fn finish(mut self) -> Rc<GreenNode> {
+		assert!(self.parents.is_empty(), "some nodes were never finished");
+		assert_eq!(self.children.len(), 1, "there should be exactly one root node");
+
+		match self.children.pop().unwrap() {
+			GreenElement::Node(node) => node,
+			GreenElement::Token(..)  => panic!("the root should be a node, not a token"),
+		}
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The red tree
+// ------------------------------------------------------------------------------------------------
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+	green:  Rc<GreenNode>,
+	offset: usize, // where the node starts in the file.
+	parent: Option<SyntaxNode>,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+	parent: SyntaxNode,
+	green:  Rc<GreenToken>,
+	offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+	Node(SyntaxNode),
+	Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+	pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+		SyntaxNode(Rc::new(NodeData { green, offset: 0, parent: None }))
+	}
+
+	pub fn kind(&self) -> SyntaxKind { self.0.green.kind }
+	pub fn green(&self) -> &Rc<GreenNode> { &self.0.green }
+	pub fn parent(&self) -> Option<&SyntaxNode> { self.0.parent.as_ref() }
+
+	// Includes any trivia inside the node, but the parser never starts or ends a node with
+	// trivia, so this is the span of the code itself.
+	pub fn span(&self) -> Span {
+		Span::new(self.0.offset, self.0.offset + self.0.green.text_len)
+	}
+
+	// The exact source code of this node. For the root, that's the whole file.
+	pub fn text(&self) -> String {
+		self.0.green.text()
+	}
+
+	// All the children, nodes and tokens (trivia too), in order.
+	pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+		let mut offset = self.0.offset;
+		let mut ret = vec![];
+
+		for child in &self.0.green.children {
+			ret.push(match child {
+				GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+					green:  green.clone(),
+					offset,
+					parent: Some(self.clone()),
+				}))),
+
+				GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+					parent: self.clone(),
+					green:  green.clone(),
+					offset,
+				}),
+			});
+
+			offset += child.text_len();
+		}
+
+		ret
+	}
+
+	// Just the child nodes.
+	pub fn children(&self) -> Vec<SyntaxNode> {
+		self.children_with_tokens().into_iter().filter_map(|child| match child {
+			SyntaxElement::Node(node) => Some(node),
+			SyntaxElement::Token(..)  => None,
+		}).collect()
+	}
+
+	// Just the child tokens, without the trivia.
+	pub fn tokens(&self) -> Vec<SyntaxToken> {
+		self.children_with_tokens().into_iter().filter_map(|child| match child {
+			SyntaxElement::Token(token) if !token.kind().is_trivia() => Some(token),
+			_ => None,
+		}).collect()
+	}
+
+	// Every token inside this node at any depth, trivia too, in order.
+	pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+		let mut ret = vec![];
+
+		for child in self.children_with_tokens() {
+			match child {
+				SyntaxElement::Node(node)   => ret.extend(node.descendant_tokens()),
+				SyntaxElement::Token(token) => ret.push(token),
+			}
+		}
+
+		ret
+	}
+}
+
+impl SyntaxToken {
+	pub fn kind(&self) -> SyntaxKind { self.green.kind }
+	pub fn text(&self) -> &str { &self.green.text }
+	pub fn green(&self) -> &Rc<GreenToken> { &self.green }
+	pub fn parent(&self) -> &SyntaxNode { &self.parent }
+
+	pub fn span(&self) -> Span {
+		Span::new(self.offset, self.offset + self.green.text_len)
+	}
+}
+
+impl SyntaxElement {
+	pub fn kind(&self) -> SyntaxKind {
+		match self {
+			SyntaxElement::Node(node)   => node.kind(),
+			SyntaxElement::Token(token) => token.kind(),
+		}
+	}
+
+	pub fn span(&self) -> Span {
+		match self {
+			SyntaxElement::Node(node)   => node.span(),
+			SyntaxElement::Token(token) => token.span(),
+		}
+	}
+}
+
+// Two red nodes are the same if they're the same green node in the same place. (The same green
+// node can be in several places, since they're shared.)
+impl PartialEq for SyntaxNode {
+	fn eq(&self, other: &Self) -> bool {
+		Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+	}
+}
+
+impl PartialEq for SyntaxToken {
+	fn eq(&self, other: &Self) -> bool {
+		Rc::ptr_eq(&self.green, &other.green) && self.offset == other.offset
+	}
+}
+
+// deriving Debug would print the parent, which prints *its* parent, and so on. this just gives
+// the kind and span, like `BinaryExp@4..9`.
+impl Debug for SyntaxNode {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{:?}@{}", self.kind(), self.span())
+	}
+}
+
+impl Debug for SyntaxToken {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{:?}@{} {:?}", self.kind(), self.span(), self.text())
+	}
+}
+
+// Shows the whole tree, one node or token per line, indented by depth:
+//
+//	Program@0..9
+//	  LetStmt@0..9
+//	    Let@0..3 "let"
+//	    Whitespace@3..4 " "
+//	    ...
+impl Display for SyntaxNode {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		self.fmt_indented(f, 0)
+	}
+}
+
+impl SyntaxNode {
+	fn fmt_indented(&self, f: &mut Formatter, depth: usize) -> FmtResult {
+		writeln!(f, "{:indent$}{:?}", "", self, indent = depth * 2)?;
+
+		for child in self.children_with_tokens() {
+			match child {
+				SyntaxElement::Node(node) => node.fmt_indented(f, depth + 1)?,
+				SyntaxElement::Token(token) =>
+					writeln!(f, "{:indent$}{:?}", "", token, indent = (depth + 1) * 2)?,
+			}
+		}
+
+		Ok(())
+	}
+}
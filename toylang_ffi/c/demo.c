@@ -0,0 +1,44 @@
+/*
+	To build and run this (on Linux or macOS), from the toylang_ffi folder:
+
+		cargo build
+		cc c/demo.c -Iinclude -Ltarget/debug -ltoylang_ffi -o demo
+		LD_LIBRARY_PATH=target/debug ./demo
+
+	(On macOS, use DYLD_LIBRARY_PATH instead of LD_LIBRARY_PATH.)
+*/
+
+#include <stdio.h>
+#include "toylang.h"
+
+static void eval(const char* src) {
+	double result;
+	int status = toylang_eval(src, &result);
+
+	if(status == TOYLANG_OK)
+		printf("%s = %g\n", src, result);
+	else
+		printf("%s failed with status %d (E%04d):\n%s\n", src, status,
+			toylang_last_error_code(), toylang_last_error());
+}
+
+int main() {
+	eval("1 + 2 * 3");
+	eval("let r = 2; pi * r * r");
+	eval("sqrt(1, 2)");
+	eval("1 +");
+
+	/* NULLs are caught, not crashes. */
+	if(toylang_eval(NULL, NULL) == TOYLANG_ERR_NULL)
+		printf("NULL: %s\n", toylang_last_error());
+
+	/* an environment keeps variables between calls. */
+	ToylangEnv* env = toylang_env_new();
+	double x;
+	toylang_env_set(env, "width", 4);
+	toylang_env_eval(env, "area = width * 3", &x);
+	toylang_env_get(env, "area", &x);
+	printf("area = %g\n", x);
+	toylang_env_free(env);
+	return 0;
+}
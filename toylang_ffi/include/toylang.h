@@ -0,0 +1,61 @@
+/*
+	C interface to the toylang evaluator. See src/lib.rs in the toylang_ffi crate for the
+	details (and the rules the Rust side follows to make this safe).
+
+	Every function that can fail returns one of the TOYLANG_* status codes. When it's not
+	TOYLANG_OK, toylang_last_error() gives a message saying what went wrong.
+*/
+
+#ifndef TOYLANG_H
+#define TOYLANG_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TOYLANG_OK           0 /* it worked. */
+#define TOYLANG_ERR_NULL     1 /* a pointer argument was NULL. */
+#define TOYLANG_ERR_UTF8     2 /* a string argument wasn't valid UTF-8. */
+#define TOYLANG_ERR_COMPILE  3 /* the code had lexical or syntax errors. */
+#define TOYLANG_ERR_RUNTIME  4 /* the code had an error while running. */
+#define TOYLANG_ERR_NO_VALUE 5 /* the code had no statements, so there's no value. */
+#define TOYLANG_ERR_PANIC    6 /* a bug in toylang itself. */
+#define TOYLANG_ERR_NOT_NUMBER 7 /* the value is a string, array, or function. */
+
+/* An environment holds variables. You can only use it through a pointer. */
+typedef struct ToylangEnv ToylangEnv;
+
+/* Runs the code in a fresh environment, and puts the value of the last statement in *out.
+   On error, *out is left alone. If the value is a string, an array, or a function, returns
+   TOYLANG_ERR_NOT_NUMBER. A bool is 1 or 0. */
+int toylang_eval(const char* src, double* out);
+
+/* The message for the last error on this thread, or NULL if the last call worked. The string
+   belongs to toylang and is only valid until the next toylang_* call; don't free() it. */
+const char* toylang_last_error(void);
+
+/* The error code (like 200 for E0200) of the last compile or runtime error, or 0. */
+int toylang_last_error_code(void);
+
+/* Makes a new environment with the standard constants and functions. Free it with
+   toylang_env_free(). */
+ToylangEnv* toylang_env_new(void);
+
+/* Frees an environment. Passing NULL does nothing. */
+void toylang_env_free(ToylangEnv* env);
+
+/* Like toylang_eval(), but variables set by the code stay in env for the next call. */
+int toylang_env_eval(ToylangEnv* env, const char* src, double* out);
+
+/* Sets a variable. */
+int toylang_env_set(ToylangEnv* env, const char* name, double value);
+
+/* Gets a variable. If it doesn't exist, returns TOYLANG_ERR_RUNTIME and leaves *out alone, and
+   if it's a string, an array, or a function, returns TOYLANG_ERR_NOT_NUMBER. */
+int toylang_env_get(const ToylangEnv* env, const char* name, double* out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
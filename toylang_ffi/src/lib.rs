@@ -0,0 +1,276 @@
+use std::cell::RefCell;
+use std::ffi::{ CStr, CString };
+use std::os::raw::{ c_char, c_double, c_int };
+use std::panic::{ catch_unwind, AssertUnwindSafe };
+use std::ptr;
+
+use diagnostics::{ render_all, Diagnostic, SourceFile };
+use toylang::{ Env, Value };
+
+/*
+This crate lets C (and anything that can call C functions, which is almost every language) use
+toylang. The C declarations for everything here are in include/toylang.h, and c/demo.c shows how
+to use it.
+
+Talking to C means giving up most of what makes Rust safe, so there are a few rules we have to
+follow very carefully:
+
+- Every pointer C gives us might be NULL, so we check every one before using it.
+- C strings are just bytes ending in a 0. They might not be valid UTF-8, so we check that too.
+- Memory has to be freed by the same side that allocated it. C can't free() Rust's memory, so
+  anything we give out has a matching toylang_*_free() function.
+- A Rust panic must never unwind into C code (that's undefined behavior), so every function
+  catches panics and turns them into an error code.
+- The error codes, struct layouts, and function signatures are the ABI: once C code is compiled
+  against them, changing them breaks that code. So we only ever *add* things.
+*/
+
+// ------------------------------------------------------------------------------------------------
+// Status codes
+// ------------------------------------------------------------------------------------------------
+
+// These must match the #defines in toylang.h. They're plain c_int constants rather than a Rust
+// enum, because it's UB for C to hand Rust an enum value that isn't one of its variants.
+pub const TOYLANG_OK:             c_int = 0;
+pub const TOYLANG_ERR_NULL:       c_int = 1; // a pointer argument was NULL.
+pub const TOYLANG_ERR_UTF8:       c_int = 2; // a string argument wasn't valid UTF-8.
+pub const TOYLANG_ERR_COMPILE:    c_int = 3; // the code had lexical or syntax errors.
+pub const TOYLANG_ERR_RUNTIME:    c_int = 4; // the code had an error while running.
+pub const TOYLANG_ERR_NO_VALUE:   c_int = 5; // the code had no statements, so there's no value.
+pub const TOYLANG_ERR_PANIC:      c_int = 6; // a bug in toylang itself.
+pub const TOYLANG_ERR_NOT_NUMBER: c_int = 7; // the value is a string, array, or function.
+
+// ------------------------------------------------------------------------------------------------
+// The last error
+// ------------------------------------------------------------------------------------------------
+
+/*
+C functions can only return one thing, and ours return a status code. So the error *message* gets
+stashed here, and C can ask for it afterwards, like C's own errno/strerror.
+
+It's thread_local so that two threads using toylang at the same time don't see each other's
+errors. It's a CString because C needs the 0 at the end.
+*/
+thread_local! {
+	static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+struct LastError {
+	message: CString,
+	code:    c_int,
+}
+
+fn set_last_error(message: &str, code: c_int) {
+	// a message with a 0 byte in it would be cut off early in C. that can't happen with our
+	// messages, but just in case, replace them.
+	let message = CString::new(message.replace('\0', "\\0")).unwrap();
+	LAST_ERROR.with(|e| *e.borrow_mut() = Some(LastError { message, code }));
+}
+
+fn clear_last_error() {
+	LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// Gives the message for the last error that happened on this thread, or NULL if the last call
+/// succeeded. For compile and runtime errors, it's the same text the toylang tool would print.
+///
+/// The string belongs to toylang. It stays valid until the next toylang_* call on this thread;
+/// copy it if you need it longer than that. Do *not* free() it.
+#[no_mangle]
+pub extern "C" fn toylang_last_error() -> *const c_char {
+	LAST_ERROR.with(|e| match &*e.borrow() {
+		Some(err) => err.message.as_ptr(),
+		None      => ptr::null(),
+	})
+}
+
+/// Gives the error code (like 200 for E0200) of the first diagnostic from the last compile or
+/// runtime error on this thread, or 0 if there isn't one.
+#[no_mangle]
+pub extern "C" fn toylang_last_error_code() -> c_int {
+	LAST_ERROR.with(|e| e.borrow().as_ref().map_or(0, |err| err.code))
+}
+
+// ------------------------------------------------------------------------------------------------
+// Environments
+// ------------------------------------------------------------------------------------------------
+
+// C only ever sees a pointer to one of these; it can't look inside. This is called an "opaque
+// type," and it's how C libraries like stdio's FILE* work too.
+pub struct ToylangEnv {
+	env: Env,
+}
+
+/// Makes a new environment with the standard constants and functions in it. Free it with
+/// toylang_env_free().
+#[no_mangle]
+pub extern "C" fn toylang_env_new() -> *mut ToylangEnv {
+	// Box::into_raw gives up Rust's ownership of the box, so it won't be freed at the end of
+	// this function. toylang_env_free() takes ownership back.
+	Box::into_raw(Box::new(ToylangEnv { env: Env::new() }))
+}
+
+/// Frees an environment made by toylang_env_new(). Passing NULL does nothing.
+///
+/// # Safety
+///
+/// `env` must be NULL or a pointer from toylang_env_new() that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn toylang_env_free(env: *mut ToylangEnv) {
+	if !env.is_null() {
+		drop(Box::from_raw(env));
+	}
+}
+
+/// Sets a variable in the environment.
+///
+/// # Safety
+///
+/// `env` must be a live pointer from toylang_env_new(), and `name` must be NULL or a
+/// 0-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn toylang_env_set(env: *mut ToylangEnv, name: *const c_char,
+value: c_double) -> c_int {
+	guard(|| {
+		let env = env.as_mut().ok_or_else(null_error)?;
+		let name = to_str(name)?;
+		env.env.set(name, value);
+		Ok(())
+	})
+}
+
+/// Gets a variable from the environment and puts its value in `*out`. If there's no such
+/// variable, returns TOYLANG_ERR_RUNTIME and leaves `*out` alone, and if it's a string, an
+/// array, or a function, returns TOYLANG_ERR_NOT_NUMBER.
+///
+/// # Safety
+///
+/// `env` must be a live pointer from toylang_env_new(), `name` must be NULL or a 0-terminated
+/// string, and `out` must be NULL or point to a double.
+#[no_mangle]
+pub unsafe extern "C" fn toylang_env_get(env: *const ToylangEnv, name: *const c_char,
+out: *mut c_double) -> c_int {
+	guard(|| {
+		let env = env.as_ref().ok_or_else(null_error)?;
+		let name = to_str(name)?;
+		let out = out.as_mut().ok_or_else(null_error)?;
+
+		match env.env.get(name) {
+			Some(val) => { *out = to_double(&val)?; Ok(()) }
+			None => Err((TOYLANG_ERR_RUNTIME, format!("undefined variable '{}'", name), 0)),
+		}
+	})
+}
+
+// ------------------------------------------------------------------------------------------------
+// Evaluating code
+// ------------------------------------------------------------------------------------------------
+
+/// Runs the code in a fresh environment, and puts the value of the last statement in `*out`.
+/// On error, `*out` is left alone, and toylang_last_error() says what went wrong. If the value
+/// is a string, an array, or a function, that's TOYLANG_ERR_NOT_NUMBER.
+///
+/// # Safety
+///
+/// `src` must be NULL or a 0-terminated string, and `out` must be NULL or point to a double.
+#[no_mangle]
+pub unsafe extern "C" fn toylang_eval(src: *const c_char, out: *mut c_double) -> c_int {
+	let mut env = Env::new();
+	eval_in(&mut env, src, out)
+}
+
+/// Same as toylang_eval(), but in the given environment, so variables that the code sets are
+/// still there for the next call.
+///
+/// # Safety
+///
+/// `env` must be a live pointer from toylang_env_new(), `src` must be NULL or a 0-terminated
+/// string, and `out` must be NULL or point to a double.
+#[no_mangle]
+pub unsafe extern "C" fn toylang_env_eval(env: *mut ToylangEnv, src: *const c_char,
+out: *mut c_double) -> c_int {
+	match env.as_mut() {
+		Some(env) => eval_in(&mut env.env, src, out),
+		None      => guard(|| Err(null_error())),
+	}
+}
+
+unsafe fn eval_in(env: &mut Env, src: *const c_char, out: *mut c_double) -> c_int {
+	guard(|| {
+		let src = to_str(src)?;
+		let out = out.as_mut().ok_or_else(null_error)?;
+
+		// the toylang functions don't say whether an error came from compiling or running, so
+		// we do the two steps separately.
+		let program = toylang::compile(src)
+			.map_err(|errs| diag_error(TOYLANG_ERR_COMPILE, src, &errs))?;
+
+		match toylang::run(&program, env) {
+			Ok(Some(val)) => { *out = to_double(&val)?; Ok(()) }
+			Ok(None)      => Err((TOYLANG_ERR_NO_VALUE, "the code has no statements".into(), 0)),
+			Err(e)        => Err(diag_error(TOYLANG_ERR_RUNTIME, src, &[e])),
+		}
+	})
+}
+
+// ------------------------------------------------------------------------------------------------
+// Helpers
+// ------------------------------------------------------------------------------------------------
+
+// (status, message, error code)
+type FfiError = (c_int, String, c_int);
+
+// Runs `f`, catching panics, and turns its result into a status code (recording the error, if
+// there was one). Every extern function goes through this.
+fn guard<F: FnOnce() -> Result<(), FfiError>>(f: F) -> c_int {
+	// AssertUnwindSafe is us promising that nothing is left half-modified if `f` panics. that's
+	// close enough to true: the worst case is an Env with some variables set.
+	match catch_unwind(AssertUnwindSafe(f)) {
+		Ok(Ok(())) => {
+			clear_last_error();
+			TOYLANG_OK
+		}
+
+		Ok(Err((status, message, code))) => {
+			set_last_error(&message, code);
+			status
+		}
+
+		Err(..) => {
+			set_last_error("internal error in toylang (this is a bug!)", 0);
+			TOYLANG_ERR_PANIC
+		}
+	}
+}
+
+fn null_error() -> FfiError {
+	(TOYLANG_ERR_NULL, "a pointer argument was NULL".into(), 0)
+}
+
+// The lifetime 'a is made up: nothing stops C from freeing the string while we're using it. The
+// functions that call this are `unsafe` because the caller has to promise it won't.
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, FfiError> {
+	if s.is_null() {
+		return Err(null_error());
+	}
+
+	CStr::from_ptr(s).to_str()
+		.map_err(|_| (TOYLANG_ERR_UTF8, "a string argument was not valid UTF-8".into(), 0))
+}
+
+// C only gets doubles. bools are 1 and 0, like they are in C, but there's no way to give it a
+// string, an array, or a function yet.
+fn to_double(val: &Value) -> Result<f64, FfiError> {
+	match val {
+		Value::Num(x)  => Ok(*x),
+		Value::Bool(b) => Ok(if *b { 1.0 } else { 0.0 }),
+		Value::Str(..) | Value::Array(..) | Value::Closure(..) => Err((TOYLANG_ERR_NOT_NUMBER,
+			format!("the value is {} ({}), not a number", val.type_name(), val.to_code()), 0)),
+	}
+}
+
+fn diag_error(status: c_int, src: &str, errs: &[Diagnostic]) -> FfiError {
+	let rendered = render_all(errs, &SourceFile::new("<input>", src));
+	let code = errs.first().and_then(|e| e.code).map_or(0, |c| c.0 as c_int);
+	(status, rendered, code)
+}
@@ -0,0 +1,242 @@
+use serde_json::{ json, Value };
+
+use diagnostics::{ Diagnostic, SourceFile };
+use toylang::*;
+
+// These turn toylang's data structures into JSON. We could put #[derive(Serialize)] on them
+// instead, but then the toylang crate would depend on serde just for the sake of the browser, and
+// the JSON would look like Rust's enums instead of what a JavaScript programmer would expect.
+
+// ------------------------------------------------------------------------------------------------
+// Tokens
+// ------------------------------------------------------------------------------------------------
+
+// { "kind": "Id", "text": "x", "start": 0, "end": 1 }
+pub fn token_to_json(tok: &Token, src: &[char]) -> Value {
+	use TokenKind::*;
+
+	let kind = match tok.kind {
+		Eof          => "Eof",
+		LParen       => "LParen",
+		RParen       => "RParen",
+		LBrace       => "LBrace",
+		RBrace       => "RBrace",
+		LBracket     => "LBracket",
+		RBracket     => "RBracket",
+		Comma        => "Comma",
+		Plus         => "Plus",
+		Minus        => "Minus",
+		Times        => "Times",
+		Divide       => "Divide",
+		Modulo       => "Modulo",
+		Assign       => "Assign",
+		Semi         => "Semi",
+		Less         => "Less",
+		LessEq       => "LessEq",
+		Greater      => "Greater",
+		GreaterEq    => "GreaterEq",
+		EqEq         => "EqEq",
+		NotEq        => "NotEq",
+		Not          => "Not",
+		AndAnd       => "AndAnd",
+		OrOr         => "OrOr",
+		FatArrow     => "FatArrow",
+		Newline      => "Newline",
+		Let          => "Let",
+		True         => "True",
+		False        => "False",
+		Include      => "Include",
+		While        => "While",
+		Break        => "Break",
+		Continue     => "Continue",
+		Match        => "Match",
+		If           => "If",
+		Else         => "Else",
+		Fn           => "Fn",
+		Id(..)       => "Id",
+		IntLit(..)   => "IntLit",
+		FloatLit(..) => "FloatLit",
+		StrLit(..)   => "StrLit",
+		Whitespace   => "Whitespace",
+		Comment      => "Comment",
+		Invalid      => "Invalid",
+	};
+
+	let text = src[tok.span.start .. tok.span.end].iter().collect::<String>();
+	json!({ "kind": kind, "text": text, "start": tok.span.start, "end": tok.span.end })
+}
+
+// ------------------------------------------------------------------------------------------------
+// AST
+// ------------------------------------------------------------------------------------------------
+
+// Every node is an object with a "type" field that says what kind of node it is, plus a "span".
+pub fn program_to_json(program: &Program) -> Value {
+	json!({
+		"type":  "Program",
+		"stmts": program.stmts.iter().map(stmt_to_json).collect::<Vec<_>>(),
+	})
+}
+
+fn stmt_to_json(stmt: &Stmt) -> Value {
+	use StmtKind::*;
+
+	let span = span_to_json(stmt.span);
+
+	match &stmt.kind {
+		Let    { name, value, .. } =>
+			json!({ "type": "Let",    "span": span, "name": name, "value": exp_to_json(value) }),
+		Assign { name, value, .. } =>
+			json!({ "type": "Assign", "span": span, "name": name, "value": exp_to_json(value) }),
+		Exp    { exp } =>
+			json!({ "type": "ExpStmt", "span": span, "exp": exp_to_json(exp) }),
+		While  { cond, body } => json!({
+			"type": "While",
+			"span": span,
+			"cond": exp_to_json(cond),
+			"body": body.iter().map(stmt_to_json).collect::<Vec<_>>(),
+		}),
+		// the else part is a Block or another If, or null if there isn't one.
+		If     { cond, then_body, else_body } => json!({
+			"type": "If",
+			"span": span,
+			"cond": exp_to_json(cond),
+			"then": stmt_to_json(then_body),
+			"else": else_body.as_deref().map(stmt_to_json),
+		}),
+		Block  { stmts } => json!({
+			"type":  "Block",
+			"span":  span,
+			"stmts": stmts.iter().map(stmt_to_json).collect::<Vec<_>>(),
+		}),
+		Break    => json!({ "type": "Break",    "span": span }),
+		Continue => json!({ "type": "Continue", "span": span }),
+	}
+}
+
+fn exp_to_json(node: &AstNode) -> Value {
+	use AstKind::*;
+
+	let span = span_to_json(node.span);
+
+	match &node.kind {
+		IntLit   { val }  => json!({ "type": "IntLit",   "span": span, "value": val }),
+		FloatLit { val }  => json!({ "type": "FloatLit", "span": span, "value": val }),
+		BoolLit  { val }  => json!({ "type": "BoolLit",  "span": span, "value": val }),
+		StrLit   { val }  => json!({ "type": "StrLit",   "span": span, "value": val }),
+		Ident    { name } => json!({ "type": "Ident",    "span": span, "name": name }),
+		Negate   { lhs }  => json!({ "type": "Negate",   "span": span, "lhs": exp_to_json(lhs) }),
+		Not      { lhs }  => json!({ "type": "Not",      "span": span, "lhs": exp_to_json(lhs) }),
+		Error             => json!({ "type": "Error",    "span": span }),
+
+		// built from the bottom of the chain up, so a long chain of operators can't use up the
+		// stack (see AstNode::left_chain()).
+		Binary { .. } => {
+			let (first, ops) = node.left_chain();
+
+			ops.into_iter().fold(exp_to_json(first), |lhs, (node, op, rhs)| json!({
+				"type": "Binary",
+				"span": span_to_json(node.span),
+				"op":   op.to_string(),
+				"lhs":  lhs,
+				"rhs":  exp_to_json(rhs),
+			}))
+		}
+
+		Call { callee, args } => json!({
+			"type":   "Call",
+			"span":   span,
+			"callee": exp_to_json(callee),
+			"args":   args.iter().map(exp_to_json).collect::<Vec<_>>(),
+		}),
+
+		Array { elems } => json!({
+			"type":  "Array",
+			"span":  span,
+			"elems": elems.iter().map(exp_to_json).collect::<Vec<_>>(),
+		}),
+
+		Index { array, index } => json!({
+			"type":  "Index",
+			"span":  span,
+			"array": exp_to_json(array),
+			"index": exp_to_json(index),
+		}),
+
+		// a wildcard pattern is null, since there's no number for it.
+		Match { scrutinee, arms } => json!({
+			"type":      "Match",
+			"span":      span,
+			"scrutinee": exp_to_json(scrutinee),
+			"arms":      arms.iter().map(|arm| json!({
+				"pattern": match arm.pattern {
+					Pattern::Int(val) => json!(val),
+					Pattern::Wildcard => Value::Null,
+				},
+				"patternSpan": span_to_json(arm.pattern_span),
+				"value":       exp_to_json(&arm.value),
+			})).collect::<Vec<_>>(),
+		}),
+
+		Func { params, body } => json!({
+			"type":   "Func",
+			"span":   span,
+			"params": params.iter().map(|p| json!({
+				"name": p.name,
+				"span": span_to_json(p.span),
+			})).collect::<Vec<_>>(),
+			"body":   body.iter().map(stmt_to_json).collect::<Vec<_>>(),
+		}),
+	}
+}
+
+fn span_to_json(span: diagnostics::Span) -> Value {
+	json!({ "start": span.start, "end": span.end })
+}
+
+// ------------------------------------------------------------------------------------------------
+// Values
+// ------------------------------------------------------------------------------------------------
+
+// An array becomes a JavaScript array. JSON has no NaN or Infinity, so those numbers become null
+// inside an array; evaluate() gives the numbers themselves when they're not in one. A function
+// can't be sent to JavaScript at all, so it becomes a string of what it looks like.
+pub fn value_to_json(val: &toylang::Value) -> Value {
+	match val {
+		toylang::Value::Num(x)      => json!(x),
+		toylang::Value::Str(s)      => json!(s),
+		toylang::Value::Bool(b)     => json!(b),
+		toylang::Value::Array(a)    => json!(a.iter().map(value_to_json).collect::<Vec<_>>()),
+		toylang::Value::Closure(..) => json!(val.to_code()),
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// Diagnostics
+// ------------------------------------------------------------------------------------------------
+
+/*
+The playground can show errors however it likes (e.g. squiggly underlines in the editor), so each
+one has all its pieces separate. "rendered" is the same text the command-line tools print, for
+when you just want to show that.
+
+Spans are *codepoint* indexes, but JavaScript strings are indexed by UTF-16 code units. They're the
+same for ASCII, but if the code has emoji in it, the JS side has to convert them.
+*/
+pub fn diagnostic_to_json(diag: &Diagnostic, source: &SourceFile) -> Value {
+	let (line, column) = match diag.span {
+		Some(span) => { let (l, c) = source.line_col(span.start); (json!(l), json!(c)) }
+		None       => (Value::Null, Value::Null),
+	};
+
+	json!({
+		"severity": diag.severity.to_string(),
+		"code":     diag.code.map(|c| c.to_string()),
+		"message":  diag.message,
+		"span":     diag.span.map(span_to_json),
+		"line":     line,
+		"column":   column,
+		"notes":    diag.notes,
+		"rendered": diag.render(source),
+	})
+}
@@ -0,0 +1,111 @@
+use serde::Serialize;
+use serde_json::{ json, Value };
+use wasm_bindgen::prelude::*;
+
+use diagnostics::{ Diagnostic, SourceFile };
+use toylang::Env;
+
+/*
+This crate wraps toylang so it can run in a web browser. Build it with wasm-pack:
+
+	wasm-pack build --target web
+
+That makes a `pkg/` folder with a .wasm file and a JavaScript module that loads it. index.html
+shows how to use it.
+
+The #[wasm_bindgen] functions are the ones JavaScript can call. If the code has errors, they
+*throw* an array of error objects (see json.rs for what those look like) instead of panicking,
+since a panic in wasm just kills the whole module with a useless "unreachable executed" message.
+
+Each one has a *_json twin that does the real work and gives back serde_json values, so that it
+can be used (and tested) without a browser.
+*/
+
+mod json;
+
+use crate::json::*;
+
+// the name that shows up in rendered error messages.
+const SOURCE_NAME: &str = "<playground>";
+
+// ------------------------------------------------------------------------------------------------
+// The JavaScript interface
+// ------------------------------------------------------------------------------------------------
+
+// Gives an array of tokens, like [{ kind: "Id", text: "x", start: 0, end: 1 }, ...].
+#[wasm_bindgen]
+pub fn tokenize(src: &str) -> Result<JsValue, JsValue> {
+	to_js_result(tokenize_json(src))
+}
+
+// Gives the AST as nested objects, like { type: "Program", stmts: [...] }.
+#[wasm_bindgen]
+pub fn parse(src: &str) -> Result<JsValue, JsValue> {
+	to_js_result(parse_json(src))
+}
+
+// Runs the code and gives the value of the last statement: a number, a string, a boolean, an
+// array, or undefined if there were no statements (or the last one had no value). Every call
+// starts with a fresh environment, so variables don't carry over between calls.
+#[wasm_bindgen]
+pub fn evaluate(src: &str) -> Result<JsValue, JsValue> {
+	match evaluate_json(src) {
+		Ok(Some(toylang::Value::Num(x)))  => Ok(JsValue::from_f64(x)),
+		Ok(Some(toylang::Value::Str(s)))  => Ok(JsValue::from_str(&s)),
+		Ok(Some(toylang::Value::Bool(b))) => Ok(JsValue::from_bool(b)),
+		Ok(Some(val))                     => Ok(to_js(&value_to_json(&val))),
+		Ok(None)                          => Ok(JsValue::UNDEFINED),
+		Err(errs)                         => Err(to_js(&errs)),
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The Rust interface
+// ------------------------------------------------------------------------------------------------
+
+pub fn tokenize_json(src: &str) -> Result<Value, Value> {
+	let chars = src.chars().collect::<Vec<_>>();
+
+	match toylang::lex(src) {
+		Ok(tokens) => {
+			let tokens = tokens.iter().map(|t| token_to_json(t, &chars)).collect::<Vec<_>>();
+			Ok(json!(tokens))
+		}
+
+		Err(errs)  => Err(errors_to_json(src, &errs)),
+	}
+}
+
+pub fn parse_json(src: &str) -> Result<Value, Value> {
+	match toylang::compile(src) {
+		Ok(program) => Ok(program_to_json(&program)),
+		Err(errs)   => Err(errors_to_json(src, &errs)),
+	}
+}
+
+pub fn evaluate_json(src: &str) -> Result<Option<toylang::Value>, Value> {
+	toylang::eval_str(src, &mut Env::new()).map_err(|errs| errors_to_json(src, &errs))
+}
+
+fn errors_to_json(src: &str, errs: &[Diagnostic]) -> Value {
+	let source = SourceFile::new(SOURCE_NAME, src);
+	json!(errs.iter().map(|e| diagnostic_to_json(e, &source)).collect::<Vec<_>>())
+}
+
+// ------------------------------------------------------------------------------------------------
+// Converting to JavaScript values
+// ------------------------------------------------------------------------------------------------
+
+fn to_js_result(result: Result<Value, Value>) -> Result<JsValue, JsValue> {
+	match result {
+		Ok(val)  => Ok(to_js(&val)),
+		Err(val) => Err(to_js(&val)),
+	}
+}
+
+// by default, serde_wasm_bindgen turns JSON objects into JavaScript Maps. json_compatible() makes
+// them plain objects instead, which is what JSON.parse() would give you.
+fn to_js(val: &Value) -> JsValue {
+	val.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+		.expect("serde_json::Value can always be converted to a JsValue")
+}
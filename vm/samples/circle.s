@@ -0,0 +1,14 @@
+; the same as toylang/samples/circle.calc:
+;   let r = 2.5
+;   area = pi * pow(r, 2)
+
+    push 2.5
+    store r
+    load pi
+    load r
+    push 2
+    call pow 2
+    mul
+    dup             ; one copy to store, and one to give back.
+    store area
+    ret
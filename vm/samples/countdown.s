@@ -0,0 +1,25 @@
+; adds up 1 + 2 + ... + 10, and gives back the total (55).
+
+    push 10
+    store n
+    push 0
+    store total
+
+top:
+    load n
+    jz done         ; stop when n gets to 0.
+
+    load total      ; total = total + n
+    load n
+    add
+    store total
+
+    load n          ; n = n - 1
+    push 1
+    sub
+    store n
+    jmp top
+
+done:
+    load total
+    ret
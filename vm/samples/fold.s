@@ -0,0 +1,20 @@
+; the kind of code a simple compiler makes. try `vm opt samples/fold.s fold.tbc`, then
+; `vm dis fold.tbc` to see what's left.
+
+    push 2          ; x = 2 * 3 - 6 + 10
+    push 3
+    mul
+    push 6
+    sub
+    push 10
+    add
+    store x
+    jmp next        ; jumping to the very next instruction does nothing.
+next:
+    load x
+    push 1          ; multiplying by 1 does nothing either.
+    mul
+    push 0
+    add
+    nop
+    ret
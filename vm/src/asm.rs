@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use diagnostics::{ Diagnostic, Span };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The assembler
+// ------------------------------------------------------------------------------------------------
+
+/*
+Assembly language is just the instructions written as text, one per line:
+
+	; anything after a semicolon is a comment.
+	    push 10         ; numbers are put in the constant table automatically,
+	    store count     ; and so are names.
+	top:                ; a label is a name for the place in the code where it is.
+	    load count
+	    jz done         ; jumps go to labels...
+	    load count
+	    push 1
+	    sub
+	    store count
+	    jmp top
+	done:
+	    halt
+
+A jump can also go to a raw byte offset, like `jmp 0x0010`, though that's mostly useful for
+looking at what the disassembler gives for broken code.
+
+Jumps forward are a problem: when the assembler sees `jz done`, it doesn't know where `done` is
+yet! So it emits the jump with a dummy target, remembers it (a *fixup*), and patches the real
+target in at the end, once it knows where every label is.
+*/
+
+pub fn assemble(src: &str) -> Result<Module, Vec<Diagnostic>> {
+	let mut a = Assembler {
+		module: Module::new(),
+		labels: HashMap::new(),
+		fixups: vec![],
+		errors: vec![],
+	};
+
+	let mut line_start = 0;
+
+	for line in src.split('\n') {
+		a.line(line, line_start);
+		line_start += line.chars().count() + 1;
+	}
+
+	for (offset, label, span) in std::mem::take(&mut a.fixups) {
+		match a.labels.get(label) {
+			Some(&target) => a.module.patch_jump(offset, target),
+			None => a.errors.push(Diagnostic::error(format!("undefined label '{}'", label), span)
+				.with_code(diagnostics::UNDEFINED_LABEL)),
+		}
+	}
+
+	if a.errors.is_empty() {
+		Ok(a.module)
+	} else {
+		Err(a.errors)
+	}
+}
+
+// a word on a line, and where it is.
+type Word<'s> = (&'s str, Span);
+
+struct Assembler<'s> {
+	module: Module,
+	labels: HashMap<&'s str, u32>,
+	fixups: Vec<(usize, &'s str, Span)>, // (offset of the jump, label it goes to, where it was)
+	errors: Vec<Diagnostic>,
+}
+
+impl<'s> Assembler<'s> {
+	fn line(&mut self, line: &'s str, line_start: usize) {
+		let line = match line.find(';') {
+			Some(i) => &line[.. i],
+			None    => line,
+		};
+
+		let mut words = &words(line, line_start)[..];
+
+		// any number of labels can come before the instruction.
+		while let Some(&(word, span)) = words.first() {
+			match word.strip_suffix(':') {
+				Some(label) => { self.label(label, span); words = &words[1 ..]; }
+				None        => break,
+			}
+		}
+
+		if let Some((&(mnemonic, span), operands)) = words.split_first() {
+			if let Err(e) = self.instruction(mnemonic, span, operands) {
+				self.errors.push(e);
+			}
+		}
+	}
+
+	fn label(&mut self, label: &'s str, span: Span) {
+		if self.labels.insert(label, self.module.here()).is_some() {
+			self.errors.push(Diagnostic::error(format!("label '{}' is defined twice", label), span)
+				.with_code(diagnostics::DUPLICATE_LABEL));
+		}
+	}
+
+	fn instruction(&mut self, mnemonic: &'s str, span: Span, operands: &[Word<'s>])
+	-> Result<(), Diagnostic> {
+		let instr = match mnemonic {
+			"push" => {
+				let [(num, span)] = expect_operands::<1>(mnemonic, span, operands)?;
+
+				match num.parse::<f64>() {
+					Ok(val) => Instr::Push(self.module.add_const(val)
+						.map_err(|e| table_full(e, span))?),
+					Err(..) => return Err(bad_operand(format!("'{}' isn't a number", num), span)),
+				}
+			}
+
+			"load" | "store" => {
+				let [(name, name_span)] = expect_operands::<1>(mnemonic, span, operands)?;
+				let name = self.module.add_name(name).map_err(|e| table_full(e, name_span))?;
+				if mnemonic == "load" { Instr::Load(name) } else { Instr::Store(name) }
+			}
+
+			"jmp" | "jz" | "jnz" => {
+				let [(target, span)] = expect_operands::<1>(mnemonic, span, operands)?;
+
+				let addr = match parse_address(target) {
+					Some(addr) => addr,
+					None => {
+						// it's a label; the real target gets patched in later.
+						self.fixups.push((self.module.code.len(), target, span));
+						0
+					}
+				};
+
+				match mnemonic {
+					"jmp" => Instr::Jmp(addr),
+					"jz"  => Instr::Jz(addr),
+					_     => Instr::Jnz(addr),
+				}
+			}
+
+			"call" => {
+				let [(name, name_span), (argc, argc_span)] =
+					expect_operands::<2>(mnemonic, span, operands)?;
+
+				let argc = argc.parse::<u8>().map_err(|_| bad_operand(
+					format!("'{}' isn't a number of arguments (0 to 255)", argc), argc_span))?;
+
+				Instr::Call(self.module.add_name(name).map_err(|e| table_full(e, name_span))?, argc)
+			}
+
+			_ => {
+				let instr = SIMPLE_INSTRS.iter().find(|i| i.mnemonic() == mnemonic).ok_or_else(||
+					Diagnostic::error(format!("unknown instruction '{}'", mnemonic), span)
+						.with_code(diagnostics::UNKNOWN_MNEMONIC))?;
+
+				expect_operands::<0>(mnemonic, span, operands)?;
+				*instr
+			}
+		};
+
+		self.module.emit(instr);
+		Ok(())
+	}
+}
+
+// Splits a line into words, with the span of each. Spans count characters from the start of the
+// whole source, like the rest of the diagnostics do.
+fn words(line: &str, line_start: usize) -> Vec<Word<'_>> {
+	let mut ret = vec![];
+	let mut start = None;
+
+	// (i is a byte index for slicing, and col is a character index for the span.)
+	for (col, (i, c)) in line.char_indices().chain(Some((line.len(), ' '))).enumerate() {
+		match (c.is_whitespace(), start) {
+			(false, None)             => start = Some((i, col)),
+			(true, Some((s, s_col)))  => {
+				ret.push((&line[s .. i], Span::new(line_start + s_col, line_start + col)));
+				start = None;
+			}
+			_ => {}
+		}
+	}
+
+	ret
+}
+
+// gives exactly N operands, or an error if there aren't exactly N.
+fn expect_operands<'s, const N: usize>(mnemonic: &str, span: Span, operands: &[Word<'s>])
+-> Result<[Word<'s>; N], Diagnostic> {
+	use std::convert::TryInto;
+
+	operands.try_into().map_err(|_| {
+		let span = operands.iter().fold(span, |span, (_, s)| span.to(*s));
+		let s = if N == 1 { "" } else { "s" };
+		bad_operand(format!("'{}' takes {} operand{}, but was given {}",
+			mnemonic, N, s, operands.len()), span)
+	})
+}
+
+fn bad_operand(message: String, span: Span) -> Diagnostic {
+	Diagnostic::error(message, span).with_code(diagnostics::BAD_OPERAND)
+}
+
+// the VmError says which byte of the code it was at, but in the source, the span is more useful.
+fn table_full(err: VmError, span: Span) -> Diagnostic {
+	Diagnostic::error(err.message, span).with_code(err.code)
+}
+
+// a number like 16 or 0x10.
+fn parse_address(s: &str) -> Option<u32> {
+	match s.strip_prefix("0x") {
+		Some(hex) => u32::from_str_radix(hex, 16).ok(),
+		None      => s.parse().ok(),
+	}
+}
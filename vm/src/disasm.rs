@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// The disassembler
+// ------------------------------------------------------------------------------------------------
+
+/*
+The disassembler does the opposite of the assembler: it turns bytecode back into text. The output
+can be assembled again, and you get the same bytecode back. Every line also has a comment with
+the offset of the instruction and its raw bytes, so you can find it in a hex editor.
+
+The labels' names are lost when assembling, so the disassembler makes up names for them from
+their offsets: a jump to offset 0x0010 goes to `L0010`.
+*/
+
+pub fn disassemble(module: &Module) -> Result<String, VmError> {
+	let instrs = module.instructions()?;
+	let mut out = String::new();
+
+	writeln!(out, "; {} constants, {} names, {} bytes of code",
+		module.consts.len(), module.names.len(), module.code.len()).unwrap();
+
+	for (i, c) in module.consts.iter().enumerate() {
+		writeln!(out, ";   const #{} = {}", i, c).unwrap();
+	}
+
+	for (i, name) in module.names.iter().enumerate() {
+		writeln!(out, ";   name  #{} = {}", i, name).unwrap();
+	}
+
+	// every offset that's the start of an instruction, and every offset that something jumps to.
+	let starts = instrs.iter().map(|(offset, _)| *offset as u32).collect::<BTreeSet<_>>();
+	let targets = instrs.iter().filter_map(|(_, i)| i.jump_target()).collect::<BTreeSet<_>>();
+
+	for &(offset, instr) in &instrs {
+		if targets.contains(&(offset as u32)) {
+			writeln!(out, "L{:04x}:", offset).unwrap();
+		}
+
+		let text = instr_text(module, instr, &starts);
+		let bytes = &module.code[offset .. offset + instr.size()];
+		let bytes = bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+		writeln!(out, "    {:<24} ; {:04x}: {}", text, offset, bytes).unwrap();
+	}
+
+	Ok(out)
+}
+
+// the instruction as the assembler would want it, with the actual constants and names.
+fn instr_text(module: &Module, instr: Instr, starts: &BTreeSet<u32>) -> String {
+	use Instr::*;
+
+	// if an index is out of range, the bytecode is broken, but we still want to see it.
+	let name = |i: u16| module.names.get(i as usize).cloned()
+		.unwrap_or_else(|| format!("<bad name #{}>", i));
+
+	match instr {
+		Push(i) => match module.consts.get(i as usize) {
+			Some(c) => format!("push {}", c),
+			None    => format!("push <bad const #{}>", i),
+		},
+
+		Load(i) | Store(i) => format!("{} {}", instr.mnemonic(), name(i)),
+
+		// jumps into the middle of an instruction can't have a label.
+		Jmp(t) | Jz(t) | Jnz(t) => match starts.contains(&t) {
+			true  => format!("{} L{:04x}", instr.mnemonic(), t),
+			false => format!("{} {:#06x}", instr.mnemonic(), t),
+		},
+
+		Call(i, argc) => format!("call {} {}", name(i), argc),
+		_             => instr.mnemonic().into(),
+	}
+}
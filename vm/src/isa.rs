@@ -0,0 +1,296 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Instructions
+// ------------------------------------------------------------------------------------------------
+
+/*
+The instruction set architecture (ISA). Every value is an f64, like in toylang. For the
+comparisons and logic instructions, 0 is false and anything else is true, and they give 1 for
+true.
+
+Each instruction is encoded as one *opcode* byte, followed by its operands (if any). Operands
+that are more than one byte are little-endian.
+
+	opcode  mnemonic  operands              what it does
+	------  --------  --------------------  --------------------------------------------------
+	0x00    nop                             nothing
+	0x01    push      const (u16)           pushes constant number `const`
+	0x02    pop                             pops and throws away the top value
+	0x03    dup                             pushes another copy of the top value
+	0x04    load      name (u16)            pushes the value of global variable `name`
+	0x05    store     name (u16)            pops into global variable `name`
+	0x10    add                             pops b, pops a, pushes a + b
+	0x11    sub                             ...  a - b
+	0x12    mul                             ...  a * b
+	0x13    div                             ...  a / b
+	0x14    mod                             ...  a % b
+	0x15    neg                             pops a, pushes -a
+	0x18    lt                              pops b, pops a, pushes a < b
+	0x19    le                              ...  a <= b
+	0x1A    gt                              ...  a > b
+	0x1B    ge                              ...  a >= b
+	0x1C    eq                              ...  a == b
+	0x1D    ne                              ...  a != b
+	0x1E    not                             pops a, pushes !a
+	0x20    jmp       target (u32)          goes to byte `target` of the code
+	0x21    jz        target (u32)          pops a, and goes to `target` if a is 0 (false)
+	0x22    jnz       target (u32)          pops a, and goes to `target` if a is not 0 (true)
+	0x30    call      name (u16), argc (u8) pops `argc` arguments, calls builtin function
+	                                        `name` with them, and pushes what it returns
+	0x31    ret                             pops a, and stops the program, giving back a
+	0x32    halt                            stops the program without giving anything back
+	0x40    alloc                           pops n, makes room for n values in memory, and
+	                                        pushes the address of the first one
+	0x41    loadm                           pops addr, pushes the value at address `addr`
+	0x42    storem                          pops v, pops addr, puts v at address `addr`
+	0x43    check                           pops n, pops i, and stops the program with an error
+	                                        unless i is a whole number from 0 to n - 1
+
+The "const" and "name" operands are indexes into the Module's constant and name tables. That
+keeps instructions small: `push 3.14159` is 3 bytes instead of 9.
+
+Memory is separate from the stack and the globals. It's made of 8-byte values, like MIPS memory
+full of doubles, so addresses are multiples of 8. `alloc` also puts n just before the first value
+(at address - 8), so arrays know their length; see the ir crate's lower.rs.
+*/
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instr {
+	Nop,
+	Push(u16),
+	Pop,
+	Dup,
+	Load(u16),
+	Store(u16),
+	Add,
+	Sub,
+	Mul,
+	Div,
+	Mod,
+	Neg,
+	Lt,
+	Le,
+	Gt,
+	Ge,
+	Eq,
+	Ne,
+	Not,
+	Jmp(u32),
+	Jz(u32),
+	Jnz(u32),
+	Call(u16, u8),
+	Ret,
+	Halt,
+	Alloc,
+	LoadM,
+	StoreM,
+	Check,
+}
+
+// every instruction without operands, so the assembler can look them up by mnemonic.
+pub const SIMPLE_INSTRS: &[Instr] = {
+	use Instr::*;
+	&[Nop, Pop, Dup, Add, Sub, Mul, Div, Mod, Neg, Lt, Le, Gt, Ge, Eq, Ne, Not, Ret, Halt,
+		Alloc, LoadM, StoreM, Check]
+};
+
+impl Instr {
+	pub fn opcode(&self) -> u8 {
+		use Instr::*;
+
+		match self {
+			Nop       => 0x00,
+			Push(..)  => 0x01,
+			Pop       => 0x02,
+			Dup       => 0x03,
+			Load(..)  => 0x04,
+			Store(..) => 0x05,
+			Add       => 0x10,
+			Sub       => 0x11,
+			Mul       => 0x12,
+			Div       => 0x13,
+			Mod       => 0x14,
+			Neg       => 0x15,
+			Lt        => 0x18,
+			Le        => 0x19,
+			Gt        => 0x1A,
+			Ge        => 0x1B,
+			Eq        => 0x1C,
+			Ne        => 0x1D,
+			Not       => 0x1E,
+			Jmp(..)   => 0x20,
+			Jz(..)    => 0x21,
+			Jnz(..)   => 0x22,
+			Call(..)  => 0x30,
+			Ret       => 0x31,
+			Halt      => 0x32,
+			Alloc     => 0x40,
+			LoadM     => 0x41,
+			StoreM    => 0x42,
+			Check     => 0x43,
+		}
+	}
+
+	pub fn mnemonic(&self) -> &'static str {
+		use Instr::*;
+
+		match self {
+			Nop       => "nop",
+			Push(..)  => "push",
+			Pop       => "pop",
+			Dup       => "dup",
+			Load(..)  => "load",
+			Store(..) => "store",
+			Add       => "add",
+			Sub       => "sub",
+			Mul       => "mul",
+			Div       => "div",
+			Mod       => "mod",
+			Neg       => "neg",
+			Lt        => "lt",
+			Le        => "le",
+			Gt        => "gt",
+			Ge        => "ge",
+			Eq        => "eq",
+			Ne        => "ne",
+			Not       => "not",
+			Jmp(..)   => "jmp",
+			Jz(..)    => "jz",
+			Jnz(..)   => "jnz",
+			Call(..)  => "call",
+			Ret       => "ret",
+			Halt      => "halt",
+			Alloc     => "alloc",
+			LoadM     => "loadm",
+			StoreM    => "storem",
+			Check     => "check",
+		}
+	}
+
+	// How many bytes this takes up when encoded.
+	pub fn size(&self) -> usize {
+		use Instr::*;
+
+		match self {
+			Push(..) | Load(..) | Store(..) => 3,
+			Jmp(..) | Jz(..) | Jnz(..)      => 5,
+			Call(..)                        => 4,
+			_                               => 1,
+		}
+	}
+
+	// Where this jumps to, if it's a jump.
+	pub fn jump_target(&self) -> Option<u32> {
+		match self {
+			Instr::Jmp(t) | Instr::Jz(t) | Instr::Jnz(t) => Some(*t),
+			_                                            => None,
+		}
+	}
+
+	// The same jump, but going somewhere else.
+	pub fn with_jump_target(self, target: u32) -> Instr {
+		match self {
+			Instr::Jmp(..) => Instr::Jmp(target),
+			Instr::Jz(..)  => Instr::Jz(target),
+			Instr::Jnz(..) => Instr::Jnz(target),
+			_              => panic!("with_jump_target() called on {:?}", self),
+		}
+	}
+
+	// Does control never go on to the next instruction after this one?
+	pub fn is_terminator(&self) -> bool {
+		matches!(self, Instr::Jmp(..) | Instr::Ret | Instr::Halt)
+	}
+
+	pub fn encode(&self, out: &mut Vec<u8>) {
+		use Instr::*;
+
+		out.push(self.opcode());
+
+		match self {
+			Push(i) | Load(i) | Store(i) => out.extend(&i.to_le_bytes()),
+			Jmp(t) | Jz(t) | Jnz(t)      => out.extend(&t.to_le_bytes()),
+			Call(name, argc)             => { out.extend(&name.to_le_bytes()); out.push(*argc); }
+			_                            => {}
+		}
+	}
+
+	// Decodes the instruction that starts at byte `offset` of `code`.
+	pub fn decode(code: &[u8], offset: usize) -> Result<Instr, VmError> {
+		use Instr::*;
+
+		let opcode = match code.get(offset) {
+			Some(op) => *op,
+			None => return Err(VmError::new(offset, diagnostics::INVALID_BYTECODE,
+				"ran off the end of the code")),
+		};
+
+		// reads `N` bytes of operands after the opcode.
+		let operand = |n: usize| -> Result<&[u8], VmError> {
+			code.get(offset + 1 .. offset + 1 + n).ok_or_else(|| VmError::new(offset,
+				diagnostics::INVALID_BYTECODE, "the code ends in the middle of an instruction"))
+		};
+
+		let u16_at = || -> Result<u16, VmError> {
+			let b = operand(2)?;
+			Ok(u16::from_le_bytes([b[0], b[1]]))
+		};
+
+		let u32_at = || -> Result<u32, VmError> {
+			let b = operand(4)?;
+			Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+		};
+
+		Ok(match opcode {
+			0x00 => Nop,
+			0x01 => Push(u16_at()?),
+			0x02 => Pop,
+			0x03 => Dup,
+			0x04 => Load(u16_at()?),
+			0x05 => Store(u16_at()?),
+			0x10 => Add,
+			0x11 => Sub,
+			0x12 => Mul,
+			0x13 => Div,
+			0x14 => Mod,
+			0x15 => Neg,
+			0x18 => Lt,
+			0x19 => Le,
+			0x1A => Gt,
+			0x1B => Ge,
+			0x1C => Eq,
+			0x1D => Ne,
+			0x1E => Not,
+			0x20 => Jmp(u32_at()?),
+			0x21 => Jz(u32_at()?),
+			0x22 => Jnz(u32_at()?),
+			0x30 => Call(u16_at()?, operand(3)?[2]),
+			0x31 => Ret,
+			0x32 => Halt,
+			0x40 => Alloc,
+			0x41 => LoadM,
+			0x42 => StoreM,
+			0x43 => Check,
+			_    => return Err(VmError::new(offset, diagnostics::INVALID_BYTECODE,
+				format!("{:#04x} isn't an opcode", opcode))),
+		})
+	}
+}
+
+// Shows the instruction with raw operands, like `push #3` or `jmp @0x0010`. The disassembler
+// shows them in a nicer way, since it knows what the indexes refer to.
+impl Display for Instr {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		use Instr::*;
+
+		match self {
+			Push(i) | Load(i) | Store(i) => write!(f, "{} #{}", self.mnemonic(), i),
+			Jmp(t) | Jz(t) | Jnz(t)      => write!(f, "{} @{:#06x}", self.mnemonic(), t),
+			Call(name, argc)             => write!(f, "call #{} {}", name, argc),
+			_                            => write!(f, "{}", self.mnemonic()),
+		}
+	}
+}
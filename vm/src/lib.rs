@@ -0,0 +1,80 @@
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use diagnostics::{ Diagnostic, ErrorCode, Severity };
+
+/*
+A *virtual machine* (VM) is a CPU that only exists in software. Instead of compiling to x86 or MIPS,
+a compiler can compile to the VM's instructions (its *bytecode*), and then the VM runs them. Java,
+Python, Lua, and C# all work like this. It's simpler than real machine code, and the same bytecode
+runs anywhere the VM does.
+
+This is a *stack machine*: instead of registers, instructions take their inputs from the top of a
+stack and push their results back onto it. So `a * b + c` is:
+
+	load a     ; stack: a
+	load b     ; stack: a b
+	mul        ; stack: (a*b)
+	load c     ; stack: (a*b) c
+	add        ; stack: (a*b+c)
+
+which is the expression in *postfix* order. That makes it really easy to compile to: just walk the
+AST, and output each node's instruction after its children's.
+
+This crate has:
+
+- isa.rs:      the instructions, and how they're encoded into bytes.
+- module.rs:   a compiled program (code, constants, and names) and its file format.
+- asm.rs:      an assembler, which turns text like the above into a Module.
+- disasm.rs:   a disassembler, which turns a Module back into text.
+- machine.rs:  the VM itself, which runs a Module.
+- peephole.rs: a simple optimizer for bytecode.
+*/
+
+mod asm;
+mod disasm;
+mod isa;
+mod machine;
+mod module;
+mod peephole;
+
+pub use crate::asm::*;
+pub use crate::disasm::*;
+pub use crate::isa::*;
+pub use crate::machine::*;
+pub use crate::module::*;
+pub use crate::peephole::*;
+
+// ------------------------------------------------------------------------------------------------
+// VmError
+// ------------------------------------------------------------------------------------------------
+
+// Something went wrong while reading or running bytecode. There's no source code to point at,
+// so errors say which byte they happened at instead.
+#[derive(Debug, PartialEq, Clone)]
+pub struct VmError {
+	pub offset:  usize,     // the byte in the code (or the file) where it happened.
+	pub code:    ErrorCode,
+	pub message: String,
+}
+
+impl VmError {
+	pub fn new(offset: usize, code: ErrorCode, message: impl Into<String>) -> Self {
+		VmError { offset, code, message: message.into() }
+	}
+}
+
+impl Display for VmError {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		write!(f, "{}", self.message)
+	}
+}
+
+impl std::error::Error for VmError {}
+
+impl From<VmError> for Diagnostic {
+	fn from(err: VmError) -> Diagnostic {
+		Diagnostic::new(Severity::Error, err.message, None)
+			.with_code(err.code)
+			.with_note(format!("at byte {:#06x}", err.offset))
+	}
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Machine
+// ------------------------------------------------------------------------------------------------
+
+// A builtin function, written in Rust. It's the same as toylang's BuiltinFn::Math, so a compiler
+// can give the VM the same builtins the interpreter has.
+pub type Native = fn(&[f64]) -> f64;
+
+// The stack can't get bigger than this many values.
+pub const MAX_STACK: usize = 1 << 16;
+
+// Memory can't get bigger than this many values (128 MB).
+pub const MAX_MEMORY: usize = 1 << 24;
+
+// The VM. It holds onto the global variables between runs, like toylang's Env, so you can run
+// several modules one after another and they can see each other's variables. Memory is kept too,
+// since a global might hold an address.
+#[derive(Debug, Clone)]
+pub struct Machine {
+	globals: HashMap<String, f64>,
+	natives: HashMap<String, (usize, Native)>,
+	memory:  Vec<f64>, // value k is at address 8 * k.
+	// if true, every instruction and the stack after it is printed as it runs.
+	pub trace: bool,
+}
+
+impl Default for Machine {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Machine {
+	// A machine with the same math constants and functions as toylang's Env::new().
+	pub fn new() -> Self {
+		let mut ret = Self::empty();
+		ret.set_global("pi", std::f64::consts::PI);
+		ret.set_global("e",  std::f64::consts::E);
+		ret.add_native("sqrt", 1, |a| a[0].sqrt());
+		ret.add_native("abs",  1, |a| a[0].abs());
+		ret.add_native("sin",  1, |a| a[0].sin());
+		ret.add_native("cos",  1, |a| a[0].cos());
+		ret.add_native("min",  2, |a| a[0].min(a[1]));
+		ret.add_native("max",  2, |a| a[0].max(a[1]));
+		ret.add_native("pow",  2, |a| a[0].powf(a[1]));
+		ret
+	}
+
+	pub fn empty() -> Self {
+		Machine { globals: HashMap::new(), natives: HashMap::new(), memory: vec![], trace: false }
+	}
+
+	pub fn get_global(&self, name: &str) -> Option<f64> {
+		self.globals.get(name).copied()
+	}
+
+	pub fn set_global(&mut self, name: &str, val: f64) {
+		self.globals.insert(name.into(), val);
+	}
+
+	// All the globals and their values, sorted by name.
+	pub fn globals(&self) -> Vec<(&str, f64)> {
+		let mut ret = self.globals.iter().map(|(name, v)| (name.as_str(), *v)).collect::<Vec<_>>();
+		ret.sort_by(|a, b| a.0.cmp(b.0));
+		ret
+	}
+
+	pub fn add_native(&mut self, name: &str, arity: usize, func: Native) {
+		self.natives.insert(name.into(), (arity, func));
+	}
+
+	// Runs the module from the beginning until it hits `ret` (which gives back a value) or `halt`
+	// (which doesn't). Any globals it sets are kept, even if there's an error.
+	pub fn run(&mut self, module: &Module) -> Result<Option<f64>, VmError> {
+		// looking up globals by name for every `load` would be slow, so they're copied into an
+		// array indexed the same way as the module's names. it's None if it's not set yet.
+		let mut globals = module.names.iter()
+			.map(|name| self.globals.get(name).copied())
+			.collect::<Vec<_>>();
+
+		// execute() only borrows self, so memory is moved out while it runs.
+		let mut memory = std::mem::take(&mut self.memory);
+		let ret = self.execute(module, &mut globals, &mut memory);
+		self.memory = memory;
+
+		for (name, val) in module.names.iter().zip(globals) {
+			if let Some(val) = val {
+				self.globals.insert(name.clone(), val);
+			}
+		}
+
+		ret
+	}
+
+	// the fetch-decode-execute loop, which is what a real CPU does too.
+	fn execute(&self, module: &Module, globals: &mut [Option<f64>], memory: &mut Vec<f64>)
+	-> Result<Option<f64>, VmError> {
+		use Instr::*;
+
+		let mut stack: Vec<f64> = vec![];
+		let mut pc = 0; // the *program counter*: the offset of the next instruction.
+
+		loop {
+			let instr = Instr::decode(&module.code, pc)?;
+			let mut next = pc + instr.size();
+
+			// a little helper to make errors at this instruction.
+			let err = |code, message: String| VmError::new(pc, code, message);
+
+			macro_rules! pop {
+				() => {
+					stack.pop().ok_or_else(|| err(diagnostics::STACK_UNDERFLOW,
+						format!("'{}' needs more values than there are on the stack",
+							instr.mnemonic())))?
+				};
+			}
+
+			let name = |i: u16| module.names.get(i as usize).ok_or_else(|| err(
+				diagnostics::INVALID_BYTECODE, format!("there's no name #{}", i)));
+
+			// turns an address into an index into memory.
+			let slot = |addr: f64, len: usize| -> Result<usize, VmError> {
+				let k = addr / 8.0;
+
+				if k.fract() == 0.0 && k >= 0.0 && k < len as f64 {
+					Ok(k as usize)
+				} else {
+					Err(err(diagnostics::BAD_ADDRESS, format!("{} isn't a valid address", addr)))
+				}
+			};
+
+			match instr {
+				Nop => {}
+
+				Push(i) => match module.consts.get(i as usize) {
+					Some(&c) => stack.push(c),
+					None => return Err(err(diagnostics::INVALID_BYTECODE,
+						format!("there's no constant #{}", i))),
+				},
+
+				Pop => { pop!(); }
+				Dup => { let a = pop!(); stack.push(a); stack.push(a); }
+
+				Load(i) => {
+					let name = name(i)?;
+
+					match globals[i as usize] {
+						Some(val) => stack.push(val),
+						None => return Err(err(diagnostics::UNDEFINED_NAME,
+							format!("undefined variable '{}'", name))),
+					}
+				}
+
+				Store(i) => {
+					name(i)?;
+					globals[i as usize] = Some(pop!());
+				}
+
+				Neg | Not => {
+					let a = pop!();
+					stack.push(unary(instr, a));
+				}
+
+				Add | Sub | Mul | Div | Mod | Lt | Le | Gt | Ge | Eq | Ne => {
+					let b = pop!();
+					let a = pop!();
+					stack.push(binary(instr, a, b));
+				}
+
+				Jmp(t) => next = t as usize,
+				Jz(t)  => if pop!() == 0.0 { next = t as usize },
+				Jnz(t) => if pop!() != 0.0 { next = t as usize },
+
+				Call(i, argc) => {
+					let name = name(i)?;
+
+					let &(arity, func) = match self.natives.get(name) {
+						Some(native) => native,
+						None => return Err(err(diagnostics::NOT_A_FUNCTION,
+							format!("no builtin function named '{}'", name))),
+					};
+
+					if arity != argc as usize {
+						return Err(err(diagnostics::WRONG_ARG_COUNT, format!(
+							"'{}' takes {} arguments, but was given {}", name, arity, argc)));
+					}
+
+					if stack.len() < arity {
+						return Err(err(diagnostics::STACK_UNDERFLOW, format!(
+							"'{}' needs {} arguments, but the stack only has {} values",
+							name, arity, stack.len())));
+					}
+
+					// the arguments are on the stack in order, with the last one on top.
+					let args = stack.split_off(stack.len() - arity);
+					stack.push(func(&args));
+				}
+
+				Ret => {
+					let ret = pop!();
+					self.trace(pc, instr, &stack);
+					return Ok(Some(ret));
+				}
+
+				Halt => {
+					self.trace(pc, instr, &stack);
+					return Ok(None);
+				}
+
+				Alloc => {
+					let n = pop!();
+
+					let too_big = memory.len() as f64 + n + 1.0 > MAX_MEMORY as f64;
+
+					if n.fract() != 0.0 || n < 0.0 || too_big {
+						return Err(err(diagnostics::WRONG_VALUE_TYPE,
+							format!("can't make room for {} values", n)));
+					}
+
+					memory.push(n);
+					stack.push(8.0 * memory.len() as f64);
+					memory.resize(memory.len() + n as usize, 0.0);
+				}
+
+				LoadM => {
+					let k = slot(pop!(), memory.len())?;
+					stack.push(memory[k]);
+				}
+
+				StoreM => {
+					let v = pop!();
+					let k = slot(pop!(), memory.len())?;
+					memory[k] = v;
+				}
+
+				Check => {
+					let n = pop!();
+					let i = pop!();
+
+					if i.fract() != 0.0 {
+						return Err(err(diagnostics::WRONG_VALUE_TYPE,
+							format!("an index has to be a whole number, not {}", i)));
+					} else if i < 0.0 || i >= n {
+						return Err(err(diagnostics::INDEX_OUT_OF_BOUNDS,
+							format!("index {} is out of bounds (the array has {} values)", i, n)));
+					}
+				}
+			}
+
+			if stack.len() > MAX_STACK {
+				return Err(err(diagnostics::STACK_OVERFLOW,
+					format!("the stack has more than {} values on it", MAX_STACK)));
+			}
+
+			self.trace(pc, instr, &stack);
+			pc = next;
+		}
+	}
+
+	fn trace(&self, pc: usize, instr: Instr, stack: &[f64]) {
+		if self.trace {
+			let stack = stack.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+			println!("{:04x}  {:<16} [{}]", pc, instr.to_string(), stack.join(", "));
+		}
+	}
+}
+
+// these are also used by the peephole optimizer to fold constants, so that it's guaranteed to get
+// the same answers as running the code would.
+pub(crate) fn unary(instr: Instr, a: f64) -> f64 {
+	match instr {
+		Instr::Neg => -a,
+		Instr::Not => from_bool(a == 0.0),
+		_          => unreachable!(),
+	}
+}
+
+pub(crate) fn binary(instr: Instr, a: f64, b: f64) -> f64 {
+	use Instr::*;
+
+	match instr {
+		Add => a + b,
+		Sub => a - b,
+		Mul => a * b,
+		Div => a / b,
+		Mod => a % b,
+		Lt  => from_bool(a <  b),
+		Le  => from_bool(a <= b),
+		Gt  => from_bool(a >  b),
+		Ge  => from_bool(a >= b),
+		Eq  => from_bool(a == b),
+		Ne  => from_bool(a != b),
+		_   => unreachable!(),
+	}
+}
+
+fn from_bool(b: bool) -> f64 {
+	if b { 1.0 } else { 0.0 }
+}
@@ -0,0 +1,104 @@
+use std::process::exit;
+
+use diagnostics::{ render_all_colored, ColorChoice, Diagnostic, SourceFile };
+use vm::*;
+
+// `vm asm prog.s prog.tbc` assembles a file into bytecode, and `vm dis prog.tbc` shows what's in
+// a bytecode file. `vm run` runs either one (it assembles .s files first), and `--trace` shows
+// every instruction as it runs, along with what's on the stack afterwards. `vm opt` runs the
+// peephole optimizer on a file, and says what it did.
+
+const USAGE: &str = "usage: vm asm <file.s> <file.tbc>
+       vm dis <file.tbc>
+       vm opt <file.tbc or file.s> <file.tbc>
+       vm run <file.tbc or file.s> [--trace] [--color=always|never|auto]";
+
+fn main() {
+	let color = ColorChoice::from_args(std::env::args()).unwrap_or_else(|e| {
+		eprintln!("error: {}", e);
+		exit(2);
+	});
+	let args = std::env::args().skip(1).filter(|a| !a.starts_with("--")).collect::<Vec<_>>();
+	let trace = std::env::args().any(|a| a == "--trace");
+
+	let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		["asm", src, dst] => assemble_file(src, color).and_then(|m| write_file(dst, &m)),
+		["dis", path]     => read_module(path, color).and_then(|m| disassemble_file(&m, color)),
+		["run", path]     => read_module(path, color).and_then(|m| run(&m, trace, color)),
+		["opt", src, dst] => read_module(src, color).and_then(|m| optimize(&m, color))
+			.and_then(|m| write_file(dst, &m)),
+		_                 => {
+			eprintln!("{}", USAGE);
+			exit(2);
+		}
+	};
+
+	// like toylang, 1 means the program had errors, and 2 means a file couldn't be read/written.
+	if let Err(code) = result {
+		exit(code);
+	}
+}
+
+fn assemble_file(path: &str, color: ColorChoice) -> Result<Module, i32> {
+	let text = std::fs::read_to_string(path).map_err(|e| {
+		eprintln!("error: could not read '{}': {}", path, e);
+		2
+	})?;
+
+	assemble(&text).map_err(|errs| {
+		eprint!("{}", render_all_colored(&errs, &SourceFile::new(path, &text), color));
+		1
+	})
+}
+
+// .s files are assembled; anything else is read as bytecode.
+fn read_module(path: &str, color: ColorChoice) -> Result<Module, i32> {
+	if path.ends_with(".s") {
+		return assemble_file(path, color);
+	}
+
+	let bytes = std::fs::read(path).map_err(|e| {
+		eprintln!("error: could not read '{}': {}", path, e);
+		2
+	})?;
+
+	Module::from_bytes(&bytes).map_err(|e| report(e, path, color))
+}
+
+fn write_file(path: &str, module: &Module) -> Result<(), i32> {
+	std::fs::write(path, module.to_bytes()).map_err(|e| {
+		eprintln!("error: could not write '{}': {}", path, e);
+		2
+	})
+}
+
+fn disassemble_file(module: &Module, color: ColorChoice) -> Result<(), i32> {
+	let text = disassemble(module).map_err(|e| report(e, "<code>", color))?;
+	print!("{}", text);
+	Ok(())
+}
+
+fn optimize(module: &Module, color: ColorChoice) -> Result<Module, i32> {
+	let (module, report) = peephole(module).map_err(|e| report(e, "<code>", color))?;
+	println!("{}", report);
+	Ok(module)
+}
+
+fn run(module: &Module, trace: bool, color: ColorChoice) -> Result<(), i32> {
+	let mut machine = Machine::new();
+	machine.trace = trace;
+
+	// `halt` doesn't give back a value, so there's nothing to print.
+	if let Some(val) = machine.run(module).map_err(|e| report(e, "<code>", color))? {
+		println!("{}", val);
+	}
+
+	Ok(())
+}
+
+// VmErrors don't have spans, so the "source file" is just used for its name.
+fn report(err: VmError, name: &str, color: ColorChoice) -> i32 {
+	let diag = Diagnostic::from(err);
+	eprint!("{}", render_all_colored(&[diag], &SourceFile::new(name, ""), color));
+	1
+}
@@ -0,0 +1,246 @@
+use std::convert::TryFrom;
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Module
+// ------------------------------------------------------------------------------------------------
+
+// A compiled program: the encoded instructions, plus the tables of constants and names that they
+// refer to by index.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Module {
+	pub consts: Vec<f64>,
+	pub names:  Vec<String>, // global variables and builtin functions.
+	pub code:   Vec<u8>,
+}
+
+impl Module {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Gives the index of this constant in the table, adding it if it isn't there yet. Indexes are
+	// u16s, so if the table already has 65,536 constants and this isn't one of them, it's an error.
+	pub fn add_const(&mut self, val: f64) -> Result<u16, VmError> {
+		// comparing the bits instead of the values makes 0.0 and -0.0 different constants (they
+		// are different!), and lets NaN be found, since NaN != NaN.
+		match self.consts.iter().position(|c| c.to_bits() == val.to_bits()) {
+			Some(i) => Ok(i as u16),
+			None => {
+				let i = self.next_index(self.consts.len(), "constants")?;
+				self.consts.push(val);
+				Ok(i)
+			}
+		}
+	}
+
+	// Same, for names.
+	pub fn add_name(&mut self, name: &str) -> Result<u16, VmError> {
+		match self.names.iter().position(|n| n == name) {
+			Some(i) => Ok(i as u16),
+			None => {
+				let i = self.next_index(self.names.len(), "names")?;
+				self.names.push(name.into());
+				Ok(i)
+			}
+		}
+	}
+
+	// the index that a new entry would get in a table with `len` things in it.
+	fn next_index(&self, len: usize, what: &str) -> Result<u16, VmError> {
+		u16::try_from(len).map_err(|_| VmError::new(self.code.len(), diagnostics::TABLE_FULL,
+			format!("a module can't have more than {} different {}", len, what)))
+	}
+
+	// Adds an instruction to the end of the code, and gives back its offset.
+	pub fn emit(&mut self, instr: Instr) -> usize {
+		let offset = self.code.len();
+		instr.encode(&mut self.code);
+		offset
+	}
+
+	// The offset that the next emitted instruction will be at. Jumps backwards go here.
+	pub fn here(&self) -> u32 {
+		self.code.len() as u32
+	}
+
+	// Changes where the jump at `offset` goes. A compiler doesn't know where a forward jump goes
+	// until it gets there, so it emits the jump with a dummy target, and patches it later.
+	pub fn patch_jump(&mut self, offset: usize, target: u32) {
+		let instr = Instr::decode(&self.code, offset).expect("patching a bad instruction");
+		let mut bytes = vec![];
+		instr.with_jump_target(target).encode(&mut bytes);
+		self.code[offset .. offset + bytes.len()].copy_from_slice(&bytes);
+	}
+
+	// Decodes all the instructions, along with the offset of each one.
+	pub fn instructions(&self) -> Result<Vec<(usize, Instr)>, VmError> {
+		let mut ret = vec![];
+		let mut offset = 0;
+
+		while offset < self.code.len() {
+			let instr = Instr::decode(&self.code, offset)?;
+			ret.push((offset, instr));
+			offset += instr.size();
+		}
+
+		Ok(ret)
+	}
+}
+
+// ------------------------------------------------------------------------------------------------
+// The file format
+// ------------------------------------------------------------------------------------------------
+
+/*
+A bytecode file (.tbc, for "toy bytecode") is laid out like this. Everything is little-endian.
+
+	size          what
+	------------  ---------------------------------------------------
+	4 bytes       the *magic number*: the bytes 'T' 'B' 'C' 0
+	u16           the format version (1)
+	u16           how many constants there are
+	8 bytes each  the constants, as f64s
+	u16           how many names there are
+	(each name)   a u16 length, then that many bytes of UTF-8
+	u32           how many bytes of code there are
+	(the code)
+
+The magic number is so that programs can tell this is a bytecode file, and not a JPEG or a text
+file or something. Almost every binary file format starts with one. The version is so that if
+the format ever changes, the VM can tell old files from new ones.
+
+Try `vm dis` on a file, and look at it in a hex editor too. It's small enough to understand every
+single byte!
+*/
+
+pub const MAGIC: &[u8; 4] = b"TBC\0";
+pub const VERSION: u16 = 1;
+
+impl Module {
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut out = vec![];
+		out.extend(MAGIC);
+		out.extend(&VERSION.to_le_bytes());
+
+		out.extend(&(self.consts.len() as u16).to_le_bytes());
+
+		for c in &self.consts {
+			out.extend(&c.to_le_bytes());
+		}
+
+		out.extend(&(self.names.len() as u16).to_le_bytes());
+
+		for name in &self.names {
+			out.extend(&(name.len() as u16).to_le_bytes());
+			out.extend(name.as_bytes());
+		}
+
+		out.extend(&(self.code.len() as u32).to_le_bytes());
+		out.extend(&self.code);
+		out
+	}
+
+	// The errors give the offset in the *file* where something went wrong.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Module, VmError> {
+		let mut r = Reader { bytes, pos: 0 };
+
+		if r.take(4)? != MAGIC {
+			return Err(r.error(0, "this isn't a bytecode file (the magic number is wrong)"));
+		}
+
+		let version = r.u16()?;
+
+		if version != VERSION {
+			return Err(r.error(4, format!("this is version {} bytecode, but only version {} is \
+				supported", version, VERSION)));
+		}
+
+		let num_consts = r.u16()?;
+		let mut consts = vec![];
+
+		for _ in 0 .. num_consts {
+			let b = r.take(8)?;
+			consts.push(f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]));
+		}
+
+		let num_names = r.u16()?;
+		let mut names = vec![];
+
+		for _ in 0 .. num_names {
+			let len = r.u16()?;
+			let start = r.pos;
+
+			match String::from_utf8(r.take(len as usize)?.to_vec()) {
+				Ok(name) => names.push(name),
+				Err(..)  => return Err(r.error(start, "a name isn't valid UTF-8")),
+			}
+		}
+
+		let code_len = r.u32()?;
+		let code = r.take(code_len as usize)?.to_vec();
+
+		if r.pos != bytes.len() {
+			return Err(r.error(r.pos, "there's extra stuff after the end of the code"));
+		}
+
+		Ok(Module { consts, names, code })
+	}
+}
+
+struct Reader<'b> {
+	bytes: &'b [u8],
+	pos:   usize,
+}
+
+impl<'b> Reader<'b> {
+	fn take(&mut self, n: usize) -> Result<&'b [u8], VmError> {
+		match self.bytes.get(self.pos .. self.pos + n) {
+			Some(b) => { self.pos += n; Ok(b) }
+			None    => Err(self.error(self.pos, "the file ends too soon")),
+		}
+	}
+
+	fn u16(&mut self) -> Result<u16, VmError> {
+		let b = self.take(2)?;
+		Ok(u16::from_le_bytes([b[0], b[1]]))
+	}
+
+	fn u32(&mut self) -> Result<u32, VmError> {
+		let b = self.take(4)?;
+		Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+	}
+
+	fn error(&self, offset: usize, message: impl Into<String>) -> VmError {
+		VmError::new(offset, diagnostics::INVALID_BYTECODE, message)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tables_hold_65536_things() {
+		// (filling them with add_const() and add_name() would take a while, since they search
+		// the whole table every time.)
+		let mut module = Module::new();
+		module.consts = (0 .. u16::MAX).map(|i| i as f64).collect();
+		module.names = (0 .. u16::MAX).map(|i| format!("x{}", i)).collect();
+
+		// there's room for one more of each...
+		assert_eq!(module.add_const(0.5), Ok(u16::MAX));
+		assert_eq!(module.add_name("y"), Ok(u16::MAX));
+
+		// and the ones that are already there can still be found...
+		assert_eq!(module.add_const(5.0), Ok(5));
+		assert_eq!(module.add_name("x5"), Ok(5));
+
+		// but then there's no room for new ones.
+		assert_eq!(module.add_const(1.5).unwrap_err().code, diagnostics::TABLE_FULL);
+		assert_eq!(module.add_name("z").unwrap_err().code, diagnostics::TABLE_FULL);
+		assert_eq!(module.consts.len(), 65536);
+		assert_eq!(module.names.len(), 65536);
+	}
+}
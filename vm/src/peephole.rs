@@ -0,0 +1,274 @@
+use std::collections::{ BTreeMap, HashSet };
+use std::fmt::{ Display, Formatter, Result as FmtResult };
+
+use crate::*;
+
+// ------------------------------------------------------------------------------------------------
+// Peephole optimization
+// ------------------------------------------------------------------------------------------------
+
+/*
+A *peephole optimizer* slides a small "window" (the peephole) over the code, looking for short
+sequences of instructions that can be replaced with something better. It doesn't know anything
+about what the program does as a whole; it just knows some patterns. It's simple, but it cleans up
+a lot of the silly code that simple compilers produce. The rules here are:
+
+	rule            before                  after
+	--------------  ----------------------  -------------------------
+	nop             nop                     (nothing)
+	identity        push 0; add             (nothing)   (x + 0 = x)
+	                push 0; sub             (nothing)   (x - 0 = x)
+	                push 1; mul             (nothing)   (x * 1 = x)
+	                push 1; div             (nothing)   (x / 1 = x)
+	fold            push k1; push k2; mul   push k      (k = k1 * k2, and same for other ops)
+	                push k1; neg            push k      (k = -k1, and same for not)
+	push-pop        push k; pop             (nothing)
+	jump-to-next    jmp L; L:               L:
+	                jz L; L:                pop; L:     (it has to get rid of the condition!)
+
+One rule firing can let another one fire: `push 2; push 3; mul; push 6; sub` folds into
+`push 6; push 6; sub`, which folds into `push 0`. So it keeps going over the code until nothing
+changes.
+
+There's one catch. If something jumps into the *middle* of a window, the window isn't really a
+sequence: in
+
+	    push 2
+	L:  push 3
+	    mul
+
+the `mul` might run after `push 3` without `push 2` ever happening. So windows that have a jump
+target inside them (anywhere but at the start) are left alone.
+*/
+
+// What the optimizer did.
+#[derive(Debug, Clone, Default)]
+pub struct PeepholeReport {
+	pub fired:        BTreeMap<&'static str, usize>, // how many times each rule was used.
+	pub bytes_before: usize,                         // how long the code was.
+	pub bytes_after:  usize,
+}
+
+impl PeepholeReport {
+	pub fn bytes_saved(&self) -> usize {
+		self.bytes_before - self.bytes_after
+	}
+}
+
+impl Display for PeepholeReport {
+	fn fmt(&self, f: &mut Formatter) -> FmtResult {
+		for (rule, count) in &self.fired {
+			writeln!(f, "{:<14} fired {} time{}", rule, count, if *count == 1 { "" } else { "s" })?;
+		}
+
+		write!(f, "saved {} bytes of code ({} -> {})",
+			self.bytes_saved(), self.bytes_before, self.bytes_after)
+	}
+}
+
+pub fn peephole(module: &Module) -> Result<(Module, PeepholeReport), VmError> {
+	let mut p = Peephole::new(module)?;
+	while p.pass() {}
+	p.finish()
+}
+
+// while optimizing, jump targets are instruction *indexes* instead of byte offsets, since the
+// offsets change as instructions are removed. removed instructions are None.
+struct Peephole<'m> {
+	module:  &'m Module,
+	code:    Vec<Option<Instr>>,
+	targets: Vec<Option<usize>>, // for each jump, the index of the instruction it goes to.
+	consts:  Vec<f64>,           // the constants, plus any new ones made by folding.
+	report:  PeepholeReport,
+}
+
+impl<'m> Peephole<'m> {
+	fn new(module: &'m Module) -> Result<Self, VmError> {
+		let instrs = module.instructions()?;
+		let mut targets = vec![];
+
+		for &(offset, instr) in &instrs {
+			if let Instr::Push(k) = instr {
+				if k as usize >= module.consts.len() {
+					return Err(VmError::new(offset, diagnostics::INVALID_BYTECODE,
+						format!("there's no constant #{}", k)));
+				}
+			}
+
+			targets.push(match instr.jump_target() {
+				// the end of the code counts as an instruction index too.
+				Some(t) if t as usize == module.code.len() => Some(instrs.len()),
+				Some(t) => match instrs.iter().position(|(o, _)| *o == t as usize) {
+					Some(i) => Some(i),
+					None => return Err(VmError::new(offset, diagnostics::INVALID_BYTECODE,
+						"this jumps into the middle of an instruction, so it can't be optimized")),
+				},
+				None => None,
+			});
+		}
+
+		Ok(Peephole {
+			module,
+			code: instrs.iter().map(|(_, i)| Some(*i)).collect(),
+			targets,
+			consts: module.consts.clone(),
+			report: PeepholeReport { bytes_before: module.code.len(), ..Default::default() },
+		})
+	}
+
+	// the index of the first instruction at or after `i` that hasn't been removed. that's where
+	// a jump to `i` really goes.
+	fn resolve(&self, i: usize) -> usize {
+		(i .. self.code.len()).find(|&j| self.code[j].is_some()).unwrap_or(self.code.len())
+	}
+
+	fn const_at(&self, instr: Instr) -> Option<f64> {
+		match instr {
+			Instr::Push(i) => self.consts.get(i as usize).copied(),
+			_              => None,
+		}
+	}
+
+	// folding makes new constants, but there's only room for 65,536 of them. (the ones that
+	// aren't used anymore are dropped at the end, but until then, they take up room.)
+	fn can_add_const(&self) -> bool {
+		self.consts.len() <= u16::MAX as usize
+	}
+
+	// only call this if can_add_const().
+	fn push_const(&mut self, val: f64) -> Instr {
+		let i = match self.consts.iter().position(|c| c.to_bits() == val.to_bits()) {
+			Some(i) => i,
+			None    => { self.consts.push(val); self.consts.len() - 1 }
+		};
+
+		Instr::Push(i as u16)
+	}
+
+	// goes over the code once. gives true if anything changed.
+	fn pass(&mut self) -> bool {
+		use Instr::*;
+
+		let live = (0 .. self.code.len()).filter(|&i| self.code[i].is_some()).collect::<Vec<_>>();
+		let jumped_to = live.iter()
+			.filter_map(|&i| self.targets[i].map(|t| self.resolve(t)))
+			.collect::<HashSet<_>>();
+
+		let mut changed = false;
+		let mut p = 0;
+
+		while p < live.len() {
+			// the window is up to 3 long, but can't have a jump target inside it.
+			let inside = live[p + 1 ..].iter().take(2).take_while(|i| !jumped_to.contains(i));
+			let window = live[p .. p + 1 + inside.count()].iter()
+				.map(|&i| self.code[i].unwrap())
+				.collect::<Vec<_>>();
+
+			let next = live.get(p + 1).copied().unwrap_or(self.code.len());
+			let jumps_to_next = self.targets[live[p]].map(|t| self.resolve(t)) == Some(next);
+
+			let (rule, replacement, used) = match window[..] {
+				[Nop, ..] => ("nop", vec![], 1),
+
+				[k, Add, ..] | [k, Sub, ..] if self.const_at(k) == Some(0.0) =>
+					("identity", vec![], 2),
+				[k, Mul, ..] | [k, Div, ..] if self.const_at(k) == Some(1.0) =>
+					("identity", vec![], 2),
+
+				[a @ Push(..), b @ Push(..), op, ..] if is_binary(op) && self.can_add_const() => {
+					let k = binary(op, self.const_at(a).unwrap(), self.const_at(b).unwrap());
+					("fold", vec![self.push_const(k)], 3)
+				}
+
+				[a @ Push(..), op @ (Neg | Not), ..] if self.can_add_const() => {
+					let k = unary(op, self.const_at(a).unwrap());
+					("fold", vec![self.push_const(k)], 2)
+				}
+
+				[Push(..), Pop, ..] => ("push-pop", vec![], 2),
+
+				[Jmp(..), ..]              if jumps_to_next => ("jump-to-next", vec![], 1),
+				[Jz(..), ..] | [Jnz(..), ..] if jumps_to_next => ("jump-to-next", vec![Pop], 1),
+
+				_ => { p += 1; continue; }
+			};
+
+			*self.report.fired.entry(rule).or_insert(0) += 1;
+			changed = true;
+
+			// the replacement (at most one instruction) goes where the window started.
+			for (j, &i) in live[p .. p + used].iter().enumerate() {
+				self.code[i] = replacement.get(j).copied();
+				self.targets[i] = None;
+			}
+
+			p += used;
+		}
+
+		changed
+	}
+
+	// lays the code back out, now that the offsets are known.
+	fn finish(self) -> Result<(Module, PeepholeReport), VmError> {
+		// offsets[i] is where instruction i ends up (or the next one, if it was removed).
+		let mut offsets = vec![0; self.code.len() + 1];
+		let mut offset = 0;
+
+		for (i, instr) in self.code.iter().enumerate() {
+			offsets[i] = offset;
+			offset += instr.map_or(0, |i| i.size());
+		}
+
+		offsets[self.code.len()] = offset;
+
+		// the constants are added again as they're used, so ones that aren't used anymore (like
+		// the inputs to a fold) are dropped.
+		let mut out = Module { names: self.module.names.clone(), ..Module::new() };
+
+		for (i, instr) in self.code.iter().enumerate() {
+			match instr {
+				Some(Instr::Push(k)) => {
+					let k = out.add_const(self.consts[*k as usize])?;
+					out.emit(Instr::Push(k));
+				}
+
+				Some(instr) => match self.targets[i] {
+					Some(t) => { out.emit(instr.with_jump_target(offsets[t] as u32)); }
+					None    => { out.emit(*instr); }
+				},
+				None => {}
+			}
+		}
+
+		let mut report = self.report;
+		report.bytes_after = out.code.len();
+		Ok((out, report))
+	}
+}
+
+fn is_binary(instr: Instr) -> bool {
+	use Instr::*;
+	matches!(instr, Add | Sub | Mul | Div | Mod | Lt | Le | Gt | Ge | Eq | Ne)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// with the constant table full, there's no room for what `push 2; push 3; add` folds into.
+	#[test]
+	fn no_folding_when_the_constants_are_full() {
+		let mut module = Module::new();
+		module.consts = (0 ..= u16::MAX).map(|i| i as f64 + 0.5).collect();
+
+		module.emit(Instr::Push(0));
+		module.emit(Instr::Push(1));
+		module.emit(Instr::Add);
+		module.emit(Instr::Ret);
+
+		let (out, report) = peephole(&module).unwrap();
+		assert_eq!(report.fired.get("fold"), None);
+		assert_eq!(out.consts, vec![0.5, 1.5]);
+		assert_eq!(Machine::new().run(&out), Ok(Some(2.0)));
+	}
+}